@@ -43,5 +43,30 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                                 ),
                         )
                 )
+                .subcommand(
+                    SubCommand::with_name("import")
+                        .about("Copies the validator directories in DIR into the validators directory.")
+                        .arg(
+                            Arg::with_name("directory")
+                                .long("directory")
+                                .value_name("DIR")
+                                .help("The directory containing the validator directories to import.")
+                                .takes_value(true)
+                                .required(true),
+                        )
+                )
+                .subcommand(
+                    SubCommand::with_name("export")
+                        .about("Moves all validator directories from the validators directory into DIR. \
+                                Refuses to run whilst a validator client is using the validators directory.")
+                        .arg(
+                            Arg::with_name("directory")
+                                .long("directory")
+                                .value_name("DIR")
+                                .help("The directory into which the validator directories are moved.")
+                                .takes_value(true)
+                                .required(true),
+                        )
+                )
         )
 }
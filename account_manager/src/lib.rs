@@ -3,11 +3,13 @@ mod cli;
 use clap::ArgMatches;
 use environment::RuntimeContext;
 use rayon::prelude::*;
-use slog::{crit, info};
+use slog::{crit, info, Logger};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use types::{ChainSpec, EthSpec};
-use validator_client::validator_directory::{ValidatorDirectory, ValidatorDirectoryBuilder};
+use validator_client::validator_directory::{
+    copy_validator_directory, ValidatorDirectory, ValidatorDirectoryBuilder, ValidatorsDirLock,
+};
 
 pub use cli::cli_app;
 
@@ -27,20 +29,7 @@ fn run_account_manager<T: EthSpec>(
 ) -> Result<(), String> {
     let log = context.log.clone();
 
-    let datadir = matches
-        .value_of("datadir")
-        .map(PathBuf::from)
-        .unwrap_or_else(|| {
-            let mut default_dir = match dirs::home_dir() {
-                Some(v) => v,
-                None => {
-                    panic!("Failed to find a home directory");
-                }
-            };
-            default_dir.push(".lighthouse");
-            default_dir.push("validators");
-            default_dir
-        });
+    let datadir = clap_utils::parse_validators_dir(matches)?;
 
     fs::create_dir_all(&datadir).map_err(|e| format!("Failed to initialize datadir: {}", e))?;
 
@@ -53,8 +42,10 @@ fn run_account_manager<T: EthSpec>(
     match matches.subcommand() {
         ("validator", Some(matches)) => match matches.subcommand() {
             ("new", Some(matches)) => run_new_validator_subcommand(matches, datadir, context)?,
+            ("import", Some(matches)) => run_import_validator_subcommand(matches, datadir, &log)?,
+            ("export", Some(matches)) => run_export_validator_subcommand(matches, datadir, &log)?,
             _ => {
-                return Err("Invalid 'validator new' command. See --help.".to_string());
+                return Err("Invalid 'validator' command. See --help.".to_string());
             }
         },
         _ => {
@@ -148,3 +139,98 @@ fn make_validators(
         })
         .collect()
 }
+
+/// Process the subcommand for importing validator directories into `datadir`.
+///
+/// Either all of the validator directories are imported, or none of them are.
+fn run_import_validator_subcommand(
+    matches: &ArgMatches,
+    datadir: PathBuf,
+    log: &Logger,
+) -> Result<(), String> {
+    let source = parse_directory(matches)?;
+
+    // Hold the lock so a validator client cannot start and load the validators part-way through.
+    let _lock = ValidatorsDirLock::acquire(&datadir)?;
+
+    let mut imported = vec![];
+    for directory in validator_directories(&source)? {
+        match copy_validator_directory(&directory, &datadir) {
+            Ok(path) => imported.push(path),
+            Err(e) => {
+                for path in &imported {
+                    let _ = fs::remove_dir_all(path);
+                }
+                return Err(e);
+            }
+        }
+    }
+
+    info!(
+        log,
+        "Imported validator directories";
+        "source" => format!("{:?}", source),
+        "count" => imported.len(),
+    );
+
+    Ok(())
+}
+
+/// Process the subcommand for moving all validator directories out of `datadir`.
+///
+/// Each directory is removed from `datadir` only once it has been completely copied.
+fn run_export_validator_subcommand(
+    matches: &ArgMatches,
+    datadir: PathBuf,
+    log: &Logger,
+) -> Result<(), String> {
+    let destination = parse_directory(matches)?;
+
+    // Hold the lock so a validator client cannot start and load the validators part-way through.
+    let _lock = ValidatorsDirLock::acquire(&datadir)?;
+
+    fs::create_dir_all(&destination)
+        .map_err(|e| format!("Failed to create export directory: {}", e))?;
+
+    let exported = validator_directories(&datadir)?
+        .iter()
+        .map(|directory| {
+            copy_validator_directory(directory, &destination)?;
+            fs::remove_dir_all(directory)
+                .map_err(|e| format!("Unable to remove exported validator directory: {}", e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    info!(
+        log,
+        "Exported validator directories";
+        "destination" => format!("{:?}", destination),
+        "count" => exported.len(),
+    );
+
+    Ok(())
+}
+
+/// Parses the `--directory` flag.
+fn parse_directory(matches: &ArgMatches) -> Result<PathBuf, String> {
+    matches
+        .value_of("directory")
+        .map(PathBuf::from)
+        .ok_or_else(|| "No directory".to_string())
+}
+
+/// Returns the path of each validator directory in `base_path`, ignoring any hidden (e.g.,
+/// temporary) directories.
+fn validator_directories(base_path: &Path) -> Result<Vec<PathBuf>, String> {
+    Ok(fs::read_dir(base_path)
+        .map_err(|e| format!("Failed to read directory {:?}: {}", base_path, e))?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| {
+            path.is_dir()
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .map_or(false, |name| !name.starts_with('.'))
+        })
+        .collect())
+}
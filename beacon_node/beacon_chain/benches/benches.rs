@@ -0,0 +1,141 @@
+//! Benchmarks block production and import on a `BeaconChain` which has been running with full
+//! attestation participation.
+//!
+//! The number of validators is read from `$BENCH_VALIDATOR_COUNT` (default: 1,024).
+//!
+//! ```bash
+//! BENCH_VALIDATOR_COUNT=16384 cargo bench --bench benches
+//! ```
+
+use beacon_chain::test_utils::{
+    generate_deterministic_keypairs, AttestationStrategy, BeaconChainHarness, BlockStrategy,
+    HarnessType,
+};
+use beacon_chain::BlockProcessingOutcome;
+use criterion::{black_box, criterion_group, criterion_main, Benchmark, Criterion};
+use std::env;
+use std::sync::Arc;
+use tree_hash::TreeHash;
+use types::{Domain, EthSpec, MinimalEthSpec, Signature};
+
+pub const VALIDATOR_COUNT_ENV: &str = "BENCH_VALIDATOR_COUNT";
+pub const DEFAULT_VALIDATOR_COUNT: usize = 1_024;
+
+/// The number of epochs of blocks to apply to the chain prior to benchmarking, such that the
+/// head state has realistic justification, finalization and pending attestations.
+pub const WARM_UP_EPOCHS: u64 = 4;
+
+type E = MinimalEthSpec;
+
+fn all_benches(c: &mut Criterion) {
+    let validator_count = env::var(VALIDATOR_COUNT_ENV)
+        .map(|count| count.parse().expect("validator count should be an integer"))
+        .unwrap_or(DEFAULT_VALIDATOR_COUNT);
+
+    let harness = Arc::new(BeaconChainHarness::new(
+        MinimalEthSpec,
+        generate_deterministic_keypairs(validator_count),
+    ));
+    harness.advance_slot();
+    harness.extend_chain(
+        (E::slots_per_epoch() * WARM_UP_EPOCHS) as usize,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::AllValidators,
+    );
+    harness.advance_slot();
+
+    let title = &format!("beacon_chain/{}_validators", validator_count);
+
+    produce_block_bench(c, title, &harness);
+    process_block_bench(c, title, &harness);
+}
+
+/// Produces a block upon the head state, including packing attestations from the operation pool
+/// and computing the post-state root.
+fn produce_block_bench(
+    c: &mut Criterion,
+    title: &str,
+    harness: &Arc<BeaconChainHarness<HarnessType<E>>>,
+) {
+    let harness = harness.clone();
+    let head_state = harness.chain.head().beacon_state;
+    let slot = head_state.slot + 1;
+    let proposer_index = harness
+        .chain
+        .block_proposer(slot)
+        .expect("should get block proposer");
+
+    let randao_reveal = {
+        let epoch = slot.epoch(E::slots_per_epoch());
+        let message = epoch.tree_hash_root();
+        let domain = harness
+            .spec
+            .get_domain(epoch, Domain::Randao, &head_state.fork);
+        Signature::new(&message, domain, &harness.keypairs[proposer_index].sk)
+    };
+
+    c.bench(
+        title,
+        Benchmark::new("produce_block_on_state", move |b| {
+            b.iter_batched(
+                || (head_state.clone(), randao_reveal.clone()),
+                |(state, randao_reveal)| {
+                    black_box(
+                        harness
+                            .chain
+                            .produce_block_on_state(state, slot, randao_reveal)
+                            .expect("should produce block"),
+                    )
+                },
+                criterion::BatchSize::SmallInput,
+            )
+        })
+        .sample_size(10),
+    );
+}
+
+/// Imports a block upon the head, including the state transition, tree hashing, writing to the
+/// database and updating fork choice.
+///
+/// Each iteration advances the slot clock and builds a block upon the previously imported block,
+/// so that no block is rejected as already known.
+fn process_block_bench(
+    c: &mut Criterion,
+    title: &str,
+    harness: &Arc<BeaconChainHarness<HarnessType<E>>>,
+) {
+    let harness = harness.clone();
+
+    c.bench(
+        title,
+        Benchmark::new("process_block", move |b| {
+            b.iter_batched(
+                || {
+                    let head_state = harness.chain.head().beacon_state;
+                    let slot = head_state.slot + 1;
+                    while harness.chain.slot().expect("should have a slot") < slot {
+                        harness.advance_slot();
+                    }
+
+                    let (block, _state) =
+                        harness.build_block(head_state, slot, BlockStrategy::OnCanonicalHead);
+                    block
+                },
+                |block| match harness
+                    .chain
+                    .process_block(block)
+                    .expect("should not error during block processing")
+                {
+                    BlockProcessingOutcome::Processed { block_root } => black_box(block_root),
+                    other => panic!("block should be processed: {:?}", other),
+                },
+                // Each block must be imported before the next is built.
+                criterion::BatchSize::PerIteration,
+            )
+        })
+        .sample_size(10),
+    );
+}
+
+criterion_group!(benches, all_benches);
+criterion_main!(benches);
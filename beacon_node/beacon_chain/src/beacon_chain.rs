@@ -1,15 +1,29 @@
+use crate::block_rewards::{compute_block_rewards, BlockRewards};
+use crate::chain_quality::{ChainQuality, ChainQualityCache};
 use crate::checkpoint::CheckPoint;
 use crate::errors::{BeaconChainError as Error, BlockProductionError};
 use crate::eth1_chain::{Eth1Chain, Eth1ChainBackend};
 use crate::events::{EventHandler, EventKind};
 use crate::fork_choice::{Error as ForkChoiceError, ForkChoice};
+use crate::head_tracker::HeadTracker;
 use crate::iter::{ReverseBlockRootIterator, ReverseStateRootIterator};
 use crate::metrics;
+use crate::migrate::Migrate;
+use crate::observed_aggregators::ObservedAggregators;
+use crate::observed_block_producers::{ObservedBlockProducers, ProducerStatus};
+use crate::observed_votes::{ObservedVotes, VoteStatus};
 use crate::persisted_beacon_chain::{PersistedBeaconChain, BEACON_CHAIN_DB_KEY};
-use lmd_ghost::LmdGhost;
+use crate::shuffling_cache::{
+    attester_dependent_slot, proposer_dependent_slot, EpochShufflings, ShufflingCache,
+};
+use crate::validator_inclusion::{
+    epoch_inclusion_summary, previous_epoch_inclusion, EpochInclusionSummary, ValidatorInclusion,
+};
+use crate::validator_pubkey_cache::ValidatorPubkeyCache;
 use operation_pool::DepositInsertStatus;
 use operation_pool::{OperationPool, PersistedOperationPool};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
+use resource_budget::ResourceBudget;
 use slog::{crit, debug, error, info, trace, warn, Logger};
 use slot_clock::SlotClock;
 use ssz::Encode;
@@ -18,16 +32,20 @@ use state_processing::per_block_processing::{
         AttestationValidationError, AttesterSlashingValidationError, DepositValidationError,
         ExitValidationError, ProposerSlashingValidationError,
     },
-    verify_attestation_for_state, VerifySignatures,
+    verify_attestation_for_state, BlockSignatureVerifier, VerifySignatures,
 };
+use state_processing::per_epoch_processing::validator_statuses::ValidatorStatuses;
 use state_processing::{
-    per_block_processing, per_slot_processing, BlockProcessingError, BlockSignatureStrategy,
+    common::get_attesting_indices, per_block_processing, per_slot_processing, BlockProcessingError,
+    BlockSignatureStrategy,
 };
+use std::fmt;
 use std::fs;
 use std::io::prelude::*;
 use std::sync::Arc;
 use store::iter::{BlockRootsIterator, StateRootsIterator};
-use store::{Error as DBError, Store};
+use store::{Error as DBError, PartialBeaconState, Store, StoreOp};
+use tracing::{debug_span, trace_span};
 use tree_hash::TreeHash;
 use types::*;
 
@@ -46,6 +64,9 @@ const WRITE_BLOCK_PROCESSING_SSZ: bool = cfg!(feature = "write_ssz_files");
 const BLOCK_SKIPPING_LOGGING_THRESHOLD: u64 = 3;
 const BLOCK_SKIPPING_FAILURE_THRESHOLD: u64 = 128;
 
+/// The number of slots after its own slot during which an aggregate may be propagated on gossip.
+pub const ATTESTATION_PROPAGATION_SLOT_RANGE: u64 = 32;
+
 #[derive(Debug, PartialEq)]
 pub enum BlockProcessingOutcome {
     /// Block was valid and imported into the block graph.
@@ -70,6 +91,67 @@ pub enum BlockProcessingOutcome {
     BlockIsAlreadyKnown,
     /// The block could not be applied to the state, it is invalid.
     PerBlockProcessingError(BlockProcessingError),
+    /// The block finalizes a chain which does not include the weak subjectivity checkpoint.
+    WeakSubjectivityConflict {
+        checkpoint: Checkpoint,
+        local_root: Hash256,
+    },
+    /// A block in a chain segment is not the child of the block before it.
+    NonLinearParentRoots,
+    /// The slots of the blocks in a chain segment are not strictly increasing.
+    NonLinearSlots,
+}
+
+impl BlockProcessingOutcome {
+    /// Returns `true` if the outcome proves that the block (or chain segment) is invalid, such that
+    /// the peer which sent it may be penalized.
+    ///
+    /// Blocks from the future, prior to finalization or with an unknown parent may be the result
+    /// of clock disparity or a slow peer, and per block processing errors which are not caused by
+    /// the block header, its signatures or one of its operations are our own.
+    pub fn is_invalid_block(&self) -> bool {
+        match self {
+            BlockProcessingOutcome::StateRootMismatch { .. }
+            | BlockProcessingOutcome::WeakSubjectivityConflict { .. }
+            | BlockProcessingOutcome::NonLinearParentRoots
+            | BlockProcessingOutcome::NonLinearSlots => true,
+            BlockProcessingOutcome::PerBlockProcessingError(e) => {
+                e.operation().is_some()
+                    || match e {
+                        BlockProcessingError::RandaoSignatureInvalid
+                        | BlockProcessingError::BulkSignatureVerificationFailed
+                        | BlockProcessingError::StateRootMismatch
+                        | BlockProcessingError::DepositCountInvalid { .. }
+                        | BlockProcessingError::HeaderInvalid { .. } => true,
+                        _ => false,
+                    }
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Names the invalid operation of blocks that fail per block processing.
+impl fmt::Display for BlockProcessingOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BlockProcessingOutcome::PerBlockProcessingError(e) => write!(f, "{}", e),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+/// The result of importing a chain segment with `BeaconChain::process_chain_segment`.
+#[derive(Debug, PartialEq)]
+pub enum ChainSegmentResult {
+    /// Every block was imported, or skipped because it was already known or finalized.
+    Successful { imported_blocks: usize },
+    /// The first `imported_blocks` blocks to be imported were, then a block failed with `outcome`
+    /// and no later block was imported.
+    Failed {
+        imported_blocks: usize,
+        outcome: BlockProcessingOutcome,
+    },
 }
 
 #[derive(Debug, PartialEq)]
@@ -91,12 +173,61 @@ pub enum AttestationProcessingOutcome {
         finalized: Epoch,
     },
     Invalid(AttestationValidationError),
+    /// An identical unaggregated attestation from the same validator has already been processed.
+    PriorAttestationKnown {
+        validator_index: usize,
+        epoch: Epoch,
+    },
+    /// The validator has signed conflicting attestations for the same target epoch. Its votes
+    /// are no longer applied to fork choice.
+    Equivocation {
+        validator_index: usize,
+        epoch: Epoch,
+    },
+    /// A valid aggregate from the same aggregator has already been processed for this slot.
+    AggregatorAlreadyKnown {
+        aggregator_index: u64,
+        slot: Slot,
+    },
+    /// There is no committee for the slot and index of the aggregate.
+    NoCommittee {
+        slot: Slot,
+        index: CommitteeIndex,
+    },
+    /// The aggregator is not a member of the committee of the aggregate.
+    AggregatorNotInCommittee {
+        aggregator_index: u64,
+    },
+    /// The selection proof does not select the aggregator to aggregate for its committee.
+    NotAggregator {
+        aggregator_index: u64,
+    },
+    /// The selection proof is not a valid signature from the aggregator.
+    InvalidSelectionProof {
+        aggregator_index: u64,
+    },
+    /// The aggregate is not from within `ATTESTATION_PROPAGATION_SLOT_RANGE` slots of the current
+    /// slot, so it must not be propagated.
+    SlotOutOfRange {
+        aggregate: Slot,
+        current: Slot,
+    },
+}
+
+/// The outcome of processing a `VoluntaryExit`, `ProposerSlashing` or `AttesterSlashing`.
+#[derive(Debug, PartialEq)]
+pub enum OperationProcessingOutcome {
+    /// The operation was valid and has been added to the op pool.
+    Processed,
+    /// The op pool already exits or slashes every validator affected by the operation, so it was
+    /// ignored without verification.
+    AlreadyKnown,
 }
 
 pub trait BeaconChainTypes: Send + Sync + 'static {
     type Store: store::Store;
+    type StoreMigrator: Migrate<Self::Store, Self::EthSpec>;
     type SlotClock: slot_clock::SlotClock;
-    type LmdGhost: LmdGhost<Self::Store, Self::EthSpec>;
     type Eth1Chain: Eth1ChainBackend<Self::EthSpec>;
     type EthSpec: types::EthSpec;
     type EventHandler: EventHandler<Self::EthSpec>;
@@ -108,6 +239,8 @@ pub struct BeaconChain<T: BeaconChainTypes> {
     pub spec: ChainSpec,
     /// Persistent storage for blocks, states, etc. Typically an on-disk store, such as LevelDB.
     pub store: Arc<T::Store>,
+    /// Database migrator for running background maintenance on the store.
+    pub store_migrator: T::StoreMigrator,
     /// Reports the current slot, typically based upon the system clock.
     pub slot_clock: T::SlotClock,
     /// Stores all operations (e.g., `Attestation`, `Deposit`, etc) that are candidates for
@@ -119,11 +252,31 @@ pub struct BeaconChain<T: BeaconChainTypes> {
     pub(crate) canonical_head: RwLock<CheckPoint<T::EthSpec>>,
     /// The root of the genesis block.
     pub genesis_block_root: Hash256,
+    /// The root of the validators of the genesis state, used to compute fork digests.
+    pub genesis_validators_root: Hash256,
     /// A state-machine that is updated with information from the network and chooses a canonical
     /// head block.
     pub fork_choice: ForkChoice<T>,
     /// A handler for events generated by the beacon chain.
     pub event_handler: T::EventHandler,
+    /// Used to track the heads of the beacon chain.
+    pub(crate) head_tracker: Arc<HeadTracker>,
+    /// Caches the decompressed public keys of all validators in the head state.
+    pub(crate) validator_pubkey_cache: RwLock<ValidatorPubkeyCache>,
+    /// Tracks the votes of validators seen in unaggregated attestations.
+    pub(crate) observed_votes: ObservedVotes,
+    /// Tracks the aggregators of each slot seen in aggregate and proofs.
+    pub(crate) observed_aggregators: ObservedAggregators,
+    /// Tracks the proposer of each slot seen in imported blocks.
+    pub(crate) observed_block_producers: ObservedBlockProducers,
+    /// The chain quality of recent epochs of the canonical chain.
+    pub(crate) chain_quality_cache: ChainQualityCache,
+    /// Caches the attester shufflings and block proposers of recent epochs.
+    pub(crate) shuffling_cache: Mutex<ShufflingCache>,
+    /// A checkpoint which must be included in any chain that finalizes its epoch.
+    pub weak_subjectivity_checkpoint: Option<Checkpoint>,
+    /// Determines how aggressively background services (e.g., sync) may consume resources.
+    pub resource_budget: ResourceBudget,
     /// Logging to CLI, etc.
     pub(crate) log: Logger,
 }
@@ -135,10 +288,16 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
     pub fn persist(&self) -> Result<(), Error> {
         let timer = metrics::start_timer(&metrics::PERSIST_CHAIN);
 
-        let p: PersistedBeaconChain<T> = PersistedBeaconChain {
+        let p: PersistedBeaconChain<T::EthSpec> = PersistedBeaconChain {
             canonical_head: self.canonical_head.read().clone(),
             op_pool: PersistedOperationPool::from_operation_pool(&self.op_pool),
             genesis_block_root: self.genesis_block_root,
+            ssz_head_tracker: self.head_tracker.to_ssz_container(),
+            ssz_fork_choice: self.fork_choice.as_ssz_container(),
+            ssz_eth1: self
+                .eth1_chain
+                .as_ref()
+                .map(|eth1_chain| eth1_chain.as_ssz_container()),
         };
 
         let key = Hash256::from_slice(&BEACON_CHAIN_DB_KEY.as_bytes());
@@ -237,7 +396,7 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             .get_block(&block_root)?
             .ok_or_else(|| Error::MissingBeaconBlock(block_root))?;
         let state = self
-            .get_state(&block.state_root)?
+            .get_state(&block.state_root, Some(block.slot))?
             .ok_or_else(|| Error::MissingBeaconState(block.state_root))?;
         let iter = BlockRootsIterator::owned(self.store.clone(), state);
         Ok(ReverseBlockRootIterator::new(
@@ -258,6 +417,32 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             .map(|(ancestor_block_root, _)| ancestor_block_root))
     }
 
+    /// Returns the root of the block at the start of the epoch of the weak subjectivity checkpoint
+    /// in the chain of `state`, if it conflicts with the checkpoint.
+    ///
+    /// Returns `None` if there is no checkpoint, or if `state` has not yet finalized its epoch.
+    pub fn weak_subjectivity_conflict(
+        &self,
+        state: &BeaconState<T::EthSpec>,
+    ) -> Result<Option<Hash256>, Error> {
+        let checkpoint = match self.weak_subjectivity_checkpoint.as_ref() {
+            Some(checkpoint) if checkpoint.epoch <= state.finalized_checkpoint.epoch => checkpoint,
+            _ => return Ok(None),
+        };
+        let slot = checkpoint.epoch.start_slot(T::EthSpec::slots_per_epoch());
+
+        let local_root = BlockRootsIterator::new(self.store.clone(), state)
+            .find(|(_, root_slot)| *root_slot == slot)
+            .map(|(root, _)| root)
+            .ok_or_else(|| Error::WeakSubjectivityRootUnavailable(slot))?;
+
+        if local_root == checkpoint.root {
+            Ok(None)
+        } else {
+            Ok(Some(local_root))
+        }
+    }
+
     /// Iterates across all `(state_root, slot)` pairs from the head of the chain (inclusive) to
     /// the earliest reachable ancestor (may or may not be genesis).
     ///
@@ -320,14 +505,18 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
 
     /// Returns the state at the given root, if any.
     ///
+    /// The `slot` of the state should be provided if it is known, since it allows states in the
+    /// freezer database to be located more efficiently.
+    ///
     /// ## Errors
     ///
     /// May return a database error.
     pub fn get_state(
         &self,
         state_root: &Hash256,
+        slot: Option<Slot>,
     ) -> Result<Option<BeaconState<T::EthSpec>>, Error> {
-        Ok(self.store.get(state_root)?)
+        Ok(self.store.get_state(state_root, slot)?)
     }
 
     /// Returns a `Checkpoint` representing the head block and state. Contains the "best block";
@@ -337,14 +526,56 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
     /// is the state as it was when the head block was received, which could be some slots prior to
     /// now.
     pub fn head(&self) -> CheckPoint<T::EthSpec> {
+        let span = trace_span!("canonical_head_read");
+        let _enter = span.enter();
+
         self.canonical_head.read().clone()
     }
 
+    /// Returns the fork of the head state.
+    ///
+    /// Unlike `self.head()`, the head state is not cloned.
+    pub fn head_fork(&self) -> Fork {
+        self.canonical_head.read().beacon_state.fork.clone()
+    }
+
+    /// Returns the validators and balances of the head state.
+    ///
+    /// Unlike `self.head()`, the rest of the head state is not cloned.
+    pub fn head_partial_state(&self) -> PartialBeaconState {
+        PartialBeaconState::from_state(&self.canonical_head.read().beacon_state)
+    }
+
+    /// Returns the digest of the fork which is active at `epoch`, according to the head state.
+    ///
+    /// Gossip topics are scoped to this digest, so that a fork can activate without nodes on
+    /// either side of it exchanging messages.
+    pub fn fork_digest(&self, epoch: Epoch) -> ForkDigest {
+        self.spec
+            .compute_fork_digest(epoch, &self.head_fork(), self.genesis_validators_root)
+    }
+
+    /// Returns the `EnrForkId` to advertise in the local ENR at `epoch`, according to the head
+    /// state.
+    pub fn enr_fork_id(&self, epoch: Epoch) -> EnrForkId {
+        self.spec
+            .enr_fork_id(epoch, &self.head_fork(), self.genesis_validators_root)
+    }
+
+    /// Returns the roots and slots of all known heads (i.e., blocks without children), including
+    /// the canonical head.
+    pub fn heads(&self) -> Vec<(Hash256, Slot)> {
+        self.head_tracker.heads()
+    }
+
     /// Returns the `BeaconState` at the given slot.
     ///
     ///  Returns `None` when the state is not found in the database or there is an error skipping
     ///  to a future state.
     pub fn state_at_slot(&self, slot: Slot) -> Result<BeaconState<T::EthSpec>, Error> {
+        let span = debug_span!("state_at_slot", slot = slot.as_u64());
+        let _enter = span.enter();
+
         let head_state = self.head().beacon_state;
 
         if slot == head_state.slot {
@@ -379,7 +610,7 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             let mut state = head_state;
             while state.slot < slot {
                 match per_slot_processing(&mut state, &self.spec) {
-                    Ok(()) => (),
+                    Ok(_) => (),
                     Err(e) => {
                         warn!(
                             self.log,
@@ -403,7 +634,7 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
 
             Ok(self
                 .store
-                .get(&state_root)?
+                .get_state(&state_root, Some(slot))?
                 .ok_or_else(|| Error::NoStateForSlot(slot))?)
         }
     }
@@ -427,14 +658,148 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
 
     /// Returns the validator index (if any) for the given public key.
     ///
-    /// Information is retrieved from the present `beacon_state.validators`.
+    /// Information is retrieved from the validator pubkey cache, which holds all the validators
+    /// in the head state.
     pub fn validator_index(&self, pubkey: &PublicKey) -> Option<usize> {
-        for (i, validator) in self.head().beacon_state.validators.iter().enumerate() {
-            if validator.pubkey == *pubkey {
-                return Some(i);
+        self.validator_pubkey_cache.read().get_index(pubkey)
+    }
+
+    /// Returns the decompressed public key (if any) of the validator with the given index.
+    ///
+    /// Information is retrieved from the validator pubkey cache, which holds all the validators
+    /// in the head state.
+    pub fn validator_pubkey(&self, validator_index: usize) -> Option<PublicKey> {
+        self.validator_pubkey_cache
+            .read()
+            .get(validator_index)
+            .cloned()
+    }
+
+    /// Returns the attestation inclusion of each validator during `epoch`, as recorded by the
+    /// canonical chain.
+    ///
+    /// The inclusion is read from the state at the last slot of `epoch + 1`, which is the last
+    /// point at which attestations from `epoch` may be included. If the head is presently in
+    /// `epoch + 1`, the head state is used instead and the result may be incomplete.
+    pub fn validator_inclusion(
+        &self,
+        epoch: Epoch,
+    ) -> Result<(EpochInclusionSummary, Vec<ValidatorInclusion>), Error> {
+        let slots_per_epoch = T::EthSpec::slots_per_epoch();
+        let head_state = self.head().beacon_state;
+        let head_epoch = head_state.current_epoch();
+
+        let mut state = if head_epoch == epoch + 1 {
+            head_state
+        } else if head_epoch > epoch + 1 {
+            self.state_at_slot((epoch + 2).start_slot(slots_per_epoch) - 1)?
+        } else {
+            return Err(Error::EpochInclusionUnavailable { epoch, head_epoch });
+        };
+
+        previous_epoch_inclusion(&mut state, &self.spec)
+    }
+
+    /// Returns the chain quality of the most recent `epochs` epochs processed by the canonical
+    /// chain, in ascending epoch order.
+    ///
+    /// At most `MAX_CHAIN_QUALITY_EPOCHS` epochs are retained.
+    pub fn chain_quality(&self, epochs: usize) -> Vec<ChainQuality> {
+        self.chain_quality_cache.latest(epochs)
+    }
+
+    /// Summarises the attestation inclusion of `epoch`, given the `validator_statuses` computed
+    /// during its epoch processing, and exports it via the Prometheus metrics.
+    fn record_epoch_inclusion(
+        &self,
+        epoch: Epoch,
+        validator_statuses: &ValidatorStatuses,
+    ) -> EpochInclusionSummary {
+        let summary = epoch_inclusion_summary(epoch, validator_statuses);
+
+        debug!(
+            self.log,
+            "Epoch attestation inclusion";
+            "epoch" => summary.epoch,
+            "active_gwei" => summary.active_gwei,
+            "source_attesting_gwei" => summary.source_attesting_gwei,
+            "target_attesting_gwei" => summary.target_attesting_gwei,
+            "head_attesting_gwei" => summary.head_attesting_gwei,
+            "included_attesters" => summary.included_attesters,
+            "total_inclusion_delay" => summary.total_inclusion_delay,
+        );
+        metrics::scrape_epoch_inclusion_summary(&summary);
+
+        summary
+    }
+
+    /// Caches the chain quality of `summary.epoch`, given `state`, the result of the epoch
+    /// processing which followed it.
+    ///
+    /// Errors are logged, rather than returned, since they should never prevent block import.
+    fn record_chain_quality(
+        &self,
+        summary: &EpochInclusionSummary,
+        state: &BeaconState<T::EthSpec>,
+    ) {
+        match ChainQuality::from_epoch_processing(summary, state) {
+            Ok(quality) => self.chain_quality_cache.insert(quality),
+            Err(e) => warn!(
+                self.log,
+                "Unable to compute chain quality";
+                "epoch" => summary.epoch,
+                "error" => format!("{:?}", e),
+            ),
+        }
+    }
+
+    /// Returns the rewards earned by the proposer of each block in the canonical chain with a slot
+    /// in `start_slot..=end_slot`, in ascending slot order.
+    ///
+    /// Each block is applied to the state of its parent, therefore this function is expensive for
+    /// long ranges or for blocks which have been migrated to the freezer database.
+    pub fn block_rewards(
+        &self,
+        start_slot: Slot,
+        end_slot: Slot,
+    ) -> Result<Vec<BlockRewards>, Error> {
+        let mut block_roots = self
+            .rev_iter_block_roots()
+            .skip_while(|(_root, slot)| *slot > end_slot)
+            .take_while(|(_root, slot)| *slot >= start_slot)
+            .collect::<Vec<_>>();
+        block_roots.reverse();
+        // The root of a block is repeated for any skipped slots which follow it.
+        block_roots.dedup_by_key(|(root, _slot)| *root);
+
+        let mut rewards = Vec::with_capacity(block_roots.len());
+        for (block_root, _slot) in block_roots {
+            let block = self
+                .get_block(&block_root)?
+                .ok_or_else(|| Error::MissingBeaconBlock(block_root))?;
+
+            // Ignore the genesis block and any block which precedes the first skipped slot.
+            if block.slot < start_slot || block.slot == self.spec.genesis_slot {
+                continue;
+            }
+
+            let parent = self
+                .get_block(&block.parent_root)?
+                .ok_or_else(|| Error::MissingBeaconBlock(block.parent_root))?;
+            let mut state = self
+                .get_state(&parent.state_root, Some(parent.slot))?
+                .ok_or_else(|| Error::MissingBeaconState(parent.state_root))?;
+
+            while state.slot < block.slot {
+                per_slot_processing(&mut state, &self.spec)?;
             }
+
+            rewards.push(compute_block_rewards(
+                &block, block_root, &mut state, &self.spec,
+            )?);
         }
-        None
+
+        Ok(rewards)
     }
 
     /// Returns the block canonical root of the current canonical chain at a given slot.
@@ -473,7 +838,8 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             self.state_at_slot(slot)?
         };
 
-        state.build_committee_cache(RelativeEpoch::Current, &self.spec)?;
+        debug_span!("build_committee_cache", epoch = epoch(slot).as_u64())
+            .in_scope(|| state.build_committee_cache(RelativeEpoch::Current, &self.spec))?;
 
         if epoch(state.slot) != epoch(slot) {
             return Err(Error::InvariantViolated(format!(
@@ -488,6 +854,24 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             .map_err(Into::into)
     }
 
+    /// Compares `block` with any block previously imported from its proposer at its slot,
+    /// allowing a second, conflicting block to be refused before it is published.
+    ///
+    /// The proposer is determined from the canonical chain, so the check is only reliable for
+    /// blocks which build upon it.
+    pub fn block_producer_status(
+        &self,
+        block: &BeaconBlock<T::EthSpec>,
+    ) -> Result<ProducerStatus, Error> {
+        let proposer_index = self.block_proposer(block.slot)?;
+
+        Ok(self.observed_block_producers.status(
+            block.slot,
+            proposer_index as u64,
+            block.canonical_root(),
+        ))
+    }
+
     /// Returns the attestation slot and committee index for a given validator index.
     ///
     /// Information is read from the current state, so only information from the present and prior
@@ -506,7 +890,8 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             self.state_at_slot(epoch.start_slot(T::EthSpec::slots_per_epoch()))?
         };
 
-        state.build_committee_cache(RelativeEpoch::Current, &self.spec)?;
+        debug_span!("build_committee_cache", epoch = epoch.as_u64())
+            .in_scope(|| state.build_committee_cache(RelativeEpoch::Current, &self.spec))?;
 
         if as_epoch(state.slot) != epoch {
             return Err(Error::InvariantViolated(format!(
@@ -525,6 +910,127 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
         }
     }
 
+    /// Returns the root of the block upon which the attester shuffling of `epoch` depends,
+    /// according to the canonical chain.
+    ///
+    /// If the dependent root of previously-fetched duties differs from this value, a re-org has
+    /// changed the shuffling and the duties are stale.
+    pub fn attester_dependent_root(&self, epoch: Epoch) -> Result<Hash256, Error> {
+        self.dependent_root(attester_dependent_slot::<T::EthSpec>(epoch))
+    }
+
+    /// Returns the root of the block upon which the block proposers of `epoch` depend, according
+    /// to the canonical chain.
+    pub fn proposer_dependent_root(&self, epoch: Epoch) -> Result<Hash256, Error> {
+        self.dependent_root(proposer_dependent_slot::<T::EthSpec>(epoch))
+    }
+
+    /// Returns the canonical block root at `slot`, or the head block root if `slot` is later than
+    /// the head. Returns the genesis block root if `slot` is `None`.
+    fn dependent_root(&self, slot: Option<Slot>) -> Result<Hash256, Error> {
+        let slot = match slot {
+            Some(slot) => slot,
+            None => return Ok(self.genesis_block_root),
+        };
+
+        let (head_block_root, head_slot) = {
+            let head = self.canonical_head.read();
+            (head.beacon_block_root, head.beacon_block.slot)
+        };
+
+        if slot >= head_slot {
+            Ok(head_block_root)
+        } else {
+            self.root_at_slot(slot)
+                .ok_or_else(|| Error::UnableToFindTargetRoot(slot))
+        }
+    }
+
+    /// Returns the attester shuffling of `epoch`, along with the root of the block upon which it
+    /// depends.
+    ///
+    /// The shuffling is read from the shuffling cache where possible, otherwise it is computed from
+    /// the state at the start of `epoch` and added to the cache.
+    pub fn attester_shuffling(
+        &self,
+        epoch: Epoch,
+    ) -> Result<(Arc<CommitteeCache>, Hash256), Error> {
+        let dependent_root = self.attester_dependent_root(epoch)?;
+
+        if let Some(committee_cache) = self
+            .shuffling_cache
+            .lock()
+            .get_attesters(epoch, dependent_root)
+        {
+            metrics::inc_counter(&metrics::SHUFFLING_CACHE_HITS);
+            return Ok((committee_cache, dependent_root));
+        }
+
+        metrics::inc_counter(&metrics::SHUFFLING_CACHE_MISSES);
+        self.cache_shufflings(epoch)
+            .map(|shufflings| (shufflings.attesters, shufflings.attester_dependent_root))
+    }
+
+    /// Returns the index of the block proposer for each slot of `epoch`, along with the root of the
+    /// block upon which they depend.
+    ///
+    /// The proposers are read from the shuffling cache where possible, otherwise they are computed
+    /// from the state at the start of `epoch` and added to the cache.
+    pub fn block_proposers(&self, epoch: Epoch) -> Result<(Vec<usize>, Hash256), Error> {
+        let dependent_root = self.proposer_dependent_root(epoch)?;
+
+        if let Some(proposers) = self
+            .shuffling_cache
+            .lock()
+            .get_proposers(epoch, dependent_root)
+        {
+            metrics::inc_counter(&metrics::SHUFFLING_CACHE_HITS);
+            return Ok((proposers, dependent_root));
+        }
+
+        metrics::inc_counter(&metrics::SHUFFLING_CACHE_MISSES);
+        self.cache_shufflings(epoch)
+            .map(|shufflings| (shufflings.proposers, shufflings.proposer_dependent_root))
+    }
+
+    /// Computes the attester shuffling and block proposers of `epoch` from the state at the start
+    /// of `epoch`, adding both to the shuffling cache.
+    ///
+    /// The dependent roots are read from the same state, so they are consistent with the
+    /// shufflings even if the head changes whilst they are computed.
+    fn cache_shufflings(&self, epoch: Epoch) -> Result<EpochShufflings, Error> {
+        let mut state = self.state_at_slot(epoch.start_slot(T::EthSpec::slots_per_epoch()))?;
+
+        debug_span!("build_committee_cache", epoch = epoch.as_u64())
+            .in_scope(|| state.build_committee_cache(RelativeEpoch::Current, &self.spec))?;
+
+        let dependent_root = |slot: Option<Slot>| -> Result<Hash256, Error> {
+            match slot {
+                Some(slot) => Ok(*state.get_block_root(slot)?),
+                None => Ok(self.genesis_block_root),
+            }
+        };
+        let attester_dependent_root = dependent_root(attester_dependent_slot::<T::EthSpec>(epoch))?;
+        let proposer_dependent_root = dependent_root(proposer_dependent_slot::<T::EthSpec>(epoch))?;
+
+        let attesters = Arc::new(state.committee_cache(RelativeEpoch::Current)?.clone());
+        let proposers = epoch
+            .slot_iter(T::EthSpec::slots_per_epoch())
+            .map(|slot| state.get_beacon_proposer_index(slot, &self.spec))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut cache = self.shuffling_cache.lock();
+        cache.insert_attesters(epoch, attester_dependent_root, attesters.clone());
+        cache.insert_proposers(epoch, proposer_dependent_root, proposers.clone());
+
+        Ok(EpochShufflings {
+            attesters,
+            attester_dependent_root,
+            proposers,
+            proposer_dependent_root,
+        })
+    }
+
     /// Produce an `Attestation` that is valid for the given `slot` and `index`.
     ///
     /// Always attests to the canonical chain.
@@ -533,15 +1039,7 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
         slot: Slot,
         index: CommitteeIndex,
     ) -> Result<Attestation<T::EthSpec>, Error> {
-        let state = self.state_at_slot(slot)?;
-        let head = self.head();
-
-        let data = self.produce_attestation_data_for_block(
-            index,
-            head.beacon_block_root,
-            head.beacon_block.slot,
-            &state,
-        )?;
+        let (data, state) = self.produce_attestation_data_and_state(slot, index)?;
 
         let committee_len = state.get_beacon_committee(slot, index)?.committee.len();
 
@@ -560,15 +1058,94 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
         slot: Slot,
         index: CommitteeIndex,
     ) -> Result<AttestationData, Error> {
-        let state = self.state_at_slot(slot)?;
+        self.produce_attestation_data_and_state(slot, index)
+            .map(|(data, _state)| data)
+    }
+
+    /// Produce an `AttestationData` for the canonical head at the given `slot` and `index`, along
+    /// with a state from the epoch of `slot` which has a committee cache for that epoch.
+    ///
+    /// The source checkpoint and the shuffling only change at epoch boundaries, so the head state
+    /// is only advanced to the first slot of the epoch of `slot` (when the head is from a prior
+    /// epoch), rather than to `slot` itself. Attestations to slots prior to the head slot are
+    /// produced from the state at `slot`.
+    fn produce_attestation_data_and_state(
+        &self,
+        slot: Slot,
+        index: CommitteeIndex,
+    ) -> Result<(AttestationData, BeaconState<T::EthSpec>), Error> {
         let head = self.head();
 
-        self.produce_attestation_data_for_block(
+        if slot < head.beacon_state.slot {
+            let state = self.state_at_slot(slot)?;
+            let data = self.produce_attestation_data_for_block(
+                index,
+                head.beacon_block_root,
+                head.beacon_block.slot,
+                &state,
+            )?;
+
+            return Ok((data, state));
+        }
+
+        // Collect some metrics.
+        metrics::inc_counter(&metrics::ATTESTATION_PRODUCTION_REQUESTS);
+        let timer = metrics::start_timer(&metrics::ATTESTATION_PRODUCTION_TIMES);
+
+        let slots_per_epoch = T::EthSpec::slots_per_epoch();
+        let epoch = slot.epoch(slots_per_epoch);
+        let epoch_start_slot = epoch.start_slot(slots_per_epoch);
+
+        let mut state = head.beacon_state;
+
+        if state.current_epoch() < epoch {
+            if epoch_start_slot > state.slot + BLOCK_SKIPPING_FAILURE_THRESHOLD {
+                return Err(Error::StateSkipTooLarge {
+                    head_slot: state.slot,
+                    requested_slot: slot,
+                });
+            }
+
+            while state.slot < epoch_start_slot {
+                per_slot_processing(&mut state, &self.spec)?;
+            }
+        }
+
+        state.build_committee_cache(RelativeEpoch::Current, &self.spec)?;
+
+        // The `target_root` is the root of the first block of the epoch. If the head block is not
+        // later than the start of the epoch, every slot since the head block has been skipped and
+        // the head block is the target.
+        let target_root = if head.beacon_block.slot <= epoch_start_slot {
+            head.beacon_block_root
+        } else {
+            *state.get_block_root(epoch_start_slot)?
+        };
+
+        let data = AttestationData {
+            slot,
             index,
-            head.beacon_block_root,
-            head.beacon_block.slot,
-            &state,
-        )
+            beacon_block_root: head.beacon_block_root,
+            source: state.current_justified_checkpoint.clone(),
+            target: Checkpoint {
+                epoch,
+                root: target_root,
+            },
+        };
+
+        // Collect some metrics.
+        metrics::inc_counter(&metrics::ATTESTATION_PRODUCTION_SUCCESSES);
+        metrics::stop_timer(timer);
+
+        trace!(
+            self.log,
+            "Produced beacon attestation data";
+            "beacon_block_root" => format!("{}", head.beacon_block_root),
+            "slot" => slot,
+            "index" => index
+        );
+
+        Ok((data, state))
     }
 
     /// Produce an `AttestationData` that attests to the chain denoted by `block_root` and `state`.
@@ -639,6 +1216,21 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
         })
     }
 
+    /// Returns the best aggregated attestation that the `self.op_pool` holds for the given `data`.
+    ///
+    /// The attestation will have been verified upon insertion to the op pool, however it may
+    /// only contain a subset of the committee.
+    pub fn get_aggregated_attestation(
+        &self,
+        data: &AttestationData,
+    ) -> Result<Option<Attestation<T::EthSpec>>, Error> {
+        let head = self.head();
+
+        Ok(self
+            .op_pool
+            .get_aggregated_attestation(data, &head.beacon_state, &self.spec))
+    }
+
     /// Accept a new, potentially invalid attestation from the network.
     ///
     /// If valid, the attestation is added to `self.op_pool` and `self.fork_choice`.
@@ -672,6 +1264,14 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
                             attestation: Box::new(attestation),
                         });
                 }
+                AttestationProcessingOutcome::PriorAttestationKnown { .. } => {
+                    trace!(
+                        self.log,
+                        "Beacon attestation already known";
+                        "target_epoch" => attestation.data.target.epoch,
+                        "index" => attestation.data.index,
+                    );
+                }
                 other => {
                     warn!(
                         self.log,
@@ -704,42 +1304,144 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
         outcome
     }
 
-    pub fn process_attestation_internal(
+    /// Accept a new, potentially invalid `AggregateAndProof` from the network.
+    ///
+    /// The aggregator must be a member of the committee of the aggregate and its selection proof
+    /// must both be valid and select it as an aggregator. Only then is the aggregate itself
+    /// processed as per `Self::process_attestation`, which verifies its aggregate signature.
+    ///
+    /// Only the first valid aggregate from each aggregator is processed for each slot. Later
+    /// aggregates are dropped without verifying their signatures.
+    ///
+    /// ## Notes
+    ///
+    /// The committee of the aggregator is read from the attester shuffling of the canonical chain.
+    pub fn process_aggregate_and_proof(
         &self,
-        attestation: Attestation<T::EthSpec>,
+        aggregate_and_proof: AggregateAndProof<T::EthSpec>,
     ) -> Result<AttestationProcessingOutcome, Error> {
-        metrics::inc_counter(&metrics::ATTESTATION_PROCESSING_REQUESTS);
-        let timer = metrics::start_timer(&metrics::ATTESTATION_PROCESSING_TIMES);
+        metrics::inc_counter(&metrics::AGGREGATE_PROCESSING_REQUESTS);
 
-        if attestation.aggregation_bits.num_set_bits() == 0 {
-            return Ok(AttestationProcessingOutcome::EmptyAggregationBitfield);
+        let aggregator_index = aggregate_and_proof.aggregator_index;
+        let data = &aggregate_and_proof.aggregate.data;
+        let slot = data.slot;
+
+        let current_slot = self.slot()?;
+        if slot > current_slot || slot + ATTESTATION_PROPAGATION_SLOT_RANGE < current_slot {
+            return Ok(AttestationProcessingOutcome::SlotOutOfRange {
+                aggregate: slot,
+                current: current_slot,
+            });
         }
 
-        // From the store, load the attestation's "head block".
-        //
-        // An honest validator would have set this block to be the head of the chain (i.e., the
-        // result of running fork choice).
-        let result = if let Some(attestation_head_block) = self
-            .store
-            .get::<BeaconBlock<T::EthSpec>>(&attestation.data.beacon_block_root)?
-        {
-            // Attempt to process the attestation using the `self.head()` state.
-            //
-            // This is purely an effort to avoid loading a `BeaconState` unnecessarily from the DB.
-            let state = &self.head().beacon_state;
+        if self.observed_aggregators.is_known(slot, aggregator_index) {
+            metrics::inc_counter(&metrics::AGGREGATE_PROCESSING_AGGREGATOR_KNOWN);
+            return Ok(AttestationProcessingOutcome::AggregatorAlreadyKnown {
+                aggregator_index,
+                slot,
+            });
+        }
 
-            // If it turns out that the attestation was made using the head state, then there
-            // is no need to load a state from the database to process the attestation.
-            //
-            // Note: use the epoch of the target because it indicates which epoch the
-            // attestation was created in. You cannot use the epoch of the head block, because
-            // the block doesn't necessarily need to be in the same epoch as the attestation
-            // (e.g., if there are skip slots between the epoch the block was created in and
-            // the epoch for the attestation).
-            //
-            // This check also ensures that the slot for `data.beacon_block_root` is not higher
-            // than `state.root` by ensuring that the block is in the history of `state`.
-            if state.current_epoch() == attestation.data.target.epoch
+        let (committee_cache, _) =
+            self.attester_shuffling(slot.epoch(T::EthSpec::slots_per_epoch()))?;
+        let committee_len = match committee_cache.get_beacon_committee(slot, data.index) {
+            Some(committee) => {
+                if !committee
+                    .committee
+                    .iter()
+                    .any(|&validator_index| validator_index as u64 == aggregator_index)
+                {
+                    return Ok(AttestationProcessingOutcome::AggregatorNotInCommittee {
+                        aggregator_index,
+                    });
+                }
+
+                committee.committee.len()
+            }
+            None => {
+                return Ok(AttestationProcessingOutcome::NoCommittee {
+                    slot,
+                    index: data.index,
+                })
+            }
+        };
+
+        if !aggregate_and_proof.is_aggregator(committee_len, &self.spec) {
+            return Ok(AttestationProcessingOutcome::NotAggregator { aggregator_index });
+        }
+
+        let aggregator_pubkey = self
+            .validator_pubkey(aggregator_index as usize)
+            .ok_or_else(|| {
+                Error::ValidatorPubkeyCacheError(format!(
+                    "Missing pubkey for aggregator {}",
+                    aggregator_index
+                ))
+            })?;
+
+        if !aggregate_and_proof.is_valid_selection_proof(
+            &aggregator_pubkey,
+            &self.head_fork(),
+            &self.spec,
+        ) {
+            return Ok(AttestationProcessingOutcome::InvalidSelectionProof { aggregator_index });
+        }
+
+        let outcome = self.process_attestation(aggregate_and_proof.aggregate)?;
+
+        if outcome == AttestationProcessingOutcome::Processed {
+            // Only record the aggregator once the aggregate is known to be valid, otherwise any
+            // peer could prevent an aggregator from being heard.
+            if self.observed_aggregators.observe(slot, aggregator_index) {
+                metrics::inc_counter(&metrics::AGGREGATE_PROCESSING_AGGREGATOR_KNOWN);
+                return Ok(AttestationProcessingOutcome::AggregatorAlreadyKnown {
+                    aggregator_index,
+                    slot,
+                });
+            }
+
+            metrics::inc_counter(&metrics::AGGREGATE_PROCESSING_SUCCESSES);
+        }
+
+        Ok(outcome)
+    }
+
+    pub fn process_attestation_internal(
+        &self,
+        attestation: Attestation<T::EthSpec>,
+    ) -> Result<AttestationProcessingOutcome, Error> {
+        metrics::inc_counter(&metrics::ATTESTATION_PROCESSING_REQUESTS);
+        let timer = metrics::start_timer(&metrics::ATTESTATION_PROCESSING_TIMES);
+
+        if attestation.aggregation_bits.num_set_bits() == 0 {
+            return Ok(AttestationProcessingOutcome::EmptyAggregationBitfield);
+        }
+
+        // From the store, load the attestation's "head block".
+        //
+        // An honest validator would have set this block to be the head of the chain (i.e., the
+        // result of running fork choice).
+        let result = if let Some(attestation_head_block) = self
+            .store
+            .get::<BeaconBlock<T::EthSpec>>(&attestation.data.beacon_block_root)?
+        {
+            // Attempt to process the attestation using the `self.head()` state.
+            //
+            // This is purely an effort to avoid loading a `BeaconState` unnecessarily from the DB.
+            let state = &self.head().beacon_state;
+
+            // If it turns out that the attestation was made using the head state, then there
+            // is no need to load a state from the database to process the attestation.
+            //
+            // Note: use the epoch of the target because it indicates which epoch the
+            // attestation was created in. You cannot use the epoch of the head block, because
+            // the block doesn't necessarily need to be in the same epoch as the attestation
+            // (e.g., if there are skip slots between the epoch the block was created in and
+            // the epoch for the attestation).
+            //
+            // This check also ensures that the slot for `data.beacon_block_root` is not higher
+            // than `state.root` by ensuring that the block is in the history of `state`.
+            if state.current_epoch() == attestation.data.target.epoch
                 && (attestation.data.beacon_block_root == self.head().beacon_block_root
                     || state
                         .get_block_root(attestation_head_block.slot)
@@ -762,7 +1464,10 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             // not guaranteed to be from the same slot or epoch as the attestation.
             let mut state: BeaconState<T::EthSpec> = self
                 .store
-                .get(&attestation_head_block.state_root)?
+                .get_state(
+                    &attestation_head_block.state_root,
+                    Some(attestation_head_block.slot),
+                )?
                 .ok_or_else(|| Error::MissingBeaconState(attestation_head_block.state_root))?;
 
             // Ensure the state loaded from the database matches the state of the attestation
@@ -860,6 +1565,28 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             state.finalized_checkpoint.epoch,
         );
 
+        let target_epoch = attestation.data.target.epoch;
+        let data_root = Hash256::from_slice(&attestation.data.tree_hash_root());
+
+        // Identify the signer of an unaggregated attestation, so that repeated and conflicting
+        // votes from the same validator can be detected.
+        //
+        // Any error is ignored here, it will be reported by `verify_attestation_for_state`.
+        let unaggregated_attester = if attestation.aggregation_bits.num_set_bits() == 1 {
+            get_attesting_indices(state, &attestation.data, &attestation.aggregation_bits)
+                .ok()
+                .and_then(|indices| indices.into_iter().next())
+        } else {
+            None
+        };
+        let prior_vote = unaggregated_attester.map(|validator_index| {
+            (
+                validator_index,
+                self.observed_votes
+                    .check(validator_index, target_epoch, data_root),
+            )
+        });
+
         // A helper function to allow attestation processing to be metered.
         let verify_attestation_for_state = |state, attestation, spec, verify_signatures| {
             let timer = metrics::start_timer(&metrics::ATTESTATION_PROCESSING_CORE);
@@ -885,6 +1612,20 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
                 attestation: block.slot.epoch(T::EthSpec::slots_per_epoch()),
                 finalized: finalized_epoch,
             })
+        } else if let Some((validator_index, VoteStatus::Duplicate)) = prior_vote {
+            // Skip the signature verification of votes which have already been processed. This
+            // limits each validator to a single verified vote per target epoch.
+            metrics::inc_counter(&metrics::ATTESTATION_PROCESSING_PRIOR_VOTE_KNOWN);
+
+            Ok(AttestationProcessingOutcome::PriorAttestationKnown {
+                validator_index,
+                epoch: target_epoch,
+            })
+        } else if let Some((validator_index, VoteStatus::Equivocator)) = prior_vote {
+            Ok(AttestationProcessingOutcome::Equivocation {
+                validator_index,
+                epoch: target_epoch,
+            })
         } else if let Err(e) =
             verify_attestation_for_state(state, &attestation, VerifySignatures::True, &self.spec)
         {
@@ -897,6 +1638,29 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
 
             Ok(AttestationProcessingOutcome::Invalid(e))
         } else {
+            // Only record the vote once its signature is known to be valid, otherwise any peer
+            // could mark a validator as an equivocator.
+            if let Some(validator_index) = unaggregated_attester {
+                if self
+                    .observed_votes
+                    .observe(validator_index, target_epoch, data_root)
+                    == VoteStatus::Equivocation
+                {
+                    warn!(
+                        self.log,
+                        "Validator equivocated";
+                        "validator_index" => validator_index,
+                        "target_epoch" => target_epoch,
+                    );
+                    metrics::inc_counter(&metrics::ATTESTATION_PROCESSING_EQUIVOCATIONS);
+
+                    return Ok(AttestationProcessingOutcome::Equivocation {
+                        validator_index,
+                        epoch: target_epoch,
+                    });
+                }
+            }
+
             // If the attestation is from the current or previous epoch, supply it to the fork
             // choice. This is FMD GHOST.
             let current_epoch = self.epoch()?;
@@ -905,14 +1669,10 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             {
                 // Provide the attestation to fork choice, updating the validator latest messages but
                 // _without_ finding and updating the head.
-                if let Err(e) = self
-                    .fork_choice
-                    .process_attestation(&state, &attestation, block)
-                {
+                if let Err(e) = self.fork_choice.process_attestation(&state, &attestation) {
                     error!(
                         self.log,
                         "Add attestation to fork choice failed";
-                        "fork_choice_integrity" => format!("{:?}", self.fork_choice.verify_integrity()),
                         "beacon_block_root" =>  format!("{}", attestation.data.beacon_block_root),
                         "error" => format!("{:?}", e)
                     );
@@ -942,63 +1702,68 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
     }
 
     /// Accept some exit and queue it for inclusion in an appropriate block.
-    pub fn process_voluntary_exit(&self, exit: VoluntaryExit) -> Result<(), ExitValidationError> {
-        match self.wall_clock_state() {
-            Ok(state) => self.op_pool.insert_voluntary_exit(exit, &state, &self.spec),
-            Err(e) => {
-                error!(
-                    &self.log,
-                    "Unable to process voluntary exit";
-                    "error" => format!("{:?}", e),
-                    "reason" => "no state"
-                );
-                Ok(())
-            }
+    ///
+    /// The exit is verified against the head state. Exits for validators which already have an
+    /// exit in the op pool are ignored without verification.
+    pub fn process_voluntary_exit(
+        &self,
+        exit: VoluntaryExit,
+    ) -> Result<OperationProcessingOutcome, ExitValidationError> {
+        if self.op_pool.contains_voluntary_exit(exit.validator_index) {
+            return Ok(OperationProcessingOutcome::AlreadyKnown);
         }
+
+        let head = self.canonical_head.read();
+        self.op_pool
+            .insert_voluntary_exit(exit, &head.beacon_state, &self.spec)?;
+
+        Ok(OperationProcessingOutcome::Processed)
     }
 
     /// Accept some proposer slashing and queue it for inclusion in an appropriate block.
+    ///
+    /// The slashing is verified against the head state. Slashings for proposers which already
+    /// have a slashing in the op pool are ignored without verification.
     pub fn process_proposer_slashing(
         &self,
         proposer_slashing: ProposerSlashing,
-    ) -> Result<(), ProposerSlashingValidationError> {
-        match self.wall_clock_state() {
-            Ok(state) => {
-                self.op_pool
-                    .insert_proposer_slashing(proposer_slashing, &state, &self.spec)
-            }
-            Err(e) => {
-                error!(
-                    &self.log,
-                    "Unable to process proposer slashing";
-                    "error" => format!("{:?}", e),
-                    "reason" => "no state"
-                );
-                Ok(())
-            }
+    ) -> Result<OperationProcessingOutcome, ProposerSlashingValidationError> {
+        if self
+            .op_pool
+            .contains_proposer_slashing(proposer_slashing.proposer_index)
+        {
+            return Ok(OperationProcessingOutcome::AlreadyKnown);
         }
+
+        let head = self.canonical_head.read();
+        self.op_pool
+            .insert_proposer_slashing(proposer_slashing, &head.beacon_state, &self.spec)?;
+
+        Ok(OperationProcessingOutcome::Processed)
     }
 
     /// Accept some attester slashing and queue it for inclusion in an appropriate block.
+    ///
+    /// The slashing is verified against the head state. Slashings which would not slash any
+    /// validator that is not already slashed (or due to be slashed by the op pool) are ignored
+    /// without verification.
     pub fn process_attester_slashing(
         &self,
         attester_slashing: AttesterSlashing<T::EthSpec>,
-    ) -> Result<(), AttesterSlashingValidationError> {
-        match self.wall_clock_state() {
-            Ok(state) => {
-                self.op_pool
-                    .insert_attester_slashing(attester_slashing, &state, &self.spec)
-            }
-            Err(e) => {
-                error!(
-                    &self.log,
-                    "Unable to process attester slashing";
-                    "error" => format!("{:?}", e),
-                    "reason" => "no state"
-                );
-                Ok(())
-            }
+    ) -> Result<OperationProcessingOutcome, AttesterSlashingValidationError> {
+        let head = self.canonical_head.read();
+
+        if self
+            .op_pool
+            .attester_slashing_is_redundant(&attester_slashing, &head.beacon_state)
+        {
+            return Ok(OperationProcessingOutcome::AlreadyKnown);
         }
+
+        self.op_pool
+            .insert_attester_slashing(attester_slashing, &head.beacon_state, &self.spec)?;
+
+        Ok(OperationProcessingOutcome::Processed)
     }
 
     /// Accept some block and attempt to add it to block DAG.
@@ -1052,6 +1817,226 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
         outcome
     }
 
+    /// Imports a segment of blocks in which each block is the child of the block before it, such
+    /// as a batch of blocks downloaded by range sync.
+    ///
+    /// Unlike calling `Self::process_block` for each block, the parent state of the first block is
+    /// loaded from the database once and then advanced through the segment in memory. The
+    /// signatures of the blocks in each epoch of the segment are verified in a single batch, and
+    /// fork choice is run once the segment has been imported.
+    ///
+    /// Leading blocks which are already known or finalized are skipped. Import stops at the first
+    /// block which fails, leaving the blocks before it imported.
+    pub fn process_chain_segment(
+        &self,
+        blocks: Vec<BeaconBlock<T::EthSpec>>,
+    ) -> Result<ChainSegmentResult, Error> {
+        let result = self.process_chain_segment_internal(&blocks);
+
+        match &result {
+            Ok(ChainSegmentResult::Successful { .. }) => {}
+            Ok(ChainSegmentResult::Failed {
+                imported_blocks,
+                outcome,
+            }) => warn!(
+                self.log,
+                "Chain segment rejected";
+                "imported_blocks" => imported_blocks,
+                "reason" => format!("{:?}", outcome),
+            ),
+            Err(e) => error!(
+                self.log,
+                "Chain segment processing error";
+                "error" => format!("{:?}", e),
+            ),
+        }
+
+        // Blocks may have been imported before a failure, so fork choice is always run.
+        if let Err(e) = self.fork_choice() {
+            error!(
+                self.log,
+                "fork choice failed to find head";
+                "error" => format!("{:?}", e)
+            )
+        };
+
+        result
+    }
+
+    fn process_chain_segment_internal(
+        &self,
+        blocks: &[BeaconBlock<T::EthSpec>],
+    ) -> Result<ChainSegmentResult, Error> {
+        let slots_per_epoch = T::EthSpec::slots_per_epoch();
+        let block_roots = blocks
+            .iter()
+            .map(|block| block.canonical_root())
+            .collect::<Vec<_>>();
+
+        for (i, block) in blocks.iter().enumerate().skip(1) {
+            let outcome = if block.parent_root != block_roots[i - 1] {
+                BlockProcessingOutcome::NonLinearParentRoots
+            } else if block.slot <= blocks[i - 1].slot {
+                BlockProcessingOutcome::NonLinearSlots
+            } else {
+                continue;
+            };
+
+            return Ok(ChainSegmentResult::Failed {
+                imported_blocks: 0,
+                outcome,
+            });
+        }
+
+        let finalized_slot = self
+            .head()
+            .beacon_state
+            .finalized_checkpoint
+            .epoch
+            .start_slot(slots_per_epoch);
+
+        // Skip the leading blocks which `Self::process_block` would not import.
+        let mut first = 0;
+        while let Some(block) = blocks.get(first) {
+            let block_root = block_roots[first];
+            if block.slot == 0
+                || block.slot <= finalized_slot
+                || block_root == self.genesis_block_root
+                || self.store.exists::<BeaconBlock<T::EthSpec>>(&block_root)?
+            {
+                first += 1;
+            } else {
+                break;
+            }
+        }
+
+        let first_block = match blocks.get(first) {
+            Some(block) => block,
+            None => return Ok(ChainSegmentResult::Successful { imported_blocks: 0 }),
+        };
+
+        let parent_block: BeaconBlock<T::EthSpec> =
+            match self.store.get(&first_block.parent_root)? {
+                Some(block) => block,
+                None => {
+                    return Ok(ChainSegmentResult::Failed {
+                        imported_blocks: 0,
+                        outcome: BlockProcessingOutcome::ParentUnknown {
+                            parent: first_block.parent_root,
+                        },
+                    })
+                }
+            };
+        let mut state = self
+            .store
+            .get_state(&parent_block.state_root, Some(parent_block.slot))?
+            .ok_or_else(|| {
+                Error::DBInconsistent(format!("Missing state {}", parent_block.state_root))
+            })?;
+
+        let present_slot = self.slot()?;
+        let mut imported_blocks = 0;
+        let mut verified_epoch = None;
+
+        for (i, block) in blocks.iter().enumerate().skip(first) {
+            let block_root = block_roots[i];
+
+            if block.slot > present_slot {
+                return Ok(ChainSegmentResult::Failed {
+                    imported_blocks,
+                    outcome: BlockProcessingOutcome::FutureSlot {
+                        present_slot,
+                        block_slot: block.slot,
+                    },
+                });
+            }
+
+            let parent_finalized_epoch = state.finalized_checkpoint.epoch;
+            let intermediate_states = self.advance_state_to_block_slot(&mut state, block)?;
+
+            state.build_committee_cache(RelativeEpoch::Previous, &self.spec)?;
+            state.build_committee_cache(RelativeEpoch::Current, &self.spec)?;
+
+            // The proposers and committees of an epoch are fixed at its start, so the signatures of
+            // all the blocks of the segment in an epoch are verified upon the state of the first.
+            let epoch = block.slot.epoch(slots_per_epoch);
+            if verified_epoch != Some(epoch) {
+                let epoch_blocks = blocks[i..]
+                    .iter()
+                    .take_while(|block| block.slot.epoch(slots_per_epoch) == epoch);
+
+                if BlockSignatureVerifier::verify_entire_blocks(&state, epoch_blocks, &self.spec)
+                    .is_err()
+                {
+                    return Ok(ChainSegmentResult::Failed {
+                        imported_blocks,
+                        outcome: BlockProcessingOutcome::PerBlockProcessingError(
+                            BlockProcessingError::BulkSignatureVerificationFailed,
+                        ),
+                    });
+                }
+                verified_epoch = Some(epoch);
+            }
+
+            match per_block_processing(
+                &mut state,
+                block,
+                Some(block_root),
+                BlockSignatureStrategy::NoVerification,
+                &self.spec,
+            ) {
+                Err(BlockProcessingError::BeaconStateError(e)) => {
+                    return Err(Error::BeaconStateError(e))
+                }
+                Err(e) => {
+                    return Ok(ChainSegmentResult::Failed {
+                        imported_blocks,
+                        outcome: BlockProcessingOutcome::PerBlockProcessingError(e),
+                    })
+                }
+                Ok(()) => {}
+            }
+
+            let state_root = state.update_tree_hash_cache()?;
+
+            if block.state_root != state_root {
+                return Ok(ChainSegmentResult::Failed {
+                    imported_blocks,
+                    outcome: BlockProcessingOutcome::StateRootMismatch {
+                        block: block.state_root,
+                        local: state_root,
+                    },
+                });
+            }
+
+            match self.import_block(
+                block,
+                block_root,
+                &state,
+                state_root,
+                &intermediate_states,
+                parent_finalized_epoch,
+            )? {
+                BlockProcessingOutcome::Processed { .. } => {
+                    imported_blocks += 1;
+                    metrics::inc_counter(&metrics::BLOCK_PROCESSING_SUCCESSES);
+                    let _ = self.event_handler.register(EventKind::BeaconBlockImported {
+                        block_root,
+                        block: Box::new(block.clone()),
+                    });
+                }
+                outcome => {
+                    return Ok(ChainSegmentResult::Failed {
+                        imported_blocks,
+                        outcome,
+                    })
+                }
+            }
+        }
+
+        Ok(ChainSegmentResult::Successful { imported_blocks })
+    }
+
     /// Accept some block and attempt to add it to block DAG.
     ///
     /// Will accept blocks from prior slots, however it will reject any block from a future slot.
@@ -1123,29 +2108,17 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
         let parent_state_root = parent_block.state_root;
         let parent_state = self
             .store
-            .get(&parent_state_root)?
+            .get_state(&parent_state_root, Some(parent_block.slot))?
             .ok_or_else(|| Error::DBInconsistent(format!("Missing state {}", parent_state_root)))?;
+        let parent_finalized_epoch = parent_state.finalized_checkpoint.epoch;
 
         metrics::stop_timer(db_read_timer);
 
         write_block(&block, block_root, &self.log);
 
-        let catchup_timer = metrics::start_timer(&metrics::BLOCK_PROCESSING_CATCHUP_STATE);
-
-        // Keep a list of any states that were "skipped" (block-less) in between the parent state
-        // slot and the block slot. These will need to be stored in the database.
-        let mut intermediate_states = vec![];
-
         // Transition the parent state to the block slot.
         let mut state: BeaconState<T::EthSpec> = parent_state;
-        for i in state.slot.as_u64()..block.slot.as_u64() {
-            if i > 0 {
-                intermediate_states.push(state.clone());
-            }
-            per_slot_processing(&mut state, &self.spec)?;
-        }
-
-        metrics::stop_timer(catchup_timer);
+        let intermediate_states = self.advance_state_to_block_slot(&mut state, &block)?;
 
         let committee_timer = metrics::start_timer(&metrics::BLOCK_PROCESSING_COMMITTEE);
 
@@ -1154,6 +2127,8 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
 
         metrics::stop_timer(committee_timer);
 
+        let proposer_index = state.get_beacon_proposer_index(block.slot, &self.spec)?;
+
         write_state(
             &format!("state_pre_block_{}", block_root),
             &state,
@@ -1182,7 +2157,9 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
 
         let state_root_timer = metrics::start_timer(&metrics::BLOCK_PROCESSING_STATE_ROOT);
 
-        let state_root = state.canonical_root();
+        // The tree hash cache was updated by slot processing. Only the changes made by the block
+        // need to be rehashed.
+        let state_root = state.update_tree_hash_cache()?;
 
         write_state(
             &format!("state_post_block_{}", block_root),
@@ -1199,8 +2176,127 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
 
         metrics::stop_timer(state_root_timer);
 
+        match self.import_block(
+            &block,
+            block_root,
+            &state,
+            state_root,
+            &intermediate_states,
+            parent_finalized_epoch,
+        )? {
+            BlockProcessingOutcome::Processed { .. } => {}
+            other => return Ok(other),
+        }
+
+        self.observed_block_producers
+            .observe(block.slot, proposer_index as u64, block_root);
+
+        let find_head_timer =
+            metrics::start_timer(&metrics::BLOCK_PROCESSING_FORK_CHOICE_FIND_HEAD);
+
+        // Execute the fork choice algorithm, enthroning a new head if discovered.
+        //
+        // Note: in the future we may choose to run fork-choice less often, potentially based upon
+        // some heuristic around number of attestations seen for the block.
+        if let Err(e) = self.fork_choice() {
+            error!(
+                self.log,
+                "fork choice failed to find head";
+                "error" => format!("{:?}", e)
+            )
+        };
+
+        metrics::stop_timer(find_head_timer);
+
+        metrics::inc_counter(&metrics::BLOCK_PROCESSING_SUCCESSES);
+        metrics::observe(
+            &metrics::OPERATIONS_PER_BLOCK_ATTESTATION,
+            block.body.attestations.len() as f64,
+        );
+        metrics::stop_timer(full_timer);
+
+        Ok(BlockProcessingOutcome::Processed { block_root })
+    }
+
+    /// Transitions `state`, the parent state of `block`, to the slot of `block`.
+    ///
+    /// Returns the states of the skipped (block-less) slots between the parent state and `block`,
+    /// which need to be stored in the database alongside `block`.
+    fn advance_state_to_block_slot(
+        &self,
+        state: &mut BeaconState<T::EthSpec>,
+        block: &BeaconBlock<T::EthSpec>,
+    ) -> Result<Vec<BeaconState<T::EthSpec>>, Error> {
+        let catchup_timer = metrics::start_timer(&metrics::BLOCK_PROCESSING_CATCHUP_STATE);
+
+        let mut intermediate_states = vec![];
+
+        // Only blocks that build upon the canonical head contribute to the attestation inclusion
+        // metrics, so that forks do not clobber the canonical view.
+        let extends_head = block.parent_root == self.head().beacon_block_root;
+
+        for i in state.slot.as_u64()..block.slot.as_u64() {
+            if i > 0 {
+                intermediate_states.push(state.clone());
+            }
+            // Epoch processing is performed on the last slot of an epoch, at which point the
+            // previous epoch is the one whose attestations have all had a chance to be included.
+            let previous_epoch = state.previous_epoch();
+            let validator_statuses = per_slot_processing(state, &self.spec)?;
+            match validator_statuses {
+                Some(validator_statuses) if extends_head => {
+                    let summary = self.record_epoch_inclusion(previous_epoch, &validator_statuses);
+                    self.record_chain_quality(&summary, state);
+                }
+                _ => (),
+            }
+        }
+
+        metrics::stop_timer(catchup_timer);
+
+        Ok(intermediate_states)
+    }
+
+    /// Stores `block`, which has been applied to produce `state`, and registers it with fork
+    /// choice. Does not run fork choice to find the new head.
+    ///
+    /// Returns `BlockProcessingOutcome::Processed` unless `block` conflicts with the weak
+    /// subjectivity checkpoint, in which case it is not stored.
+    fn import_block(
+        &self,
+        block: &BeaconBlock<T::EthSpec>,
+        block_root: Hash256,
+        state: &BeaconState<T::EthSpec>,
+        state_root: Hash256,
+        intermediate_states: &[BeaconState<T::EthSpec>],
+        parent_finalized_epoch: Epoch,
+    ) -> Result<BlockProcessingOutcome, Error> {
+        // Only the first block of a chain to finalize the epoch of the weak subjectivity
+        // checkpoint is verified, since all of its descendants share the same ancestry.
+        if let Some(checkpoint) = self.weak_subjectivity_checkpoint.clone() {
+            if parent_finalized_epoch < checkpoint.epoch {
+                if let Some(local_root) = self.weak_subjectivity_conflict(state)? {
+                    crit!(
+                        self.log,
+                        "Block conflicts with weak subjectivity checkpoint";
+                        "msg" => "peers may be serving an attacker chain, check --wss-checkpoint",
+                        "block_root" => format!("{}", block_root),
+                        "checkpoint_root" => format!("{}", checkpoint.root),
+                        "checkpoint_epoch" => checkpoint.epoch,
+                        "local_root" => format!("{}", local_root),
+                    );
+                    return Ok(BlockProcessingOutcome::WeakSubjectivityConflict {
+                        checkpoint,
+                        local_root,
+                    });
+                }
+            }
+        }
+
         let db_write_timer = metrics::start_timer(&metrics::BLOCK_PROCESSING_DB_WRITE);
 
+        let mut ops = Vec::with_capacity(intermediate_states.len() + 2);
+
         // Store all the states between the parent block state and this blocks slot before storing
         // the final state.
         for (i, intermediate_state) in intermediate_states.iter().enumerate() {
@@ -1208,33 +2304,40 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             // state_roots field to find the root.
             let following_state = match intermediate_states.get(i + 1) {
                 Some(following_state) => following_state,
-                None => &state,
+                None => state,
             };
             let intermediate_state_root =
                 following_state.get_state_root(intermediate_state.slot)?;
 
-            self.store
-                .put(&intermediate_state_root, intermediate_state)?;
+            ops.push(StoreOp::PutState(
+                *intermediate_state_root,
+                intermediate_state,
+            ));
         }
 
         // Store the block and state.
-        self.store.put(&block_root, &block)?;
-        self.store.put(&state_root, &state)?;
+        ops.push(StoreOp::PutBlock(block_root, block));
+        ops.push(StoreOp::PutState(state_root, state));
+
+        // Write the block and all of its states in a single batch, so that a crash can never leave
+        // the block in the database without its state.
+        self.store.do_atomically(ops)?;
 
         metrics::stop_timer(db_write_timer);
 
+        self.head_tracker.register_block(block_root, block);
+
         let fork_choice_register_timer =
             metrics::start_timer(&metrics::BLOCK_PROCESSING_FORK_CHOICE_REGISTER);
 
         // Register the new block with the fork choice service.
         if let Err(e) = self
             .fork_choice
-            .process_block(self, &state, &block, block_root)
+            .process_block(self, state, block, block_root)
         {
             error!(
                 self.log,
                 "Add block to fork choice failed";
-                "fork_choice_integrity" => format!("{:?}", self.fork_choice.verify_integrity()),
                 "block_root" =>  format!("{}", block_root),
                 "error" => format!("{:?}", e),
             )
@@ -1242,30 +2345,6 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
 
         metrics::stop_timer(fork_choice_register_timer);
 
-        let find_head_timer =
-            metrics::start_timer(&metrics::BLOCK_PROCESSING_FORK_CHOICE_FIND_HEAD);
-
-        // Execute the fork choice algorithm, enthroning a new head if discovered.
-        //
-        // Note: in the future we may choose to run fork-choice less often, potentially based upon
-        // some heuristic around number of attestations seen for the block.
-        if let Err(e) = self.fork_choice() {
-            error!(
-                self.log,
-                "fork choice failed to find head";
-                "error" => format!("{:?}", e)
-            )
-        };
-
-        metrics::stop_timer(find_head_timer);
-
-        metrics::inc_counter(&metrics::BLOCK_PROCESSING_SUCCESSES);
-        metrics::observe(
-            &metrics::OPERATIONS_PER_BLOCK_ATTESTATION,
-            block.body.attestations.len() as f64,
-        );
-        metrics::stop_timer(full_timer);
-
         Ok(BlockProcessingOutcome::Processed { block_root })
     }
 
@@ -1278,9 +2357,11 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
         randao_reveal: Signature,
         slot: Slot,
     ) -> Result<BeaconBlockAndState<T::EthSpec>, BlockProductionError> {
+        let state_load_timer = metrics::start_timer(&metrics::BLOCK_PRODUCTION_STATE_LOAD_TIMES);
         let state = self
             .state_at_slot(slot - 1)
             .map_err(|_| BlockProductionError::UnableToProduceAtSlot(slot))?;
+        metrics::stop_timer(state_load_timer);
 
         self.produce_block_on_state(state.clone(), slot, randao_reveal)
     }
@@ -1307,6 +2388,9 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             .as_ref()
             .ok_or_else(|| BlockProductionError::NoEth1ChainConnection)?;
 
+        let slot_process_timer =
+            metrics::start_timer(&metrics::BLOCK_PRODUCTION_SLOT_PROCESS_TIMES);
+
         // If required, transition the new state to the present slot.
         while state.slot < produce_at_slot {
             per_slot_processing(&mut state, &self.spec)?;
@@ -1314,6 +2398,8 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
 
         state.build_committee_cache(RelativeEpoch::Current, &self.spec)?;
 
+        metrics::stop_timer(slot_process_timer);
+
         let parent_root = if state.slot > 0 {
             *state
                 .get_block_root(state.slot - 1)
@@ -1325,9 +2411,23 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
         let mut graffiti: [u8; 32] = [0; 32];
         graffiti.copy_from_slice(GRAFFITI.as_bytes());
 
+        let operations_timer = metrics::start_timer(&metrics::BLOCK_PRODUCTION_OPERATIONS_TIMES);
+
         let (proposer_slashings, attester_slashings) =
             self.op_pool.get_slashings(&state, &self.spec);
 
+        let eth1_data = eth1_chain.eth1_data_for_block_production(&state, &self.spec)?;
+        let deposits = eth1_chain.deposits_for_block_inclusion(&state, &eth1_data, &self.spec)?;
+
+        let attestation_packing = self.op_pool.get_attestation_packing(&state, &self.spec);
+        metrics::observe(
+            &metrics::BLOCK_PRODUCTION_ATTESTATION_PACKING_EFFICIENCY,
+            attestation_packing.efficiency(),
+        );
+        let voluntary_exits = self.op_pool.get_voluntary_exits(&state, &self.spec);
+
+        metrics::stop_timer(operations_timer);
+
         let mut block = BeaconBlock {
             slot: state.slot,
             parent_root,
@@ -1336,18 +2436,18 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             signature: Signature::empty_signature(),
             body: BeaconBlockBody {
                 randao_reveal,
-                eth1_data: eth1_chain.eth1_data_for_block_production(&state, &self.spec)?,
+                eth1_data,
                 graffiti,
                 proposer_slashings: proposer_slashings.into(),
                 attester_slashings: attester_slashings.into(),
-                attestations: self.op_pool.get_attestations(&state, &self.spec).into(),
-                deposits: eth1_chain
-                    .deposits_for_block_inclusion(&state, &self.spec)?
-                    .into(),
-                voluntary_exits: self.op_pool.get_voluntary_exits(&state, &self.spec).into(),
+                attestations: attestation_packing.attestations.into(),
+                deposits: deposits.into(),
+                voluntary_exits: voluntary_exits.into(),
             },
         };
 
+        let state_root_timer = metrics::start_timer(&metrics::BLOCK_PRODUCTION_STATE_ROOT_TIMES);
+
         per_block_processing(
             &mut state,
             &block,
@@ -1356,10 +2456,11 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             &self.spec,
         )?;
 
-        let state_root = state.canonical_root();
+        let state_root = state.update_tree_hash_cache()?;
 
         block.state_root = state_root;
 
+        metrics::stop_timer(state_root_timer);
         metrics::inc_counter(&metrics::BLOCK_PRODUCTION_SUCCESSES);
         metrics::stop_timer(timer);
 
@@ -1368,12 +2469,28 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             "Produced beacon block";
             "parent" => format!("{}", block.parent_root),
             "attestations" => block.body.attestations.len(),
+            "included_attesting_gwei" => attestation_packing.included_balance,
+            "available_attesting_gwei" => attestation_packing.available_balance,
             "slot" => block.slot
         );
 
         Ok((block, state))
     }
 
+    /// Runs fork choice so that attestations received since the last block (e.g., via gossip) are
+    /// reflected in the head, even when no blocks are being produced.
+    ///
+    /// Intended to be called once at the start of each slot.
+    pub fn per_slot_task(&self) {
+        if let Err(e) = self.fork_choice() {
+            error!(
+                self.log,
+                "Fork choice failed at slot start";
+                "error" => format!("{:?}", e)
+            )
+        }
+    }
+
     /// Execute the fork choice algorithm and enthrone the result as the canonical head.
     pub fn fork_choice(&self) -> Result<(), Error> {
         metrics::inc_counter(&metrics::FORK_CHOICE_REQUESTS);
@@ -1396,13 +2513,23 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
             let beacon_state_root = beacon_block.state_root;
             let beacon_state: BeaconState<T::EthSpec> = self
                 .store
-                .get(&beacon_state_root)?
+                .get_state(&beacon_state_root, Some(beacon_block.slot))?
                 .ok_or_else(|| Error::MissingBeaconState(beacon_state_root))?;
 
-            let previous_slot = self.head().beacon_block.slot;
+            let (previous_root, previous_slot, previous_state_root) = {
+                let head = self.canonical_head.read();
+                (
+                    head.beacon_block_root,
+                    head.beacon_block.slot,
+                    head.beacon_state_root,
+                )
+            };
             let new_slot = beacon_block.slot;
 
-            let is_reorg = self.head().beacon_block_root != beacon_block.parent_root;
+            // A re-org occurs when the new head does not descend from the previous head.
+            let is_reorg = previous_root != beacon_block.parent_root
+                && self.get_ancestor_block_root(beacon_block_root, previous_slot)?
+                    != Some(previous_root);
 
             // If we switched to a new chain (instead of building atop the present chain).
             if is_reorg {
@@ -1410,7 +2537,7 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
                 warn!(
                     self.log,
                     "Beacon chain re-org";
-                    "previous_head" => format!("{}", self.head().beacon_block_root),
+                    "previous_head" => format!("{}", previous_root),
                     "previous_slot" => previous_slot,
                     "new_head_parent" => format!("{}", beacon_block.parent_root),
                     "new_head" => format!("{}", beacon_block_root),
@@ -1456,6 +2583,12 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
 
                 new_head.beacon_state.build_all_caches(&self.spec)?;
 
+                // Validator indices never change, so any keys appended to the registry by the new
+                // head can be added to the cache regardless of whether this is a re-org.
+                self.validator_pubkey_cache
+                    .write()
+                    .import_new_pubkeys(&new_head.beacon_state)?;
+
                 let timer = metrics::start_timer(&metrics::UPDATE_HEAD_TIMES);
 
                 // Update the checkpoint that stores the head of the chain at the time it received the
@@ -1473,6 +2606,13 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
                     current_head_beacon_block_root,
                 });
 
+                if is_reorg {
+                    self.after_reorg(
+                        (previous_root, previous_slot, previous_state_root),
+                        (beacon_block_root, new_slot, beacon_state_root),
+                    );
+                }
+
                 if new_finalized_epoch != old_finalized_epoch {
                     self.after_finalization(old_finalized_epoch, finalized_root)?;
                 }
@@ -1493,6 +2633,175 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
         result
     }
 
+    /// Called after the head has switched to a block that does not descend from the previous
+    /// head, with the `(block_root, slot, state_root)` of the old and new heads.
+    ///
+    /// Computes the depth of the re-org, emits a `ChainReorg` event and returns the operations
+    /// from the orphaned blocks to the op pool so they may be included in the new chain. The head
+    /// state caches are rebuilt whenever the head changes, so no other caches are invalidated.
+    ///
+    /// Errors are logged, rather than returned, since the new head has already been applied.
+    fn after_reorg(&self, old_head: (Hash256, Slot, Hash256), new_head: (Hash256, Slot, Hash256)) {
+        let (old_head_block, old_head_slot, old_head_state) = old_head;
+        let (new_head_block, new_head_slot, new_head_state) = new_head;
+
+        let common_ancestor = match self.find_common_ancestor(
+            (old_head_block, old_head_slot),
+            (new_head_block, new_head_slot),
+        ) {
+            Ok(Some(ancestor)) => ancestor,
+            Ok(None) => {
+                warn!(
+                    self.log,
+                    "Unable to find re-org common ancestor";
+                    "old_head" => format!("{}", old_head_block),
+                    "new_head" => format!("{}", new_head_block),
+                );
+                return;
+            }
+            Err(e) => {
+                error!(
+                    self.log,
+                    "Error finding re-org common ancestor";
+                    "error" => format!("{:?}", e),
+                );
+                return;
+            }
+        };
+
+        let (common_ancestor_root, common_ancestor_slot) = common_ancestor;
+        let depth = old_head_slot
+            .as_u64()
+            .saturating_sub(common_ancestor_slot.as_u64());
+
+        metrics::set_gauge(&metrics::FORK_CHOICE_REORG_DEPTH, depth as i64);
+
+        warn!(
+            self.log,
+            "Beacon chain re-org depth";
+            "depth" => depth,
+            "common_ancestor" => format!("{}", common_ancestor_root),
+            "common_ancestor_slot" => common_ancestor_slot,
+        );
+
+        let _ = self.event_handler.register(EventKind::ChainReorg {
+            slot: new_head_slot,
+            depth,
+            old_head_block,
+            new_head_block,
+            old_head_state,
+            new_head_state,
+        });
+
+        if let Err(e) = self.reinsert_orphaned_operations(old_head_block, common_ancestor_slot) {
+            error!(
+                self.log,
+                "Unable to restore orphaned operations";
+                "error" => format!("{:?}", e),
+            );
+        }
+    }
+
+    /// Returns the `(block_root, slot)` of the most recent block shared by the chains of the two
+    /// given `(block_root, slot)` pairs, if it can be found.
+    ///
+    /// The returned slot is the highest slot at which both chains agree, which is later than the
+    /// slot of the ancestor block if it was followed by skipped slots.
+    pub fn find_common_ancestor(
+        &self,
+        a: (Hash256, Slot),
+        b: (Hash256, Slot),
+    ) -> Result<Option<(Hash256, Slot)>, Error> {
+        let lowest_slot = std::cmp::min(a.1, b.1);
+
+        let a_iter = self
+            .rev_iter_block_roots_from(a.0)?
+            .skip_while(|(_, slot)| *slot > lowest_slot);
+        let b_iter = self
+            .rev_iter_block_roots_from(b.0)?
+            .skip_while(|(_, slot)| *slot > lowest_slot);
+
+        Ok(a_iter
+            .zip(b_iter)
+            .find(|((a_root, _), (b_root, _))| a_root == b_root)
+            .map(|(ancestor, _)| ancestor))
+    }
+
+    /// Returns the operations in the blocks between `orphaned_head` and `ancestor_slot`
+    /// (exclusive) to the op pool, verifying them against the canonical head state.
+    ///
+    /// Operations which have already been included in the canonical chain, or which are otherwise
+    /// invalid, are rejected by the op pool or filtered out during block production. Deposits are
+    /// not restored, since they are always drawn from the eth1 cache.
+    fn reinsert_orphaned_operations(
+        &self,
+        orphaned_head: Hash256,
+        ancestor_slot: Slot,
+    ) -> Result<(), Error> {
+        let mut orphaned_roots = self
+            .rev_iter_block_roots_from(orphaned_head)?
+            .take_while(|(_, slot)| *slot > ancestor_slot)
+            .map(|(root, _)| root)
+            .collect::<Vec<_>>();
+        // Skipped slots repeat the root of the prior block.
+        orphaned_roots.dedup();
+
+        let head = self.head();
+        let state = &head.beacon_state;
+
+        let mut restored = 0;
+        for root in orphaned_roots {
+            let block = self
+                .get_block(&root)?
+                .ok_or_else(|| Error::MissingBeaconBlock(root))?;
+
+            for attestation in block.body.attestations.iter() {
+                if self
+                    .op_pool
+                    .insert_attestation(attestation.clone(), state, &self.spec)
+                    .is_ok()
+                {
+                    restored += 1;
+                }
+            }
+            for slashing in block.body.proposer_slashings.iter() {
+                if self
+                    .op_pool
+                    .insert_proposer_slashing(slashing.clone(), state, &self.spec)
+                    .is_ok()
+                {
+                    restored += 1;
+                }
+            }
+            for slashing in block.body.attester_slashings.iter() {
+                if self
+                    .op_pool
+                    .insert_attester_slashing(slashing.clone(), state, &self.spec)
+                    .is_ok()
+                {
+                    restored += 1;
+                }
+            }
+            for exit in block.body.voluntary_exits.iter() {
+                if self
+                    .op_pool
+                    .insert_voluntary_exit(exit.clone(), state, &self.spec)
+                    .is_ok()
+                {
+                    restored += 1;
+                }
+            }
+        }
+
+        debug!(
+            self.log,
+            "Restored orphaned operations";
+            "count" => restored,
+        );
+
+        Ok(())
+    }
+
     /// Called after `self` has had a new block finalized.
     ///
     /// Performs pruning and finality-based optimizations.
@@ -1514,15 +2823,20 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
                 new_epoch: new_finalized_epoch,
             })
         } else {
-            self.fork_choice
-                .process_finalization(&finalized_block, finalized_block_root)?;
-
             let finalized_state = self
-                .store
-                .get::<BeaconState<T::EthSpec>>(&finalized_block.state_root)?
+                .get_state(&finalized_block.state_root, Some(finalized_block.slot))?
                 .ok_or_else(|| Error::MissingBeaconState(finalized_block.state_root))?;
 
-            self.op_pool.prune_all(&finalized_state, &self.spec);
+            self.prune_to_finalized_block(finalized_block_root, &finalized_state)?;
+
+            // Prune abandoned forks and move the finalized states into the freezer database (if
+            // any). Depending on the migrator, this may happen in a background thread.
+            self.store_migrator.process_finalization(
+                finalized_block_root,
+                finalized_block.state_root,
+                finalized_state,
+                self.head_tracker.clone(),
+            );
 
             let _ = self.event_handler.register(EventKind::BeaconFinalization {
                 epoch: new_finalized_epoch,
@@ -1533,6 +2847,78 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
         }
     }
 
+    /// Prunes the fork choice tree and the operation pool so they only contain items that are
+    /// relevant to descendants of `finalized_block_root`.
+    fn prune_to_finalized_block(
+        &self,
+        finalized_block_root: Hash256,
+        finalized_state: &BeaconState<T::EthSpec>,
+    ) -> Result<(), Error> {
+        self.fork_choice
+            .process_finalization(finalized_block_root)?;
+
+        self.op_pool.prune_all(finalized_state, &self.spec);
+
+        Ok(())
+    }
+
+    /// Prunes the fork choice tree and the operation pool against the finalized checkpoint of
+    /// the current head, then prunes abandoned forks from the database and migrates the finalized
+    /// states to the freezer via the store migrator.
+    ///
+    /// Pruning happens automatically upon finalization, this function allows it to be triggered
+    /// manually (e.g., via the HTTP API). Depending on the migrator, the database may be pruned
+    /// in a background thread after this function returns.
+    pub fn prune(&self) -> Result<(), Error> {
+        let finalized_block_root = match self.head().beacon_state.finalized_checkpoint.root {
+            // The zero root is an alias for genesis.
+            root if root == Hash256::zero() => self.genesis_block_root,
+            root => root,
+        };
+
+        let finalized_block = self
+            .store
+            .get::<BeaconBlock<T::EthSpec>>(&finalized_block_root)?
+            .ok_or_else(|| Error::MissingBeaconBlock(finalized_block_root))?;
+        let finalized_state = self
+            .get_state(&finalized_block.state_root, Some(finalized_block.slot))?
+            .ok_or_else(|| Error::MissingBeaconState(finalized_block.state_root))?;
+
+        self.prune_to_finalized_block(finalized_block_root, &finalized_state)?;
+
+        self.store_migrator.process_finalization(
+            finalized_block_root,
+            finalized_block.state_root,
+            finalized_state,
+            self.head_tracker.clone(),
+        );
+
+        Ok(())
+    }
+
+    /// Clears the shuffling cache and the caches of the store, then drops and rebuilds all the
+    /// caches of the head state.
+    ///
+    /// The caches are rebuilt on a copy of the head state, so the head is not locked whilst the
+    /// (potentially slow) rebuild takes place. If the head changes during the rebuild, the new
+    /// head is left untouched.
+    pub fn clear_caches(&self) -> Result<(), Error> {
+        self.shuffling_cache.lock().clear();
+        self.store.clear_caches();
+
+        let mut head = self.head();
+
+        head.beacon_state.drop_all_caches();
+        head.beacon_state.build_all_caches(&self.spec)?;
+
+        let mut canonical_head = self.canonical_head.write();
+        if canonical_head.beacon_block_root == head.beacon_block_root {
+            *canonical_head = head;
+        }
+
+        Ok(())
+    }
+
     /// Returns `true` if the given block root has not been processed.
     pub fn is_new_block_root(&self, beacon_block_root: &Hash256) -> Result<bool, Error> {
         Ok(!self
@@ -1568,9 +2954,12 @@ impl<T: BeaconChainTypes> BeaconChain<T> {
                     Error::DBInconsistent(format!("Missing block {}", beacon_block_root))
                 })?;
             let beacon_state_root = beacon_block.state_root;
-            let beacon_state = self.store.get(&beacon_state_root)?.ok_or_else(|| {
-                Error::DBInconsistent(format!("Missing state {}", beacon_state_root))
-            })?;
+            let beacon_state = self
+                .store
+                .get_state(&beacon_state_root, Some(beacon_block.slot))?
+                .ok_or_else(|| {
+                    Error::DBInconsistent(format!("Missing state {}", beacon_state_root))
+                })?;
 
             let slot = CheckPoint {
                 beacon_block,
@@ -0,0 +1,179 @@
+use crate::errors::BeaconChainError;
+use serde_derive::{Deserialize, Serialize};
+use state_processing::common::get_attesting_indices;
+use state_processing::per_epoch_processing::apply_rewards::get_base_reward;
+use std::collections::HashSet;
+use types::{BeaconBlock, BeaconState, ChainSpec, Epoch, EthSpec, Hash256, RelativeEpoch, Slot};
+
+/// The rewards earned by the proposer of a single block for the operations that it included.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct BlockRewards {
+    pub slot: Slot,
+    pub block_root: Hash256,
+    pub proposer_index: usize,
+    /// The count of validators whose attestation for some target epoch was included for the
+    /// first time by this block.
+    pub included_attesters: u64,
+    /// The reward for including the attestations of `included_attesters`, in gwei.
+    ///
+    /// This reward is paid when the target epoch of the attestations is processed, and is
+    /// computed from the balances of the state prior to the block. It is therefore an estimate
+    /// if balances change in the meantime.
+    pub attestation_rewards: u64,
+    /// The reward for including proposer slashings, in gwei.
+    pub proposer_slashing_rewards: u64,
+    /// The reward for including attester slashings, in gwei.
+    pub attester_slashing_rewards: u64,
+    /// The sum of all rewards, in gwei.
+    pub total: u64,
+}
+
+/// Computes the rewards earned by the proposer of `block` for the operations that it included.
+///
+/// The `state` must be the pre-state of `block`, advanced to the slot of the block.
+pub fn compute_block_rewards<T: EthSpec>(
+    block: &BeaconBlock<T>,
+    block_root: Hash256,
+    state: &mut BeaconState<T>,
+    spec: &ChainSpec,
+) -> Result<BlockRewards, BeaconChainError> {
+    state.build_committee_cache(RelativeEpoch::Previous, spec)?;
+    state.build_committee_cache(RelativeEpoch::Current, spec)?;
+
+    let current_epoch = state.current_epoch();
+    let mut rewards = BlockRewards {
+        slot: block.slot,
+        block_root,
+        proposer_index: state.get_beacon_proposer_index(block.slot, spec)?,
+        ..BlockRewards::default()
+    };
+
+    // Only the earliest inclusion of an attester is rewarded, so attesters which were included by
+    // an earlier block are ignored.
+    let mut included = HashSet::<(Epoch, usize)>::new();
+    for pending in state
+        .previous_epoch_attestations
+        .iter()
+        .chain(state.current_epoch_attestations.iter())
+    {
+        for index in get_attesting_indices(state, &pending.data, &pending.aggregation_bits)? {
+            included.insert((pending.data.target.epoch, index));
+        }
+    }
+
+    let total_active_balance =
+        state.get_total_balance(&state.get_active_validator_indices(current_epoch), spec)?;
+
+    for attestation in block.body.attestations.iter() {
+        let target_epoch = attestation.data.target.epoch;
+        for index in get_attesting_indices(state, &attestation.data, &attestation.aggregation_bits)?
+        {
+            if !included.insert((target_epoch, index)) || state.validators[index].slashed {
+                continue;
+            }
+
+            rewards.included_attesters += 1;
+            rewards.attestation_rewards +=
+                get_base_reward(state, index, total_active_balance, spec)?
+                    / spec.proposer_reward_quotient;
+        }
+    }
+
+    // The proposer is the whistleblower, so it receives the entire whistleblower reward. A
+    // validator is only slashed once, even if it is included in several slashings.
+    let mut slashed = HashSet::new();
+    let mut whistleblower_reward = |index: usize| -> Result<u64, BeaconChainError> {
+        let validator = state.validators.get(index).ok_or_else(|| {
+            BeaconChainError::InvariantViolated(format!("Unknown validator {}", index))
+        })?;
+
+        if validator.is_slashable_at(current_epoch) && slashed.insert(index) {
+            Ok(validator.effective_balance / spec.whistleblower_reward_quotient)
+        } else {
+            Ok(0)
+        }
+    };
+
+    for proposer_slashing in block.body.proposer_slashings.iter() {
+        rewards.proposer_slashing_rewards +=
+            whistleblower_reward(proposer_slashing.proposer_index as usize)?;
+    }
+
+    for attester_slashing in block.body.attester_slashings.iter() {
+        let indices_1 = attester_slashing
+            .attestation_1
+            .attesting_indices
+            .iter()
+            .collect::<HashSet<_>>();
+        let mut slashable_indices = attester_slashing
+            .attestation_2
+            .attesting_indices
+            .iter()
+            .filter(|index| indices_1.contains(index))
+            .map(|index| *index as usize)
+            .collect::<Vec<_>>();
+        slashable_indices.sort();
+
+        for index in slashable_indices {
+            rewards.attester_slashing_rewards += whistleblower_reward(index)?;
+        }
+    }
+
+    rewards.total = rewards.attestation_rewards
+        + rewards.proposer_slashing_rewards
+        + rewards.attester_slashing_rewards;
+
+    Ok(rewards)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use types::{
+        test_utils::{
+            ProposerSlashingTestTask, TestingBeaconStateBuilder, TestingProposerSlashingBuilder,
+        },
+        Domain, MinimalEthSpec, Signature,
+    };
+
+    #[test]
+    fn proposer_slashing_rewards() {
+        let spec = MinimalEthSpec::default_spec();
+        let builder = TestingBeaconStateBuilder::from_deterministic_keypairs(8, &spec);
+        let (mut state, _keypairs) = builder.build();
+
+        let mut block = BeaconBlock::<MinimalEthSpec>::empty(&spec);
+        block.slot = state.slot;
+
+        let rewards = compute_block_rewards(&block, Hash256::zero(), &mut state, &spec)
+            .expect("should compute rewards");
+        assert_eq!(rewards.total, 0);
+        assert_eq!(
+            rewards.proposer_index,
+            state
+                .get_beacon_proposer_index(state.slot, &spec)
+                .expect("should get proposer")
+        );
+
+        // signatures are not verified when computing rewards
+        let signer = |_: u64, _: &[u8], _: Epoch, _: Domain| Signature::empty_signature();
+        let slashing = TestingProposerSlashingBuilder::double_vote::<MinimalEthSpec, _>(
+            ProposerSlashingTestTask::Valid,
+            1,
+            signer,
+        );
+        // the same validator is only rewarded once
+        block
+            .body
+            .proposer_slashings
+            .push(slashing.clone())
+            .unwrap();
+        block.body.proposer_slashings.push(slashing).unwrap();
+
+        let rewards = compute_block_rewards(&block, Hash256::zero(), &mut state, &spec)
+            .expect("should compute rewards");
+        let expected = spec.max_effective_balance / spec.whistleblower_reward_quotient;
+        assert_eq!(rewards.proposer_slashing_rewards, expected);
+        assert_eq!(rewards.total, expected);
+    }
+}
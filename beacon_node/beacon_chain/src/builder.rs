@@ -1,48 +1,64 @@
-use crate::eth1_chain::CachingEth1Backend;
+use crate::chain_quality::ChainQualityCache;
+use crate::eth1_chain::{CachingEth1Backend, SszEth1};
 use crate::events::NullEventHandler;
-use crate::persisted_beacon_chain::{PersistedBeaconChain, BEACON_CHAIN_DB_KEY};
+use crate::head_tracker::HeadTracker;
+use crate::migrate::Migrate;
+use crate::observed_aggregators::ObservedAggregators;
+use crate::observed_block_producers::ObservedBlockProducers;
+use crate::observed_votes::ObservedVotes;
+use crate::persisted_beacon_chain::{
+    GenesisValidatorsRoot, PersistedBeaconChain, BEACON_CHAIN_DB_KEY,
+    GENESIS_VALIDATORS_ROOT_DB_KEY,
+};
+use crate::shuffling_cache::ShufflingCache;
+use crate::validator_pubkey_cache::ValidatorPubkeyCache;
 use crate::{
     BeaconChain, BeaconChainTypes, CheckPoint, Eth1Chain, Eth1ChainBackend, EventHandler,
     ForkChoice,
 };
 use eth1::Config as Eth1Config;
-use lmd_ghost::{LmdGhost, ThreadSafeReducedTree};
 use operation_pool::OperationPool;
-use parking_lot::RwLock;
-use slog::{info, Logger};
+use parking_lot::{Mutex, RwLock};
+use resource_budget::ResourceBudget;
+use slog::{info, warn, Logger};
 use slot_clock::{SlotClock, TestingSlotClock};
 use std::marker::PhantomData;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
-use store::Store;
-use types::{BeaconBlock, BeaconState, ChainSpec, EthSpec, Hash256, Slot};
+use store::{Store, StoreItem, StoreOp};
+use tree_hash::TreeHash;
+use types::{BeaconBlock, BeaconState, ChainSpec, Checkpoint, EthSpec, Hash256, Slot};
+
+/// The name of the file in the data directory that stores the validator pubkey cache.
+pub const PUBKEY_CACHE_FILENAME: &str = "pubkey_cache.bin";
 
 /// An empty struct used to "witness" all the `BeaconChainTypes` traits. It has no user-facing
 /// functionality and only exists to satisfy the type system.
-pub struct Witness<TStore, TSlotClock, TLmdGhost, TEth1Backend, TEthSpec, TEventHandler>(
+pub struct Witness<TStore, TStoreMigrator, TSlotClock, TEth1Backend, TEthSpec, TEventHandler>(
     PhantomData<(
         TStore,
+        TStoreMigrator,
         TSlotClock,
-        TLmdGhost,
         TEth1Backend,
         TEthSpec,
         TEventHandler,
     )>,
 );
 
-impl<TStore, TSlotClock, TLmdGhost, TEth1Backend, TEthSpec, TEventHandler> BeaconChainTypes
-    for Witness<TStore, TSlotClock, TLmdGhost, TEth1Backend, TEthSpec, TEventHandler>
+impl<TStore, TStoreMigrator, TSlotClock, TEth1Backend, TEthSpec, TEventHandler> BeaconChainTypes
+    for Witness<TStore, TStoreMigrator, TSlotClock, TEth1Backend, TEthSpec, TEventHandler>
 where
     TStore: Store + 'static,
+    TStoreMigrator: Migrate<TStore, TEthSpec> + 'static,
     TSlotClock: SlotClock + 'static,
-    TLmdGhost: LmdGhost<TStore, TEthSpec> + 'static,
     TEth1Backend: Eth1ChainBackend<TEthSpec> + 'static,
     TEthSpec: EthSpec + 'static,
     TEventHandler: EventHandler<TEthSpec> + 'static,
 {
     type Store = TStore;
+    type StoreMigrator = TStoreMigrator;
     type SlotClock = TSlotClock;
-    type LmdGhost = TLmdGhost;
     type Eth1Chain = TEth1Backend;
     type EthSpec = TEthSpec;
     type EventHandler = TEventHandler;
@@ -58,27 +74,37 @@ where
 /// See the tests for an example of a complete working example.
 pub struct BeaconChainBuilder<T: BeaconChainTypes> {
     store: Option<Arc<T::Store>>,
+    store_migrator: Option<T::StoreMigrator>,
     /// The finalized checkpoint to anchor the chain. May be genesis or a higher
     /// checkpoint.
     pub finalized_checkpoint: Option<CheckPoint<T::EthSpec>>,
     genesis_block_root: Option<Hash256>,
+    genesis_validators_root: Option<Hash256>,
     op_pool: Option<OperationPool<T::EthSpec>>,
     fork_choice: Option<ForkChoice<T>>,
     eth1_chain: Option<Eth1Chain<T::Eth1Chain, T::EthSpec>>,
+    /// The cached state of the eth1 chain, if it was restored from the database.
+    persisted_eth1: Option<SszEth1>,
     event_handler: Option<T::EventHandler>,
     slot_clock: Option<T::SlotClock>,
+    head_tracker: Option<HeadTracker>,
+    data_dir: Option<PathBuf>,
+    /// The validator pubkey cache, if it was loaded before the head state was decoded.
+    validator_pubkey_cache: Option<ValidatorPubkeyCache>,
+    weak_subjectivity_checkpoint: Option<Checkpoint>,
+    resource_budget: ResourceBudget,
     spec: ChainSpec,
     log: Option<Logger>,
 }
 
-impl<TStore, TSlotClock, TLmdGhost, TEth1Backend, TEthSpec, TEventHandler>
+impl<TStore, TStoreMigrator, TSlotClock, TEth1Backend, TEthSpec, TEventHandler>
     BeaconChainBuilder<
-        Witness<TStore, TSlotClock, TLmdGhost, TEth1Backend, TEthSpec, TEventHandler>,
+        Witness<TStore, TStoreMigrator, TSlotClock, TEth1Backend, TEthSpec, TEventHandler>,
     >
 where
     TStore: Store + 'static,
+    TStoreMigrator: Migrate<TStore, TEthSpec> + 'static,
     TSlotClock: SlotClock + 'static,
-    TLmdGhost: LmdGhost<TStore, TEthSpec> + 'static,
     TEth1Backend: Eth1ChainBackend<TEthSpec> + 'static,
     TEthSpec: EthSpec + 'static,
     TEventHandler: EventHandler<TEthSpec> + 'static,
@@ -90,13 +116,21 @@ where
     pub fn new(_eth_spec_instance: TEthSpec) -> Self {
         Self {
             store: None,
+            store_migrator: None,
             finalized_checkpoint: None,
             genesis_block_root: None,
+            genesis_validators_root: None,
             op_pool: None,
             fork_choice: None,
             eth1_chain: None,
+            persisted_eth1: None,
             event_handler: None,
             slot_clock: None,
+            head_tracker: None,
+            data_dir: None,
+            validator_pubkey_cache: None,
+            weak_subjectivity_checkpoint: None,
+            resource_budget: ResourceBudget::default(),
             spec: TEthSpec::default_spec(),
             log: None,
         }
@@ -119,6 +153,39 @@ where
         self
     }
 
+    /// Sets the store migrator.
+    pub fn store_migrator(mut self, store_migrator: TStoreMigrator) -> Self {
+        self.store_migrator = Some(store_migrator);
+        self
+    }
+
+    /// Sets the directory in which the validator pubkey cache is persisted.
+    ///
+    /// If this is not called, the cache is kept in memory and must be rebuilt each time the
+    /// chain is built.
+    pub fn data_dir(mut self, path: PathBuf) -> Self {
+        self.data_dir = Some(path);
+        self
+    }
+
+    /// Sets a checkpoint which must be included in the canonical chain once it finalizes the epoch
+    /// of the checkpoint.
+    ///
+    /// Protects a node which syncs from a distant (or untrusted) state from being fed a chain which
+    /// conflicts with the checkpoint.
+    pub fn weak_subjectivity_checkpoint(mut self, checkpoint: Checkpoint) -> Self {
+        self.weak_subjectivity_checkpoint = Some(checkpoint);
+        self
+    }
+
+    /// Sets the resource budget shared with the background services of the node.
+    ///
+    /// Defaults to an unthrottled budget.
+    pub fn resource_budget(mut self, resource_budget: ResourceBudget) -> Self {
+        self.resource_budget = resource_budget;
+        self
+    }
+
     /// Sets the logger.
     ///
     /// Should generally be called early in the build chain.
@@ -147,10 +214,17 @@ where
             .clone()
             .ok_or_else(|| "load_from_store requires a store.".to_string())?;
 
+        // Load the persisted public keys before decoding the head state, so that its keys are not
+        // decompressed again.
+        if let Some(data_dir) = self.data_dir.as_ref() {
+            self.validator_pubkey_cache = Some(
+                ValidatorPubkeyCache::open(data_dir.join(PUBKEY_CACHE_FILENAME))
+                    .map_err(|e| format!("Unable to open validator pubkey cache: {:?}", e))?,
+            );
+        }
+
         let key = Hash256::from_slice(&BEACON_CHAIN_DB_KEY.as_bytes());
-        let p: PersistedBeaconChain<
-            Witness<TStore, TSlotClock, TLmdGhost, TEth1Backend, TEthSpec, TEventHandler>,
-        > = match store.get(&key) {
+        let p: PersistedBeaconChain<TEthSpec> = match store.get(&key) {
             Err(e) => {
                 return Err(format!(
                     "DB error when reading persisted beacon chain: {:?}",
@@ -166,8 +240,47 @@ where
                 .into_operation_pool(&p.canonical_head.beacon_state, &self.spec),
         );
 
+        let genesis_validators_root_key =
+            Hash256::from_slice(&GENESIS_VALIDATORS_ROOT_DB_KEY.as_bytes());
+        let genesis_validators_root = match store
+            .get::<GenesisValidatorsRoot>(&genesis_validators_root_key)
+            .map_err(|e| format!("DB error when reading genesis validators root: {:?}", e))?
+        {
+            Some(GenesisValidatorsRoot(root)) => root,
+            // Databases created prior to the genesis validators root being stored must compute
+            // it from the genesis state.
+            None => {
+                let genesis_block = store
+                    .get::<BeaconBlock<TEthSpec>>(&p.genesis_block_root)
+                    .map_err(|e| format!("DB error when reading genesis block: {:?}", e))?
+                    .ok_or_else(|| "Genesis block not found in store".to_string())?;
+                let genesis_state = store
+                    .get_state::<TEthSpec>(&genesis_block.state_root, Some(genesis_block.slot))
+                    .map_err(|e| format!("DB error when reading genesis state: {:?}", e))?
+                    .ok_or_else(|| "Genesis state not found in store".to_string())?;
+                let root = Hash256::from_slice(&genesis_state.validators.tree_hash_root());
+
+                store
+                    .put(&genesis_validators_root_key, &GenesisValidatorsRoot(root))
+                    .map_err(|e| format!("Failed to store genesis validators root: {:?}", e))?;
+
+                root
+            }
+        };
+
+        self.fork_choice = Some(
+            ForkChoice::from_ssz_container(p.ssz_fork_choice)
+                .map_err(|e| format!("Unable to decode fork choice from db: {:?}", e))?,
+        );
+
+        self.persisted_eth1 = p.ssz_eth1;
         self.finalized_checkpoint = Some(p.canonical_head);
         self.genesis_block_root = Some(p.genesis_block_root);
+        self.genesis_validators_root = Some(genesis_validators_root);
+        self.head_tracker = Some(
+            HeadTracker::from_ssz_container(&p.ssz_head_tracker)
+                .map_err(|e| format!("Failed to decode head tracker for database: {:?}", e))?,
+        );
 
         Ok(self)
     }
@@ -192,22 +305,26 @@ where
         beacon_block.state_root = beacon_state_root;
         let beacon_block_root = beacon_block.canonical_root();
 
+        let genesis_validators_root =
+            Hash256::from_slice(&beacon_state.validators.tree_hash_root());
+
         self.genesis_block_root = Some(beacon_block_root);
+        self.genesis_validators_root = Some(genesis_validators_root);
 
+        // Store the genesis state and block, along with the genesis block under the `ZERO_HASH`
+        // key.
         store
-            .put(&beacon_state_root, &beacon_state)
-            .map_err(|e| format!("Failed to store genesis state: {:?}", e))?;
-        store
-            .put(&beacon_block_root, &beacon_block)
-            .map_err(|e| format!("Failed to store genesis block: {:?}", e))?;
-
-        // Store the genesis block under the `ZERO_HASH` key.
-        store.put(&Hash256::zero(), &beacon_block).map_err(|e| {
-            format!(
-                "Failed to store genesis block under 0x00..00 alias: {:?}",
-                e
-            )
-        })?;
+            .do_atomically(vec![
+                StoreOp::PutState(beacon_state_root, &beacon_state),
+                StoreOp::PutBlock(beacon_block_root, &beacon_block),
+                StoreOp::PutBlock(Hash256::zero(), &beacon_block),
+                StoreOp::KeyValueOp(
+                    GenesisValidatorsRoot(genesis_validators_root).as_kv_store_op(
+                        &Hash256::from_slice(&GENESIS_VALIDATORS_ROOT_DB_KEY.as_bytes()),
+                    ),
+                ),
+            ])
+            .map_err(|e| format!("Failed to store genesis block and state: {:?}", e))?;
 
         self.finalized_checkpoint = Some(CheckPoint {
             beacon_block_root,
@@ -219,28 +336,6 @@ where
         Ok(self.empty_op_pool())
     }
 
-    /// Sets the `BeaconChain` fork choice backend.
-    ///
-    /// Requires the store and state to have been specified earlier in the build chain.
-    pub fn fork_choice_backend(mut self, backend: TLmdGhost) -> Result<Self, String> {
-        let store = self
-            .store
-            .clone()
-            .ok_or_else(|| "reduced_tree_fork_choice requires a store")?;
-        let genesis_block_root = self
-            .genesis_block_root
-            .ok_or_else(|| "fork_choice_backend requires a genesis_block_root")?;
-
-        self.fork_choice = Some(ForkChoice::new(
-            store,
-            backend,
-            genesis_block_root,
-            self.spec.genesis_slot,
-        ));
-
-        Ok(self)
-    }
-
     /// Sets the `BeaconChain` eth1 backend.
     pub fn eth1_backend(mut self, backend: Option<TEth1Backend>) -> Self {
         self.eth1_chain = backend.map(Eth1Chain::new);
@@ -279,7 +374,9 @@ where
     pub fn build(
         self,
     ) -> Result<
-        BeaconChain<Witness<TStore, TSlotClock, TLmdGhost, TEth1Backend, TEthSpec, TEventHandler>>,
+        BeaconChain<
+            Witness<TStore, TStoreMigrator, TSlotClock, TEth1Backend, TEthSpec, TEventHandler>,
+        >,
         String,
     > {
         let mut canonical_head = self
@@ -299,11 +396,57 @@ where
             return Err("beacon_block.state_root != beacon_state".to_string());
         }
 
+        let pubkey_cache = if let Some(mut cache) = self.validator_pubkey_cache {
+            cache
+                .sync_with_state(&canonical_head.beacon_state)
+                .map(|()| cache)
+        } else if let Some(data_dir) = self.data_dir.as_ref() {
+            ValidatorPubkeyCache::load_from_file(
+                data_dir.join(PUBKEY_CACHE_FILENAME),
+                &canonical_head.beacon_state,
+            )
+        } else {
+            let mut cache = ValidatorPubkeyCache::in_memory();
+            cache
+                .import_new_pubkeys(&canonical_head.beacon_state)
+                .map(|()| cache)
+        }
+        .map_err(|e| format!("Unable to init validator pubkey cache: {:?}", e))?;
+
+        // If a fork choice was not restored from the database, start with an empty one rooted at
+        // the finalized checkpoint.
+        let fork_choice = if let Some(fork_choice) = self.fork_choice {
+            fork_choice
+        } else {
+            ForkChoice::from_finalized_block(
+                &canonical_head.beacon_block,
+                canonical_head.beacon_block_root,
+            )
+            .map_err(|e| format!("Unable to init fork choice: {:?}", e))?
+        };
+
+        // A stale or undecodable eth1 data cache is not fatal, it will be rebuilt from the eth1
+        // block cache the next time a block is produced.
+        if let (Some(eth1_chain), Some(persisted_eth1)) =
+            (self.eth1_chain.as_ref(), self.persisted_eth1.as_ref())
+        {
+            if let Err(e) = eth1_chain.restore_from_ssz_container(persisted_eth1) {
+                warn!(
+                    log,
+                    "Unable to restore eth1 cache";
+                    "error" => e
+                );
+            }
+        }
+
         let beacon_chain = BeaconChain {
             spec: self.spec,
             store: self
                 .store
                 .ok_or_else(|| "Cannot build without store".to_string())?,
+            store_migrator: self
+                .store_migrator
+                .ok_or_else(|| "Cannot build without store migrator".to_string())?,
             slot_clock: self
                 .slot_clock
                 .ok_or_else(|| "Cannot build without slot clock".to_string())?,
@@ -315,15 +458,40 @@ where
             genesis_block_root: self
                 .genesis_block_root
                 .ok_or_else(|| "Cannot build without a genesis block root".to_string())?,
-            fork_choice: self
-                .fork_choice
-                .ok_or_else(|| "Cannot build without a fork choice".to_string())?,
+            genesis_validators_root: self
+                .genesis_validators_root
+                .ok_or_else(|| "Cannot build without a genesis validators root".to_string())?,
+            fork_choice,
             event_handler: self
                 .event_handler
                 .ok_or_else(|| "Cannot build without an event handler".to_string())?,
+            head_tracker: Arc::new(self.head_tracker.unwrap_or_default()),
+            validator_pubkey_cache: RwLock::new(pubkey_cache),
+            observed_votes: ObservedVotes::default(),
+            observed_aggregators: ObservedAggregators::default(),
+            observed_block_producers: ObservedBlockProducers::default(),
+            chain_quality_cache: ChainQualityCache::default(),
+            shuffling_cache: Mutex::new(ShufflingCache::new()),
+            weak_subjectivity_checkpoint: self.weak_subjectivity_checkpoint,
+            resource_budget: self.resource_budget,
             log: log.clone(),
         };
 
+        // Refuse to start upon a chain which has already finalized a conflicting block.
+        if let Some(checkpoint) = beacon_chain.weak_subjectivity_checkpoint.as_ref() {
+            if let Some(local_root) = beacon_chain
+                .weak_subjectivity_conflict(&beacon_chain.head().beacon_state)
+                .map_err(|e| format!("Unable to verify weak subjectivity checkpoint: {:?}", e))?
+            {
+                return Err(format!(
+                    "The finalized chain conflicts with the weak subjectivity checkpoint. \
+                     Expected block {:?} at epoch {} but found {:?}. The database must be removed \
+                     and the node re-synced.",
+                    checkpoint.root, checkpoint.epoch, local_root
+                ));
+            }
+        }
+
         info!(
             log,
             "Beacon chain initialized";
@@ -336,54 +504,12 @@ where
     }
 }
 
-impl<TStore, TSlotClock, TEth1Backend, TEthSpec, TEventHandler>
-    BeaconChainBuilder<
-        Witness<
-            TStore,
-            TSlotClock,
-            ThreadSafeReducedTree<TStore, TEthSpec>,
-            TEth1Backend,
-            TEthSpec,
-            TEventHandler,
-        >,
-    >
-where
-    TStore: Store + 'static,
-    TSlotClock: SlotClock + 'static,
-    TEth1Backend: Eth1ChainBackend<TEthSpec> + 'static,
-    TEthSpec: EthSpec + 'static,
-    TEventHandler: EventHandler<TEthSpec> + 'static,
-{
-    /// Initializes a new, empty (no recorded votes or blocks) fork choice, using the
-    /// `ThreadSafeReducedTree` backend.
-    ///
-    /// Requires the store and state to be initialized.
-    pub fn empty_reduced_tree_fork_choice(self) -> Result<Self, String> {
-        let store = self
-            .store
-            .clone()
-            .ok_or_else(|| "reduced_tree_fork_choice requires a store")?;
-        let finalized_checkpoint = &self
-            .finalized_checkpoint
-            .as_ref()
-            .expect("should have finalized checkpoint");
-
-        let backend = ThreadSafeReducedTree::new(
-            store.clone(),
-            &finalized_checkpoint.beacon_block,
-            finalized_checkpoint.beacon_block_root,
-        );
-
-        self.fork_choice_backend(backend)
-    }
-}
-
-impl<TStore, TSlotClock, TLmdGhost, TEthSpec, TEventHandler>
+impl<TStore, TStoreMigrator, TSlotClock, TEthSpec, TEventHandler>
     BeaconChainBuilder<
         Witness<
             TStore,
+            TStoreMigrator,
             TSlotClock,
-            TLmdGhost,
             CachingEth1Backend<TEthSpec, TStore>,
             TEthSpec,
             TEventHandler,
@@ -391,8 +517,8 @@ impl<TStore, TSlotClock, TLmdGhost, TEthSpec, TEventHandler>
     >
 where
     TStore: Store + 'static,
+    TStoreMigrator: Migrate<TStore, TEthSpec> + 'static,
     TSlotClock: SlotClock + 'static,
-    TLmdGhost: LmdGhost<TStore, TEthSpec> + 'static,
     TEthSpec: EthSpec + 'static,
     TEventHandler: EventHandler<TEthSpec> + 'static,
 {
@@ -417,7 +543,8 @@ where
             .clone()
             .ok_or_else(|| "dummy_eth1_backend requires a store.".to_string())?;
 
-        let backend = CachingEth1Backend::new(Eth1Config::default(), log.clone(), store);
+        let backend =
+            CachingEth1Backend::new(Eth1Config::default(), log.clone(), store, self.spec.clone());
 
         let mut eth1_chain = Eth1Chain::new(backend);
         eth1_chain.use_dummy_backend = true;
@@ -428,13 +555,13 @@ where
     }
 }
 
-impl<TStore, TLmdGhost, TEth1Backend, TEthSpec, TEventHandler>
+impl<TStore, TStoreMigrator, TEth1Backend, TEthSpec, TEventHandler>
     BeaconChainBuilder<
-        Witness<TStore, TestingSlotClock, TLmdGhost, TEth1Backend, TEthSpec, TEventHandler>,
+        Witness<TStore, TStoreMigrator, TestingSlotClock, TEth1Backend, TEthSpec, TEventHandler>,
     >
 where
     TStore: Store + 'static,
-    TLmdGhost: LmdGhost<TStore, TEthSpec> + 'static,
+    TStoreMigrator: Migrate<TStore, TEthSpec> + 'static,
     TEth1Backend: Eth1ChainBackend<TEthSpec> + 'static,
     TEthSpec: EthSpec + 'static,
     TEventHandler: EventHandler<TEthSpec> + 'static,
@@ -460,14 +587,21 @@ where
     }
 }
 
-impl<TStore, TSlotClock, TLmdGhost, TEth1Backend, TEthSpec>
+impl<TStore, TStoreMigrator, TSlotClock, TEth1Backend, TEthSpec>
     BeaconChainBuilder<
-        Witness<TStore, TSlotClock, TLmdGhost, TEth1Backend, TEthSpec, NullEventHandler<TEthSpec>>,
+        Witness<
+            TStore,
+            TStoreMigrator,
+            TSlotClock,
+            TEth1Backend,
+            TEthSpec,
+            NullEventHandler<TEthSpec>,
+        >,
     >
 where
     TStore: Store + 'static,
+    TStoreMigrator: Migrate<TStore, TEthSpec> + 'static,
     TSlotClock: SlotClock + 'static,
-    TLmdGhost: LmdGhost<TStore, TEthSpec> + 'static,
     TEth1Backend: Eth1ChainBackend<TEthSpec> + 'static,
     TEthSpec: EthSpec + 'static,
 {
@@ -489,6 +623,7 @@ fn genesis_block<T: EthSpec>(genesis_state: &BeaconState<T>, spec: &ChainSpec) -
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::migrate::NullMigrator;
     use eth2_hashing::hash;
     use genesis::{generate_deterministic_keypairs, interop_genesis_state};
     use sloggers::{null::NullLoggerBuilder, Build};
@@ -523,6 +658,7 @@ mod test {
         let chain = BeaconChainBuilder::new(MinimalEthSpec)
             .logger(log.clone())
             .store(store.clone())
+            .store_migrator(NullMigrator)
             .genesis_state(genesis_state)
             .expect("should build state using recent genesis")
             .dummy_eth1_backend()
@@ -530,8 +666,6 @@ mod test {
             .null_event_handler()
             .testing_slot_clock(Duration::from_secs(1))
             .expect("should configure testing slot clock")
-            .empty_reduced_tree_fork_choice()
-            .expect("should add fork choice to builder")
             .build()
             .expect("should build");
 
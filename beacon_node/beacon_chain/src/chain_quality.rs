@@ -0,0 +1,158 @@
+use crate::errors::BeaconChainError;
+use crate::validator_inclusion::EpochInclusionSummary;
+use parking_lot::RwLock;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use types::{BeaconState, Epoch, EthSpec};
+
+/// The maximum number of epochs for which chain quality is retained. The lowest epochs are dropped
+/// first.
+pub const MAX_CHAIN_QUALITY_EPOCHS: usize = 64;
+
+/// A summary of the health of the canonical chain during some epoch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainQuality {
+    /// The epoch to which this summary applies.
+    pub epoch: Epoch,
+    /// The fraction of the active balance which voted for the correct target, between `0.0` and
+    /// `1.0`.
+    pub participation_rate: f64,
+    /// The number of slots in `epoch` without a canonical block.
+    pub missed_proposals: u64,
+    /// The mean number of slots taken to include an attestation, or `None` if no attestations were
+    /// included.
+    pub average_inclusion_delay: Option<f64>,
+    /// `true` if `epoch` was justified by the end of the following epoch.
+    pub justified: bool,
+    /// `true` if `epoch` was finalized by the end of the following epoch.
+    pub finalized: bool,
+}
+
+impl ChainQuality {
+    /// Summarises the quality of the chain during `summary.epoch`.
+    ///
+    /// The `state` must be the result of the epoch processing which followed `summary`, i.e., the
+    /// state at the first slot of `summary.epoch + 2`.
+    pub fn from_epoch_processing<T: EthSpec>(
+        summary: &EpochInclusionSummary,
+        state: &BeaconState<T>,
+    ) -> Result<Self, BeaconChainError> {
+        let epoch = summary.epoch;
+        let slots_per_epoch = T::slots_per_epoch();
+
+        let mut missed_proposals = 0;
+        for slot in epoch.slot_iter(slots_per_epoch) {
+            // A slot at which the block root is unchanged from the previous slot was skipped. The
+            // genesis slot always has a block.
+            if slot > 0 && state.get_block_root(slot)? == state.get_block_root(slot - 1)? {
+                missed_proposals += 1;
+            }
+        }
+
+        let participation_rate = if summary.active_gwei == 0 {
+            0.0
+        } else {
+            summary.target_attesting_gwei as f64 / summary.active_gwei as f64
+        };
+
+        let average_inclusion_delay = if summary.included_attesters == 0 {
+            None
+        } else {
+            Some(summary.total_inclusion_delay as f64 / summary.included_attesters as f64)
+        };
+
+        // Following epoch processing, the first justification bit refers to the current epoch and
+        // the second to the previous epoch, which is `epoch`.
+        let justified = state.justification_bits.get(1).unwrap_or(false);
+
+        Ok(Self {
+            epoch,
+            participation_rate,
+            missed_proposals,
+            average_inclusion_delay,
+            justified,
+            finalized: state.finalized_checkpoint.epoch >= epoch,
+        })
+    }
+}
+
+/// The chain quality of recent epochs, populated as the canonical chain processes each epoch.
+#[derive(Default)]
+pub struct ChainQualityCache {
+    epochs: RwLock<BTreeMap<Epoch, ChainQuality>>,
+}
+
+impl ChainQualityCache {
+    /// Caches the chain quality of `quality.epoch`, replacing any existing entry and dropping the
+    /// entry of the lowest epoch if the cache is full.
+    pub fn insert(&self, quality: ChainQuality) {
+        let mut epochs = self.epochs.write();
+        epochs.insert(quality.epoch, quality);
+
+        while epochs.len() > MAX_CHAIN_QUALITY_EPOCHS {
+            if let Some(lowest) = epochs.keys().next().copied() {
+                epochs.remove(&lowest);
+            }
+        }
+    }
+
+    /// Returns the chain quality of the most recent `count` epochs, in ascending epoch order.
+    pub fn latest(&self, count: usize) -> Vec<ChainQuality> {
+        let epochs = self.epochs.read();
+        let skip = epochs.len().saturating_sub(count);
+
+        epochs.values().skip(skip).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn quality(epoch: u64) -> ChainQuality {
+        ChainQuality {
+            epoch: Epoch::new(epoch),
+            participation_rate: 1.0,
+            missed_proposals: 0,
+            average_inclusion_delay: Some(1.0),
+            justified: true,
+            finalized: false,
+        }
+    }
+
+    #[test]
+    fn retains_latest_epochs() {
+        let cache = ChainQualityCache::default();
+
+        for epoch in 0..MAX_CHAIN_QUALITY_EPOCHS as u64 + 2 {
+            cache.insert(quality(epoch));
+        }
+
+        let all = cache.latest(usize::max_value());
+        assert_eq!(all.len(), MAX_CHAIN_QUALITY_EPOCHS);
+        assert_eq!(all[0].epoch, Epoch::new(2));
+
+        let latest = cache.latest(2);
+        assert_eq!(
+            latest.iter().map(|q| q.epoch.as_u64()).collect::<Vec<_>>(),
+            vec![
+                MAX_CHAIN_QUALITY_EPOCHS as u64,
+                MAX_CHAIN_QUALITY_EPOCHS as u64 + 1
+            ]
+        );
+    }
+
+    #[test]
+    fn replaces_reorged_epochs() {
+        let cache = ChainQualityCache::default();
+
+        cache.insert(quality(1));
+        cache.insert(ChainQuality {
+            missed_proposals: 3,
+            ..quality(1)
+        });
+
+        assert_eq!(cache.latest(10).len(), 1);
+        assert_eq!(cache.latest(10)[0].missed_proposals, 3);
+    }
+}
@@ -46,6 +46,16 @@ pub enum BeaconChainError {
     /// Returned when an internal check fails, indicating corrupt data.
     InvariantViolated(String),
     SszTypesError(SszTypesError),
+    ValidatorPubkeyCacheError(String),
+    DuplicateValidatorPublicKey,
+    /// The attestation inclusion for `epoch` is not yet known, since the head has not progressed
+    /// past `epoch`.
+    EpochInclusionUnavailable {
+        epoch: Epoch,
+        head_epoch: Epoch,
+    },
+    /// The block root at the start of the weak subjectivity checkpoint epoch could not be read.
+    WeakSubjectivityRootUnavailable(Slot),
 }
 
 easy_from_to!(SlotProcessingError, BeaconChainError);
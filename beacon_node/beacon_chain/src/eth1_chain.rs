@@ -1,19 +1,23 @@
-use eth1::{Config as Eth1Config, Eth1Block, Service as HttpService};
+use eth1::{Config as Eth1Config, DepositLog, Eth1Block, Service as HttpService};
 use eth2_hashing::hash;
 use exit_future::Exit;
 use futures::Future;
 use integer_sqrt::IntegerSquareRoot;
+use parking_lot::RwLock;
 use rand::prelude::*;
 use slog::{crit, Logger};
+use ssz::{Decode, Encode};
+use ssz_derive::{Decode, Encode};
 use std::collections::HashMap;
 use std::iter::DoubleEndedIterator;
 use std::iter::FromIterator;
 use std::marker::PhantomData;
+use std::ops::Range;
 use std::sync::Arc;
 use store::{Error as StoreError, Store};
 use types::{
-    BeaconState, BeaconStateError, ChainSpec, Deposit, Eth1Data, EthSpec, Hash256, Slot, Unsigned,
-    DEPOSIT_TREE_DEPTH,
+    BeaconState, BeaconStateError, ChainSpec, Deposit, Eth1Data, EthSpec, Hash256, PublicKeyBytes,
+    Slot, Unsigned, DEPOSIT_TREE_DEPTH,
 };
 
 type BlockNumber = u64;
@@ -29,6 +33,11 @@ pub enum Error {
     /// The deposit index of the state is higher than the deposit contract. This is a critical
     /// consensus error.
     DepositIndexTooHigh,
+    /// The block must include the `count` deposits starting at index `start`, but the backend is
+    /// unable to supply them.
+    ///
+    /// The deposit cache is likely behind the eth1 chain.
+    DepositsUnavailable { start: u64, count: u64 },
     /// The current state was unable to return the root for the state at the start of the eth1
     /// voting period.
     UnableToGetPreviousStateRoot(BeaconStateError),
@@ -46,6 +55,12 @@ pub enum Error {
     UnknownPreviousEth1BlockHash,
 }
 
+/// Contains the cached state of an `Eth1Chain`, used to restore it after a restart.
+#[derive(Encode, Decode, Clone)]
+pub struct SszEth1 {
+    backend_bytes: Vec<u8>,
+}
+
 /// Holds an `Eth1ChainBackend` and serves requests from the `BeaconChain`.
 pub struct Eth1Chain<T, E>
 where
@@ -86,19 +101,65 @@ where
         }
     }
 
-    /// Returns a list of `Deposits` that may be included in a block.
+    /// Returns the `Deposits` that must be included in a block with the `eth1_data_vote`, produced
+    /// upon `state`.
     ///
-    /// Including all of the returned `Deposits` in a block should _not_ cause it to become
-    /// invalid.
+    /// Exactly the deposits required by `required_deposit_range` are returned, or an error if the
+    /// backend is unable to supply them all.
     pub fn deposits_for_block_inclusion(
         &self,
         state: &BeaconState<E>,
+        eth1_data_vote: &Eth1Data,
         spec: &ChainSpec,
     ) -> Result<Vec<Deposit>, Error> {
         if self.use_dummy_backend {
-            DummyEth1ChainBackend::default().queued_deposits(state, spec)
+            DummyEth1ChainBackend::default().queued_deposits(state, eth1_data_vote, spec)
+        } else {
+            self.backend.queued_deposits(state, eth1_data_vote, spec)
+        }
+    }
+
+    /// Returns a container which may be used to restore the cached state of `self` after a
+    /// restart.
+    pub fn as_ssz_container(&self) -> SszEth1 {
+        SszEth1 {
+            backend_bytes: self.backend.as_bytes(),
+        }
+    }
+
+    /// Restores the cached state of the backend from a container produced by
+    /// `Self::as_ssz_container`.
+    pub fn restore_from_ssz_container(&self, container: &SszEth1) -> Result<(), String> {
+        self.backend.restore_from_bytes(&container.backend_bytes)
+    }
+
+    /// Returns the logs of the deposits to the validator with `pubkey` that are known to the
+    /// backend, in order of deposit index.
+    pub fn deposits_for_pubkey(&self, pubkey: &PublicKeyBytes) -> Result<Vec<DepositLog>, String> {
+        if self.use_dummy_backend {
+            DummyEth1ChainBackend::<E>::default().deposits_for_pubkey(pubkey)
+        } else {
+            self.backend.deposits_for_pubkey(pubkey)
+        }
+    }
+
+    /// Encodes the deposit cache of the backend as SSZ, so that it may be imported by another
+    /// node with `Self::import_deposit_cache`.
+    pub fn deposit_cache_as_ssz_bytes(&self) -> Result<Vec<u8>, String> {
+        if self.use_dummy_backend {
+            DummyEth1ChainBackend::<E>::default().deposit_cache_as_ssz_bytes()
+        } else {
+            self.backend.deposit_cache_as_ssz_bytes()
+        }
+    }
+
+    /// Imports a deposit cache encoded by `Self::deposit_cache_as_ssz_bytes` into the backend,
+    /// returning the number of deposits that were not already known.
+    pub fn import_deposit_cache(&self, bytes: &[u8]) -> Result<usize, String> {
+        if self.use_dummy_backend {
+            DummyEth1ChainBackend::<E>::default().import_deposit_cache(bytes)
         } else {
-            self.backend.queued_deposits(state, spec)
+            self.backend.import_deposit_cache(bytes)
         }
     }
 }
@@ -109,18 +170,34 @@ pub trait Eth1ChainBackend<T: EthSpec>: Sized + Send + Sync {
     fn eth1_data(&self, beacon_state: &BeaconState<T>, spec: &ChainSpec)
         -> Result<Eth1Data, Error>;
 
-    /// Returns all `Deposits` between `state.eth1_deposit_index` and
-    /// `state.eth1_data.deposit_count`.
+    /// Returns the `Deposits` in the `required_deposit_range` of a block with the
+    /// `eth1_data_vote`, produced upon `beacon_state`.
     ///
-    /// # Note:
-    ///
-    /// It is possible that not all returned `Deposits` can be included in a block. E.g., there may
-    /// be more than `MAX_DEPOSIT_COUNT` or the churn may be too high.
+    /// Returns `Error::DepositsUnavailable` unless every required deposit can be returned.
     fn queued_deposits(
         &self,
         beacon_state: &BeaconState<T>,
+        eth1_data_vote: &Eth1Data,
         spec: &ChainSpec,
     ) -> Result<Vec<Deposit>, Error>;
+
+    /// Encodes any cached state of `self` so that it may be restored with
+    /// `Self::restore_from_bytes` after a restart.
+    fn as_bytes(&self) -> Vec<u8>;
+
+    /// Restores the cached state of `self` from bytes produced by `Self::as_bytes`.
+    fn restore_from_bytes(&self, bytes: &[u8]) -> Result<(), String>;
+
+    /// Returns the logs of the deposits to the validator with `pubkey`, in order of deposit index.
+    fn deposits_for_pubkey(&self, pubkey: &PublicKeyBytes) -> Result<Vec<DepositLog>, String>;
+
+    /// Encodes the deposit cache of `self` as SSZ, so that another node may import it rather than
+    /// downloading the deposit logs from an eth1 node.
+    fn deposit_cache_as_ssz_bytes(&self) -> Result<Vec<u8>, String>;
+
+    /// Imports a deposit cache encoded by `Self::deposit_cache_as_ssz_bytes`, returning the number
+    /// of deposits that were not already known.
+    fn import_deposit_cache(&self, bytes: &[u8]) -> Result<usize, String>;
 }
 
 /// Provides a simple, testing-only backend that generates deterministic, meaningless eth1 data.
@@ -146,8 +223,42 @@ impl<T: EthSpec> Eth1ChainBackend<T> for DummyEth1ChainBackend<T> {
         })
     }
 
-    fn queued_deposits(&self, _: &BeaconState<T>, _: &ChainSpec) -> Result<Vec<Deposit>, Error> {
-        Ok(vec![])
+    fn queued_deposits(
+        &self,
+        state: &BeaconState<T>,
+        eth1_data_vote: &Eth1Data,
+        _spec: &ChainSpec,
+    ) -> Result<Vec<Deposit>, Error> {
+        let (range, _deposit_count) = required_deposit_range(state, eth1_data_vote)?;
+
+        if range.start == range.end {
+            Ok(vec![])
+        } else {
+            Err(Error::DepositsUnavailable {
+                start: range.start,
+                count: range.end - range.start,
+            })
+        }
+    }
+
+    fn as_bytes(&self) -> Vec<u8> {
+        vec![]
+    }
+
+    fn restore_from_bytes(&self, _bytes: &[u8]) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn deposits_for_pubkey(&self, _pubkey: &PublicKeyBytes) -> Result<Vec<DepositLog>, String> {
+        Err("The dummy eth1 backend does not have a deposit cache".to_string())
+    }
+
+    fn deposit_cache_as_ssz_bytes(&self) -> Result<Vec<u8>, String> {
+        Err("The dummy eth1 backend does not have a deposit cache".to_string())
+    }
+
+    fn import_deposit_cache(&self, _bytes: &[u8]) -> Result<usize, String> {
+        Err("The dummy eth1 backend does not have a deposit cache".to_string())
     }
 }
 
@@ -157,6 +268,44 @@ impl<T: EthSpec> Default for DummyEth1ChainBackend<T> {
     }
 }
 
+/// The `Eth1Data` candidates that may be voted upon during a single eth1 voting period.
+///
+/// Finding the candidates requires a scan of the eth1 block cache, so they are found once per
+/// voting period and reused for every block produced in that period. The candidates are only
+/// rescanned if new eth1 blocks arrive whilst the cache is not yet final.
+#[derive(Debug, PartialEq, Clone, Encode, Decode)]
+pub struct Eth1DataCache {
+    /// The first slot of the voting period.
+    pub voting_period_start_slot: Slot,
+    /// The `state.eth1_data.block_hash` at the start of the voting period.
+    pub prev_eth1_hash: Hash256,
+    /// The highest block in the eth1 block cache when the candidates were found.
+    pub highest_block_number: Option<BlockNumber>,
+    /// `true` if the eth1 block cache contained a block from after the start of the voting
+    /// period when the candidates were found. Blocks arriving later cannot change the candidates.
+    pub is_final: bool,
+    /// The `new_eth1_data` set, as returned from `eth1_data_sets`.
+    pub new_eth1_data: Vec<(Eth1Data, BlockNumber)>,
+    /// The `all_eth1_data` set, as returned from `eth1_data_sets`.
+    pub all_eth1_data: Vec<(Eth1Data, BlockNumber)>,
+}
+
+impl Eth1DataCache {
+    /// Returns `true` if `self` holds the candidates for the voting period starting at
+    /// `voting_period_start_slot`, given the eth1 block cache now extends to
+    /// `highest_block_number`.
+    fn is_valid_for(
+        &self,
+        voting_period_start_slot: Slot,
+        prev_eth1_hash: Hash256,
+        highest_block_number: Option<BlockNumber>,
+    ) -> bool {
+        self.voting_period_start_slot == voting_period_start_slot
+            && self.prev_eth1_hash == prev_eth1_hash
+            && (self.is_final || self.highest_block_number == highest_block_number)
+    }
+}
+
 /// Maintains a cache of eth1 blocks and deposits and provides functions to allow block producers
 /// to include new deposits and vote on `Eth1Data`.
 ///
@@ -167,6 +316,7 @@ pub struct CachingEth1Backend<T: EthSpec, S> {
     pub core: HttpService,
     store: Arc<S>,
     log: Logger,
+    eth1_data_cache: Arc<RwLock<Option<Eth1DataCache>>>,
     _phantom: PhantomData<T>,
 }
 
@@ -174,11 +324,12 @@ impl<T: EthSpec, S: Store> CachingEth1Backend<T, S> {
     /// Instantiates `self` with empty caches.
     ///
     /// Does not connect to the eth1 node or start any tasks to keep the cache updated.
-    pub fn new(config: Eth1Config, log: Logger, store: Arc<S>) -> Self {
+    pub fn new(config: Eth1Config, log: Logger, store: Arc<S>, spec: ChainSpec) -> Self {
         Self {
-            core: HttpService::new(config, log.clone()),
+            core: HttpService::new::<T>(config, log.clone(), spec),
             store,
             log,
+            eth1_data_cache: Arc::new(RwLock::new(None)),
             _phantom: PhantomData,
         }
     }
@@ -194,20 +345,79 @@ impl<T: EthSpec, S: Store> CachingEth1Backend<T, S> {
             log: service.log.clone(),
             core: service,
             store,
+            eth1_data_cache: Arc::new(RwLock::new(None)),
             _phantom: PhantomData,
         }
     }
+
+    /// Returns the `Eth1Data` candidates that may be voted upon in the voting period of `state`.
+    ///
+    /// The result is cached and only recomputed when `state` is in a different voting period (or
+    /// on a different fork) to the cache, or when new eth1 blocks have arrived before the cache
+    /// was final. Returns `None` if the eth1 cache does not yet contain `prev_eth1_hash`, in which
+    /// case nothing is cached.
+    fn eth1_data_cache(
+        &self,
+        state: &BeaconState<T>,
+        prev_eth1_hash: Hash256,
+        spec: &ChainSpec,
+    ) -> Option<Eth1DataCache> {
+        let period = T::SlotsPerEth1VotingPeriod::to_u64();
+        let voting_period_start_slot = (state.slot / period) * period;
+
+        let blocks = self.core.blocks().read();
+        let highest_block_number = blocks.highest_block_number();
+
+        if let Some(cache) = self.eth1_data_cache.read().as_ref() {
+            if cache.is_valid_for(
+                voting_period_start_slot,
+                prev_eth1_hash,
+                highest_block_number,
+            ) {
+                return Some(cache.clone());
+            }
+        }
+
+        let (new_eth1_data, all_eth1_data) =
+            eth1_data_sets(blocks.iter(), state, prev_eth1_hash, spec)?;
+
+        let voting_period_start_seconds = slot_start_seconds::<T>(
+            state.genesis_time,
+            spec.milliseconds_per_slot,
+            voting_period_start_slot,
+        );
+        let is_final = blocks
+            .iter()
+            .next_back()
+            .map_or(false, |block| block.timestamp > voting_period_start_seconds);
+
+        let cache = Eth1DataCache {
+            voting_period_start_slot,
+            prev_eth1_hash,
+            highest_block_number,
+            is_final,
+            new_eth1_data: new_eth1_data.into_iter().collect(),
+            all_eth1_data: all_eth1_data.into_iter().collect(),
+        };
+
+        *self.eth1_data_cache.write() = Some(cache.clone());
+
+        Some(cache)
+    }
 }
 
 impl<T: EthSpec, S: Store> Eth1ChainBackend<T> for CachingEth1Backend<T, S> {
     fn eth1_data(&self, state: &BeaconState<T>, spec: &ChainSpec) -> Result<Eth1Data, Error> {
         let prev_eth1_hash = eth1_block_hash_at_start_of_voting_period(self.store.clone(), state)?;
 
-        let blocks = self.core.blocks().read();
-
-        let eth1_data = eth1_data_sets(blocks.iter(), state, prev_eth1_hash, spec)
-            .map(|(new_eth1_data, all_eth1_data)| {
-                collect_valid_votes(state, new_eth1_data, all_eth1_data)
+        let eth1_data = self
+            .eth1_data_cache(state, prev_eth1_hash, spec)
+            .map(|cache| {
+                collect_valid_votes(
+                    state,
+                    HashMap::from_iter(cache.new_eth1_data),
+                    HashMap::from_iter(cache.all_eth1_data),
+                )
             })
             .and_then(find_winning_vote)
             .unwrap_or_else(|| {
@@ -226,28 +436,104 @@ impl<T: EthSpec, S: Store> Eth1ChainBackend<T> for CachingEth1Backend<T, S> {
     fn queued_deposits(
         &self,
         state: &BeaconState<T>,
+        eth1_data_vote: &Eth1Data,
         _spec: &ChainSpec,
     ) -> Result<Vec<Deposit>, Error> {
-        let deposit_count = state.eth1_data.deposit_count;
-        let deposit_index = state.eth1_deposit_index;
+        let (range, deposit_count) = required_deposit_range(state, eth1_data_vote)?;
 
-        if deposit_index > deposit_count {
-            Err(Error::DepositIndexTooHigh)
-        } else if deposit_index == deposit_count {
-            Ok(vec![])
-        } else {
-            let next = deposit_index;
-            let last = std::cmp::min(deposit_count, next + T::MaxDeposits::to_u64());
+        if range.start == range.end {
+            return Ok(vec![]);
+        }
 
-            self.core
-                .deposits()
-                .read()
-                .cache
-                .get_deposits(next..last, deposit_count, DEPOSIT_TREE_DEPTH)
-                .map_err(|e| Error::BackendError(format!("Failed to get deposits: {:?}", e)))
-                .map(|(_deposit_root, deposits)| deposits)
+        let unavailable = || Error::DepositsUnavailable {
+            start: range.start,
+            count: range.end - range.start,
+        };
+
+        let deposits = self.core.deposits().read();
+        let deposit_cache = &deposits.cache;
+
+        // Proofs are made against the tree of the first `deposit_count` deposits, so all of them
+        // must be known, not only those in `range`.
+        if (deposit_cache.len() as u64) < deposit_count {
+            return Err(unavailable());
+        }
+
+        let required = deposit_cache
+            .get_deposits(range.clone(), deposit_count, DEPOSIT_TREE_DEPTH)
+            .map_err(|e| Error::BackendError(format!("Failed to get deposits: {:?}", e)))
+            .map(|(_deposit_root, deposits)| deposits)?;
+
+        if required.len() as u64 == range.end - range.start {
+            Ok(required)
+        } else {
+            Err(unavailable())
         }
     }
+
+    /// Only a final cache is persisted, since the candidates of a cache which is not yet final
+    /// may be changed by eth1 blocks which arrive whilst the node is offline.
+    fn as_bytes(&self) -> Vec<u8> {
+        self.eth1_data_cache
+            .read()
+            .as_ref()
+            .filter(|cache| cache.is_final)
+            .cloned()
+            .as_ssz_bytes()
+    }
+
+    fn restore_from_bytes(&self, bytes: &[u8]) -> Result<(), String> {
+        let cache = Option::<Eth1DataCache>::from_ssz_bytes(bytes)
+            .map_err(|e| format!("Unable to decode eth1 data cache: {:?}", e))?;
+
+        *self.eth1_data_cache.write() = cache;
+
+        Ok(())
+    }
+
+    fn deposits_for_pubkey(&self, pubkey: &PublicKeyBytes) -> Result<Vec<DepositLog>, String> {
+        Ok(self.core.deposits_for_pubkey(pubkey))
+    }
+
+    fn deposit_cache_as_ssz_bytes(&self) -> Result<Vec<u8>, String> {
+        Ok(self.core.deposit_cache_as_ssz_bytes())
+    }
+
+    fn import_deposit_cache(&self, bytes: &[u8]) -> Result<usize, String> {
+        self.core.import_deposit_cache(bytes)
+    }
+}
+
+/// Returns the range of the indices of the deposits that a block with the `eth1_data_vote` must
+/// include when applied to `state`, along with the deposit count of the deposit tree that the
+/// deposits are proven against.
+///
+/// The vote is counted first, since a vote which reaches a majority updates `state.eth1_data`
+/// before the deposits of the block are processed.
+pub fn required_deposit_range<T: EthSpec>(
+    state: &BeaconState<T>,
+    eth1_data_vote: &Eth1Data,
+) -> Result<(Range<u64>, u64), Error> {
+    let num_votes = state
+        .eth1_data_votes
+        .iter()
+        .filter(|vote| *vote == eth1_data_vote)
+        .count()
+        + 1;
+
+    let deposit_count = if num_votes * 2 > T::SlotsPerEth1VotingPeriod::to_usize() {
+        eth1_data_vote.deposit_count
+    } else {
+        state.eth1_data.deposit_count
+    };
+    let deposit_index = state.eth1_deposit_index;
+
+    if deposit_index > deposit_count {
+        Err(Error::DepositIndexTooHigh)
+    } else {
+        let last = std::cmp::min(deposit_count, deposit_index + T::MaxDeposits::to_u64());
+        Ok((deposit_index..last, deposit_count))
+    }
 }
 
 /// Produces an `Eth1Data` with all fields sourced from `rand::thread_rng()`.
@@ -293,7 +579,7 @@ fn eth1_block_hash_at_start_of_voting_period<T: EthSpec, S: Store>(
             .map_err(|e| Error::UnableToGetPreviousStateRoot(e))?;
 
         store
-            .get::<BeaconState<T>>(&prev_state_root)
+            .get_state::<T>(&prev_state_root, Some(slot))
             .map_err(|e| Error::StoreError(e))?
             .map(|state| state.eth1_data.block_hash)
             .ok_or_else(|| Error::PreviousStateNotInDB)
@@ -480,7 +766,12 @@ mod test {
 
             let log = null_logger().unwrap();
             let store = Arc::new(MemoryStore::open());
-            Eth1Chain::new(CachingEth1Backend::new(eth1_config, log, store))
+            Eth1Chain::new(CachingEth1Backend::new(
+                eth1_config,
+                log,
+                store,
+                E::default_spec(),
+            ))
         }
 
         fn get_deposit_log(i: u64, spec: &ChainSpec) -> DepositLog {
@@ -494,6 +785,7 @@ mod test {
                 deposit_data,
                 block_number: i,
                 index: i,
+                signature_is_valid: true,
             }
         }
 
@@ -514,17 +806,16 @@ mod test {
 
             assert!(
                 eth1_chain
-                    .deposits_for_block_inclusion(&state, spec)
+                    .deposits_for_block_inclusion(&state, &state.eth1_data, spec)
                     .is_ok(),
                 "should succeed if cache is empty but no deposits are required"
             );
 
             state.eth1_data.deposit_count = 1;
 
-            assert!(
-                eth1_chain
-                    .deposits_for_block_inclusion(&state, spec)
-                    .is_err(),
+            assert_eq!(
+                eth1_chain.deposits_for_block_inclusion(&state, &state.eth1_data, spec),
+                Err(Error::DepositsUnavailable { start: 0, count: 1 }),
                 "should fail to get deposits if required, but cache is empty"
             );
         }
@@ -567,7 +858,7 @@ mod test {
 
             assert!(
                 eth1_chain
-                    .deposits_for_block_inclusion(&state, spec)
+                    .deposits_for_block_inclusion(&state, &state.eth1_data, spec)
                     .is_ok(),
                 "should succeed if no deposits are required"
             );
@@ -579,7 +870,7 @@ mod test {
                     state.eth1_data.deposit_count = i as u64;
 
                     let deposits_for_inclusion = eth1_chain
-                        .deposits_for_block_inclusion(&state, spec)
+                        .deposits_for_block_inclusion(&state, &state.eth1_data, spec)
                         .expect(&format!("should find deposit for {}", i));
 
                     let expected_len =
@@ -612,6 +903,86 @@ mod test {
             })
         }
 
+        #[test]
+        fn deposits_for_majority_vote() {
+            let spec = &E::default_spec();
+
+            let eth1_chain = get_eth1_chain();
+            let max_deposits = <E as EthSpec>::MaxDeposits::to_u64();
+
+            for i in 0..max_deposits + 2 {
+                eth1_chain
+                    .backend
+                    .core
+                    .deposits()
+                    .write()
+                    .cache
+                    .insert_log(get_deposit_log(i, spec))
+                    .expect("should insert log");
+            }
+
+            let mut state: BeaconState<E> = BeaconState::new(0, get_eth1_data(0), &spec);
+            state.eth1_deposit_index = 0;
+            state.eth1_data.deposit_count = 0;
+
+            let vote = get_eth1_data(2);
+            let majority = <E as EthSpec>::SlotsPerEth1VotingPeriod::to_usize() / 2;
+
+            for _ in 0..majority - 1 {
+                state
+                    .eth1_data_votes
+                    .push(vote.clone())
+                    .expect("should push vote");
+            }
+            assert_eq!(
+                required_deposit_range(&state, &vote),
+                Ok((0..0, 0)),
+                "a vote short of a majority should not require deposits"
+            );
+
+            state
+                .eth1_data_votes
+                .push(vote.clone())
+                .expect("should push vote");
+            assert_eq!(
+                required_deposit_range(&state, &vote),
+                Ok((0..2, 2)),
+                "a vote which reaches a majority should require its deposits"
+            );
+            assert_eq!(
+                eth1_chain
+                    .deposits_for_block_inclusion(&state, &vote, spec)
+                    .expect("should get deposits")
+                    .len(),
+                2,
+                "should return the deposits of the majority vote"
+            );
+
+            let vote = get_eth1_data(max_deposits + 4);
+            for _ in 0..majority {
+                state
+                    .eth1_data_votes
+                    .push(vote.clone())
+                    .expect("should push vote");
+            }
+            assert_eq!(
+                eth1_chain.deposits_for_block_inclusion(&state, &vote, spec),
+                Err(Error::DepositsUnavailable {
+                    start: 0,
+                    count: max_deposits
+                }),
+                "should fail if the cache does not contain the full deposit tree"
+            );
+
+            state.eth1_deposit_index = 3;
+            state.eth1_data.deposit_count = 2;
+            assert_eq!(
+                required_deposit_range(&state, &state.eth1_data),
+                Err(Error::DepositIndexTooHigh),
+                "should fail if the state has processed more deposits than exist"
+            );
+        }
+
         #[test]
         fn eth1_data_empty_cache() {
             let spec = &E::default_spec();
@@ -696,6 +1067,114 @@ mod test {
                 "random votes should be returned if the previous eth1 data block hash is unknown"
             );
         }
+
+        #[test]
+        fn eth1_data_from_restored_cache() {
+            let spec = &E::default_spec();
+
+            let mut state: BeaconState<E> = BeaconState::new(0, get_eth1_data(0), &spec);
+            let vote = get_eth1_data(42);
+            state.eth1_data_votes = vec![vote.clone()].into();
+
+            let cache = Eth1DataCache {
+                voting_period_start_slot: Slot::new(0),
+                prev_eth1_hash: state.eth1_data.block_hash,
+                highest_block_number: None,
+                is_final: true,
+                new_eth1_data: vec![(vote.clone(), 42)],
+                all_eth1_data: vec![(vote.clone(), 42)],
+            };
+
+            let original = get_eth1_chain();
+            *original.backend.eth1_data_cache.write() = Some(cache.clone());
+
+            let restored = get_eth1_chain();
+            restored
+                .restore_from_ssz_container(&original.as_ssz_container())
+                .expect("should restore cache");
+
+            assert_eq!(
+                *restored.backend.eth1_data_cache.read(),
+                Some(cache),
+                "should restore the cache"
+            );
+            assert_eq!(
+                restored.eth1_data_for_block_production(&state, &spec),
+                Ok(vote),
+                "should vote from the restored cache with an empty block cache"
+            );
+        }
+
+        #[test]
+        fn eth1_data_cache_updates_with_new_blocks() {
+            let spec = &E::default_spec();
+            let eth1_chain = get_eth1_chain();
+            let follow_distance = spec.eth1_follow_distance;
+
+            let mut state: BeaconState<E> = BeaconState::new(0, get_eth1_data(0), &spec);
+            state.genesis_time = follow_distance * 4;
+            state.eth1_data.block_hash = Hash256::from_low_u64_be(0);
+
+            let insert_blocks = |range: std::ops::Range<u64>| {
+                let mut blocks = eth1_chain.backend.core.blocks().write();
+                range.for_each(|i| {
+                    blocks
+                        .insert_root_or_child(get_eth1_block(i, i))
+                        .expect("should add blocks to cache")
+                });
+            };
+
+            // All blocks precede the voting period, the candidates may still change.
+            insert_blocks(0..follow_distance * 2);
+            eth1_chain
+                .eth1_data_for_block_production(&state, &spec)
+                .expect("should produce eth1 data");
+            let first = eth1_chain
+                .backend
+                .eth1_data_cache
+                .read()
+                .clone()
+                .expect("should cache the candidates");
+            assert!(!first.is_final, "cache should not be final");
+
+            let restored = get_eth1_chain();
+            restored
+                .restore_from_ssz_container(&eth1_chain.as_ssz_container())
+                .expect("should restore cache");
+            assert_eq!(
+                *restored.backend.eth1_data_cache.read(),
+                None,
+                "should not persist a cache which is not final"
+            );
+
+            // Blocks arriving from after the start of the voting period finalize the candidates.
+            insert_blocks(follow_distance * 2..follow_distance * 5);
+            eth1_chain
+                .eth1_data_for_block_production(&state, &spec)
+                .expect("should produce eth1 data");
+            let second = eth1_chain
+                .backend
+                .eth1_data_cache
+                .read()
+                .clone()
+                .expect("should cache the candidates");
+            assert!(second.is_final, "cache should be final");
+            assert!(
+                first.new_eth1_data != second.new_eth1_data,
+                "candidates should be recomputed with the new blocks"
+            );
+
+            // Blocks arriving after the cache is final are ignored.
+            insert_blocks(follow_distance * 5..follow_distance * 6);
+            eth1_chain
+                .eth1_data_for_block_production(&state, &spec)
+                .expect("should produce eth1 data");
+            assert_eq!(
+                *eth1_chain.backend.eth1_data_cache.read(),
+                Some(second),
+                "final cache should not be recomputed"
+            );
+        }
     }
 
     mod prev_block_hash {
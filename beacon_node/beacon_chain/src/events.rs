@@ -1,6 +1,6 @@
 use serde_derive::{Deserialize, Serialize};
 use std::marker::PhantomData;
-use types::{Attestation, BeaconBlock, Epoch, EthSpec, Hash256};
+use types::{Attestation, BeaconBlock, Epoch, EthSpec, Hash256, Slot};
 pub use websocket_server::WebSocketSender;
 
 pub trait EventHandler<T: EthSpec>: Sized + Send + Sync {
@@ -47,6 +47,17 @@ pub enum EventKind<T: EthSpec> {
         epoch: Epoch,
         root: Hash256,
     },
+    /// The head switched to a block which does not descend from the previous head.
+    ChainReorg {
+        /// The slot of the new head.
+        slot: Slot,
+        /// The number of slots between the previous head and the common ancestor.
+        depth: u64,
+        old_head_block: Hash256,
+        new_head_block: Hash256,
+        old_head_state: Hash256,
+        new_head_state: Hash256,
+    },
     BeaconBlockImported {
         block_root: Hash256,
         block: Box<BeaconBlock<T>>,
@@ -1,11 +1,13 @@
 use crate::{errors::BeaconChainError, metrics, BeaconChain, BeaconChainTypes};
-use lmd_ghost::LmdGhost;
 use parking_lot::RwLock;
+use proto_array::{ForkChoiceNode, ProtoArrayForkChoice};
+use ssz_derive::{Decode, Encode};
 use state_processing::{common::get_attesting_indices, per_slot_processing};
-use std::sync::Arc;
-use store::{Error as StoreError, Store};
+use std::marker::PhantomData;
+use store::Error as StoreError;
 use types::{
-    Attestation, BeaconBlock, BeaconState, BeaconStateError, Checkpoint, EthSpec, Hash256, Slot,
+    Attestation, BeaconBlock, BeaconState, BeaconStateError, Checkpoint, Epoch, EthSpec, Hash256,
+    Slot,
 };
 
 type Result<T> = std::result::Result<T, Error>;
@@ -18,11 +20,11 @@ pub enum Error {
     BeaconStateError(BeaconStateError),
     StoreError(StoreError),
     BeaconChainError(Box<BeaconChainError>),
+    UnknownBlockSlot(Hash256),
 }
 
 pub struct ForkChoice<T: BeaconChainTypes> {
-    store: Arc<T::Store>,
-    backend: T::LmdGhost,
+    backend: ProtoArrayForkChoice,
     /// Used for resolving the `0x00..00` alias back to genesis.
     ///
     /// Does not necessarily need to be the _actual_ genesis, it suffices to be the finalized root
@@ -32,6 +34,20 @@ pub struct ForkChoice<T: BeaconChainTypes> {
     justified_checkpoint: RwLock<Checkpoint>,
     /// The best justified checkpoint we've seen, which may be ahead of `justified_checkpoint`.
     best_justified_checkpoint: RwLock<Checkpoint>,
+    /// The highest finalized checkpoint of any block imported into fork choice. Heads that do not
+    /// agree with this checkpoint are filtered out.
+    finalized_checkpoint: RwLock<Checkpoint>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: BeaconChainTypes> PartialEq for ForkChoice<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.backend == other.backend
+            && self.genesis_block_root == other.genesis_block_root
+            && *self.justified_checkpoint.read() == *other.justified_checkpoint.read()
+            && *self.best_justified_checkpoint.read() == *other.best_justified_checkpoint.read()
+            && *self.finalized_checkpoint.read() == *other.finalized_checkpoint.read()
+    }
 }
 
 impl<T: BeaconChainTypes> ForkChoice<T> {
@@ -40,24 +56,42 @@ impl<T: BeaconChainTypes> ForkChoice<T> {
     /// "Genesis" does not necessarily need to be the absolute genesis, it can be some finalized
     /// block.
     pub fn new(
-        store: Arc<T::Store>,
-        backend: T::LmdGhost,
+        backend: ProtoArrayForkChoice,
         genesis_block_root: Hash256,
         genesis_slot: Slot,
     ) -> Self {
-        let justified_checkpoint = Checkpoint {
+        let checkpoint = Checkpoint {
             epoch: genesis_slot.epoch(T::EthSpec::slots_per_epoch()),
             root: genesis_block_root,
         };
         Self {
-            store: store.clone(),
             backend,
             genesis_block_root,
-            justified_checkpoint: RwLock::new(justified_checkpoint.clone()),
-            best_justified_checkpoint: RwLock::new(justified_checkpoint),
+            justified_checkpoint: RwLock::new(checkpoint.clone()),
+            best_justified_checkpoint: RwLock::new(checkpoint.clone()),
+            finalized_checkpoint: RwLock::new(checkpoint),
+            _phantom: PhantomData,
         }
     }
 
+    /// Instantiate a new, empty (no recorded votes or blocks) fork chooser, rooted at the given
+    /// finalized block.
+    pub fn from_finalized_block(
+        finalized_block: &BeaconBlock<T::EthSpec>,
+        finalized_block_root: Hash256,
+    ) -> Result<Self> {
+        let epoch = finalized_block.slot.epoch(T::EthSpec::slots_per_epoch());
+
+        let backend =
+            ProtoArrayForkChoice::new(finalized_block.slot, epoch, epoch, finalized_block_root)?;
+
+        Ok(Self::new(
+            backend,
+            finalized_block_root,
+            finalized_block.slot,
+        ))
+    }
+
     /// Determine whether the fork choice's view of the justified checkpoint should be updated.
     ///
     /// To prevent the bouncing attack, an update is allowed only in these conditions:
@@ -106,7 +140,7 @@ impl<T: BeaconChainTypes> ForkChoice<T> {
     pub fn find_head(&self, chain: &BeaconChain<T>) -> Result<Hash256> {
         let timer = metrics::start_timer(&metrics::FORK_CHOICE_FIND_HEAD_TIMES);
 
-        let (start_state, start_block_root, start_block_slot) = {
+        let (start_state, start_block_root, start_epoch) = {
             // Check if we should update our view of the justified checkpoint.
             // Doing this check here should be quasi-equivalent to the update in the `on_tick`
             // function of the spec, so long as `find_head` is called at least once during the first
@@ -138,29 +172,41 @@ impl<T: BeaconChainTypes> ForkChoice<T> {
             };
 
             let mut state = chain
-                .get_state(&block.state_root)?
+                .get_state(&block.state_root, Some(block.slot))?
                 .ok_or_else(|| Error::MissingState(block.state_root))?;
 
             // Fast-forward the state to the start slot of the epoch where it was justified.
             for _ in block.slot.as_u64()..block_justified_slot.as_u64() {
                 per_slot_processing(&mut state, &chain.spec)
-                    .map_err(|e| BeaconChainError::SlotProcessingError(e))?
+                    .map_err(|e| BeaconChainError::SlotProcessingError(e))?;
             }
 
-            (state, block_root, block_justified_slot)
+            (state, block_root, current_justified_checkpoint.epoch)
         };
 
-        // A function that returns the weight for some validator index.
-        let weight = |validator_index: usize| -> Option<u64> {
-            start_state
-                .validators
-                .get(validator_index)
-                .map(|v| v.effective_balance)
-        };
+        // The effective balance of each validator in the justified state. Validators that are not
+        // active have no weight.
+        let current_epoch = start_state.current_epoch();
+        let balances = start_state
+            .validators
+            .iter()
+            .map(|validator| {
+                if validator.is_active_at(current_epoch) {
+                    validator.effective_balance
+                } else {
+                    0
+                }
+            })
+            .collect::<Vec<_>>();
 
         let result = self
             .backend
-            .find_head(start_block_slot, start_block_root, weight)
+            .find_head(
+                start_epoch,
+                start_block_root,
+                self.finalized_checkpoint.read().epoch,
+                &balances,
+            )
             .map_err(Into::into);
 
         metrics::stop_timer(timer);
@@ -187,14 +233,7 @@ impl<T: BeaconChainTypes> ForkChoice<T> {
         //
         // https://github.com/ethereum/eth2.0-specs/blob/v0.7.0/specs/core/0_fork-choice.md
         for attestation in &block.body.attestations {
-            // If the `data.beacon_block_root` block is not known to us, simply ignore the latest
-            // vote.
-            if let Some(block) = self
-                .store
-                .get::<BeaconBlock<T::EthSpec>>(&attestation.data.beacon_block_root)?
-            {
-                self.process_attestation(state, attestation, &block)?;
-            }
+            self.process_attestation(state, attestation)?;
         }
 
         // Check if we should update our view of the justified checkpoint
@@ -207,26 +246,37 @@ impl<T: BeaconChainTypes> ForkChoice<T> {
             }
         }
 
+        // Track the highest finalized checkpoint, so that heads which disagree with it are filtered
+        // out.
+        if state.finalized_checkpoint.epoch > self.finalized_checkpoint.read().epoch {
+            *self.finalized_checkpoint.write() = state.finalized_checkpoint.clone();
+        }
+
         // This does not apply a vote to the block, it just makes fork choice aware of the block so
         // it can still be identified as the head even if it doesn't have any votes.
         //
         // A case where a block without any votes can be the head is where it is the only child of
         // a block that has the majority of votes applied to it.
-        self.backend.process_block(block, block_root)?;
+        self.backend.process_block(
+            block.slot,
+            block_root,
+            block.parent_root,
+            state.current_justified_checkpoint.epoch,
+            state.finalized_checkpoint.epoch,
+        )?;
 
         metrics::stop_timer(timer);
 
         Ok(())
     }
 
-    /// Process an attestation which references `block` in `attestation.data.beacon_block_root`.
+    /// Process an attestation, updating the latest message of each attesting validator.
     ///
     /// Assumes the attestation is valid.
     pub fn process_attestation(
         &self,
         state: &BeaconState<T::EthSpec>,
         attestation: &Attestation<T::EthSpec>,
-        block: &BeaconBlock<T::EthSpec>,
     ) -> Result<()> {
         let timer = metrics::start_timer(&metrics::FORK_CHOICE_PROCESS_ATTESTATION_TIMES);
 
@@ -247,13 +297,16 @@ impl<T: BeaconChainTypes> ForkChoice<T> {
         // to genesis just by being present in the chain.
         //
         // Additionally, don't add any block hash to fork choice unless we have imported the block.
-        if block_hash != Hash256::zero() {
+        if block_hash != Hash256::zero() && self.backend.contains_block(&block_hash) {
             let validator_indices =
                 get_attesting_indices(state, &attestation.data, &attestation.aggregation_bits)?;
 
             for validator_index in validator_indices {
-                self.backend
-                    .process_attestation(validator_index, block_hash, block.slot)?;
+                self.backend.process_attestation(
+                    validator_index,
+                    block_hash,
+                    attestation.data.target.epoch,
+                )?;
             }
         }
 
@@ -264,32 +317,122 @@ impl<T: BeaconChainTypes> ForkChoice<T> {
 
     /// Returns the latest message for a given validator, if any.
     ///
-    /// Returns `(block_root, block_slot)`.
-    pub fn latest_message(&self, validator_index: usize) -> Option<(Hash256, Slot)> {
+    /// Returns `(block_root, target_epoch)`.
+    pub fn latest_message(&self, validator_index: usize) -> Option<(Hash256, Epoch)> {
         self.backend.latest_message(validator_index)
     }
 
-    /// Runs an integrity verification function on the underlying fork choice algorithm.
-    ///
-    /// Returns `Ok(())` if the underlying fork choice has maintained it's integrity,
-    /// `Err(description)` otherwise.
-    pub fn verify_integrity(&self) -> core::result::Result<(), String> {
-        self.backend.verify_integrity()
+    /// Returns `true` if the given block is known to fork choice.
+    pub fn contains_block(&self, block_root: &Hash256) -> bool {
+        self.backend.contains_block(block_root)
     }
 
-    /// Inform the fork choice that the given block (and corresponding root) have been finalized so
-    /// it may prune it's storage.
-    ///
-    /// `finalized_block_root` must be the root of `finalized_block`.
-    pub fn process_finalization(
-        &self,
-        finalized_block: &BeaconBlock<T::EthSpec>,
-        finalized_block_root: Hash256,
-    ) -> Result<()> {
+    /// Returns the slot of the given block, if it is known to fork choice.
+    pub fn block_slot(&self, block_root: &Hash256) -> Result<Slot> {
+        self.backend
+            .block_slot(block_root)
+            .ok_or_else(|| Error::UnknownBlockSlot(*block_root))
+    }
+
+    /// Returns the fork choice rule's current view of the justified checkpoint.
+    pub fn justified_checkpoint(&self) -> Checkpoint {
+        self.justified_checkpoint.read().clone()
+    }
+
+    /// Returns a snapshot of every node in the underlying block tree, for debugging.
+    pub fn nodes(&self) -> Result<Vec<ForkChoiceNode>> {
+        Ok(self.backend.nodes())
+    }
+
+    /// Inform the fork choice that the given block root has been finalized so it may prune its
+    /// storage.
+    pub fn process_finalization(&self, finalized_block_root: Hash256) -> Result<()> {
         self.backend
-            .update_finalized_root(finalized_block, finalized_block_root)
+            .update_finalized_root(finalized_block_root)
             .map_err(Into::into)
     }
+
+    /// Returns a `SszForkChoice` which contains the current state of `Self`.
+    pub fn as_ssz_container(&self) -> SszForkChoice {
+        SszForkChoice {
+            genesis_block_root: self.genesis_block_root,
+            justified_checkpoint: self.justified_checkpoint.read().clone(),
+            best_justified_checkpoint: self.best_justified_checkpoint.read().clone(),
+            finalized_checkpoint: self.finalized_checkpoint.read().clone(),
+            backend_bytes: self.backend.as_bytes(),
+        }
+    }
+
+    /// Instantiates `Self` from a prior `SszForkChoice`.
+    ///
+    /// The created `Self` will have the same state as the `Self` that created the `SszForkChoice`.
+    pub fn from_ssz_container(ssz_container: SszForkChoice) -> Result<Self> {
+        let backend = ProtoArrayForkChoice::from_bytes(&ssz_container.backend_bytes)?;
+
+        Ok(Self {
+            backend,
+            genesis_block_root: ssz_container.genesis_block_root,
+            justified_checkpoint: RwLock::new(ssz_container.justified_checkpoint),
+            best_justified_checkpoint: RwLock::new(ssz_container.best_justified_checkpoint),
+            finalized_checkpoint: RwLock::new(ssz_container.finalized_checkpoint),
+            _phantom: PhantomData,
+        })
+    }
+}
+
+/// Helper struct that is used to encode/decode the state of the `ForkChoice` as SSZ bytes.
+///
+/// This is used when persisting the state of the `BeaconChain` to disk.
+#[derive(Encode, Decode, Clone)]
+pub struct SszForkChoice {
+    genesis_block_root: Hash256,
+    justified_checkpoint: Checkpoint,
+    best_justified_checkpoint: Checkpoint,
+    finalized_checkpoint: Checkpoint,
+    backend_bytes: Vec<u8>,
+}
+
+impl SszForkChoice {
+    /// Returns the container of a fork chooser with no recorded votes, containing the finalized
+    /// block of `finalized_checkpoint` and its descendants, `blocks`.
+    ///
+    /// Each of `blocks` is given as its root, the block and the justified and finalized epochs of
+    /// its post-state, in ascending slot order. The checkpoints must not use the `0x00..00` alias
+    /// for genesis.
+    ///
+    /// Used to upgrade databases which were created before the fork choice was persisted.
+    pub fn from_canonical_chain<E: EthSpec>(
+        genesis_block_root: Hash256,
+        justified_checkpoint: Checkpoint,
+        finalized_checkpoint: Checkpoint,
+        finalized_block: &BeaconBlock<E>,
+        blocks: &[(Hash256, BeaconBlock<E>, Epoch, Epoch)],
+    ) -> Result<Self> {
+        let backend = ProtoArrayForkChoice::new(
+            finalized_block.slot,
+            justified_checkpoint.epoch,
+            finalized_checkpoint.epoch,
+            finalized_checkpoint.root,
+        )?;
+
+        for (block_root, block, justified_epoch, finalized_epoch) in blocks {
+            backend.process_block(
+                block.slot,
+                *block_root,
+                block.parent_root,
+                *justified_epoch,
+                *finalized_epoch,
+            )?;
+        }
+
+        Ok(Self {
+            genesis_block_root,
+            justified_checkpoint: justified_checkpoint.clone(),
+            best_justified_checkpoint: justified_checkpoint,
+            finalized_checkpoint,
+            backend_bytes: backend.as_bytes(),
+        })
+    }
 }
 
 impl From<BeaconStateError> for Error {
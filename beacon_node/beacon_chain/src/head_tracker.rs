@@ -0,0 +1,180 @@
+use parking_lot::RwLock;
+use ssz_derive::{Decode, Encode};
+use std::collections::HashMap;
+use types::{BeaconBlock, EthSpec, Hash256, Slot};
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    MismatchingLengths { roots_len: usize, slots_len: usize },
+}
+
+/// Maintains a list of `BeaconChain` head block roots and slots.
+///
+/// Each time a new block is imported, it should be applied to the `Self::register_block` function.
+/// In order for this struct to be effective, every single block that is imported must be
+/// registered here.
+#[derive(Default, Debug)]
+pub struct HeadTracker(RwLock<HashMap<Hash256, Slot>>);
+
+impl HeadTracker {
+    /// Register a block with `Self`, so it may or may not be included in a `Self::heads` call.
+    ///
+    /// This function assumes that no block is imported without its parent having already been
+    /// imported. It cannot detect an error if this is not the case, it is the responsibility of
+    /// the upstream user.
+    pub fn register_block<E: EthSpec>(&self, block_root: Hash256, block: &BeaconBlock<E>) {
+        let mut map = self.0.write();
+
+        map.remove(&block.parent_root);
+        map.insert(block_root, block.slot);
+    }
+
+    /// Removes the given `block_roots` from `Self`, typically because they belong to forks that
+    /// have been pruned from the database.
+    pub fn remove_heads(&self, block_roots: &[Hash256]) {
+        let mut map = self.0.write();
+
+        for block_root in block_roots {
+            map.remove(block_root);
+        }
+    }
+
+    /// Returns the list of heads in the chain.
+    pub fn heads(&self) -> Vec<(Hash256, Slot)> {
+        self.0
+            .read()
+            .iter()
+            .map(|(root, slot)| (*root, *slot))
+            .collect()
+    }
+
+    /// Returns a `SszHeadTracker`, which contains all necessary information to restore the state
+    /// of `Self` at some later point.
+    pub fn to_ssz_container(&self) -> SszHeadTracker {
+        let (roots, slots) = self
+            .0
+            .read()
+            .iter()
+            .map(|(root, slot)| (*root, *slot))
+            .unzip();
+
+        SszHeadTracker { roots, slots }
+    }
+
+    /// Creates a new `Self` from the given `SszHeadTracker`, restoring `Self` to the same state of
+    /// the `Self` that created the `SszHeadTracker`.
+    pub fn from_ssz_container(ssz_container: &SszHeadTracker) -> Result<Self, Error> {
+        let roots_len = ssz_container.roots.len();
+        let slots_len = ssz_container.slots.len();
+
+        if roots_len != slots_len {
+            Err(Error::MismatchingLengths {
+                roots_len,
+                slots_len,
+            })
+        } else {
+            let map = ssz_container
+                .roots
+                .iter()
+                .zip(ssz_container.slots.iter())
+                .map(|(root, slot)| (*root, *slot))
+                .collect::<HashMap<_, _>>();
+
+            Ok(Self(RwLock::new(map)))
+        }
+    }
+}
+
+impl PartialEq<HeadTracker> for HeadTracker {
+    fn eq(&self, other: &HeadTracker) -> bool {
+        *self.0.read() == *other.0.read()
+    }
+}
+
+/// Helper struct that is used to encode/decode the state of the `HeadTracker` as SSZ bytes.
+///
+/// This is used when persisting the state of the `BeaconChain` to disk.
+#[derive(Encode, Decode, Clone)]
+pub struct SszHeadTracker {
+    roots: Vec<Hash256>,
+    slots: Vec<Slot>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ssz::{Decode, Encode};
+    use types::{BeaconBlock, MainnetEthSpec};
+
+    type E = MainnetEthSpec;
+
+    fn block_at(slot: u64, parent_root: Hash256) -> BeaconBlock<E> {
+        let spec = E::default_spec();
+        let mut block = BeaconBlock::<E>::empty(&spec);
+        block.slot = Slot::new(slot);
+        block.parent_root = parent_root;
+        block
+    }
+
+    #[test]
+    fn block_add() {
+        let tracker = HeadTracker::default();
+
+        let genesis_root = Hash256::from_low_u64_be(1);
+        tracker.register_block(genesis_root, &block_at(0, Hash256::zero()));
+        assert_eq!(tracker.heads(), vec![(genesis_root, Slot::new(0))]);
+
+        // A child of the head replaces it.
+        let child_root = Hash256::from_low_u64_be(2);
+        tracker.register_block(child_root, &block_at(1, genesis_root));
+        assert_eq!(tracker.heads(), vec![(child_root, Slot::new(1))]);
+
+        // A second child of genesis creates a fork.
+        let fork_root = Hash256::from_low_u64_be(3);
+        tracker.register_block(fork_root, &block_at(2, genesis_root));
+
+        let mut heads = tracker.heads();
+        heads.sort_by_key(|(_, slot)| *slot);
+        assert_eq!(
+            heads,
+            vec![(child_root, Slot::new(1)), (fork_root, Slot::new(2))]
+        );
+
+        tracker.remove_heads(&[fork_root]);
+        assert_eq!(tracker.heads(), vec![(child_root, Slot::new(1))]);
+    }
+
+    #[test]
+    fn non_empty_round_trip() {
+        let non_empty = HeadTracker::default();
+        for i in 0..16 {
+            non_empty
+                .0
+                .write()
+                .insert(Hash256::from_low_u64_be(i), Slot::new(i));
+        }
+        let bytes = non_empty.to_ssz_container().as_ssz_bytes();
+
+        assert_eq!(
+            HeadTracker::from_ssz_container(
+                &SszHeadTracker::from_ssz_bytes(&bytes).expect("should decode")
+            ),
+            Ok(non_empty),
+            "non_empty should pass round trip"
+        );
+    }
+
+    #[test]
+    fn empty_round_trip() {
+        let empty = HeadTracker::default();
+        let bytes = empty.to_ssz_container().as_ssz_bytes();
+
+        assert_eq!(
+            HeadTracker::from_ssz_container(
+                &SszHeadTracker::from_ssz_bytes(&bytes).expect("should decode")
+            ),
+            Ok(empty),
+            "empty should pass round trip"
+        );
+    }
+}
@@ -3,28 +3,45 @@
 extern crate lazy_static;
 
 mod beacon_chain;
+mod block_rewards;
 pub mod builder;
+mod chain_quality;
 mod checkpoint;
 mod errors;
 pub mod eth1_chain;
 pub mod events;
 mod fork_choice;
+mod head_tracker;
 mod iter;
 mod metrics;
+pub mod migrate;
+mod observed_aggregators;
+mod observed_block_producers;
+mod observed_votes;
 mod persisted_beacon_chain;
+pub mod schema_change;
+mod shuffling_cache;
 pub mod test_utils;
+mod validator_inclusion;
+mod validator_pubkey_cache;
 
 pub use self::beacon_chain::{
     AttestationProcessingOutcome, BeaconChain, BeaconChainTypes, BlockProcessingOutcome,
+    ChainSegmentResult, OperationProcessingOutcome, ATTESTATION_PROPAGATION_SLOT_RANGE,
 };
 pub use self::checkpoint::CheckPoint;
 pub use self::errors::{BeaconChainError, BlockProductionError};
+pub use block_rewards::BlockRewards;
+pub use chain_quality::{ChainQuality, MAX_CHAIN_QUALITY_EPOCHS};
 pub use eth1_chain::{Eth1Chain, Eth1ChainBackend};
 pub use events::EventHandler;
-pub use fork_choice::ForkChoice;
-pub use lmd_ghost;
+pub use fork_choice::{ForkChoice, SszForkChoice};
+pub use head_tracker::HeadTracker;
 pub use metrics::scrape_for_metrics;
+pub use observed_block_producers::ProducerStatus;
 pub use parking_lot;
+pub use proto_array;
+pub use resource_budget;
 pub use slot_clock;
 pub use state_processing::per_block_processing::errors::{
     AttestationValidationError, AttesterSlashingValidationError, DepositValidationError,
@@ -32,3 +49,4 @@ pub use state_processing::per_block_processing::errors::{
 };
 pub use store;
 pub use types;
+pub use validator_inclusion::{EpochInclusionSummary, ValidatorInclusion};
@@ -1,3 +1,4 @@
+use crate::validator_inclusion::EpochInclusionSummary;
 use crate::{BeaconChain, BeaconChainTypes};
 pub use lighthouse_metrics::*;
 use types::{BeaconState, Epoch, Hash256, Slot};
@@ -66,6 +67,27 @@ lazy_static! {
     );
     pub static ref BLOCK_PRODUCTION_TIMES: Result<Histogram> =
         try_create_histogram("beacon_block_production_seconds", "Full runtime of block production");
+    pub static ref BLOCK_PRODUCTION_STATE_LOAD_TIMES: Result<Histogram> = try_create_histogram(
+        "beacon_block_production_state_load_seconds",
+        "Time taken to load the parent state for block production"
+    );
+    pub static ref BLOCK_PRODUCTION_SLOT_PROCESS_TIMES: Result<Histogram> = try_create_histogram(
+        "beacon_block_production_slot_process_seconds",
+        "Time taken to advance the parent state to the slot of the produced block"
+    );
+    pub static ref BLOCK_PRODUCTION_OPERATIONS_TIMES: Result<Histogram> = try_create_histogram(
+        "beacon_block_production_operations_seconds",
+        "Time taken to select the operations (attestations, deposits, etc) for a produced block"
+    );
+    pub static ref BLOCK_PRODUCTION_STATE_ROOT_TIMES: Result<Histogram> = try_create_histogram(
+        "beacon_block_production_state_root_seconds",
+        "Time taken to apply a produced block and compute its state root"
+    );
+    pub static ref BLOCK_PRODUCTION_ATTESTATION_PACKING_EFFICIENCY: Result<Histogram> =
+        try_create_histogram(
+            "beacon_block_production_attestation_packing_efficiency",
+            "Fraction of the fresh attesting balance in the op pool which was included in a produced block"
+        );
 
     /*
      * Block Statistics
@@ -94,6 +116,30 @@ lazy_static! {
         "beacon_attestation_processing_core_seconds",
         "Time spent on the core spec processing of attestation processing"
     );
+    pub static ref ATTESTATION_PROCESSING_PRIOR_VOTE_KNOWN: Result<IntCounter> = try_create_int_counter(
+        "beacon_attestation_processing_prior_vote_known_total",
+        "Count of unaggregated attestations dropped because an identical vote was already processed"
+    );
+    pub static ref ATTESTATION_PROCESSING_EQUIVOCATIONS: Result<IntCounter> = try_create_int_counter(
+        "beacon_attestation_processing_equivocations_total",
+        "Count of validators observed signing conflicting unaggregated attestations"
+    );
+
+    /*
+     * Aggregate and Proof Processing
+     */
+    pub static ref AGGREGATE_PROCESSING_REQUESTS: Result<IntCounter> = try_create_int_counter(
+        "beacon_aggregate_processing_requests_total",
+        "Count of all aggregate and proofs submitted for processing"
+    );
+    pub static ref AGGREGATE_PROCESSING_SUCCESSES: Result<IntCounter> = try_create_int_counter(
+        "beacon_aggregate_processing_successes_total",
+        "Number of aggregate and proofs processed without error"
+    );
+    pub static ref AGGREGATE_PROCESSING_AGGREGATOR_KNOWN: Result<IntCounter> = try_create_int_counter(
+        "beacon_aggregate_processing_aggregator_known_total",
+        "Count of aggregate and proofs dropped because the aggregator was already seen for the slot"
+    );
 
     /*
      * Attestation Production
@@ -130,6 +176,10 @@ lazy_static! {
         "beacon_fork_choice_reorg_total",
         "Count of occasions fork choice has switched to a different chain"
     );
+    pub static ref FORK_CHOICE_REORG_DEPTH: Result<IntGauge> = try_create_int_gauge(
+        "beacon_fork_choice_reorg_depth",
+        "The number of slots between the previous head and the common ancestor in the last re-org"
+    );
     pub static ref FORK_CHOICE_TIMES: Result<Histogram> =
         try_create_histogram("beacon_fork_choice_seconds", "Full runtime of fork choice");
     pub static ref FORK_CHOICE_FIND_HEAD_TIMES: Result<Histogram> =
@@ -143,6 +193,18 @@ lazy_static! {
         "Time taken to add an attestation to fork choice"
     );
 
+    /*
+     * Shuffling Cache
+     */
+    pub static ref SHUFFLING_CACHE_HITS: Result<IntCounter> = try_create_int_counter(
+        "beacon_shuffling_cache_hits_total",
+        "Count of duties requests served from the shuffling cache"
+    );
+    pub static ref SHUFFLING_CACHE_MISSES: Result<IntCounter> = try_create_int_counter(
+        "beacon_shuffling_cache_misses_total",
+        "Count of duties requests which required a state to compute the shuffling"
+    );
+
     /*
      * Persisting BeaconChain to disk
      */
@@ -184,6 +246,29 @@ lazy_static! {
         try_create_int_gauge("beacon_head_state_withdrawn_validators_total", "Sum of all validator balances at the head of the chain");
     pub static ref HEAD_STATE_ETH1_DEPOSIT_INDEX: Result<IntGauge> =
         try_create_int_gauge("beacon_head_state_eth1_deposit_index", "Eth1 deposit index at the head of the chain");
+
+    /*
+     * Attestation Inclusion
+     *
+     * Recorded when the canonical chain performs epoch processing, describing the epoch prior to
+     * the one being processed.
+     */
+    pub static ref PARTICIPATION_EPOCH: Result<IntGauge> =
+        try_create_int_gauge("beacon_participation_prev_epoch", "The epoch described by the participation metrics");
+    pub static ref PARTICIPATION_ACTIVE_VALIDATORS: Result<IntGauge> =
+        try_create_int_gauge("beacon_participation_prev_epoch_active_validators_total", "Count of validators active in the previous epoch");
+    pub static ref PARTICIPATION_ACTIVE_GWEI: Result<IntGauge> =
+        try_create_int_gauge("beacon_participation_prev_epoch_active_gwei_total", "Total effective balance of validators active in the previous epoch");
+    pub static ref PARTICIPATION_SOURCE_ATTESTING_GWEI: Result<IntGauge> =
+        try_create_int_gauge("beacon_participation_prev_epoch_source_attesting_gwei_total", "Total effective balance of validators with a previous epoch attestation included on chain");
+    pub static ref PARTICIPATION_TARGET_ATTESTING_GWEI: Result<IntGauge> =
+        try_create_int_gauge("beacon_participation_prev_epoch_target_attesting_gwei_total", "Total effective balance of validators that voted for the correct previous epoch target");
+    pub static ref PARTICIPATION_HEAD_ATTESTING_GWEI: Result<IntGauge> =
+        try_create_int_gauge("beacon_participation_prev_epoch_head_attesting_gwei_total", "Total effective balance of validators that voted for the correct previous epoch head");
+    pub static ref PARTICIPATION_INCLUDED_ATTESTERS: Result<IntGauge> =
+        try_create_int_gauge("beacon_participation_prev_epoch_included_attesters_total", "Count of validators with a previous epoch attestation included on chain");
+    pub static ref PARTICIPATION_TOTAL_INCLUSION_DELAY: Result<IntGauge> =
+        try_create_int_gauge("beacon_participation_prev_epoch_inclusion_delay_slots_total", "Sum of the inclusion delays of all included previous epoch attesters");
 }
 
 /// Scrape the `beacon_chain` for metrics that are not constantly updated (e.g., the present slot,
@@ -249,6 +334,33 @@ fn scrape_head_state<T: BeaconChainTypes>(state: &BeaconState<T::EthSpec>, state
     set_gauge_by_u64(&HEAD_STATE_ETH1_DEPOSIT_INDEX, state.eth1_deposit_index);
 }
 
+/// Update the attestation inclusion metrics with the given `summary`.
+pub fn scrape_epoch_inclusion_summary(summary: &EpochInclusionSummary) {
+    set_gauge_by_epoch(&PARTICIPATION_EPOCH, summary.epoch);
+    set_gauge_by_u64(&PARTICIPATION_ACTIVE_VALIDATORS, summary.active_validators);
+    set_gauge_by_u64(&PARTICIPATION_ACTIVE_GWEI, summary.active_gwei);
+    set_gauge_by_u64(
+        &PARTICIPATION_SOURCE_ATTESTING_GWEI,
+        summary.source_attesting_gwei,
+    );
+    set_gauge_by_u64(
+        &PARTICIPATION_TARGET_ATTESTING_GWEI,
+        summary.target_attesting_gwei,
+    );
+    set_gauge_by_u64(
+        &PARTICIPATION_HEAD_ATTESTING_GWEI,
+        summary.head_attesting_gwei,
+    );
+    set_gauge_by_u64(
+        &PARTICIPATION_INCLUDED_ATTESTERS,
+        summary.included_attesters,
+    );
+    set_gauge_by_u64(
+        &PARTICIPATION_TOTAL_INCLUSION_DELAY,
+        summary.total_inclusion_delay,
+    );
+}
+
 fn set_gauge_by_slot(gauge: &Result<IntGauge>, value: Slot) {
     set_gauge(gauge, value.as_u64() as i64);
 }
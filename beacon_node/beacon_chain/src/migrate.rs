@@ -0,0 +1,359 @@
+use crate::head_tracker::HeadTracker;
+use parking_lot::Mutex;
+use resource_budget::ResourceBudget;
+use slog::{debug, warn, Logger};
+use std::collections::HashSet;
+use std::mem;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use store::iter::{BlockRootsIterator, StateRootsIterator};
+use store::{Error, Store, StoreOp};
+use types::{BeaconBlock, BeaconState, EthSpec, Hash256, Slot};
+
+/// Trait for migration processes that update the database upon finalization.
+pub trait Migrate<S: Store, E: EthSpec>: Send + Sync + 'static {
+    fn new(db: Arc<S>, log: Logger, resource_budget: ResourceBudget) -> Self;
+
+    /// Called each time the finalized checkpoint advances.
+    ///
+    /// Implementations should delete any blocks and states that no longer descend from the
+    /// finalized block and move the finalized portion of the chain into the freezer database (if
+    /// any).
+    fn process_finalization(
+        &self,
+        _finalized_block_root: Hash256,
+        _finalized_state_root: Hash256,
+        _finalized_state: BeaconState<E>,
+        _head_tracker: Arc<HeadTracker>,
+    ) {
+    }
+}
+
+/// Migrator that does nothing, for stores that don't need migration.
+pub struct NullMigrator;
+
+impl<S: Store, E: EthSpec> Migrate<S, E> for NullMigrator {
+    fn new(_: Arc<S>, _: Logger, _: ResourceBudget) -> Self {
+        NullMigrator
+    }
+}
+
+/// Migrator that immediately prunes and migrates (in the same thread) upon finalization.
+///
+/// Mostly useful for tests.
+pub struct BlockingMigrator<S> {
+    db: Arc<S>,
+    log: Logger,
+}
+
+impl<S: Store + 'static, E: EthSpec> Migrate<S, E> for BlockingMigrator<S> {
+    fn new(db: Arc<S>, log: Logger, _: ResourceBudget) -> Self {
+        BlockingMigrator { db, log }
+    }
+
+    fn process_finalization(
+        &self,
+        finalized_block_root: Hash256,
+        finalized_state_root: Hash256,
+        finalized_state: BeaconState<E>,
+        head_tracker: Arc<HeadTracker>,
+    ) {
+        run_migration(
+            self.db.clone(),
+            MigrationNotification {
+                finalized_block_root,
+                finalized_state_root,
+                finalized_state,
+                head_tracker,
+            },
+            &self.log,
+        );
+    }
+}
+
+/// The information required to prune and migrate the database upon finalization.
+struct MigrationNotification<E: EthSpec> {
+    finalized_block_root: Hash256,
+    finalized_state_root: Hash256,
+    finalized_state: BeaconState<E>,
+    head_tracker: Arc<HeadTracker>,
+}
+
+/// Migrator that runs a background thread to prune and migrate the database, so that
+/// finalization is never blocked by database deletions.
+pub struct BackgroundMigrator<S, E: EthSpec> {
+    db: Arc<S>,
+    tx_thread: Mutex<(
+        mpsc::Sender<MigrationNotification<E>>,
+        thread::JoinHandle<()>,
+    )>,
+    log: Logger,
+    resource_budget: ResourceBudget,
+}
+
+impl<S: Store + 'static, E: EthSpec> Migrate<S, E> for BackgroundMigrator<S, E> {
+    fn new(db: Arc<S>, log: Logger, resource_budget: ResourceBudget) -> Self {
+        let tx_thread = Mutex::new(Self::spawn_thread(
+            db.clone(),
+            log.clone(),
+            resource_budget.clone(),
+        ));
+        Self {
+            db,
+            tx_thread,
+            log,
+            resource_budget,
+        }
+    }
+
+    /// Send a message to the background thread, which will prune and migrate the database.
+    ///
+    /// If the thread has died (e.g., due to a panic) a new one is spawned to replace it.
+    fn process_finalization(
+        &self,
+        finalized_block_root: Hash256,
+        finalized_state_root: Hash256,
+        finalized_state: BeaconState<E>,
+        head_tracker: Arc<HeadTracker>,
+    ) {
+        let notification = MigrationNotification {
+            finalized_block_root,
+            finalized_state_root,
+            finalized_state,
+            head_tracker,
+        };
+
+        let (ref mut tx, ref mut thread) = *self.tx_thread.lock();
+
+        if let Err(tx_err) = tx.send(notification) {
+            let (new_tx, new_thread) = Self::spawn_thread(
+                self.db.clone(),
+                self.log.clone(),
+                self.resource_budget.clone(),
+            );
+
+            *tx = new_tx;
+            let old_thread = mem::replace(thread, new_thread);
+
+            warn!(
+                self.log,
+                "Migration thread died, restarting";
+                "error" => format!("{:?}", old_thread.join())
+            );
+
+            // Retry the send using the new thread. Any failure here is logged and the migration is
+            // skipped, it will be picked up by the next finalization.
+            if let Err(e) = tx.send(tx_err.0) {
+                warn!(
+                    self.log,
+                    "Unable to send to migration thread";
+                    "finalized_block_root" => format!("{}", e.0.finalized_block_root)
+                );
+            }
+        }
+    }
+}
+
+impl<S: Store + 'static, E: EthSpec> BackgroundMigrator<S, E> {
+    /// Spawn a new child thread to run the migration process.
+    ///
+    /// Return a channel handle for sending new finalized states to the thread.
+    fn spawn_thread(
+        db: Arc<S>,
+        log: Logger,
+        resource_budget: ResourceBudget,
+    ) -> (
+        mpsc::Sender<MigrationNotification<E>>,
+        thread::JoinHandle<()>,
+    ) {
+        let (tx, rx) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            while let Ok(notification) = rx.recv() {
+                // When the resource budget is constrained, wait before migrating so that any
+                // finalizations arriving in the meantime are handled by a single migration.
+                let delay = resource_budget.migration_delay();
+                if delay > Duration::from_secs(0) {
+                    thread::sleep(delay);
+                }
+
+                // Only the most recent finalization needs to be processed, it supersedes any that
+                // are still queued. This batches the work if finalization outpaces the thread.
+                let notification = rx.try_iter().last().unwrap_or(notification);
+
+                run_migration(db.clone(), notification, &log);
+            }
+        });
+
+        (tx, thread)
+    }
+}
+
+/// Prunes abandoned forks and then moves the finalized states into the freezer database.
+fn run_migration<S: Store, E: EthSpec>(
+    db: Arc<S>,
+    notification: MigrationNotification<E>,
+    log: &Logger,
+) {
+    let MigrationNotification {
+        finalized_block_root,
+        finalized_state_root,
+        finalized_state,
+        head_tracker,
+    } = notification;
+
+    if let Err(e) = prune_abandoned_forks(
+        db.clone(),
+        &head_tracker,
+        finalized_block_root,
+        finalized_state_root,
+        &finalized_state,
+        log,
+    ) {
+        warn!(
+            log,
+            "Database pruning failed";
+            "error" => format!("{:?}", e)
+        );
+    }
+
+    if let Err(e) = S::freeze_to_state(db, finalized_state_root, &finalized_state) {
+        warn!(
+            log,
+            "Database migration failed";
+            "error" => format!("{:?}", e)
+        );
+    }
+}
+
+/// Deletes the blocks and states of all the forks in `head_tracker` that do not descend from the
+/// finalized block.
+///
+/// Each head is traversed backwards until it joins the canonical chain (as recorded in the
+/// `finalized_state`). If it joins at the finalized block the head is retained, otherwise every
+/// block and state that was passed over is deleted and the head is removed from `head_tracker`.
+pub fn prune_abandoned_forks<S: Store, E: EthSpec>(
+    db: Arc<S>,
+    head_tracker: &HeadTracker,
+    finalized_block_root: Hash256,
+    finalized_state_root: Hash256,
+    finalized_state: &BeaconState<E>,
+    log: &Logger,
+) -> Result<(), Error> {
+    let finalized_slot = finalized_state.slot;
+
+    let mut abandoned_heads = vec![];
+    let mut abandoned_blocks = HashSet::new();
+    let mut abandoned_states = HashSet::new();
+
+    for (head_block_root, head_slot) in head_tracker.heads() {
+        let head_block = match db.get::<BeaconBlock<E>>(&head_block_root)? {
+            Some(block) => block,
+            // The head may have been removed by an earlier, interrupted pruning run.
+            None => {
+                abandoned_heads.push(head_block_root);
+                continue;
+            }
+        };
+        let head_state_root = head_block.state_root;
+        let head_state = match db.get_state::<E>(&head_state_root, Some(head_slot))? {
+            Some(state) => state,
+            None => continue,
+        };
+
+        let ancestors = BlockRootsIterator::new(db.clone(), &head_state)
+            .zip(StateRootsIterator::new(db.clone(), &head_state))
+            .map(|((block_root, slot), (state_root, _))| (block_root, state_root, slot));
+
+        let mut fork_blocks = HashSet::new();
+        let mut fork_states = HashSet::new();
+        let mut descends_from_finalized = false;
+        let mut reached_finalized_slot = false;
+
+        for (block_root, state_root, slot) in
+            std::iter::once((head_block_root, head_state_root, head_slot)).chain(ancestors)
+        {
+            if slot <= finalized_slot {
+                reached_finalized_slot = true;
+
+                let canonical = canonical_roots(
+                    finalized_block_root,
+                    finalized_state_root,
+                    finalized_state,
+                    slot,
+                );
+
+                match canonical {
+                    Some((canonical_block_root, canonical_state_root))
+                        if block_root == canonical_block_root
+                            && state_root == canonical_state_root =>
+                    {
+                        // The fork has joined the canonical chain. The block at this slot may be
+                        // repeated in skip slots above it, so ensure it is not deleted.
+                        fork_blocks.remove(&block_root);
+                        descends_from_finalized = slot == finalized_slot;
+                        break;
+                    }
+                    // Unable to determine the canonical chain this far back. The blocks and
+                    // states passed over so far are known to be abandoned, so delete them.
+                    None => break,
+                    Some(_) => (),
+                }
+            }
+
+            fork_blocks.insert(block_root);
+            fork_states.insert(state_root);
+        }
+
+        // A head that could not be traced back to the finalized slot (e.g., due to a missing
+        // state) is left alone, it may be pruned upon a later finalization.
+        if !descends_from_finalized && reached_finalized_slot {
+            abandoned_heads.push(head_block_root);
+            abandoned_blocks.extend(fork_blocks);
+            abandoned_states.extend(fork_states);
+        }
+    }
+
+    let batch: Vec<StoreOp<E>> = abandoned_blocks
+        .iter()
+        .map(|block_root| StoreOp::DeleteBlock(*block_root))
+        .chain(
+            abandoned_states
+                .iter()
+                .map(|state_root| StoreOp::DeleteState(*state_root)),
+        )
+        .collect();
+    db.do_atomically(batch)?;
+
+    head_tracker.remove_heads(&abandoned_heads);
+
+    debug!(
+        log,
+        "Pruned abandoned forks";
+        "heads" => abandoned_heads.len(),
+        "blocks" => abandoned_blocks.len(),
+        "states" => abandoned_states.len(),
+        "finalized_slot" => finalized_slot.as_u64(),
+    );
+
+    Ok(())
+}
+
+/// Returns the canonical `(block_root, state_root)` at `slot`, according to the finalized state.
+///
+/// Returns `None` if `slot` is later than the finalized state, or too old to be recorded in it.
+fn canonical_roots<E: EthSpec>(
+    finalized_block_root: Hash256,
+    finalized_state_root: Hash256,
+    finalized_state: &BeaconState<E>,
+    slot: Slot,
+) -> Option<(Hash256, Hash256)> {
+    if slot == finalized_state.slot {
+        Some((finalized_block_root, finalized_state_root))
+    } else {
+        let block_root = *finalized_state.get_block_root(slot).ok()?;
+        let state_root = *finalized_state.get_state_root(slot).ok()?;
+        Some((block_root, state_root))
+    }
+}
@@ -0,0 +1,75 @@
+use parking_lot::RwLock;
+use std::collections::{HashMap, HashSet};
+use types::Slot;
+
+/// The number of slots prior to the latest observed slot for which aggregators are retained.
+///
+/// Aggregates older than this are unlikely to be propagated, so there is little value in
+/// remembering their aggregators.
+const RETAINED_SLOTS: u64 = 64;
+
+/// Tracks the validators which have produced a valid `AggregateAndProof` for each slot, so that
+/// subsequent aggregates from the same validator may be dropped without verifying their
+/// signatures.
+#[derive(Default)]
+pub struct ObservedAggregators {
+    aggregators: RwLock<HashMap<Slot, HashSet<u64>>>,
+}
+
+impl ObservedAggregators {
+    /// Returns `true` if an aggregate from `aggregator_index` has already been observed for
+    /// `slot`.
+    pub fn is_known(&self, slot: Slot, aggregator_index: u64) -> bool {
+        self.aggregators
+            .read()
+            .get(&slot)
+            .map_or(false, |indices| indices.contains(&aggregator_index))
+    }
+
+    /// Records that a valid aggregate from `aggregator_index` has been observed for `slot`,
+    /// returning `true` if it had already been observed.
+    ///
+    /// Slots that are more than `RETAINED_SLOTS` prior to `slot` are pruned.
+    pub fn observe(&self, slot: Slot, aggregator_index: u64) -> bool {
+        let mut aggregators = self.aggregators.write();
+
+        let already_known = !aggregators
+            .entry(slot)
+            .or_insert_with(HashSet::new)
+            .insert(aggregator_index);
+
+        let lowest_retained = slot.as_u64().saturating_sub(RETAINED_SLOTS);
+        aggregators.retain(|slot, _| slot.as_u64() >= lowest_retained);
+
+        already_known
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn observe_and_prune() {
+        let observed = ObservedAggregators::default();
+        let slot = Slot::new(RETAINED_SLOTS);
+
+        assert!(!observed.is_known(slot, 0));
+        assert!(!observed.observe(slot, 0));
+        assert!(observed.is_known(slot, 0));
+        assert!(observed.observe(slot, 0));
+
+        assert!(!observed.is_known(slot, 1));
+        assert!(!observed.is_known(slot + 1, 0));
+
+        assert!(!observed.observe(Slot::new(0), 0));
+        assert!(observed.is_known(Slot::new(0), 0));
+
+        // Observing a much later slot prunes the earlier ones.
+        let later_slot = Slot::new(RETAINED_SLOTS * 2 + 1);
+        assert!(!observed.observe(later_slot, 0));
+        assert!(!observed.is_known(Slot::new(0), 0));
+        assert!(!observed.is_known(slot, 0));
+        assert!(observed.is_known(later_slot, 0));
+    }
+}
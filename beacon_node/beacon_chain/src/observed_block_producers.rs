@@ -0,0 +1,105 @@
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use types::{Hash256, Slot};
+
+/// The number of slots prior to the latest observed slot for which block producers are retained.
+///
+/// Blocks older than this are unlikely to be published, so there is little value in remembering
+/// their producers.
+const RETAINED_SLOTS: u64 = 64;
+
+/// The result of comparing a block with those previously observed from the same producer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProducerStatus {
+    /// No block has been observed from the producer at the slot.
+    Unseen,
+    /// The same block has already been observed.
+    Duplicate,
+    /// A different block, with the given root, has already been observed from the producer at the
+    /// slot. Publishing another would be a slashable equivocation.
+    Equivocation { existing_block_root: Hash256 },
+}
+
+/// Tracks the root of the block imported from each proposer at each slot, so that a second,
+/// conflicting block from the same proposer may be detected before it is published.
+#[derive(Default)]
+pub struct ObservedBlockProducers {
+    producers: RwLock<HashMap<Slot, HashMap<u64, Hash256>>>,
+}
+
+impl ObservedBlockProducers {
+    /// Compares the block with `block_root` from `proposer_index` at `slot` to any previously
+    /// observed block, without recording it.
+    pub fn status(&self, slot: Slot, proposer_index: u64, block_root: Hash256) -> ProducerStatus {
+        let producers = self.producers.read();
+        let existing = producers
+            .get(&slot)
+            .and_then(|proposers| proposers.get(&proposer_index));
+
+        match existing {
+            None => ProducerStatus::Unseen,
+            Some(root) if *root == block_root => ProducerStatus::Duplicate,
+            Some(root) => ProducerStatus::Equivocation {
+                existing_block_root: *root,
+            },
+        }
+    }
+
+    /// Records the block with `block_root` from `proposer_index` at `slot`, returning its status
+    /// prior to being recorded.
+    ///
+    /// Only the first block from a proposer at a slot is retained. Slots that are more than
+    /// `RETAINED_SLOTS` prior to `slot` are pruned.
+    pub fn observe(&self, slot: Slot, proposer_index: u64, block_root: Hash256) -> ProducerStatus {
+        let status = self.status(slot, proposer_index, block_root);
+
+        let mut producers = self.producers.write();
+
+        producers
+            .entry(slot)
+            .or_insert_with(HashMap::new)
+            .entry(proposer_index)
+            .or_insert(block_root);
+
+        let lowest_retained = slot.as_u64().saturating_sub(RETAINED_SLOTS);
+        producers.retain(|slot, _| slot.as_u64() >= lowest_retained);
+
+        status
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn observe_and_detect_equivocation() {
+        let observed = ObservedBlockProducers::default();
+        let slot = Slot::new(RETAINED_SLOTS);
+        let root_a = Hash256::from_low_u64_be(1);
+        let root_b = Hash256::from_low_u64_be(2);
+
+        assert_eq!(observed.status(slot, 0, root_a), ProducerStatus::Unseen);
+        assert_eq!(observed.observe(slot, 0, root_a), ProducerStatus::Unseen);
+        assert_eq!(observed.observe(slot, 0, root_a), ProducerStatus::Duplicate);
+
+        let equivocation = ProducerStatus::Equivocation {
+            existing_block_root: root_a,
+        };
+        assert_eq!(observed.status(slot, 0, root_b), equivocation);
+        assert_eq!(observed.observe(slot, 0, root_b), equivocation);
+        // The first block is retained.
+        assert_eq!(observed.status(slot, 0, root_a), ProducerStatus::Duplicate);
+
+        assert_eq!(observed.status(slot, 1, root_b), ProducerStatus::Unseen);
+        assert_eq!(observed.status(slot + 1, 0, root_b), ProducerStatus::Unseen);
+
+        // Observing a much later slot prunes the earlier ones.
+        let later_slot = Slot::new(RETAINED_SLOTS * 2 + 1);
+        assert_eq!(
+            observed.observe(later_slot, 0, root_b),
+            ProducerStatus::Unseen
+        );
+        assert_eq!(observed.status(slot, 0, root_b), ProducerStatus::Unseen);
+    }
+}
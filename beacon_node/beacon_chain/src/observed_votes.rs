@@ -0,0 +1,122 @@
+use parking_lot::RwLock;
+use std::collections::{HashMap, HashSet};
+use types::{Epoch, Hash256};
+
+/// The result of comparing a new unaggregated attestation to the votes previously observed from
+/// the same validator.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum VoteStatus {
+    /// No vote has been observed from the validator for this target epoch, or any later epoch.
+    New,
+    /// An identical vote has already been observed.
+    Duplicate,
+    /// A vote for a later target epoch has already been observed. The vote cannot change the
+    /// latest message of the validator.
+    Stale,
+    /// A different vote for the same target epoch has already been observed, the validator has
+    /// double-voted.
+    Equivocation,
+    /// The validator has previously equivocated, all further votes are ignored.
+    Equivocator,
+}
+
+/// Tracks the latest vote seen from each validator via unaggregated attestations, allowing
+/// repeated votes to be dropped before signature verification and equivocating validators to be
+/// excluded from fork choice.
+///
+/// Only a single vote is stored per validator, therefore the size of `Self` is bounded by the size
+/// of the validator registry and it never requires pruning.
+#[derive(Default)]
+pub struct ObservedVotes {
+    /// Maps a validator index to the `(target_epoch, attestation_data_root)` of its latest vote.
+    latest: RwLock<HashMap<usize, (Epoch, Hash256)>>,
+    equivocators: RwLock<HashSet<usize>>,
+}
+
+impl ObservedVotes {
+    /// Returns the status of a vote, without recording it.
+    ///
+    /// Useful for dropping known votes prior to verifying their signature.
+    pub fn check(
+        &self,
+        validator_index: usize,
+        target_epoch: Epoch,
+        data_root: Hash256,
+    ) -> VoteStatus {
+        if self.equivocators.read().contains(&validator_index) {
+            return VoteStatus::Equivocator;
+        }
+
+        match self.latest.read().get(&validator_index) {
+            Some((epoch, root)) if *epoch == target_epoch && *root == data_root => {
+                VoteStatus::Duplicate
+            }
+            Some((epoch, _)) if *epoch == target_epoch => VoteStatus::Equivocation,
+            Some((epoch, _)) if *epoch > target_epoch => VoteStatus::Stale,
+            _ => VoteStatus::New,
+        }
+    }
+
+    /// Records a vote which has been verified, returning its status as per `Self::check`.
+    ///
+    /// A `VoteStatus::Equivocation` marks the validator as an equivocator.
+    pub fn observe(
+        &self,
+        validator_index: usize,
+        target_epoch: Epoch,
+        data_root: Hash256,
+    ) -> VoteStatus {
+        let status = self.check(validator_index, target_epoch, data_root);
+
+        match status {
+            VoteStatus::New => {
+                self.latest
+                    .write()
+                    .insert(validator_index, (target_epoch, data_root));
+            }
+            VoteStatus::Equivocation => {
+                self.equivocators.write().insert(validator_index);
+            }
+            VoteStatus::Duplicate | VoteStatus::Stale | VoteStatus::Equivocator => {}
+        }
+
+        status
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn vote_statuses() {
+        let votes = ObservedVotes::default();
+        let root_a = Hash256::from_low_u64_be(1);
+        let root_b = Hash256::from_low_u64_be(2);
+
+        assert_eq!(votes.check(0, Epoch::new(1), root_a), VoteStatus::New);
+        assert_eq!(votes.check(0, Epoch::new(1), root_a), VoteStatus::New);
+        assert_eq!(votes.observe(0, Epoch::new(1), root_a), VoteStatus::New);
+        assert_eq!(
+            votes.observe(0, Epoch::new(1), root_a),
+            VoteStatus::Duplicate
+        );
+        assert_eq!(votes.observe(0, Epoch::new(0), root_b), VoteStatus::Stale);
+        assert_eq!(votes.observe(0, Epoch::new(2), root_b), VoteStatus::New);
+
+        assert_eq!(
+            votes.observe(0, Epoch::new(2), root_a),
+            VoteStatus::Equivocation
+        );
+        assert_eq!(
+            votes.check(0, Epoch::new(2), root_b),
+            VoteStatus::Equivocator
+        );
+        assert_eq!(
+            votes.observe(0, Epoch::new(3), root_a),
+            VoteStatus::Equivocator
+        );
+
+        assert_eq!(votes.observe(1, Epoch::new(2), root_a), VoteStatus::New);
+    }
+}
@@ -1,21 +1,50 @@
-use crate::{BeaconChainTypes, CheckPoint};
+use crate::eth1_chain::SszEth1;
+use crate::fork_choice::SszForkChoice;
+use crate::head_tracker::SszHeadTracker;
+use crate::CheckPoint;
 use operation_pool::PersistedOperationPool;
 use ssz::{Decode, Encode};
 use ssz_derive::{Decode, Encode};
 use store::{DBColumn, Error as StoreError, StoreItem};
-use types::Hash256;
+use types::{EthSpec, Hash256};
 
 /// 32-byte key for accessing the `PersistedBeaconChain`.
 pub const BEACON_CHAIN_DB_KEY: &str = "PERSISTEDBEACONCHAINPERSISTEDBEA";
+/// 32-byte key for accessing the `GenesisValidatorsRoot`.
+pub const GENESIS_VALIDATORS_ROOT_DB_KEY: &str = "GENESISVALIDATORSROOTGENESISVALI";
 
 #[derive(Encode, Decode)]
-pub struct PersistedBeaconChain<T: BeaconChainTypes> {
-    pub canonical_head: CheckPoint<T::EthSpec>,
-    pub op_pool: PersistedOperationPool<T::EthSpec>,
+pub struct PersistedBeaconChain<E: EthSpec> {
+    pub canonical_head: CheckPoint<E>,
+    pub op_pool: PersistedOperationPool<E>,
     pub genesis_block_root: Hash256,
+    pub ssz_head_tracker: SszHeadTracker,
+    pub ssz_fork_choice: SszForkChoice,
+    pub ssz_eth1: Option<SszEth1>,
 }
 
-impl<T: BeaconChainTypes> StoreItem for PersistedBeaconChain<T> {
+impl<E: EthSpec> StoreItem for PersistedBeaconChain<E> {
+    fn db_column() -> DBColumn {
+        DBColumn::BeaconChain
+    }
+
+    fn as_store_bytes(&self) -> Vec<u8> {
+        self.as_ssz_bytes()
+    }
+
+    fn from_store_bytes(bytes: &mut [u8]) -> Result<Self, StoreError> {
+        Self::from_ssz_bytes(bytes).map_err(Into::into)
+    }
+}
+
+/// The root of the validators of the genesis state, from which fork digests are computed.
+///
+/// Stored separately from the `PersistedBeaconChain` so that databases created before it was
+/// required remain readable.
+#[derive(Encode, Decode)]
+pub struct GenesisValidatorsRoot(pub Hash256);
+
+impl StoreItem for GenesisValidatorsRoot {
     fn db_column() -> DBColumn {
         DBColumn::BeaconChain
     }
@@ -0,0 +1,348 @@
+//! Upgrades the items stored by the beacon chain between versions of the database schema.
+//!
+//! Items belonging to the store itself are upgraded by `HotColdDB::migrate_schema`, which calls
+//! `migrate_schema` for the versions which change an item stored here.
+use crate::fork_choice::SszForkChoice;
+use crate::head_tracker::SszHeadTracker;
+use crate::persisted_beacon_chain::{PersistedBeaconChain, BEACON_CHAIN_DB_KEY};
+use crate::CheckPoint;
+use operation_pool::PersistedOperationPool;
+use ssz::{Decode, Encode};
+use ssz_derive::{Decode, Encode};
+use store::{
+    DBColumn, DiskStore, Error as StoreError, HotColdDBError, SchemaVersion, Store, StoreItem,
+};
+use types::{BeaconBlock, Checkpoint, EthSpec, Hash256};
+
+/// Upgrade the items stored by the beacon chain in `db` from schema version `from` to `to`.
+pub fn migrate_schema<E: EthSpec>(
+    db: &DiskStore,
+    from: SchemaVersion,
+    to: SchemaVersion,
+) -> Result<(), StoreError> {
+    let key = Hash256::from_slice(BEACON_CHAIN_DB_KEY.as_bytes());
+
+    match (from, to) {
+        // The fork choice was previously rebuilt from the head block upon every restart, so
+        // databases created before it was persisted start from a fork choice containing the
+        // canonical chain since finalization.
+        (SchemaVersion(1), SchemaVersion(2)) => {
+            if let Some(p) = db.get::<PersistedBeaconChainV1<E>>(&key)? {
+                let ssz_fork_choice =
+                    fork_choice_from_head(db, &p.canonical_head, p.genesis_block_root)?;
+
+                db.put(
+                    &key,
+                    &PersistedBeaconChainV2 {
+                        canonical_head: p.canonical_head,
+                        op_pool: p.op_pool,
+                        genesis_block_root: p.genesis_block_root,
+                        ssz_head_tracker: p.ssz_head_tracker,
+                        ssz_fork_choice,
+                    },
+                )?;
+            }
+            Ok(())
+        }
+        // Databases created before the eth1 voting period cache was persisted have no cache to
+        // restore, and find the candidates afresh.
+        (SchemaVersion(2), SchemaVersion(3)) => {
+            if let Some(p) = db.get::<PersistedBeaconChainV2<E>>(&key)? {
+                db.put(
+                    &key,
+                    &PersistedBeaconChain {
+                        canonical_head: p.canonical_head,
+                        op_pool: p.op_pool,
+                        genesis_block_root: p.genesis_block_root,
+                        ssz_head_tracker: p.ssz_head_tracker,
+                        ssz_fork_choice: p.ssz_fork_choice,
+                        ssz_eth1: None,
+                    },
+                )?;
+            }
+            Ok(())
+        }
+        _ => Err(HotColdDBError::MissingSchemaMigration { from }.into()),
+    }
+}
+
+/// Returns a fork choice containing the canonical chain from the finalized block of `head` to
+/// `head`, using the justified and finalized checkpoints of the head state.
+///
+/// The blocks and states since finalization are all stored in the hot database, so an error is
+/// only returned if the database is incomplete.
+fn fork_choice_from_head<E: EthSpec>(
+    db: &DiskStore,
+    head: &CheckPoint<E>,
+    genesis_block_root: Hash256,
+) -> Result<SszForkChoice, StoreError> {
+    let resync_error = |e: String| {
+        StoreError::SchemaMigrationError(format!(
+            "Unable to initialise fork choice, please resync the node: {}",
+            e
+        ))
+    };
+    // Resolve the `0x00..00` alias back to genesis.
+    let resolve = |checkpoint: &Checkpoint| Checkpoint {
+        epoch: checkpoint.epoch,
+        root: if checkpoint.root == Hash256::zero() {
+            genesis_block_root
+        } else {
+            checkpoint.root
+        },
+    };
+
+    let head_state = &head.beacon_state;
+    let justified_checkpoint = resolve(&head_state.current_justified_checkpoint);
+    let finalized_checkpoint = resolve(&head_state.finalized_checkpoint);
+
+    let finalized_block = db
+        .get::<BeaconBlock<E>>(&finalized_checkpoint.root)?
+        .ok_or_else(|| resync_error(format!("missing block {:?}", finalized_checkpoint.root)))?;
+
+    // Walk back from the head to the finalized block.
+    let mut blocks = vec![];
+    let mut block_root = head.beacon_block_root;
+    let mut block = head.beacon_block.clone();
+
+    while block_root != finalized_checkpoint.root {
+        if block.slot <= finalized_block.slot {
+            return Err(resync_error(
+                "the finalized block is not an ancestor of the head".to_string(),
+            ));
+        }
+
+        let (justified_epoch, finalized_epoch) = if block_root == head.beacon_block_root {
+            (
+                head_state.current_justified_checkpoint.epoch,
+                head_state.finalized_checkpoint.epoch,
+            )
+        } else {
+            let state = db
+                .get_state::<E>(&block.state_root, Some(block.slot))?
+                .ok_or_else(|| resync_error(format!("missing state {:?}", block.state_root)))?;
+            (
+                state.current_justified_checkpoint.epoch,
+                state.finalized_checkpoint.epoch,
+            )
+        };
+
+        let parent_root = block.parent_root;
+        blocks.push((block_root, block, justified_epoch, finalized_epoch));
+
+        block_root = parent_root;
+        block = db
+            .get::<BeaconBlock<E>>(&block_root)?
+            .ok_or_else(|| resync_error(format!("missing block {:?}", block_root)))?;
+    }
+
+    blocks.reverse();
+
+    SszForkChoice::from_canonical_chain(
+        genesis_block_root,
+        justified_checkpoint,
+        finalized_checkpoint,
+        &finalized_block,
+        &blocks,
+    )
+    .map_err(|e| resync_error(format!("{:?}", e)))
+}
+
+/// Implements `StoreItem` for a prior layout of the `PersistedBeaconChain`.
+macro_rules! impl_persisted_beacon_chain {
+    ($name: ident) => {
+        impl<E: EthSpec> StoreItem for $name<E> {
+            fn db_column() -> DBColumn {
+                DBColumn::BeaconChain
+            }
+
+            fn as_store_bytes(&self) -> Vec<u8> {
+                self.as_ssz_bytes()
+            }
+
+            fn from_store_bytes(bytes: &mut [u8]) -> Result<Self, StoreError> {
+                Self::from_ssz_bytes(bytes).map_err(Into::into)
+            }
+        }
+    };
+}
+
+/// The `PersistedBeaconChain` of schema version 1.
+#[derive(Encode, Decode)]
+struct PersistedBeaconChainV1<E: EthSpec> {
+    canonical_head: CheckPoint<E>,
+    op_pool: PersistedOperationPool<E>,
+    genesis_block_root: Hash256,
+    ssz_head_tracker: SszHeadTracker,
+}
+
+impl_persisted_beacon_chain!(PersistedBeaconChainV1);
+
+/// The `PersistedBeaconChain` of schema version 2.
+#[derive(Encode, Decode)]
+struct PersistedBeaconChainV2<E: EthSpec> {
+    canonical_head: CheckPoint<E>,
+    op_pool: PersistedOperationPool<E>,
+    genesis_block_root: Hash256,
+    ssz_head_tracker: SszHeadTracker,
+    ssz_fork_choice: SszForkChoice,
+}
+
+impl_persisted_beacon_chain!(PersistedBeaconChainV2);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{
+        generate_deterministic_keypairs, AttestationStrategy, BeaconChainHarness, BlockStrategy,
+        DiskHarnessType,
+    };
+    use crate::ForkChoice;
+    use std::sync::Arc;
+    use store::{StoreConfig, CURRENT_SCHEMA_VERSION, SCHEMA_VERSION_KEY};
+    use tempfile::tempdir;
+    use types::MinimalEthSpec;
+
+    type E = MinimalEthSpec;
+
+    #[test]
+    fn v1_fork_choice_uses_head_state_checkpoints() {
+        let db_dir = tempdir().unwrap();
+        let store = Arc::new(
+            DiskStore::open(
+                &db_dir.path().join("hot_db"),
+                &db_dir.path().join("cold_db"),
+                StoreConfig::default(),
+                E::default_spec(),
+                migrate_schema::<E>,
+            )
+            .expect("should open store"),
+        );
+        let harness = BeaconChainHarness::new_with_disk_store(
+            MinimalEthSpec,
+            store.clone(),
+            generate_deterministic_keypairs(8),
+        );
+        harness.extend_chain(
+            E::slots_per_epoch() as usize * 4,
+            BlockStrategy::OnCanonicalHead,
+            AttestationStrategy::AllValidators,
+        );
+        let head = harness.chain.head();
+
+        let key = Hash256::from_slice(BEACON_CHAIN_DB_KEY.as_bytes());
+        store
+            .put(
+                &key,
+                &PersistedBeaconChainV1 {
+                    canonical_head: head.clone(),
+                    op_pool: PersistedOperationPool::from_operation_pool(&harness.chain.op_pool),
+                    genesis_block_root: harness.chain.genesis_block_root,
+                    ssz_head_tracker: harness.chain.head_tracker.to_ssz_container(),
+                },
+            )
+            .unwrap();
+
+        migrate_schema::<E>(&store, SchemaVersion(1), SchemaVersion(2))
+            .expect("should migrate to version 2");
+
+        let p = store
+            .get::<PersistedBeaconChainV2<E>>(&key)
+            .unwrap()
+            .expect("should store version 2 chain");
+        let fork_choice =
+            ForkChoice::<DiskHarnessType<E>>::from_ssz_container(p.ssz_fork_choice).unwrap();
+
+        let justified_checkpoint = &head.beacon_state.current_justified_checkpoint;
+        assert!(
+            justified_checkpoint.epoch > E::genesis_epoch(),
+            "the chain should have justified an epoch"
+        );
+
+        assert_eq!(p.canonical_head, head);
+        assert!(fork_choice.contains_block(&head.beacon_block_root));
+        assert!(fork_choice.contains_block(&justified_checkpoint.root));
+        assert_eq!(fork_choice.justified_checkpoint(), *justified_checkpoint);
+    }
+
+    #[test]
+    fn resumes_from_v1_database() {
+        let db_dir = tempdir().unwrap();
+        let open_store = || {
+            Arc::new(
+                DiskStore::open(
+                    &db_dir.path().join("hot_db"),
+                    &db_dir.path().join("cold_db"),
+                    StoreConfig::default(),
+                    E::default_spec(),
+                    migrate_schema::<E>,
+                )
+                .expect("should open store"),
+            )
+        };
+        let keypairs = generate_deterministic_keypairs(8);
+
+        let head = {
+            let store = open_store();
+            let harness = BeaconChainHarness::new_with_disk_store(
+                MinimalEthSpec,
+                store.clone(),
+                keypairs.clone(),
+            );
+            harness.extend_chain(
+                E::slots_per_epoch() as usize * 4,
+                BlockStrategy::OnCanonicalHead,
+                AttestationStrategy::AllValidators,
+            );
+            let head = harness.chain.head();
+
+            // Rewrite the chain as it was stored by schema version 1.
+            store
+                .put(
+                    &Hash256::from_slice(BEACON_CHAIN_DB_KEY.as_bytes()),
+                    &PersistedBeaconChainV1 {
+                        canonical_head: head.clone(),
+                        op_pool: PersistedOperationPool::from_operation_pool(
+                            &harness.chain.op_pool,
+                        ),
+                        genesis_block_root: harness.chain.genesis_block_root,
+                        ssz_head_tracker: harness.chain.head_tracker.to_ssz_container(),
+                    },
+                )
+                .unwrap();
+            store
+                .put(
+                    &Hash256::from_slice(SCHEMA_VERSION_KEY.as_bytes()),
+                    &SchemaVersion(1),
+                )
+                .unwrap();
+
+            head
+        };
+
+        let store = open_store();
+        assert_eq!(store.load_schema_version(), Ok(CURRENT_SCHEMA_VERSION));
+
+        let harness = BeaconChainHarness::resume_from_disk_store(MinimalEthSpec, store, keypairs);
+        assert_eq!(
+            harness.chain.head().beacon_block_root,
+            head.beacon_block_root,
+            "should resume the head of the migrated chain"
+        );
+
+        // Blocks imported after the migration must be viable heads.
+        let num_blocks = E::slots_per_epoch() as usize * 2;
+        harness.advance_slot();
+        harness.extend_chain(
+            num_blocks,
+            BlockStrategy::OnCanonicalHead,
+            AttestationStrategy::AllValidators,
+        );
+
+        assert_eq!(
+            harness.chain.head().beacon_block.slot,
+            head.beacon_block.slot + num_blocks as u64,
+            "the head should advance after the migration"
+        );
+    }
+}
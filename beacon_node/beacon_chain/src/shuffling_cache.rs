@@ -0,0 +1,153 @@
+use lru::LruCache;
+use std::sync::Arc;
+use types::{CommitteeCache, Epoch, EthSpec, Hash256, Slot};
+
+/// The maximum number of epochs to retain, for each of the attester and proposer caches.
+///
+/// Sufficient for the previous, current and next epochs across a handful of competing forks.
+const CACHE_SIZE: usize = 16;
+
+/// The attester shuffling and block proposers of an epoch, along with the roots of the blocks upon
+/// which they depend.
+pub struct EpochShufflings {
+    pub attesters: Arc<CommitteeCache>,
+    pub attester_dependent_root: Hash256,
+    pub proposers: Vec<usize>,
+    pub proposer_dependent_root: Hash256,
+}
+
+/// Caches the attester shufflings and block proposers of recent epochs, so that validator duties
+/// may be served without loading (and possibly advancing) a `BeaconState` for each request.
+///
+/// Entries are keyed by the epoch and its "dependent root"; the root of the last block which can
+/// influence the shuffling. Chains which share a dependent root share the shuffling, whilst a
+/// re-org which changes the dependent root results in a cache miss.
+pub struct ShufflingCache {
+    attesters: LruCache<(Epoch, Hash256), Arc<CommitteeCache>>,
+    proposers: LruCache<(Epoch, Hash256), Vec<usize>>,
+}
+
+impl ShufflingCache {
+    pub fn new() -> Self {
+        Self {
+            attesters: LruCache::new(CACHE_SIZE),
+            proposers: LruCache::new(CACHE_SIZE),
+        }
+    }
+
+    /// Returns the attester shuffling of `epoch`, if it is known for `dependent_root`.
+    pub fn get_attesters(
+        &mut self,
+        epoch: Epoch,
+        dependent_root: Hash256,
+    ) -> Option<Arc<CommitteeCache>> {
+        self.attesters.get(&(epoch, dependent_root)).cloned()
+    }
+
+    /// Returns the proposer index of each slot in `epoch`, if it is known for `dependent_root`.
+    pub fn get_proposers(&mut self, epoch: Epoch, dependent_root: Hash256) -> Option<Vec<usize>> {
+        self.proposers.get(&(epoch, dependent_root)).cloned()
+    }
+
+    pub fn insert_attesters(
+        &mut self,
+        epoch: Epoch,
+        dependent_root: Hash256,
+        committee_cache: Arc<CommitteeCache>,
+    ) {
+        self.attesters.put((epoch, dependent_root), committee_cache);
+    }
+
+    pub fn insert_proposers(
+        &mut self,
+        epoch: Epoch,
+        dependent_root: Hash256,
+        proposers: Vec<usize>,
+    ) {
+        self.proposers.put((epoch, dependent_root), proposers);
+    }
+
+    /// Removes all attester shufflings and block proposers from the cache.
+    pub fn clear(&mut self) {
+        self.attesters.clear();
+        self.proposers.clear();
+    }
+}
+
+impl Default for ShufflingCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the slot of the block upon which the attester shuffling of `epoch` depends, or `None` if
+/// it depends upon the genesis block.
+///
+/// The shuffling is seeded from the RANDAO mix at the end of `epoch - 2`, so it is fixed by the
+/// last block in that epoch (or its most recent ancestor, if that slot was skipped).
+pub fn attester_dependent_slot<E: EthSpec>(epoch: Epoch) -> Option<Slot> {
+    proposer_dependent_slot::<E>(epoch.saturating_sub(1u64))
+}
+
+/// Returns the slot of the block upon which the block proposers of `epoch` depend, or `None` if
+/// they depend upon the genesis block.
+///
+/// Proposers are selected using the effective balances at the start of `epoch`, so they are fixed
+/// by the last block of the prior epoch (or its most recent ancestor, if that slot was skipped).
+pub fn proposer_dependent_slot<E: EthSpec>(epoch: Epoch) -> Option<Slot> {
+    let start_slot = epoch.start_slot(E::slots_per_epoch());
+
+    if start_slot == Slot::new(0) {
+        None
+    } else {
+        Some(start_slot - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::MinimalEthSpec;
+
+    type E = MinimalEthSpec;
+
+    #[test]
+    fn dependent_slots() {
+        let slots_per_epoch = E::slots_per_epoch();
+
+        assert_eq!(proposer_dependent_slot::<E>(Epoch::new(0)), None);
+        assert_eq!(
+            proposer_dependent_slot::<E>(Epoch::new(1)),
+            Some(Slot::new(slots_per_epoch - 1))
+        );
+
+        assert_eq!(attester_dependent_slot::<E>(Epoch::new(0)), None);
+        assert_eq!(attester_dependent_slot::<E>(Epoch::new(1)), None);
+        assert_eq!(
+            attester_dependent_slot::<E>(Epoch::new(2)),
+            Some(Slot::new(slots_per_epoch - 1))
+        );
+        assert_eq!(
+            attester_dependent_slot::<E>(Epoch::new(3)),
+            proposer_dependent_slot::<E>(Epoch::new(2))
+        );
+    }
+
+    #[test]
+    fn keyed_by_dependent_root() {
+        let mut cache = ShufflingCache::new();
+        let epoch = Epoch::new(1);
+        let root_a = Hash256::from_low_u64_be(1);
+        let root_b = Hash256::from_low_u64_be(2);
+
+        cache.insert_proposers(epoch, root_a, vec![1, 2, 3]);
+        cache.insert_attesters(epoch, root_a, Arc::new(CommitteeCache::default()));
+
+        assert_eq!(cache.get_proposers(epoch, root_a), Some(vec![1, 2, 3]));
+        assert!(cache.get_attesters(epoch, root_a).is_some());
+
+        assert_eq!(cache.get_proposers(epoch, root_b), None);
+        assert!(cache.get_attesters(epoch, root_b).is_none());
+        assert_eq!(cache.get_proposers(epoch + 1, root_a), None);
+    }
+}
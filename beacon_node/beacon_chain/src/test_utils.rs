@@ -2,17 +2,19 @@ use crate::{
     builder::{BeaconChainBuilder, Witness},
     eth1_chain::CachingEth1Backend,
     events::NullEventHandler,
+    migrate::{BlockingMigrator, Migrate},
     AttestationProcessingOutcome, BeaconChain, BeaconChainTypes, BlockProcessingOutcome,
 };
 use genesis::interop_genesis_state;
-use lmd_ghost::ThreadSafeReducedTree;
 use rayon::prelude::*;
+use resource_budget::ResourceBudget;
+use slog::Logger;
 use sloggers::{terminal::TerminalLoggerBuilder, types::Severity, Build};
 use slot_clock::TestingSlotClock;
 use state_processing::per_slot_processing;
 use std::sync::Arc;
 use std::time::Duration;
-use store::MemoryStore;
+use store::{DiskStore, MemoryStore, Store};
 use tree_hash::{SignedRoot, TreeHash};
 use types::{
     AggregateSignature, Attestation, BeaconBlock, BeaconState, BitList, ChainSpec, Domain, EthSpec,
@@ -24,15 +26,23 @@ pub use types::test_utils::generate_deterministic_keypairs;
 
 pub const HARNESS_GENESIS_TIME: u64 = 1_567_552_690; // 4th September 2019
 
-pub type HarnessType<E> = Witness<
-    MemoryStore,
+/// The `BeaconChainTypes` of a harness, generic over the store and its migrator.
+pub type BaseHarnessType<TStore, TStoreMigrator, TEthSpec> = Witness<
+    TStore,
+    TStoreMigrator,
     TestingSlotClock,
-    ThreadSafeReducedTree<MemoryStore, E>,
-    CachingEth1Backend<E, MemoryStore>,
-    E,
-    NullEventHandler<E>,
+    CachingEth1Backend<TEthSpec, TStore>,
+    TEthSpec,
+    NullEventHandler<TEthSpec>,
 >;
 
+/// A harness backed by an in-memory store.
+pub type HarnessType<E> = BaseHarnessType<MemoryStore, BlockingMigrator<MemoryStore>, E>;
+
+/// A harness backed by an on-disk store, for tests which need the freezer database or to restart
+/// the chain.
+pub type DiskHarnessType<E> = BaseHarnessType<DiskStore, BlockingMigrator<DiskStore>, E>;
+
 /// Indicates how the `BeaconChainHarness` should produce blocks.
 #[derive(Clone, Copy, Debug)]
 pub enum BlockStrategy {
@@ -71,17 +81,78 @@ pub struct BeaconChainHarness<T: BeaconChainTypes> {
 impl<E: EthSpec> BeaconChainHarness<HarnessType<E>> {
     /// Instantiate a new harness with `validator_count` initial validators.
     pub fn new(eth_spec_instance: E, keypairs: Vec<Keypair>) -> Self {
+        Self::from_genesis(eth_spec_instance, Arc::new(MemoryStore::open()), keypairs)
+    }
+}
+
+impl<E: EthSpec> BeaconChainHarness<DiskHarnessType<E>> {
+    /// Instantiate a new harness with `validator_count` initial validators, storing the chain in
+    /// the empty on-disk `store`.
+    pub fn new_with_disk_store(
+        eth_spec_instance: E,
+        store: Arc<DiskStore>,
+        keypairs: Vec<Keypair>,
+    ) -> Self {
+        Self::from_genesis(eth_spec_instance, store, keypairs)
+    }
+
+    /// Instantiate a harness which resumes the chain persisted in the on-disk `store` (e.g., by a
+    /// previous harness), with the slot clock set to the slot of the head.
+    pub fn resume_from_disk_store(
+        eth_spec_instance: E,
+        store: Arc<DiskStore>,
+        keypairs: Vec<Keypair>,
+    ) -> Self {
         let spec = E::default_spec();
+        let log = test_logger();
 
-        let log = TerminalLoggerBuilder::new()
-            .level(Severity::Warning)
+        let chain = BeaconChainBuilder::new(eth_spec_instance)
+            .logger(log.clone())
+            .custom_spec(spec)
+            .store(store.clone())
+            .store_migrator(<BlockingMigrator<_> as Migrate<_, E>>::new(
+                store,
+                log,
+                ResourceBudget::default(),
+            ))
+            .resume_from_db()
+            .expect("should resume beacon chain from db")
+            .dummy_eth1_backend()
+            .expect("should build dummy backend")
+            .null_event_handler()
+            .testing_slot_clock(Duration::from_secs(1))
+            .expect("should configure testing slot clock")
             .build()
-            .expect("logger should build");
+            .expect("should build");
+
+        chain
+            .slot_clock
+            .set_slot(chain.head().beacon_block.slot.as_u64());
+
+        Self {
+            spec: chain.spec.clone(),
+            chain,
+            keypairs,
+        }
+    }
+}
+
+impl<S, M, E> BeaconChainHarness<BaseHarnessType<S, M, E>>
+where
+    S: Store + 'static,
+    M: Migrate<S, E> + 'static,
+    E: EthSpec,
+{
+    /// Instantiate a new harness, starting the chain from an interop genesis state of `keypairs`.
+    fn from_genesis(eth_spec_instance: E, store: Arc<S>, keypairs: Vec<Keypair>) -> Self {
+        let spec = E::default_spec();
+        let log = test_logger();
 
         let chain = BeaconChainBuilder::new(eth_spec_instance)
             .logger(log.clone())
             .custom_spec(spec.clone())
-            .store(Arc::new(MemoryStore::open()))
+            .store(store.clone())
+            .store_migrator(M::new(store, log.clone(), ResourceBudget::default()))
             .genesis_state(
                 interop_genesis_state::<E>(&keypairs, HARNESS_GENESIS_TIME, &spec)
                     .expect("should generate interop state"),
@@ -92,8 +163,6 @@ impl<E: EthSpec> BeaconChainHarness<HarnessType<E>> {
             .null_event_handler()
             .testing_slot_clock(Duration::from_secs(1))
             .expect("should configure testing slot clock")
-            .empty_reduced_tree_fork_choice()
-            .expect("should add fork choice to builder")
             .build()
             .expect("should build");
 
@@ -177,7 +246,7 @@ impl<E: EthSpec> BeaconChainHarness<HarnessType<E>> {
     }
 
     /// Returns a newly created block, signed by the proposer for the given slot.
-    fn build_block(
+    pub fn build_block(
         &self,
         mut state: BeaconState<E>,
         slot: Slot,
@@ -396,3 +465,11 @@ impl<E: EthSpec> BeaconChainHarness<HarnessType<E>> {
         &self.keypairs[validator_index].sk
     }
 }
+
+/// Returns a logger which only emits warnings and above, to keep test output readable.
+fn test_logger() -> Logger {
+    TerminalLoggerBuilder::new()
+        .level(Severity::Warning)
+        .build()
+        .expect("logger should build")
+}
@@ -0,0 +1,179 @@
+use crate::errors::BeaconChainError;
+use serde_derive::{Deserialize, Serialize};
+use state_processing::per_epoch_processing::validator_statuses::ValidatorStatuses;
+use types::{BeaconState, ChainSpec, Epoch, EthSpec, RelativeEpoch};
+
+/// The participation of a single validator in some epoch, as recorded in the `BeaconState` when
+/// that epoch is processed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidatorInclusion {
+    /// The epoch to which this record applies.
+    pub epoch: Epoch,
+    pub validator_index: usize,
+    /// True if the validator was active during `epoch`.
+    pub is_active: bool,
+    pub is_slashed: bool,
+    /// True if an attestation from the validator was included on-chain. Only attestations with the
+    /// correct source can be included, so this also indicates a correct source vote.
+    pub is_source_attester: bool,
+    /// True if an included attestation voted for the correct target (epoch boundary) block.
+    pub is_target_attester: bool,
+    /// True if an included attestation voted for the correct head block.
+    pub is_head_attester: bool,
+    /// The number of slots between the attestation slot and the slot of the block that included
+    /// it, for the earliest-included attestation. `None` if no attestation was included.
+    pub inclusion_delay: Option<u64>,
+    /// The balance of the validator at the end of the epoch following `epoch`, prior to the
+    /// rewards and penalties for `epoch`.
+    pub balance: u64,
+}
+
+/// The participation of all validators in some epoch, summed across the validator registry.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct EpochInclusionSummary {
+    /// The epoch to which this summary applies.
+    pub epoch: Epoch,
+    /// The count of validators active during `epoch`.
+    pub active_validators: u64,
+    /// The total effective balance of validators active during `epoch`.
+    pub active_gwei: u64,
+    /// The total effective balance of unslashed validators with an included attestation.
+    pub source_attesting_gwei: u64,
+    /// The total effective balance of unslashed validators that voted for the correct target.
+    pub target_attesting_gwei: u64,
+    /// The total effective balance of unslashed validators that voted for the correct head.
+    pub head_attesting_gwei: u64,
+    /// The count of validators with an included attestation.
+    pub included_attesters: u64,
+    /// The sum of the inclusion delays of all `included_attesters`.
+    ///
+    /// Divide by `included_attesters` to find the mean inclusion delay.
+    pub total_inclusion_delay: u64,
+}
+
+/// Describes the participation of each validator in the _previous_ epoch of `state`.
+///
+/// The `state` should be at the last slot of the epoch following the epoch of interest, prior to
+/// epoch processing. Only then has every attestation for the previous epoch had a chance to be
+/// included. Any earlier state will produce a partial result.
+pub fn previous_epoch_inclusion<T: EthSpec>(
+    state: &mut BeaconState<T>,
+    spec: &ChainSpec,
+) -> Result<(EpochInclusionSummary, Vec<ValidatorInclusion>), BeaconChainError> {
+    state.build_committee_cache(RelativeEpoch::Previous, spec)?;
+    state.build_committee_cache(RelativeEpoch::Current, spec)?;
+
+    let mut validator_statuses = ValidatorStatuses::new(state, spec)?;
+    validator_statuses.process_attestations(state, spec)?;
+
+    let epoch = state.previous_epoch();
+    let summary = epoch_inclusion_summary(epoch, &validator_statuses);
+
+    let validators = validator_statuses
+        .statuses
+        .iter()
+        .enumerate()
+        .map(|(validator_index, status)| ValidatorInclusion {
+            epoch,
+            validator_index,
+            is_active: status.is_active_in_previous_epoch,
+            is_slashed: status.is_slashed,
+            is_source_attester: status.is_previous_epoch_attester,
+            is_target_attester: status.is_previous_epoch_target_attester,
+            is_head_attester: status.is_previous_epoch_head_attester,
+            inclusion_delay: status.inclusion_info.map(|info| info.delay),
+            balance: state.balances.get(validator_index).copied().unwrap_or(0),
+        })
+        .collect();
+
+    Ok((summary, validators))
+}
+
+/// Sums the participation of all validators in `epoch`, given the `validator_statuses` computed
+/// when that epoch was the previous epoch (e.g., those returned by `per_slot_processing`).
+pub fn epoch_inclusion_summary(
+    epoch: Epoch,
+    validator_statuses: &ValidatorStatuses,
+) -> EpochInclusionSummary {
+    let balances = &validator_statuses.total_balances;
+
+    let mut summary = EpochInclusionSummary {
+        epoch,
+        active_gwei: balances.previous_epoch,
+        source_attesting_gwei: balances.previous_epoch_attesters,
+        target_attesting_gwei: balances.previous_epoch_target_attesters,
+        head_attesting_gwei: balances.previous_epoch_head_attesters,
+        ..EpochInclusionSummary::default()
+    };
+
+    for status in &validator_statuses.statuses {
+        if status.is_active_in_previous_epoch {
+            summary.active_validators += 1;
+        }
+        if let Some(info) = status.inclusion_info {
+            summary.included_attesters += 1;
+            summary.total_inclusion_delay += info.delay;
+        }
+    }
+
+    summary
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use state_processing::per_slot_processing;
+    use types::{test_utils::TestingBeaconStateBuilder, MinimalEthSpec, Slot};
+
+    #[test]
+    fn no_attestations() {
+        let spec = MinimalEthSpec::default_spec();
+        let builder = TestingBeaconStateBuilder::from_deterministic_keypairs(8, &spec);
+        let (mut state, _keypairs) = builder.build();
+
+        let (summary, validators) =
+            previous_epoch_inclusion(&mut state, &spec).expect("should compute inclusion");
+
+        assert_eq!(summary.epoch, state.previous_epoch());
+        assert_eq!(summary.active_validators, 8);
+        assert_eq!(summary.active_gwei, 8 * spec.max_effective_balance);
+        assert_eq!(summary.source_attesting_gwei, 0);
+        assert_eq!(summary.included_attesters, 0);
+        assert_eq!(summary.total_inclusion_delay, 0);
+
+        assert_eq!(validators.len(), 8);
+        for (i, validator) in validators.iter().enumerate() {
+            assert_eq!(validator.validator_index, i);
+            assert!(validator.is_active);
+            assert!(!validator.is_source_attester);
+            assert_eq!(validator.inclusion_delay, None);
+            assert_eq!(validator.balance, spec.max_effective_balance);
+        }
+    }
+
+    #[test]
+    fn summary_from_epoch_processing() {
+        let spec = MinimalEthSpec::default_spec();
+        let slots_per_epoch = MinimalEthSpec::slots_per_epoch();
+        let mut builder = TestingBeaconStateBuilder::from_deterministic_keypairs(8, &spec);
+        builder.teleport_to_slot(Slot::new(4 * slots_per_epoch - 2));
+        let (mut state, _keypairs) = builder.build();
+        state.build_all_caches(&spec).expect("should build caches");
+
+        // Epoch processing is not performed prior to the last slot of the epoch.
+        assert!(per_slot_processing(&mut state, &spec)
+            .expect("should process slot")
+            .is_none());
+
+        let (expected, _validators) =
+            previous_epoch_inclusion(&mut state.clone(), &spec).expect("should compute inclusion");
+        let validator_statuses = per_slot_processing(&mut state, &spec)
+            .expect("should process slot")
+            .expect("should perform epoch processing");
+
+        assert_eq!(
+            epoch_inclusion_summary(expected.epoch, &validator_statuses),
+            expected
+        );
+    }
+}
@@ -0,0 +1,429 @@
+use crate::errors::BeaconChainError;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use types::{BeaconState, EthSpec, PublicKey};
+
+/// Provides a mapping of `validator_index -> validator_publickey`, where the public keys are
+/// stored in their decompressed form.
+///
+/// Decompressing a BLS public key is expensive, so the decompressed keys are written to a file
+/// (typically in the beacon node datadir) and read back at startup, instead of being decompressed
+/// again. Since the validator registry is append-only, the index of a public key never changes
+/// and new keys can simply be appended to the end of the file.
+pub struct ValidatorPubkeyCache {
+    pubkeys: Vec<PublicKey>,
+    indices: HashMap<PublicKey, usize>,
+    backing: PubkeyCacheFile,
+}
+
+impl ValidatorPubkeyCache {
+    /// Create a new, empty cache that persists its keys to `path`.
+    ///
+    /// Any existing file at `path` is truncated.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, BeaconChainError> {
+        Ok(Self {
+            pubkeys: vec![],
+            indices: HashMap::new(),
+            backing: PubkeyCacheFile::create(path.as_ref())?,
+        })
+    }
+
+    /// Create a new, empty cache that is not persisted to disk.
+    pub fn in_memory() -> Self {
+        Self {
+            pubkeys: vec![],
+            indices: HashMap::new(),
+            backing: PubkeyCacheFile::none(),
+        }
+    }
+
+    /// Load the cache from the file at `path`, without reference to any `BeaconState`.
+    ///
+    /// The loaded keys are registered with `PublicKey::register_known_keys`, so that the keys of
+    /// any state decoded afterwards (e.g., the head state at startup) are not decompressed again.
+    /// The cache must then be brought up to date with `Self::sync_with_state`.
+    ///
+    /// If the file does not exist or cannot be read, a new, empty file is created.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, BeaconChainError> {
+        let path = path.as_ref();
+
+        let cache = if path.exists() {
+            PubkeyCacheFile::open(path)
+                .and_then(|(pubkeys, backing)| Self::from_pubkeys(pubkeys, backing))
+                .ok()
+        } else {
+            None
+        };
+
+        match cache {
+            Some(cache) => {
+                PublicKey::register_known_keys(&cache.pubkeys);
+                Ok(cache)
+            }
+            None => Self::create(path),
+        }
+    }
+
+    /// Add any keys in `state` that are missing from `self`.
+    ///
+    /// If `self` does not agree with `state`, the cache is rebuilt from `state` (and its file, if
+    /// any, is replaced).
+    pub fn sync_with_state<T: EthSpec>(
+        &mut self,
+        state: &BeaconState<T>,
+    ) -> Result<(), BeaconChainError> {
+        if !self.is_consistent_with(state) {
+            *self = match self.backing.path.clone() {
+                Some(path) => Self::create(path)?,
+                None => Self::in_memory(),
+            };
+        }
+
+        self.import_new_pubkeys(state)
+    }
+
+    /// Load the cache from the file at `path`, then add any keys in `state` that are missing from
+    /// the file.
+    ///
+    /// If the file does not exist, or if its contents do not agree with `state`, the cache is
+    /// rebuilt from `state` and the file is replaced.
+    pub fn load_from_file<P: AsRef<Path>, T: EthSpec>(
+        path: P,
+        state: &BeaconState<T>,
+    ) -> Result<Self, BeaconChainError> {
+        let mut cache = Self::open(path)?;
+        cache.sync_with_state(state)?;
+
+        Ok(cache)
+    }
+
+    fn from_pubkeys(
+        pubkeys: Vec<PublicKey>,
+        backing: PubkeyCacheFile,
+    ) -> Result<Self, BeaconChainError> {
+        let indices = pubkeys
+            .iter()
+            .enumerate()
+            .map(|(i, pubkey)| (pubkey.clone(), i))
+            .collect::<HashMap<_, _>>();
+
+        if indices.len() != pubkeys.len() {
+            return Err(BeaconChainError::ValidatorPubkeyCacheError(
+                "Duplicate public key in cache file".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            pubkeys,
+            indices,
+            backing,
+        })
+    }
+
+    /// Returns `true` if the last key in `self` matches the key at the same index in `state`.
+    ///
+    /// Only the last key is compared, since the keys are always appended in registry order.
+    fn is_consistent_with<T: EthSpec>(&self, state: &BeaconState<T>) -> bool {
+        match self.pubkeys.last() {
+            Some(pubkey) => state
+                .validators
+                .get(self.pubkeys.len() - 1)
+                .map_or(false, |validator| validator.pubkey == *pubkey),
+            None => true,
+        }
+    }
+
+    /// Scan the given `state` and add any new validator public keys.
+    ///
+    /// Does not delete any keys from `self` if they don't appear in `state`.
+    pub fn import_new_pubkeys<T: EthSpec>(
+        &mut self,
+        state: &BeaconState<T>,
+    ) -> Result<(), BeaconChainError> {
+        for validator in state.validators.iter().skip(self.pubkeys.len()) {
+            let i = self.pubkeys.len();
+
+            if self.indices.contains_key(&validator.pubkey) {
+                return Err(BeaconChainError::DuplicateValidatorPublicKey);
+            }
+
+            self.backing.append(&validator.pubkey)?;
+
+            self.pubkeys.push(validator.pubkey.clone());
+            self.indices.insert(validator.pubkey.clone(), i);
+        }
+
+        Ok(())
+    }
+
+    /// Get the public key for a validator with index `i`.
+    pub fn get(&self, i: usize) -> Option<&PublicKey> {
+        self.pubkeys.get(i)
+    }
+
+    /// Get the index of a validator with `pubkey`.
+    pub fn get_index(&self, pubkey: &PublicKey) -> Option<usize> {
+        self.indices.get(pubkey).copied()
+    }
+
+    /// Returns the number of validators in the cache.
+    pub fn len(&self) -> usize {
+        self.pubkeys.len()
+    }
+
+    /// Returns `true` if there are no validators in the cache.
+    pub fn is_empty(&self) -> bool {
+        self.pubkeys.is_empty()
+    }
+}
+
+/// The file that backs a `ValidatorPubkeyCache`.
+///
+/// The file is a concatenation of the uncompressed bytes of each public key, in order of
+/// validator index.
+struct PubkeyCacheFile {
+    file: Option<File>,
+    path: Option<PathBuf>,
+}
+
+impl PubkeyCacheFile {
+    fn none() -> Self {
+        Self {
+            file: None,
+            path: None,
+        }
+    }
+
+    fn create(path: &Path) -> Result<Self, BeaconChainError> {
+        let file = File::create(path).map_err(|e| file_error("create", path, e))?;
+
+        Ok(Self {
+            file: Some(file),
+            path: Some(path.to_path_buf()),
+        })
+    }
+
+    /// Opens the file at `path`, returning all the keys in it.
+    ///
+    /// A trailing, partially-written key (e.g., due to an unclean shutdown) is discarded.
+    fn open(path: &Path) -> Result<(Vec<PublicKey>, Self), BeaconChainError> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(|e| file_error("open", path, e))?;
+
+        let mut bytes = vec![];
+        file.read_to_end(&mut bytes)
+            .map_err(|e| file_error("read", path, e))?;
+
+        let key_len = uncompressed_key_len();
+        let complete_len = bytes.len() - bytes.len() % key_len;
+
+        let pubkeys = bytes[0..complete_len]
+            .chunks(key_len)
+            .map(|chunk| {
+                PublicKey::from_uncompressed_bytes(chunk).map_err(|e| {
+                    BeaconChainError::ValidatorPubkeyCacheError(format!(
+                        "Invalid public key in {:?}: {:?}",
+                        path, e
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if complete_len != bytes.len() {
+            file.set_len(complete_len as u64)
+                .and_then(|()| file.seek(SeekFrom::Start(complete_len as u64)))
+                .map_err(|e| file_error("truncate", path, e))?;
+        }
+
+        Ok((
+            pubkeys,
+            Self {
+                file: Some(file),
+                path: Some(path.to_path_buf()),
+            },
+        ))
+    }
+
+    fn append(&mut self, pubkey: &PublicKey) -> Result<(), BeaconChainError> {
+        if let (Some(file), Some(path)) = (self.file.as_mut(), self.path.as_ref()) {
+            // The cursor is always left at the end of the file by `Self::open`.
+            file.write_all(&pubkey.as_uncompressed_bytes())
+                .map_err(|e| file_error("write", path, e))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the length of an uncompressed public key, as written to a `PubkeyCacheFile`.
+fn uncompressed_key_len() -> usize {
+    PublicKey::default().as_uncompressed_bytes().len()
+}
+
+fn file_error(action: &str, path: &Path, e: io::Error) -> BeaconChainError {
+    BeaconChainError::ValidatorPubkeyCacheError(format!(
+        "Unable to {} pubkey cache file {:?}: {:?}",
+        action, path, e
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ssz::{Decode, Encode};
+    use tempfile::tempdir;
+    use types::{
+        test_utils::TestingBeaconStateBuilder, BeaconState, EthSpec, Keypair, MinimalEthSpec,
+    };
+
+    fn get_state(validator_count: usize) -> (BeaconState<MinimalEthSpec>, Vec<Keypair>) {
+        let spec = MinimalEthSpec::default_spec();
+        let builder =
+            TestingBeaconStateBuilder::from_deterministic_keypairs(validator_count, &spec);
+        builder.build()
+    }
+
+    fn check_cache_get(cache: &ValidatorPubkeyCache, keypairs: &[Keypair]) {
+        let validator_count = keypairs.len();
+
+        for i in 0..validator_count + 1 {
+            if i < validator_count {
+                let pubkey = cache.get(i).expect("pubkey should be present");
+                assert_eq!(pubkey, &keypairs[i].pk, "pubkey should match cache");
+
+                assert_eq!(Some(i), cache.get_index(pubkey), "index should match cache");
+            } else {
+                assert_eq!(
+                    cache.get(i),
+                    None,
+                    "should not get pubkey for out of bounds index",
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn basic_operation() {
+        let (state, keypairs) = get_state(8);
+
+        let mut cache = ValidatorPubkeyCache::in_memory();
+        cache.import_new_pubkeys(&state).expect("should import");
+
+        check_cache_get(&cache, &keypairs[..]);
+
+        // Try adding a state with the same number of keypairs.
+        let (state, keypairs) = get_state(8);
+        cache
+            .import_new_pubkeys(&state)
+            .expect("should import same state");
+        check_cache_get(&cache, &keypairs[..]);
+
+        // Try adding a state with less keypairs.
+        let (state, _) = get_state(1);
+        cache
+            .import_new_pubkeys(&state)
+            .expect("should import smaller state");
+        check_cache_get(&cache, &keypairs[..]);
+
+        // Try adding a state with more keypairs.
+        let (state, keypairs) = get_state(12);
+        cache
+            .import_new_pubkeys(&state)
+            .expect("should import larger state");
+        check_cache_get(&cache, &keypairs[..]);
+    }
+
+    #[test]
+    fn persistence() {
+        let dir = tempdir().expect("should create tempdir");
+        let path = dir.path().join("cache.bin");
+
+        let (state, keypairs) = get_state(8);
+
+        let cache =
+            ValidatorPubkeyCache::load_from_file(&path, &state).expect("should create cache");
+        check_cache_get(&cache, &keypairs[..]);
+        drop(cache);
+
+        // Re-init the cache from the file.
+        let cache = ValidatorPubkeyCache::load_from_file(&path, &state).expect("should open cache");
+        check_cache_get(&cache, &keypairs[..]);
+        drop(cache);
+
+        // Grow the registry and re-open the cache, which should append the new keys.
+        let (state, keypairs) = get_state(12);
+        let cache = ValidatorPubkeyCache::load_from_file(&path, &state).expect("should open cache");
+        check_cache_get(&cache, &keypairs[..]);
+        drop(cache);
+
+        let cache = ValidatorPubkeyCache::load_from_file(&path, &state).expect("should open cache");
+        check_cache_get(&cache, &keypairs[..]);
+    }
+
+    #[test]
+    fn partial_and_inconsistent_files_are_recovered() {
+        let dir = tempdir().expect("should create tempdir");
+        let path = dir.path().join("cache.bin");
+
+        let (state, keypairs) = get_state(8);
+
+        let cache =
+            ValidatorPubkeyCache::load_from_file(&path, &state).expect("should create cache");
+        drop(cache);
+
+        // Simulate an unclean shutdown midway through writing a key.
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .expect("should open file");
+        file.write_all(&[42; 7]).expect("should write junk");
+        drop(file);
+
+        let cache = ValidatorPubkeyCache::load_from_file(&path, &state).expect("should open cache");
+        check_cache_get(&cache, &keypairs[..]);
+        drop(cache);
+
+        // A file written for a different registry should be discarded.
+        let mut other_state = state.clone();
+        other_state.validators[7].pubkey = keypairs[0].pk.clone();
+        other_state.validators[0].pubkey = keypairs[7].pk.clone();
+        let cache = ValidatorPubkeyCache::load_from_file(&path, &other_state)
+            .expect("should rebuild cache");
+        assert_eq!(cache.get(0), Some(&keypairs[7].pk));
+        assert_eq!(cache.get_index(&keypairs[0].pk), Some(7));
+    }
+
+    #[test]
+    fn open_without_state() {
+        let dir = tempdir().expect("should create tempdir");
+        let path = dir.path().join("cache.bin");
+
+        // A missing file yields an empty cache.
+        let cache = ValidatorPubkeyCache::open(&path).expect("should create cache");
+        assert!(cache.is_empty());
+        drop(cache);
+
+        let (state, keypairs) = get_state(8);
+        let cache =
+            ValidatorPubkeyCache::load_from_file(&path, &state).expect("should create cache");
+        drop(cache);
+
+        // The keys are available before any state is supplied.
+        let mut cache = ValidatorPubkeyCache::open(&path).expect("should open cache");
+        check_cache_get(&cache, &keypairs[..]);
+
+        // The keys of a state decoded afterwards are those loaded from the file.
+        let decoded = BeaconState::<MinimalEthSpec>::from_ssz_bytes(&state.as_ssz_bytes())
+            .expect("should decode state");
+        assert_eq!(decoded.validators, state.validators);
+
+        let (state, keypairs) = get_state(12);
+        cache.sync_with_state(&state).expect("should sync cache");
+        check_cache_get(&cache, &keypairs[..]);
+    }
+}
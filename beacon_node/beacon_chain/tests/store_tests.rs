@@ -0,0 +1,190 @@
+#![cfg(not(debug_assertions))]
+
+#[macro_use]
+extern crate lazy_static;
+
+use beacon_chain::schema_change::migrate_schema;
+use beacon_chain::test_utils::{
+    AttestationStrategy, BeaconChainHarness, BlockStrategy, DiskHarnessType,
+};
+use ssz::Encode;
+use std::sync::Arc;
+use store::{DiskStore, Store, StoreConfig};
+use tempfile::{tempdir, TempDir};
+use types::{EthSpec, Keypair, MinimalEthSpec};
+
+type E = MinimalEthSpec;
+
+pub const VALIDATOR_COUNT: usize = 24;
+
+lazy_static! {
+    /// A cached set of keys.
+    static ref KEYPAIRS: Vec<Keypair> = types::test_utils::generate_deterministic_keypairs(VALIDATOR_COUNT);
+}
+
+fn get_store(db_path: &TempDir) -> Arc<DiskStore> {
+    get_store_with_config(db_path, StoreConfig::default())
+}
+
+fn get_store_with_config(db_path: &TempDir, config: StoreConfig) -> Arc<DiskStore> {
+    let spec = E::default_spec();
+    let hot_path = db_path.path().join("hot_db");
+    let cold_path = db_path.path().join("cold_db");
+    Arc::new(
+        DiskStore::open(&hot_path, &cold_path, config, spec, migrate_schema::<E>)
+            .expect("disk store should open"),
+    )
+}
+
+fn get_harness(store: Arc<DiskStore>) -> BeaconChainHarness<DiskHarnessType<E>> {
+    let harness = BeaconChainHarness::new_with_disk_store(MinimalEthSpec, store, KEYPAIRS.to_vec());
+
+    harness.advance_slot();
+
+    harness
+}
+
+#[test]
+fn finalizes_on_disk() {
+    let db_path = tempdir().unwrap();
+    let store = get_store(&db_path);
+    let harness = get_harness(store.clone());
+
+    harness.extend_chain(
+        E::slots_per_epoch() as usize * 5,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::AllValidators,
+    );
+
+    let head = harness.chain.head();
+    assert!(
+        head.beacon_state.finalized_checkpoint.epoch > 0,
+        "the chain should have finalized"
+    );
+    assert!(
+        store.get_split_slot() > 0,
+        "finalized states should be moved to the freezer"
+    );
+}
+
+#[test]
+fn replayed_frozen_states_match_stored_states() {
+    let db_path = tempdir().unwrap();
+    let store = get_store_with_config(
+        &db_path,
+        StoreConfig {
+            slots_per_restore_point: 2 * E::slots_per_epoch(),
+            // Ensure frozen states are replayed, rather than read from the cache.
+            state_cache_size: 0,
+            ..StoreConfig::default()
+        },
+    );
+    let harness = get_harness(store.clone());
+
+    let mut block_states = vec![];
+    for i in 0..E::slots_per_epoch() * 6 {
+        harness.extend_chain(
+            1,
+            BlockStrategy::OnCanonicalHead,
+            AttestationStrategy::AllValidators,
+        );
+
+        let head = harness.chain.head();
+        block_states.push((head.beacon_state_root, head.beacon_state));
+
+        harness.advance_slot();
+        // Skip a slot in each epoch, so that skip slots are replayed too.
+        if i % E::slots_per_epoch() == 3 {
+            harness.advance_slot();
+        }
+    }
+
+    let split_slot = store.get_split_slot();
+    assert!(
+        split_slot > 2 * E::slots_per_epoch(),
+        "states after a restore point should be frozen"
+    );
+
+    let frozen_state_roots = harness
+        .chain
+        .rev_iter_state_roots()
+        .filter(|(_, slot)| *slot < split_slot)
+        .collect::<Vec<_>>();
+    assert_eq!(frozen_state_roots.len() as u64, split_slot.as_u64());
+
+    for (state_root, slot) in frozen_state_roots {
+        let state = store
+            .get_state::<E>(&state_root, Some(slot))
+            .expect("should read frozen state")
+            .expect("frozen state should exist");
+
+        assert_eq!(state.slot, slot);
+        assert_eq!(
+            state.canonical_root(),
+            state_root,
+            "the replayed state at slot {} should match the stored root",
+            slot
+        );
+    }
+
+    for (state_root, expected) in block_states
+        .iter()
+        .filter(|(_, state)| state.slot < split_slot)
+    {
+        let state = store
+            .get_state::<E>(state_root, Some(expected.slot))
+            .expect("should read frozen state")
+            .expect("frozen state should exist");
+
+        assert_eq!(
+            state.as_ssz_bytes(),
+            expected.as_ssz_bytes(),
+            "the replayed state at slot {} should match the imported state",
+            expected.slot
+        );
+    }
+}
+
+#[test]
+fn resumes_from_disk() {
+    let db_path = tempdir().unwrap();
+    let num_blocks = E::slots_per_epoch() as usize * 3;
+
+    let head = {
+        let harness = get_harness(get_store(&db_path));
+
+        harness.extend_chain(
+            num_blocks,
+            BlockStrategy::OnCanonicalHead,
+            AttestationStrategy::AllValidators,
+        );
+        harness.chain.persist().expect("should persist chain");
+
+        harness.chain.head()
+    };
+
+    let harness = BeaconChainHarness::resume_from_disk_store(
+        MinimalEthSpec,
+        get_store(&db_path),
+        KEYPAIRS.to_vec(),
+    );
+
+    assert_eq!(
+        harness.chain.head().beacon_block_root,
+        head.beacon_block_root,
+        "the head should be restored"
+    );
+
+    harness.advance_slot();
+    harness.extend_chain(
+        1,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::AllValidators,
+    );
+
+    assert_eq!(
+        harness.chain.head().beacon_block.slot,
+        head.beacon_block.slot + 1,
+        "the resumed chain should be extendable"
+    );
+}
@@ -3,18 +3,23 @@
 #[macro_use]
 extern crate lazy_static;
 
-use beacon_chain::AttestationProcessingOutcome;
 use beacon_chain::{
     test_utils::{
         AttestationStrategy, BeaconChainHarness, BlockStrategy, HarnessType, PersistedBeaconChain,
         BEACON_CHAIN_DB_KEY,
     },
-    BlockProcessingOutcome,
+    BlockProcessingOutcome, ChainSegmentResult,
 };
+use beacon_chain::{AttestationProcessingOutcome, ATTESTATION_PROPAGATION_SLOT_RANGE};
 use rand::Rng;
+use state_processing::per_block_processing::errors::AttestationInvalid;
+use state_processing::BlockProcessingError;
 use store::Store;
 use types::test_utils::{SeedableRng, TestRandom, XorShiftRng};
-use types::{Deposit, EthSpec, Hash256, Keypair, MinimalEthSpec, RelativeEpoch, Slot};
+use types::{
+    AggregateAndProof, BeaconBlock, BeaconState, Checkpoint, Deposit, Epoch, EthSpec, Hash256,
+    Keypair, MinimalEthSpec, RelativeEpoch, Signature, Slot,
+};
 
 // Should ideally be divisible by 3.
 pub const VALIDATOR_COUNT: usize = 24;
@@ -144,6 +149,143 @@ fn chooses_fork() {
     );
 }
 
+#[test]
+fn finds_common_ancestor_of_forks() {
+    let harness = get_harness(VALIDATOR_COUNT);
+
+    let two_thirds = (VALIDATOR_COUNT / 3) * 2;
+    let honest_validators: Vec<usize> = (0..two_thirds).collect();
+    let faulty_validators: Vec<usize> = (two_thirds..VALIDATOR_COUNT).collect();
+
+    harness.extend_chain(
+        2,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::AllValidators,
+    );
+
+    let ancestor_root = harness.chain.head().beacon_block_root;
+    let ancestor_slot = harness.chain.head().beacon_block.slot;
+
+    let (honest_head, faulty_head) = harness.generate_two_forks_by_skipping_a_block(
+        &honest_validators,
+        &faulty_validators,
+        2,
+        3,
+    );
+
+    let honest_slot = harness
+        .chain
+        .get_block(&honest_head)
+        .expect("should read db")
+        .expect("should find honest head")
+        .slot;
+    let faulty_slot = harness
+        .chain
+        .get_block(&faulty_head)
+        .expect("should read db")
+        .expect("should find faulty head")
+        .slot;
+
+    assert_eq!(
+        harness
+            .chain
+            .find_common_ancestor((honest_head, honest_slot), (faulty_head, faulty_slot))
+            .expect("should search for ancestor"),
+        Some((ancestor_root, ancestor_slot)),
+        "the forks should share the block prior to the fork"
+    );
+    assert_eq!(
+        harness
+            .chain
+            .find_common_ancestor((honest_head, honest_slot), (honest_head, honest_slot))
+            .expect("should search for ancestor"),
+        Some((honest_head, honest_slot)),
+        "a block should be its own common ancestor"
+    );
+}
+
+#[test]
+fn prunes_abandoned_fork_upon_finalization() {
+    let harness = get_harness(VALIDATOR_COUNT);
+
+    let two_thirds = (VALIDATOR_COUNT / 3) * 2;
+    let delay = MinimalEthSpec::default_spec().min_attestation_inclusion_delay as usize;
+
+    let honest_validators: Vec<usize> = (0..two_thirds).collect();
+    let faulty_validators: Vec<usize> = (two_thirds..VALIDATOR_COUNT).collect();
+
+    harness.extend_chain(
+        delay + 1,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::AllValidators,
+    );
+
+    let (honest_head, faulty_head) = harness.generate_two_forks_by_skipping_a_block(
+        &honest_validators,
+        &faulty_validators,
+        delay + 1,
+        delay + 2,
+    );
+
+    let faulty_block: BeaconBlock<MinimalEthSpec> = harness
+        .chain
+        .store
+        .get(&faulty_head)
+        .unwrap()
+        .expect("faulty head should be stored");
+
+    let heads = harness.chain.heads();
+    assert_eq!(heads.len(), 2, "should track both forks");
+    assert!(heads.iter().any(|(root, _)| *root == honest_head));
+    assert!(heads.iter().any(|(root, _)| *root == faulty_head));
+
+    // Extend the honest chain until it is finalized beyond the fork.
+    harness.extend_chain(
+        MinimalEthSpec::slots_per_epoch() as usize * 5,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::AllValidators,
+    );
+
+    let head = harness.chain.head();
+    assert!(
+        head.beacon_state.finalized_checkpoint.epoch > 0,
+        "chain should have finalized"
+    );
+
+    assert_eq!(
+        harness.chain.heads(),
+        vec![(head.beacon_block_root, head.beacon_block.slot)],
+        "only the canonical head should remain"
+    );
+    assert_eq!(
+        harness
+            .chain
+            .store
+            .get::<BeaconBlock<MinimalEthSpec>>(&faulty_head)
+            .unwrap(),
+        None,
+        "abandoned block should be pruned"
+    );
+    assert_eq!(
+        harness
+            .chain
+            .store
+            .get::<BeaconState<MinimalEthSpec>>(&faulty_block.state_root)
+            .unwrap(),
+        None,
+        "abandoned state should be pruned"
+    );
+    assert!(
+        harness
+            .chain
+            .store
+            .get::<BeaconBlock<MinimalEthSpec>>(&honest_head)
+            .unwrap()
+            .is_some(),
+        "canonical block should be retained"
+    );
+}
+
 #[test]
 fn finalizes_with_full_participation() {
     let num_blocks_produced = MinimalEthSpec::slots_per_epoch() * 5;
@@ -179,6 +321,204 @@ fn finalizes_with_full_participation() {
     );
 }
 
+/// Imports the canonical chain of `source` into `harness`, block by block, returning the outcome
+/// of the first block that is not processed.
+fn import_chain(
+    source: &BeaconChainHarness<HarnessType<MinimalEthSpec>>,
+    harness: &BeaconChainHarness<HarnessType<MinimalEthSpec>>,
+) -> Option<BlockProcessingOutcome> {
+    let mut dump = source.chain.chain_dump().expect("should dump chain");
+    dump.reverse();
+
+    // The genesis block is skipped, since it is already known.
+    for block in dump
+        .into_iter()
+        .skip(1)
+        .map(|checkpoint| checkpoint.beacon_block)
+    {
+        while harness.chain.slot().expect("should have a slot") < block.slot {
+            harness.advance_slot();
+        }
+
+        match harness
+            .chain
+            .process_block(block)
+            .expect("should not error during block processing")
+        {
+            BlockProcessingOutcome::Processed { .. } => {}
+            other => return Some(other),
+        }
+    }
+
+    None
+}
+
+#[test]
+fn imports_chain_segment() {
+    let source = get_harness(VALIDATOR_COUNT);
+    source.extend_chain(
+        (MinimalEthSpec::slots_per_epoch() * 3) as usize,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::AllValidators,
+    );
+
+    let mut dump = source.chain.chain_dump().expect("should dump chain");
+    dump.reverse();
+    // The genesis block is skipped, since it is already known.
+    let blocks = dump
+        .into_iter()
+        .skip(1)
+        .map(|checkpoint| checkpoint.beacon_block)
+        .collect::<Vec<_>>();
+    let last_slot = blocks.last().expect("should have blocks").slot;
+
+    let harness = get_harness(VALIDATOR_COUNT);
+    while harness.chain.slot().expect("should have a slot") < last_slot {
+        harness.advance_slot();
+    }
+
+    let mut non_linear = blocks.clone();
+    non_linear.swap(2, 3);
+    assert_eq!(
+        harness.chain.process_chain_segment(non_linear),
+        Ok(ChainSegmentResult::Failed {
+            imported_blocks: 0,
+            outcome: BlockProcessingOutcome::NonLinearParentRoots,
+        }),
+        "should reject a segment which is not a chain"
+    );
+
+    // The last block is alone in its epoch, so all the blocks before it are imported.
+    let mut invalid_signature = blocks.clone();
+    invalid_signature
+        .last_mut()
+        .expect("should have blocks")
+        .signature = Signature::empty_signature();
+    assert_eq!(
+        harness.chain.process_chain_segment(invalid_signature),
+        Ok(ChainSegmentResult::Failed {
+            imported_blocks: blocks.len() - 1,
+            outcome: BlockProcessingOutcome::PerBlockProcessingError(
+                BlockProcessingError::BulkSignatureVerificationFailed
+            ),
+        }),
+        "should reject the block with an invalid signature"
+    );
+    assert_eq!(
+        harness.chain.head().beacon_block.slot,
+        last_slot - 1,
+        "should import the blocks before the invalid block"
+    );
+
+    assert_eq!(
+        harness.chain.process_chain_segment(blocks),
+        Ok(ChainSegmentResult::Successful { imported_blocks: 1 }),
+        "should skip the known blocks and import the last block"
+    );
+    assert_eq!(
+        harness.chain.head().beacon_block_root,
+        source.chain.head().beacon_block_root,
+        "should import the whole chain"
+    );
+}
+
+#[test]
+fn classifies_invalid_blocks() {
+    let attestation_invalid =
+        BlockProcessingOutcome::PerBlockProcessingError(BlockProcessingError::AttestationInvalid {
+            index: 3,
+            reason: AttestationInvalid::BadTargetEpoch,
+        });
+    assert!(
+        attestation_invalid.is_invalid_block(),
+        "a block with an invalid operation is invalid"
+    );
+    assert_eq!(
+        attestation_invalid.to_string(),
+        "attestation #3 invalid: BadTargetEpoch",
+        "should name the invalid operation"
+    );
+
+    assert!(BlockProcessingOutcome::NonLinearParentRoots.is_invalid_block());
+    assert!(BlockProcessingOutcome::PerBlockProcessingError(
+        BlockProcessingError::BulkSignatureVerificationFailed
+    )
+    .is_invalid_block());
+
+    assert!(
+        !BlockProcessingOutcome::FutureSlot {
+            present_slot: Slot::new(1),
+            block_slot: Slot::new(2),
+        }
+        .is_invalid_block(),
+        "a block from the future may be the result of clock disparity"
+    );
+    assert!(
+        !BlockProcessingOutcome::PerBlockProcessingError(BlockProcessingError::SszTypesError(
+            ssz_types::Error::OutOfBounds { i: 1, len: 0 }
+        ))
+        .is_invalid_block(),
+        "an ssz error is not the fault of the block"
+    );
+}
+
+#[test]
+fn follows_chain_including_weak_subjectivity_checkpoint() {
+    let source = get_harness(VALIDATOR_COUNT);
+    source.extend_chain(
+        (MinimalEthSpec::slots_per_epoch() * 5) as usize,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::AllValidators,
+    );
+
+    let epoch = Epoch::new(2);
+    let root = source
+        .chain
+        .rev_iter_block_roots()
+        .find(|(_, slot)| *slot == epoch.start_slot(MinimalEthSpec::slots_per_epoch()))
+        .map(|(root, _)| root)
+        .expect("should find checkpoint root");
+
+    let mut harness = get_harness(VALIDATOR_COUNT);
+    harness.chain.weak_subjectivity_checkpoint = Some(Checkpoint { epoch, root });
+
+    assert_eq!(import_chain(&source, &harness), None);
+    assert!(
+        harness.chain.head().beacon_state.finalized_checkpoint.epoch >= epoch,
+        "should finalize the checkpoint epoch"
+    );
+}
+
+#[test]
+fn rejects_chain_conflicting_with_weak_subjectivity_checkpoint() {
+    let source = get_harness(VALIDATOR_COUNT);
+    source.extend_chain(
+        (MinimalEthSpec::slots_per_epoch() * 5) as usize,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::AllValidators,
+    );
+
+    let checkpoint = Checkpoint {
+        epoch: Epoch::new(2),
+        root: Hash256::repeat_byte(42),
+    };
+
+    let mut harness = get_harness(VALIDATOR_COUNT);
+    harness.chain.weak_subjectivity_checkpoint = Some(checkpoint.clone());
+
+    match import_chain(&source, &harness) {
+        Some(BlockProcessingOutcome::WeakSubjectivityConflict {
+            checkpoint: conflict,
+            ..
+        }) => assert_eq!(conflict, checkpoint),
+        other => panic!("should reject the conflicting chain: {:?}", other),
+    }
+    assert!(
+        harness.chain.head().beacon_state.finalized_checkpoint.epoch < checkpoint.epoch,
+        "should not finalize the conflicting chain"
+    );
+}
+
 #[test]
 fn finalizes_with_two_thirds_participation() {
     let num_blocks_produced = MinimalEthSpec::slots_per_epoch() * 5;
@@ -222,6 +562,123 @@ fn finalizes_with_two_thirds_participation() {
     );
 }
 
+#[test]
+fn validator_inclusion_with_two_thirds_participation() {
+    let num_blocks_produced = MinimalEthSpec::slots_per_epoch() * 5;
+
+    let harness = get_harness(VALIDATOR_COUNT);
+
+    let two_thirds = (VALIDATOR_COUNT / 3) * 2;
+    let attesters = (0..two_thirds).collect();
+
+    harness.extend_chain(
+        num_blocks_produced as usize,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::SomeValidators(attesters),
+    );
+
+    let head_epoch = harness.chain.head().beacon_state.current_epoch();
+    let epoch = head_epoch - 2;
+
+    let (summary, validators) = harness
+        .chain
+        .validator_inclusion(epoch)
+        .expect("should compute inclusion for a processed epoch");
+
+    assert_eq!(
+        summary.epoch, epoch,
+        "summary should be for the requested epoch"
+    );
+    assert_eq!(summary.active_validators, VALIDATOR_COUNT as u64);
+    assert_eq!(summary.included_attesters, two_thirds as u64);
+    assert!(
+        summary.total_inclusion_delay
+            >= two_thirds as u64 * harness.spec.min_attestation_inclusion_delay,
+        "every inclusion delay should be at least the minimum"
+    );
+    assert_eq!(
+        summary.source_attesting_gwei,
+        two_thirds as u64 * harness.spec.max_effective_balance
+    );
+
+    assert_eq!(validators.len(), VALIDATOR_COUNT);
+    for (i, validator) in validators.iter().enumerate() {
+        assert_eq!(validator.epoch, epoch);
+        assert_eq!(validator.validator_index, i);
+        assert!(validator.is_active, "all validators should be active");
+
+        if i < two_thirds {
+            assert!(
+                validator.is_source_attester,
+                "attester {} should be included",
+                i
+            );
+            assert!(
+                validator.is_target_attester,
+                "attester {} should match target",
+                i
+            );
+            assert!(
+                validator.is_head_attester,
+                "attester {} should match head",
+                i
+            );
+            assert!(
+                validator.inclusion_delay.is_some(),
+                "attester {} should have an inclusion delay",
+                i
+            );
+        } else {
+            assert!(
+                !validator.is_source_attester,
+                "validator {} did not attest",
+                i
+            );
+            assert_eq!(validator.inclusion_delay, None);
+        }
+    }
+
+    assert!(
+        harness.chain.validator_inclusion(head_epoch).is_err(),
+        "inclusion should be unavailable for the head epoch"
+    );
+}
+
+#[test]
+fn chain_quality_with_full_participation() {
+    let num_blocks_produced = MinimalEthSpec::slots_per_epoch() * 5;
+
+    let harness = get_harness(VALIDATOR_COUNT);
+
+    harness.extend_chain(
+        num_blocks_produced as usize,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::AllValidators,
+    );
+
+    let head_epoch = harness.chain.head().beacon_state.current_epoch();
+    let epochs = harness.chain.chain_quality(usize::max_value());
+
+    assert_eq!(
+        epochs.last().map(|quality| quality.epoch),
+        Some(head_epoch - 2),
+        "the latest epoch should be the last to complete epoch processing"
+    );
+    assert_eq!(harness.chain.chain_quality(2).len(), 2);
+
+    for quality in &epochs {
+        assert_eq!(quality.missed_proposals, 0, "no slots should be skipped");
+        assert!(quality.average_inclusion_delay.is_some());
+    }
+
+    let latest = epochs.last().expect("should have chain quality");
+    assert!(
+        latest.participation_rate > 0.99,
+        "all validators should attest"
+    );
+    assert!(latest.justified, "the latest epoch should be justified");
+}
+
 #[test]
 fn does_not_finalize_with_less_than_two_thirds_participation() {
     let num_blocks_produced = MinimalEthSpec::slots_per_epoch() * 5;
@@ -319,8 +776,7 @@ fn roundtrip_operation_pool() {
     harness.chain.persist().unwrap();
 
     let key = Hash256::from_slice(&BEACON_CHAIN_DB_KEY.as_bytes());
-    let p: PersistedBeaconChain<HarnessType<MinimalEthSpec>> =
-        harness.chain.store.get(&key).unwrap().unwrap();
+    let p: PersistedBeaconChain<MinimalEthSpec> = harness.chain.store.get(&key).unwrap().unwrap();
 
     let restored_op_pool = p
         .op_pool
@@ -363,10 +819,10 @@ fn free_attestations_added_to_fork_choice_some_none() {
         if slot <= num_blocks_produced && slot != 0 {
             assert_eq!(
                 latest_message.unwrap().1,
-                slot,
-                "Latest message slot for {} should be equal to slot {}.",
+                slot.epoch(MinimalEthSpec::slots_per_epoch()),
+                "Latest message epoch for {} should be equal to epoch {}.",
                 validator,
-                slot
+                slot.epoch(MinimalEthSpec::slots_per_epoch())
             )
         } else {
             assert!(
@@ -411,6 +867,219 @@ fn attestations_with_increasing_slots() {
     }
 }
 
+#[test]
+fn repeated_and_conflicting_unaggregated_attestations() {
+    let harness = get_harness(VALIDATOR_COUNT);
+
+    harness.extend_chain(
+        2,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::SomeValidators(vec![]),
+    );
+
+    let head = harness.chain.head();
+    let attestations = harness.get_free_attestations(
+        &AttestationStrategy::AllValidators,
+        &head.beacon_state,
+        head.beacon_block_root,
+        head.beacon_block.slot,
+    );
+    let conflicting_attestations = harness.get_free_attestations(
+        &AttestationStrategy::AllValidators,
+        &head.beacon_state,
+        head.beacon_block.parent_root,
+        head.beacon_block.slot - 1,
+    );
+
+    let attestation = attestations[0].clone();
+    let epoch = attestation.data.target.epoch;
+
+    assert_eq!(
+        harness.chain.process_attestation(attestation.clone()),
+        Ok(AttestationProcessingOutcome::Processed),
+        "should process the first vote"
+    );
+
+    let validator_index = match harness.chain.process_attestation(attestation) {
+        Ok(AttestationProcessingOutcome::PriorAttestationKnown {
+            validator_index,
+            epoch: known_epoch,
+        }) => {
+            assert_eq!(known_epoch, epoch);
+            validator_index
+        }
+        other => panic!("should drop repeated vote, got {:?}", other),
+    };
+
+    assert_eq!(
+        harness
+            .chain
+            .process_attestation(conflicting_attestations[0].clone()),
+        Ok(AttestationProcessingOutcome::Equivocation {
+            validator_index,
+            epoch
+        }),
+        "should detect conflicting vote"
+    );
+}
+
+#[test]
+fn verifies_aggregate_and_proofs() {
+    let harness = get_harness(VALIDATOR_COUNT);
+
+    harness.extend_chain(
+        2,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::SomeValidators(vec![]),
+    );
+
+    let head = harness.chain.head();
+    let state = &head.beacon_state;
+    let committee = state
+        .get_beacon_committee(state.slot, 0)
+        .expect("should get committee");
+
+    let mut attestations = harness
+        .get_free_attestations(
+            &AttestationStrategy::AllValidators,
+            state,
+            head.beacon_block_root,
+            head.beacon_block.slot,
+        )
+        .into_iter()
+        .filter(|attestation| attestation.data.index == 0);
+    let mut aggregate = attestations.next().expect("should produce an attestation");
+    for attestation in attestations {
+        aggregate.aggregate(&attestation);
+    }
+
+    let aggregator_index = committee.committee[0];
+    let non_member_index = (0..VALIDATOR_COUNT)
+        .find(|i| !committee.committee.contains(i))
+        .expect("should find a validator outside the committee");
+
+    let aggregate_and_proof = |aggregator_index: usize, signer_index: usize| AggregateAndProof {
+        aggregator_index: aggregator_index as u64,
+        selection_proof: AggregateAndProof::<MinimalEthSpec>::produce_selection_proof(
+            aggregate.data.slot,
+            &KEYPAIRS[signer_index].sk,
+            &state.fork,
+            &harness.spec,
+        ),
+        aggregate: aggregate.clone(),
+    };
+
+    assert_eq!(
+        harness
+            .chain
+            .process_aggregate_and_proof(aggregate_and_proof(non_member_index, non_member_index)),
+        Ok(AttestationProcessingOutcome::AggregatorNotInCommittee {
+            aggregator_index: non_member_index as u64
+        }),
+        "should reject an aggregator outside the committee"
+    );
+
+    assert_eq!(
+        harness
+            .chain
+            .process_aggregate_and_proof(aggregate_and_proof(aggregator_index, non_member_index)),
+        Ok(AttestationProcessingOutcome::InvalidSelectionProof {
+            aggregator_index: aggregator_index as u64
+        }),
+        "should reject a selection proof from another validator"
+    );
+
+    assert_eq!(
+        harness
+            .chain
+            .process_aggregate_and_proof(aggregate_and_proof(aggregator_index, aggregator_index)),
+        Ok(AttestationProcessingOutcome::Processed),
+        "should process a valid aggregate"
+    );
+
+    assert_eq!(
+        harness
+            .chain
+            .process_aggregate_and_proof(aggregate_and_proof(aggregator_index, aggregator_index)),
+        Ok(AttestationProcessingOutcome::AggregatorAlreadyKnown {
+            aggregator_index: aggregator_index as u64,
+            slot: aggregate.data.slot,
+        }),
+        "should drop a second aggregate from the same aggregator"
+    );
+
+    let current_slot = aggregate.data.slot + ATTESTATION_PROPAGATION_SLOT_RANGE + 1;
+    harness.chain.slot_clock.set_slot(current_slot.as_u64());
+    assert_eq!(
+        harness
+            .chain
+            .process_aggregate_and_proof(aggregate_and_proof(aggregator_index, aggregator_index)),
+        Ok(AttestationProcessingOutcome::SlotOutOfRange {
+            aggregate: aggregate.data.slot,
+            current: current_slot,
+        }),
+        "should not propagate an aggregate from outside the propagation range"
+    );
+}
+
+#[test]
+fn produces_attestations_during_skip_slots() {
+    let num_blocks_produced = MinimalEthSpec::slots_per_epoch() + 2;
+    let harness = get_harness(VALIDATOR_COUNT);
+
+    harness.extend_chain(
+        num_blocks_produced as usize,
+        BlockStrategy::OnCanonicalHead,
+        AttestationStrategy::AllValidators,
+    );
+
+    let chain = &harness.chain;
+    let head = chain.head();
+
+    // Attest to every slot over the following two epochs, without producing any blocks.
+    let first_slot = head.beacon_state.slot;
+    let last_slot = first_slot + MinimalEthSpec::slots_per_epoch() * 2;
+
+    for slot in (first_slot.as_u64()..last_slot.as_u64()).map(Slot::new) {
+        let mut state = chain
+            .state_at_slot(slot)
+            .expect("should skip to the attestation slot");
+        state
+            .build_committee_cache(RelativeEpoch::Current, &chain.spec)
+            .expect("should build committee cache");
+
+        let expected_data = chain
+            .produce_attestation_data_for_block(
+                0,
+                head.beacon_block_root,
+                head.beacon_block.slot,
+                &state,
+            )
+            .expect("should produce attestation data from the state at the slot");
+        let expected_committee_len = state
+            .get_beacon_committee(slot, 0)
+            .expect("should get committee")
+            .committee
+            .len();
+
+        let attestation = chain
+            .produce_attestation(slot, 0)
+            .expect("should produce attestation");
+
+        assert_eq!(
+            attestation.data, expected_data,
+            "attestation data should match the state at slot {}",
+            slot
+        );
+        assert_eq!(
+            attestation.aggregation_bits.len(),
+            expected_committee_len,
+            "aggregation bits should match the committee at slot {}",
+            slot
+        );
+    }
+}
+
 #[test]
 fn free_attestations_added_to_fork_choice_all_updated() {
     let num_blocks_produced = MinimalEthSpec::slots_per_epoch() * 2 - 1;
@@ -444,8 +1113,8 @@ fn free_attestations_added_to_fork_choice_all_updated() {
 
         assert_eq!(
             latest_message.unwrap().1,
-            slot,
-            "Latest message slot should be equal to attester duty."
+            slot.epoch(MinimalEthSpec::slots_per_epoch()),
+            "Latest message epoch should be equal to the epoch of the attester duty."
         );
 
         if slot != num_blocks_produced {
@@ -3,9 +3,10 @@ use crate::Client;
 use beacon_chain::{
     builder::{BeaconChainBuilder, Witness},
     eth1_chain::CachingEth1Backend,
-    lmd_ghost::ThreadSafeReducedTree,
+    migrate::Migrate,
+    schema_change::migrate_schema,
     slot_clock::{SlotClock, SystemTimeSlotClock},
-    store::{DiskStore, MemoryStore, Store},
+    store::{DiskStore, MemoryStore, Store, StoreConfig},
     BeaconChain, BeaconChainTypes, Eth1ChainBackend, EventHandler,
 };
 use environment::RuntimeContext;
@@ -17,24 +18,33 @@ use genesis::{
     generate_deterministic_keypairs, interop_genesis_state, state_from_ssz_file, Eth1GenesisService,
 };
 use lighthouse_bootstrap::Bootstrapper;
-use lmd_ghost::LmdGhost;
+use logging::RequestId;
 use network::{NetworkConfig, NetworkMessage, Service as NetworkService};
-use slog::{debug, error, info, warn};
+use notifier::{Event, Notifier};
+#[cfg(feature = "otlp-tracing")]
+use opentelemetry_otlp::Uninstall;
+use resource_budget::ResourceBudget;
+use slog::{debug, error, info, warn, OwnedKV};
 use std::net::SocketAddr;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::timer::Interval;
-use types::{ChainSpec, EthSpec};
+use types::{ChainSpec, Checkpoint, EthSpec};
 use websocket_server::{Config as WebSocketConfig, WebSocketSender};
 
 /// The interval between notifier events.
 pub const NOTIFIER_INTERVAL_SECONDS: u64 = 15;
 /// Create a warning log whenever the peer count is at or below this value.
 pub const WARN_PEER_COUNT: usize = 1;
+/// Create a warning log whenever the finalized epoch is more than this many epochs behind the
+/// current epoch.
+pub const WARN_FINALITY_DISTANCE_EPOCHS: u64 = 4;
 /// Interval between polling the eth1 node for genesis information.
 pub const ETH1_GENESIS_UPDATE_INTERVAL_MILLIS: u64 = 500;
+/// The interval between logs counting down to genesis.
+pub const GENESIS_COUNTDOWN_INTERVAL_SECONDS: u64 = 60;
 
 /// Builds a `Client` instance.
 ///
@@ -54,6 +64,7 @@ pub struct ClientBuilder<T: BeaconChainTypes> {
     store: Option<Arc<T::Store>>,
     runtime_context: Option<RuntimeContext<T::EthSpec>>,
     chain_spec: Option<ChainSpec>,
+    data_dir: Option<PathBuf>,
     beacon_chain_builder: Option<BeaconChainBuilder<T>>,
     beacon_chain: Option<Arc<BeaconChain<T>>>,
     eth1_service: Option<Eth1Service>,
@@ -62,16 +73,23 @@ pub struct ClientBuilder<T: BeaconChainTypes> {
     libp2p_network: Option<Arc<NetworkService<T>>>,
     libp2p_network_send: Option<UnboundedSender<NetworkMessage>>,
     http_listen_addr: Option<SocketAddr>,
+    pre_genesis_http: Option<rest_api::PreGenesisServer>,
+    grpc_listen_addr: Option<SocketAddr>,
     websocket_listen_addr: Option<SocketAddr>,
+    tracing_uninstall: Option<Uninstall>,
+    resource_budget: ResourceBudget,
+    notifier: Notifier,
     eth_spec_instance: T::EthSpec,
 }
 
-impl<TStore, TSlotClock, TLmdGhost, TEth1Backend, TEthSpec, TEventHandler>
-    ClientBuilder<Witness<TStore, TSlotClock, TLmdGhost, TEth1Backend, TEthSpec, TEventHandler>>
+impl<TStore, TStoreMigrator, TSlotClock, TEth1Backend, TEthSpec, TEventHandler>
+    ClientBuilder<
+        Witness<TStore, TStoreMigrator, TSlotClock, TEth1Backend, TEthSpec, TEventHandler>,
+    >
 where
     TStore: Store + 'static,
+    TStoreMigrator: Migrate<TStore, TEthSpec>,
     TSlotClock: SlotClock + Clone + 'static,
-    TLmdGhost: LmdGhost<TStore, TEthSpec> + 'static,
     TEth1Backend: Eth1ChainBackend<TEthSpec> + 'static,
     TEthSpec: EthSpec + 'static,
     TEventHandler: EventHandler<TEthSpec> + 'static,
@@ -85,6 +103,7 @@ where
             store: None,
             runtime_context: None,
             chain_spec: None,
+            data_dir: None,
             beacon_chain_builder: None,
             beacon_chain: None,
             eth1_service: None,
@@ -93,7 +112,12 @@ where
             libp2p_network: None,
             libp2p_network_send: None,
             http_listen_addr: None,
+            pre_genesis_http: None,
+            grpc_listen_addr: None,
             websocket_listen_addr: None,
+            tracing_uninstall: None,
+            resource_budget: ResourceBudget::default(),
+            notifier: Notifier::default(),
             eth_spec_instance,
         }
     }
@@ -104,12 +128,36 @@ where
         self
     }
 
+    /// Specifies the budget which throttles eth1 polling, sync, caches and database migration.
+    ///
+    /// Should be called before the store and `BeaconChainBuilder` are initialized, so that they
+    /// observe the budget.
+    pub fn resource_budget(mut self, resource_budget: ResourceBudget) -> Self {
+        self.resource_budget = resource_budget;
+        self
+    }
+
+    /// Specifies the `Notifier` which raises alerts about the eth1 node, peer count and finality.
+    ///
+    /// Should be called before the eth1 backend is initialized.
+    pub fn notifier(mut self, notifier: Notifier) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
     /// Specifies the `ChainSpec`.
     pub fn chain_spec(mut self, spec: ChainSpec) -> Self {
         self.chain_spec = Some(spec);
         self
     }
 
+    /// Specifies the directory in which the `BeaconChain` persists files outside of the database
+    /// (e.g., the validator pubkey cache).
+    pub fn data_dir(mut self, path: PathBuf) -> Self {
+        self.data_dir = Some(path);
+        self
+    }
+
     /// Initializes the `BeaconChainBuilder`. The `build_beacon_chain` method will need to be
     /// called later in order to actually instantiate the `BeaconChain`.
     pub fn beacon_chain_builder(
@@ -119,8 +167,10 @@ where
     ) -> impl Future<Item = Self, Error = String> {
         let store = self.store.clone();
         let chain_spec = self.chain_spec.clone();
+        let data_dir = self.data_dir.clone();
         let runtime_context = self.runtime_context.clone();
         let eth_spec_instance = self.eth_spec_instance.clone();
+        let resource_budget = self.resource_budget.clone();
 
         future::ok(())
             .and_then(move |()| {
@@ -132,11 +182,26 @@ where
                 let spec = chain_spec
                     .ok_or_else(|| "beacon_chain_start_method requires a chain spec".to_string())?;
 
+                // Messages logged whilst serving an HTTP API request include its ID.
+                let log = context.log.new(OwnedKV(RequestId));
+
                 let builder = BeaconChainBuilder::new(eth_spec_instance)
-                    .logger(context.log.clone())
+                    .logger(log.clone())
                     .store(store.clone())
+                    .store_migrator(TStoreMigrator::new(
+                        store.clone(),
+                        log,
+                        resource_budget.clone(),
+                    ))
+                    .resource_budget(resource_budget)
                     .custom_spec(spec.clone());
 
+                let builder = if let Some(data_dir) = data_dir {
+                    builder.data_dir(data_dir)
+                } else {
+                    builder
+                };
+
                 Ok((builder, spec, context))
             })
             .and_then(move |(builder, spec, context)| {
@@ -167,7 +232,7 @@ where
                             Box::new(future)
                         }
                         ClientGenesis::DepositContract => {
-                            let genesis_service = Eth1GenesisService::new(
+                            let genesis_service = Eth1GenesisService::new::<TEthSpec>(
                                 // Some of the configuration options for `Eth1Config` are
                                 // hard-coded when listening for genesis from the deposit contract.
                                 //
@@ -179,7 +244,7 @@ where
                                 Eth1Config {
                                     // Truncating the block cache makes searching for genesis more
                                     // complicated.
-                                    block_cache_truncation: None,
+                                    block_cache_max_bytes: None,
                                     // Scan large ranges of blocks when awaiting genesis.
                                     blocks_per_log_query: 1_000,
                                     // Only perform a single log request each time the eth1 node is
@@ -198,6 +263,7 @@ where
                                     ..config
                                 },
                                 context.log.clone(),
+                                spec.clone(),
                             );
 
                             let future = genesis_service
@@ -256,9 +322,13 @@ where
             .ok_or_else(|| "libp2p_network requires a runtime_context")?
             .service_context("network");
 
-        let (network, network_send) =
-            NetworkService::new(beacon_chain, config, &context.executor, context.log)
-                .map_err(|e| format!("Failed to start libp2p network: {:?}", e))?;
+        let (network, network_send) = NetworkService::new(
+            beacon_chain,
+            config,
+            context.executor.runtime_executor(),
+            context.log,
+        )
+        .map_err(|e| format!("Failed to start libp2p network: {:?}", e))?;
 
         self.libp2p_network = Some(network);
         self.libp2p_network_send = Some(network_send);
@@ -295,9 +365,13 @@ where
             network_chan: network_send.clone(),
         };
 
+        // Take over the socket of the pre-genesis server, if it was started.
+        let listener = self.pre_genesis_http.take().map(|server| server.shutdown());
+
         let (exit_signal, listening_addr) = rest_api::start_server(
             &client_config.rest_api,
-            &context.executor,
+            listener,
+            context.executor.runtime_executor(),
             beacon_chain.clone(),
             network_info,
             client_config.db_path().expect("unable to read datadir"),
@@ -312,8 +386,120 @@ where
         Ok(self)
     }
 
+    /// Immediately starts a minimal HTTP server which reports that the node is waiting for genesis,
+    /// so that the API is reachable before the beacon chain has been built.
+    ///
+    /// Should be called before `self.beacon_chain_builder(..)` when the genesis state must be
+    /// awaited from the eth1 deposit contract. The server is replaced by the full API when
+    /// `self.http_server(..)` is called.
+    pub fn pre_genesis_http_server(mut self, config: &rest_api::Config) -> Result<Self, String> {
+        let context = self
+            .runtime_context
+            .as_ref()
+            .ok_or_else(|| "pre_genesis_http_server requires a runtime_context")?
+            .service_context("http");
+
+        let server = rest_api::start_pre_genesis_server(
+            config,
+            context.executor.runtime_executor(),
+            context.log,
+        )?;
+
+        self.pre_genesis_http = Some(server);
+
+        Ok(self)
+    }
+
+    /// Immediately starts the beacon node gRPC server, if it is enabled in `client_config`.
+    #[cfg(feature = "grpc")]
+    pub fn grpc_server(mut self, client_config: &ClientConfig) -> Result<Self, String> {
+        if !client_config.grpc.enabled {
+            return Ok(self);
+        }
+
+        let beacon_chain = self
+            .beacon_chain
+            .clone()
+            .ok_or_else(|| "grpc_server requires a beacon chain")?;
+        let context = self
+            .runtime_context
+            .as_ref()
+            .ok_or_else(|| "grpc_server requires a runtime_context")?
+            .service_context("grpc");
+        let network_send = self
+            .libp2p_network_send
+            .clone()
+            .ok_or_else(|| "grpc_server requires a libp2p network sender")?;
+
+        let (exit_signal, listening_addr) = grpc_server::start_server(
+            &client_config.grpc,
+            context.executor.runtime_executor(),
+            beacon_chain,
+            network_send,
+            &context.log,
+        )
+        .map_err(|e| format!("Failed to start gRPC server: {:?}", e))?;
+
+        self.exit_signals.push(exit_signal);
+        self.grpc_listen_addr = Some(listening_addr);
+
+        Ok(self)
+    }
+
+    /// Returns an error if the gRPC server is enabled in `client_config`, since it can only be
+    /// started if the `grpc` feature is enabled.
+    #[cfg(not(feature = "grpc"))]
+    pub fn grpc_server(self, client_config: &ClientConfig) -> Result<Self, String> {
+        if client_config.grpc.enabled {
+            Err("--grpc requires Lighthouse to be compiled with the grpc feature".to_string())
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Immediately starts exporting `tracing` spans, if a `tracing_endpoint` is specified in
+    /// `client_config`.
+    #[cfg(feature = "otlp-tracing")]
+    pub fn tracing_exporter(mut self, client_config: &ClientConfig) -> Result<Self, String> {
+        let endpoint = match &client_config.tracing_endpoint {
+            Some(endpoint) => endpoint,
+            None => return Ok(self),
+        };
+        let log = self
+            .runtime_context
+            .as_ref()
+            .ok_or_else(|| "tracing_exporter requires a runtime_context")?
+            .log
+            .clone();
+
+        self.tracing_uninstall = Some(crate::tracing_exporter::install(endpoint)?);
+
+        info!(
+            log,
+            "Tracing exporter started";
+            "endpoint" => endpoint,
+            "protocol" => "otlp"
+        );
+
+        Ok(self)
+    }
+
+    /// Returns an error if a `tracing_endpoint` is specified in `client_config`, since spans can
+    /// only be exported if the `otlp-tracing` feature is enabled.
+    #[cfg(not(feature = "otlp-tracing"))]
+    pub fn tracing_exporter(self, client_config: &ClientConfig) -> Result<Self, String> {
+        match &client_config.tracing_endpoint {
+            Some(_) => Err(
+                "--tracing-endpoint requires Lighthouse to be compiled with the otlp-tracing \
+                 feature"
+                    .to_string(),
+            ),
+            None => Ok(self),
+        }
+    }
+
     /// Immediately starts the service that periodically logs about the libp2p peer count.
-    pub fn peer_count_notifier(mut self) -> Result<Self, String> {
+    pub fn peer_count_notifier(self) -> Result<Self, String> {
         let context = self
             .runtime_context
             .as_ref()
@@ -325,10 +511,7 @@ where
             .libp2p_network
             .clone()
             .ok_or_else(|| "peer_notifier requires a libp2p network")?;
-
-        let (exit_signal, exit) = exit_future::signal();
-
-        self.exit_signals.push(exit_signal);
+        let notifier = self.notifier.clone();
 
         let interval_future = Interval::new(
             Instant::now(),
@@ -343,20 +526,22 @@ where
 
             if connected_peer_count <= WARN_PEER_COUNT {
                 warn!(log, "Low peer count"; "peer_count" => connected_peer_count);
+                notifier.notify(Event::LowPeerCount {
+                    peer_count: connected_peer_count,
+                });
             }
 
             Ok(())
         });
 
-        context
-            .executor
-            .spawn(exit.until(interval_future).map(|_| ()));
+        context.executor.spawn(interval_future, "peer_notifier");
 
         Ok(self)
     }
 
-    /// Immediately starts the service that periodically logs information each slot.
-    pub fn slot_notifier(mut self) -> Result<Self, String> {
+    /// Immediately starts the service that runs fork choice and logs information at the start of
+    /// each slot.
+    pub fn slot_notifier(self) -> Result<Self, String> {
         let context = self
             .runtime_context
             .as_ref()
@@ -368,25 +553,52 @@ where
             .beacon_chain
             .clone()
             .ok_or_else(|| "slot_notifier requires a libp2p network")?;
-        let spec = self
-            .chain_spec
-            .clone()
-            .ok_or_else(|| "slot_notifier requires a chain spec".to_string())?;
-        let slot_duration = Duration::from_millis(spec.milliseconds_per_slot);
-        let duration_to_next_slot = beacon_chain
+        let interval = beacon_chain
             .slot_clock
-            .duration_to_next_slot()
+            .slot_interval(Duration::from_secs(0))
             .ok_or_else(|| "slot_notifier unable to determine time to next slot")?;
+        let notifier = self.notifier.clone();
+
+        // Log a countdown until genesis, after which the slot interval takes over.
+        if beacon_chain.slot_clock.now().is_none() {
+            let slot_clock = beacon_chain.slot_clock.clone();
+            let slot_clock_2 = slot_clock.clone();
+            let countdown_log = log.clone();
+            let countdown_log_2 = log.clone();
+
+            let countdown_future = Interval::new(
+                Instant::now(),
+                Duration::from_secs(GENESIS_COUNTDOWN_INTERVAL_SECONDS),
+            )
+            .map_err(move |e| {
+                error!(countdown_log_2, "Genesis countdown timer failed"; "error" => format!("{:?}", e))
+            })
+            .take_while(move |_| Ok(slot_clock.now().is_none()))
+            .for_each(move |_| {
+                if let Some(duration) = slot_clock_2.duration_to_next_slot() {
+                    info!(
+                        countdown_log,
+                        "Waiting for genesis";
+                        "seconds_to_genesis" => duration.as_secs(),
+                    );
+                }
 
-        let (exit_signal, exit) = exit_future::signal();
+                Ok(())
+            });
 
-        self.exit_signals.push(exit_signal);
+            context
+                .executor
+                .spawn(countdown_future, "genesis_countdown");
+        }
 
-        let interval_future = Interval::new(Instant::now() + duration_to_next_slot, slot_duration)
+        let interval_future = interval
             .map_err(move |e| error!(log_2, "Slot timer failed"; "error" => format!("{:?}", e)))
             .for_each(move |_| {
-                let best_slot = beacon_chain.head().beacon_block.slot;
-                let latest_block_root = beacon_chain.head().beacon_block_root;
+                beacon_chain.per_slot_task();
+
+                let head = beacon_chain.head();
+                let best_slot = head.beacon_block.slot;
+                let latest_block_root = head.beacon_block_root;
 
                 if let Ok(current_slot) = beacon_chain.slot() {
                     info!(
@@ -396,7 +608,24 @@ where
                         "best_block_root" => format!("{}", latest_block_root),
                         "best_block_slot" => best_slot,
                         "slot" => current_slot,
-                    )
+                    );
+
+                    let current_epoch = current_slot.epoch(TEthSpec::slots_per_epoch());
+                    let finalized_epoch = head.beacon_state.finalized_checkpoint.epoch;
+
+                    if current_epoch.saturating_sub(finalized_epoch) > WARN_FINALITY_DISTANCE_EPOCHS
+                    {
+                        warn!(
+                            log,
+                            "Chain is not finalizing";
+                            "finalized_epoch" => finalized_epoch,
+                            "current_epoch" => current_epoch,
+                        );
+                        notifier.notify(Event::FinalityStall {
+                            current_epoch: current_epoch.as_u64(),
+                            finalized_epoch: finalized_epoch.as_u64(),
+                        });
+                    }
                 } else {
                     error!(
                         log,
@@ -407,9 +636,7 @@ where
                 Ok(())
             });
 
-        context
-            .executor
-            .spawn(exit.until(interval_future).map(|_| ()));
+        context.executor.spawn(interval_future, "slot_notifier");
 
         Ok(self)
     }
@@ -420,30 +647,28 @@ where
     /// If type inference errors are being raised, see the comment on the definition of `Self`.
     pub fn build(
         self,
-    ) -> Client<Witness<TStore, TSlotClock, TLmdGhost, TEth1Backend, TEthSpec, TEventHandler>> {
+    ) -> Client<Witness<TStore, TStoreMigrator, TSlotClock, TEth1Backend, TEthSpec, TEventHandler>>
+    {
         Client {
             beacon_chain: self.beacon_chain,
             libp2p_network: self.libp2p_network,
             http_listen_addr: self.http_listen_addr,
+            grpc_listen_addr: self.grpc_listen_addr,
             websocket_listen_addr: self.websocket_listen_addr,
             _exit_signals: self.exit_signals,
+            #[cfg(feature = "otlp-tracing")]
+            _tracing_uninstall: self.tracing_uninstall,
         }
     }
 }
 
-impl<TStore, TSlotClock, TEth1Backend, TEthSpec, TEventHandler>
+impl<TStore, TStoreMigrator, TSlotClock, TEth1Backend, TEthSpec, TEventHandler>
     ClientBuilder<
-        Witness<
-            TStore,
-            TSlotClock,
-            ThreadSafeReducedTree<TStore, TEthSpec>,
-            TEth1Backend,
-            TEthSpec,
-            TEventHandler,
-        >,
+        Witness<TStore, TStoreMigrator, TSlotClock, TEth1Backend, TEthSpec, TEventHandler>,
     >
 where
     TStore: Store + 'static,
+    TStoreMigrator: Migrate<TStore, TEthSpec>,
     TSlotClock: SlotClock + Clone + 'static,
     TEth1Backend: Eth1ChainBackend<TEthSpec> + 'static,
     TEthSpec: EthSpec + 'static,
@@ -463,8 +688,6 @@ where
                     .clone()
                     .ok_or_else(|| "beacon_chain requires a slot clock")?,
             )
-            .empty_reduced_tree_fork_choice()
-            .map_err(|e| format!("Failed to init fork choice: {}", e))?
             .build()
             .map_err(|e| format!("Failed to build beacon chain: {}", e))?;
 
@@ -476,14 +699,21 @@ where
     }
 }
 
-impl<TStore, TSlotClock, TLmdGhost, TEth1Backend, TEthSpec>
+impl<TStore, TStoreMigrator, TSlotClock, TEth1Backend, TEthSpec>
     ClientBuilder<
-        Witness<TStore, TSlotClock, TLmdGhost, TEth1Backend, TEthSpec, WebSocketSender<TEthSpec>>,
+        Witness<
+            TStore,
+            TStoreMigrator,
+            TSlotClock,
+            TEth1Backend,
+            TEthSpec,
+            WebSocketSender<TEthSpec>,
+        >,
     >
 where
     TStore: Store + 'static,
+    TStoreMigrator: Migrate<TStore, TEthSpec>,
     TSlotClock: SlotClock + 'static,
-    TLmdGhost: LmdGhost<TStore, TEthSpec> + 'static,
     TEth1Backend: Eth1ChainBackend<TEthSpec> + 'static,
     TEthSpec: EthSpec + 'static,
 {
@@ -500,8 +730,11 @@ where
             Option<_>,
             Option<_>,
         ) = if config.enabled {
-            let (sender, exit, listening_addr) =
-                websocket_server::start_server(&config, &context.executor, &context.log)?;
+            let (sender, exit, listening_addr) = websocket_server::start_server(
+                &config,
+                context.executor.runtime_executor(),
+                &context.log,
+            )?;
             (sender, Some(exit), Some(listening_addr))
         } else {
             (WebSocketSender::dummy(), None, None)
@@ -517,31 +750,52 @@ where
     }
 }
 
-impl<TSlotClock, TLmdGhost, TEth1Backend, TEthSpec, TEventHandler>
-    ClientBuilder<Witness<DiskStore, TSlotClock, TLmdGhost, TEth1Backend, TEthSpec, TEventHandler>>
+impl<TStoreMigrator, TSlotClock, TEth1Backend, TEthSpec, TEventHandler>
+    ClientBuilder<
+        Witness<DiskStore, TStoreMigrator, TSlotClock, TEth1Backend, TEthSpec, TEventHandler>,
+    >
 where
+    TStoreMigrator: Migrate<DiskStore, TEthSpec>,
     TSlotClock: SlotClock + 'static,
-    TLmdGhost: LmdGhost<DiskStore, TEthSpec> + 'static,
     TEth1Backend: Eth1ChainBackend<TEthSpec> + 'static,
     TEthSpec: EthSpec + 'static,
     TEventHandler: EventHandler<TEthSpec> + 'static,
 {
-    /// Specifies that the `Client` should use a `DiskStore` database.
-    pub fn disk_store(mut self, path: &Path) -> Result<Self, String> {
-        let store = DiskStore::open(path)
-            .map_err(|e| format!("Unable to open database: {:?}", e).to_string())?;
+    /// Specifies that the `Client` should use a `DiskStore` database, with the hot database at
+    /// `hot_path` and the freezer database at `cold_path`, both backed by `backend`.
+    pub fn disk_store(
+        mut self,
+        hot_path: &Path,
+        cold_path: &Path,
+        mut config: StoreConfig,
+    ) -> Result<Self, String> {
+        config.state_cache_size = self.resource_budget.cache_size(config.state_cache_size);
+
+        let spec = self
+            .chain_spec
+            .clone()
+            .ok_or_else(|| "disk_store requires a chain spec".to_string())?;
+
+        let store = DiskStore::open(
+            hot_path,
+            cold_path,
+            config,
+            spec,
+            migrate_schema::<TEthSpec>,
+        )
+        .map_err(|e| format!("Unable to open database: {:?}", e).to_string())?;
         self.store = Some(Arc::new(store));
         Ok(self)
     }
 }
 
-impl<TSlotClock, TLmdGhost, TEth1Backend, TEthSpec, TEventHandler>
+impl<TStoreMigrator, TSlotClock, TEth1Backend, TEthSpec, TEventHandler>
     ClientBuilder<
-        Witness<MemoryStore, TSlotClock, TLmdGhost, TEth1Backend, TEthSpec, TEventHandler>,
+        Witness<MemoryStore, TStoreMigrator, TSlotClock, TEth1Backend, TEthSpec, TEventHandler>,
     >
 where
+    TStoreMigrator: Migrate<MemoryStore, TEthSpec>,
     TSlotClock: SlotClock + 'static,
-    TLmdGhost: LmdGhost<MemoryStore, TEthSpec> + 'static,
     TEth1Backend: Eth1ChainBackend<TEthSpec> + 'static,
     TEthSpec: EthSpec + 'static,
     TEventHandler: EventHandler<TEthSpec> + 'static,
@@ -554,12 +808,12 @@ where
     }
 }
 
-impl<TStore, TSlotClock, TLmdGhost, TEthSpec, TEventHandler>
+impl<TStore, TStoreMigrator, TSlotClock, TEthSpec, TEventHandler>
     ClientBuilder<
         Witness<
             TStore,
+            TStoreMigrator,
             TSlotClock,
-            TLmdGhost,
             CachingEth1Backend<TEthSpec, TStore>,
             TEthSpec,
             TEventHandler,
@@ -567,8 +821,8 @@ impl<TStore, TSlotClock, TLmdGhost, TEthSpec, TEventHandler>
     >
 where
     TStore: Store + 'static,
+    TStoreMigrator: Migrate<TStore, TEthSpec>,
     TSlotClock: SlotClock + 'static,
-    TLmdGhost: LmdGhost<TStore, TEthSpec> + 'static,
     TEthSpec: EthSpec + 'static,
     TEventHandler: EventHandler<TEthSpec> + 'static,
 {
@@ -576,6 +830,11 @@ where
     /// (e.g., Parity/Geth) and refer to that cache when collecting deposits or eth1 votes during
     /// block production.
     pub fn caching_eth1_backend(mut self, config: Eth1Config) -> Result<Self, String> {
+        let config = Eth1Config {
+            resource_budget: self.resource_budget.clone(),
+            notifier: self.notifier.clone(),
+            ..config
+        };
         let context = self
             .runtime_context
             .as_ref()
@@ -593,25 +852,38 @@ where
             eth1_service_from_genesis.update_config(config.clone())?;
             CachingEth1Backend::from_service(eth1_service_from_genesis, store)
         } else {
-            CachingEth1Backend::new(config, context.log, store)
+            let spec = context.eth2_config().spec.clone();
+            CachingEth1Backend::new(config, context.log, store, spec)
         };
 
         self.eth1_service = None;
 
-        let exit = {
-            let (tx, rx) = exit_future::signal();
-            self.exit_signals.push(tx);
-            rx
-        };
-
         // Starts the service that connects to an eth1 node and periodically updates caches.
-        context.executor.spawn(backend.start(exit));
+        //
+        // The service observes the exit signal itself, so that it is not stopped part way through
+        // an update.
+        context
+            .executor
+            .spawn_without_exit(backend.start(context.executor.exit()), "eth1");
 
         self.beacon_chain_builder = Some(beacon_chain_builder.eth1_backend(Some(backend)));
 
         Ok(self)
     }
 
+    /// Requires the `BeaconChain` to include `checkpoint` once it finalizes the epoch of the
+    /// checkpoint.
+    pub fn weak_subjectivity_checkpoint(mut self, checkpoint: Checkpoint) -> Result<Self, String> {
+        let beacon_chain_builder = self
+            .beacon_chain_builder
+            .ok_or_else(|| "weak_subjectivity_checkpoint requires a beacon_chain_builder")?;
+
+        self.beacon_chain_builder =
+            Some(beacon_chain_builder.weak_subjectivity_checkpoint(checkpoint));
+
+        Ok(self)
+    }
+
     /// Do not use any eth1 backend. The client will not be able to produce beacon blocks.
     pub fn no_eth1_backend(mut self) -> Result<Self, String> {
         let beacon_chain_builder = self
@@ -643,13 +915,13 @@ where
     }
 }
 
-impl<TStore, TLmdGhost, TEth1Backend, TEthSpec, TEventHandler>
+impl<TStore, TStoreMigrator, TEth1Backend, TEthSpec, TEventHandler>
     ClientBuilder<
-        Witness<TStore, SystemTimeSlotClock, TLmdGhost, TEth1Backend, TEthSpec, TEventHandler>,
+        Witness<TStore, TStoreMigrator, SystemTimeSlotClock, TEth1Backend, TEthSpec, TEventHandler>,
     >
 where
     TStore: Store + 'static,
-    TLmdGhost: LmdGhost<TStore, TEthSpec> + 'static,
+    TStoreMigrator: Migrate<TStore, TEthSpec>,
     TEth1Backend: Eth1ChainBackend<TEthSpec> + 'static,
     TEthSpec: EthSpec + 'static,
     TEventHandler: EventHandler<TEthSpec> + 'static,
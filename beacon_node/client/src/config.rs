@@ -1,8 +1,10 @@
 use clap::ArgMatches;
 use network::NetworkConfig;
+use resource_budget::ExecutionMode;
 use serde_derive::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
+use types::Checkpoint;
 
 /// The number initial validators when starting the `Minimal`.
 const TESTNET_SPEC_CONSTANTS: &str = "minimal";
@@ -39,6 +41,9 @@ pub struct Config {
     pub data_dir: PathBuf,
     pub db_type: String,
     db_name: String,
+    freezer_db_path: Option<PathBuf>,
+    /// Configuration for the hot and freezer databases.
+    pub store: store::StoreConfig,
     pub log_file: PathBuf,
     pub spec_constants: String,
     /// If true, the node will use co-ordinated junk for eth1 values.
@@ -46,12 +51,23 @@ pub struct Config {
     /// This is the method used for the 2019 client interop in Canada.
     pub dummy_eth1_backend: bool,
     pub sync_eth1_chain: bool,
+    /// A checkpoint which the canonical chain must include, as per `--wss-checkpoint`.
+    pub weak_subjectivity_checkpoint: Option<Checkpoint>,
+    /// The address of the OpenTelemetry collector to export `tracing` spans to, if any.
+    pub tracing_endpoint: Option<String>,
+    /// Determines how aggressively eth1 polling, sync and database migration use resources.
+    #[serde(default)]
+    pub execution_mode: ExecutionMode,
+    /// URLs to which critical events are POSTed, as per `--webhooks`.
+    #[serde(default)]
+    pub webhooks: Vec<String>,
     #[serde(skip)]
     /// The `genesis` field is not serialized or deserialized by `serde` to ensure it is defined
     /// via the CLI at runtime, instead of from a configuration file saved to disk.
     pub genesis: ClientGenesis,
     pub network: network::NetworkConfig,
     pub rest_api: rest_api::Config,
+    pub grpc: grpc_server::Config,
     pub websocket_server: websocket_server::Config,
     pub eth1: eth1::Config,
 }
@@ -63,13 +79,20 @@ impl Default for Config {
             log_file: PathBuf::from(""),
             db_type: "disk".to_string(),
             db_name: "chain_db".to_string(),
+            freezer_db_path: None,
+            store: <_>::default(),
             genesis: <_>::default(),
             network: NetworkConfig::new(),
             rest_api: <_>::default(),
+            grpc: <_>::default(),
             websocket_server: <_>::default(),
             spec_constants: TESTNET_SPEC_CONSTANTS.into(),
             dummy_eth1_backend: false,
             sync_eth1_chain: false,
+            weak_subjectivity_checkpoint: None,
+            tracing_endpoint: None,
+            execution_mode: ExecutionMode::Default,
+            webhooks: vec![],
             eth1: <_>::default(),
         }
     }
@@ -82,6 +105,15 @@ impl Config {
             .and_then(|path| Some(path.join(&self.db_name)))
     }
 
+    /// Returns the path to which the client may initialize the on-disk freezer database.
+    ///
+    /// Defaults to `freezer_db` within the data directory.
+    pub fn get_freezer_db_path(&self) -> Option<PathBuf> {
+        self.freezer_db_path
+            .clone()
+            .or_else(|| self.data_dir().map(|path| path.join("freezer_db")))
+    }
+
     /// Returns the core path for the client.
     ///
     /// Creates the directory if it does not exist.
@@ -104,8 +136,43 @@ impl Config {
             self.db_type = dir.to_string();
         };
 
+        if let Some(freezer_dir) = args.value_of("freezer-dir") {
+            self.freezer_db_path = Some(PathBuf::from(freezer_dir));
+        }
+
+        if let Some(slots_per_restore_point) = args.value_of("slots-per-restore-point") {
+            self.store.slots_per_restore_point = slots_per_restore_point
+                .parse::<u64>()
+                .map_err(|_| "slots-per-restore-point is not a valid u64.".to_string())?;
+        }
+
+        if let Some(db_backend) = args.value_of("db-backend") {
+            self.store.backend = db_backend.parse()?;
+        }
+
+        if let Some(archive) = args.value_of("archive") {
+            self.store.archive = archive
+                .parse::<bool>()
+                .map_err(|_| "archive is not a valid bool.".to_string())?;
+        }
+
+        if let Some(history_horizon) = args.value_of("history-horizon") {
+            self.store.history_horizon = history_horizon
+                .parse::<u64>()
+                .map_err(|_| "history-horizon is not a valid u64.".to_string())?;
+        }
+
+        if let Some(state_cache_size) = args.value_of("state-cache-size") {
+            self.store.state_cache_size = state_cache_size
+                .parse::<usize>()
+                .map_err(|_| "state-cache-size is not a valid usize.".to_string())?
+                * 1024
+                * 1024;
+        }
+
         self.network.apply_cli_args(args)?;
         self.rest_api.apply_cli_args(args)?;
+        self.grpc.apply_cli_args(args)?;
         self.websocket_server.apply_cli_args(args)?;
 
         Ok(())
@@ -1,6 +1,8 @@
 extern crate slog;
 
 mod config;
+#[cfg(feature = "otlp-tracing")]
+mod tracing_exporter;
 
 pub mod builder;
 pub mod error;
@@ -9,6 +11,8 @@ use beacon_chain::BeaconChain;
 use eth2_libp2p::{Enr, Multiaddr};
 use exit_future::Signal;
 use network::Service as NetworkService;
+#[cfg(feature = "otlp-tracing")]
+use opentelemetry_otlp::Uninstall;
 use std::net::SocketAddr;
 use std::sync::Arc;
 
@@ -24,9 +28,13 @@ pub struct Client<T: BeaconChainTypes> {
     beacon_chain: Option<Arc<BeaconChain<T>>>,
     libp2p_network: Option<Arc<NetworkService<T>>>,
     http_listen_addr: Option<SocketAddr>,
+    grpc_listen_addr: Option<SocketAddr>,
     websocket_listen_addr: Option<SocketAddr>,
     /// Exit signals will "fire" when dropped, causing each service to exit gracefully.
     _exit_signals: Vec<Signal>,
+    /// Stops exporting `tracing` spans when dropped.
+    #[cfg(feature = "otlp-tracing")]
+    _tracing_uninstall: Option<Uninstall>,
 }
 
 impl<T: BeaconChainTypes> Client<T> {
@@ -40,6 +48,11 @@ impl<T: BeaconChainTypes> Client<T> {
         self.http_listen_addr
     }
 
+    /// Returns the address of the client's gRPC server, if it was started.
+    pub fn grpc_listen_addr(&self) -> Option<SocketAddr> {
+        self.grpc_listen_addr
+    }
+
     /// Returns the address of the client's WebSocket API server, if it was started.
     pub fn websocket_listen_addr(&self) -> Option<SocketAddr> {
         self.websocket_listen_addr
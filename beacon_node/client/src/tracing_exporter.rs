@@ -0,0 +1,23 @@
+//! Exports the `tracing` spans of the beacon node to an OpenTelemetry collector via OTLP.
+//!
+//! The HTTP API opens a span for each request, within which the beacon chain and the database open
+//! spans for state reads, committee computation and lock acquisition. Exporting them allows the
+//! time spent serving a slow request to be attributed to each.
+use opentelemetry_otlp::Uninstall;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+/// Installs the global `tracing` subscriber, exporting spans to the collector at `endpoint`.
+///
+/// Spans are exported until the returned value is dropped.
+pub fn install(endpoint: &str) -> Result<Uninstall, String> {
+    let (tracer, uninstall) = opentelemetry_otlp::new_pipeline()
+        .with_endpoint(endpoint)
+        .install()
+        .map_err(|e| format!("Unable to start OTLP exporter: {:?}", e))?;
+
+    let subscriber = Registry::default().with(tracing_opentelemetry::layer().with_tracer(tracer));
+    tracing::subscriber::set_global_default(subscriber)
+        .map_err(|e| format!("Unable to set tracing subscriber: {:?}", e))?;
+
+    Ok(uninstall)
+}
@@ -1,3 +1,4 @@
+use std::mem;
 use std::ops::RangeInclusive;
 use types::{Eth1Data, Hash256};
 
@@ -27,6 +28,13 @@ pub struct Eth1Block {
 }
 
 impl Eth1Block {
+    /// Returns the approximate number of bytes occupied by a block in the `BlockCache`.
+    ///
+    /// The block has no heap allocations, so this is its size in the cache's vector.
+    pub fn approximate_size() -> usize {
+        mem::size_of::<Self>()
+    }
+
     pub fn eth1_data(self) -> Option<Eth1Data> {
         Some(Eth1Data {
             deposit_root: self.deposit_root?,
@@ -54,6 +62,31 @@ impl BlockCache {
         self.blocks.is_empty()
     }
 
+    /// Returns the approximate number of bytes occupied by the blocks in `self`.
+    pub fn approximate_size(&self) -> usize {
+        self.blocks.capacity() * Eth1Block::approximate_size()
+    }
+
+    /// Returns the number of blocks which may be stored within approximately `max_bytes`.
+    pub fn len_for_size(max_bytes: usize) -> usize {
+        max_bytes / Eth1Block::approximate_size()
+    }
+
+    /// Returns the number of blocks with a timestamp less than `seconds` prior to that of the
+    /// latest block.
+    pub fn len_within_seconds_of_latest(&self, seconds: u64) -> usize {
+        let latest_timestamp = match self.blocks.last() {
+            Some(latest) => latest.timestamp,
+            None => return 0,
+        };
+
+        self.blocks
+            .iter()
+            .rev()
+            .take_while(|block| latest_timestamp.saturating_sub(block.timestamp) < seconds)
+            .count()
+    }
+
     /// Returns the highest block number stored.
     pub fn highest_block_number(&self) -> Option<u64> {
         self.blocks.last().map(|block| block.number)
@@ -220,6 +253,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn size_accounting() {
+        let n = 16;
+        let mut cache = BlockCache::default();
+
+        for block in get_blocks(n, 10) {
+            insert(&mut cache, block).expect("should add consecutive blocks");
+        }
+        cache.truncate(8);
+
+        assert_eq!(
+            cache.approximate_size(),
+            8 * Eth1Block::approximate_size(),
+            "truncation should release memory"
+        );
+        assert_eq!(
+            BlockCache::len_for_size(cache.approximate_size() + 1),
+            8,
+            "should only count whole blocks"
+        );
+    }
+
+    #[test]
+    fn len_within_seconds_of_latest() {
+        let n = 16;
+        let mut cache = BlockCache::default();
+
+        assert_eq!(cache.len_within_seconds_of_latest(100), 0);
+
+        for block in get_blocks(n, 10) {
+            insert(&mut cache, block).expect("should add consecutive blocks");
+        }
+
+        assert_eq!(cache.len_within_seconds_of_latest(0), 0);
+        assert_eq!(cache.len_within_seconds_of_latest(25), 3);
+        assert_eq!(cache.len_within_seconds_of_latest(30), 3);
+        assert_eq!(cache.len_within_seconds_of_latest(31), 4);
+        assert_eq!(cache.len_within_seconds_of_latest(u64::max_value()), n);
+    }
+
     #[test]
     fn inserts() {
         let n = 16;
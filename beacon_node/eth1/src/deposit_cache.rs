@@ -2,7 +2,7 @@ use crate::DepositLog;
 use eth2_hashing::hash;
 use std::ops::Range;
 use tree_hash::TreeHash;
-use types::{Deposit, Hash256};
+use types::{Deposit, Hash256, PublicKeyBytes};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Error {
@@ -104,6 +104,17 @@ impl DepositCache {
         self.logs.get(i)
     }
 
+    /// Returns the logs of the deposits to the validator with `pubkey`, in order of deposit index.
+    ///
+    /// Includes deposits with invalid signatures, since they are still part of the deposit
+    /// contract tree.
+    pub fn deposits_for_pubkey(&self, pubkey: &PublicKeyBytes) -> Vec<&DepositLog> {
+        self.logs
+            .iter()
+            .filter(|log| log.deposit_data.pubkey == *pubkey)
+            .collect()
+    }
+
     /// Adds `log` to self.
     ///
     /// This function enforces that `logs` are imported one-by-one with no gaps between
@@ -134,6 +145,42 @@ impl DepositCache {
         }
     }
 
+    /// Adds `logs` to the cache, in order, as per `Self::insert_log`.
+    ///
+    /// The indices of `logs` are checked for continuity before any log is inserted, so that a gap
+    /// in `logs` does not leave `self` partially updated.
+    ///
+    /// ## Errors
+    ///
+    /// - If the indices of `logs` are not consecutive, or the first log does not follow on from
+    /// (or overlap with) the logs in `self`.
+    /// - If a log is distinct to a known log with the same index. In this case, the logs prior to
+    /// the distinct log will have been inserted.
+    pub fn insert_logs(&mut self, logs: Vec<DepositLog>) -> Result<(), Error> {
+        let mut expected = match logs.first() {
+            Some(log) if log.index <= self.logs.len() as u64 => log.index,
+            Some(log) => {
+                return Err(Error::NonConsecutive {
+                    log_index: log.index,
+                    expected: self.logs.len(),
+                })
+            }
+            None => return Ok(()),
+        };
+
+        for log in &logs {
+            if log.index != expected {
+                return Err(Error::NonConsecutive {
+                    log_index: log.index,
+                    expected: expected as usize,
+                });
+            }
+            expected += 1;
+        }
+
+        logs.into_iter().try_for_each(|log| self.insert_log(log))
+    }
+
     /// Returns a list of `Deposit` objects, within the given deposit index `range`.
     ///
     /// The `deposit_count` is used to generate the proofs for the `Deposits`. For example, if we
@@ -203,6 +250,21 @@ impl DepositCache {
             Ok((tree.root(), deposits))
         }
     }
+
+    /// Returns the root of the deposit contract merkle tree once it contained the first
+    /// `deposit_count` deposits.
+    ///
+    /// ## Errors
+    ///
+    /// - There are fewer than `deposit_count` deposits in the cache.
+    pub fn get_deposit_root(
+        &self,
+        deposit_count: u64,
+        tree_depth: usize,
+    ) -> Result<Hash256, Error> {
+        self.get_deposits(0..0, deposit_count, tree_depth)
+            .map(|(root, _deposits)| root)
+    }
 }
 
 /// Returns `int` as little-endian bytes with a length of 32.
@@ -217,15 +279,16 @@ pub mod tests {
     use super::*;
     use crate::deposit_log::tests::EXAMPLE_LOG;
     use crate::http::Log;
+    use types::{EthSpec, MainnetEthSpec};
 
     pub const TREE_DEPTH: usize = 32;
 
-    fn example_log() -> DepositLog {
+    pub fn example_log() -> DepositLog {
         let log = Log {
             block_number: 42,
             data: EXAMPLE_LOG.to_vec(),
         };
-        DepositLog::from_log(&log).expect("should decode log")
+        DepositLog::from_log(&log, &MainnetEthSpec::default_spec()).expect("should decode log")
     }
 
     #[test]
@@ -266,6 +329,71 @@ pub mod tests {
         assert!(tree.insert_log(log).is_err());
     }
 
+    #[test]
+    fn insert_logs_checks_continuity_first() {
+        let mut tree = DepositCache::default();
+
+        let logs = |indices: &[u64]| {
+            indices
+                .iter()
+                .map(|i| {
+                    let mut log = example_log();
+                    log.index = *i;
+                    log
+                })
+                .collect::<Vec<_>>()
+        };
+
+        tree.insert_logs(logs(&[0, 1, 2]))
+            .expect("should add consecutive logs");
+        assert_eq!(tree.len(), 3);
+
+        // Overlapping with known logs is permitted.
+        tree.insert_logs(logs(&[2, 3]))
+            .expect("should add overlapping logs");
+        assert_eq!(tree.len(), 4);
+
+        // Nothing is inserted if there is a gap in the batch.
+        assert!(tree.insert_logs(logs(&[4, 5, 7])).is_err());
+        assert_eq!(tree.len(), 4);
+
+        // Nothing is inserted if the batch does not follow on from the cache.
+        assert!(tree.insert_logs(logs(&[5, 6])).is_err());
+        assert_eq!(tree.len(), 4);
+
+        tree.insert_logs(vec![])
+            .expect("should accept an empty batch");
+        assert_eq!(tree.len(), 4);
+    }
+
+    #[test]
+    fn deposits_for_pubkey() {
+        let mut tree = DepositCache::default();
+        let pubkey = example_log().deposit_data.pubkey;
+        let other_pubkey = PublicKeyBytes::from_bytes(&[42; 48]).expect("should decode pubkey");
+
+        for i in 0..8 {
+            let mut log = example_log();
+            log.index = i;
+            if i % 2 == 1 {
+                log.deposit_data.pubkey = other_pubkey.clone();
+            }
+            tree.insert_log(log).expect("should add consecutive logs")
+        }
+
+        let indices = |pubkey| {
+            tree.deposits_for_pubkey(pubkey)
+                .iter()
+                .map(|log| log.index)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(indices(&pubkey), vec![0, 2, 4, 6]);
+        assert_eq!(indices(&other_pubkey), vec![1, 3, 5, 7]);
+
+        let unknown_pubkey = PublicKeyBytes::from_bytes(&[7; 48]).expect("should decode pubkey");
+        assert!(tree.deposits_for_pubkey(&unknown_pubkey).is_empty());
+    }
+
     #[test]
     fn get_deposit_valid() {
         let n = 1_024;
@@ -327,6 +455,21 @@ pub mod tests {
             "should return half deposits"
         );
 
+        assert_eq!(
+            tree.get_deposit_root(n, TREE_DEPTH),
+            Ok(full_root),
+            "should get the full root"
+        );
+        assert_eq!(
+            tree.get_deposit_root(n / 2, TREE_DEPTH),
+            Ok(half_root),
+            "should get the half root"
+        );
+        assert!(
+            tree.get_deposit_root(n + 1, TREE_DEPTH).is_err(),
+            "should not get a root for unknown deposits"
+        );
+
         // Get 4 deposits, with half deposit count.
         let (root, deposits) = tree
             .get_deposits(0..4, n / 2, TREE_DEPTH)
@@ -1,9 +1,16 @@
 use super::http::Log;
 use ssz::Decode;
-use types::{DepositData, Hash256, PublicKeyBytes, SignatureBytes};
+use ssz_derive::{Decode, Encode};
+use state_processing::per_block_processing::verify_deposit_signature;
+use types::{ChainSpec, DepositData, Hash256, PublicKeyBytes, SignatureBytes};
 
 /// The following constants define the layout of bytes in the deposit contract `DepositEvent`. The
 /// event bytes are formatted according to the  Ethereum ABI.
+///
+/// The event has five `bytes` fields. The head is one 32-byte offset word per field, each offset
+/// pointing to a 32-byte length word which is followed by the field bytes (right-padded to a
+/// multiple of 32 bytes).
+const ABI_WORD_LEN: usize = 32;
 const PUBKEY_START: usize = 192;
 const PUBKEY_LEN: usize = 48;
 const CREDS_START: usize = PUBKEY_START + 64 + 32;
@@ -14,22 +21,86 @@ const SIG_START: usize = AMOUNT_START + 32 + 32;
 const SIG_LEN: usize = 96;
 const INDEX_START: usize = SIG_START + 96 + 32;
 const INDEX_LEN: usize = 8;
+/// The total length of a `DepositEvent`.
+const DEPOSIT_EVENT_LEN: usize = INDEX_START + 32;
+
+/// The `(name, start, len)` of each field in the `DepositEvent`, in the order of the head.
+const FIELDS: [(&str, usize, usize); 5] = [
+    ("pubkey", PUBKEY_START, PUBKEY_LEN),
+    ("withdrawal_credentials", CREDS_START, CREDS_LEN),
+    ("amount", AMOUNT_START, AMOUNT_LEN),
+    ("signature", SIG_START, SIG_LEN),
+    ("index", INDEX_START, INDEX_LEN),
+];
+
+/// Reads the 32-byte big-endian ABI word at `start`, returning an error if it does not fit in a
+/// `u64`.
+fn read_abi_word(bytes: &[u8], start: usize) -> Result<u64, String> {
+    let word = bytes
+        .get(start..start + ABI_WORD_LEN)
+        .ok_or_else(|| format!("Insufficient bytes for ABI word at {}", start))?;
+    let (high, low) = word.split_at(ABI_WORD_LEN - 8);
+
+    if high.iter().any(|byte| *byte != 0) {
+        return Err(format!("ABI word at {} exceeds u64", start));
+    }
+
+    let mut array = [0; 8];
+    array.copy_from_slice(low);
+    Ok(u64::from_be_bytes(array))
+}
+
+/// Checks that `bytes` has the fixed length of a `DepositEvent` and that the offset and length
+/// of each field are as expected.
+///
+/// The layout never changes, so this is sufficient to decode each field from a constant position.
+fn verify_layout(bytes: &[u8]) -> Result<(), String> {
+    if bytes.len() != DEPOSIT_EVENT_LEN {
+        return Err(format!(
+            "Invalid deposit log length. Expected {}, got {}",
+            DEPOSIT_EVENT_LEN,
+            bytes.len()
+        ));
+    }
+
+    for (i, (name, start, len)) in FIELDS.iter().enumerate() {
+        let offset = read_abi_word(bytes, i * ABI_WORD_LEN)?;
+        if offset != (start - ABI_WORD_LEN) as u64 {
+            return Err(format!("Invalid {} offset: {}", name, offset));
+        }
+
+        let length = read_abi_word(bytes, start - ABI_WORD_LEN)?;
+        if length != *len as u64 {
+            return Err(format!("Invalid {} length: {}", name, length));
+        }
+    }
+
+    Ok(())
+}
 
 /// A fully parsed eth1 deposit contract log.
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Encode, Decode)]
 pub struct DepositLog {
     pub deposit_data: DepositData,
     /// The block number of the log that included this `DepositData`.
     pub block_number: u64,
     /// The index included with the deposit log.
     pub index: u64,
+    /// True if the signature of `deposit_data` is valid.
+    ///
+    /// A deposit with an invalid signature is still included in the deposit contract tree, it is
+    /// just ignored by the state transition if it creates a new validator.
+    pub signature_is_valid: bool,
 }
 
 impl DepositLog {
-    /// Attempts to parse a raw `Log` from the deposit contract into a `DepositLog`.
-    pub fn from_log(log: &Log) -> Result<Self, String> {
+    /// Attempts to parse a raw `Log` from the deposit contract into a `DepositLog`, verifying the
+    /// signature of the deposit.
+    pub fn from_log(log: &Log, spec: &ChainSpec) -> Result<Self, String> {
         let bytes = &log.data;
 
+        verify_layout(bytes)?;
+
         let pubkey = bytes
             .get(PUBKEY_START..PUBKEY_START + PUBKEY_LEN)
             .ok_or_else(|| "Insufficient bytes for pubkey".to_string())?;
@@ -57,10 +128,13 @@ impl DepositLog {
                 .map_err(|e| format!("Invalid signature ssz: {:?}", e))?,
         };
 
+        let signature_is_valid = verify_deposit_signature(&deposit_data, spec).is_ok();
+
         Ok(DepositLog {
             deposit_data,
             block_number: log.block_number,
             index: u64::from_ssz_bytes(index).map_err(|e| format!("Invalid index ssz: {:?}", e))?,
+            signature_is_valid,
         })
     }
 }
@@ -69,6 +143,8 @@ impl DepositLog {
 pub mod tests {
     use super::*;
     use crate::http::Log;
+    use ssz::Encode;
+    use types::{EthSpec, MainnetEthSpec};
 
     /// The data from a deposit event, using the v0.8.3 version of the deposit contract.
     pub const EXAMPLE_LOG: &[u8] = &[
@@ -96,12 +172,102 @@ pub mod tests {
         0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
     ];
 
-    #[test]
-    fn can_parse_example_log() {
-        let log = Log {
+    fn spec() -> ChainSpec {
+        MainnetEthSpec::default_spec()
+    }
+
+    fn example_log() -> Log {
+        Log {
             block_number: 42,
             data: EXAMPLE_LOG.to_vec(),
-        };
-        DepositLog::from_log(&log).expect("should decode log");
+        }
+    }
+
+    #[test]
+    fn can_parse_example_log() {
+        let deposit_log = DepositLog::from_log(&example_log(), &spec()).expect("should decode log");
+
+        assert_eq!(deposit_log.block_number, 42);
+        assert_eq!(deposit_log.index, 7);
+        assert_eq!(deposit_log.deposit_data.amount, 32_000_000_000);
+        assert_eq!(
+            deposit_log.deposit_data.withdrawal_credentials,
+            Hash256::zero()
+        );
+        assert_eq!(
+            &deposit_log.deposit_data.pubkey.as_ssz_bytes()[..],
+            &EXAMPLE_LOG[PUBKEY_START..PUBKEY_START + PUBKEY_LEN]
+        );
+        assert_eq!(
+            &deposit_log.deposit_data.signature.as_ssz_bytes()[..],
+            &EXAMPLE_LOG[SIG_START..SIG_START + SIG_LEN]
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_and_extended_logs() {
+        for len in 0..DEPOSIT_EVENT_LEN {
+            let mut log = example_log();
+            log.data.truncate(len);
+            assert!(
+                DepositLog::from_log(&log, &spec()).is_err(),
+                "should reject log of length {}",
+                len
+            );
+        }
+
+        let mut log = example_log();
+        log.data.extend_from_slice(&[0; ABI_WORD_LEN]);
+        assert!(DepositLog::from_log(&log, &spec()).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_offsets() {
+        for i in 0..FIELDS.len() {
+            let mut log = example_log();
+            log.data[(i + 1) * ABI_WORD_LEN - 1] ^= 1;
+            assert!(
+                DepositLog::from_log(&log, &spec()).is_err(),
+                "should reject invalid offset for field {}",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_lengths() {
+        for (name, start, _) in FIELDS.iter() {
+            let mut log = example_log();
+            log.data[start - 1] ^= 1;
+            assert!(
+                DepositLog::from_log(&log, &spec()).is_err(),
+                "should reject invalid {} length",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_oversized_abi_words() {
+        for (name, start, _) in FIELDS.iter() {
+            let mut log = example_log();
+            log.data[start - ABI_WORD_LEN] = 1;
+            assert!(
+                DepositLog::from_log(&log, &spec()).is_err(),
+                "should reject oversized {} length",
+                name
+            );
+        }
+
+        let mut log = example_log();
+        log.data[0] = 1;
+        assert!(DepositLog::from_log(&log, &spec()).is_err());
+    }
+
+    #[test]
+    fn read_abi_word_bounds() {
+        assert_eq!(read_abi_word(EXAMPLE_LOG, 0), Ok(160));
+        assert!(read_abi_word(EXAMPLE_LOG, DEPOSIT_EVENT_LEN - ABI_WORD_LEN + 1).is_err());
+        assert!(read_abi_word(&[], 0).is_err());
     }
 }
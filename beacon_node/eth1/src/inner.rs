@@ -1,6 +1,9 @@
 use crate::Config;
-use crate::{block_cache::BlockCache, deposit_cache::DepositCache};
+use crate::{block_cache::BlockCache, deposit_cache::DepositCache, DepositLog};
 use parking_lot::RwLock;
+use ssz_derive::{Decode, Encode};
+use std::cmp;
+use types::ChainSpec;
 
 #[derive(Default)]
 pub struct DepositUpdater {
@@ -8,20 +11,247 @@ pub struct DepositUpdater {
     pub last_processed_block: Option<u64>,
 }
 
-#[derive(Default)]
+impl DepositUpdater {
+    /// Returns a snapshot of `self` which may be imported by another node with `Self::import`.
+    pub fn as_ssz_container(&self) -> SszDepositCache {
+        SszDepositCache {
+            logs: self.cache.iter().cloned().collect(),
+            last_processed_block: self.last_processed_block,
+        }
+    }
+
+    /// Replaces the cache with the snapshot in `container`, returning the number of deposits that
+    /// were not already known.
+    ///
+    /// The snapshot is ignored if it has not processed more eth1 blocks than `self`. `self` is not
+    /// modified if the snapshot is invalid or if it conflicts with any known deposit.
+    pub fn import(&mut self, container: SszDepositCache) -> Result<usize, String> {
+        let last_processed_block = match container.last_processed_block {
+            Some(block)
+                if self
+                    .last_processed_block
+                    .map_or(true, |known| block > known) =>
+            {
+                block
+            }
+            _ => return Ok(0),
+        };
+
+        if let Some(log) = container
+            .logs
+            .iter()
+            .find(|log| log.block_number > last_processed_block)
+        {
+            return Err(format!(
+                "Deposit {} is from block {}, after the last processed block {}",
+                log.index, log.block_number, last_processed_block
+            ));
+        }
+
+        let mut cache = DepositCache::default();
+        for log in container.logs {
+            cache
+                .insert_log(log)
+                .map_err(|e| format!("Invalid deposit log: {:?}", e))?;
+        }
+
+        if let Some(index) = (0..self.cache.len()).find(|&i| self.cache.get(i) != cache.get(i)) {
+            return Err(format!(
+                "Deposit {} conflicts with the known deposit",
+                index
+            ));
+        }
+
+        let imported = cache.len() - self.cache.len();
+        self.cache = cache;
+        self.last_processed_block = Some(last_processed_block);
+
+        Ok(imported)
+    }
+}
+
+/// An SSZ-encodable snapshot of a `DepositUpdater`, allowing a node to bootstrap its deposit cache
+/// from another node rather than downloading the logs from an eth1 node.
+#[derive(Encode, Decode)]
+pub struct SszDepositCache {
+    logs: Vec<DepositLog>,
+    last_processed_block: Option<u64>,
+}
+
 pub struct Inner {
     pub block_cache: RwLock<BlockCache>,
     pub deposit_cache: RwLock<DepositUpdater>,
     pub config: RwLock<Config>,
+    /// The number of slots in an eth1 voting period, from the `EthSpec` of the chain.
+    pub slots_per_eth1_voting_period: u64,
+    pub spec: ChainSpec,
 }
 
 impl Inner {
-    /// Prunes the block cache to `self.target_block_cache_len`.
+    /// Returns the number of blocks that the block cache should be truncated to, or `None` if it
+    /// should not be truncated.
     ///
-    /// Is a no-op if `self.target_block_cache_len` is `None`.
+    /// The length is derived from `block_cache_max_bytes` (which is reduced when the node is in
+    /// low-power mode), but always includes the blocks which may be required for eth1 voting:
+    /// those from the current and previous voting periods (the `Eth1Data` at the start of the
+    /// current period was voted upon in the previous one) and the `follow_distance` blocks prior.
+    pub fn block_cache_max_len(&self) -> Option<usize> {
+        let config = self.config.read();
+        let max_bytes = config
+            .resource_budget
+            .cache_size(config.block_cache_max_bytes?);
+
+        let voting_period_seconds =
+            self.slots_per_eth1_voting_period * self.spec.milliseconds_per_slot / 1_000;
+        let min_len = self
+            .block_cache
+            .read()
+            .len_within_seconds_of_latest(voting_period_seconds * 2)
+            + config.follow_distance as usize;
+
+        Some(cmp::max(BlockCache::len_for_size(max_bytes), min_len))
+    }
+
+    /// Prunes the block cache to `self.block_cache_max_len()`.
+    ///
+    /// Is a no-op if `self.block_cache_max_len()` is `None`.
     pub fn prune_blocks(&self) {
-        if let Some(block_cache_truncation) = self.config.read().block_cache_truncation {
-            self.block_cache.write().truncate(block_cache_truncation);
+        if let Some(max_len) = self.block_cache_max_len() {
+            self.block_cache.write().truncate(max_len);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deposit_cache::tests::example_log;
+    use crate::Eth1Block;
+    use ssz::{Decode, Encode};
+    use types::{EthSpec, Hash256, MinimalEthSpec};
+
+    fn updater(num_logs: u64, last_processed_block: u64) -> DepositUpdater {
+        let mut updater = DepositUpdater::default();
+        for i in 0..num_logs {
+            let mut log = example_log();
+            log.index = i;
+            log.block_number = i;
+            updater.cache.insert_log(log).expect("should add log");
+        }
+        updater.last_processed_block = Some(last_processed_block);
+        updater
+    }
+
+    fn snapshot(updater: &DepositUpdater) -> SszDepositCache {
+        let bytes = updater.as_ssz_container().as_ssz_bytes();
+        SszDepositCache::from_ssz_bytes(&bytes).expect("should decode snapshot")
+    }
+
+    #[test]
+    fn import_into_empty_cache() {
+        let source = updater(8, 10);
+        let mut target = DepositUpdater::default();
+
+        assert_eq!(target.import(snapshot(&source)), Ok(8));
+        assert_eq!(target.cache.len(), 8);
+        assert_eq!(target.last_processed_block, Some(10));
+        assert!(target.cache.iter().eq(source.cache.iter()));
+    }
+
+    #[test]
+    fn import_extends_known_deposits() {
+        let source = updater(8, 10);
+        let mut target = updater(4, 4);
+
+        assert_eq!(target.import(snapshot(&source)), Ok(4));
+        assert_eq!(target.cache.len(), 8);
+        assert_eq!(target.last_processed_block, Some(10));
+    }
+
+    #[test]
+    fn import_ignores_stale_snapshot() {
+        let source = updater(4, 4);
+        let mut target = updater(8, 10);
+
+        assert_eq!(target.import(snapshot(&source)), Ok(0));
+        assert_eq!(target.cache.len(), 8);
+        assert_eq!(target.last_processed_block, Some(10));
+    }
+
+    #[test]
+    fn import_rejects_conflicting_deposits() {
+        let source = updater(8, 10);
+        let mut target = updater(4, 4);
+        target.cache = DepositCache::default();
+        for i in 0..4 {
+            let mut log = example_log();
+            log.index = i;
+            log.block_number = i + 1;
+            target.cache.insert_log(log).expect("should add log");
+        }
+
+        assert!(target.import(snapshot(&source)).is_err());
+        assert_eq!(target.cache.len(), 4);
+        assert_eq!(target.last_processed_block, Some(4));
+    }
+
+    #[test]
+    fn import_rejects_logs_after_last_processed_block() {
+        let source = updater(8, 6);
+        let mut target = DepositUpdater::default();
+
+        assert!(target.import(snapshot(&source)).is_err());
+        assert!(target.cache.is_empty());
+    }
+
+    #[test]
+    fn block_cache_retains_voting_blocks() {
+        let spec = MinimalEthSpec::default_spec();
+        let voting_period_seconds = MinimalEthSpec::slots_per_eth1_voting_period() as u64
+            * spec.milliseconds_per_slot
+            / 1_000;
+        let follow_distance = 4;
+
+        // One block every 10 seconds, spanning four voting periods.
+        let block_interval = 10;
+        let mut block_cache = BlockCache::default();
+        for number in 0..voting_period_seconds * 4 / block_interval {
+            block_cache
+                .insert_root_or_child(Eth1Block {
+                    hash: Hash256::from_low_u64_be(number),
+                    timestamp: number * block_interval,
+                    number,
+                    deposit_root: None,
+                    deposit_count: None,
+                })
+                .expect("should insert block");
+        }
+
+        let inner = Inner {
+            block_cache: RwLock::new(block_cache),
+            deposit_cache: RwLock::new(DepositUpdater::default()),
+            config: RwLock::new(Config {
+                follow_distance,
+                block_cache_max_bytes: Some(1),
+                ..Config::default()
+            }),
+            slots_per_eth1_voting_period: MinimalEthSpec::slots_per_eth1_voting_period() as u64,
+            spec,
+        };
+
+        // The blocks less than two voting periods prior to the latest.
+        let voting_len =
+            ((voting_period_seconds * 2 + block_interval - 1) / block_interval) as usize;
+        assert_eq!(
+            inner.block_cache_max_len(),
+            Some(voting_len + follow_distance as usize),
+            "two voting periods and the follow distance should be retained"
+        );
+
+        inner.prune_blocks();
+        assert_eq!(
+            inner.block_cache.read().len(),
+            voting_len + follow_distance as usize
+        );
+    }
+}
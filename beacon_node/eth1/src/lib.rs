@@ -1,8 +1,12 @@
+#[macro_use]
+extern crate lazy_static;
+
 mod block_cache;
 mod deposit_cache;
 mod deposit_log;
 pub mod http;
 mod inner;
+mod metrics;
 mod service;
 
 pub use block_cache::{BlockCache, Eth1Block};
@@ -0,0 +1,43 @@
+pub use lighthouse_metrics::*;
+
+lazy_static! {
+    /*
+     * Updates
+     */
+    pub static ref UPDATE_TIMES: Result<Histogram> = try_create_histogram(
+        "eth1_update_seconds",
+        "Time taken to update both the deposit and block caches from the eth1 node"
+    );
+    pub static ref DEPOSIT_CACHE_UPDATE_FAILURES: Result<IntCounter> = try_create_int_counter(
+        "eth1_deposit_cache_update_failures_total",
+        "Count of failed attempts to update the deposit cache"
+    );
+    pub static ref BLOCK_CACHE_UPDATE_FAILURES: Result<IntCounter> = try_create_int_counter(
+        "eth1_block_cache_update_failures_total",
+        "Count of failed attempts to update the block cache"
+    );
+
+    /*
+     * Caches
+     */
+    pub static ref DEPOSIT_CACHE_LEN: Result<IntGauge> = try_create_int_gauge(
+        "eth1_deposit_cache_len",
+        "Number of deposits in the eth1 deposit cache"
+    );
+    pub static ref HIGHEST_PROCESSED_DEPOSIT_BLOCK: Result<IntGauge> = try_create_int_gauge(
+        "eth1_highest_processed_deposit_block",
+        "Number of the highest block checked for deposits"
+    );
+    pub static ref BLOCK_CACHE_LEN: Result<IntGauge> = try_create_int_gauge(
+        "eth1_block_cache_len",
+        "Number of blocks in the eth1 block cache"
+    );
+    pub static ref BLOCK_CACHE_SIZE: Result<IntGauge> = try_create_int_gauge(
+        "eth1_block_cache_size_bytes",
+        "Approximate number of bytes occupied by the eth1 block cache"
+    );
+    pub static ref LATEST_CACHED_BLOCK_NUMBER: Result<IntGauge> = try_create_int_gauge(
+        "eth1_latest_cached_block_number",
+        "Number of the latest block in the eth1 block cache"
+    );
+}
@@ -4,21 +4,26 @@ use crate::{
     http::{
         get_block, get_block_number, get_deposit_count, get_deposit_logs_in_range, get_deposit_root,
     },
-    inner::{DepositUpdater, Inner},
-    DepositLog,
+    inner::{DepositUpdater, Inner, SszDepositCache},
+    metrics, DepositLog,
 };
 use exit_future::Exit;
 use futures::{
     future::{loop_fn, Loop},
     stream, Future, Stream,
 };
+use notifier::{Event, Notifier};
 use parking_lot::{RwLock, RwLockReadGuard};
+use rayon::prelude::*;
+use resource_budget::ResourceBudget;
 use serde::{Deserialize, Serialize};
 use slog::{debug, error, trace, Logger};
+use ssz::{Decode, Encode};
 use std::ops::{Range, RangeInclusive};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::timer::Delay;
+use types::{ChainSpec, EthSpec, PublicKeyBytes};
 
 const STANDARD_TIMEOUT_MILLIS: u64 = 15_000;
 
@@ -32,6 +37,8 @@ const GET_DEPOSIT_ROOT_TIMEOUT_MILLIS: u64 = STANDARD_TIMEOUT_MILLIS;
 const GET_DEPOSIT_COUNT_TIMEOUT_MILLIS: u64 = STANDARD_TIMEOUT_MILLIS;
 /// Timeout when doing an eth_getLogs to read the deposit contract logs.
 const GET_DEPOSIT_LOG_TIMEOUT_MILLIS: u64 = STANDARD_TIMEOUT_MILLIS;
+/// The default size of the block cache after pruning, enough for several thousand blocks.
+const DEFAULT_BLOCK_CACHE_MAX_BYTES: usize = 512 * 1_024;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Error {
@@ -99,9 +106,9 @@ pub struct Config {
     ///
     /// Note: this should be less than or equal to the specification's `ETH1_FOLLOW_DISTANCE`.
     pub follow_distance: u64,
-    /// Defines the number of blocks that should be retained each time the `BlockCache` calls truncate on
-    /// itself.
-    pub block_cache_truncation: Option<usize>,
+    /// The approximate number of bytes the `BlockCache` may occupy after pruning. Blocks which may
+    /// be required for eth1 voting are retained regardless. If `None`, the cache is never pruned.
+    pub block_cache_max_bytes: Option<usize>,
     /// The interval between updates when using the `auto_update` function.
     pub auto_update_interval_millis: u64,
     /// The span of blocks we should query for logs, per request.
@@ -110,6 +117,12 @@ pub struct Config {
     pub max_log_requests_per_update: Option<usize>,
     /// The maximum number of log requests per update.
     pub max_blocks_per_update: Option<usize>,
+    /// Throttles the polling of the eth1 node when the node is in low-power mode.
+    #[serde(skip)]
+    pub resource_budget: ResourceBudget,
+    /// Raises an alert when the eth1 node cannot be reached.
+    #[serde(skip)]
+    pub notifier: Notifier,
 }
 
 impl Default for Config {
@@ -120,15 +133,26 @@ impl Default for Config {
             deposit_contract_deploy_block: 0,
             lowest_cached_block_number: 0,
             follow_distance: 128,
-            block_cache_truncation: Some(4_096),
+            block_cache_max_bytes: Some(DEFAULT_BLOCK_CACHE_MAX_BYTES),
             auto_update_interval_millis: 500,
             blocks_per_log_query: 1_000,
             max_log_requests_per_update: None,
             max_blocks_per_update: None,
+            resource_budget: ResourceBudget::default(),
+            notifier: Notifier::default(),
         }
     }
 }
 
+impl Config {
+    /// Returns the interval between updates when using the `auto_update` function, as permitted by
+    /// `self.resource_budget`.
+    pub fn update_interval(&self) -> Duration {
+        self.resource_budget
+            .eth1_update_interval(Duration::from_millis(self.auto_update_interval_millis))
+    }
+}
+
 /// Provides a set of Eth1 caches and async functions to update them.
 ///
 /// Stores the following caches:
@@ -143,16 +167,27 @@ pub struct Service {
 
 impl Service {
     /// Creates a new service. Does not attempt to connect to the eth1 node.
-    pub fn new(config: Config, log: Logger) -> Self {
+    ///
+    /// The blocks which may be required for eth1 voting are determined by the voting period of
+    /// `E`.
+    pub fn new<E: EthSpec>(config: Config, log: Logger, spec: ChainSpec) -> Self {
         Self {
             inner: Arc::new(Inner {
+                block_cache: RwLock::new(BlockCache::default()),
+                deposit_cache: RwLock::new(DepositUpdater::default()),
                 config: RwLock::new(config),
-                ..Inner::default()
+                slots_per_eth1_voting_period: E::slots_per_eth1_voting_period() as u64,
+                spec,
             }),
             log,
         }
     }
 
+    /// Returns the spec used to verify the signatures of deposits.
+    pub fn chain_spec(&self) -> &ChainSpec {
+        &self.inner.spec
+    }
+
     /// Provides access to the block cache.
     pub fn blocks(&self) -> &RwLock<BlockCache> {
         &self.inner.block_cache
@@ -168,11 +203,45 @@ impl Service {
         self.blocks().read().len()
     }
 
+    /// Returns the approximate number of bytes occupied by the block cache.
+    pub fn block_cache_size(&self) -> usize {
+        self.blocks().read().approximate_size()
+    }
+
     /// Returns the number deposits available in the deposit cache.
     pub fn deposit_cache_len(&self) -> usize {
         self.deposits().read().cache.len()
     }
 
+    /// Returns the logs of the deposits to the validator with `pubkey`, in order of deposit index.
+    pub fn deposits_for_pubkey(&self, pubkey: &PublicKeyBytes) -> Vec<DepositLog> {
+        self.deposits()
+            .read()
+            .cache
+            .deposits_for_pubkey(pubkey)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Encodes the deposit cache as SSZ, so that it may be imported by another node with
+    /// `Self::import_deposit_cache`.
+    pub fn deposit_cache_as_ssz_bytes(&self) -> Vec<u8> {
+        self.deposits().read().as_ssz_container().as_ssz_bytes()
+    }
+
+    /// Imports a deposit cache encoded by `Self::deposit_cache_as_ssz_bytes`, returning the number
+    /// of deposits that were not already known.
+    ///
+    /// The import is ignored if `self` has already processed at least as many eth1 blocks as the
+    /// encoded cache. Deposit logs are resumed from the last block processed by the encoded cache.
+    pub fn import_deposit_cache(&self, bytes: &[u8]) -> Result<usize, String> {
+        let container = SszDepositCache::from_ssz_bytes(bytes)
+            .map_err(|e| format!("Unable to decode deposit cache: {:?}", e))?;
+
+        self.deposits().write().import(container)
+    }
+
     /// Read the service's configuration.
     pub fn config(&self) -> RwLockReadGuard<Config> {
         self.inner.config.read()
@@ -180,7 +249,7 @@ impl Service {
 
     /// Updates the configuration in `self to be `new_config`.
     ///
-    /// Will truncate the block cache if the new configure specifies truncation.
+    /// Will prune the block cache if the new config specifies a `block_cache_max_bytes`.
     pub fn update_config(&self, new_config: Config) -> Result<(), String> {
         let mut old_config = self.inner.config.write();
 
@@ -220,22 +289,44 @@ impl Service {
     {
         let log_a = self.log.clone();
         let log_b = self.log.clone();
+        let service_a = self.clone();
+        let service_b = self.clone();
+
+        let timer = metrics::start_timer(&metrics::UPDATE_TIMES);
 
         let deposit_future = self
             .update_deposit_cache()
             .map_err(|e| format!("Failed to update eth1 cache: {:?}", e))
             .then(move |result| {
                 match &result {
-                    Ok(DepositCacheUpdateOutcome::Success { logs_imported }) => trace!(
-                        log_a,
-                        "Updated eth1 deposit cache";
-                        "logs_imported" => logs_imported,
-                    ),
-                    Err(e) => error!(
-                        log_a,
-                        "Failed to update eth1 deposit cache";
-                        "error" => e
-                    ),
+                    Ok(DepositCacheUpdateOutcome::Success { logs_imported }) => {
+                        let deposits = service_a.deposits().read();
+                        metrics::set_gauge(
+                            &metrics::DEPOSIT_CACHE_LEN,
+                            deposits.cache.len() as i64,
+                        );
+                        if let Some(block_number) = deposits.last_processed_block {
+                            metrics::set_gauge(
+                                &metrics::HIGHEST_PROCESSED_DEPOSIT_BLOCK,
+                                block_number as i64,
+                            );
+                        }
+
+                        trace!(
+                            log_a,
+                            "Updated eth1 deposit cache";
+                            "logs_imported" => logs_imported,
+                        )
+                    }
+                    Err(e) => {
+                        metrics::inc_counter(&metrics::DEPOSIT_CACHE_UPDATE_FAILURES);
+
+                        error!(
+                            log_a,
+                            "Failed to update eth1 deposit cache";
+                            "error" => e
+                        )
+                    }
                 };
 
                 result
@@ -249,28 +340,55 @@ impl Service {
                     Ok(BlockCacheUpdateOutcome::Success {
                         blocks_imported,
                         head_block_number,
-                    }) => trace!(
-                        log_b,
-                        "Updated eth1 block cache";
-                        "blocks_imported" => blocks_imported,
-                        "head_block" => head_block_number,
-                    ),
-                    Err(e) => error!(
-                        log_b,
-                        "Failed to update eth1 block cache";
-                        "error" => e
-                    ),
+                    }) => {
+                        metrics::set_gauge(
+                            &metrics::BLOCK_CACHE_LEN,
+                            service_b.block_cache_len() as i64,
+                        );
+                        metrics::set_gauge(
+                            &metrics::BLOCK_CACHE_SIZE,
+                            service_b.block_cache_size() as i64,
+                        );
+                        if let Some(block_number) = head_block_number {
+                            metrics::set_gauge(
+                                &metrics::LATEST_CACHED_BLOCK_NUMBER,
+                                *block_number as i64,
+                            );
+                        }
+
+                        trace!(
+                            log_b,
+                            "Updated eth1 block cache";
+                            "blocks_imported" => blocks_imported,
+                            "head_block" => head_block_number,
+                        )
+                    }
+                    Err(e) => {
+                        metrics::inc_counter(&metrics::BLOCK_CACHE_UPDATE_FAILURES);
+
+                        error!(
+                            log_b,
+                            "Failed to update eth1 block cache";
+                            "error" => e
+                        )
+                    }
                 };
 
                 result
             });
 
-        deposit_future.join(block_future)
+        deposit_future.join(block_future).then(move |result| {
+            metrics::stop_timer(timer);
+            result
+        })
     }
 
-    /// A looping future that updates the cache, then waits `config.auto_update_interval` before
+    /// A looping future that updates the cache, then waits `config.update_interval()` before
     /// updating it again.
     ///
+    /// The interval is re-read before each wait, so a change to the execution mode of the
+    /// `ResourceBudget` takes effect without restarting the loop.
+    ///
     /// ## Returns
     ///
     /// - Ok(_) if the update was successful (the cache may or may not have been modified).
@@ -280,11 +398,13 @@ impl Service {
     pub fn auto_update(&self, exit: Exit) -> impl Future<Item = (), Error = ()> {
         let service = self.clone();
         let log = self.log.clone();
-        let update_interval = Duration::from_millis(self.config().auto_update_interval_millis);
 
         loop_fn((), move |()| {
             let exit = exit.clone();
             let service = service.clone();
+            let notifier = service.config().notifier.clone();
+            let endpoint = service.config().endpoint.clone();
+            let update_interval = service.config().update_interval();
             let log_a = log.clone();
             let log_b = log.clone();
 
@@ -292,12 +412,16 @@ impl Service {
                 .update()
                 .then(move |update_result| {
                     match update_result {
-                        Err(e) => error!(
-                            log_a,
-                            "Failed to update eth1 genesis cache";
-                            "retry_millis" => update_interval.as_millis(),
-                            "error" => e,
-                        ),
+                        Err(e) => {
+                            error!(
+                                log_a,
+                                "Failed to update eth1 genesis cache";
+                                "retry_millis" => update_interval.as_millis(),
+                                "error" => &e,
+                            );
+
+                            notifier.notify(Event::Eth1EndpointDown { endpoint, error: e });
+                        }
                         Ok((deposit, block)) => debug!(
                             log_a,
                             "Updated eth1 genesis cache";
@@ -404,13 +528,13 @@ impl Service {
                     None => None,
                 },
             )
-            .fold(0, move |mut sum, (block_range, log_chunk)| {
-                let mut cache = service_2.deposits().write();
-
-                log_chunk
-                    .into_iter()
+            .fold(0, move |sum, (block_range, log_chunk)| {
+                // Parsing the logs and verifying their signatures is expensive when catching up
+                // on many deposits, so it is done in parallel and without a lock on the cache.
+                let deposit_logs = log_chunk
+                    .par_iter()
                     .map(|raw_log| {
-                        DepositLog::from_log(&raw_log).map_err(|error| {
+                        DepositLog::from_log(raw_log, service_2.chain_spec()).map_err(|error| {
                             Error::FailedToParseDepositLog {
                                 block_range: block_range.clone(),
                                 error,
@@ -419,31 +543,28 @@ impl Service {
                     })
                     // Return early if any of the logs cannot be parsed.
                     //
-                    // This costs an additional `collect`, however it enforces that no logs are
-                    // imported if any one of them cannot be parsed.
-                    .collect::<Result<Vec<_>, _>>()?
-                    .into_iter()
-                    .map(|deposit_log| {
-                        cache
-                            .cache
-                            .insert_log(deposit_log)
-                            .map_err(Error::FailedToInsertDeposit)?;
-
-                        sum += 1;
-
-                        Ok(())
-                    })
-                    // Returns if a deposit is unable to be added to the cache.
-                    //
-                    // If this error occurs, the cache will no longer be guaranteed to hold either
-                    // none or all of the logs for each block (i.e., they may exist _some_ logs for
-                    // a block, but not _all_ logs for that block). This scenario can cause the
-                    // node to choose an invalid genesis state or propose an invalid block.
-                    .collect::<Result<_, _>>()?;
+                    // This enforces that no logs are imported if any one of them cannot be parsed.
+                    .collect::<Result<Vec<_>, _>>()?;
+                let logs_in_chunk = deposit_logs.len();
+
+                let mut cache = service_2.deposits().write();
+
+                // Returns if the logs do not have consecutive indices, or are unable to be added
+                // to the cache.
+                //
+                // Gaps are detected before any log is inserted. However, if a log conflicts with a
+                // known log, the cache will no longer be guaranteed to hold either none or all of
+                // the logs for each block (i.e., they may exist _some_ logs for a block, but not
+                // _all_ logs for that block). This scenario can cause the node to choose an
+                // invalid genesis state or propose an invalid block.
+                cache
+                    .cache
+                    .insert_logs(deposit_logs)
+                    .map_err(Error::FailedToInsertDeposit)?;
 
                 cache.last_processed_block = Some(block_range.end.saturating_sub(1));
 
-                Ok(sum)
+                Ok(sum + logs_in_chunk)
             })
             .map(|logs_imported| DepositCacheUpdateOutcome::Success { logs_imported })
         })
@@ -467,7 +588,7 @@ impl Service {
         let cache_4 = self.inner.clone();
         let cache_5 = self.inner.clone();
 
-        let block_cache_truncation = self.config().block_cache_truncation;
+        let block_cache_max_len = self.inner.block_cache_max_len();
         let max_blocks_per_update = self
             .config()
             .max_blocks_per_update
@@ -499,7 +620,7 @@ impl Service {
                         Err(Error::Internal("Range was not increasing".into()))
                     } else {
                         let range_size = range.end() - range.start();
-                        let max_size = block_cache_truncation
+                        let max_size = block_cache_max_len
                             .map(|n| n as u64)
                             .unwrap_or_else(u64::max_value);
 
@@ -1,7 +1,7 @@
 #![cfg(test)]
 use environment::{Environment, EnvironmentBuilder};
 use eth1::http::{get_deposit_count, get_deposit_logs_in_range, get_deposit_root, Block, Log};
-use eth1::{Config, Service};
+use eth1::{Config, Eth1Block, Service};
 use eth1::{DepositCache, DepositLog};
 use eth1_test_rig::GanacheEth1Instance;
 use exit_future;
@@ -101,6 +101,20 @@ fn get_block_number(runtime: &mut Runtime, web3: &Web3<Http>) -> u64 {
         .expect("should get block number")
 }
 
+/// Mines a block two eth1 voting periods after the previous one, so that the earlier blocks are
+/// not required for voting and may be pruned from the block cache.
+fn mine_block_after_voting_periods(runtime: &mut Runtime, eth1: &GanacheEth1Instance) {
+    let voting_period_seconds = MainnetEthSpec::slots_per_eth1_voting_period() as u64
+        * MainnetEthSpec::default_spec().milliseconds_per_slot
+        / 1_000;
+    runtime
+        .block_on(eth1.ganache.increase_time(voting_period_seconds * 2))
+        .expect("should increase time");
+    runtime
+        .block_on(eth1.ganache.evm_mine())
+        .expect("should mine block")
+}
+
 mod auto_update {
     use super::*;
 
@@ -118,17 +132,18 @@ mod auto_update {
 
         let now = get_block_number(runtime, &web3);
 
-        let service = Service::new(
+        let service = Service::new::<MainnetEthSpec>(
             Config {
                 endpoint: eth1.endpoint(),
                 deposit_contract_address: deposit_contract.address(),
                 deposit_contract_deploy_block: now,
                 lowest_cached_block_number: now,
                 follow_distance: 0,
-                block_cache_truncation: None,
+                block_cache_max_bytes: None,
                 ..Config::default()
             },
             log,
+            MainnetEthSpec::default_spec(),
         );
 
         // NOTE: this test is sensitive to the response speed of the external web3 server. If
@@ -203,7 +218,7 @@ mod eth1_cache {
 
             let initial_block_number = get_block_number(runtime, &web3);
 
-            let service = Service::new(
+            let service = Service::new::<MainnetEthSpec>(
                 Config {
                     endpoint: eth1.endpoint(),
                     deposit_contract_address: deposit_contract.address(),
@@ -212,6 +227,7 @@ mod eth1_cache {
                     ..Config::default()
                 },
                 log.clone(),
+                MainnetEthSpec::default_spec(),
             );
 
             // Create some blocks and then consume them, performing the test `rounds` times.
@@ -274,24 +290,23 @@ mod eth1_cache {
 
         let cache_len = 4;
 
-        let service = Service::new(
+        let service = Service::new::<MainnetEthSpec>(
             Config {
                 endpoint: eth1.endpoint(),
                 deposit_contract_address: deposit_contract.address(),
                 lowest_cached_block_number: get_block_number(runtime, &web3),
                 follow_distance: 0,
-                block_cache_truncation: Some(cache_len),
+                block_cache_max_bytes: Some(cache_len * Eth1Block::approximate_size()),
                 ..Config::default()
             },
             log,
+            MainnetEthSpec::default_spec(),
         );
 
         let blocks = cache_len * 2;
 
         for _ in 0..blocks {
-            runtime
-                .block_on(eth1.ganache.evm_mine())
-                .expect("should mine block")
+            mine_block_after_voting_periods(runtime, &eth1);
         }
 
         runtime
@@ -321,23 +336,22 @@ mod eth1_cache {
 
         let cache_len = 4;
 
-        let service = Service::new(
+        let service = Service::new::<MainnetEthSpec>(
             Config {
                 endpoint: eth1.endpoint(),
                 deposit_contract_address: deposit_contract.address(),
                 lowest_cached_block_number: get_block_number(runtime, &web3),
                 follow_distance: 0,
-                block_cache_truncation: Some(cache_len),
+                block_cache_max_bytes: Some(cache_len * Eth1Block::approximate_size()),
                 ..Config::default()
             },
             log,
+            MainnetEthSpec::default_spec(),
         );
 
         for _ in 0..4 {
             for _ in 0..cache_len / 2 {
-                runtime
-                    .block_on(eth1.ganache.evm_mine())
-                    .expect("should mine block")
+                mine_block_after_voting_periods(runtime, &eth1);
             }
             runtime
                 .block_on(service.update_block_cache())
@@ -365,7 +379,7 @@ mod eth1_cache {
         let deposit_contract = &eth1.deposit_contract;
         let web3 = eth1.web3();
 
-        let service = Service::new(
+        let service = Service::new::<MainnetEthSpec>(
             Config {
                 endpoint: eth1.endpoint(),
                 deposit_contract_address: deposit_contract.address(),
@@ -374,6 +388,7 @@ mod eth1_cache {
                 ..Config::default()
             },
             log,
+            MainnetEthSpec::default_spec(),
         );
 
         for _ in 0..n {
@@ -413,7 +428,7 @@ mod deposit_tree {
 
         let start_block = get_block_number(runtime, &web3);
 
-        let service = Service::new(
+        let service = Service::new::<MainnetEthSpec>(
             Config {
                 endpoint: eth1.endpoint(),
                 deposit_contract_address: deposit_contract.address(),
@@ -422,6 +437,7 @@ mod deposit_tree {
                 ..Config::default()
             },
             log,
+            MainnetEthSpec::default_spec(),
         );
 
         for round in 0..3 {
@@ -486,7 +502,7 @@ mod deposit_tree {
 
         let start_block = get_block_number(runtime, &web3);
 
-        let service = Service::new(
+        let service = Service::new::<MainnetEthSpec>(
             Config {
                 endpoint: eth1.endpoint(),
                 deposit_contract_address: deposit_contract.address(),
@@ -496,6 +512,7 @@ mod deposit_tree {
                 ..Config::default()
             },
             log,
+            MainnetEthSpec::default_spec(),
         );
 
         let deposits: Vec<_> = (0..n).into_iter().map(|_| random_deposit_data()).collect();
@@ -521,6 +538,7 @@ mod deposit_tree {
     fn cache_consistency() {
         let mut env = new_env();
         let runtime = env.runtime();
+        let spec = MainnetEthSpec::default_spec();
 
         let n = 8;
 
@@ -557,7 +575,7 @@ mod deposit_tree {
         let block_number = get_block_number(runtime, &web3);
         let logs: Vec<_> = blocking_deposit_logs(runtime, &eth1, 0..block_number)
             .iter()
-            .map(|raw| DepositLog::from_log(raw).expect("should parse deposit log"))
+            .map(|raw| DepositLog::from_log(raw, &spec).expect("should parse deposit log"))
             .inspect(|log| {
                 tree.insert_log(log.clone())
                     .expect("should add consecutive logs")
@@ -711,3 +729,77 @@ mod http {
         }
     }
 }
+
+/// Tests for the deposit contract test rig, and the parsing of the logs it produces.
+mod deposit_contract {
+    use super::*;
+    use eth1_test_rig::{InvalidDeposit, WRONG_AMOUNT_DIFFERENCE_GWEI};
+
+    #[test]
+    fn multiple_and_invalid_deposits() {
+        let mut env = new_env();
+        let runtime = env.runtime();
+        let spec = MainnetEthSpec::default_spec();
+
+        let eth1 = runtime
+            .block_on(GanacheEth1Instance::new())
+            .expect("should start eth1 environment");
+        let deposit_contract = &eth1.deposit_contract;
+        let web3 = eth1.web3();
+
+        let valid_deposits = (0..4).map(|_| random_deposit_data()).collect::<Vec<_>>();
+        runtime
+            .block_on(deposit_contract.deposit_multiple(valid_deposits.clone()))
+            .expect("should perform multiple deposits");
+
+        let bad_signature = random_deposit_data();
+        runtime
+            .block_on(deposit_contract.deposit_invalid_async::<MainnetEthSpec>(
+                bad_signature.clone(),
+                InvalidDeposit::BadSignature,
+            ))
+            .expect("should perform a deposit with a bad signature");
+
+        let wrong_amount = random_deposit_data();
+        runtime
+            .block_on(deposit_contract.deposit_invalid_async::<MainnetEthSpec>(
+                wrong_amount.clone(),
+                InvalidDeposit::WrongAmount,
+            ))
+            .expect("should perform a deposit with the wrong amount");
+
+        let block_number = get_block_number(runtime, &web3);
+        let logs = blocking_deposit_logs(runtime, &eth1, 0..block_number)
+            .iter()
+            .map(|log| DepositLog::from_log(log, &spec).expect("should parse log"))
+            .collect::<Vec<_>>();
+
+        assert_eq!(logs.len(), 6, "should have a log for each deposit");
+
+        for (log, deposit) in logs.iter().zip(valid_deposits.iter()) {
+            assert_eq!(
+                &log.deposit_data, deposit,
+                "valid deposits should be logged"
+            );
+            assert!(
+                log.signature_is_valid,
+                "valid deposits should have a valid signature"
+            );
+        }
+
+        assert_eq!(logs[4].deposit_data.pubkey, bad_signature.pubkey);
+        assert!(
+            !logs[4].signature_is_valid,
+            "bad signature deposit should have an invalid signature"
+        );
+
+        assert_eq!(
+            logs[5].deposit_data.amount,
+            wrong_amount.amount + WRONG_AMOUNT_DIFFERENCE_GWEI
+        );
+        assert!(
+            !logs[5].signature_is_valid,
+            "wrong amount deposit should have an invalid signature"
+        );
+    }
+}
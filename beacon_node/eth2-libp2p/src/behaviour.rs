@@ -1,13 +1,18 @@
-use crate::config::*;
-use crate::discovery::Discovery;
-use crate::rpc::{RPCEvent, RPCMessage, RPC};
-use crate::{error, NetworkConfig};
-use crate::{Topic, TopicHash};
-use crate::{BEACON_ATTESTATION_TOPIC, BEACON_BLOCK_TOPIC};
+use crate::config::GOSSIP_MAX_SIZE;
+use crate::discovery::{Discovery, DiscoveryEvent};
+use crate::duplicate_cache::DuplicateCache;
+use crate::metrics;
+use crate::peer_db::PeerDb;
+use crate::peer_manager::PeerManager;
+use crate::rpc::{
+    MetaData, Ping as PingMessage, RPCErrorResponse, RPCEvent, RPCMessage, RPCRequest, RPCResponse,
+    RPC,
+};
+use crate::{error, Eth2Enr, NetworkConfig};
+use crate::{message_id, GossipKind, GossipTopic, Topic, TopicHash};
 use futures::prelude::*;
 use libp2p::{
     core::identity::Keypair,
-    discv5::Discv5Event,
     gossipsub::{Gossipsub, GossipsubEvent},
     identify::{Identify, IdentifyEvent},
     ping::{Ping, PingConfig, PingEvent},
@@ -15,9 +20,11 @@ use libp2p::{
     tokio_io::{AsyncRead, AsyncWrite},
     NetworkBehaviour, PeerId,
 };
-use slog::{debug, o};
+use slog::{debug, o, trace, warn};
+use std::collections::HashSet;
 use std::num::NonZeroU32;
 use std::time::Duration;
+use types::{EnrForkId, ForkDigest};
 
 const MAX_IDENTIFY_ADDRESSES: usize = 20;
 
@@ -42,6 +49,23 @@ pub struct Behaviour<TSubstream: AsyncRead + AsyncWrite> {
     #[behaviour(ignore)]
     /// The events generated by this behaviour to be consumed in the swarm poll.
     events: Vec<BehaviourEvent>,
+    /// Maintains the scores of peers and decides which peers to disconnect or ban.
+    #[behaviour(ignore)]
+    peer_manager: PeerManager,
+    /// The fork digests whose topics are subscribed to. Messages are published to the topics of
+    /// the first digest, which is that of the current fork.
+    #[behaviour(ignore)]
+    fork_digests: Vec<ForkDigest>,
+    /// The kinds of topic which are subscribed to, for every fork digest.
+    #[behaviour(ignore)]
+    gossip_kinds: HashSet<GossipKind>,
+    /// The message-ids of recently received gossipsub messages.
+    #[behaviour(ignore)]
+    seen_gossip_messages: DuplicateCache,
+    /// The `MetaData` served to peers. Its sequence number is incremented whenever the
+    /// attestation subnets advertised in the local ENR change.
+    #[behaviour(ignore)]
+    meta_data: MetaData,
     /// Logger for behaviour actions.
     #[behaviour(ignore)]
     log: slog::Logger,
@@ -51,6 +75,7 @@ impl<TSubstream: AsyncRead + AsyncWrite> Behaviour<TSubstream> {
     pub fn new(
         local_key: &Keypair,
         net_conf: &NetworkConfig,
+        enr_fork_id: EnrForkId,
         log: &slog::Logger,
     ) -> error::Result<Self> {
         let local_peer_id = local_key.public().clone().into_peer_id();
@@ -68,13 +93,32 @@ impl<TSubstream: AsyncRead + AsyncWrite> Behaviour<TSubstream> {
             local_key.public(),
         );
 
+        let mut discovery = Discovery::new(local_key, net_conf, &enr_fork_id, log)?;
+        let meta_data = MetaData {
+            seq_number: 0,
+            attnets: discovery.local_enr().bitfield().unwrap_or_default(),
+        };
+
+        // the peers which were connected before the last restart are dialed immediately
+        let mut peer_manager = PeerManager::new(net_conf, log);
+        let peer_db = PeerDb::open(&net_conf.network_dir, log);
+        for enr in peer_db.enrs() {
+            discovery.add_enr(enr);
+        }
+        peer_manager.set_peer_db(peer_db);
+
         Ok(Behaviour {
             eth2_rpc: RPC::new(log.clone()),
-            gossipsub: Gossipsub::new(local_peer_id.clone(), net_conf.gs_config.clone()),
-            discovery: Discovery::new(local_key, net_conf, log)?,
+            gossipsub: Gossipsub::new(local_peer_id.clone(), net_conf.gs_config()),
+            discovery,
             ping: Ping::new(ping_config),
             identify,
             events: Vec::new(),
+            peer_manager,
+            fork_digests: vec![enr_fork_id.fork_digest],
+            gossip_kinds: HashSet::new(),
+            seen_gossip_messages: DuplicateCache::new(net_conf.duplicate_cache_time),
+            meta_data,
             log: behaviour_log,
         })
     }
@@ -82,6 +126,14 @@ impl<TSubstream: AsyncRead + AsyncWrite> Behaviour<TSubstream> {
     pub fn discovery(&self) -> &Discovery<TSubstream> {
         &self.discovery
     }
+
+    pub fn peer_manager(&self) -> &PeerManager {
+        &self.peer_manager
+    }
+
+    pub fn peer_manager_mut(&mut self) -> &mut PeerManager {
+        &mut self.peer_manager
+    }
 }
 
 // Implement the NetworkBehaviourEventProcess trait so that we can derive NetworkBehaviour for Behaviour
@@ -91,8 +143,29 @@ impl<TSubstream: AsyncRead + AsyncWrite> NetworkBehaviourEventProcess<GossipsubE
     fn inject_event(&mut self, event: GossipsubEvent) {
         match event {
             GossipsubEvent::Message(propagation_source, gs_msg) => {
+                // Identical messages from different publishers are only processed once.
+                if !self
+                    .seen_gossip_messages
+                    .insert(message_id(&gs_msg.topics, &gs_msg.data))
+                {
+                    trace!(self.log, "Dropping duplicate gossip message";
+                        "peer" => format!("{:?}", propagation_source),
+                    );
+                    metrics::inc_counter(&metrics::GOSSIP_DUPLICATE_MESSAGE_COUNT);
+                    return;
+                }
+
                 let id = gs_msg.id();
-                let msg = PubsubMessage::from_topics(&gs_msg.topics, gs_msg.data);
+                let msg = match PubsubMessage::from_topics(&gs_msg.topics, gs_msg.data) {
+                    Ok(msg) => msg,
+                    Err(e) => {
+                        debug!(self.log, "Could not decode gossip message";
+                            "peer" => format!("{:?}", propagation_source),
+                            "error" => e,
+                        );
+                        return;
+                    }
+                };
 
                 // Note: We are keeping track here of the peer that sent us the message, not the
                 // peer that originally published the message.
@@ -115,11 +188,57 @@ impl<TSubstream: AsyncRead + AsyncWrite> NetworkBehaviourEventProcess<RPCMessage
     fn inject_event(&mut self, event: RPCMessage) {
         match event {
             RPCMessage::PeerDialed(peer_id) => {
+                self.peer_manager.connect(peer_id.clone());
+                let enr = self
+                    .discovery
+                    .connected_peer_set()
+                    .get(&peer_id)
+                    .and_then(|info| info.enr.clone());
+                if let Some(enr) = enr {
+                    self.peer_manager.peer_enr(&peer_id, enr);
+                }
+                self.send_meta_data_request(peer_id.clone());
                 self.events.push(BehaviourEvent::PeerDialed(peer_id))
             }
             RPCMessage::PeerDisconnected(peer_id) => {
+                self.peer_manager.disconnect(&peer_id);
                 self.events.push(BehaviourEvent::PeerDisconnected(peer_id))
             }
+            // pings and metadata are handled by the behaviour, all other messages are passed up
+            RPCMessage::RPC(peer_id, RPCEvent::Request(id, RPCRequest::Ping(ping))) => {
+                self.send_rpc(
+                    peer_id.clone(),
+                    RPCEvent::Response(
+                        id,
+                        RPCErrorResponse::Success(RPCResponse::Pong(PingMessage {
+                            data: self.meta_data.seq_number,
+                        })),
+                    ),
+                );
+                self.peer_manager.ping_received(&peer_id, ping.data);
+            }
+            RPCMessage::RPC(peer_id, RPCEvent::Request(id, RPCRequest::MetaData)) => {
+                let meta_data = self.meta_data.clone();
+                self.send_rpc(
+                    peer_id,
+                    RPCEvent::Response(
+                        id,
+                        RPCErrorResponse::Success(RPCResponse::MetaData(meta_data)),
+                    ),
+                );
+            }
+            RPCMessage::RPC(
+                peer_id,
+                RPCEvent::Response(_, RPCErrorResponse::Success(RPCResponse::Pong(ping))),
+            ) => {
+                self.peer_manager.ping_received(&peer_id, ping.data);
+            }
+            RPCMessage::RPC(
+                peer_id,
+                RPCEvent::Response(_, RPCErrorResponse::Success(RPCResponse::MetaData(meta_data))),
+            ) => {
+                self.peer_manager.meta_data_received(&peer_id, meta_data);
+            }
             RPCMessage::RPC(peer_id, rpc_event) => {
                 self.events.push(BehaviourEvent::RPC(peer_id, rpc_event))
             }
@@ -179,11 +298,16 @@ impl<TSubstream: AsyncRead + AsyncWrite> NetworkBehaviourEventProcess<IdentifyEv
     }
 }
 
-impl<TSubstream: AsyncRead + AsyncWrite> NetworkBehaviourEventProcess<Discv5Event>
+impl<TSubstream: AsyncRead + AsyncWrite> NetworkBehaviourEventProcess<DiscoveryEvent>
     for Behaviour<TSubstream>
 {
-    fn inject_event(&mut self, _event: Discv5Event) {
-        // discv5 has no events to inject
+    fn inject_event(&mut self, event: DiscoveryEvent) {
+        match event {
+            DiscoveryEvent::PeersFound { peers, subnet_id } => {
+                // peers on a needed subnet are dialed even if we have the target number of peers
+                self.peer_manager.dial_peers(peers, subnet_id.is_some());
+            }
+        }
     }
 }
 
@@ -196,11 +320,119 @@ impl<TSubstream: AsyncRead + AsyncWrite> Behaviour<TSubstream> {
         self.gossipsub.subscribe(topic)
     }
 
-    /// Publishes a message on the pubsub (gossipsub) behaviour.
-    pub fn publish(&mut self, topics: &[Topic], message: PubsubMessage) {
-        let message_data = message.to_data();
-        for topic in topics {
-            self.gossipsub.publish(topic, message_data.clone());
+    /// Unsubscribe from a gossipsub topic.
+    pub fn unsubscribe(&mut self, topic: Topic) -> bool {
+        self.gossipsub.unsubscribe(topic)
+    }
+
+    /// Subscribes to the topic of `kind` for each of the active fork digests, and for any fork
+    /// digests which become active later.
+    pub fn subscribe_kind(&mut self, kind: GossipKind) -> bool {
+        self.gossip_kinds.insert(kind);
+
+        let mut subscribed = true;
+        for fork_digest in self.fork_digests.clone() {
+            subscribed &= self.subscribe_encodings(kind, fork_digest);
+        }
+        subscribed
+    }
+
+    /// Unsubscribes from the topic of `kind` for each of the active fork digests.
+    pub fn unsubscribe_kind(&mut self, kind: GossipKind) -> bool {
+        self.gossip_kinds.remove(&kind);
+
+        let mut unsubscribed = true;
+        for fork_digest in self.fork_digests.clone() {
+            unsubscribed &= self.unsubscribe_encodings(kind, fork_digest);
+        }
+        unsubscribed
+    }
+
+    /// Subscribes to the topics of `kind` on `fork_digest` in every encoding, so that messages
+    /// from peers which only publish in one of the encodings are received.
+    fn subscribe_encodings(&mut self, kind: GossipKind, fork_digest: ForkDigest) -> bool {
+        let mut subscribed = true;
+        for topic in GossipTopic::all_encodings(kind, fork_digest) {
+            subscribed &= self.subscribe(topic.into());
+        }
+        subscribed
+    }
+
+    /// Unsubscribes from the topics of `kind` on `fork_digest` in every encoding.
+    fn unsubscribe_encodings(&mut self, kind: GossipKind, fork_digest: ForkDigest) -> bool {
+        let mut unsubscribed = true;
+        for topic in GossipTopic::all_encodings(kind, fork_digest) {
+            unsubscribed &= self.unsubscribe(topic.into());
+        }
+        unsubscribed
+    }
+
+    /// Returns the active fork digests, the first of which is that of the current fork.
+    pub fn fork_digests(&self) -> &[ForkDigest] {
+        &self.fork_digests
+    }
+
+    /// Replaces the active fork digests, subscribing to every kind of topic for the new digests
+    /// and unsubscribing from the topics of digests which are no longer active.
+    ///
+    /// Messages are published to the topics of the first digest.
+    pub fn update_fork_digests(&mut self, fork_digests: Vec<ForkDigest>) {
+        if fork_digests.is_empty() || fork_digests == self.fork_digests {
+            return;
+        }
+
+        let kinds = self.gossip_kinds.iter().cloned().collect::<Vec<_>>();
+        let old_fork_digests = std::mem::replace(&mut self.fork_digests, fork_digests.clone());
+
+        for fork_digest in old_fork_digests
+            .iter()
+            .filter(|digest| !fork_digests.contains(digest))
+        {
+            for kind in &kinds {
+                self.unsubscribe_encodings(*kind, *fork_digest);
+            }
+        }
+
+        for fork_digest in fork_digests
+            .iter()
+            .filter(|digest| !old_fork_digests.contains(digest))
+        {
+            for kind in &kinds {
+                self.subscribe_encodings(*kind, *fork_digest);
+            }
+        }
+
+        debug!(
+            self.log,
+            "Updated fork digests";
+            "old" => format!("{:?}", old_fork_digests.iter().map(hex::encode).collect::<Vec<_>>()),
+            "new" => format!("{:?}", fork_digests.iter().map(hex::encode).collect::<Vec<_>>()),
+        );
+    }
+
+    /// Publishes a message on the pubsub (gossipsub) behaviour, to the topics of the given
+    /// `kinds` for the current fork. Messages are published in every encoding we subscribe to, so
+    /// that they reach peers subscribed to either.
+    pub fn publish(&mut self, kinds: &[GossipKind], message: PubsubMessage) {
+        let fork_digest = match self.fork_digests.first() {
+            Some(fork_digest) => *fork_digest,
+            None => return,
+        };
+
+        let data = message.to_data();
+        for kind in kinds {
+            for topic in GossipTopic::all_encodings(*kind, fork_digest) {
+                let message_data = match topic.encoding.encode(data.clone()) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        warn!(self.log, "Could not encode gossip message"; "error" => e);
+                        continue;
+                    }
+                };
+
+                let topic: Topic = topic.into();
+                self.gossipsub.publish(&topic, message_data);
+            }
         }
     }
 
@@ -218,6 +450,27 @@ impl<TSubstream: AsyncRead + AsyncWrite> Behaviour<TSubstream> {
         self.eth2_rpc.send_rpc(peer_id, rpc_event);
     }
 
+    /// Pings `peer_id`, advertising the sequence number of the local `MetaData`.
+    pub fn send_ping(&mut self, peer_id: PeerId) {
+        let ping = PingMessage {
+            data: self.meta_data.seq_number,
+        };
+        trace!(self.log, "Sending ping"; "peer_id" => format!("{}", peer_id), "seq_number" => ping.data);
+        // use 0 as the request id, the response is handled by the behaviour.
+        self.send_rpc(peer_id, RPCEvent::Request(0, RPCRequest::Ping(ping)));
+    }
+
+    /// Requests the `MetaData` of `peer_id`.
+    pub fn send_meta_data_request(&mut self, peer_id: PeerId) {
+        trace!(self.log, "Requesting metadata"; "peer_id" => format!("{}", peer_id));
+        self.send_rpc(peer_id, RPCEvent::Request(0, RPCRequest::MetaData));
+    }
+
+    /// Returns the `MetaData` served to peers.
+    pub fn meta_data(&self) -> &MetaData {
+        &self.meta_data
+    }
+
     /* Discovery / Peer management functions */
     pub fn connected_peers(&self) -> usize {
         self.discovery.connected_peers()
@@ -227,6 +480,39 @@ impl<TSubstream: AsyncRead + AsyncWrite> Behaviour<TSubstream> {
     pub fn update_local_enr_socket(&mut self, socket: std::net::SocketAddr, is_tcp: bool) {
         self.discovery.update_local_enr(socket, is_tcp);
     }
+
+    /// Searches for peers which advertise the attestation subnet with id `subnet_id`. Any peers
+    /// found are dialed, if there is room for them.
+    pub fn discover_subnet_peers(&mut self, subnet_id: u64) {
+        self.discovery.discover_subnet_peers(subnet_id);
+    }
+
+    /// Sets whether the local ENR advertises the attestation subnet with id `subnet_id`.
+    ///
+    /// The sequence number of the local `MetaData` is incremented if the subnets change.
+    pub fn update_enr_subnet(&mut self, subnet_id: u64, value: bool) {
+        if let Err(e) = self.discovery.update_enr_subnet(subnet_id, value) {
+            warn!(self.log, "Could not update ENR subnets"; "subnet_id" => subnet_id, "error" => e);
+            return;
+        }
+
+        match self.discovery.local_enr().bitfield() {
+            Ok(attnets) if attnets != self.meta_data.attnets => {
+                self.meta_data.seq_number += 1;
+                self.meta_data.attnets = attnets;
+                debug!(self.log, "Updated local metadata"; "seq_number" => self.meta_data.seq_number);
+            }
+            Ok(_) => {}
+            Err(e) => warn!(self.log, "Could not read ENR subnets"; "error" => e),
+        }
+    }
+
+    /// Sets the fork id advertised in the local ENR.
+    pub fn update_eth2_enr(&mut self, enr_fork_id: EnrForkId) {
+        if let Err(e) = self.discovery.update_eth2_enr(enr_fork_id) {
+            warn!(self.log, "Could not update ENR fork id"; "error" => e);
+        }
+    }
 }
 
 /// The types of events than can be obtained from polling the behaviour.
@@ -258,6 +544,9 @@ pub enum PubsubMessage {
     Block(Vec<u8>),
     /// Gossipsub message providing notification of a new attestation.
     Attestation(Vec<u8>),
+    /// Gossipsub message providing notification of an aggregated attestation and the proof that
+    /// the sender was selected to aggregate.
+    AggregateAndProof(Vec<u8>),
     /// Gossipsub message providing notification of a voluntary exit.
     VoluntaryExit(Vec<u8>),
     /// Gossipsub message providing notification of a new proposer slashing.
@@ -274,32 +563,37 @@ impl PubsubMessage {
      *
      * Also note that a message can be associated with many topics. As soon as one of the topics is
      * known we match. If none of the topics are known we return an unknown state.
+     *
+     * The message is decompressed according to the encoding of the known topic, which fails if
+     * the message is not validly compressed.
      */
-    fn from_topics(topics: &Vec<TopicHash>, data: Vec<u8>) -> Self {
+    fn from_topics(topics: &Vec<TopicHash>, data: Vec<u8>) -> Result<Self, String> {
         for topic in topics {
-            // compare the prefix and postfix, then match on the topic
-            let topic_parts: Vec<&str> = topic.as_str().split('/').collect();
-            if topic_parts.len() == 4
-                && topic_parts[1] == TOPIC_PREFIX
-                && topic_parts[3] == TOPIC_ENCODING_POSTFIX
-            {
-                match topic_parts[2] {
-                    BEACON_BLOCK_TOPIC => return PubsubMessage::Block(data),
-                    BEACON_ATTESTATION_TOPIC => return PubsubMessage::Attestation(data),
-                    VOLUNTARY_EXIT_TOPIC => return PubsubMessage::VoluntaryExit(data),
-                    PROPOSER_SLASHING_TOPIC => return PubsubMessage::ProposerSlashing(data),
-                    ATTESTER_SLASHING_TOPIC => return PubsubMessage::AttesterSlashing(data),
-                    _ => {}
-                }
+            // Messages on the topics of any fork are accepted, since only the topics of the
+            // active forks are subscribed to.
+            if let Some(GossipTopic { kind, encoding, .. }) = GossipTopic::decode(topic.as_str()) {
+                let data = encoding.decode(data, GOSSIP_MAX_SIZE)?;
+                return Ok(match kind {
+                    GossipKind::BeaconBlock => PubsubMessage::Block(data),
+                    // Attestations published to a committee index subnet.
+                    GossipKind::BeaconAttestation | GossipKind::CommitteeIndex(_) => {
+                        PubsubMessage::Attestation(data)
+                    }
+                    GossipKind::BeaconAggregateAndProof => PubsubMessage::AggregateAndProof(data),
+                    GossipKind::VoluntaryExit => PubsubMessage::VoluntaryExit(data),
+                    GossipKind::ProposerSlashing => PubsubMessage::ProposerSlashing(data),
+                    GossipKind::AttesterSlashing => PubsubMessage::AttesterSlashing(data),
+                });
             }
         }
-        PubsubMessage::Unknown(data)
+        Ok(PubsubMessage::Unknown(data))
     }
 
     fn to_data(self) -> Vec<u8> {
         match self {
             PubsubMessage::Block(data)
             | PubsubMessage::Attestation(data)
+            | PubsubMessage::AggregateAndProof(data)
             | PubsubMessage::VoluntaryExit(data)
             | PubsubMessage::ProposerSlashing(data)
             | PubsubMessage::AttesterSlashing(data)
@@ -1,23 +1,34 @@
 use clap::ArgMatches;
 use enr::Enr;
 use libp2p::gossipsub::{GossipsubConfig, GossipsubConfigBuilder};
-use libp2p::Multiaddr;
+use libp2p::multiaddr::Protocol;
+use libp2p::{Multiaddr, PeerId};
 use serde_derive::{Deserialize, Serialize};
+use std::fmt;
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::time::Duration;
 
 /// The gossipsub topic names.
-// These constants form a topic name of the form /TOPIC_PREFIX/TOPIC/ENCODING_POSTFIX
-// For example /eth2/beacon_block/ssz
+// These constants form a topic name of the form /TOPIC_PREFIX/FORK_DIGEST/TOPIC/ENCODING_POSTFIX
+// For example /eth2/e7a75d5a/beacon_block/ssz_snappy. See `GossipTopic`.
 pub const TOPIC_PREFIX: &str = "eth2";
 pub const TOPIC_ENCODING_POSTFIX: &str = "ssz";
+/// The encoding postfix of topics whose messages are snappy-compressed SSZ.
+pub const TOPIC_SNAPPY_ENCODING_POSTFIX: &str = "ssz_snappy";
 pub const BEACON_BLOCK_TOPIC: &str = "beacon_block";
 pub const BEACON_ATTESTATION_TOPIC: &str = "beacon_attestation";
+pub const BEACON_AGGREGATE_AND_PROOF_TOPIC: &str = "beacon_aggregate_and_proof";
+pub const COMMITTEE_INDEX_TOPIC_PREFIX: &str = "committee_index";
+pub const COMMITTEE_INDEX_TOPIC_POSTFIX: &str = "_beacon_attestation";
 pub const VOLUNTARY_EXIT_TOPIC: &str = "voluntary_exit";
 pub const PROPOSER_SLASHING_TOPIC: &str = "proposer_slashing";
 pub const ATTESTER_SLASHING_TOPIC: &str = "attester_slashing";
 pub const SHARD_TOPIC_PREFIX: &str = "shard";
 
+/// The maximum size of a gossipsub message, before and after decompression.
+pub const GOSSIP_MAX_SIZE: usize = 1_048_576;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(default)]
 /// Network configuration for lighthouse.
@@ -37,7 +48,22 @@ pub struct Config {
     /// UDP port that discovery listens on.
     pub discovery_port: u16,
 
-    /// Target number of connected peers.
+    /// If true, the `discovery_address` was set by the user and is not replaced in the ENR by the
+    /// external address discovered through UPnP or NAT-PMP.
+    pub enr_address_pinned: bool,
+
+    /// Attempt to map the libp2p and discovery ports on the local gateway using UPnP.
+    pub upnp_enabled: bool,
+
+    /// Attempt to map the libp2p and discovery ports on the local gateway using NAT-PMP, if UPnP
+    /// is disabled or fails.
+    pub nat_pmp_enabled: bool,
+
+    /// Target number of connected peers. When more peers are connected, the worst-scoring peers
+    /// are disconnected.
+    pub target_peers: usize,
+
+    /// Maximum number of connected peers. Peers which connect beyond this are disconnected.
     pub max_peers: usize,
 
     /// A secp256k1 secret key, as bytes in ASCII-encoded hex.
@@ -46,9 +72,12 @@ pub struct Config {
     #[serde(skip)]
     pub secret_key_hex: Option<String>,
 
-    /// Gossipsub configuration parameters.
-    #[serde(skip)]
-    pub gs_config: GossipsubConfig,
+    /// The gossipsub mesh parameters. See `Config::gs_config`.
+    pub gossipsub: GossipsubParams,
+
+    /// How long the ids of received gossipsub messages are remembered. Messages with an id that
+    /// has been seen within this time are dropped as duplicates.
+    pub duplicate_cache_time: Duration,
 
     /// List of nodes to initially connect to.
     pub boot_nodes: Vec<Enr>,
@@ -56,6 +85,10 @@ pub struct Config {
     /// List of libp2p nodes to initially connect to.
     pub libp2p_nodes: Vec<Multiaddr>,
 
+    /// Peers which are never disconnected or banned for their score, and which are redialed
+    /// whenever they disconnect. Each address ends with the `/p2p/` id of the peer.
+    pub trusted_peers: Vec<Multiaddr>,
+
     /// Client version
     pub client_version: String,
 
@@ -75,17 +108,17 @@ impl Default for Config {
             libp2p_port: 9000,
             discovery_address: "127.0.0.1".parse().expect("valid ip address"),
             discovery_port: 9000,
-            max_peers: 10,
+            enr_address_pinned: false,
+            upnp_enabled: false,
+            nat_pmp_enabled: false,
+            target_peers: 10,
+            max_peers: 15,
             secret_key_hex: None,
-            // Note: The topics by default are sent as plain strings. Hashes are an optional
-            // parameter.
-            gs_config: GossipsubConfigBuilder::new()
-                .max_transmit_size(1_048_576)
-                .heartbeat_interval(Duration::from_secs(20)) // TODO: Reduce for mainnet
-                .manual_propagation(true) // require validation before propagation
-                .build(),
+            gossipsub: GossipsubParams::default(),
+            duplicate_cache_time: Duration::from_secs(48),
             boot_nodes: vec![],
             libp2p_nodes: vec![],
+            trusted_peers: vec![],
             client_version: version::version(),
             topics: Vec::new(),
         }
@@ -98,6 +131,24 @@ impl Config {
         Config::default()
     }
 
+    /// Builds the gossipsub configuration from the `gossipsub` parameters.
+    pub fn gs_config(&self) -> GossipsubConfig {
+        let params = &self.gossipsub;
+        // Note: The topics by default are sent as plain strings. Hashes are an optional
+        // parameter.
+        GossipsubConfigBuilder::new()
+            .max_transmit_size(GOSSIP_MAX_SIZE)
+            .mesh_n(params.mesh_n)
+            .mesh_n_low(params.mesh_n_low)
+            .mesh_n_high(params.mesh_n_high)
+            .gossip_lazy(params.gossip_lazy)
+            .heartbeat_interval(params.heartbeat_interval)
+            .history_length(params.history_length)
+            .history_gossip(params.history_gossip)
+            .manual_propagation(true) // require validation before propagation
+            .build()
+    }
+
     pub fn apply_cli_args(&mut self, args: &ArgMatches) -> Result<(), String> {
         // If a `datadir` has been specified, set the network dir to be inside it.
         if let Some(dir) = args.value_of("datadir") {
@@ -117,12 +168,25 @@ impl Config {
             self.discovery_address = listen_address;
         }
 
+        if let Some(target_peers_str) = args.value_of("target-peers") {
+            self.target_peers = target_peers_str
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid number of target peers: {}", target_peers_str))?;
+        }
+
         if let Some(max_peers_str) = args.value_of("maxpeers") {
             self.max_peers = max_peers_str
                 .parse::<usize>()
                 .map_err(|_| format!("Invalid number of max peers: {}", max_peers_str))?;
         }
 
+        if self.target_peers > self.max_peers {
+            return Err(format!(
+                "The target number of peers ({}) exceeds the maximum number of peers ({})",
+                self.target_peers, self.max_peers
+            ));
+        }
+
         if let Some(port_str) = args.value_of("port") {
             let port = port_str
                 .parse::<u16>()
@@ -131,21 +195,31 @@ impl Config {
             self.discovery_port = port;
         }
 
-        if let Some(boot_enr_str) = args.value_of("boot-nodes") {
-            self.boot_nodes = boot_enr_str
-                .split(',')
-                .map(|enr| enr.parse().map_err(|_| format!("Invalid ENR: {}", enr)))
-                .collect::<Result<Vec<Enr>, _>>()?;
+        if let Some(boot_nodes_str) = args.value_of("boot-nodes") {
+            self.boot_nodes = vec![];
+            for address in boot_nodes_str.split(',') {
+                match parse_peer_address(address)? {
+                    PeerAddress::Enr(enr) => self.boot_nodes.push(enr),
+                    PeerAddress::Multiaddr(multiaddr) => self.libp2p_nodes.push(multiaddr),
+                }
+            }
         }
 
+        // Added to any multiaddrs given as `--boot-nodes`.
         if let Some(libp2p_addresses_str) = args.value_of("libp2p-addresses") {
-            self.libp2p_nodes = libp2p_addresses_str
-                .split(',')
-                .map(|multiaddr| {
+            for multiaddr in libp2p_addresses_str.split(',') {
+                self.libp2p_nodes.push(
                     multiaddr
                         .parse()
-                        .map_err(|_| format!("Invalid Multiaddr: {}", multiaddr))
-                })
+                        .map_err(|_| format!("Invalid Multiaddr: {}", multiaddr))?,
+                );
+            }
+        }
+
+        if let Some(trusted_peers_str) = args.value_of("trusted-peers") {
+            self.trusted_peers = trusted_peers_str
+                .split(',')
+                .map(|address| parse_peer_address(address).and_then(trusted_peer_multiaddr))
                 .collect::<Result<Vec<Multiaddr>, _>>()?;
         }
 
@@ -156,7 +230,8 @@ impl Config {
         if let Some(discovery_address_str) = args.value_of("discovery-address") {
             self.discovery_address = discovery_address_str
                 .parse()
-                .map_err(|_| format!("Invalid discovery address: {:?}", discovery_address_str))?
+                .map_err(|_| format!("Invalid discovery address: {:?}", discovery_address_str))?;
+            self.enr_address_pinned = true;
         }
 
         if let Some(disc_port_str) = args.value_of("disc-port") {
@@ -165,10 +240,313 @@ impl Config {
                 .map_err(|_| format!("Invalid discovery port: {}", disc_port_str))?;
         }
 
+        if args.is_present("enable-upnp") {
+            self.upnp_enabled = true;
+        }
+
+        if args.is_present("enable-nat-pmp") {
+            self.nat_pmp_enabled = true;
+        }
+
         if let Some(p2p_priv_key) = args.value_of("p2p-priv-key") {
             self.secret_key_hex = Some(p2p_priv_key.to_string());
         }
 
+        if let Some(profile_str) = args.value_of("gossipsub-profile") {
+            self.gossipsub = GossipsubParams::from_profile(profile_str.parse()?);
+        }
+
+        if let Some(d_str) = args.value_of("gossipsub-d") {
+            self.gossipsub.mesh_n = d_str
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid gossipsub D: {}", d_str))?;
+        }
+
+        if let Some(d_low_str) = args.value_of("gossipsub-d-low") {
+            self.gossipsub.mesh_n_low = d_low_str
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid gossipsub D_low: {}", d_low_str))?;
+        }
+
+        if let Some(d_high_str) = args.value_of("gossipsub-d-high") {
+            self.gossipsub.mesh_n_high = d_high_str
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid gossipsub D_high: {}", d_high_str))?;
+        }
+
+        if let Some(interval_str) = args.value_of("gossipsub-heartbeat-interval") {
+            self.gossipsub.heartbeat_interval = interval_str
+                .parse::<u64>()
+                .map(Duration::from_millis)
+                .map_err(|_| format!("Invalid gossipsub heartbeat interval: {}", interval_str))?;
+        }
+
+        if let Some(history_length_str) = args.value_of("gossipsub-history-length") {
+            self.gossipsub.history_length = history_length_str
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid gossipsub history length: {}", history_length_str))?;
+            self.gossipsub.history_gossip =
+                std::cmp::min(self.gossipsub.history_gossip, self.gossipsub.history_length);
+        }
+
+        self.gossipsub.validate()?;
+
         Ok(())
     }
 }
+
+/// A named set of gossipsub mesh parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GossipsubProfile {
+    /// The parameters recommended by the gossipsub specification.
+    Default,
+    /// A smaller mesh with less frequent heartbeats, which reduces the bandwidth spent on
+    /// duplicate messages and control traffic at the cost of higher propagation latency.
+    LowBandwidth,
+    /// A larger mesh with frequent heartbeats, which minimises propagation latency at the cost of
+    /// higher bandwidth.
+    Aggressive,
+}
+
+impl FromStr for GossipsubProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(GossipsubProfile::Default),
+            "low-bandwidth" => Ok(GossipsubProfile::LowBandwidth),
+            "aggressive" => Ok(GossipsubProfile::Aggressive),
+            other => Err(format!("Unknown gossipsub profile: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for GossipsubProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GossipsubProfile::Default => write!(f, "default"),
+            GossipsubProfile::LowBandwidth => write!(f, "low-bandwidth"),
+            GossipsubProfile::Aggressive => write!(f, "aggressive"),
+        }
+    }
+}
+
+/// The gossipsub mesh parameters.
+///
+/// Peer scoring is not supported by the gossipsub implementation in use, so there are no scoring
+/// parameters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GossipsubParams {
+    /// The target number of peers in the mesh of each topic (`D`).
+    pub mesh_n: usize,
+    /// The number of mesh peers below which more peers are grafted (`D_low`).
+    pub mesh_n_low: usize,
+    /// The number of mesh peers above which peers are pruned (`D_high`).
+    pub mesh_n_high: usize,
+    /// The number of peers outside the mesh that message ids are gossiped to (`D_lazy`).
+    pub gossip_lazy: usize,
+    /// The time between heartbeats, which maintain the mesh and emit gossip.
+    pub heartbeat_interval: Duration,
+    /// The number of heartbeats that messages are kept in the message cache for.
+    pub history_length: usize,
+    /// The number of heartbeats of the message cache that are gossiped.
+    pub history_gossip: usize,
+}
+
+impl Default for GossipsubParams {
+    fn default() -> Self {
+        GossipsubParams::from_profile(GossipsubProfile::Default)
+    }
+}
+
+impl GossipsubParams {
+    /// Returns the parameters of `profile`.
+    pub fn from_profile(profile: GossipsubProfile) -> Self {
+        match profile {
+            GossipsubProfile::Default => GossipsubParams {
+                mesh_n: 6,
+                mesh_n_low: 4,
+                mesh_n_high: 12,
+                gossip_lazy: 6,
+                heartbeat_interval: Duration::from_secs(20), // TODO: Reduce for mainnet
+                history_length: 5,
+                history_gossip: 3,
+            },
+            GossipsubProfile::LowBandwidth => GossipsubParams {
+                mesh_n: 4,
+                mesh_n_low: 3,
+                mesh_n_high: 6,
+                gossip_lazy: 3,
+                heartbeat_interval: Duration::from_secs(30),
+                history_length: 4,
+                history_gossip: 2,
+            },
+            GossipsubProfile::Aggressive => GossipsubParams {
+                mesh_n: 8,
+                mesh_n_low: 6,
+                mesh_n_high: 16,
+                gossip_lazy: 8,
+                heartbeat_interval: Duration::from_secs(1),
+                history_length: 6,
+                history_gossip: 3,
+            },
+        }
+    }
+
+    /// Returns an error if the parameters would not form a usable mesh.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.mesh_n == 0 {
+            return Err("The gossipsub D must be greater than zero".into());
+        }
+        if self.mesh_n_low > self.mesh_n || self.mesh_n > self.mesh_n_high {
+            return Err(format!(
+                "The gossipsub parameters must satisfy D_low ({}) <= D ({}) <= D_high ({})",
+                self.mesh_n_low, self.mesh_n, self.mesh_n_high
+            ));
+        }
+        if self.heartbeat_interval == Duration::from_secs(0) {
+            return Err("The gossipsub heartbeat interval must be greater than zero".into());
+        }
+        if self.history_gossip > self.history_length {
+            return Err(format!(
+                "The gossipsub history gossip ({}) exceeds the history length ({})",
+                self.history_gossip, self.history_length
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A peer given on the command line.
+enum PeerAddress {
+    Enr(Enr),
+    Multiaddr(Multiaddr),
+}
+
+/// Parses a peer given on the command line as either a multiaddr, which always begins with a
+/// `/`, or a base64-encoded ENR.
+fn parse_peer_address(address: &str) -> Result<PeerAddress, String> {
+    if address.starts_with('/') {
+        address
+            .parse()
+            .map(PeerAddress::Multiaddr)
+            .map_err(|_| format!("Invalid Multiaddr: {}", address))
+    } else {
+        address
+            .parse()
+            .map(PeerAddress::Enr)
+            .map_err(|_| format!("Invalid ENR: {}", address))
+    }
+}
+
+/// Returns the TCP multiaddr of a trusted peer, ending with the `/p2p/` id of the peer.
+///
+/// The id of a trusted peer must be known so that it can be exempted from scoring, so a multiaddr
+/// without a `/p2p/` component is rejected.
+fn trusted_peer_multiaddr(address: PeerAddress) -> Result<Multiaddr, String> {
+    match address {
+        PeerAddress::Enr(enr) => {
+            let mut multiaddr = enr
+                .multiaddr()
+                .into_iter()
+                .find(|multiaddr| {
+                    multiaddr.iter().any(|protocol| match protocol {
+                        Protocol::Tcp(_) => true,
+                        _ => false,
+                    })
+                })
+                .ok_or_else(|| format!("Trusted peer ENR has no TCP address: {}", enr))?;
+            multiaddr.push(Protocol::P2p(enr.peer_id().into()));
+            Ok(multiaddr)
+        }
+        PeerAddress::Multiaddr(multiaddr) => {
+            if multiaddr_peer_id(&multiaddr).is_some() {
+                Ok(multiaddr)
+            } else {
+                Err(format!(
+                    "Trusted peer multiaddr must end with a /p2p/ peer id: {}",
+                    multiaddr
+                ))
+            }
+        }
+    }
+}
+
+/// Returns the peer id of the `/p2p/` component of `multiaddr`, if it has one.
+pub(crate) fn multiaddr_peer_id(multiaddr: &Multiaddr) -> Option<PeerId> {
+    multiaddr.iter().find_map(|protocol| match protocol {
+        Protocol::P2p(multihash) => PeerId::from_multihash(multihash).ok(),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gossipsub_profiles_are_valid() {
+        for profile in &[
+            GossipsubProfile::Default,
+            GossipsubProfile::LowBandwidth,
+            GossipsubProfile::Aggressive,
+        ] {
+            assert_eq!(profile.to_string().parse(), Ok(*profile));
+            assert_eq!(GossipsubParams::from_profile(*profile).validate(), Ok(()));
+        }
+        assert!("fast".parse::<GossipsubProfile>().is_err());
+    }
+
+    #[test]
+    fn libp2p_addresses_are_added_to_boot_node_multiaddrs() {
+        let app = clap::App::new("test")
+            .arg(
+                clap::Arg::with_name("boot-nodes")
+                    .long("boot-nodes")
+                    .takes_value(true),
+            )
+            .arg(
+                clap::Arg::with_name("libp2p-addresses")
+                    .long("libp2p-addresses")
+                    .takes_value(true),
+            );
+        let args = app.get_matches_from(vec![
+            "test",
+            "--boot-nodes",
+            "/ip4/10.0.0.1/tcp/9000",
+            "--libp2p-addresses",
+            "/ip4/10.0.0.2/tcp/9000,/ip4/10.0.0.3/tcp/9000",
+        ]);
+
+        let mut config = Config::default();
+        config.apply_cli_args(&args).unwrap();
+        assert_eq!(
+            config.libp2p_nodes,
+            vec![
+                "/ip4/10.0.0.1/tcp/9000".parse::<Multiaddr>().unwrap(),
+                "/ip4/10.0.0.2/tcp/9000".parse().unwrap(),
+                "/ip4/10.0.0.3/tcp/9000".parse().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_gossipsub_params() {
+        let mut params = GossipsubParams::default();
+        params.mesh_n_low = params.mesh_n + 1;
+        assert!(params.validate().is_err());
+
+        let mut params = GossipsubParams::default();
+        params.mesh_n_high = params.mesh_n - 1;
+        assert!(params.validate().is_err());
+
+        let mut params = GossipsubParams::default();
+        params.history_gossip = params.history_length + 1;
+        assert!(params.validate().is_err());
+
+        let mut params = GossipsubParams::default();
+        params.heartbeat_interval = Duration::from_secs(0);
+        assert!(params.validate().is_err());
+    }
+}
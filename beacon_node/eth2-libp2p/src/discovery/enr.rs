@@ -0,0 +1,164 @@
+//! Helper functions and an extension trait for the local and remote Ethereum Node Records (ENRs)
+//! used by eth2.
+use crate::NetworkConfig;
+use libp2p::core::identity::Keypair;
+use libp2p::enr::{Enr, EnrBuilder};
+use slog::{debug, warn};
+use ssz::{Decode, Encode};
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+use std::str::FromStr;
+use types::{typenum, BitVector, EnrForkId};
+
+/// The ENR field specifying the fork id.
+pub const ETH2_ENR_KEY: &str = "eth2";
+/// The ENR field specifying the attestation subnets that the node is subscribed to.
+pub const BITFIELD_ENR_KEY: &str = "attnets";
+/// Local ENR storage filename.
+const ENR_FILENAME: &str = "enr.dat";
+
+/// A bitfield of the attestation subnets that a node is subscribed to, as advertised in its ENR.
+pub type SubnetBitfield = BitVector<typenum::U64>;
+
+/// Extension trait for reading the eth2-specific fields of an ENR.
+pub trait Eth2Enr {
+    /// The attestation subnets that the node advertises.
+    fn bitfield(&self) -> Result<SubnetBitfield, &'static str>;
+
+    /// The fork id that the node advertises.
+    fn eth2(&self) -> Result<EnrForkId, &'static str>;
+}
+
+impl Eth2Enr for Enr {
+    fn bitfield(&self) -> Result<SubnetBitfield, &'static str> {
+        let bitfield_bytes = self
+            .get(BITFIELD_ENR_KEY)
+            .ok_or_else(|| "ENR has no attestation subnet bitfield")?;
+
+        SubnetBitfield::from_ssz_bytes(bitfield_bytes)
+            .map_err(|_| "Could not decode the ENR attestation subnet bitfield")
+    }
+
+    fn eth2(&self) -> Result<EnrForkId, &'static str> {
+        let eth2_bytes = self
+            .get(ETH2_ENR_KEY)
+            .ok_or_else(|| "ENR has no eth2 field")?;
+
+        EnrForkId::from_ssz_bytes(eth2_bytes).map_err(|_| "Could not decode the ENR eth2 field")
+    }
+}
+
+/// Builds the local ENR from the configuration, advertising `enr_fork_id` and no attestation
+/// subnets.
+fn build_enr(
+    local_key: &Keypair,
+    config: &NetworkConfig,
+    enr_fork_id: &EnrForkId,
+) -> Result<Enr, String> {
+    // Note: Discovery should update the ENR record's IP to the external IP as seen by the
+    // majority of our peers.
+    let mut builder = EnrBuilder::new("v4");
+    builder
+        .ip(config.discovery_address)
+        .tcp(config.libp2p_port)
+        .udp(config.discovery_port)
+        .add_value(ETH2_ENR_KEY, enr_fork_id.as_ssz_bytes())
+        .add_value(BITFIELD_ENR_KEY, SubnetBitfield::new().as_ssz_bytes());
+
+    builder
+        .build(local_key)
+        .map_err(|e| format!("Could not build Local ENR: {:?}", e))
+}
+
+/// Loads an ENR from file if it exists and matches the current NodeId and configuration. If none
+/// exists, generates a new one.
+///
+/// If an ENR exists with the same NodeId, addresses and eth2 fields, we use the disk-generated one
+/// as its ENR sequence will be equal or higher than a newly generated one.
+pub fn load_enr(
+    local_key: &Keypair,
+    config: &NetworkConfig,
+    enr_fork_id: &EnrForkId,
+    log: &slog::Logger,
+) -> Result<Enr, String> {
+    let mut local_enr = build_enr(local_key, config, enr_fork_id)?;
+
+    let enr_f = config.network_dir.join(ENR_FILENAME);
+    if let Ok(mut enr_file) = File::open(enr_f.clone()) {
+        let mut enr_string = String::new();
+        match enr_file.read_to_string(&mut enr_string) {
+            Err(_) => debug!(log, "Could not read ENR from file"),
+            Ok(_) => {
+                match Enr::from_str(&enr_string) {
+                    Ok(enr) => {
+                        if enr.node_id() == local_enr.node_id() {
+                            if enr.ip().map(Into::into) == Some(config.discovery_address)
+                                && enr.tcp() == Some(config.libp2p_port)
+                                && enr.udp() == Some(config.discovery_port)
+                                && enr.get(ETH2_ENR_KEY) == local_enr.get(ETH2_ENR_KEY)
+                                && enr.get(BITFIELD_ENR_KEY) == local_enr.get(BITFIELD_ENR_KEY)
+                            {
+                                debug!(log, "ENR loaded from file"; "file" => format!("{:?}", enr_f));
+                                // the stored ENR has the same configuration, use it
+                                return Ok(enr);
+                            }
+
+                            // same node id, different configuration - update the sequence number
+                            let new_seq_no = enr.seq().checked_add(1).ok_or_else(|| "ENR sequence number on file is too large. Remove it to generate a new NodeId")?;
+                            local_enr.set_seq(new_seq_no, local_key).map_err(|e| {
+                                format!("Could not update ENR sequence number: {:?}", e)
+                            })?;
+                            debug!(log, "ENR sequence number increased"; "seq" =>  new_seq_no);
+                        }
+                    }
+                    Err(e) => {
+                        warn!(log, "ENR from file could not be decoded"; "error" => format!("{:?}", e));
+                    }
+                }
+            }
+        }
+    }
+
+    save_enr_to_disc(&config.network_dir, &local_enr, log);
+
+    Ok(local_enr)
+}
+
+pub fn save_enr_to_disc(dir: &Path, enr: &Enr, log: &slog::Logger) {
+    let _ = std::fs::create_dir_all(dir);
+    match File::create(dir.join(Path::new(ENR_FILENAME)))
+        .and_then(|mut f| f.write_all(&enr.to_base64().as_bytes()))
+    {
+        Ok(_) => {
+            debug!(log, "ENR written to disk");
+        }
+        Err(e) => {
+            warn!(
+                log,
+                "Could not write ENR to file"; "file" => format!("{:?}{:?}",dir, ENR_FILENAME),  "error" => format!("{}", e)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::Epoch;
+
+    #[test]
+    fn eth2_fields() {
+        let local_key = Keypair::generate_secp256k1();
+        let enr_fork_id = EnrForkId {
+            fork_digest: [1, 2, 3, 4],
+            next_fork_version: [0; 4],
+            next_fork_epoch: Epoch::new(42),
+        };
+
+        let enr = build_enr(&local_key, &NetworkConfig::default(), &enr_fork_id).unwrap();
+
+        assert_eq!(enr.eth2(), Ok(enr_fork_id));
+        assert_eq!(enr.bitfield(), Ok(SubnetBitfield::new()));
+    }
+}
@@ -1,40 +1,61 @@
+//! This manages the discovery and management of peers.
+//!
+//! Currently using discv5 for peer discovery.
+pub mod enr;
+
+use self::enr::{load_enr, save_enr_to_disc, Eth2Enr, BITFIELD_ENR_KEY, ETH2_ENR_KEY};
 use crate::metrics;
+use crate::peer_info::PeerInfo;
 use crate::{error, NetworkConfig};
-/// This manages the discovery and management of peers.
-///
-/// Currently using discv5 for peer discovery.
-///
 use futures::prelude::*;
 use libp2p::core::{identity::Keypair, ConnectedPoint, Multiaddr, PeerId};
 use libp2p::discv5::{Discv5, Discv5Event};
-use libp2p::enr::{Enr, EnrBuilder, NodeId};
+use libp2p::enr::{Enr, NodeId};
 use libp2p::multiaddr::Protocol;
 use libp2p::swarm::{NetworkBehaviour, NetworkBehaviourAction, PollParameters, ProtocolsHandler};
 use slog::{debug, info, warn};
-use std::collections::HashSet;
-use std::fs::File;
-use std::io::prelude::*;
+use ssz::Encode;
+use std::collections::HashMap;
 use std::path::Path;
-use std::str::FromStr;
 use std::time::{Duration, Instant};
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_timer::Delay;
+use types::EnrForkId;
 
 /// Maximum seconds before searching for extra peers.
 const MAX_TIME_BETWEEN_PEER_SEARCHES: u64 = 60;
 /// Initial delay between peer searches.
 const INITIAL_SEARCH_DELAY: u64 = 5;
-/// Local ENR storage filename.
-const ENR_FILENAME: &str = "enr.dat";
+
+/// The purpose of a discovery query.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum QueryType {
+    /// A search for any peers, used to maintain the target number of peers.
+    FindPeers,
+    /// A search for peers which advertise the given attestation subnet in their ENR.
+    Subnet(u64),
+}
+
+/// Events generated by the discovery behaviour.
+#[derive(Debug)]
+pub enum DiscoveryEvent {
+    /// Peers have been found which may be dialed. Peers found by a subnet query advertise the
+    /// subnet with the given id in their ENR.
+    PeersFound {
+        peers: Vec<PeerId>,
+        subnet_id: Option<u64>,
+    },
+}
 
 /// Lighthouse discovery behaviour. This provides peer management and discovery using the Discv5
 /// libp2p protocol.
 pub struct Discovery<TSubstream> {
     /// The peers currently connected to libp2p streams.
-    connected_peers: HashSet<PeerId>,
+    connected_peers: HashMap<PeerId, PeerInfo>,
 
-    /// The target number of connected peers on the libp2p interface.
-    max_peers: usize,
+    /// The target number of connected peers on the libp2p interface. Discovery searches for, and
+    /// dials, new peers whilst fewer peers are connected.
+    target_peers: usize,
 
     /// directory to save ENR to
     enr_dir: String,
@@ -42,6 +63,9 @@ pub struct Discovery<TSubstream> {
     /// The delay between peer discovery searches.
     peer_discovery_delay: Delay,
 
+    /// The queries which are in progress, keyed by the target of the query.
+    active_queries: HashMap<NodeId, QueryType>,
+
     /// Tracks the last discovery delay. The delay is doubled each round until the max
     /// time is reached.
     past_discovery_delay: u64,
@@ -62,12 +86,13 @@ impl<TSubstream> Discovery<TSubstream> {
     pub fn new(
         local_key: &Keypair,
         config: &NetworkConfig,
+        enr_fork_id: &EnrForkId,
         log: &slog::Logger,
     ) -> error::Result<Self> {
         let log = log.clone();
 
         // checks if current ENR matches that found on disk
-        let local_enr = load_enr(local_key, config, &log)?;
+        let local_enr = load_enr(local_key, config, enr_fork_id, &log)?;
 
         let enr_dir = match config.network_dir.to_str() {
             Some(path) => String::from(path),
@@ -92,9 +117,10 @@ impl<TSubstream> Discovery<TSubstream> {
         }
 
         Ok(Self {
-            connected_peers: HashSet::new(),
-            max_peers: config.max_peers,
+            connected_peers: HashMap::new(),
+            target_peers: config.target_peers,
             peer_discovery_delay: Delay::new(Instant::now()),
+            active_queries: HashMap::new(),
             past_discovery_delay: INITIAL_SEARCH_DELAY,
             tcp_port: config.libp2p_port,
             discovery,
@@ -125,6 +151,61 @@ impl<TSubstream> Discovery<TSubstream> {
         self.discovery.local_enr()
     }
 
+    /// Sets whether the local ENR advertises the attestation subnet with id `subnet_id`.
+    pub fn update_enr_subnet(&mut self, subnet_id: u64, value: bool) -> Result<(), String> {
+        let mut bitfield = self.local_enr().bitfield()?;
+
+        if bitfield
+            .get(subnet_id as usize)
+            .map_err(|_| format!("Subnet id {} is larger than the ENR bitfield", subnet_id))?
+            == value
+        {
+            return Ok(());
+        }
+
+        bitfield
+            .set(subnet_id as usize, value)
+            .map_err(|_| format!("Subnet id {} is larger than the ENR bitfield", subnet_id))?;
+
+        self.update_enr_field(BITFIELD_ENR_KEY, bitfield.as_ssz_bytes())
+    }
+
+    /// Sets the fork id advertised in the `eth2` field of the local ENR.
+    pub fn update_eth2_enr(&mut self, enr_fork_id: EnrForkId) -> Result<(), String> {
+        if self.local_enr().eth2().as_ref() == Ok(&enr_fork_id) {
+            return Ok(());
+        }
+
+        self.update_enr_field(ETH2_ENR_KEY, enr_fork_id.as_ssz_bytes())
+    }
+
+    /// Inserts a field into the local ENR, incrementing its sequence number, and saves the ENR to
+    /// disk.
+    fn update_enr_field(&mut self, key: &str, value: Vec<u8>) -> Result<(), String> {
+        self.discovery
+            .enr_insert(key, value)
+            .map_err(|e| format!("Unable to update ENR field {}: {:?}", key, e))?;
+
+        let enr = self.discovery.local_enr();
+        info!(self.log, "ENR Updated"; "field" => key, "seq" => enr.seq());
+        save_enr_to_disc(Path::new(&self.enr_dir), enr, &self.log);
+
+        Ok(())
+    }
+
+    /// Search for peers which advertise the attestation subnet with id `subnet_id` in their ENR.
+    ///
+    /// Does nothing if a search for the subnet is already in progress.
+    pub fn discover_subnet_peers(&mut self, subnet_id: u64) {
+        let query = QueryType::Subnet(subnet_id);
+        if self.active_queries.values().any(|active| *active == query) {
+            return;
+        }
+
+        debug!(self.log, "Searching for subnet peers"; "subnet_id" => subnet_id);
+        self.start_query(query);
+    }
+
     /// Manually search for peers. This restarts the discovery round, sparking multiple rapid
     /// queries.
     pub fn discover_peers(&mut self) {
@@ -142,17 +223,46 @@ impl<TSubstream> Discovery<TSubstream> {
         self.connected_peers.len()
     }
 
-    /// The current number of connected libp2p peers.
-    pub fn connected_peer_set(&self) -> &HashSet<PeerId> {
+    /// The set of connected libp2p peers, along with information about each connection.
+    pub fn connected_peer_set(&self) -> &HashMap<PeerId, PeerInfo> {
         &self.connected_peers
     }
 
+    /// Returns the ENR of `peer_id`, if it is present in the discovery routing table.
+    fn enr_of_peer(&mut self, peer_id: &PeerId) -> Option<Enr> {
+        self.discovery
+            .enr_entries()
+            .find(|enr| enr.peer_id() == *peer_id)
+            .cloned()
+    }
+
+    /// Starts a query for the nodes closest to a random `NodeId`.
+    fn start_query(&mut self, query: QueryType) {
+        let random_node = NodeId::random();
+        self.active_queries.insert(random_node, query);
+        self.discovery.find_node(random_node);
+    }
+
+    /// Returns `true` if `enr` advertises the attestation subnet with id `subnet_id` and a fork
+    /// which is compatible with our own.
+    fn enr_matches_subnet(&self, enr: &Enr, subnet_id: u64) -> bool {
+        let same_fork = match (enr.eth2(), self.local_enr().eth2()) {
+            (Ok(remote), Ok(local)) => remote.fork_digest == local.fork_digest,
+            _ => false,
+        };
+
+        same_fork
+            && enr
+                .bitfield()
+                .ok()
+                .and_then(|bitfield| bitfield.get(subnet_id as usize).ok())
+                .unwrap_or(false)
+    }
+
     /// Search for new peers using the underlying discovery mechanism.
     fn find_peers(&mut self) {
-        // pick a random NodeId
-        let random_node = NodeId::random();
         debug!(self.log, "Searching for peers");
-        self.discovery.find_node(random_node);
+        self.start_query(QueryType::FindPeers);
 
         // update the time until next discovery
         let delay = {
@@ -174,7 +284,7 @@ where
     TSubstream: AsyncRead + AsyncWrite,
 {
     type ProtocolsHandler = <Discv5<TSubstream> as NetworkBehaviour>::ProtocolsHandler;
-    type OutEvent = <Discv5<TSubstream> as NetworkBehaviour>::OutEvent;
+    type OutEvent = DiscoveryEvent;
 
     fn new_handler(&mut self) -> Self::ProtocolsHandler {
         NetworkBehaviour::new_handler(&mut self.discovery)
@@ -185,9 +295,11 @@ where
         self.discovery.addresses_of_peer(peer_id)
     }
 
-    fn inject_connected(&mut self, peer_id: PeerId, _endpoint: ConnectedPoint) {
-        self.connected_peers.insert(peer_id);
-        // TODO: Drop peers if over max_peer limit
+    fn inject_connected(&mut self, peer_id: PeerId, endpoint: ConnectedPoint) {
+        let enr = self.enr_of_peer(&peer_id);
+        self.connected_peers
+            .insert(peer_id, PeerInfo::new(&endpoint, enr));
+        // Excess peers are disconnected by the `PeerManager`.
 
         metrics::inc_counter(&metrics::PEER_CONNECT_EVENT_COUNT);
         metrics::set_gauge(&metrics::PEERS_CONNECTED, self.connected_peers() as i64);
@@ -202,11 +314,14 @@ where
 
     fn inject_replaced(
         &mut self,
-        _peer_id: PeerId,
+        peer_id: PeerId,
         _closed: ConnectedPoint,
-        _opened: ConnectedPoint,
+        opened: ConnectedPoint,
     ) {
-        // discv5 doesn't implement
+        // discv5 doesn't implement, however the connection details may have changed.
+        let enr = self.enr_of_peer(&peer_id);
+        self.connected_peers
+            .insert(peer_id, PeerInfo::new(&opened, enr));
     }
 
     fn inject_node_event(
@@ -230,7 +345,7 @@ where
         loop {
             match self.peer_discovery_delay.poll() {
                 Ok(Async::Ready(_)) => {
-                    if self.connected_peers.len() < self.max_peers {
+                    if self.connected_peers.len() < self.target_peers {
                         self.find_peers();
                     }
                 }
@@ -246,9 +361,33 @@ where
             match self.discovery.poll(params) {
                 Async::Ready(NetworkBehaviourAction::GenerateEvent(event)) => {
                     match event {
-                        Discv5Event::Discovered(_enr) => {
-                            // not concerned about FINDNODE results, rather the result of an entire
-                            // query.
+                        Discv5Event::Discovered(enr) => {
+                            // Peers found by a random query are dialed once the query completes,
+                            // however peers on a sought-after subnet are dialed immediately.
+                            let peer_id = enr.peer_id();
+                            if self.connected_peers.contains_key(&peer_id) {
+                                continue;
+                            }
+
+                            let subnet_id =
+                                self.active_queries.values().find_map(|query| match query {
+                                    QueryType::Subnet(subnet_id)
+                                        if self.enr_matches_subnet(&enr, *subnet_id) =>
+                                    {
+                                        Some(*subnet_id)
+                                    }
+                                    _ => None,
+                                });
+
+                            if let Some(subnet_id) = subnet_id {
+                                debug!(self.log, "Subnet peer discovered"; "peer_id" => format!("{:?}", peer_id), "subnet_id" => subnet_id);
+                                return Async::Ready(NetworkBehaviourAction::GenerateEvent(
+                                    DiscoveryEvent::PeersFound {
+                                        peers: vec![peer_id],
+                                        subnet_id: Some(subnet_id),
+                                    },
+                                ));
+                            }
                         }
                         Discv5Event::SocketUpdated(socket) => {
                             info!(self.log, "Address updated"; "IP" => format!("{}",socket.ip()));
@@ -262,21 +401,30 @@ where
                                 address,
                             });
                         }
-                        Discv5Event::FindNodeResult { closer_peers, .. } => {
-                            debug!(self.log, "Discovery query completed"; "peers_found" => closer_peers.len());
+                        Discv5Event::FindNodeResult { key, closer_peers } => {
+                            let query = self.active_queries.remove(&key);
+                            debug!(self.log, "Discovery query completed"; "peers_found" => closer_peers.len(), "query" => format!("{:?}", query));
                             if closer_peers.is_empty() {
                                 debug!(self.log, "Discovery random query found no peers");
                             }
-                            for peer_id in closer_peers {
-                                // if we need more peers, attempt a connection
-                                if self.connected_peers.len() < self.max_peers
-                                    && self.connected_peers.get(&peer_id).is_none()
-                                {
-                                    debug!(self.log, "Peer discovered"; "peer_id"=> format!("{:?}", peer_id));
-                                    return Async::Ready(NetworkBehaviourAction::DialPeer {
-                                        peer_id,
-                                    });
-                                }
+
+                            // Subnet peers have already been reported as they were discovered.
+                            if query != Some(QueryType::FindPeers) {
+                                continue;
+                            }
+
+                            // the peer manager dials these peers if we need more peers
+                            let peers = closer_peers
+                                .into_iter()
+                                .filter(|peer_id| !self.connected_peers.contains_key(peer_id))
+                                .collect::<Vec<_>>();
+                            if !peers.is_empty() {
+                                return Async::Ready(NetworkBehaviourAction::GenerateEvent(
+                                    DiscoveryEvent::PeersFound {
+                                        peers,
+                                        subnet_id: None,
+                                    },
+                                ));
                             }
                         }
                         _ => {}
@@ -290,79 +438,3 @@ where
         Async::NotReady
     }
 }
-
-/// Loads an ENR from file if it exists and matches the current NodeId and sequence number. If none
-/// exists, generates a new one.
-///
-/// If an ENR exists, with the same NodeId and IP address, we use the disk-generated one as its
-/// ENR sequence will be equal or higher than a newly generated one.
-fn load_enr(
-    local_key: &Keypair,
-    config: &NetworkConfig,
-    log: &slog::Logger,
-) -> Result<Enr, String> {
-    // Build the local ENR.
-    // Note: Discovery should update the ENR record's IP to the external IP as seen by the
-    // majority of our peers.
-    let mut local_enr = EnrBuilder::new("v4")
-        .ip(config.discovery_address)
-        .tcp(config.libp2p_port)
-        .udp(config.discovery_port)
-        .build(&local_key)
-        .map_err(|e| format!("Could not build Local ENR: {:?}", e))?;
-
-    let enr_f = config.network_dir.join(ENR_FILENAME);
-    if let Ok(mut enr_file) = File::open(enr_f.clone()) {
-        let mut enr_string = String::new();
-        match enr_file.read_to_string(&mut enr_string) {
-            Err(_) => debug!(log, "Could not read ENR from file"),
-            Ok(_) => {
-                match Enr::from_str(&enr_string) {
-                    Ok(enr) => {
-                        if enr.node_id() == local_enr.node_id() {
-                            if enr.ip().map(Into::into) == Some(config.discovery_address)
-                                && enr.tcp() == Some(config.libp2p_port)
-                                && enr.udp() == Some(config.discovery_port)
-                            {
-                                debug!(log, "ENR loaded from file"; "file" => format!("{:?}", enr_f));
-                                // the stored ENR has the same configuration, use it
-                                return Ok(enr);
-                            }
-
-                            // same node id, different configuration - update the sequence number
-                            let new_seq_no = enr.seq().checked_add(1).ok_or_else(|| "ENR sequence number on file is too large. Remove it to generate a new NodeId")?;
-                            local_enr.set_seq(new_seq_no, local_key).map_err(|e| {
-                                format!("Could not update ENR sequence number: {:?}", e)
-                            })?;
-                            debug!(log, "ENR sequence number increased"; "seq" =>  new_seq_no);
-                        }
-                    }
-                    Err(e) => {
-                        warn!(log, "ENR from file could not be decoded"; "error" => format!("{:?}", e));
-                    }
-                }
-            }
-        }
-    }
-
-    save_enr_to_disc(&config.network_dir, &local_enr, log);
-
-    Ok(local_enr)
-}
-
-fn save_enr_to_disc(dir: &Path, enr: &Enr, log: &slog::Logger) {
-    let _ = std::fs::create_dir_all(dir);
-    match File::create(dir.join(Path::new(ENR_FILENAME)))
-        .and_then(|mut f| f.write_all(&enr.to_base64().as_bytes()))
-    {
-        Ok(_) => {
-            debug!(log, "ENR written to disk");
-        }
-        Err(e) => {
-            warn!(
-                log,
-                "Could not write ENR to file"; "file" => format!("{:?}{:?}",dir, ENR_FILENAME),  "error" => format!("{}", e)
-            );
-        }
-    }
-}
@@ -0,0 +1,99 @@
+use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+/// A time-bounded set of the ids of recently received gossipsub messages.
+///
+/// Gossipsub only de-duplicates messages by their publisher and sequence number, so identical
+/// messages that are published by several peers (e.g. the same attestation re-published during
+/// an aggregation window) are otherwise all passed on to the beacon chain for verification.
+pub struct DuplicateCache {
+    /// The ids in the cache.
+    set: HashSet<String>,
+    /// The ids in the cache, with the time they were inserted, oldest first.
+    list: VecDeque<(Instant, String)>,
+    /// How long an id remains in the cache.
+    ttl: Duration,
+}
+
+impl DuplicateCache {
+    pub fn new(ttl: Duration) -> Self {
+        DuplicateCache {
+            set: HashSet::new(),
+            list: VecDeque::new(),
+            ttl,
+        }
+    }
+
+    /// Inserts a message id. Returns `false` if the id is already in the cache.
+    pub fn insert(&mut self, id: String) -> bool {
+        self.insert_at(id, Instant::now())
+    }
+
+    /// Returns the number of ids in the cache.
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    /// Returns `true` if the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    fn insert_at(&mut self, id: String, now: Instant) -> bool {
+        self.prune(now);
+
+        if self.set.contains(&id) {
+            return false;
+        }
+
+        self.set.insert(id.clone());
+        self.list.push_back((now, id));
+        true
+    }
+
+    /// Removes the ids that were inserted more than `ttl` before `now`.
+    fn prune(&mut self, now: Instant) {
+        while let Some((inserted, _)) = self.list.front() {
+            if now.duration_since(*inserted) < self.ttl {
+                break;
+            }
+            if let Some((_, id)) = self.list.pop_front() {
+                self.set.remove(&id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_duplicates() {
+        let mut cache = DuplicateCache::new(Duration::from_secs(60));
+
+        assert!(cache.insert("a".into()));
+        assert!(cache.insert("b".into()));
+        assert!(!cache.insert("a".into()));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn expires_ids() {
+        let ttl = Duration::from_secs(12);
+        let mut cache = DuplicateCache::new(ttl);
+        let start = Instant::now();
+
+        assert!(cache.insert_at("a".into(), start));
+        assert!(cache.insert_at("b".into(), start + ttl / 2));
+        assert!(!cache.insert_at("a".into(), start + ttl / 2));
+
+        // "a" has expired, "b" has not
+        assert!(cache.insert_at("a".into(), start + ttl));
+        assert!(!cache.insert_at("b".into(), start + ttl));
+        assert_eq!(cache.len(), 2);
+
+        cache.prune(start + ttl * 3);
+        assert!(cache.is_empty());
+    }
+}
@@ -8,16 +8,23 @@ extern crate lazy_static;
 pub mod behaviour;
 mod config;
 mod discovery;
+mod duplicate_cache;
 pub mod error;
 mod metrics;
+pub mod peer_db;
+pub mod peer_info;
+pub mod peer_manager;
 pub mod rpc;
 mod service;
+mod topics;
 
 pub use behaviour::PubsubMessage;
 pub use config::{
-    Config as NetworkConfig, BEACON_ATTESTATION_TOPIC, BEACON_BLOCK_TOPIC, SHARD_TOPIC_PREFIX,
-    TOPIC_ENCODING_POSTFIX, TOPIC_PREFIX,
+    Config as NetworkConfig, GossipsubParams, GossipsubProfile, BEACON_AGGREGATE_AND_PROOF_TOPIC,
+    BEACON_ATTESTATION_TOPIC, BEACON_BLOCK_TOPIC, SHARD_TOPIC_PREFIX, TOPIC_ENCODING_POSTFIX,
+    TOPIC_PREFIX,
 };
+pub use discovery::enr::{Eth2Enr, SubnetBitfield};
 pub use libp2p::enr::Enr;
 pub use libp2p::gossipsub::{Topic, TopicHash};
 pub use libp2p::multiaddr;
@@ -26,6 +33,10 @@ pub use libp2p::{
     gossipsub::{GossipsubConfig, GossipsubConfigBuilder},
     PeerId, Swarm,
 };
+pub use peer_db::PeerDb;
+pub use peer_info::{ConnectionDirection, PeerInfo};
+pub use peer_manager::{PeerAction, PeerManager, PeerManagerEvent};
 pub use rpc::RPCEvent;
 pub use service::Libp2pEvent;
 pub use service::Service;
+pub use topics::{message_id, GossipEncoding, GossipKind, GossipTopic};
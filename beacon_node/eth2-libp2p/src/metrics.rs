@@ -17,4 +17,12 @@ lazy_static! {
         "libp2p_peer_disconnect_event_total",
         "Count of libp2p peer disconnect events"
     );
+    pub static ref PEER_BAN_EVENT_COUNT: Result<IntCounter> = try_create_int_counter(
+        "libp2p_peer_ban_event_total",
+        "Count of peers banned for having a low score"
+    );
+    pub static ref GOSSIP_DUPLICATE_MESSAGE_COUNT: Result<IntCounter> = try_create_int_counter(
+        "libp2p_gossip_duplicate_message_total",
+        "Count of gossipsub messages dropped for having been seen recently"
+    );
 }
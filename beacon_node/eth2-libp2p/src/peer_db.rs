@@ -0,0 +1,290 @@
+//! Persists the ENRs of well-behaved peers in the network directory, so that they can be dialed
+//! immediately after a restart rather than waiting for discovery to find new peers.
+//!
+//! Records of peers which have not been connected for `RECORD_EXPIRY` are dropped, as are the
+//! records of peers whose score falls to the `DISCONNECT_THRESHOLD` of the `PeerManager`.
+use crate::peer_manager::DISCONNECT_THRESHOLD;
+use libp2p::enr::Enr;
+use libp2p::PeerId;
+use serde_derive::{Deserialize, Serialize};
+use slog::{debug, warn};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The file in the network directory that the peer records are stored in.
+pub const PEER_DB_FILENAME: &str = "peers.json";
+/// The time after which the record of a peer that has not been connected is dropped, in seconds.
+pub const RECORD_EXPIRY: u64 = 3 * 24 * 60 * 60;
+/// The maximum number of records stored. The most recently seen peers are kept.
+pub const MAX_RECORDS: usize = 256;
+
+/// The record of a peer, as stored on disk.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PersistedPeer {
+    /// The base64 encoding of the ENR of the peer.
+    enr: String,
+    /// The UNIX time, in seconds, at which the peer was last connected.
+    last_seen: u64,
+    /// The score of the peer when it was last connected.
+    score: f64,
+}
+
+#[derive(Debug, Clone)]
+struct PeerRecord {
+    enr: Enr,
+    last_seen: u64,
+    score: f64,
+}
+
+/// The records of well-behaved peers, which are saved to and loaded from the network directory.
+pub struct PeerDb {
+    path: PathBuf,
+    records: HashMap<PeerId, PeerRecord>,
+    log: slog::Logger,
+}
+
+impl PeerDb {
+    /// Loads the records stored in `network_dir`, dropping any which have expired.
+    ///
+    /// Starts with no records if the file does not exist or cannot be read.
+    pub fn open(network_dir: &Path, log: &slog::Logger) -> Self {
+        let mut peer_db = Self {
+            path: network_dir.join(PEER_DB_FILENAME),
+            records: HashMap::new(),
+            log: log.clone(),
+        };
+
+        if peer_db.path.exists() {
+            match peer_db.load(unix_now()) {
+                Ok(()) => {
+                    debug!(log, "Loaded peer records"; "peers" => peer_db.records.len());
+                }
+                Err(e) => {
+                    warn!(log, "Could not load peer records"; "file" => format!("{:?}", peer_db.path), "error" => e);
+                }
+            }
+        }
+
+        peer_db
+    }
+
+    fn load(&mut self, now: u64) -> Result<(), String> {
+        let bytes = fs::read(&self.path).map_err(|e| format!("{}", e))?;
+        let persisted: Vec<PersistedPeer> =
+            serde_json::from_slice(&bytes).map_err(|e| format!("{}", e))?;
+
+        for peer in persisted {
+            match Enr::from_str(&peer.enr) {
+                Ok(enr) => self.insert(enr, peer.score, peer.last_seen),
+                Err(e) => {
+                    debug!(self.log, "Ignoring invalid peer record"; "error" => format!("{:?}", e))
+                }
+            }
+        }
+
+        self.prune(now);
+
+        Ok(())
+    }
+
+    /// The number of peer records.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Returns `true` if there are no peer records.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Returns the ENRs of the stored peers, the highest-scoring (then most recently seen) first.
+    pub fn enrs(&self) -> Vec<Enr> {
+        let mut records = self.records.values().collect::<Vec<_>>();
+        records.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(b.last_seen.cmp(&a.last_seen))
+        });
+        records
+            .into_iter()
+            .map(|record| record.enr.clone())
+            .collect()
+    }
+
+    /// Records that the peer with `enr` was connected at `now`, with the given `score`.
+    ///
+    /// The record is removed instead if the score is not above the `DISCONNECT_THRESHOLD`.
+    pub fn insert(&mut self, enr: Enr, score: f64, now: u64) {
+        let peer_id = enr.peer_id();
+        if score <= DISCONNECT_THRESHOLD {
+            self.records.remove(&peer_id);
+            return;
+        }
+
+        let record = self.records.entry(peer_id).or_insert(PeerRecord {
+            enr: enr.clone(),
+            last_seen: now,
+            score,
+        });
+        // keep the newest ENR of the peer
+        if enr.seq() >= record.enr.seq() {
+            record.enr = enr;
+        }
+        record.last_seen = std::cmp::max(record.last_seen, now);
+        record.score = score;
+    }
+
+    /// Updates the score and last-seen time of the peer with `peer_id`, if a record of it exists.
+    ///
+    /// The record is removed if the score is not above the `DISCONNECT_THRESHOLD`.
+    pub fn update(&mut self, peer_id: &PeerId, score: f64, now: u64) {
+        if let Some(enr) = self.records.get(peer_id).map(|record| record.enr.clone()) {
+            self.insert(enr, score, now);
+        }
+    }
+
+    /// Removes the record of `peer_id`, if any.
+    pub fn remove(&mut self, peer_id: &PeerId) {
+        self.records.remove(peer_id);
+    }
+
+    /// Drops the records which have expired at `now`, then the least recently seen records in
+    /// excess of `MAX_RECORDS`.
+    fn prune(&mut self, now: u64) {
+        self.records
+            .retain(|_, record| record.last_seen.saturating_add(RECORD_EXPIRY) > now);
+
+        if self.records.len() > MAX_RECORDS {
+            let mut records = self.records.drain().collect::<Vec<_>>();
+            records.sort_by(|(_, a), (_, b)| b.last_seen.cmp(&a.last_seen));
+            records.truncate(MAX_RECORDS);
+            self.records = records.into_iter().collect();
+        }
+    }
+
+    /// Prunes the expired records, then writes the remaining records to the network directory.
+    ///
+    /// The records are written to a temporary file which then replaces the existing file, so
+    /// that the existing records are not lost if the node stops part way through the write.
+    pub fn save(&mut self, now: u64) -> Result<(), String> {
+        self.prune(now);
+
+        let persisted = self
+            .records
+            .values()
+            .map(|record| PersistedPeer {
+                enr: record.enr.to_base64(),
+                last_seen: record.last_seen,
+                score: record.score,
+            })
+            .collect::<Vec<_>>();
+        let bytes = serde_json::to_vec(&persisted).map_err(|e| format!("{}", e))?;
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("{}", e))?;
+        }
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, bytes)
+            .and_then(|()| fs::rename(&temp_path, &self.path))
+            .map_err(|e| format!("{}", e))?;
+
+        debug!(self.log, "Saved peer records"; "peers" => persisted.len());
+
+        Ok(())
+    }
+}
+
+/// The current UNIX time, in seconds.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::core::identity::Keypair;
+    use libp2p::enr::EnrBuilder;
+    use slog::{o, Drain};
+
+    fn log() -> slog::Logger {
+        slog::Logger::root(slog::Discard.fuse(), o!())
+    }
+
+    fn random_enr() -> Enr {
+        EnrBuilder::new("v4")
+            .build(&Keypair::generate_secp256k1())
+            .expect("should build enr")
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "lighthouse_peer_db_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn saves_and_loads_records() {
+        let dir = temp_dir("round_trip");
+        let now = unix_now();
+        let good = random_enr();
+        let better = random_enr();
+
+        let mut peer_db = PeerDb::open(&dir, &log());
+        assert!(peer_db.is_empty());
+        peer_db.insert(good.clone(), 0.0, now);
+        peer_db.insert(better.clone(), 10.0, now);
+        peer_db.save(now).expect("should save records");
+
+        let peer_db = PeerDb::open(&dir, &log());
+        assert_eq!(peer_db.enrs(), vec![better, good]);
+
+        fs::remove_dir_all(&dir).expect("should remove temp dir");
+    }
+
+    #[test]
+    fn drops_expired_and_poorly_scored_records() {
+        let now = RECORD_EXPIRY * 2;
+        let mut peer_db = PeerDb::open(&temp_dir("expiry"), &log());
+
+        let stale = random_enr();
+        peer_db.insert(stale, 0.0, now - RECORD_EXPIRY);
+        let fresh = random_enr();
+        peer_db.insert(fresh.clone(), 0.0, now - RECORD_EXPIRY + 1);
+        let penalised = random_enr();
+        peer_db.insert(penalised.clone(), 0.0, now);
+        peer_db.update(&penalised.peer_id(), DISCONNECT_THRESHOLD, now);
+        assert_eq!(peer_db.len(), 2);
+
+        peer_db.prune(now);
+        assert_eq!(peer_db.enrs(), vec![fresh]);
+    }
+
+    #[test]
+    fn keeps_most_recently_seen_records() {
+        let mut peer_db = PeerDb::open(&temp_dir("max_records"), &log());
+        let now = MAX_RECORDS as u64;
+
+        let enrs = (0..MAX_RECORDS + 2)
+            .map(|_| random_enr())
+            .collect::<Vec<_>>();
+        for (i, enr) in enrs.iter().enumerate() {
+            peer_db.insert(enr.clone(), 0.0, i as u64);
+        }
+        peer_db.prune(now);
+
+        assert_eq!(peer_db.len(), MAX_RECORDS);
+        assert!(peer_db.records.get(&enrs[0].peer_id()).is_none());
+        assert!(peer_db.records.get(&enrs[1].peer_id()).is_none());
+    }
+}
@@ -0,0 +1,56 @@
+use libp2p::core::ConnectedPoint;
+use libp2p::enr::Enr;
+use libp2p::Multiaddr;
+use serde_derive::{Deserialize, Serialize};
+
+/// The score assigned to a newly connected peer.
+pub const DEFAULT_SCORE: f64 = 0.0;
+
+/// Indicates which side initiated a connection to a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionDirection {
+    /// The peer dialed us.
+    Inbound,
+    /// We dialed the peer.
+    Outbound,
+}
+
+impl From<&ConnectedPoint> for ConnectionDirection {
+    fn from(endpoint: &ConnectedPoint) -> Self {
+        match endpoint {
+            ConnectedPoint::Dialer { .. } => ConnectionDirection::Outbound,
+            ConnectedPoint::Listener { .. } => ConnectionDirection::Inbound,
+        }
+    }
+}
+
+/// Information about a peer that is currently connected via libp2p.
+#[derive(Debug, Clone)]
+pub struct PeerInfo {
+    /// The address that the connection was established with.
+    pub address: Multiaddr,
+    /// Indicates which side initiated the connection.
+    pub direction: ConnectionDirection,
+    /// The ENR of the peer, if it is known to discovery.
+    pub enr: Option<Enr>,
+    /// The reputation of the peer, as maintained by the `PeerManager`. Lower is worse.
+    pub score: f64,
+}
+
+impl PeerInfo {
+    /// Creates a new `PeerInfo` for a connection established via `endpoint`.
+    pub fn new(endpoint: &ConnectedPoint, enr: Option<Enr>) -> Self {
+        let address = match endpoint {
+            ConnectedPoint::Dialer { address } => address.clone(),
+            ConnectedPoint::Listener { send_back_addr, .. } => send_back_addr.clone(),
+        };
+
+        Self {
+            address,
+            direction: endpoint.into(),
+            enr,
+            score: DEFAULT_SCORE,
+        }
+    }
+}
@@ -0,0 +1,1004 @@
+//! Tracks the reputation of peers, disconnecting or banning peers which misbehave and pruning the
+//! worst-scoring peers when more than the target number of peers are connected.
+//!
+//! Peers found by discovery are queued to be dialed whilst fewer than the target number of peers
+//! are connected. Peers which advertise a needed attestation subnet take priority, and are dialed
+//! whilst fewer than the maximum number of peers are connected.
+//!
+//! Each peer has a score made up of two components:
+//!
+//! - A gossipsub component, which is penalised when the peer sends invalid gossipsub messages.
+//! - An application component, which is penalised for behaviour detected by the beacon node, such
+//!   as invalid blocks and failed or slow RPC requests.
+//!
+//! Both components decay exponentially towards zero, so that peers are able to recover from
+//! occasional faults. A peer whose score drops below `DISCONNECT_THRESHOLD` is disconnected, and
+//! a peer whose score drops below `BAN_THRESHOLD` is banned for `BAN_DURATION`.
+//!
+//! Trusted peers, given by the user, are scored but are never disconnected, banned or pruned.
+//! Whenever a trusted peer is not connected it is redialed, with an exponentially increasing
+//! delay between attempts.
+//!
+//! Connected peers are pinged every `PING_INTERVAL`. The `MetaData` of a peer is requested
+//! whenever a ping reveals a sequence number that is newer than that of the `MetaData` we hold.
+//!
+//! If given a `PeerDb`, the ENRs and scores of connected peers are recorded in it, and it is saved
+//! every `PEER_DB_SAVE_INTERVAL` and when the `PeerManager` is dropped.
+use crate::config::multiaddr_peer_id;
+use crate::metrics;
+use crate::peer_db::{unix_now, PeerDb};
+use crate::rpc::methods::{GoodbyeReason, MetaData};
+use crate::NetworkConfig;
+use futures::prelude::*;
+use libp2p::enr::Enr;
+use libp2p::{Multiaddr, PeerId};
+use slog::{debug, warn};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tokio_timer::{delay_queue, DelayQueue, Interval};
+
+/// The time between heartbeats, in which scores are decayed, bans are lifted and excess peers are
+/// pruned.
+const HEARTBEAT_INTERVAL: u64 = 30;
+/// The time taken for a score to decay to half of its value, in seconds.
+const SCORE_HALFLIFE: f64 = 600.0;
+/// The lowest possible score.
+pub const MIN_SCORE: f64 = -100.0;
+/// The highest possible score.
+pub const MAX_SCORE: f64 = 100.0;
+/// Peers with a score at or below this value are disconnected.
+pub const DISCONNECT_THRESHOLD: f64 = -20.0;
+/// Peers with a score at or below this value are banned.
+pub const BAN_THRESHOLD: f64 = -50.0;
+/// The time for which a peer is banned, in seconds.
+const BAN_DURATION: u64 = 3600;
+/// Disconnected peers whose score is above this value are forgotten at the next heartbeat.
+const FORGET_THRESHOLD: f64 = -1.0;
+/// The maximum number of peers waiting to be dialed. Further peers are dropped.
+const MAX_DIAL_QUEUE_LEN: usize = 100;
+/// The time after which a dial is assumed to have failed, in seconds.
+const DIAL_TIMEOUT: u64 = 30;
+/// The delay before the first redial of a disconnected trusted peer, in seconds.
+const TRUSTED_PEER_INITIAL_BACKOFF: u64 = 5;
+/// The maximum delay between redials of a trusted peer, in seconds.
+const TRUSTED_PEER_MAX_BACKOFF: u64 = 300;
+/// The time between pings of each connected peer, in seconds.
+const PING_INTERVAL: u64 = 60;
+/// The time between saves of the `PeerDb`, in seconds.
+const PEER_DB_SAVE_INTERVAL: u64 = 300;
+
+/// Behaviour of a peer which affects its score.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PeerAction {
+    /// The peer has proven that it is malicious or following an incompatible chain. The peer is
+    /// banned immediately.
+    Fatal,
+    /// The peer sent us an invalid block.
+    InvalidBlock,
+    /// The peer sent us a gossipsub message that could not be decoded or was invalid.
+    InvalidGossip,
+    /// The peer sent us an invalid RPC response, or an RPC request to the peer failed.
+    RpcError,
+    /// The peer did not respond to an RPC request in time.
+    SlowResponse,
+}
+
+impl PeerAction {
+    /// The change to the gossipsub and application components of a peer's score caused by the
+    /// action.
+    fn penalty(self) -> (f64, f64) {
+        match self {
+            PeerAction::Fatal => (0.0, 2.0 * MIN_SCORE),
+            PeerAction::InvalidBlock => (0.0, -20.0),
+            PeerAction::InvalidGossip => (-5.0, 0.0),
+            PeerAction::RpcError => (0.0, -10.0),
+            PeerAction::SlowResponse => (0.0, -2.0),
+        }
+    }
+}
+
+/// The score of a peer. Lower is worse.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PeerScore {
+    /// The score arising from the peer's behaviour on gossipsub.
+    gossipsub: f64,
+    /// The score arising from the peer's behaviour as observed by the beacon node.
+    application: f64,
+}
+
+impl PeerScore {
+    /// The combined score of the peer, between `MIN_SCORE` and `MAX_SCORE`.
+    pub fn score(&self) -> f64 {
+        (self.gossipsub + self.application)
+            .max(MIN_SCORE)
+            .min(MAX_SCORE)
+    }
+
+    fn apply(&mut self, action: PeerAction) {
+        let (gossipsub, application) = action.penalty();
+        self.gossipsub = (self.gossipsub + gossipsub).max(MIN_SCORE).min(MAX_SCORE);
+        self.application = (self.application + application)
+            .max(MIN_SCORE)
+            .min(MAX_SCORE);
+    }
+
+    /// Decays both components of the score towards zero, according to `SCORE_HALFLIFE`.
+    fn decay(&mut self, elapsed: Duration) {
+        let factor = 0.5_f64.powf(elapsed.as_secs_f64() / SCORE_HALFLIFE);
+        self.gossipsub *= factor;
+        self.application *= factor;
+    }
+}
+
+/// The connection status of a peer known to the `PeerManager`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PeerStatus {
+    Connected,
+    /// We have asked the peer to disconnect, but it has not yet done so.
+    Disconnecting,
+    Disconnected,
+    /// The peer is banned until the given instant.
+    Banned(Instant),
+}
+
+impl PeerStatus {
+    fn is_connected(self) -> bool {
+        match self {
+            PeerStatus::Connected | PeerStatus::Disconnecting => true,
+            PeerStatus::Disconnected | PeerStatus::Banned(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PeerEntry {
+    score: PeerScore,
+    status: PeerStatus,
+    /// The latest `MetaData` received from the peer whilst it has been connected.
+    meta_data: Option<MetaData>,
+}
+
+/// A peer given by the user which is kept connected regardless of its score.
+struct TrustedPeer {
+    /// The address the peer is dialed on.
+    address: Multiaddr,
+    /// The delay before the next redial is attempted.
+    backoff: Duration,
+    /// The key of the pending redial of the peer, if any.
+    redial: Option<delay_queue::Key>,
+}
+
+/// Actions that the `PeerManager` requires of the network.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PeerManagerEvent {
+    /// Dial the peer.
+    DialPeer(PeerId),
+    /// Dial the address of a trusted peer.
+    DialAddress(Multiaddr),
+    /// Send a goodbye to the peer, asking it to disconnect.
+    DisconnectPeer(PeerId, GoodbyeReason),
+    /// Disconnect from the peer and refuse any future connections from it.
+    BanPeer(PeerId),
+    /// Allow connections from a previously banned peer.
+    UnbanPeer(PeerId),
+    /// Send a ping to the peer, advertising the sequence number of our `MetaData`.
+    Ping(PeerId),
+    /// Request the `MetaData` of the peer.
+    MetaData(PeerId),
+}
+
+/// Maintains the scores of peers, and decides which peers to disconnect or ban.
+///
+/// The `PeerManager` does not act on the network itself. Instead, it is polled as a `Stream` of
+/// `PeerManagerEvent`s which the network service must carry out.
+pub struct PeerManager {
+    peers: HashMap<PeerId, PeerEntry>,
+    /// The number of connected peers that excess peers are pruned down to.
+    target_peers: usize,
+    /// The maximum number of connected peers. Peers which connect beyond this are disconnected.
+    max_peers: usize,
+    /// The actions awaiting collection by the network service.
+    events: Vec<PeerManagerEvent>,
+    /// Peers waiting to be dialed, and whether they are a priority (i.e., they advertise a needed
+    /// attestation subnet).
+    dial_queue: VecDeque<(PeerId, bool)>,
+    /// Peers which are being dialed, and when the dial began.
+    dialing: HashMap<PeerId, Instant>,
+    /// The peers which are exempt from score-based disconnections and are always redialed.
+    trusted_peers: HashMap<PeerId, TrustedPeer>,
+    /// The trusted peers waiting to be redialed.
+    redials: DelayQueue<PeerId>,
+    heartbeat: Interval,
+    /// Triggers a ping of every connected peer.
+    ping_interval: Interval,
+    /// The time at which scores were last decayed.
+    last_decay: Instant,
+    /// The records of well-behaved peers which are persisted across restarts, if any.
+    peer_db: Option<PeerDb>,
+    /// The time at which the `peer_db` was last saved.
+    last_peer_db_save: Instant,
+    log: slog::Logger,
+}
+
+impl PeerManager {
+    pub fn new(config: &NetworkConfig, log: &slog::Logger) -> Self {
+        let heartbeat_interval = Duration::from_secs(HEARTBEAT_INTERVAL);
+        let ping_interval = Duration::from_secs(PING_INTERVAL);
+
+        let mut peer_manager = Self {
+            peers: HashMap::new(),
+            target_peers: config.target_peers,
+            max_peers: config.max_peers,
+            events: Vec::new(),
+            dial_queue: VecDeque::new(),
+            dialing: HashMap::new(),
+            trusted_peers: HashMap::new(),
+            redials: DelayQueue::new(),
+            heartbeat: Interval::new(Instant::now() + heartbeat_interval, heartbeat_interval),
+            ping_interval: Interval::new(Instant::now() + ping_interval, ping_interval),
+            last_decay: Instant::now(),
+            peer_db: None,
+            last_peer_db_save: Instant::now(),
+            log: log.clone(),
+        };
+
+        // dial the trusted peers immediately, redialing them if they fail to connect
+        for address in &config.trusted_peers {
+            if let Some(peer_id) = multiaddr_peer_id(address) {
+                peer_manager.trusted_peers.insert(
+                    peer_id.clone(),
+                    TrustedPeer {
+                        address: address.clone(),
+                        backoff: Duration::from_secs(TRUSTED_PEER_INITIAL_BACKOFF),
+                        redial: None,
+                    },
+                );
+                peer_manager
+                    .events
+                    .push(PeerManagerEvent::DialAddress(address.clone()));
+                peer_manager.schedule_redial(&peer_id);
+            }
+        }
+
+        peer_manager
+    }
+
+    /// Records the ENRs and scores of connected peers in `peer_db`, and queues the peers already
+    /// recorded in it to be dialed.
+    ///
+    /// The ENRs of the queued peers must be known to discovery for the dials to succeed.
+    pub fn set_peer_db(&mut self, peer_db: PeerDb) {
+        let peers = peer_db.enrs().iter().map(Enr::peer_id).collect();
+        self.dial_peers(peers, false);
+        self.peer_db = Some(peer_db);
+    }
+
+    /// Records the ENR of the connected peer `peer_id` in the `PeerDb`, along with its score.
+    pub fn peer_enr(&mut self, peer_id: &PeerId, enr: Enr) {
+        let score = self.score(peer_id);
+        if let Some(peer_db) = self.peer_db.as_mut() {
+            if enr.peer_id() == *peer_id {
+                peer_db.insert(enr, score, unix_now());
+            }
+        }
+    }
+
+    /// Updates the records of connected peers in the `PeerDb`, saving it if it has not been saved
+    /// for `PEER_DB_SAVE_INTERVAL`.
+    fn update_peer_db(&mut self, now: Instant) {
+        let peer_db = match self.peer_db.as_mut() {
+            Some(peer_db) => peer_db,
+            None => return,
+        };
+
+        let seconds = unix_now();
+        for (peer_id, entry) in self.peers.iter() {
+            if entry.status == PeerStatus::Connected {
+                peer_db.update(peer_id, entry.score.score(), seconds);
+            }
+        }
+
+        if now.duration_since(self.last_peer_db_save) >= Duration::from_secs(PEER_DB_SAVE_INTERVAL)
+        {
+            self.last_peer_db_save = now;
+            if let Err(e) = peer_db.save(seconds) {
+                warn!(self.log, "Could not save peer records"; "error" => e);
+            }
+        }
+    }
+
+    /// Returns `true` if `peer_id` is a trusted peer.
+    pub fn is_trusted(&self, peer_id: &PeerId) -> bool {
+        self.trusted_peers.contains_key(peer_id)
+    }
+
+    /// Returns the score of `peer_id`, or the default score if the peer is unknown.
+    pub fn score(&self, peer_id: &PeerId) -> f64 {
+        self.peers
+            .get(peer_id)
+            .map(|entry| entry.score.score())
+            .unwrap_or(crate::peer_info::DEFAULT_SCORE)
+    }
+
+    /// Returns `true` if `peer_id` is currently banned.
+    pub fn is_banned(&self, peer_id: &PeerId) -> bool {
+        match self.peers.get(peer_id).map(|entry| entry.status) {
+            Some(PeerStatus::Banned(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the latest `MetaData` received from `peer_id`, if any.
+    pub fn meta_data(&self, peer_id: &PeerId) -> Option<&MetaData> {
+        self.peers
+            .get(peer_id)
+            .and_then(|entry| entry.meta_data.as_ref())
+    }
+
+    /// Registers the `MetaData` sequence number advertised by `peer_id` in a ping, requesting the
+    /// `MetaData` of the peer if it is newer than the `MetaData` we hold.
+    pub fn ping_received(&mut self, peer_id: &PeerId, seq_number: u64) {
+        let known = self
+            .peers
+            .get(peer_id)
+            .and_then(|entry| entry.meta_data.as_ref())
+            .map_or(false, |meta_data| meta_data.seq_number >= seq_number);
+
+        if !known {
+            self.events
+                .push(PeerManagerEvent::MetaData(peer_id.clone()));
+        }
+    }
+
+    /// Stores the `MetaData` received from `peer_id`, unless it is older than the `MetaData` we
+    /// already hold.
+    pub fn meta_data_received(&mut self, peer_id: &PeerId, meta_data: MetaData) {
+        let entry = self.peers.entry(peer_id.clone()).or_insert(PeerEntry {
+            score: PeerScore::default(),
+            status: PeerStatus::Disconnected,
+            meta_data: None,
+        });
+
+        let is_newer = entry
+            .meta_data
+            .as_ref()
+            .map_or(true, |known| known.seq_number < meta_data.seq_number);
+        if is_newer {
+            debug!(self.log, "Received peer metadata"; "peer_id" => format!("{}", peer_id), "seq_number" => meta_data.seq_number);
+            entry.meta_data = Some(meta_data);
+        }
+    }
+
+    /// The number of peers that are currently connected.
+    pub fn connected_peers(&self) -> usize {
+        self.peers
+            .values()
+            .filter(|entry| entry.status.is_connected())
+            .count()
+    }
+
+    /// Queues peers found by discovery to be dialed, ignoring peers which are connected, banned or
+    /// already queued.
+    ///
+    /// Priority peers are dialed before others, whilst fewer than `max_peers` are connected. Other
+    /// peers are only dialed whilst fewer than `target_peers` are connected.
+    pub fn dial_peers(&mut self, peers: Vec<PeerId>, priority: bool) {
+        for peer_id in peers {
+            let known = self
+                .peers
+                .get(&peer_id)
+                .map_or(false, |entry| entry.status != PeerStatus::Disconnected);
+            if known
+                || self.dialing.contains_key(&peer_id)
+                || self.dial_queue.iter().any(|(queued, _)| *queued == peer_id)
+            {
+                continue;
+            }
+
+            if priority {
+                self.dial_queue.push_front((peer_id, priority));
+            } else {
+                self.dial_queue.push_back((peer_id, priority));
+            }
+        }
+
+        self.dial_queue.truncate(MAX_DIAL_QUEUE_LEN);
+    }
+
+    /// Returns the next peer to dial, if the queue is not empty and there is room for the peer.
+    fn next_dial(&mut self) -> Option<PeerId> {
+        let (_, priority) = self.dial_queue.front()?;
+        let limit = if *priority {
+            self.max_peers
+        } else {
+            self.target_peers
+        };
+
+        if self.connected_peers() + self.dialing.len() >= limit {
+            return None;
+        }
+
+        let (peer_id, _) = self.dial_queue.pop_front()?;
+        self.dialing.insert(peer_id.clone(), Instant::now());
+        Some(peer_id)
+    }
+
+    /// Registers a newly connected peer.
+    ///
+    /// Banned peers, and peers which connect whilst `max_peers` peers are already connected, are
+    /// disconnected. Trusted peers are always accepted.
+    pub fn connect(&mut self, peer_id: PeerId) {
+        self.dialing.remove(&peer_id);
+
+        if self.is_banned(&peer_id) {
+            self.events.push(PeerManagerEvent::BanPeer(peer_id));
+            return;
+        }
+
+        if let Some(trusted_peer) = self.trusted_peers.get_mut(&peer_id) {
+            trusted_peer.backoff = Duration::from_secs(TRUSTED_PEER_INITIAL_BACKOFF);
+            if let Some(key) = trusted_peer.redial.take() {
+                self.redials.remove(&key);
+            }
+        }
+
+        let trusted = self.is_trusted(&peer_id);
+        let connected_peers = self.connected_peers();
+        let entry = self.peers.entry(peer_id.clone()).or_insert(PeerEntry {
+            score: PeerScore::default(),
+            status: PeerStatus::Disconnected,
+            meta_data: None,
+        });
+
+        if trusted {
+            entry.status = PeerStatus::Connected;
+        } else if connected_peers >= self.max_peers {
+            debug!(self.log, "Too many peers, disconnecting new peer"; "peer_id" => format!("{}", peer_id));
+            entry.status = PeerStatus::Disconnecting;
+            self.events.push(PeerManagerEvent::DisconnectPeer(
+                peer_id,
+                GoodbyeReason::TooManyPeers,
+            ));
+        } else if entry.score.score() <= DISCONNECT_THRESHOLD {
+            entry.status = PeerStatus::Disconnecting;
+            self.events.push(PeerManagerEvent::DisconnectPeer(
+                peer_id,
+                GoodbyeReason::Fault,
+            ));
+        } else {
+            entry.status = PeerStatus::Connected;
+        }
+    }
+
+    /// Registers the disconnection of a peer. Its score is remembered until it has decayed.
+    ///
+    /// Trusted peers are scheduled to be redialed.
+    pub fn disconnect(&mut self, peer_id: &PeerId) {
+        if let Some(entry) = self.peers.get_mut(peer_id) {
+            if entry.status.is_connected() {
+                entry.status = PeerStatus::Disconnected;
+            }
+            // the metadata may change whilst the peer is disconnected
+            entry.meta_data = None;
+        }
+
+        self.schedule_redial(peer_id);
+    }
+
+    /// Schedules a redial of `peer_id` after its backoff, doubling the backoff for the following
+    /// attempt. Does nothing if the peer is not trusted or a redial is already scheduled.
+    fn schedule_redial(&mut self, peer_id: &PeerId) {
+        if let Some(trusted_peer) = self.trusted_peers.get_mut(peer_id) {
+            if trusted_peer.redial.is_some() {
+                return;
+            }
+
+            trusted_peer.redial = Some(self.redials.insert(peer_id.clone(), trusted_peer.backoff));
+            trusted_peer.backoff = std::cmp::min(
+                trusted_peer.backoff * 2,
+                Duration::from_secs(TRUSTED_PEER_MAX_BACKOFF),
+            );
+        }
+    }
+
+    /// Redials a trusted peer whose backoff has elapsed, if it is still not connected.
+    fn redial(&mut self, peer_id: PeerId) {
+        let address = match self.trusted_peers.get_mut(&peer_id) {
+            Some(trusted_peer) => {
+                trusted_peer.redial = None;
+                trusted_peer.address.clone()
+            }
+            None => return,
+        };
+
+        let connected = self
+            .peers
+            .get(&peer_id)
+            .map_or(false, |entry| entry.status.is_connected());
+        if connected {
+            return;
+        }
+
+        debug!(self.log, "Redialing trusted peer"; "peer_id" => format!("{}", peer_id));
+        self.events.push(PeerManagerEvent::DialAddress(address));
+        // the dial may fail without the peer ever connecting, so the next attempt is scheduled now
+        self.schedule_redial(&peer_id);
+    }
+
+    /// Adjusts the score of `peer_id` according to `action`, disconnecting or banning the peer if
+    /// its score falls below the relevant threshold. Trusted peers are never disconnected or
+    /// banned.
+    pub fn report_peer(&mut self, peer_id: &PeerId, action: PeerAction) {
+        let entry = self.peers.entry(peer_id.clone()).or_insert(PeerEntry {
+            score: PeerScore::default(),
+            status: PeerStatus::Disconnected,
+            meta_data: None,
+        });
+
+        entry.score.apply(action);
+        let score = entry.score.score();
+
+        if let Some(peer_db) = self.peer_db.as_mut() {
+            peer_db.update(peer_id, score, unix_now());
+        }
+
+        debug!(
+            self.log,
+            "Peer reported";
+            "peer_id" => format!("{}", peer_id),
+            "action" => format!("{:?}", action),
+            "score" => score,
+        );
+
+        if let PeerStatus::Banned(_) = entry.status {
+            return;
+        }
+
+        if self.trusted_peers.contains_key(peer_id) {
+            if score <= DISCONNECT_THRESHOLD {
+                debug!(self.log, "Not disconnecting trusted peer with low score"; "peer_id" => format!("{}", peer_id), "score" => score);
+            }
+            return;
+        }
+
+        if score <= BAN_THRESHOLD {
+            warn!(self.log, "Banning peer"; "peer_id" => format!("{}", peer_id), "score" => score);
+            metrics::inc_counter(&metrics::PEER_BAN_EVENT_COUNT);
+            entry.status = PeerStatus::Banned(Instant::now() + Duration::from_secs(BAN_DURATION));
+            self.events.push(PeerManagerEvent::BanPeer(peer_id.clone()));
+        } else if score <= DISCONNECT_THRESHOLD && entry.status == PeerStatus::Connected {
+            debug!(self.log, "Disconnecting peer with low score"; "peer_id" => format!("{}", peer_id), "score" => score);
+            entry.status = PeerStatus::Disconnecting;
+            self.events.push(PeerManagerEvent::DisconnectPeer(
+                peer_id.clone(),
+                GoodbyeReason::Fault,
+            ));
+        }
+    }
+
+    /// Decays all scores, lifts expired bans, forgets peers with a negligible score and prunes the
+    /// worst-scoring peers if more than `target_peers` are connected.
+    fn heartbeat(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.last_decay);
+        self.last_decay = now;
+
+        let mut unbanned = vec![];
+        for (peer_id, entry) in self.peers.iter_mut() {
+            entry.score.decay(elapsed);
+
+            if let PeerStatus::Banned(until) = entry.status {
+                if until <= now {
+                    entry.status = PeerStatus::Disconnected;
+                    unbanned.push(peer_id.clone());
+                }
+            }
+        }
+
+        for peer_id in unbanned {
+            debug!(self.log, "Unbanning peer"; "peer_id" => format!("{}", peer_id));
+            self.events.push(PeerManagerEvent::UnbanPeer(peer_id));
+        }
+
+        self.dialing
+            .retain(|_, started| now.duration_since(*started) < Duration::from_secs(DIAL_TIMEOUT));
+
+        self.peers.retain(|_, entry| {
+            entry.status != PeerStatus::Disconnected || entry.score.score() <= FORGET_THRESHOLD
+        });
+
+        self.prune_excess_peers();
+        self.update_peer_db(now);
+    }
+
+    /// Pings every connected peer, so that changes to their `MetaData` are noticed.
+    fn ping_peers(&mut self) {
+        for (peer_id, entry) in self.peers.iter() {
+            if entry.status == PeerStatus::Connected {
+                self.events.push(PeerManagerEvent::Ping(peer_id.clone()));
+            }
+        }
+    }
+
+    /// Disconnects the worst-scoring connected peers until at most `target_peers` remain. Trusted
+    /// peers are neither pruned nor counted.
+    fn prune_excess_peers(&mut self) {
+        let trusted_peers = &self.trusted_peers;
+        let mut connected = self
+            .peers
+            .iter()
+            .filter(|(peer_id, entry)| {
+                entry.status == PeerStatus::Connected && !trusted_peers.contains_key(peer_id)
+            })
+            .map(|(peer_id, entry)| (peer_id.clone(), entry.score.score()))
+            .collect::<Vec<_>>();
+
+        // Peers which are already disconnecting are not counted, since they are leaving anyway.
+        let excess = connected.len().saturating_sub(self.target_peers);
+        if excess == 0 {
+            return;
+        }
+
+        connected.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        for (peer_id, score) in connected.into_iter().take(excess) {
+            debug!(self.log, "Pruning excess peer"; "peer_id" => format!("{}", peer_id), "score" => score);
+            if let Some(entry) = self.peers.get_mut(&peer_id) {
+                entry.status = PeerStatus::Disconnecting;
+            }
+            self.events.push(PeerManagerEvent::DisconnectPeer(
+                peer_id,
+                GoodbyeReason::TooManyPeers,
+            ));
+        }
+    }
+}
+
+impl Drop for PeerManager {
+    fn drop(&mut self) {
+        if let Some(peer_db) = self.peer_db.as_mut() {
+            if let Err(e) = peer_db.save(unix_now()) {
+                warn!(self.log, "Could not save peer records"; "error" => e);
+            }
+        }
+    }
+}
+
+impl Stream for PeerManager {
+    type Item = PeerManagerEvent;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            if !self.events.is_empty() {
+                return Ok(Async::Ready(Some(self.events.remove(0))));
+            }
+
+            if let Some(peer_id) = self.next_dial() {
+                return Ok(Async::Ready(Some(PeerManagerEvent::DialPeer(peer_id))));
+            }
+
+            match self.redials.poll() {
+                Ok(Async::Ready(Some(peer_id))) => {
+                    self.redial(peer_id.into_inner());
+                    continue;
+                }
+                Ok(Async::Ready(None)) | Ok(Async::NotReady) => {}
+                Err(e) => {
+                    warn!(self.log, "Trusted peer redial timer failed"; "error" => format!("{:?}", e));
+                }
+            }
+
+            match self.ping_interval.poll() {
+                Ok(Async::Ready(Some(_))) => {
+                    self.ping_peers();
+                    continue;
+                }
+                Ok(Async::Ready(None)) | Ok(Async::NotReady) => {}
+                Err(e) => {
+                    warn!(self.log, "Peer manager ping timer failed"; "error" => format!("{:?}", e));
+                }
+            }
+
+            match self.heartbeat.poll() {
+                Ok(Async::Ready(Some(_))) => self.heartbeat(Instant::now()),
+                Ok(Async::Ready(None)) | Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Err(e) => {
+                    warn!(self.log, "Peer manager heartbeat failed"; "error" => format!("{:?}", e));
+                    return Ok(Async::NotReady);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::core::identity::Keypair;
+    use libp2p::enr::EnrBuilder;
+    use libp2p::multiaddr::Protocol;
+    use slog::{o, Drain};
+
+    fn peer_manager(target_peers: usize, max_peers: usize) -> PeerManager {
+        let log = slog::Logger::root(slog::Discard.fuse(), o!());
+        let config = NetworkConfig {
+            target_peers,
+            max_peers,
+            ..NetworkConfig::default()
+        };
+        PeerManager::new(&config, &log)
+    }
+
+    fn trusted_peer_manager(trusted_peer: &PeerId) -> PeerManager {
+        let log = slog::Logger::root(slog::Discard.fuse(), o!());
+        let mut address: Multiaddr = "/ip4/127.0.0.1/tcp/9000".parse().unwrap();
+        address.push(Protocol::P2p(trusted_peer.clone().into()));
+        let config = NetworkConfig {
+            target_peers: 1,
+            max_peers: 1,
+            trusted_peers: vec![address],
+            ..NetworkConfig::default()
+        };
+        PeerManager::new(&config, &log)
+    }
+
+    fn take_events(peer_manager: &mut PeerManager) -> Vec<PeerManagerEvent> {
+        std::mem::replace(&mut peer_manager.events, vec![])
+    }
+
+    #[test]
+    fn score_decays() {
+        let mut score = PeerScore::default();
+        score.apply(PeerAction::RpcError);
+        score.apply(PeerAction::InvalidGossip);
+        assert_eq!(score.score(), -15.0);
+
+        score.decay(Duration::from_secs(SCORE_HALFLIFE as u64));
+        assert!((score.score() + 7.5).abs() < 1e-9);
+
+        score.apply(PeerAction::Fatal);
+        assert_eq!(score.score(), MIN_SCORE);
+    }
+
+    #[test]
+    fn disconnects_then_bans() {
+        let mut peer_manager = peer_manager(10, 10);
+        let peer_id = PeerId::random();
+        peer_manager.connect(peer_id.clone());
+
+        peer_manager.report_peer(&peer_id, PeerAction::RpcError);
+        assert!(take_events(&mut peer_manager).is_empty());
+
+        peer_manager.report_peer(&peer_id, PeerAction::RpcError);
+        assert_eq!(
+            take_events(&mut peer_manager),
+            vec![PeerManagerEvent::DisconnectPeer(
+                peer_id.clone(),
+                GoodbyeReason::Fault
+            )]
+        );
+
+        peer_manager.report_peer(&peer_id, PeerAction::InvalidBlock);
+        peer_manager.report_peer(&peer_id, PeerAction::InvalidBlock);
+        assert_eq!(
+            take_events(&mut peer_manager),
+            vec![PeerManagerEvent::BanPeer(peer_id.clone())]
+        );
+        assert!(peer_manager.is_banned(&peer_id));
+
+        // The ban is lifted once it expires.
+        let expiry = Instant::now() + Duration::from_secs(BAN_DURATION + 1);
+        peer_manager.heartbeat(expiry);
+        assert!(!peer_manager.is_banned(&peer_id));
+        assert_eq!(
+            take_events(&mut peer_manager),
+            vec![PeerManagerEvent::UnbanPeer(peer_id)]
+        );
+    }
+
+    #[test]
+    fn refuses_peers_above_max() {
+        let mut peer_manager = peer_manager(1, 2);
+        let peers = (0..3).map(|_| PeerId::random()).collect::<Vec<_>>();
+
+        for peer_id in &peers {
+            peer_manager.connect(peer_id.clone());
+        }
+
+        assert_eq!(
+            take_events(&mut peer_manager),
+            vec![PeerManagerEvent::DisconnectPeer(
+                peers[2].clone(),
+                GoodbyeReason::TooManyPeers
+            )]
+        );
+    }
+
+    #[test]
+    fn dials_priority_peers_up_to_max() {
+        let mut peer_manager = peer_manager(1, 2);
+        let peers = (0..4).map(|_| PeerId::random()).collect::<Vec<_>>();
+
+        peer_manager.dial_peers(vec![peers[0].clone(), peers[1].clone()], false);
+        peer_manager.dial_peers(vec![peers[2].clone(), peers[3].clone()], true);
+        // Queued peers are not queued twice.
+        peer_manager.dial_peers(vec![peers[0].clone()], false);
+        assert_eq!(peer_manager.dial_queue.len(), 4);
+
+        // Priority peers are dialed first, up to `max_peers`.
+        assert_eq!(peer_manager.next_dial(), Some(peers[3].clone()));
+        assert_eq!(peer_manager.next_dial(), Some(peers[2].clone()));
+        assert_eq!(peer_manager.next_dial(), None);
+
+        // Once connected, the remaining peers are not dialed as the target is already met.
+        peer_manager.connect(peers[3].clone());
+        peer_manager.connect(peers[2].clone());
+        assert!(peer_manager.dialing.is_empty());
+        assert_eq!(peer_manager.next_dial(), None);
+    }
+
+    #[test]
+    fn prunes_worst_peers_above_target() {
+        let mut peer_manager = peer_manager(2, 5);
+        let peers = (0..4).map(|_| PeerId::random()).collect::<Vec<_>>();
+
+        for peer_id in &peers {
+            peer_manager.connect(peer_id.clone());
+        }
+        peer_manager.report_peer(&peers[1], PeerAction::SlowResponse);
+        peer_manager.report_peer(&peers[3], PeerAction::RpcError);
+
+        peer_manager.heartbeat(Instant::now());
+
+        let mut pruned = take_events(&mut peer_manager);
+        pruned.sort_by_key(|event| format!("{:?}", event));
+        let mut expected = vec![
+            PeerManagerEvent::DisconnectPeer(peers[1].clone(), GoodbyeReason::TooManyPeers),
+            PeerManagerEvent::DisconnectPeer(peers[3].clone(), GoodbyeReason::TooManyPeers),
+        ];
+        expected.sort_by_key(|event| format!("{:?}", event));
+        assert_eq!(pruned, expected);
+
+        // Peers which are disconnecting are not pruned again.
+        peer_manager.heartbeat(Instant::now());
+        assert!(take_events(&mut peer_manager).is_empty());
+    }
+
+    #[test]
+    fn requests_newer_meta_data() {
+        let mut peer_manager = peer_manager(10, 10);
+        let peer_id = PeerId::random();
+        peer_manager.connect(peer_id.clone());
+
+        // the metadata of a new peer is unknown
+        peer_manager.ping_received(&peer_id, 0);
+        assert_eq!(
+            take_events(&mut peer_manager),
+            vec![PeerManagerEvent::MetaData(peer_id.clone())]
+        );
+
+        let meta_data = |seq_number| MetaData {
+            seq_number,
+            attnets: crate::SubnetBitfield::new(),
+        };
+        peer_manager.meta_data_received(&peer_id, meta_data(2));
+        peer_manager.ping_received(&peer_id, 2);
+        assert!(take_events(&mut peer_manager).is_empty());
+
+        // older metadata is ignored
+        peer_manager.meta_data_received(&peer_id, meta_data(1));
+        assert_eq!(peer_manager.meta_data(&peer_id), Some(&meta_data(2)));
+
+        peer_manager.ping_received(&peer_id, 3);
+        assert_eq!(
+            take_events(&mut peer_manager),
+            vec![PeerManagerEvent::MetaData(peer_id.clone())]
+        );
+
+        peer_manager.ping_peers();
+        assert_eq!(
+            take_events(&mut peer_manager),
+            vec![PeerManagerEvent::Ping(peer_id.clone())]
+        );
+
+        peer_manager.disconnect(&peer_id);
+        assert_eq!(peer_manager.meta_data(&peer_id), None);
+    }
+
+    #[test]
+    fn records_connected_peers_in_peer_db() {
+        let log = slog::Logger::root(slog::Discard.fuse(), o!());
+        let dir =
+            std::env::temp_dir().join(format!("lighthouse_peer_manager_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let enr = |key: &Keypair| EnrBuilder::new("v4").build(key).unwrap();
+        let good_key = Keypair::generate_secp256k1();
+        let bad_key = Keypair::generate_secp256k1();
+        let good_peer = good_key.public().into_peer_id();
+        let bad_peer = bad_key.public().into_peer_id();
+
+        {
+            let mut peer_manager = peer_manager(10, 10);
+            peer_manager.set_peer_db(PeerDb::open(&dir, &log));
+            for (peer_id, key) in &[(&good_peer, &good_key), (&bad_peer, &bad_key)] {
+                peer_manager.connect((*peer_id).clone());
+                peer_manager.peer_enr(peer_id, enr(key));
+            }
+            // poorly-scored peers are forgotten
+            peer_manager.report_peer(&bad_peer, PeerAction::Fatal);
+            // the records are saved when the peer manager is dropped
+        }
+
+        let mut peer_manager = peer_manager(10, 10);
+        peer_manager.set_peer_db(PeerDb::open(&dir, &log));
+        assert_eq!(
+            peer_manager.dial_queue.iter().cloned().collect::<Vec<_>>(),
+            vec![(good_peer, false)]
+        );
+
+        peer_manager.peer_db = None;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn trusted_peers_are_not_disconnected() {
+        let trusted_peer = PeerId::random();
+        let mut peer_manager = trusted_peer_manager(&trusted_peer);
+        take_events(&mut peer_manager);
+
+        let other_peer = PeerId::random();
+        peer_manager.connect(other_peer.clone());
+        // the trusted peer is accepted beyond `max_peers`
+        peer_manager.connect(trusted_peer.clone());
+        assert!(take_events(&mut peer_manager).is_empty());
+
+        peer_manager.report_peer(&trusted_peer, PeerAction::Fatal);
+        assert!(take_events(&mut peer_manager).is_empty());
+        assert!(!peer_manager.is_banned(&trusted_peer));
+        assert_eq!(peer_manager.score(&trusted_peer), MIN_SCORE);
+
+        // trusted peers are not pruned
+        peer_manager.report_peer(&other_peer, PeerAction::SlowResponse);
+        peer_manager.heartbeat(Instant::now());
+        assert!(take_events(&mut peer_manager).is_empty());
+    }
+
+    #[test]
+    fn trusted_peers_are_redialed_with_backoff() {
+        let trusted_peer = PeerId::random();
+        let mut peer_manager = trusted_peer_manager(&trusted_peer);
+        let address = peer_manager.trusted_peers[&trusted_peer].address.clone();
+        assert_eq!(
+            take_events(&mut peer_manager),
+            vec![PeerManagerEvent::DialAddress(address.clone())]
+        );
+
+        let initial_backoff = Duration::from_secs(TRUSTED_PEER_INITIAL_BACKOFF);
+        let backoff =
+            |peer_manager: &PeerManager| peer_manager.trusted_peers[&trusted_peer].backoff;
+        assert_eq!(backoff(&peer_manager), initial_backoff * 2);
+
+        // each failed redial doubles the backoff, up to the maximum
+        for _ in 0..10 {
+            peer_manager.redial(trusted_peer.clone());
+            assert_eq!(
+                take_events(&mut peer_manager),
+                vec![PeerManagerEvent::DialAddress(address.clone())]
+            );
+        }
+        assert_eq!(
+            backoff(&peer_manager),
+            Duration::from_secs(TRUSTED_PEER_MAX_BACKOFF)
+        );
+
+        // connecting resets the backoff and cancels the pending redial
+        peer_manager.connect(trusted_peer.clone());
+        assert_eq!(backoff(&peer_manager), initial_backoff);
+        assert!(peer_manager.trusted_peers[&trusted_peer].redial.is_none());
+
+        peer_manager.disconnect(&trusted_peer);
+        assert!(peer_manager.trusted_peers[&trusted_peer].redial.is_some());
+        assert_eq!(backoff(&peer_manager), initial_backoff * 2);
+    }
+}
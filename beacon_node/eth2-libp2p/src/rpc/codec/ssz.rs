@@ -40,9 +40,11 @@ impl Encoder for SSZInboundCodec {
         let bytes = match item {
             RPCErrorResponse::Success(resp) => {
                 match resp {
-                    RPCResponse::Hello(res) => res.as_ssz_bytes(),
-                    RPCResponse::BeaconBlocks(res) => res, // already raw bytes
-                    RPCResponse::RecentBeaconBlocks(res) => res, // already raw bytes
+                    RPCResponse::Status(res) => res.as_ssz_bytes(),
+                    RPCResponse::Pong(res) => res.as_ssz_bytes(),
+                    RPCResponse::MetaData(res) => res.as_ssz_bytes(),
+                    RPCResponse::BlocksByRange(res) => res, // already raw bytes
+                    RPCResponse::BlocksByRoot(res) => res,  // already raw bytes
                 }
             }
             RPCErrorResponse::InvalidRequest(err) => err.as_ssz_bytes(),
@@ -73,8 +75,8 @@ impl Decoder for SSZInboundCodec {
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
         match self.inner.decode(src).map_err(RPCError::from) {
             Ok(Some(packet)) => match self.protocol.message_name.as_str() {
-                "hello" => match self.protocol.version.as_str() {
-                    "1" => Ok(Some(RPCRequest::Hello(HelloMessage::from_ssz_bytes(
+                "status" => match self.protocol.version.as_str() {
+                    "1" => Ok(Some(RPCRequest::Status(StatusMessage::from_ssz_bytes(
                         &packet,
                     )?))),
                     _ => unreachable!("Cannot negotiate an unknown version"),
@@ -85,18 +87,27 @@ impl Decoder for SSZInboundCodec {
                     )?))),
                     _ => unreachable!("Cannot negotiate an unknown version"),
                 },
-                "beacon_blocks" => match self.protocol.version.as_str() {
-                    "1" => Ok(Some(RPCRequest::BeaconBlocks(
-                        BeaconBlocksRequest::from_ssz_bytes(&packet)?,
+                "beacon_blocks_by_range" => match self.protocol.version.as_str() {
+                    "1" => Ok(Some(RPCRequest::BlocksByRange(
+                        BlocksByRangeRequest::from_ssz_bytes(&packet)?,
                     ))),
                     _ => unreachable!("Cannot negotiate an unknown version"),
                 },
-                "recent_beacon_blocks" => match self.protocol.version.as_str() {
-                    "1" => Ok(Some(RPCRequest::RecentBeaconBlocks(
-                        RecentBeaconBlocksRequest::from_ssz_bytes(&packet)?,
+                "beacon_blocks_by_root" => match self.protocol.version.as_str() {
+                    "1" => Ok(Some(RPCRequest::BlocksByRoot(
+                        BlocksByRootRequest::from_ssz_bytes(&packet)?,
                     ))),
                     _ => unreachable!("Cannot negotiate an unknown version"),
                 },
+                "ping" => match self.protocol.version.as_str() {
+                    "1" => Ok(Some(RPCRequest::Ping(Ping::from_ssz_bytes(&packet)?))),
+                    _ => unreachable!("Cannot negotiate an unknown version"),
+                },
+                "metadata" => match self.protocol.version.as_str() {
+                    // the request has no body
+                    "1" => Ok(Some(RPCRequest::MetaData)),
+                    _ => unreachable!("Cannot negotiate an unknown version"),
+                },
                 _ => unreachable!("Cannot negotiate an unknown protocol"),
             },
             Ok(None) => Ok(None),
@@ -134,10 +145,12 @@ impl Encoder for SSZOutboundCodec {
 
     fn encode(&mut self, item: Self::Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
         let bytes = match item {
-            RPCRequest::Hello(req) => req.as_ssz_bytes(),
+            RPCRequest::Status(req) => req.as_ssz_bytes(),
             RPCRequest::Goodbye(req) => req.as_ssz_bytes(),
-            RPCRequest::BeaconBlocks(req) => req.as_ssz_bytes(),
-            RPCRequest::RecentBeaconBlocks(req) => req.as_ssz_bytes(),
+            RPCRequest::BlocksByRange(req) => req.as_ssz_bytes(),
+            RPCRequest::BlocksByRoot(req) => req.as_ssz_bytes(),
+            RPCRequest::Ping(req) => req.as_ssz_bytes(),
+            RPCRequest::MetaData => Vec::new(),
         };
         // length-prefix
         self.inner
@@ -159,19 +172,23 @@ impl Decoder for SSZOutboundCodec {
         if src.len() == 1 && src[0] == 0_u8 {
             // the object is empty. We return the empty object if this is the case
             match self.protocol.message_name.as_str() {
-                "hello" => match self.protocol.version.as_str() {
+                "status" => match self.protocol.version.as_str() {
                     "1" => Err(RPCError::Custom(
-                        "Hello stream terminated unexpectedly".into(),
-                    )), // cannot have an empty HELLO message. The stream has terminated unexpectedly
+                        "Status stream terminated unexpectedly".into(),
+                    )), // cannot have an empty STATUS message. The stream has terminated unexpectedly
                     _ => unreachable!("Cannot negotiate an unknown version"),
                 },
+                "ping" | "metadata" => Err(RPCError::Custom(format!(
+                    "Empty {} response",
+                    self.protocol.message_name
+                ))),
                 "goodbye" => Err(RPCError::InvalidProtocol("GOODBYE doesn't have a response")),
-                "beacon_blocks" => match self.protocol.version.as_str() {
-                    "1" => Ok(Some(RPCResponse::BeaconBlocks(Vec::new()))),
+                "beacon_blocks_by_range" => match self.protocol.version.as_str() {
+                    "1" => Ok(Some(RPCResponse::BlocksByRange(Vec::new()))),
                     _ => unreachable!("Cannot negotiate an unknown version"),
                 },
-                "recent_beacon_blocks" => match self.protocol.version.as_str() {
-                    "1" => Ok(Some(RPCResponse::RecentBeaconBlocks(Vec::new()))),
+                "beacon_blocks_by_root" => match self.protocol.version.as_str() {
+                    "1" => Ok(Some(RPCResponse::BlocksByRoot(Vec::new()))),
                     _ => unreachable!("Cannot negotiate an unknown version"),
                 },
                 _ => unreachable!("Cannot negotiate an unknown protocol"),
@@ -179,19 +196,29 @@ impl Decoder for SSZOutboundCodec {
         } else {
             match self.inner.decode(src).map_err(RPCError::from) {
                 Ok(Some(packet)) => match self.protocol.message_name.as_str() {
-                    "hello" => match self.protocol.version.as_str() {
-                        "1" => Ok(Some(RPCResponse::Hello(HelloMessage::from_ssz_bytes(
+                    "status" => match self.protocol.version.as_str() {
+                        "1" => Ok(Some(RPCResponse::Status(StatusMessage::from_ssz_bytes(
                             &packet,
                         )?))),
                         _ => unreachable!("Cannot negotiate an unknown version"),
                     },
                     "goodbye" => Err(RPCError::InvalidProtocol("GOODBYE doesn't have a response")),
-                    "beacon_blocks" => match self.protocol.version.as_str() {
-                        "1" => Ok(Some(RPCResponse::BeaconBlocks(packet.to_vec()))),
+                    "beacon_blocks_by_range" => match self.protocol.version.as_str() {
+                        "1" => Ok(Some(RPCResponse::BlocksByRange(packet.to_vec()))),
+                        _ => unreachable!("Cannot negotiate an unknown version"),
+                    },
+                    "beacon_blocks_by_root" => match self.protocol.version.as_str() {
+                        "1" => Ok(Some(RPCResponse::BlocksByRoot(packet.to_vec()))),
+                        _ => unreachable!("Cannot negotiate an unknown version"),
+                    },
+                    "ping" => match self.protocol.version.as_str() {
+                        "1" => Ok(Some(RPCResponse::Pong(Ping::from_ssz_bytes(&packet)?))),
                         _ => unreachable!("Cannot negotiate an unknown version"),
                     },
-                    "recent_beacon_blocks" => match self.protocol.version.as_str() {
-                        "1" => Ok(Some(RPCResponse::RecentBeaconBlocks(packet.to_vec()))),
+                    "metadata" => match self.protocol.version.as_str() {
+                        "1" => Ok(Some(RPCResponse::MetaData(MetaData::from_ssz_bytes(
+                            &packet,
+                        )?))),
                         _ => unreachable!("Cannot negotiate an unknown version"),
                     },
                     _ => unreachable!("Cannot negotiate an unknown protocol"),
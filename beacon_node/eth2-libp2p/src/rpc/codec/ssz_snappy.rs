@@ -0,0 +1,447 @@
+use crate::rpc::methods::*;
+use crate::rpc::{
+    codec::base::OutboundCodec,
+    protocol::{ProtocolId, RPCError},
+};
+use crate::rpc::{ErrorMessage, RPCErrorResponse, RPCRequest, RPCResponse};
+use bytes::BytesMut;
+use snap::read::FrameDecoder;
+use snap::write::FrameEncoder;
+use ssz::{Decode, Encode};
+use std::io::{Cursor, ErrorKind, Read, Write};
+use tokio::codec::{Decoder, Encoder};
+use unsigned_varint::codec::Uvi;
+
+/* Inbound Codec */
+
+pub struct SSZSnappyInboundCodec {
+    /// Encodes and decodes the length prefix of the uncompressed payload.
+    inner: Uvi<usize>,
+    protocol: ProtocolId,
+    /// The uncompressed length of the payload currently being decoded, if its prefix has been
+    /// read.
+    len: Option<usize>,
+    max_packet_size: usize,
+}
+
+impl SSZSnappyInboundCodec {
+    pub fn new(protocol: ProtocolId, max_packet_size: usize) -> Self {
+        // this encoding only applies to ssz_snappy.
+        debug_assert!(protocol.encoding.as_str() == "ssz_snappy");
+
+        SSZSnappyInboundCodec {
+            inner: Uvi::default(),
+            protocol,
+            len: None,
+            max_packet_size,
+        }
+    }
+}
+
+// Encoder for inbound
+impl Encoder for SSZSnappyInboundCodec {
+    type Item = RPCErrorResponse;
+    type Error = RPCError;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let bytes = match item {
+            RPCErrorResponse::Success(resp) => {
+                match resp {
+                    RPCResponse::Status(res) => res.as_ssz_bytes(),
+                    RPCResponse::Pong(res) => res.as_ssz_bytes(),
+                    RPCResponse::MetaData(res) => res.as_ssz_bytes(),
+                    RPCResponse::BlocksByRange(res) => res, // already raw bytes
+                    RPCResponse::BlocksByRoot(res) => res,  // already raw bytes
+                }
+            }
+            RPCErrorResponse::InvalidRequest(err) => err.as_ssz_bytes(),
+            RPCErrorResponse::ServerError(err) => err.as_ssz_bytes(),
+            RPCErrorResponse::Unknown(err) => err.as_ssz_bytes(),
+        };
+
+        encode_snappy(&mut self.inner, bytes, self.max_packet_size, dst)
+    }
+}
+
+// Decoder for inbound
+impl Decoder for SSZSnappyInboundCodec {
+    type Item = RPCRequest;
+    type Error = RPCError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let packet = match decode_snappy(&mut self.inner, &mut self.len, self.max_packet_size, src)?
+        {
+            Some(packet) => packet,
+            None => return Ok(None),
+        };
+
+        match self.protocol.message_name.as_str() {
+            "status" => match self.protocol.version.as_str() {
+                "1" => Ok(Some(RPCRequest::Status(StatusMessage::from_ssz_bytes(
+                    &packet,
+                )?))),
+                _ => unreachable!("Cannot negotiate an unknown version"),
+            },
+            "goodbye" => match self.protocol.version.as_str() {
+                "1" => Ok(Some(RPCRequest::Goodbye(GoodbyeReason::from_ssz_bytes(
+                    &packet,
+                )?))),
+                _ => unreachable!("Cannot negotiate an unknown version"),
+            },
+            "beacon_blocks_by_range" => match self.protocol.version.as_str() {
+                "1" => Ok(Some(RPCRequest::BlocksByRange(
+                    BlocksByRangeRequest::from_ssz_bytes(&packet)?,
+                ))),
+                _ => unreachable!("Cannot negotiate an unknown version"),
+            },
+            "beacon_blocks_by_root" => match self.protocol.version.as_str() {
+                "1" => Ok(Some(RPCRequest::BlocksByRoot(
+                    BlocksByRootRequest::from_ssz_bytes(&packet)?,
+                ))),
+                _ => unreachable!("Cannot negotiate an unknown version"),
+            },
+            "ping" => match self.protocol.version.as_str() {
+                "1" => Ok(Some(RPCRequest::Ping(Ping::from_ssz_bytes(&packet)?))),
+                _ => unreachable!("Cannot negotiate an unknown version"),
+            },
+            "metadata" => match self.protocol.version.as_str() {
+                // the request has no body
+                "1" => Ok(Some(RPCRequest::MetaData)),
+                _ => unreachable!("Cannot negotiate an unknown version"),
+            },
+            _ => unreachable!("Cannot negotiate an unknown protocol"),
+        }
+    }
+}
+
+/* Outbound Codec */
+
+pub struct SSZSnappyOutboundCodec {
+    /// Encodes and decodes the length prefix of the uncompressed payload.
+    inner: Uvi<usize>,
+    protocol: ProtocolId,
+    /// The uncompressed length of the payload currently being decoded, if its prefix has been
+    /// read.
+    len: Option<usize>,
+    max_packet_size: usize,
+}
+
+impl SSZSnappyOutboundCodec {
+    pub fn new(protocol: ProtocolId, max_packet_size: usize) -> Self {
+        // this encoding only applies to ssz_snappy.
+        debug_assert!(protocol.encoding.as_str() == "ssz_snappy");
+
+        SSZSnappyOutboundCodec {
+            inner: Uvi::default(),
+            protocol,
+            len: None,
+            max_packet_size,
+        }
+    }
+}
+
+// Encoder for outbound
+impl Encoder for SSZSnappyOutboundCodec {
+    type Item = RPCRequest;
+    type Error = RPCError;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let bytes = match item {
+            RPCRequest::Status(req) => req.as_ssz_bytes(),
+            RPCRequest::Goodbye(req) => req.as_ssz_bytes(),
+            RPCRequest::BlocksByRange(req) => req.as_ssz_bytes(),
+            RPCRequest::BlocksByRoot(req) => req.as_ssz_bytes(),
+            RPCRequest::Ping(req) => req.as_ssz_bytes(),
+            RPCRequest::MetaData => Vec::new(),
+        };
+
+        encode_snappy(&mut self.inner, bytes, self.max_packet_size, dst)
+    }
+}
+
+// Decoder for outbound streams
+//
+// As with the SSZ codec, blocks are returned as raw (decompressed) bytes and decoded upstream.
+impl Decoder for SSZSnappyOutboundCodec {
+    type Item = RPCResponse;
+    type Error = RPCError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let packet = match decode_snappy(&mut self.inner, &mut self.len, self.max_packet_size, src)?
+        {
+            Some(packet) => packet,
+            None => return Ok(None),
+        };
+
+        match self.protocol.message_name.as_str() {
+            "status" => match self.protocol.version.as_str() {
+                "1" => {
+                    if packet.is_empty() {
+                        // cannot have an empty STATUS message. The stream has terminated unexpectedly
+                        Err(RPCError::Custom(
+                            "Status stream terminated unexpectedly".into(),
+                        ))
+                    } else {
+                        Ok(Some(RPCResponse::Status(StatusMessage::from_ssz_bytes(
+                            &packet,
+                        )?)))
+                    }
+                }
+                _ => unreachable!("Cannot negotiate an unknown version"),
+            },
+            "goodbye" => Err(RPCError::InvalidProtocol("GOODBYE doesn't have a response")),
+            "beacon_blocks_by_range" => match self.protocol.version.as_str() {
+                "1" => Ok(Some(RPCResponse::BlocksByRange(packet))),
+                _ => unreachable!("Cannot negotiate an unknown version"),
+            },
+            "beacon_blocks_by_root" => match self.protocol.version.as_str() {
+                "1" => Ok(Some(RPCResponse::BlocksByRoot(packet))),
+                _ => unreachable!("Cannot negotiate an unknown version"),
+            },
+            "ping" => match self.protocol.version.as_str() {
+                "1" => Ok(Some(RPCResponse::Pong(Ping::from_ssz_bytes(&packet)?))),
+                _ => unreachable!("Cannot negotiate an unknown version"),
+            },
+            "metadata" => match self.protocol.version.as_str() {
+                "1" => Ok(Some(RPCResponse::MetaData(MetaData::from_ssz_bytes(
+                    &packet,
+                )?))),
+                _ => unreachable!("Cannot negotiate an unknown version"),
+            },
+            _ => unreachable!("Cannot negotiate an unknown protocol"),
+        }
+    }
+}
+
+impl OutboundCodec for SSZSnappyOutboundCodec {
+    type ErrorType = ErrorMessage;
+
+    fn decode_error(&mut self, src: &mut BytesMut) -> Result<Option<Self::ErrorType>, RPCError> {
+        match decode_snappy(&mut self.inner, &mut self.len, self.max_packet_size, src)? {
+            Some(packet) => Ok(Some(ErrorMessage::from_ssz_bytes(&packet)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/* Snappy framing */
+
+/// Writes the unsigned varint length of the uncompressed `bytes`, followed by `bytes` compressed
+/// with the snappy frame format.
+fn encode_snappy(
+    inner: &mut Uvi<usize>,
+    bytes: Vec<u8>,
+    max_packet_size: usize,
+    dst: &mut BytesMut,
+) -> Result<(), RPCError> {
+    if bytes.len() > max_packet_size {
+        return Err(RPCError::Custom(format!(
+            "Attempted to encode {} bytes, exceeding the maximum of {}",
+            bytes.len(),
+            max_packet_size
+        )));
+    }
+
+    inner.encode(bytes.len(), dst)?;
+
+    let mut writer = FrameEncoder::new(Vec::new());
+    writer.write_all(&bytes)?;
+    writer.flush()?;
+    dst.extend_from_slice(writer.get_ref());
+    Ok(())
+}
+
+/// Reads a payload written by `encode_snappy` from `src`, returning `None` if more bytes are
+/// required.
+///
+/// `len` holds the uncompressed length between calls once the prefix has been consumed. The
+/// declared length is checked against `max_packet_size` before any buffer is allocated.
+fn decode_snappy(
+    inner: &mut Uvi<usize>,
+    len: &mut Option<usize>,
+    max_packet_size: usize,
+    src: &mut BytesMut,
+) -> Result<Option<Vec<u8>>, RPCError> {
+    let length = match *len {
+        Some(length) => length,
+        None => match inner.decode(src)? {
+            Some(length) => {
+                *len = Some(length);
+                length
+            }
+            None => return Ok(None),
+        },
+    };
+
+    if length > max_packet_size {
+        return Err(RPCError::Custom(format!(
+            "Declared payload of {} bytes exceeds the maximum of {}",
+            length, max_packet_size
+        )));
+    }
+
+    let mut reader = FrameDecoder::new(Cursor::new(&src[..]));
+    let mut decoded = vec![0; length];
+    match reader.read_exact(&mut decoded) {
+        Ok(()) => {
+            let read = reader.get_ref().position() as usize;
+            src.split_to(read);
+            *len = None;
+            Ok(Some(decoded))
+        }
+        // the remaining frames have not arrived yet
+        Err(ref e) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(RPCError::from(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck_macros::quickcheck;
+
+    const MAX_PACKET_SIZE: usize = 1_024;
+
+    fn codecs() -> (SSZSnappyInboundCodec, SSZSnappyOutboundCodec) {
+        let protocol = ProtocolId::new("beacon_blocks_by_range", "1", "ssz_snappy");
+        (
+            SSZSnappyInboundCodec::new(protocol.clone(), MAX_PACKET_SIZE),
+            SSZSnappyOutboundCodec::new(protocol, MAX_PACKET_SIZE),
+        )
+    }
+
+    fn blocks_response(response: Option<RPCResponse>) -> Vec<u8> {
+        match response {
+            Some(RPCResponse::BlocksByRange(bytes)) => bytes,
+            other => panic!("Unexpected response: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn response_round_trip() {
+        let (mut inbound, mut outbound) = codecs();
+        let payload = vec![42; 512];
+
+        let mut buf = BytesMut::new();
+        inbound
+            .encode(
+                RPCErrorResponse::Success(RPCResponse::BlocksByRange(payload.clone())),
+                &mut buf,
+            )
+            .unwrap();
+
+        assert_eq!(blocks_response(outbound.decode(&mut buf).unwrap()), payload);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn meta_data_round_trip() {
+        let protocol = ProtocolId::new("metadata", "1", "ssz_snappy");
+        let mut inbound = SSZSnappyInboundCodec::new(protocol.clone(), MAX_PACKET_SIZE);
+        let mut outbound = SSZSnappyOutboundCodec::new(protocol, MAX_PACKET_SIZE);
+
+        let mut buf = BytesMut::new();
+        outbound.encode(RPCRequest::MetaData, &mut buf).unwrap();
+        match inbound.decode(&mut buf).unwrap() {
+            Some(RPCRequest::MetaData) => {}
+            other => panic!("Unexpected request: {:?}", other),
+        }
+
+        let mut attnets = crate::SubnetBitfield::new();
+        attnets.set(3, true).unwrap();
+        let meta_data = MetaData {
+            seq_number: 7,
+            attnets,
+        };
+
+        let mut buf = BytesMut::new();
+        inbound
+            .encode(
+                RPCErrorResponse::Success(RPCResponse::MetaData(meta_data.clone())),
+                &mut buf,
+            )
+            .unwrap();
+        match outbound.decode(&mut buf).unwrap() {
+            Some(RPCResponse::MetaData(decoded)) => assert_eq!(decoded, meta_data),
+            other => panic!("Unexpected response: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn waits_for_all_frames() {
+        let (mut inbound, mut outbound) = codecs();
+        let payload = (0..255).collect::<Vec<u8>>();
+
+        let mut encoded = BytesMut::new();
+        inbound
+            .encode(
+                RPCErrorResponse::Success(RPCResponse::BlocksByRange(payload.clone())),
+                &mut encoded,
+            )
+            .unwrap();
+
+        let mut buf = encoded.split_to(encoded.len() / 2);
+        assert!(outbound.decode(&mut buf).unwrap().is_none());
+
+        buf.extend_from_slice(&encoded);
+        assert_eq!(blocks_response(outbound.decode(&mut buf).unwrap()), payload);
+    }
+
+    #[test]
+    fn rejects_oversized_payloads() {
+        let (_, mut outbound) = codecs();
+
+        let mut buf = BytesMut::new();
+        Uvi::<usize>::default()
+            .encode(MAX_PACKET_SIZE + 1, &mut buf)
+            .unwrap();
+
+        assert!(outbound.decode(&mut buf).is_err());
+    }
+
+    #[quickcheck]
+    fn decode_arbitrary_bytes_is_bounded(data: Vec<u8>) -> bool {
+        // arbitrary input must never panic, nor decompress beyond the maximum packet size
+        let (_, mut outbound) = codecs();
+        let mut buf = BytesMut::from(data);
+        match outbound.decode(&mut buf) {
+            Ok(Some(response)) => blocks_response(Some(response)).len() <= MAX_PACKET_SIZE,
+            Ok(None) | Err(_) => true,
+        }
+    }
+
+    #[quickcheck]
+    fn decode_declared_length_is_bounded(declared_len: usize, data: Vec<u8>) -> bool {
+        // a valid length prefix followed by arbitrary frames
+        let (_, mut outbound) = codecs();
+        let mut buf = BytesMut::new();
+        Uvi::<usize>::default()
+            .encode(declared_len, &mut buf)
+            .unwrap();
+        buf.extend_from_slice(&data);
+
+        match outbound.decode(&mut buf) {
+            Ok(Some(response)) => {
+                let len = blocks_response(Some(response)).len();
+                len == declared_len && len <= MAX_PACKET_SIZE
+            }
+            Ok(None) => declared_len <= MAX_PACKET_SIZE,
+            Err(_) => true,
+        }
+    }
+
+    #[quickcheck]
+    fn encoding_round_trips(payload: Vec<u8>) -> bool {
+        let (mut inbound, mut outbound) = codecs();
+        let mut buf = BytesMut::new();
+        let result = inbound.encode(
+            RPCErrorResponse::Success(RPCResponse::BlocksByRange(payload.clone())),
+            &mut buf,
+        );
+
+        if payload.len() > MAX_PACKET_SIZE {
+            return result.is_err();
+        }
+        result.is_ok() && blocks_response(outbound.decode(&mut buf).unwrap()) == payload
+    }
+}
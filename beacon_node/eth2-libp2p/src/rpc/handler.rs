@@ -136,7 +136,10 @@ where
     /// Opens an outbound substream with a request.
     #[inline]
     pub fn send_request(&mut self, rpc_event: RPCEvent) {
-        self.keep_alive = KeepAlive::Yes;
+        // a connection which is closing after a goodbye is not revived
+        if self.keep_alive != KeepAlive::No {
+            self.keep_alive = KeepAlive::Yes;
+        }
 
         self.dial_queue.push(rpc_event);
     }
@@ -174,9 +177,11 @@ where
         out: <RPCProtocol as InboundUpgrade<TSubstream>>::Output,
     ) {
         let (req, substream) = out;
-        // drop the stream and return a 0 id for goodbye "requests"
+        // drop the stream and return a 0 id for goodbye "requests". The peer is leaving, so the
+        // connection is closed.
         if let r @ RPCRequest::Goodbye(_) = req {
             self.events_out.push(RPCEvent::Request(0, r));
+            self.keep_alive = KeepAlive::No;
             return;
         }
 
@@ -201,7 +206,9 @@ where
     ) {
         self.dial_negotiated -= 1;
 
-        if self.dial_negotiated == 0
+        if self.keep_alive == KeepAlive::No {
+            // a goodbye has been exchanged, the connection is closing
+        } else if self.dial_negotiated == 0
             && self.dial_queue.is_empty()
             && self.waiting_substreams.is_empty()
         {
@@ -212,6 +219,11 @@ where
 
         // add the stream to substreams if we expect a response, otherwise drop the stream.
         if let RPCEvent::Request(id, req) = rpc_event {
+            // the goodbye has been sent, close the connection to the peer.
+            if let RPCRequest::Goodbye(_) = req {
+                self.keep_alive = KeepAlive::No;
+            }
+
             if req.expect_response() {
                 let awaiting_stream = SubstreamState::RequestPendingResponse {
                     substream: out,
@@ -334,6 +346,11 @@ where
                                     rpc_event,
                                     timeout,
                                 });
+                        } else {
+                            // the peer did not respond in time, inform the requester
+                            return Ok(Async::Ready(ProtocolsHandlerEvent::Custom(
+                                RPCEvent::Error(rpc_event.id(), RPCError::StreamTimeout),
+                            )));
                         }
                     }
                     Err(e) => {
@@ -1,7 +1,8 @@
 //!Available RPC methods types and ids.
 
+use crate::SubnetBitfield;
 use ssz_derive::{Decode, Encode};
-use types::{Epoch, Hash256, Slot};
+use types::{Epoch, ForkDigest, Hash256, Slot};
 
 /* Request/Response data structures for RPC methods */
 
@@ -9,11 +10,15 @@ use types::{Epoch, Hash256, Slot};
 
 pub type RequestId = usize;
 
-/// The HELLO request/response handshake message.
-#[derive(Encode, Decode, Clone, Debug)]
-pub struct HelloMessage {
-    /// The fork version of the chain we are broadcasting.
-    pub fork_version: [u8; 4],
+/// The maximum number of blocks that may be requested in a single `BlocksByRange` or
+/// `BlocksByRoot` request.
+pub const MAX_REQUEST_BLOCKS: u64 = 1024;
+
+/// The STATUS request/response handshake message.
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct StatusMessage {
+    /// The digest of the fork that the node is currently on.
+    pub fork_digest: ForkDigest,
 
     /// Latest finalized root.
     pub finalized_root: Hash256,
@@ -28,12 +33,30 @@ pub struct HelloMessage {
     pub head_slot: Slot,
 }
 
+/// The PING request/response message.
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct Ping {
+    /// The sequence number of the sender's `MetaData`.
+    pub data: u64,
+}
+
+/// The METADATA response, describing the attestation subnets that a node is subscribed to.
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct MetaData {
+    /// Incremented whenever any other field of the `MetaData` changes.
+    pub seq_number: u64,
+
+    /// The long-lived attestation subnets that the node is subscribed to, as advertised in its
+    /// ENR.
+    pub attnets: SubnetBitfield,
+}
+
 /// The reason given for a `Goodbye` message.
 ///
 /// Note: any unknown `u64::into(n)` will resolve to `Goodbye::Unknown` for any unknown `n`,
 /// however `GoodbyeReason::Unknown.into()` will go into `0_u64`. Therefore de-serializing then
 /// re-serializing may not return the same bytes.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum GoodbyeReason {
     /// This node has shutdown.
     ClientShutdown = 1,
@@ -44,6 +67,9 @@ pub enum GoodbyeReason {
     /// Error/fault in the RPC.
     Fault = 3,
 
+    /// The node has too many connected peers.
+    TooManyPeers = 129,
+
     /// Unknown reason.
     Unknown = 0,
 }
@@ -54,6 +80,7 @@ impl From<u64> for GoodbyeReason {
             1 => GoodbyeReason::ClientShutdown,
             2 => GoodbyeReason::IrrelevantNetwork,
             3 => GoodbyeReason::Fault,
+            129 => GoodbyeReason::TooManyPeers,
             _ => GoodbyeReason::Unknown,
         }
     }
@@ -98,9 +125,9 @@ impl ssz::Decode for GoodbyeReason {
     }
 }
 
-/// Request a number of beacon block roots from a peer.
+/// Request a range of beacon blocks from a peer.
 #[derive(Encode, Decode, Clone, Debug, PartialEq)]
-pub struct BeaconBlocksRequest {
+pub struct BlocksByRangeRequest {
     /// The hash tree root of a block on the requested chain.
     pub head_block_root: Hash256,
 
@@ -108,6 +135,8 @@ pub struct BeaconBlocksRequest {
     pub start_slot: u64,
 
     /// The number of blocks from the start slot.
+    ///
+    /// Responders serve at most `MAX_REQUEST_BLOCKS` blocks.
     pub count: u64,
 
     /// The step increment to receive blocks.
@@ -118,10 +147,12 @@ pub struct BeaconBlocksRequest {
     pub step: u64,
 }
 
-/// Request a number of beacon block bodies from a peer.
+/// Request a number of beacon blocks from a peer by their roots.
 #[derive(Encode, Decode, Clone, Debug, PartialEq)]
-pub struct RecentBeaconBlocksRequest {
-    /// The list of beacon block bodies being requested.
+pub struct BlocksByRootRequest {
+    /// The list of beacon block roots being requested.
+    ///
+    /// Responders serve at most `MAX_REQUEST_BLOCKS` blocks.
     pub block_roots: Vec<Hash256>,
 }
 
@@ -130,12 +161,16 @@ pub struct RecentBeaconBlocksRequest {
 
 #[derive(Debug, Clone)]
 pub enum RPCResponse {
-    /// A HELLO message.
-    Hello(HelloMessage),
-    /// A response to a get BEACON_BLOCKS request.
-    BeaconBlocks(Vec<u8>),
-    /// A response to a get RECENT_BEACON_BLOCKS request.
-    RecentBeaconBlocks(Vec<u8>),
+    /// A STATUS message.
+    Status(StatusMessage),
+    /// A PONG response to a PING request.
+    Pong(Ping),
+    /// A METADATA response.
+    MetaData(MetaData),
+    /// A response to a BLOCKS_BY_RANGE request.
+    BlocksByRange(Vec<u8>),
+    /// A response to a BLOCKS_BY_ROOT request.
+    BlocksByRoot(Vec<u8>),
 }
 
 #[derive(Debug)]
@@ -187,19 +222,31 @@ impl ErrorMessage {
     }
 }
 
-impl std::fmt::Display for HelloMessage {
+impl std::fmt::Display for StatusMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Status Message: Fork Digest: {:?}, Finalized Root: {}, Finalized Epoch: {}, Head Root: {}, Head Slot: {}", self.fork_digest, self.finalized_root, self.finalized_epoch, self.head_root, self.head_slot)
+    }
+}
+
+impl std::fmt::Display for MetaData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Hello Message: Fork Version: {:?}, Finalized Root: {}, Finalized Epoch: {}, Head Root: {}, Head Slot: {}", self.fork_version, self.finalized_root, self.finalized_epoch, self.head_root, self.head_slot)
+        write!(
+            f,
+            "MetaData: Seq Number: {}, Attnets: {:?}",
+            self.seq_number, self.attnets
+        )
     }
 }
 
 impl std::fmt::Display for RPCResponse {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            RPCResponse::Hello(hello) => write!(f, "{}", hello),
-            RPCResponse::BeaconBlocks(data) => write!(f, "<BeaconBlocks>, len: {}", data.len()),
-            RPCResponse::RecentBeaconBlocks(data) => {
-                write!(f, "<RecentBeaconBlocks>, len: {}", data.len())
+            RPCResponse::Status(status) => write!(f, "{}", status),
+            RPCResponse::Pong(ping) => write!(f, "Pong: {}", ping.data),
+            RPCResponse::MetaData(meta_data) => write!(f, "{}", meta_data),
+            RPCResponse::BlocksByRange(data) => write!(f, "<BlocksByRange>, len: {}", data.len()),
+            RPCResponse::BlocksByRoot(data) => {
+                write!(f, "<BlocksByRoot>, len: {}", data.len())
             }
         }
     }
@@ -222,12 +269,13 @@ impl std::fmt::Display for GoodbyeReason {
             GoodbyeReason::ClientShutdown => write!(f, "Client Shutdown"),
             GoodbyeReason::IrrelevantNetwork => write!(f, "Irrelevant Network"),
             GoodbyeReason::Fault => write!(f, "Fault"),
+            GoodbyeReason::TooManyPeers => write!(f, "Too Many Peers"),
             GoodbyeReason::Unknown => write!(f, "Unknown Reason"),
         }
     }
 }
 
-impl std::fmt::Display for BeaconBlocksRequest {
+impl std::fmt::Display for BlocksByRangeRequest {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
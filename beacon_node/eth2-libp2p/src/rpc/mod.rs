@@ -11,16 +11,21 @@ use libp2p::swarm::{
     protocols_handler::ProtocolsHandler, NetworkBehaviour, NetworkBehaviourAction, PollParameters,
 };
 use libp2p::{Multiaddr, PeerId};
-pub use methods::{ErrorMessage, HelloMessage, RPCErrorResponse, RPCResponse, RequestId};
-pub use protocol::{RPCError, RPCProtocol, RPCRequest};
-use slog::o;
+pub use methods::{
+    ErrorMessage, MetaData, Ping, RPCErrorResponse, RPCResponse, RequestId, StatusMessage,
+};
+pub use protocol::{RPCError, RPCProtocol, RPCRequest, MAX_RPC_SIZE};
+use rate_limiter::RPCRateLimiter;
+use slog::{debug, o};
 use std::marker::PhantomData;
+use std::time::Instant;
 use tokio::io::{AsyncRead, AsyncWrite};
 
 pub(crate) mod codec;
 mod handler;
 pub mod methods;
 mod protocol;
+mod rate_limiter;
 // mod request_response;
 
 /// The return type used in the behaviour and the resultant event from the protocols handler.
@@ -62,10 +67,12 @@ impl std::fmt::Display for RPCEvent {
 pub struct RPC<TSubstream> {
     /// Queue of events to processed.
     events: Vec<NetworkBehaviourAction<RPCEvent, RPCMessage>>,
+    /// Limits the rate of inbound requests per peer.
+    limiter: RPCRateLimiter,
     /// Pins the generic substream.
     marker: PhantomData<(TSubstream)>,
     /// Slog logger for RPC behaviour.
-    _log: slog::Logger,
+    log: slog::Logger,
 }
 
 impl<TSubstream> RPC<TSubstream> {
@@ -73,8 +80,9 @@ impl<TSubstream> RPC<TSubstream> {
         let log = log.new(o!("service" => "libp2p_rpc"));
         RPC {
             events: Vec::new(),
+            limiter: RPCRateLimiter::default(),
             marker: PhantomData,
-            _log: log,
+            log,
         }
     }
 
@@ -106,7 +114,7 @@ where
     }
 
     fn inject_connected(&mut self, peer_id: PeerId, connected_point: ConnectedPoint) {
-        // if initialised the connection, report this upwards to send the HELLO request
+        // if initialised the connection, report this upwards to send the STATUS request
         if let ConnectedPoint::Dialer { .. } = connected_point {
             self.events.push(NetworkBehaviourAction::GenerateEvent(
                 RPCMessage::PeerDialed(peer_id),
@@ -115,6 +123,8 @@ where
     }
 
     fn inject_disconnected(&mut self, peer_id: &PeerId, _: ConnectedPoint) {
+        self.limiter.remove_peer(peer_id);
+
         // inform the rpc handler that the peer has disconnected
         self.events.push(NetworkBehaviourAction::GenerateEvent(
             RPCMessage::PeerDisconnected(peer_id.clone()),
@@ -126,6 +136,33 @@ where
         source: PeerId,
         event: <Self::ProtocolsHandler as ProtocolsHandler>::OutEvent,
     ) {
+        if let RPCEvent::Request(id, ref request) = event {
+            if let Err(e) = self.limiter.allows(&source, request, Instant::now()) {
+                debug!(self.log, "Rate limited RPC request"; "peer" => format!("{:?}", source), "request" => format!("{}", request), "reason" => format!("{:?}", e));
+
+                // inform the peer, unless the request has no response stream
+                if request.expect_response() {
+                    self.events.push(NetworkBehaviourAction::SendEvent {
+                        peer_id: source.clone(),
+                        event: RPCEvent::Response(
+                            id,
+                            RPCErrorResponse::InvalidRequest(ErrorMessage {
+                                error_message: b"Rate limited".to_vec(),
+                            }),
+                        ),
+                    });
+                }
+
+                // let the user penalise the peer
+                self.events
+                    .push(NetworkBehaviourAction::GenerateEvent(RPCMessage::RPC(
+                        source,
+                        RPCEvent::Error(id, RPCError::RateLimited),
+                    )));
+                return;
+            }
+        }
+
         // send the event to the user
         self.events
             .push(NetworkBehaviourAction::GenerateEvent(RPCMessage::RPC(
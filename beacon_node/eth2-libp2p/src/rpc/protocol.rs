@@ -2,6 +2,7 @@ use super::methods::*;
 use crate::rpc::codec::{
     base::{BaseInboundCodec, BaseOutboundCodec},
     ssz::{SSZInboundCodec, SSZOutboundCodec},
+    ssz_snappy::{SSZSnappyInboundCodec, SSZSnappyOutboundCodec},
     InboundCodec, OutboundCodec,
 };
 use futures::{
@@ -19,7 +20,7 @@ use tokio::util::FutureExt;
 use tokio_io_timeout::TimeoutStream;
 
 /// The maximum bytes that can be sent across the RPC.
-const MAX_RPC_SIZE: usize = 4_194_304; // 4M
+pub const MAX_RPC_SIZE: usize = 4_194_304; // 4M
 /// The protocol prefix the RPC protocol id.
 const PROTOCOL_PREFIX: &str = "/eth2/beacon_chain/req";
 /// Time allowed for the first byte of a request to arrive before we time out (Time To First Byte).
@@ -37,10 +38,18 @@ impl UpgradeInfo for RPCProtocol {
 
     fn protocol_info(&self) -> Self::InfoIter {
         vec![
-            ProtocolId::new("hello", "1", "ssz"),
+            ProtocolId::new("status", "1", "ssz_snappy"),
+            ProtocolId::new("status", "1", "ssz"),
+            ProtocolId::new("goodbye", "1", "ssz_snappy"),
             ProtocolId::new("goodbye", "1", "ssz"),
-            ProtocolId::new("beacon_blocks", "1", "ssz"),
-            ProtocolId::new("recent_beacon_blocks", "1", "ssz"),
+            ProtocolId::new("beacon_blocks_by_range", "1", "ssz_snappy"),
+            ProtocolId::new("beacon_blocks_by_range", "1", "ssz"),
+            ProtocolId::new("beacon_blocks_by_root", "1", "ssz_snappy"),
+            ProtocolId::new("beacon_blocks_by_root", "1", "ssz"),
+            ProtocolId::new("ping", "1", "ssz_snappy"),
+            ProtocolId::new("ping", "1", "ssz"),
+            ProtocolId::new("metadata", "1", "ssz_snappy"),
+            ProtocolId::new("metadata", "1", "ssz"),
         ]
     }
 }
@@ -117,26 +126,29 @@ where
         socket: upgrade::Negotiated<TSocket>,
         protocol: ProtocolId,
     ) -> Self::Future {
-        match protocol.encoding.as_str() {
-            "ssz" | _ => {
-                let ssz_codec = BaseInboundCodec::new(SSZInboundCodec::new(protocol, MAX_RPC_SIZE));
-                let codec = InboundCodec::SSZ(ssz_codec);
-                let mut timed_socket = TimeoutStream::new(socket);
-                timed_socket.set_read_timeout(Some(Duration::from_secs(TTFB_TIMEOUT)));
-                Framed::new(timed_socket, codec)
-                    .into_future()
-                    .timeout(Duration::from_secs(REQUEST_TIMEOUT))
-                    .map_err(RPCError::from as FnMapErr<TSocket>)
-                    .and_then({
-                        |(req, stream)| match req {
-                            Some(req) => futures::future::ok((req, stream)),
-                            None => futures::future::err(RPCError::Custom(
-                                "Stream terminated early".into(),
-                            )),
-                        }
-                    } as FnAndThen<TSocket>)
-            }
-        }
+        let codec = match protocol.encoding.as_str() {
+            "ssz_snappy" => InboundCodec::SSZSnappy(BaseInboundCodec::new(
+                SSZSnappyInboundCodec::new(protocol, MAX_RPC_SIZE),
+            )),
+            "ssz" | _ => InboundCodec::SSZ(BaseInboundCodec::new(SSZInboundCodec::new(
+                protocol,
+                MAX_RPC_SIZE,
+            ))),
+        };
+        let mut timed_socket = TimeoutStream::new(socket);
+        timed_socket.set_read_timeout(Some(Duration::from_secs(TTFB_TIMEOUT)));
+        Framed::new(timed_socket, codec)
+            .into_future()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT))
+            .map_err(RPCError::from as FnMapErr<TSocket>)
+            .and_then({
+                |(req, stream)| match req {
+                    Some(req) => futures::future::ok((req, stream)),
+                    None => {
+                        futures::future::err(RPCError::Custom("Stream terminated early".into()))
+                    }
+                }
+            } as FnAndThen<TSocket>)
     }
 }
 
@@ -147,10 +159,13 @@ where
 
 #[derive(Debug, Clone)]
 pub enum RPCRequest {
-    Hello(HelloMessage),
+    Status(StatusMessage),
     Goodbye(GoodbyeReason),
-    BeaconBlocks(BeaconBlocksRequest),
-    RecentBeaconBlocks(RecentBeaconBlocksRequest),
+    BlocksByRange(BlocksByRangeRequest),
+    BlocksByRoot(BlocksByRootRequest),
+    Ping(Ping),
+    /// A METADATA request has an empty body.
+    MetaData,
 }
 
 impl UpgradeInfo for RPCRequest {
@@ -168,12 +183,30 @@ impl RPCRequest {
     pub fn supported_protocols(&self) -> Vec<ProtocolId> {
         match self {
             // add more protocols when versions/encodings are supported
-            RPCRequest::Hello(_) => vec![ProtocolId::new("hello", "1", "ssz")],
-            RPCRequest::Goodbye(_) => vec![ProtocolId::new("goodbye", "1", "ssz")],
-            RPCRequest::BeaconBlocks(_) => vec![ProtocolId::new("beacon_blocks", "1", "ssz")],
-            RPCRequest::RecentBeaconBlocks(_) => {
-                vec![ProtocolId::new("recent_beacon_blocks", "1", "ssz")]
-            }
+            RPCRequest::Status(_) => vec![
+                ProtocolId::new("status", "1", "ssz_snappy"),
+                ProtocolId::new("status", "1", "ssz"),
+            ],
+            RPCRequest::Goodbye(_) => vec![
+                ProtocolId::new("goodbye", "1", "ssz_snappy"),
+                ProtocolId::new("goodbye", "1", "ssz"),
+            ],
+            RPCRequest::BlocksByRange(_) => vec![
+                ProtocolId::new("beacon_blocks_by_range", "1", "ssz_snappy"),
+                ProtocolId::new("beacon_blocks_by_range", "1", "ssz"),
+            ],
+            RPCRequest::BlocksByRoot(_) => vec![
+                ProtocolId::new("beacon_blocks_by_root", "1", "ssz_snappy"),
+                ProtocolId::new("beacon_blocks_by_root", "1", "ssz"),
+            ],
+            RPCRequest::Ping(_) => vec![
+                ProtocolId::new("ping", "1", "ssz_snappy"),
+                ProtocolId::new("ping", "1", "ssz"),
+            ],
+            RPCRequest::MetaData => vec![
+                ProtocolId::new("metadata", "1", "ssz_snappy"),
+                ProtocolId::new("metadata", "1", "ssz"),
+            ],
         }
     }
 
@@ -205,14 +238,16 @@ where
         socket: upgrade::Negotiated<TSocket>,
         protocol: Self::Info,
     ) -> Self::Future {
-        match protocol.encoding.as_str() {
-            "ssz" | _ => {
-                let ssz_codec =
-                    BaseOutboundCodec::new(SSZOutboundCodec::new(protocol, MAX_RPC_SIZE));
-                let codec = OutboundCodec::SSZ(ssz_codec);
-                Framed::new(socket, codec).send(self)
-            }
-        }
+        let codec = match protocol.encoding.as_str() {
+            "ssz_snappy" => OutboundCodec::SSZSnappy(BaseOutboundCodec::new(
+                SSZSnappyOutboundCodec::new(protocol, MAX_RPC_SIZE),
+            )),
+            "ssz" | _ => OutboundCodec::SSZ(BaseOutboundCodec::new(SSZOutboundCodec::new(
+                protocol,
+                MAX_RPC_SIZE,
+            ))),
+        };
+        Framed::new(socket, codec).send(self)
     }
 }
 
@@ -229,6 +264,8 @@ pub enum RPCError {
     IoError(io::Error),
     /// Waiting for a request/response timed out, or timer error'd.
     StreamTimeout,
+    /// The peer exceeded its request quota.
+    RateLimited,
     /// Custom message.
     Custom(String),
 }
@@ -271,6 +308,7 @@ impl std::fmt::Display for RPCError {
             RPCError::InvalidProtocol(ref err) => write!(f, "Invalid Protocol: {}", err),
             RPCError::IoError(ref err) => write!(f, "IO Error: {}", err),
             RPCError::StreamTimeout => write!(f, "Stream Timeout"),
+            RPCError::RateLimited => write!(f, "Rate limited"),
             RPCError::Custom(ref err) => write!(f, "{}", err),
         }
     }
@@ -284,6 +322,7 @@ impl std::error::Error for RPCError {
             RPCError::InvalidProtocol(_) => None,
             RPCError::IoError(ref err) => Some(err),
             RPCError::StreamTimeout => None,
+            RPCError::RateLimited => None,
             RPCError::Custom(_) => None,
         }
     }
@@ -292,10 +331,12 @@ impl std::error::Error for RPCError {
 impl std::fmt::Display for RPCRequest {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            RPCRequest::Hello(hello) => write!(f, "Hello Message: {}", hello),
+            RPCRequest::Status(status) => write!(f, "{}", status),
             RPCRequest::Goodbye(reason) => write!(f, "Goodbye: {}", reason),
-            RPCRequest::BeaconBlocks(req) => write!(f, "Beacon Blocks: {}", req),
-            RPCRequest::RecentBeaconBlocks(req) => write!(f, "Recent Beacon Blocks: {:?}", req),
+            RPCRequest::BlocksByRange(req) => write!(f, "Beacon Blocks: {}", req),
+            RPCRequest::BlocksByRoot(req) => write!(f, "Recent Beacon Blocks: {:?}", req),
+            RPCRequest::Ping(ping) => write!(f, "Ping: {}", ping.data),
+            RPCRequest::MetaData => write!(f, "MetaData request"),
         }
     }
 }
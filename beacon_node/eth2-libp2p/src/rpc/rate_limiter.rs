@@ -0,0 +1,246 @@
+//! Per-peer rate limiting of inbound RPC requests.
+//!
+//! Each protocol has a `Quota` of tokens that replenishes over a fixed period. A request costs one
+//! token, except for block requests which cost one token per requested block. Limits are
+//! enforced with the Generic Cell Rate Algorithm (GCRA), which only needs to store a single
+//! "theoretical arrival time" per peer.
+
+use super::methods::MAX_REQUEST_BLOCKS;
+use super::protocol::RPCRequest;
+use fnv::FnvHashMap;
+use libp2p::PeerId;
+use std::time::{Duration, Instant};
+
+/// The number of `Status` requests a peer may send per `STATUS_PERIOD`.
+const STATUS_QUOTA: u64 = 5;
+const STATUS_PERIOD: Duration = Duration::from_secs(15);
+/// The number of `Goodbye` requests a peer may send per `GOODBYE_PERIOD`.
+const GOODBYE_QUOTA: u64 = 1;
+const GOODBYE_PERIOD: Duration = Duration::from_secs(10);
+/// The number of blocks a peer may request by range per `BLOCKS_BY_RANGE_PERIOD`.
+const BLOCKS_BY_RANGE_QUOTA: u64 = MAX_REQUEST_BLOCKS;
+const BLOCKS_BY_RANGE_PERIOD: Duration = Duration::from_secs(10);
+/// The number of blocks a peer may request by root per `BLOCKS_BY_ROOT_PERIOD`.
+const BLOCKS_BY_ROOT_QUOTA: u64 = 128;
+const BLOCKS_BY_ROOT_PERIOD: Duration = Duration::from_secs(10);
+/// The number of `Ping` requests a peer may send per `PING_PERIOD`.
+const PING_QUOTA: u64 = 2;
+const PING_PERIOD: Duration = Duration::from_secs(10);
+/// The number of `MetaData` requests a peer may send per `META_DATA_PERIOD`.
+const META_DATA_QUOTA: u64 = 2;
+const META_DATA_PERIOD: Duration = Duration::from_secs(5);
+
+/// Reasons a request may be refused by the rate limiter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateLimitedErr {
+    /// The request costs more tokens than the quota allows, so it can never be served.
+    TooLarge,
+    /// The quota is exhausted. The request can be served after the given duration.
+    TooSoon(Duration),
+}
+
+/// A number of tokens that fully replenishes over a period.
+#[derive(Debug, Clone, Copy)]
+pub struct Quota {
+    /// The time taken to replenish all tokens.
+    replenish_all_every: Duration,
+    /// The maximum number of tokens that can be spent in a single burst.
+    max_tokens: u64,
+}
+
+/// A GCRA limiter for a single protocol.
+struct Limiter {
+    /// The time taken to replenish all tokens (`tau`).
+    tau: Duration,
+    /// The time taken to replenish a single token (`t`).
+    t: Duration,
+    /// The maximum number of tokens a single request may cost.
+    max_tokens: u64,
+    /// The theoretical arrival time of the next request for each peer.
+    tat_per_peer: FnvHashMap<PeerId, Instant>,
+}
+
+impl Limiter {
+    fn from_quota(quota: Quota) -> Self {
+        Limiter {
+            tau: quota.replenish_all_every,
+            t: quota.replenish_all_every / quota.max_tokens as u32,
+            max_tokens: quota.max_tokens,
+            tat_per_peer: FnvHashMap::default(),
+        }
+    }
+
+    fn allows(
+        &mut self,
+        peer_id: &PeerId,
+        tokens: u64,
+        now: Instant,
+    ) -> Result<(), RateLimitedErr> {
+        // empty requests still cost a token to bound the number of requests.
+        let tokens = std::cmp::max(tokens, 1);
+        if tokens > self.max_tokens {
+            return Err(RateLimitedErr::TooLarge);
+        }
+
+        let tat = self.tat_per_peer.entry(peer_id.clone()).or_insert(now);
+        let new_tat = std::cmp::max(*tat, now) + self.t * tokens as u32;
+
+        // the request is allowed if it fits within the burst permitted by the quota.
+        let limit = now + self.tau;
+        if new_tat > limit {
+            Err(RateLimitedErr::TooSoon(new_tat - limit))
+        } else {
+            *tat = new_tat;
+            Ok(())
+        }
+    }
+
+    fn remove(&mut self, peer_id: &PeerId) {
+        self.tat_per_peer.remove(peer_id);
+    }
+}
+
+/// Rate limits the inbound requests of each peer, per protocol.
+pub struct RPCRateLimiter {
+    status_rl: Limiter,
+    goodbye_rl: Limiter,
+    blocks_by_range_rl: Limiter,
+    blocks_by_root_rl: Limiter,
+    ping_rl: Limiter,
+    meta_data_rl: Limiter,
+}
+
+impl Default for RPCRateLimiter {
+    fn default() -> Self {
+        RPCRateLimiter {
+            status_rl: Limiter::from_quota(Quota {
+                replenish_all_every: STATUS_PERIOD,
+                max_tokens: STATUS_QUOTA,
+            }),
+            goodbye_rl: Limiter::from_quota(Quota {
+                replenish_all_every: GOODBYE_PERIOD,
+                max_tokens: GOODBYE_QUOTA,
+            }),
+            blocks_by_range_rl: Limiter::from_quota(Quota {
+                replenish_all_every: BLOCKS_BY_RANGE_PERIOD,
+                max_tokens: BLOCKS_BY_RANGE_QUOTA,
+            }),
+            blocks_by_root_rl: Limiter::from_quota(Quota {
+                replenish_all_every: BLOCKS_BY_ROOT_PERIOD,
+                max_tokens: BLOCKS_BY_ROOT_QUOTA,
+            }),
+            ping_rl: Limiter::from_quota(Quota {
+                replenish_all_every: PING_PERIOD,
+                max_tokens: PING_QUOTA,
+            }),
+            meta_data_rl: Limiter::from_quota(Quota {
+                replenish_all_every: META_DATA_PERIOD,
+                max_tokens: META_DATA_QUOTA,
+            }),
+        }
+    }
+}
+
+impl RPCRateLimiter {
+    /// Spends the tokens required by `request` from the quota of `peer_id`, returning an error
+    /// if the request should be refused.
+    pub fn allows(
+        &mut self,
+        peer_id: &PeerId,
+        request: &RPCRequest,
+        now: Instant,
+    ) -> Result<(), RateLimitedErr> {
+        let (limiter, tokens) = match request {
+            RPCRequest::Status(_) => (&mut self.status_rl, 1),
+            RPCRequest::Goodbye(_) => (&mut self.goodbye_rl, 1),
+            RPCRequest::BlocksByRange(req) => (&mut self.blocks_by_range_rl, req.count),
+            RPCRequest::BlocksByRoot(req) => {
+                (&mut self.blocks_by_root_rl, req.block_roots.len() as u64)
+            }
+            RPCRequest::Ping(_) => (&mut self.ping_rl, 1),
+            RPCRequest::MetaData => (&mut self.meta_data_rl, 1),
+        };
+
+        limiter.allows(peer_id, tokens, now)
+    }
+
+    /// Forgets the quotas of a disconnected peer.
+    pub fn remove_peer(&mut self, peer_id: &PeerId) {
+        self.status_rl.remove(peer_id);
+        self.goodbye_rl.remove(peer_id);
+        self.blocks_by_range_rl.remove(peer_id);
+        self.blocks_by_root_rl.remove(peer_id);
+        self.ping_rl.remove(peer_id);
+        self.meta_data_rl.remove(peer_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::methods::{BlocksByRangeRequest, BlocksByRootRequest};
+    use types::Hash256;
+
+    fn blocks_by_range(count: u64) -> RPCRequest {
+        RPCRequest::BlocksByRange(BlocksByRangeRequest {
+            head_block_root: Hash256::zero(),
+            start_slot: 0,
+            count,
+            step: 1,
+        })
+    }
+
+    #[test]
+    fn quota_replenishes() {
+        let mut limiter = RPCRateLimiter::default();
+        let peer_id = PeerId::random();
+        let now = Instant::now();
+
+        assert_eq!(
+            limiter.allows(&peer_id, &blocks_by_range(MAX_REQUEST_BLOCKS), now),
+            Ok(())
+        );
+        assert!(limiter.allows(&peer_id, &blocks_by_range(1), now).is_err());
+
+        // half the period replenishes half the quota.
+        let later = now + BLOCKS_BY_RANGE_PERIOD / 2;
+        assert_eq!(
+            limiter.allows(&peer_id, &blocks_by_range(MAX_REQUEST_BLOCKS / 2), later),
+            Ok(())
+        );
+        assert!(limiter
+            .allows(&peer_id, &blocks_by_range(1), later)
+            .is_err());
+
+        // other peers have their own quota.
+        assert_eq!(
+            limiter.allows(&PeerId::random(), &blocks_by_range(1), later),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn empty_requests_cost_a_token() {
+        let mut limiter = RPCRateLimiter::default();
+        let peer_id = PeerId::random();
+        let now = Instant::now();
+
+        for _ in 0..BLOCKS_BY_RANGE_QUOTA {
+            assert_eq!(limiter.allows(&peer_id, &blocks_by_range(0), now), Ok(()));
+        }
+        assert!(limiter.allows(&peer_id, &blocks_by_range(0), now).is_err());
+    }
+
+    #[test]
+    fn refuses_oversized_requests() {
+        let mut limiter = RPCRateLimiter::default();
+        let request = RPCRequest::BlocksByRoot(BlocksByRootRequest {
+            block_roots: vec![Hash256::zero(); BLOCKS_BY_ROOT_QUOTA as usize + 1],
+        });
+
+        assert_eq!(
+            limiter.allows(&PeerId::random(), &request, Instant::now()),
+            Err(RateLimitedErr::TooLarge)
+        );
+    }
+}
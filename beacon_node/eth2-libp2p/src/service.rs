@@ -1,10 +1,12 @@
 use crate::behaviour::{Behaviour, BehaviourEvent, PubsubMessage};
-use crate::config::*;
 use crate::error;
 use crate::multiaddr::Protocol;
+use crate::peer_manager::PeerManagerEvent;
+use crate::rpc::methods::GoodbyeReason;
 use crate::rpc::RPCEvent;
+use crate::rpc::RPCRequest;
 use crate::NetworkConfig;
-use crate::{Topic, TopicHash};
+use crate::{GossipKind, Topic, TopicHash};
 use futures::prelude::*;
 use futures::Stream;
 use libp2p::core::{
@@ -17,6 +19,7 @@ use std::fs::File;
 use std::io::prelude::*;
 use std::io::{Error, ErrorKind};
 use std::time::Duration;
+use types::EnrForkId;
 
 type Libp2pStream = Boxed<(PeerId, StreamMuxerBox), Error>;
 type Libp2pBehaviour = Behaviour<Substream<StreamMuxerBox>>;
@@ -37,7 +40,15 @@ pub struct Service {
 }
 
 impl Service {
-    pub fn new(config: NetworkConfig, log: slog::Logger) -> error::Result<Self> {
+    /// Starts the libp2p service, advertising `enr_fork_id` in the local ENR and subscribing to
+    /// the default topics of its fork.
+    pub fn new(
+        config: NetworkConfig,
+        enr_fork_id: EnrForkId,
+        log: slog::Logger,
+    ) -> error::Result<Self> {
+        let fork_digest = enr_fork_id.fork_digest;
+
         trace!(log, "Libp2p Service starting");
 
         let local_keypair = if let Some(hex_bytes) = &config.secret_key_hex {
@@ -54,7 +65,7 @@ impl Service {
             // Set up the transport - tcp/ws with secio and mplex/yamux
             let transport = build_transport(local_keypair.clone());
             // Lighthouse network behaviour
-            let behaviour = Behaviour::new(&local_keypair, &config, &log)?;
+            let behaviour = Behaviour::new(&local_keypair, &config, enr_fork_id, &log)?;
             Swarm::new(transport, behaviour, local_peer_id.clone())
         };
 
@@ -110,45 +121,33 @@ impl Service {
             }
         }
 
-        // subscribe to default gossipsub topics
-        let mut topics = vec![];
-
         /* Here we subscribe to all the required gossipsub topics required for interop.
-         * The topic builder adds the required prefix and postfix to the hardcoded topics that we
-         * must subscribe to.
+         * The topics of each kind are scoped to the current fork, and are resubscribed to by the
+         * behaviour whenever the fork digest changes.
          */
-        let topic_builder = |topic| {
-            Topic::new(format!(
-                "/{}/{}/{}",
-                TOPIC_PREFIX, topic, TOPIC_ENCODING_POSTFIX,
-            ))
-        };
-        topics.push(topic_builder(BEACON_BLOCK_TOPIC));
-        topics.push(topic_builder(BEACON_ATTESTATION_TOPIC));
-        topics.push(topic_builder(VOLUNTARY_EXIT_TOPIC));
-        topics.push(topic_builder(PROPOSER_SLASHING_TOPIC));
-        topics.push(topic_builder(ATTESTER_SLASHING_TOPIC));
-
-        // Add any topics specified by the user
-        topics.append(
-            &mut config
-                .topics
-                .iter()
-                .cloned()
-                .map(|s| Topic::new(s))
-                .collect(),
-        );
-
-        let mut subscribed_topics = vec![];
-        for topic in topics {
-            if swarm.subscribe(topic.clone()) {
-                trace!(log, "Subscribed to topic"; "topic" => format!("{}", topic));
-                subscribed_topics.push(topic);
+        let mut subscribed_kinds = vec![];
+        for kind in GossipKind::defaults() {
+            if swarm.subscribe_kind(kind) {
+                trace!(log, "Subscribed to topic"; "kind" => format!("{:?}", kind));
+                subscribed_kinds.push(kind);
             } else {
+                warn!(log, "Could not subscribe to topic"; "kind" => format!("{:?}", kind));
+            }
+        }
+
+        // Subscribe to any topics specified by the user. These are not scoped to a fork.
+        for topic in config.topics.iter().cloned().map(Topic::new) {
+            if !swarm.subscribe(topic.clone()) {
                 warn!(log, "Could not subscribe to topic"; "topic" => format!("{}", topic));
             }
         }
-        info!(log, "Subscribed to topics"; "topics" => format!("{:?}", subscribed_topics.iter().map(|t| format!("{}", t)).collect::<Vec<String>>()));
+        info!(
+            log,
+            "Subscribed to topics";
+            "kinds" => format!("{:?}", subscribed_kinds),
+            "fork_digest" => hex::encode(fork_digest),
+            "extra_topics" => format!("{:?}", config.topics),
+        );
 
         Ok(Service {
             local_peer_id,
@@ -165,6 +164,11 @@ impl Stream for Service {
 
     fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
         loop {
+            // carry out any disconnections or bans required by the peer manager
+            while let Ok(Async::Ready(Some(event))) = self.swarm.peer_manager_mut().poll() {
+                self.handle_peer_manager_event(event);
+            }
+
             match self.swarm.poll() {
                 Ok(Async::Ready(Some(event))) => match event {
                     BehaviourEvent::GossipMessage {
@@ -211,6 +215,45 @@ impl Stream for Service {
     }
 }
 
+impl Service {
+    /// Carries out an action required by the `PeerManager`.
+    fn handle_peer_manager_event(&mut self, event: PeerManagerEvent) {
+        match event {
+            PeerManagerEvent::DialPeer(peer_id) => {
+                debug!(self.log, "Dialing discovered peer"; "peer_id" => format!("{}", peer_id));
+                Swarm::dial(&mut self.swarm, peer_id);
+            }
+            PeerManagerEvent::DialAddress(multiaddr) => {
+                debug!(self.log, "Dialing trusted peer"; "address" => format!("{}", multiaddr));
+                if let Err(e) = Swarm::dial_addr(&mut self.swarm, multiaddr.clone()) {
+                    debug!(self.log, "Could not dial trusted peer"; "address" => format!("{}", multiaddr), "error" => format!("{:?}", e));
+                }
+            }
+            PeerManagerEvent::DisconnectPeer(peer_id, reason) => {
+                debug!(self.log, "Disconnecting peer"; "peer_id" => format!("{}", peer_id), "reason" => format!("{}", reason));
+                self.send_goodbye(peer_id, reason);
+            }
+            PeerManagerEvent::BanPeer(peer_id) => {
+                info!(self.log, "Banning peer"; "peer_id" => format!("{}", peer_id));
+                self.send_goodbye(peer_id.clone(), GoodbyeReason::Fault);
+                Swarm::ban_peer_id(&mut self.swarm, peer_id);
+            }
+            PeerManagerEvent::UnbanPeer(peer_id) => {
+                debug!(self.log, "Unbanning peer"; "peer_id" => format!("{}", peer_id));
+                Swarm::unban_peer_id(&mut self.swarm, peer_id);
+            }
+            PeerManagerEvent::Ping(peer_id) => self.swarm.send_ping(peer_id),
+            PeerManagerEvent::MetaData(peer_id) => self.swarm.send_meta_data_request(peer_id),
+        }
+    }
+
+    fn send_goodbye(&mut self, peer_id: PeerId, reason: GoodbyeReason) {
+        // use 0 as the request id, a response is not expected.
+        self.swarm
+            .send_rpc(peer_id, RPCEvent::Request(0, RPCRequest::Goodbye(reason)));
+    }
+}
+
 /// Converts a multiaddr to a `SocketAddr` if the multiaddr has the TCP/IP form. Libp2p currently
 /// only supports TCP, so the UDP case is currently ignored.
 fn multiaddr_to_socket_addr(multiaddr: &Multiaddr) -> Option<std::net::SocketAddr> {
@@ -0,0 +1,365 @@
+use crate::config::*;
+use crate::{Topic, TopicHash};
+use eth2_hashing::hash;
+use types::ForkDigest;
+
+/// The kinds of gossipsub topic, independent of the fork they are scoped to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GossipKind {
+    BeaconBlock,
+    BeaconAttestation,
+    BeaconAggregateAndProof,
+    /// Attestations for the committee index subnet with the given id.
+    CommitteeIndex(u64),
+    VoluntaryExit,
+    ProposerSlashing,
+    AttesterSlashing,
+}
+
+impl GossipKind {
+    /// The kinds of topic which are subscribed to by every node.
+    pub fn defaults() -> Vec<GossipKind> {
+        vec![
+            GossipKind::BeaconBlock,
+            GossipKind::BeaconAttestation,
+            GossipKind::BeaconAggregateAndProof,
+            GossipKind::VoluntaryExit,
+            GossipKind::ProposerSlashing,
+            GossipKind::AttesterSlashing,
+        ]
+    }
+
+    /// Returns the name of the topic, as it appears in the full topic string.
+    fn name(self) -> String {
+        match self {
+            GossipKind::BeaconBlock => BEACON_BLOCK_TOPIC.into(),
+            GossipKind::BeaconAttestation => BEACON_ATTESTATION_TOPIC.into(),
+            GossipKind::BeaconAggregateAndProof => BEACON_AGGREGATE_AND_PROOF_TOPIC.into(),
+            GossipKind::CommitteeIndex(subnet_id) => format!(
+                "{}{}{}",
+                COMMITTEE_INDEX_TOPIC_PREFIX, subnet_id, COMMITTEE_INDEX_TOPIC_POSTFIX
+            ),
+            GossipKind::VoluntaryExit => VOLUNTARY_EXIT_TOPIC.into(),
+            GossipKind::ProposerSlashing => PROPOSER_SLASHING_TOPIC.into(),
+            GossipKind::AttesterSlashing => ATTESTER_SLASHING_TOPIC.into(),
+        }
+    }
+
+    /// Parses the name of a topic, as it appears in the full topic string.
+    fn from_name(name: &str) -> Option<GossipKind> {
+        match name {
+            BEACON_BLOCK_TOPIC => Some(GossipKind::BeaconBlock),
+            BEACON_ATTESTATION_TOPIC => Some(GossipKind::BeaconAttestation),
+            BEACON_AGGREGATE_AND_PROOF_TOPIC => Some(GossipKind::BeaconAggregateAndProof),
+            VOLUNTARY_EXIT_TOPIC => Some(GossipKind::VoluntaryExit),
+            PROPOSER_SLASHING_TOPIC => Some(GossipKind::ProposerSlashing),
+            ATTESTER_SLASHING_TOPIC => Some(GossipKind::AttesterSlashing),
+            subnet_topic
+                if subnet_topic.starts_with(COMMITTEE_INDEX_TOPIC_PREFIX)
+                    && subnet_topic.ends_with(COMMITTEE_INDEX_TOPIC_POSTFIX) =>
+            {
+                subnet_topic[COMMITTEE_INDEX_TOPIC_PREFIX.len()
+                    ..subnet_topic.len() - COMMITTEE_INDEX_TOPIC_POSTFIX.len()]
+                    .parse()
+                    .ok()
+                    .map(GossipKind::CommitteeIndex)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The encoding of the messages of a gossipsub topic, given by the topic's postfix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GossipEncoding {
+    /// Plain SSZ.
+    SSZ,
+    /// SSZ compressed with the snappy block format.
+    SSZSnappy,
+}
+
+impl GossipEncoding {
+    /// The encodings whose topics are subscribed to. Messages are published with the first.
+    pub fn all() -> [GossipEncoding; 2] {
+        [GossipEncoding::SSZSnappy, GossipEncoding::SSZ]
+    }
+
+    fn postfix(self) -> &'static str {
+        match self {
+            GossipEncoding::SSZ => TOPIC_ENCODING_POSTFIX,
+            GossipEncoding::SSZSnappy => TOPIC_SNAPPY_ENCODING_POSTFIX,
+        }
+    }
+
+    fn from_postfix(postfix: &str) -> Option<GossipEncoding> {
+        match postfix {
+            TOPIC_ENCODING_POSTFIX => Some(GossipEncoding::SSZ),
+            TOPIC_SNAPPY_ENCODING_POSTFIX => Some(GossipEncoding::SSZSnappy),
+            _ => None,
+        }
+    }
+
+    /// Encodes the SSZ bytes of a message for publishing.
+    pub fn encode(self, data: Vec<u8>) -> Result<Vec<u8>, String> {
+        match self {
+            GossipEncoding::SSZ => Ok(data),
+            GossipEncoding::SSZSnappy => snap::raw::Encoder::new()
+                .compress_vec(&data)
+                .map_err(|e| format!("Snappy compression failed: {}", e)),
+        }
+    }
+
+    /// Decodes a received message into its SSZ bytes. Snappy payloads which declare an
+    /// uncompressed length above `max_size` are rejected before they are decompressed.
+    pub fn decode(self, data: Vec<u8>, max_size: usize) -> Result<Vec<u8>, String> {
+        match self {
+            GossipEncoding::SSZ => Ok(data),
+            GossipEncoding::SSZSnappy => {
+                let len = snap::raw::decompress_len(&data)
+                    .map_err(|e| format!("Invalid snappy length: {}", e))?;
+                if len > max_size {
+                    return Err(format!(
+                        "Decompressed message of {} bytes exceeds the maximum of {}",
+                        len, max_size
+                    ));
+                }
+                snap::raw::Decoder::new()
+                    .decompress_vec(&data)
+                    .map_err(|e| format!("Snappy decompression failed: {}", e))
+            }
+        }
+    }
+}
+
+/// A gossipsub topic, scoped to a single fork.
+///
+/// Topics have the form `/TOPIC_PREFIX/FORK_DIGEST/TOPIC/ENCODING_POSTFIX`, where the fork digest
+/// is hex-encoded. For example, `/eth2/e7a75d5a/beacon_block/ssz_snappy`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GossipTopic {
+    pub fork_digest: ForkDigest,
+    pub kind: GossipKind,
+    pub encoding: GossipEncoding,
+}
+
+impl GossipTopic {
+    pub fn new(kind: GossipKind, encoding: GossipEncoding, fork_digest: ForkDigest) -> Self {
+        Self {
+            fork_digest,
+            kind,
+            encoding,
+        }
+    }
+
+    /// Returns the topics of `kind` on `fork_digest` in every encoding.
+    ///
+    /// Peers may subscribe to either encoding, so messages are published to (and received from)
+    /// all of these topics.
+    pub fn all_encodings(kind: GossipKind, fork_digest: ForkDigest) -> Vec<Self> {
+        GossipEncoding::all()
+            .iter()
+            .map(|encoding| Self::new(kind, *encoding, fork_digest))
+            .collect()
+    }
+
+    /// Parses a full topic string, returning `None` if it is not a known eth2 topic.
+    pub fn decode(topic: &str) -> Option<Self> {
+        let topic_parts: Vec<&str> = topic.split('/').collect();
+
+        if topic_parts.len() != 5 || !topic_parts[0].is_empty() || topic_parts[1] != TOPIC_PREFIX {
+            return None;
+        }
+
+        let encoding = GossipEncoding::from_postfix(topic_parts[4])?;
+
+        let digest_bytes = hex::decode(topic_parts[2]).ok()?;
+        if digest_bytes.len() != 4 {
+            return None;
+        }
+        let mut fork_digest = [0; 4];
+        fork_digest.copy_from_slice(&digest_bytes);
+
+        GossipKind::from_name(topic_parts[3]).map(|kind| Self::new(kind, encoding, fork_digest))
+    }
+}
+
+impl From<GossipTopic> for String {
+    fn from(topic: GossipTopic) -> String {
+        format!(
+            "/{}/{}/{}/{}",
+            TOPIC_PREFIX,
+            hex::encode(topic.fork_digest),
+            topic.kind.name(),
+            topic.encoding.postfix()
+        )
+    }
+}
+
+impl From<GossipTopic> for Topic {
+    fn from(topic: GossipTopic) -> Topic {
+        Topic::new(String::from(topic))
+    }
+}
+
+/// Computes the message-id of a gossipsub message: the hex-encoded SHA256 hash of the message
+/// payload.
+///
+/// Messages on snappy-encoded topics are hashed after decompression, so that the same message
+/// compressed differently by two peers has the same id. Payloads that fail to decompress, or
+/// that declare a decompressed length above `GOSSIP_MAX_SIZE` (which is checked before anything is
+/// decompressed), are hashed as they are and are rejected once decoded.
+pub fn message_id(topics: &[TopicHash], data: &[u8]) -> String {
+    let encoding = topics
+        .iter()
+        .filter_map(|topic| GossipTopic::decode(topic.as_str()))
+        .map(|topic| topic.encoding)
+        .next();
+
+    let decompressed = match encoding {
+        Some(GossipEncoding::SSZSnappy) => GossipEncoding::SSZSnappy
+            .decode(data.to_vec(), GOSSIP_MAX_SIZE)
+            .ok(),
+        _ => None,
+    };
+
+    hex::encode(hash(decompressed.as_ref().map_or(data, Vec::as_slice)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck_macros::quickcheck;
+
+    #[test]
+    fn encode_and_decode() {
+        let fork_digest = [0xe7, 0xa7, 0x5d, 0x5a];
+        let kinds = vec![
+            GossipKind::BeaconBlock,
+            GossipKind::CommitteeIndex(0),
+            GossipKind::CommitteeIndex(42),
+            GossipKind::AttesterSlashing,
+        ];
+
+        for kind in kinds {
+            for encoding in &GossipEncoding::all() {
+                let topic = GossipTopic::new(kind, *encoding, fork_digest);
+                let topic_string = String::from(topic.clone());
+                assert_eq!(GossipTopic::decode(&topic_string), Some(topic));
+            }
+        }
+
+        let topic_string = String::from(GossipTopic::new(
+            GossipKind::CommitteeIndex(3),
+            GossipEncoding::SSZ,
+            fork_digest,
+        ));
+        assert_eq!(
+            topic_string,
+            "/eth2/e7a75d5a/committee_index3_beacon_attestation/ssz"
+        );
+
+        let topic_string = String::from(GossipTopic::new(
+            GossipKind::BeaconBlock,
+            GossipEncoding::SSZSnappy,
+            fork_digest,
+        ));
+        assert_eq!(topic_string, "/eth2/e7a75d5a/beacon_block/ssz_snappy");
+    }
+
+    #[test]
+    fn all_encodings() {
+        let fork_digest = [0xe7, 0xa7, 0x5d, 0x5a];
+        let topics = GossipTopic::all_encodings(GossipKind::BeaconBlock, fork_digest)
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            topics,
+            vec![
+                "/eth2/e7a75d5a/beacon_block/ssz_snappy",
+                "/eth2/e7a75d5a/beacon_block/ssz",
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_invalid() {
+        for topic in &[
+            "/eth2/beacon_block/ssz",
+            "/eth2/e7a75d5a/beacon_block/ssz_lz4",
+            "/eth2/e7a75d/beacon_block/ssz",
+            "/eth2/e7a75d5a/committee_indexx_beacon_attestation/ssz",
+            "/eth2/e7a75d5a/unknown/ssz",
+            "eth2/e7a75d5a/beacon_block/ssz/",
+        ] {
+            assert_eq!(GossipTopic::decode(topic), None, "{}", topic);
+        }
+    }
+
+    #[test]
+    fn message_id_of_snappy_message() {
+        let data = vec![42; 1024];
+        let compressed = snap::raw::Encoder::new().compress_vec(&data).unwrap();
+        let ssz_topic = TopicHash::from_raw("/eth2/e7a75d5a/beacon_block/ssz");
+        let snappy_topic = TopicHash::from_raw("/eth2/e7a75d5a/beacon_block/ssz_snappy");
+
+        assert_eq!(
+            message_id(&[snappy_topic.clone()], &compressed),
+            message_id(&[ssz_topic.clone()], &data)
+        );
+        assert_ne!(
+            message_id(&[ssz_topic], &compressed),
+            message_id(&[snappy_topic], &compressed)
+        );
+    }
+
+    #[test]
+    fn message_id_of_oversized_snappy_message() {
+        let data = vec![0; GOSSIP_MAX_SIZE + 1];
+        let compressed = snap::raw::Encoder::new().compress_vec(&data).unwrap();
+        assert!(compressed.len() < GOSSIP_MAX_SIZE);
+        let snappy_topic = TopicHash::from_raw("/eth2/e7a75d5a/beacon_block/ssz_snappy");
+
+        // The payload must not be decompressed, so it is hashed as it is.
+        assert_eq!(
+            message_id(&[snappy_topic], &compressed),
+            hex::encode(hash(&compressed))
+        );
+    }
+
+    #[test]
+    fn snappy_round_trip() {
+        let data = (0..2048).map(|i| (i % 7) as u8).collect::<Vec<_>>();
+        let encoded = GossipEncoding::SSZSnappy.encode(data.clone()).unwrap();
+
+        assert!(encoded.len() < data.len());
+        assert_eq!(
+            GossipEncoding::SSZSnappy.decode(encoded, data.len()),
+            Ok(data)
+        );
+    }
+
+    #[test]
+    fn snappy_rejects_oversized_messages() {
+        let data = vec![0; 2048];
+        let encoded = GossipEncoding::SSZSnappy.encode(data).unwrap();
+
+        assert!(GossipEncoding::SSZSnappy.decode(encoded, 2047).is_err());
+    }
+
+    #[quickcheck]
+    fn snappy_decode_is_bounded(data: Vec<u8>, max_size: u16) -> bool {
+        // arbitrary input must never panic, nor decompress beyond the maximum size
+        match GossipEncoding::SSZSnappy.decode(data, max_size as usize) {
+            Ok(decoded) => decoded.len() <= max_size as usize,
+            Err(_) => true,
+        }
+    }
+
+    #[quickcheck]
+    fn snappy_encoding_round_trips(data: Vec<u8>) -> bool {
+        let encoded = GossipEncoding::SSZSnappy.encode(data.clone()).unwrap();
+        GossipEncoding::SSZSnappy.decode(encoded, data.len()) == Ok(data)
+    }
+}
@@ -37,9 +37,9 @@ pub struct Eth1GenesisService {
 
 impl Eth1GenesisService {
     /// Creates a new service. Does not attempt to connect to the Eth1 node.
-    pub fn new(config: Eth1Config, log: Logger) -> Self {
+    pub fn new<E: EthSpec>(config: Eth1Config, log: Logger, spec: ChainSpec) -> Self {
         Self {
-            core: Service::new(config, log),
+            core: Service::new::<E>(config, log, spec),
             highest_processed_block: Arc::new(Mutex::new(None)),
             sync_blocks: Arc::new(Mutex::new(false)),
         }
@@ -38,17 +38,18 @@ fn basic() {
         .block_on(web3.eth().block_number().map(|v| v.as_u64()))
         .expect("should get block number");
 
-    let service = Eth1GenesisService::new(
+    let service = Eth1GenesisService::new::<MinimalEthSpec>(
         Eth1Config {
             endpoint: eth1.endpoint(),
             deposit_contract_address: deposit_contract.address(),
             deposit_contract_deploy_block: now,
             lowest_cached_block_number: now,
             follow_distance: 0,
-            block_cache_truncation: None,
+            block_cache_max_bytes: None,
             ..Eth1Config::default()
         },
         log,
+        spec.clone(),
     );
 
     // NOTE: this test is sensitive to the response speed of the external web3 server. If
@@ -73,7 +74,7 @@ fn basic() {
         })
         .collect::<Vec<_>>();
 
-    let deposit_future = deposit_contract.deposit_multiple(deposits.clone());
+    let deposit_future = deposit_contract.deposit_with_delays(deposits.clone());
 
     let wait_future =
         service.wait_for_genesis_state::<MinimalEthSpec>(update_interval, spec.clone());
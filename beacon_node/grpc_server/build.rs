@@ -0,0 +1,16 @@
+#[cfg(feature = "server")]
+fn main() {
+    use std::path::Path;
+
+    let proto_root = Path::new("src");
+    println!(
+        "cargo:rerun-if-changed={}",
+        proto_root.join("services.proto").display()
+    );
+    protoc_grpcio::compile_grpc_protos(&["services.proto"], &[proto_root], &proto_root, None)
+        .expect("Failed to compile gRPC definitions!");
+}
+
+/// Without the `server` feature, there are no gRPC services to generate.
+#[cfg(not(feature = "server"))]
+fn main() {}
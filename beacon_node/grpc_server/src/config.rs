@@ -0,0 +1,46 @@
+use clap::ArgMatches;
+use serde_derive::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
+
+/// gRPC server configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Enable the gRPC server.
+    pub enabled: bool,
+    /// The IPv4 address the gRPC server will listen on.
+    pub listen_address: Ipv4Addr,
+    /// The port the gRPC server will listen on.
+    pub port: u16,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            enabled: false,
+            listen_address: Ipv4Addr::new(127, 0, 0, 1),
+            port: 5054,
+        }
+    }
+}
+
+impl Config {
+    pub fn apply_cli_args(&mut self, args: &ArgMatches) -> Result<(), &'static str> {
+        if args.is_present("grpc") {
+            self.enabled = true;
+        }
+
+        if let Some(grpc_address) = args.value_of("grpc-address") {
+            self.listen_address = grpc_address
+                .parse::<Ipv4Addr>()
+                .map_err(|_| "grpc-address is not a valid IPv4 address.")?;
+        }
+
+        if let Some(grpc_port) = args.value_of("grpc-port") {
+            self.port = grpc_port
+                .parse::<u16>()
+                .map_err(|_| "grpc-port is not a valid u16.")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,24 @@
+//! A gRPC server providing the validator endpoints of the HTTP API.
+//!
+//! The server is only compiled with the `server` feature, which requires `protoc` to generate the
+//! gRPC services. Without it, only the `Config` is available.
+
+mod config;
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "server")]
+#[allow(clippy::all)]
+mod services;
+#[cfg(feature = "server")]
+#[allow(clippy::all)]
+mod services_grpc;
+#[cfg(feature = "server")]
+mod validator;
+
+pub use config::Config;
+#[cfg(feature = "server")]
+pub use server::start_server;
+#[cfg(feature = "server")]
+pub use services::*;
+#[cfg(feature = "server")]
+pub use services_grpc::{create_validator_service, ValidatorServiceClient};
@@ -0,0 +1,197 @@
+use crate::services_grpc::create_validator_service;
+use crate::validator::ValidatorServiceInstance;
+use crate::Config;
+use beacon_chain::{BeaconChain, BeaconChainTypes};
+use futures::Future;
+use grpcio::{Environment, ServerBuilder};
+use network::NetworkMessage;
+use slog::{info, warn};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::runtime::TaskExecutor;
+use tokio::sync::mpsc;
+
+/// Starts the gRPC server, returning a signal that will shut it down when dropped and the address
+/// it is listening on.
+pub fn start_server<T: BeaconChainTypes>(
+    config: &Config,
+    executor: &TaskExecutor,
+    beacon_chain: Arc<BeaconChain<T>>,
+    network_chan: mpsc::UnboundedSender<NetworkMessage>,
+    log: &slog::Logger,
+) -> Result<(exit_future::Signal, SocketAddr), String> {
+    let env = Arc::new(Environment::new(1));
+
+    let validator_service = create_validator_service(ValidatorServiceInstance {
+        beacon_chain,
+        network_chan,
+        log: log.clone(),
+    });
+
+    let mut server = ServerBuilder::new(env)
+        .register_service(validator_service)
+        .bind(config.listen_address.to_string(), config.port)
+        .build()
+        .map_err(|e| format!("Failed to build gRPC server: {:?}", e))?;
+
+    server.start();
+
+    let (host, port) = server
+        .bind_addrs()
+        .first()
+        .cloned()
+        .ok_or_else(|| "gRPC server is not bound to any address".to_string())?;
+    let listen_addr = format!("{}:{}", host, port)
+        .parse::<SocketAddr>()
+        .map_err(|e| format!("Unable to parse gRPC listen address: {:?}", e))?;
+
+    info!(
+        log,
+        "gRPC server started";
+        "address" => format!("{}", listen_addr.ip()),
+        "port" => listen_addr.port(),
+    );
+
+    let (exit_signal, exit) = exit_future::signal();
+    let log = log.clone();
+    executor.spawn(exit.and_then(move |_| {
+        info!(log, "gRPC server shutting down");
+        server.shutdown().wait().map_err(
+            |e| warn!(log, "gRPC server failed to shutdown"; "error" => format!("{:?}", e)),
+        )
+    }));
+
+    Ok((exit_signal, listen_addr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        GetDutiesRequest, ProduceAttestationRequest, PublishAttestationRequest,
+        ValidatorServiceClient,
+    };
+    use beacon_chain::test_utils::{BeaconChainHarness, HarnessType};
+    use eth2_libp2p::{GossipKind, PubsubMessage};
+    use futures::Stream;
+    use grpcio::{ChannelBuilder, Error as GrpcError, RpcStatusCode};
+    use protobuf::RepeatedField;
+    use ssz::{Decode, Encode};
+    use std::net::Ipv4Addr;
+    use tokio::runtime::Runtime;
+    use types::test_utils::generate_deterministic_keypairs;
+    use types::{Attestation, MinimalEthSpec, RelativeEpoch, Slot};
+
+    const VALIDATOR_COUNT: usize = 8;
+
+    #[test]
+    fn round_trip() {
+        let keypairs = generate_deterministic_keypairs(VALIDATOR_COUNT + 1);
+        let harness: BeaconChainHarness<HarnessType<MinimalEthSpec>> =
+            BeaconChainHarness::new(MinimalEthSpec, keypairs[0..VALIDATOR_COUNT].to_vec());
+        let beacon_chain = Arc::new(harness.chain);
+
+        let runtime = Runtime::new().expect("should create runtime");
+        let (network_send, network_recv) = mpsc::unbounded_channel();
+        let log = slog::Logger::root(slog::Discard, slog::o!());
+        let config = Config {
+            enabled: true,
+            listen_address: Ipv4Addr::new(127, 0, 0, 1),
+            port: 0,
+        };
+
+        let (_exit_signal, listen_addr) = start_server(
+            &config,
+            &runtime.executor(),
+            beacon_chain.clone(),
+            network_send,
+            &log,
+        )
+        .expect("should start server");
+
+        let channel =
+            ChannelBuilder::new(Arc::new(Environment::new(1))).connect(&listen_addr.to_string());
+        let client = ValidatorServiceClient::new(channel);
+
+        // The last pubkey is not in the validator set.
+        let pubkeys = keypairs
+            .iter()
+            .map(|keypair| keypair.pk.as_ssz_bytes())
+            .collect::<Vec<_>>();
+        let mut req = GetDutiesRequest::new();
+        req.set_epoch(0);
+        req.set_validator_pubkeys(RepeatedField::from_vec(pubkeys.clone()));
+        let resp = client.get_duties(&req).expect("should get duties");
+
+        let mut state = beacon_chain.head().beacon_state;
+        state
+            .build_committee_cache(RelativeEpoch::Current, &beacon_chain.spec)
+            .expect("should build committee cache");
+        assert_eq!(resp.get_duties().len(), VALIDATOR_COUNT + 1);
+        for (validator_index, duty) in resp.get_duties().iter().enumerate() {
+            assert_eq!(duty.get_validator_pubkey(), &pubkeys[validator_index][..]);
+
+            if validator_index == VALIDATOR_COUNT {
+                assert!(
+                    !duty.get_has_attestation_duty(),
+                    "unknown validators should have no duties"
+                );
+                continue;
+            }
+
+            let expected = state
+                .get_attestation_duties(validator_index, RelativeEpoch::Current)
+                .expect("should get attestation duties")
+                .expect("every validator should attest in each epoch");
+            assert!(duty.get_has_attestation_duty());
+            assert_eq!(duty.get_attestation_slot(), expected.slot.as_u64());
+            assert_eq!(duty.get_attestation_committee_index(), expected.index);
+            assert_eq!(
+                duty.get_attestation_committee_position(),
+                expected.committee_position as u64
+            );
+        }
+
+        req.set_epoch(2);
+        match client.get_duties(&req) {
+            Err(GrpcError::RpcFailure(status)) => {
+                assert_eq!(status.status, RpcStatusCode::InvalidArgument)
+            }
+            other => panic!("duties of a distant epoch should be rejected: {:?}", other),
+        }
+
+        let mut req = ProduceAttestationRequest::new();
+        req.set_slot(0);
+        req.set_committee_index(0);
+        let attestation_bytes = client
+            .produce_attestation(&req)
+            .expect("should produce attestation")
+            .take_attestation();
+        let attestation = Attestation::<MinimalEthSpec>::from_ssz_bytes(&attestation_bytes)
+            .expect("should decode attestation");
+        assert_eq!(
+            attestation,
+            beacon_chain
+                .produce_attestation(Slot::new(0), 0)
+                .expect("should produce attestation locally")
+        );
+
+        let mut req = PublishAttestationRequest::new();
+        req.set_attestation(attestation_bytes.clone());
+        let resp = client
+            .publish_attestation(&req)
+            .expect("should publish attestation");
+        assert!(
+            !resp.get_processed(),
+            "an unsigned attestation should fail processing"
+        );
+
+        match network_recv.into_future().wait() {
+            Ok((Some(NetworkMessage::Publish { topics, message }), _)) => {
+                assert_eq!(topics, vec![GossipKind::BeaconAttestation]);
+                assert_eq!(message, PubsubMessage::Attestation(attestation_bytes));
+            }
+            _ => panic!("the attestation should be published to the network"),
+        }
+    }
+}
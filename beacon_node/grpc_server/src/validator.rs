@@ -0,0 +1,335 @@
+use crate::services::{
+    GetDutiesRequest, GetDutiesResponse, ProduceAttestationRequest, ProduceAttestationResponse,
+    ProduceBlockRequest, ProduceBlockResponse, PublishAttestationRequest, PublishBlockRequest,
+    PublishResponse, ValidatorDuty,
+};
+use crate::services_grpc::ValidatorService;
+use beacon_chain::{
+    AttestationProcessingOutcome, BeaconChain, BeaconChainTypes, BlockProcessingOutcome,
+};
+use bls::{PublicKey, Signature};
+use eth2_libp2p::{GossipKind, PubsubMessage};
+use futures::Future;
+use grpcio::{RpcContext, RpcStatus, RpcStatusCode, UnarySink};
+use network::NetworkMessage;
+use protobuf::RepeatedField;
+use slog::{info, trace, warn};
+use ssz::{Decode, Encode};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use types::{Attestation, BeaconBlock, Epoch, EthSpec, RelativeEpoch, Slot};
+
+#[derive(Clone)]
+pub struct ValidatorServiceInstance<T: BeaconChainTypes> {
+    pub beacon_chain: Arc<BeaconChain<T>>,
+    pub network_chan: mpsc::UnboundedSender<NetworkMessage>,
+    pub log: slog::Logger,
+}
+
+impl<T: BeaconChainTypes> ValidatorService for ValidatorServiceInstance<T> {
+    /// Returns the duties of each of the requested validators during the requested epoch.
+    fn get_duties(
+        &mut self,
+        ctx: RpcContext,
+        req: GetDutiesRequest,
+        sink: UnarySink<GetDutiesResponse>,
+    ) {
+        trace!(self.log, "RPC request"; "endpoint" => "GetDuties", "epoch" => req.get_epoch());
+
+        let result = check_deadline(&ctx)
+            .and_then(|_| self.duties(Epoch::new(req.get_epoch()), req.get_validator_pubkeys()))
+            .map(|duties| {
+                let mut resp = GetDutiesResponse::new();
+                resp.set_duties(RepeatedField::from_vec(duties));
+                resp
+            });
+
+        respond(ctx, sink, result, &self.log)
+    }
+
+    /// Produces an unsigned block at the requested slot.
+    fn produce_block(
+        &mut self,
+        ctx: RpcContext,
+        req: ProduceBlockRequest,
+        sink: UnarySink<ProduceBlockResponse>,
+    ) {
+        trace!(self.log, "RPC request"; "endpoint" => "ProduceBlock", "slot" => req.get_slot());
+
+        let result = check_deadline(&ctx)
+            .and_then(|_| {
+                Signature::from_bytes(req.get_randao_reveal()).map_err(|e| {
+                    invalid_argument(format!("Invalid randao_reveal signature: {:?}", e))
+                })
+            })
+            .and_then(|randao_reveal| {
+                self.beacon_chain
+                    .produce_block(randao_reveal, Slot::new(req.get_slot()))
+                    .map_err(|e| {
+                        internal(format!(
+                            "Beacon node is not able to produce a block: {:?}",
+                            e
+                        ))
+                    })
+            })
+            .map(|(block, _state)| {
+                let mut resp = ProduceBlockResponse::new();
+                resp.set_block(block.as_ssz_bytes());
+                resp
+            });
+
+        respond(ctx, sink, result, &self.log)
+    }
+
+    /// Processes a signed block and publishes it to the network, even if it fails processing.
+    fn publish_block(
+        &mut self,
+        ctx: RpcContext,
+        req: PublishBlockRequest,
+        sink: UnarySink<PublishResponse>,
+    ) {
+        trace!(self.log, "RPC request"; "endpoint" => "PublishBlock");
+
+        let result = check_deadline(&ctx)
+            .and_then(|_| {
+                BeaconBlock::<T::EthSpec>::from_ssz_bytes(req.get_block())
+                    .map_err(|e| invalid_argument(format!("Invalid block: {:?}", e)))
+            })
+            .and_then(|block| {
+                let outcome = self
+                    .beacon_chain
+                    .process_block(block.clone())
+                    .map_err(|e| internal(format!("Error while processing block: {:?}", e)))?;
+
+                let slot = block.slot;
+                let message = PubsubMessage::Block(block.as_ssz_bytes());
+                self.publish(GossipKind::BeaconBlock, message)?;
+
+                Ok(match outcome {
+                    BlockProcessingOutcome::Processed { block_root } => {
+                        info!(
+                            self.log,
+                            "Processed valid block from gRPC, transmitting to network.";
+                            "block_slot" => slot,
+                            "block_root" => format!("{}", block_root)
+                        );
+                        publish_response(true, String::new())
+                    }
+                    outcome => {
+                        warn!(
+                            self.log,
+                            "BeaconBlock could not be processed, but is being sent to the network anyway.";
+                            "outcome" => format!("{:?}", outcome)
+                        );
+                        publish_response(false, format!("{:?}", outcome))
+                    }
+                })
+            });
+
+        respond(ctx, sink, result, &self.log)
+    }
+
+    /// Produces an unsigned attestation for the requested slot and committee.
+    fn produce_attestation(
+        &mut self,
+        ctx: RpcContext,
+        req: ProduceAttestationRequest,
+        sink: UnarySink<ProduceAttestationResponse>,
+    ) {
+        trace!(self.log, "RPC request"; "endpoint" => "ProduceAttestation", "slot" => req.get_slot());
+
+        let result = check_deadline(&ctx)
+            .and_then(|_| {
+                self.beacon_chain
+                    .produce_attestation(Slot::new(req.get_slot()), req.get_committee_index())
+                    .map_err(|e| {
+                        invalid_argument(format!("Unable to produce attestation: {:?}", e))
+                    })
+            })
+            .map(|attestation| {
+                let mut resp = ProduceAttestationResponse::new();
+                resp.set_attestation(attestation.as_ssz_bytes());
+                resp
+            });
+
+        respond(ctx, sink, result, &self.log)
+    }
+
+    /// Processes a signed attestation and publishes it to the network, even if it fails
+    /// processing.
+    fn publish_attestation(
+        &mut self,
+        ctx: RpcContext,
+        req: PublishAttestationRequest,
+        sink: UnarySink<PublishResponse>,
+    ) {
+        trace!(self.log, "RPC request"; "endpoint" => "PublishAttestation");
+
+        let result = check_deadline(&ctx)
+            .and_then(|_| {
+                Attestation::<T::EthSpec>::from_ssz_bytes(req.get_attestation())
+                    .map_err(|e| invalid_argument(format!("Invalid attestation: {:?}", e)))
+            })
+            .and_then(|attestation| {
+                let outcome = self
+                    .beacon_chain
+                    .process_attestation(attestation.clone())
+                    .map_err(|e| {
+                        internal(format!("Error while processing attestation: {:?}", e))
+                    })?;
+
+                let message = PubsubMessage::Attestation(attestation.as_ssz_bytes());
+                self.publish(GossipKind::BeaconAttestation, message)?;
+
+                Ok(match outcome {
+                    AttestationProcessingOutcome::Processed => {
+                        info!(
+                            self.log,
+                            "Processed valid attestation from gRPC, transmitting to network."
+                        );
+                        publish_response(true, String::new())
+                    }
+                    outcome => {
+                        warn!(
+                            self.log,
+                            "Attestation could not be processed, but is being sent to the network anyway.";
+                            "outcome" => format!("{:?}", outcome)
+                        );
+                        publish_response(false, format!("{:?}", outcome))
+                    }
+                })
+            });
+
+        respond(ctx, sink, result, &self.log)
+    }
+}
+
+impl<T: BeaconChainTypes> ValidatorServiceInstance<T> {
+    /// Returns the duties of each of the `pubkeys` during `epoch`.
+    ///
+    /// The given `epoch` must be within one epoch of the current epoch.
+    fn duties(&self, epoch: Epoch, pubkeys: &[Vec<u8>]) -> Result<Vec<ValidatorDuty>, RpcStatus> {
+        let spec = &self.beacon_chain.spec;
+
+        let mut state = self
+            .beacon_chain
+            .state_at_slot(epoch.start_slot(T::EthSpec::slots_per_epoch()))
+            .map_err(|e| internal(format!("Unable to load state for epoch {}: {:?}", epoch, e)))?;
+
+        let relative_epoch =
+            RelativeEpoch::from_epoch(state.current_epoch(), epoch).map_err(|_| {
+                invalid_argument("Epoch must be within one epoch of the current epoch".into())
+            })?;
+
+        state
+            .build_committee_cache(relative_epoch, spec)
+            .map_err(|e| internal(format!("Unable to build committee cache: {:?}", e)))?;
+        state
+            .update_pubkey_cache()
+            .map_err(|e| internal(format!("Unable to build pubkey cache: {:?}", e)))?;
+
+        let proposers = epoch
+            .slot_iter(T::EthSpec::slots_per_epoch())
+            .map(|slot| {
+                state
+                    .get_beacon_proposer_index(slot, spec)
+                    .map(|i| (i, slot))
+                    .map_err(|e| internal(format!("Unable to get proposer index: {:?}", e)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        pubkeys
+            .iter()
+            .map(|bytes| {
+                let pubkey = PublicKey::from_bytes(bytes)
+                    .map_err(|e| invalid_argument(format!("Invalid pubkey: {:?}", e)))?;
+
+                let mut duty = ValidatorDuty::new();
+                duty.set_validator_pubkey(bytes.clone());
+
+                let validator_index = state
+                    .get_validator_index(&pubkey)
+                    .map_err(|e| internal(format!("Unable to read pubkey cache: {:?}", e)))?;
+
+                if let Some(validator_index) = validator_index {
+                    let attestation_duty = state
+                        .get_attestation_duties(validator_index, relative_epoch)
+                        .map_err(|e| {
+                            internal(format!("Unable to obtain attestation duties: {:?}", e))
+                        })?;
+
+                    if let Some(attestation_duty) = attestation_duty {
+                        duty.set_has_attestation_duty(true);
+                        duty.set_attestation_slot(attestation_duty.slot.as_u64());
+                        duty.set_attestation_committee_index(attestation_duty.index);
+                        duty.set_attestation_committee_position(
+                            attestation_duty.committee_position as u64,
+                        );
+                    }
+
+                    if let Some((_, slot)) = proposers.iter().find(|(i, _)| *i == validator_index) {
+                        duty.set_has_block_proposal(true);
+                        duty.set_block_proposal_slot(slot.as_u64());
+                    }
+                }
+
+                Ok(duty)
+            })
+            .collect()
+    }
+
+    /// Publishes `message` to the gossipsub topic of `kind`, for the current fork.
+    fn publish(&mut self, kind: GossipKind, message: PubsubMessage) -> Result<(), RpcStatus> {
+        self.network_chan
+            .try_send(NetworkMessage::Publish {
+                topics: vec![kind],
+                message,
+            })
+            .map_err(|e| internal(format!("Unable to send message to network: {:?}", e)))
+    }
+}
+
+/// Returns an error if the deadline set by the client has already passed, allowing expensive
+/// requests to be abandoned early.
+fn check_deadline(ctx: &RpcContext) -> Result<(), RpcStatus> {
+    if ctx.deadline().exceeded() {
+        Err(RpcStatus::new(
+            RpcStatusCode::DeadlineExceeded,
+            Some("Deadline exceeded before the request was processed".into()),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn invalid_argument(details: String) -> RpcStatus {
+    RpcStatus::new(RpcStatusCode::InvalidArgument, Some(details))
+}
+
+fn internal(details: String) -> RpcStatus {
+    RpcStatus::new(RpcStatusCode::Internal, Some(details))
+}
+
+fn publish_response(processed: bool, message: String) -> PublishResponse {
+    let mut resp = PublishResponse::new();
+    resp.set_processed(processed);
+    resp.set_message(message);
+    resp
+}
+
+/// Sends `result` to the client via `sink`.
+fn respond<R: Send + 'static>(
+    ctx: RpcContext,
+    sink: UnarySink<R>,
+    result: Result<R, RpcStatus>,
+    log: &slog::Logger,
+) {
+    let log = log.clone();
+    let future = match result {
+        Ok(resp) => sink.success(resp),
+        Err(status) => sink.fail(status),
+    }
+    .map_err(move |e| warn!(log, "Failed to reply to gRPC request"; "error" => format!("{:?}", e)));
+
+    ctx.spawn(future)
+}
@@ -0,0 +1,477 @@
+//! Manages the attestation subnets that the node is subscribed to.
+//!
+//! Subnets are subscribed to for two reasons:
+//! - Validators that are due to aggregate attestations in a slot request a subscription to the
+//!   subnet of their committee. The subnet is subscribed to until the end of the slot.
+//! - To maintain a stable mesh of peers on every subnet, each node is subscribed to
+//!   `RANDOM_SUBNETS_PER_NODE` long-lived subnets. These subnets are derived from the node id and
+//!   rotate every `EPOCHS_PER_RANDOM_SUBNET_SUBSCRIPTION` epochs. Long-lived subnets are
+//!   advertised in the `attnets` bitfield of the local ENR.
+//!
+//! The `AttestationService` is a stream of `AttServiceMessage`s, which the network service applies
+//! to the gossipsub behaviour, the local ENR and discovery.
+
+use beacon_chain::{BeaconChain, BeaconChainTypes};
+use eth2_hashing::hash;
+use futures::prelude::*;
+use slog::{debug, trace, warn};
+use slot_clock::SlotClock;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::timer::{DelayQueue, Interval};
+use types::{EthSpec, Slot, ValidatorSubscription};
+
+/// The number of long-lived subnets each node subscribes to.
+const RANDOM_SUBNETS_PER_NODE: u64 = 2;
+/// The number of epochs a node remains subscribed to its long-lived subnets before they rotate.
+const EPOCHS_PER_RANDOM_SUBNET_SUBSCRIPTION: u64 = 256;
+
+/// An action the network service should take to follow the subscriptions of the
+/// `AttestationService`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttServiceMessage {
+    /// Subscribe to the gossip topic of the attestation subnet.
+    Subscribe(u64),
+    /// Unsubscribe from the gossip topic of the attestation subnet.
+    Unsubscribe(u64),
+    /// Advertise the attestation subnet in the local ENR.
+    EnrAdd(u64),
+    /// Stop advertising the attestation subnet in the local ENR.
+    EnrRemove(u64),
+    /// Search for peers on the attestation subnet.
+    DiscoverPeers(u64),
+}
+
+/// Tracks the attestation subnets the node is subscribed to and when to leave them.
+pub struct AttestationService<T: BeaconChainTypes> {
+    /// Actions that are yet to be returned to the network service.
+    events: VecDeque<AttServiceMessage>,
+    /// A reference to the beacon chain, to read the slot clock.
+    beacon_chain: Arc<BeaconChain<T>>,
+    /// The raw node id of the local ENR, from which the long-lived subnets are derived.
+    node_id: [u8; 32],
+    /// The long-lived subnets the node is currently subscribed to.
+    random_subnets: HashSet<u64>,
+    /// The subscription period of `random_subnets`.
+    random_subnet_period: Option<u64>,
+    /// Checks whether the long-lived subnets have rotated once per epoch.
+    random_subnet_update: Interval,
+    /// The last slot in which each short-lived subnet is required.
+    subscriptions: HashMap<u64, Slot>,
+    /// Subnets whose short-lived subscription may be expiring.
+    unsubscriptions: DelayQueue<u64>,
+    log: slog::Logger,
+}
+
+impl<T: BeaconChainTypes> AttestationService<T> {
+    pub fn new(beacon_chain: Arc<BeaconChain<T>>, node_id: [u8; 32], log: &slog::Logger) -> Self {
+        let epoch_duration = Duration::from_millis(
+            beacon_chain.spec.milliseconds_per_slot * T::EthSpec::slots_per_epoch(),
+        );
+
+        let mut service = AttestationService {
+            events: VecDeque::new(),
+            beacon_chain,
+            node_id,
+            random_subnets: HashSet::new(),
+            random_subnet_period: None,
+            random_subnet_update: Interval::new(Instant::now() + epoch_duration, epoch_duration),
+            subscriptions: HashMap::new(),
+            unsubscriptions: DelayQueue::new(),
+            log: log.clone(),
+        };
+        service.update_random_subnets();
+        service
+    }
+
+    /// Processes the subscriptions of validators to the subnets of their committees.
+    ///
+    /// Only aggregators subscribe to the subnet, until the end of the requested slot. For all
+    /// subscriptions, peers on the subnet are searched for so that attestations can be published.
+    pub fn validator_subscriptions(&mut self, subscriptions: Vec<ValidatorSubscription>) {
+        let current_slot = match self.beacon_chain.slot_clock.now() {
+            Some(slot) => slot,
+            None => {
+                warn!(
+                    self.log,
+                    "Could not read the slot clock, ignoring subscriptions"
+                );
+                return;
+            }
+        };
+
+        for subscription in subscriptions {
+            let subnet_id = subscription.subnet_id(&self.beacon_chain.spec);
+
+            if subscription.slot < current_slot {
+                trace!(self.log, "Ignoring subscription to a past slot";
+                    "subnet_id" => subnet_id,
+                    "slot" => subscription.slot,
+                );
+                continue;
+            }
+
+            debug!(self.log, "Validator subscription";
+                "subnet_id" => subnet_id,
+                "validator_index" => subscription.validator_index,
+                "slot" => subscription.slot,
+                "is_aggregator" => subscription.is_aggregator,
+            );
+
+            self.events
+                .push_back(AttServiceMessage::DiscoverPeers(subnet_id));
+
+            if subscription.is_aggregator {
+                self.subscribe_until(subnet_id, subscription.slot, current_slot);
+            }
+        }
+    }
+
+    /// Subscribes to `subnet_id` until the end of `slot`.
+    fn subscribe_until(&mut self, subnet_id: u64, slot: Slot, current_slot: Slot) {
+        let subscribed = self.is_subscribed(subnet_id);
+
+        let expiry = self.subscriptions.entry(subnet_id).or_insert(slot);
+        if *expiry < slot {
+            *expiry = slot;
+        }
+
+        if !subscribed {
+            self.events
+                .push_back(AttServiceMessage::Subscribe(subnet_id));
+        }
+
+        // check the subscription again once the slot has passed
+        let delay = self.duration_to_end_of(slot, current_slot);
+        self.unsubscriptions.insert(subnet_id, delay);
+    }
+
+    /// Returns the duration until the end of `slot`, which is no earlier than `current_slot`.
+    fn duration_to_end_of(&self, slot: Slot, current_slot: Slot) -> Duration {
+        let slot_duration = self.beacon_chain.slot_clock.slot_duration();
+        let duration_to_next_slot = self
+            .beacon_chain
+            .slot_clock
+            .duration_to_next_slot()
+            .unwrap_or(slot_duration);
+        let slots_after_next = (slot - current_slot).as_u64() as u32;
+        duration_to_next_slot + slot_duration * slots_after_next
+    }
+
+    /// Returns `true` if the node is subscribed to `subnet_id`, either because it is a long-lived
+    /// subnet or because of a validator subscription.
+    fn is_subscribed(&self, subnet_id: u64) -> bool {
+        self.random_subnets.contains(&subnet_id) || self.subscriptions.contains_key(&subnet_id)
+    }
+
+    /// Leaves `subnet_id` if no validator requires it anymore and it is not a long-lived subnet.
+    fn handle_unsubscription(&mut self, subnet_id: u64) {
+        let current_slot = match self.beacon_chain.slot_clock.now() {
+            Some(slot) => slot,
+            None => return,
+        };
+
+        match self.subscriptions.get(&subnet_id) {
+            // a later subscription to the subnet is still active, or the timer fired before the
+            // end of the slot (e.g., due to clock drift), so check again once it has passed
+            Some(&slot) if slot >= current_slot => {
+                let delay = self.duration_to_end_of(slot, current_slot);
+                self.unsubscriptions.insert(subnet_id, delay);
+                return;
+            }
+            Some(_) => {
+                self.subscriptions.remove(&subnet_id);
+            }
+            None => return,
+        }
+
+        if !self.is_subscribed(subnet_id) {
+            debug!(self.log, "Unsubscribing from subnet"; "subnet_id" => subnet_id);
+            self.events
+                .push_back(AttServiceMessage::Unsubscribe(subnet_id));
+        }
+    }
+
+    /// Updates the long-lived subnets if their subscription period has passed.
+    fn update_random_subnets(&mut self) {
+        // prior to genesis, the genesis epoch is used
+        let epoch = self.beacon_chain.epoch().unwrap_or_else(|_| {
+            self.beacon_chain
+                .spec
+                .genesis_slot
+                .epoch(T::EthSpec::slots_per_epoch())
+        });
+        let period = epoch.as_u64() / EPOCHS_PER_RANDOM_SUBNET_SUBSCRIPTION;
+        if self.random_subnet_period == Some(period) {
+            return;
+        }
+        self.random_subnet_period = Some(period);
+
+        let new_subnets = compute_random_subnets(
+            &self.node_id,
+            period,
+            self.beacon_chain.spec.attestation_subnet_count,
+        );
+
+        for subnet_id in self.random_subnets.difference(&new_subnets) {
+            self.events
+                .push_back(AttServiceMessage::EnrRemove(*subnet_id));
+            if !self.subscriptions.contains_key(subnet_id) {
+                self.events
+                    .push_back(AttServiceMessage::Unsubscribe(*subnet_id));
+            }
+        }
+
+        for subnet_id in new_subnets.difference(&self.random_subnets) {
+            if !self.subscriptions.contains_key(subnet_id) {
+                self.events
+                    .push_back(AttServiceMessage::Subscribe(*subnet_id));
+            }
+            self.events.push_back(AttServiceMessage::EnrAdd(*subnet_id));
+            self.events
+                .push_back(AttServiceMessage::DiscoverPeers(*subnet_id));
+        }
+
+        debug!(self.log, "Updated long-lived subnets";
+            "subnets" => format!("{:?}", new_subnets),
+            "period" => period,
+        );
+        self.random_subnets = new_subnets;
+    }
+}
+
+/// Derives the long-lived subnets of a node from its id and the subscription `period`.
+///
+/// The subnets are distinct, unless there are fewer subnets than `RANDOM_SUBNETS_PER_NODE`.
+pub fn compute_random_subnets(node_id: &[u8; 32], period: u64, subnet_count: u64) -> HashSet<u64> {
+    let target = std::cmp::min(RANDOM_SUBNETS_PER_NODE, subnet_count) as usize;
+    let mut subnets = HashSet::with_capacity(target);
+
+    let mut index: u64 = 0;
+    while subnets.len() < target {
+        let mut preimage = node_id.to_vec();
+        preimage.extend_from_slice(&period.to_le_bytes());
+        preimage.extend_from_slice(&index.to_le_bytes());
+
+        let digest = hash(&preimage);
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&digest[0..8]);
+        subnets.insert(u64::from_le_bytes(bytes) % subnet_count);
+
+        index += 1;
+    }
+
+    subnets
+}
+
+impl<T: BeaconChainTypes> Stream for AttestationService<T> {
+    type Item = AttServiceMessage;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        // rotate the long-lived subnets
+        loop {
+            match self.random_subnet_update.poll() {
+                Ok(Async::Ready(Some(_))) => self.update_random_subnets(),
+                Ok(Async::Ready(None)) | Ok(Async::NotReady) => break,
+                Err(e) => {
+                    warn!(self.log, "Subnet rotation timer failed"; "error" => format!("{:?}", e));
+                    break;
+                }
+            }
+        }
+
+        // leave subnets whose subscriptions have expired
+        loop {
+            match self.unsubscriptions.poll() {
+                Ok(Async::Ready(Some(expired))) => self.handle_unsubscription(expired.into_inner()),
+                Ok(Async::Ready(None)) | Ok(Async::NotReady) => break,
+                Err(e) => {
+                    warn!(self.log, "Subnet unsubscription timer failed"; "error" => format!("{:?}", e));
+                    break;
+                }
+            }
+        }
+
+        match self.events.pop_front() {
+            Some(event) => Ok(Async::Ready(Some(event))),
+            None => Ok(Async::NotReady),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use beacon_chain::test_utils::{BeaconChainHarness, HarnessType};
+    use futures::future;
+    use tokio::runtime::current_thread::Runtime;
+    use tokio::timer::Delay;
+    use types::test_utils::generate_deterministic_keypairs;
+    use types::MinimalEthSpec;
+
+    /// The testing slot clock always reports that the next slot starts in one second.
+    const PAST_NEXT_SLOT: Duration = Duration::from_millis(1_100);
+
+    struct Tester {
+        runtime: Runtime,
+        beacon_chain: Arc<BeaconChain<HarnessType<MinimalEthSpec>>>,
+        service: AttestationService<HarnessType<MinimalEthSpec>>,
+    }
+
+    impl Tester {
+        /// Returns a service whose initial events (for its long-lived subnets) have been drained,
+        /// with the slot clock at `slot`.
+        fn new(slot: u64) -> Self {
+            let beacon_chain = Arc::new(
+                BeaconChainHarness::new(MinimalEthSpec, generate_deterministic_keypairs(8)).chain,
+            );
+            beacon_chain.slot_clock.set_slot(slot);
+
+            let mut runtime = Runtime::new().expect("should create runtime");
+            let log = slog::Logger::root(slog::Discard, slog::o!());
+            let chain = beacon_chain.clone();
+            let service = runtime
+                .block_on(future::lazy(move || {
+                    Ok::<_, ()>(AttestationService::new(chain, [7; 32], &log))
+                }))
+                .expect("should create service");
+
+            let mut tester = Tester {
+                runtime,
+                beacon_chain,
+                service,
+            };
+            tester.events_after(Duration::from_millis(0));
+            tester
+        }
+
+        /// Returns the events of the service once `delay` has passed.
+        fn events_after(&mut self, delay: Duration) -> Vec<AttServiceMessage> {
+            let service = &mut self.service;
+            self.runtime
+                .block_on(
+                    Delay::new(Instant::now() + delay)
+                        .map_err(|_| ())
+                        .and_then(|_| {
+                            future::poll_fn(|| {
+                                let mut events = vec![];
+                                while let Async::Ready(Some(event)) = service.poll()? {
+                                    events.push(event);
+                                }
+                                Ok(Async::Ready(events))
+                            })
+                        }),
+                )
+                .expect("should poll service")
+        }
+
+        /// Returns a subnet which is not one of the long-lived subnets of the service.
+        fn short_lived_subnet(&self, skip: usize) -> u64 {
+            (0..self.beacon_chain.spec.attestation_subnet_count)
+                .filter(|subnet_id| !self.service.random_subnets.contains(subnet_id))
+                .nth(skip)
+                .expect("should have a short-lived subnet")
+        }
+
+        fn subscribe(&mut self, subnet_id: u64, slot: u64, is_aggregator: bool) {
+            self.service
+                .validator_subscriptions(vec![ValidatorSubscription {
+                    validator_index: 0,
+                    attestation_committee_index: subnet_id,
+                    slot: Slot::new(slot),
+                    is_aggregator,
+                }]);
+        }
+    }
+
+    #[test]
+    fn aggregators_subscribe_until_the_end_of_their_slot() {
+        let mut tester = Tester::new(10);
+        let (subnet, other_subnet) = (tester.short_lived_subnet(0), tester.short_lived_subnet(1));
+
+        tester.subscribe(subnet, 10, true);
+        tester.subscribe(other_subnet, 10, false);
+        tester.subscribe(other_subnet, 9, true);
+        assert_eq!(
+            tester.events_after(Duration::from_millis(0)),
+            vec![
+                AttServiceMessage::DiscoverPeers(subnet),
+                AttServiceMessage::Subscribe(subnet),
+                AttServiceMessage::DiscoverPeers(other_subnet),
+            ],
+            "only aggregators of current or future slots should subscribe"
+        );
+
+        assert_eq!(
+            tester.events_after(PAST_NEXT_SLOT),
+            vec![],
+            "should remain subscribed while the slot has not passed"
+        );
+
+        tester.beacon_chain.slot_clock.set_slot(11);
+        assert_eq!(
+            tester.events_after(PAST_NEXT_SLOT),
+            vec![AttServiceMessage::Unsubscribe(subnet)],
+            "should unsubscribe once the slot has passed"
+        );
+    }
+
+    #[test]
+    fn later_subscriptions_delay_unsubscription() {
+        let mut tester = Tester::new(10);
+        let subnet = tester.short_lived_subnet(0);
+
+        tester.subscribe(subnet, 10, true);
+        tester.subscribe(subnet, 12, true);
+        assert_eq!(
+            tester.events_after(Duration::from_millis(0)),
+            vec![
+                AttServiceMessage::DiscoverPeers(subnet),
+                AttServiceMessage::Subscribe(subnet),
+                AttServiceMessage::DiscoverPeers(subnet),
+            ],
+            "should subscribe to the subnet once"
+        );
+
+        tester.beacon_chain.slot_clock.set_slot(11);
+        assert_eq!(
+            tester.events_after(PAST_NEXT_SLOT),
+            vec![],
+            "should remain subscribed for the later slot"
+        );
+
+        tester.beacon_chain.slot_clock.set_slot(13);
+        assert_eq!(
+            tester.events_after(PAST_NEXT_SLOT),
+            vec![AttServiceMessage::Unsubscribe(subnet)],
+            "should unsubscribe once the later slot has passed"
+        );
+    }
+
+    #[test]
+    fn long_lived_subnets_are_not_unsubscribed() {
+        let mut tester = Tester::new(10);
+        let subnet = *tester
+            .service
+            .random_subnets
+            .iter()
+            .next()
+            .expect("should have long-lived subnets");
+
+        tester.subscribe(subnet, 10, true);
+        assert_eq!(
+            tester.events_after(Duration::from_millis(0)),
+            vec![AttServiceMessage::DiscoverPeers(subnet)],
+            "should already be subscribed to the long-lived subnet"
+        );
+
+        tester.beacon_chain.slot_clock.set_slot(11);
+        assert_eq!(
+            tester.events_after(PAST_NEXT_SLOT),
+            vec![],
+            "should remain subscribed to the long-lived subnet"
+        );
+    }
+}
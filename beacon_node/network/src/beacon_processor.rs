@@ -0,0 +1,793 @@
+//! Provides the `BeaconProcessor`, a bounded queue of work that is passed from the network layer
+//! to the `BeaconChain` and processed by a pool of worker threads.
+//!
+//! Work is sorted into lanes, which are served in strict priority order:
+//!
+//! 1. Gossip blocks.
+//! 2. Gossip aggregate attestations.
+//! 3. Gossip unaggregated attestations.
+//! 4. RPC blocks and chain segments, i.e., blocks imported by sync.
+//!
+//! So that a flood of gossip cannot starve sync, queued RPC work is served ahead of gossip once
+//! `MAX_CONSECUTIVE_GOSSIP_WORK` items of gossip have been served in a row.
+//!
+//! One worker thread is spawned per CPU. When a lane is full, the oldest work in the lane is
+//! dropped (it is the most likely to have become stale) and counted in the metrics. A flood of
+//! attestations therefore cannot delay the import of blocks, nor grow the queue without bound.
+//!
+//! Sync waits for the outcome of each RPC block or chain segment before submitting the next, so a
+//! busy processor slows sync down rather than causing its blocks to be dropped. The outcome of an
+//! RPC block is returned via a `oneshot` channel that sync polls, so a parent lookup never blocks
+//! the sync manager.
+
+use crate::metrics;
+use crate::pending_work::PendingWork;
+use crate::service::NetworkMessage;
+use crate::sync::{NetworkContext, SyncMessage, FUTURE_SLOT_TOLERANCE};
+use beacon_chain::{
+    AttestationProcessingOutcome, BeaconChain, BeaconChainTypes, BlockProcessingOutcome,
+    ChainSegmentResult,
+};
+use eth2_libp2p::{PeerAction, PeerId};
+use futures::{Async, Future, Poll};
+use parking_lot::{Condvar, Mutex};
+use slog::{debug, error, info, trace, warn, Logger};
+use ssz::Encode;
+use std::collections::VecDeque;
+use std::sync::{mpsc as std_mpsc, Arc};
+use std::thread;
+use tokio::sync::{mpsc, oneshot};
+use tree_hash::SignedRoot;
+use types::{AggregateAndProof, Attestation, BeaconBlock, EthSpec, Hash256};
+
+/// The maximum number of gossip blocks queued for processing.
+const MAX_GOSSIP_BLOCK_QUEUE_LEN: usize = 1_024;
+
+/// The maximum number of gossip aggregates queued for processing.
+const MAX_AGGREGATE_QUEUE_LEN: usize = 4_096;
+
+/// The maximum number of gossip unaggregated attestations queued for processing.
+const MAX_UNAGGREGATED_ATTESTATION_QUEUE_LEN: usize = 16_384;
+
+/// The maximum number of RPC blocks queued for processing.
+const MAX_RPC_BLOCK_QUEUE_LEN: usize = 1_024;
+
+/// The maximum number of items of gossip work served in a row while RPC work is queued.
+const MAX_CONSECUTIVE_GOSSIP_WORK: usize = 16;
+
+/// The result of importing an RPC block, returned to sync.
+pub type RpcBlockResult = Result<BlockProcessingOutcome, String>;
+
+/// The result of importing a chain segment, returned to sync.
+pub type ChainSegmentImportResult = Result<ChainSegmentResult, String>;
+
+/// An item of work for the `BeaconProcessor`.
+pub enum Work<E: EthSpec> {
+    /// A block received on gossip. It is propagated if it is valid.
+    GossipBlock {
+        message_id: String,
+        peer_id: PeerId,
+        block: BeaconBlock<E>,
+    },
+    /// An aggregate attestation received on gossip. It is propagated if it is valid.
+    GossipAggregate {
+        message_id: String,
+        peer_id: PeerId,
+        aggregate_and_proof: AggregateAndProof<E>,
+    },
+    /// An unaggregated attestation received on gossip.
+    GossipAttestation {
+        peer_id: PeerId,
+        attestation: Attestation<E>,
+    },
+    /// A block downloaded by sync, whose outcome is returned via `result_tx`.
+    RpcBlock {
+        block: BeaconBlock<E>,
+        result_tx: oneshot::Sender<RpcBlockResult>,
+    },
+    /// A segment of consecutive blocks downloaded by range sync, whose result is returned via
+    /// `result_tx`.
+    ChainSegment {
+        blocks: Vec<BeaconBlock<E>>,
+        result_tx: std_mpsc::Sender<ChainSegmentImportResult>,
+    },
+}
+
+/// A FIFO queue of work which drops its oldest item once it reaches `max_len`.
+struct Lane<E: EthSpec> {
+    queue: VecDeque<Work<E>>,
+    max_len: usize,
+    length_gauge: &'static metrics::Result<metrics::IntGauge>,
+    dropped_counter: &'static metrics::Result<metrics::IntCounter>,
+}
+
+impl<E: EthSpec> Lane<E> {
+    fn new(
+        max_len: usize,
+        length_gauge: &'static metrics::Result<metrics::IntGauge>,
+        dropped_counter: &'static metrics::Result<metrics::IntCounter>,
+    ) -> Self {
+        Self {
+            queue: VecDeque::new(),
+            max_len,
+            length_gauge,
+            dropped_counter,
+        }
+    }
+
+    /// Queues `work`, returning `true` if the oldest work was dropped to make room for it.
+    fn push(&mut self, work: Work<E>) -> bool {
+        let dropped = self.queue.len() >= self.max_len;
+        if dropped {
+            self.queue.pop_front();
+            metrics::inc_counter(self.dropped_counter);
+        }
+        self.queue.push_back(work);
+        metrics::set_gauge(self.length_gauge, self.queue.len() as i64);
+        dropped
+    }
+
+    fn pop(&mut self) -> Option<Work<E>> {
+        let work = self.queue.pop_front();
+        metrics::set_gauge(self.length_gauge, self.queue.len() as i64);
+        work
+    }
+
+    fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+/// The lanes of queued work, shared between the senders and the workers.
+struct Queues<E: EthSpec> {
+    gossip_blocks: Lane<E>,
+    aggregates: Lane<E>,
+    unaggregated_attestations: Lane<E>,
+    rpc_blocks: Lane<E>,
+    /// The number of items of gossip work served in a row while RPC work was queued.
+    consecutive_gossip_work: usize,
+    /// The number of workers that are presently processing work.
+    active_workers: usize,
+    /// Set once the `BeaconProcessor` is dropped, causing the workers to exit.
+    shutdown: bool,
+}
+
+impl<E: EthSpec> Queues<E> {
+    fn new() -> Self {
+        Self {
+            gossip_blocks: Lane::new(
+                MAX_GOSSIP_BLOCK_QUEUE_LEN,
+                &metrics::BEACON_PROCESSOR_GOSSIP_BLOCK_QUEUE_TOTAL,
+                &metrics::BEACON_PROCESSOR_GOSSIP_BLOCK_DROPPED_TOTAL,
+            ),
+            aggregates: Lane::new(
+                MAX_AGGREGATE_QUEUE_LEN,
+                &metrics::BEACON_PROCESSOR_AGGREGATE_QUEUE_TOTAL,
+                &metrics::BEACON_PROCESSOR_AGGREGATE_DROPPED_TOTAL,
+            ),
+            unaggregated_attestations: Lane::new(
+                MAX_UNAGGREGATED_ATTESTATION_QUEUE_LEN,
+                &metrics::BEACON_PROCESSOR_UNAGGREGATED_ATTESTATION_QUEUE_TOTAL,
+                &metrics::BEACON_PROCESSOR_UNAGGREGATED_ATTESTATION_DROPPED_TOTAL,
+            ),
+            rpc_blocks: Lane::new(
+                MAX_RPC_BLOCK_QUEUE_LEN,
+                &metrics::BEACON_PROCESSOR_RPC_BLOCK_QUEUE_TOTAL,
+                &metrics::BEACON_PROCESSOR_RPC_BLOCK_DROPPED_TOTAL,
+            ),
+            consecutive_gossip_work: 0,
+            active_workers: 0,
+            shutdown: false,
+        }
+    }
+
+    /// Queues `work` in its lane, returning `true` if older work was dropped to make room for it.
+    fn push(&mut self, work: Work<E>) -> bool {
+        match work {
+            Work::GossipBlock { .. } => self.gossip_blocks.push(work),
+            Work::GossipAggregate { .. } => self.aggregates.push(work),
+            Work::GossipAttestation { .. } => self.unaggregated_attestations.push(work),
+            Work::RpcBlock { .. } | Work::ChainSegment { .. } => self.rpc_blocks.push(work),
+        }
+    }
+
+    /// Returns the next item of work from the highest priority lane that is not empty, unless
+    /// `MAX_CONSECUTIVE_GOSSIP_WORK` items of gossip have been served while RPC work was queued, in
+    /// which case the next RPC work is returned.
+    fn pop(&mut self) -> Option<Work<E>> {
+        if self.rpc_blocks.is_empty() {
+            self.consecutive_gossip_work = 0;
+        } else if self.consecutive_gossip_work >= MAX_CONSECUTIVE_GOSSIP_WORK {
+            self.consecutive_gossip_work = 0;
+            return self.rpc_blocks.pop();
+        }
+
+        match self
+            .gossip_blocks
+            .pop()
+            .or_else(|| self.aggregates.pop())
+            .or_else(|| self.unaggregated_attestations.pop())
+        {
+            Some(work) => {
+                if !self.rpc_blocks.is_empty() {
+                    self.consecutive_gossip_work += 1;
+                }
+                Some(work)
+            }
+            None => {
+                self.consecutive_gossip_work = 0;
+                self.rpc_blocks.pop()
+            }
+        }
+    }
+}
+
+struct Shared<E: EthSpec> {
+    queues: Mutex<Queues<E>>,
+    /// Notified when work is queued or the processor shuts down.
+    condvar: Condvar,
+    /// Counts the work that is queued or being processed.
+    pending_work: PendingWork,
+}
+
+impl<E: EthSpec> Shared<E> {
+    /// Blocks until work is available and marks a worker as active, or returns `None` once the
+    /// processor has shut down.
+    fn next_work(&self) -> Option<Work<E>> {
+        let mut queues = self.queues.lock();
+        loop {
+            if queues.shutdown {
+                return None;
+            }
+            if let Some(work) = queues.pop() {
+                queues.active_workers += 1;
+                metrics::set_gauge(
+                    &metrics::BEACON_PROCESSOR_WORKERS_ACTIVE_TOTAL,
+                    queues.active_workers as i64,
+                );
+                return Some(work);
+            }
+            self.condvar.wait(&mut queues);
+        }
+    }
+
+    /// Marks a worker as idle, after it has processed the work returned by `Self::next_work`.
+    fn work_done(&self) {
+        let mut queues = self.queues.lock();
+        queues.active_workers -= 1;
+        metrics::set_gauge(
+            &metrics::BEACON_PROCESSOR_WORKERS_ACTIVE_TOTAL,
+            queues.active_workers as i64,
+        );
+        self.pending_work.done(1);
+    }
+}
+
+/// A handle for submitting work to a `BeaconProcessor`.
+pub struct BeaconProcessorSend<E: EthSpec>(Arc<Shared<E>>);
+
+impl<E: EthSpec> Clone for BeaconProcessorSend<E> {
+    fn clone(&self) -> Self {
+        BeaconProcessorSend(self.0.clone())
+    }
+}
+
+impl<E: EthSpec> BeaconProcessorSend<E> {
+    /// Queues `work` for processing, possibly dropping older work of the same kind.
+    ///
+    /// Work sent after the processor has shut down is dropped.
+    pub fn send(&self, work: Work<E>) {
+        let mut queues = self.0.queues.lock();
+        if queues.shutdown {
+            return;
+        }
+        self.0.pending_work.add();
+        if queues.push(work) {
+            self.0.pending_work.done(1);
+        }
+        drop(queues);
+        self.0.condvar.notify_one();
+    }
+
+    /// Queues a block downloaded by sync for import, without waiting for it to be processed.
+    ///
+    /// The returned future resolves to the outcome of the import, or to an error if the block is
+    /// dropped from the queue.
+    pub fn import_rpc_block(&self, block: BeaconBlock<E>) -> RpcBlockImport {
+        let (result_tx, result_rx) = oneshot::channel();
+        // Counted before it is queued, so that the import is pending until sync has its outcome.
+        self.0.pending_work.add();
+        self.send(Work::RpcBlock { block, result_tx });
+        RpcBlockImport {
+            result_rx,
+            pending_work: Some(self.0.pending_work.clone()),
+        }
+    }
+
+    /// Imports a segment of consecutive blocks downloaded by sync, blocking until it has been
+    /// processed.
+    pub fn import_chain_segment(&self, blocks: Vec<BeaconBlock<E>>) -> ChainSegmentImportResult {
+        let (result_tx, result_rx) = std_mpsc::channel();
+        self.send(Work::ChainSegment { blocks, result_tx });
+
+        result_rx
+            .recv()
+            .map_err(|_| "Chain segment was dropped by the beacon processor".to_string())?
+    }
+}
+
+/// The outcome of a block queued by `BeaconProcessorSend::import_rpc_block`.
+///
+/// The import is counted as pending work until `Self` is dropped or resolves. Once it has resolved,
+/// the receiver of the outcome is responsible for marking it as done (see `PendingWork`).
+pub struct RpcBlockImport {
+    result_rx: oneshot::Receiver<RpcBlockResult>,
+    pending_work: Option<PendingWork>,
+}
+
+impl Future for RpcBlockImport {
+    type Item = RpcBlockResult;
+    type Error = oneshot::error::RecvError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let poll = self.result_rx.poll();
+        match poll {
+            Ok(Async::NotReady) => {}
+            _ => self.pending_work = None,
+        }
+        poll
+    }
+}
+
+impl Drop for RpcBlockImport {
+    fn drop(&mut self) {
+        if let Some(pending_work) = &self.pending_work {
+            pending_work.done(1);
+        }
+    }
+}
+
+/// Owns the queue and the worker threads that process it. The workers exit once `Self` is
+/// dropped, discarding any queued work.
+pub struct BeaconProcessor<E: EthSpec> {
+    send: BeaconProcessorSend<E>,
+    log: Logger,
+}
+
+impl<E: EthSpec> BeaconProcessor<E> {
+    /// Creates a processor without any workers. Work is queued until `Self::spawn_workers` is
+    /// called.
+    ///
+    /// Queued work is counted in `pending_work` until it has been processed.
+    pub fn new(pending_work: PendingWork, log: Logger) -> Self {
+        Self {
+            send: BeaconProcessorSend(Arc::new(Shared {
+                queues: Mutex::new(Queues::new()),
+                condvar: Condvar::new(),
+                pending_work,
+            })),
+            log,
+        }
+    }
+
+    /// Returns a handle for submitting work to `self`.
+    pub fn sender(&self) -> BeaconProcessorSend<E> {
+        self.send.clone()
+    }
+
+    /// Queues `work` for processing, possibly dropping older work of the same kind.
+    pub fn send(&self, work: Work<E>) {
+        self.send.send(work)
+    }
+
+    /// Spawns one worker thread per CPU.
+    pub fn spawn_workers<T: BeaconChainTypes<EthSpec = E>>(
+        &self,
+        beacon_chain: Arc<BeaconChain<T>>,
+        network_send: mpsc::UnboundedSender<NetworkMessage>,
+        sync_send: mpsc::UnboundedSender<SyncMessage<E>>,
+    ) {
+        let max_workers = num_cpus::get();
+
+        for i in 0..max_workers {
+            let shared = self.send.0.clone();
+            let worker = Worker {
+                chain: beacon_chain.clone(),
+                network: NetworkContext::new(network_send.clone(), self.log.clone()),
+                sync_send: sync_send.clone(),
+                pending_work: shared.pending_work.clone(),
+                log: self.log.clone(),
+            };
+
+            if let Err(e) = thread::Builder::new()
+                .name(format!("beacon_processor_{}", i))
+                .spawn(move || worker.run(&shared))
+            {
+                error!(self.log, "Failed to spawn beacon processor worker"; "error" => format!("{:?}", e));
+            }
+        }
+
+        debug!(self.log, "Beacon processor started"; "workers" => max_workers);
+    }
+}
+
+impl<E: EthSpec> Drop for BeaconProcessor<E> {
+    fn drop(&mut self) {
+        let mut queues = self.send.0.queues.lock();
+        queues.shutdown = true;
+        // Dropping queued RPC work fails any sync task awaiting its outcome.
+        while queues.pop().is_some() {
+            self.send.0.pending_work.done(1);
+        }
+        drop(queues);
+        self.send.0.condvar.notify_all();
+    }
+}
+
+/// Processes work from the queue on a dedicated thread.
+struct Worker<T: BeaconChainTypes> {
+    chain: Arc<BeaconChain<T>>,
+    network: NetworkContext,
+    sync_send: mpsc::UnboundedSender<SyncMessage<T::EthSpec>>,
+    pending_work: PendingWork,
+    log: Logger,
+}
+
+impl<T: BeaconChainTypes> Worker<T> {
+    /// Processes work until the processor shuts down.
+    fn run(mut self, shared: &Shared<T::EthSpec>) {
+        while let Some(work) = shared.next_work() {
+            self.process(work);
+            shared.work_done();
+        }
+    }
+
+    fn process(&mut self, work: Work<T::EthSpec>) {
+        match work {
+            Work::GossipBlock {
+                message_id,
+                peer_id,
+                block,
+            } => {
+                if self.process_gossip_block(peer_id.clone(), block) {
+                    self.network.propagate(message_id, peer_id);
+                }
+            }
+            Work::GossipAggregate {
+                message_id,
+                peer_id,
+                aggregate_and_proof,
+            } => {
+                if self.process_gossip_aggregate(peer_id.clone(), aggregate_and_proof) {
+                    self.network.propagate(message_id, peer_id);
+                }
+            }
+            Work::GossipAttestation {
+                peer_id,
+                attestation,
+            } => self.process_gossip_attestation(peer_id, attestation),
+            Work::RpcBlock { block, result_tx } => {
+                let result = self
+                    .chain
+                    .process_block(block)
+                    .map_err(|e| format!("{:?}", e));
+                // sync may have given up waiting for the result
+                let _ = result_tx.send(result);
+            }
+            Work::ChainSegment { blocks, result_tx } => {
+                let result = self
+                    .chain
+                    .process_chain_segment(blocks)
+                    .map_err(|e| format!("{:?}", e));
+                let _ = result_tx.send(result);
+            }
+        }
+    }
+
+    /// Attempts to apply a gossip block to the beacon chain. Blocks with an unknown parent are
+    /// passed to sync.
+    ///
+    /// Returns `true` if the block should be forwarded to our peers.
+    fn process_gossip_block(&mut self, peer_id: PeerId, block: BeaconBlock<T::EthSpec>) -> bool {
+        match self.chain.process_block(block.clone()) {
+            Ok(outcome) => match outcome {
+                BlockProcessingOutcome::Processed { .. } => {
+                    trace!(self.log, "Gossipsub block processed";
+                            "peer_id" => format!("{:?}",peer_id));
+                    true
+                }
+                BlockProcessingOutcome::ParentUnknown { parent: _ } => {
+                    // Inform the sync manager to find parents for this block
+                    trace!(self.log, "Block with unknown parent received";
+                            "peer_id" => format!("{:?}",peer_id));
+                    self.pending_work.add();
+                    self.sync_send
+                        .try_send(SyncMessage::UnknownBlock(peer_id, block))
+                        .unwrap_or_else(|_| {
+                            self.pending_work.done(1);
+                            warn!(self.log, "Could not send message to the sync service")
+                        });
+                    true
+                }
+                BlockProcessingOutcome::FutureSlot {
+                    present_slot,
+                    block_slot,
+                } if present_slot + FUTURE_SLOT_TOLERANCE >= block_slot => {
+                    //TODO: Decide the logic here
+                    true
+                }
+                BlockProcessingOutcome::BlockIsAlreadyKnown => true,
+                other => {
+                    if other.is_invalid_block() {
+                        self.network.report_peer(peer_id, PeerAction::InvalidBlock);
+                    }
+
+                    warn!(
+                        self.log,
+                        "Invalid gossip beacon block";
+                        "outcome" => other.to_string(),
+                        "block root" => format!("{}", Hash256::from_slice(&block.signed_root()[..])),
+                        "block slot" => block.slot
+                    );
+                    trace!(
+                        self.log,
+                        "Invalid gossip beacon block ssz";
+                        "ssz" => format!("0x{}", hex::encode(block.as_ssz_bytes())),
+                    );
+                    false //TODO: Decide if we want to forward these
+                }
+            },
+            Err(e) => {
+                error!(
+                    self.log,
+                    "Error processing gossip beacon block";
+                    "error" => format!("{:?}", e),
+                    "block slot" => block.slot
+                );
+                trace!(
+                    self.log,
+                    "Erroneous gossip beacon block ssz";
+                    "ssz" => format!("0x{}", hex::encode(block.as_ssz_bytes())),
+                );
+                false
+            }
+        }
+    }
+
+    /// Verifies a gossip aggregate and proof and applies its aggregate to the beacon chain.
+    ///
+    /// Returns `true` if the aggregate should be forwarded to our peers.
+    fn process_gossip_aggregate(
+        &mut self,
+        peer_id: PeerId,
+        aggregate_and_proof: AggregateAndProof<T::EthSpec>,
+    ) -> bool {
+        match self
+            .chain
+            .process_aggregate_and_proof(aggregate_and_proof.clone())
+        {
+            Ok(AttestationProcessingOutcome::Processed) => {
+                trace!(
+                    self.log,
+                    "Gossip aggregate processed";
+                    "aggregator_index" => aggregate_and_proof.aggregator_index,
+                    "peer_id" => format!("{:?}", peer_id)
+                );
+                true
+            }
+            Ok(AttestationProcessingOutcome::AggregatorAlreadyKnown { .. }) => false,
+            Ok(other) => {
+                // Aggregates which fail verification are provably invalid, whereas aggregates
+                // for unknown blocks or prior to finalization may be the result of a slow peer.
+                if let AttestationProcessingOutcome::AggregatorNotInCommittee { .. }
+                | AttestationProcessingOutcome::NotAggregator { .. }
+                | AttestationProcessingOutcome::InvalidSelectionProof { .. }
+                | AttestationProcessingOutcome::EmptyAggregationBitfield
+                | AttestationProcessingOutcome::Invalid(_) = other
+                {
+                    self.network.report_peer(peer_id, PeerAction::InvalidGossip);
+                }
+
+                debug!(
+                    self.log,
+                    "Invalid gossip aggregate";
+                    "outcome" => format!("{:?}", other),
+                    "aggregator_index" => aggregate_and_proof.aggregator_index,
+                    "slot" => aggregate_and_proof.aggregate.data.slot,
+                );
+                trace!(
+                    self.log,
+                    "Invalid gossip aggregate ssz";
+                    "ssz" => format!("0x{}", hex::encode(aggregate_and_proof.as_ssz_bytes())),
+                );
+                false
+            }
+            Err(e) => {
+                error!(
+                    self.log,
+                    "Error processing gossip aggregate";
+                    "error" => format!("{:?}", e),
+                );
+                false
+            }
+        }
+    }
+
+    /// Applies a gossip attestation to the beacon chain.
+    fn process_gossip_attestation(&mut self, _peer_id: PeerId, msg: Attestation<T::EthSpec>) {
+        match self.chain.process_attestation(msg.clone()) {
+            Ok(outcome) => {
+                info!(
+                    self.log,
+                    "Processed attestation";
+                    "source" => "gossip",
+                    "outcome" => format!("{:?}", outcome)
+                );
+
+                if outcome != AttestationProcessingOutcome::Processed {
+                    trace!(
+                        self.log,
+                        "Invalid gossip attestation ssz";
+                        "ssz" => format!("0x{}", hex::encode(msg.as_ssz_bytes())),
+                    );
+                }
+            }
+            Err(e) => {
+                trace!(
+                    self.log,
+                    "Erroneous gossip attestation ssz";
+                    "ssz" => format!("0x{}", hex::encode(msg.as_ssz_bytes())),
+                );
+                error!(self.log, "Invalid gossip attestation"; "error" => format!("{:?}", e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::Future;
+    use std::time::Duration;
+    use types::{ChainSpec, MinimalEthSpec, Slot};
+
+    type E = MinimalEthSpec;
+
+    fn block(slot: u64) -> BeaconBlock<E> {
+        let mut block = BeaconBlock::empty(&ChainSpec::minimal());
+        block.slot = Slot::new(slot);
+        block
+    }
+
+    fn gossip_block(slot: u64) -> Work<E> {
+        Work::GossipBlock {
+            message_id: String::new(),
+            peer_id: PeerId::random(),
+            block: block(slot),
+        }
+    }
+
+    fn rpc_block(slot: u64) -> (Work<E>, oneshot::Receiver<RpcBlockResult>) {
+        let (result_tx, result_rx) = oneshot::channel();
+        let work = Work::RpcBlock {
+            block: block(slot),
+            result_tx,
+        };
+        (work, result_rx)
+    }
+
+    /// Returns the slot of the block of `work` and whether it is gossip work.
+    fn describe(work: Work<E>) -> (u64, bool) {
+        match work {
+            Work::GossipBlock { block, .. } => (block.slot.as_u64(), true),
+            Work::RpcBlock { block, .. } => (block.slot.as_u64(), false),
+            _ => panic!("unexpected work"),
+        }
+    }
+
+    #[test]
+    fn lane_drops_oldest_work() {
+        let mut lane = Lane::new(
+            2,
+            &metrics::BEACON_PROCESSOR_GOSSIP_BLOCK_QUEUE_TOTAL,
+            &metrics::BEACON_PROCESSOR_GOSSIP_BLOCK_DROPPED_TOTAL,
+        );
+        for slot in 0..3 {
+            lane.push(gossip_block(slot));
+        }
+
+        assert_eq!(lane.pop().map(describe), Some((1, true)));
+        assert_eq!(lane.pop().map(describe), Some((2, true)));
+        assert!(lane.pop().is_none());
+    }
+
+    #[test]
+    fn gossip_blocks_precede_rpc_blocks() {
+        let mut queues = Queues::new();
+        queues.push(rpc_block(0).0);
+        queues.push(gossip_block(1));
+
+        assert_eq!(queues.pop().map(describe), Some((1, true)));
+        assert_eq!(queues.pop().map(describe), Some((0, false)));
+        assert!(queues.pop().is_none());
+    }
+
+    #[test]
+    fn gossip_cannot_starve_rpc_blocks() {
+        let mut queues = Queues::new();
+        for slot in 0..MAX_CONSECUTIVE_GOSSIP_WORK as u64 * 2 {
+            queues.push(gossip_block(slot));
+        }
+        queues.push(rpc_block(1_000).0);
+
+        for _ in 0..MAX_CONSECUTIVE_GOSSIP_WORK {
+            assert_eq!(queues.pop().map(|work| describe(work).1), Some(true));
+        }
+        assert_eq!(queues.pop().map(describe), Some((1_000, false)));
+
+        // With no RPC work queued, gossip is served without limit.
+        for _ in 0..MAX_CONSECUTIVE_GOSSIP_WORK {
+            assert_eq!(queues.pop().map(|work| describe(work).1), Some(true));
+        }
+        assert!(queues.pop().is_none());
+    }
+
+    #[test]
+    fn worker_waits_for_work_and_exits_on_shutdown() {
+        let log = slog::Logger::root(slog::Discard, slog::o!());
+        let processor = BeaconProcessor::<E>::new(PendingWork::default(), log);
+        let shared = processor.sender().0;
+
+        let worker_shared = shared.clone();
+        let (slots_tx, slots_rx) = std_mpsc::channel();
+        let worker = thread::spawn(move || {
+            while let Some(work) = worker_shared.next_work() {
+                slots_tx.send(describe(work).0).unwrap();
+                worker_shared.work_done();
+            }
+        });
+
+        processor.send(gossip_block(7));
+        assert_eq!(slots_rx.recv_timeout(Duration::from_secs(5)), Ok(7));
+
+        drop(processor);
+        worker.join().unwrap();
+        assert_eq!(shared.queues.lock().active_workers, 0);
+    }
+
+    #[test]
+    fn dropped_rpc_blocks_resolve_with_an_error() {
+        let log = slog::Logger::root(slog::Discard, slog::o!());
+        let processor = BeaconProcessor::<E>::new(PendingWork::default(), log);
+        let result_rx = processor.sender().import_rpc_block(block(1));
+
+        // No workers were spawned, so the block is still queued when the processor is dropped.
+        drop(processor);
+        assert!(result_rx.wait().is_err());
+    }
+
+    #[test]
+    fn pending_work_counts_queued_work_and_rpc_imports() {
+        let log = slog::Logger::root(slog::Discard, slog::o!());
+        let pending_work = PendingWork::default();
+        let processor = BeaconProcessor::<E>::new(pending_work.clone(), log);
+
+        processor.send(gossip_block(1));
+        let abandoned = processor.sender().import_rpc_block(block(2));
+        let awaited = processor.sender().import_rpc_block(block(3));
+
+        // Dropping the processor discards the queued work, but not the imports awaiting it.
+        drop(processor);
+        assert!(!pending_work.is_idle());
+
+        // An import which is dropped before it resolves is no longer pending.
+        drop(abandoned);
+        assert!(!pending_work.is_idle());
+
+        // An import which resolves is pending until its receiver marks it as done.
+        assert!(awaited.wait().is_err());
+        assert!(!pending_work.is_idle());
+        pending_work.done(1);
+        assert!(pending_work.is_idle());
+    }
+}
@@ -1,9 +1,19 @@
 /// This crate provides the network server for Lighthouse.
+#[macro_use]
+extern crate lazy_static;
+
+pub mod attestation_service;
+mod beacon_processor;
 pub mod error;
 pub mod message_handler;
+mod metrics;
+mod nat;
+mod pending_work;
 pub mod service;
 pub mod sync;
 
 pub use eth2_libp2p::NetworkConfig;
+pub use pending_work::PendingWork;
 pub use service::NetworkMessage;
 pub use service::Service;
+pub use sync::{ChainProgress, RangeSyncType, SyncProgress, SyncState};
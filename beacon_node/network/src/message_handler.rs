@@ -1,19 +1,23 @@
 use crate::error;
+use crate::pending_work::PendingWork;
 use crate::service::NetworkMessage;
-use crate::sync::MessageProcessor;
+use crate::sync::{MessageProcessor, SyncProgress};
 use beacon_chain::{BeaconChain, BeaconChainTypes};
 use eth2_libp2p::{
     behaviour::PubsubMessage,
     rpc::{RPCError, RPCErrorResponse, RPCRequest, RPCResponse, RequestId},
-    PeerId, RPCEvent,
+    PeerAction, PeerId, RPCEvent,
 };
 use futures::future::Future;
 use futures::stream::Stream;
+use parking_lot::RwLock;
 use slog::{debug, o, trace, warn};
 use ssz::{Decode, DecodeError};
 use std::sync::Arc;
 use tokio::sync::mpsc;
-use types::{Attestation, AttesterSlashing, BeaconBlock, ProposerSlashing, VoluntaryExit};
+use types::{
+    AggregateAndProof, Attestation, AttesterSlashing, BeaconBlock, ProposerSlashing, VoluntaryExit,
+};
 
 /// Handles messages received from the network and client and organises syncing. This
 /// functionality of this struct is to validate an decode messages from the network before
@@ -43,22 +47,50 @@ pub enum HandlerMessage {
     PubsubMessage(String, PeerId, PubsubMessage),
 }
 
+/// Sends messages to a `MessageHandler`, counting each as pending work until it has been handled.
+#[derive(Clone)]
+pub struct HandlerSend {
+    send: mpsc::UnboundedSender<HandlerMessage>,
+    pending_work: PendingWork,
+}
+
+impl HandlerSend {
+    /// Sends `message` to the handler, returning an error if the handler has stopped.
+    pub fn try_send(&mut self, message: HandlerMessage) -> Result<(), String> {
+        self.pending_work.add();
+        self.send.try_send(message).map_err(|e| {
+            self.pending_work.done(1);
+            format!("Message handler has stopped: {:?}", e)
+        })
+    }
+}
+
 impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
     /// Initializes and runs the MessageHandler.
+    ///
+    /// The messages sent to the handler, and the work they cause, are counted by `pending_work`.
     pub fn spawn(
         beacon_chain: Arc<BeaconChain<T>>,
         network_send: mpsc::UnboundedSender<NetworkMessage>,
+        sync_progress: Arc<RwLock<SyncProgress>>,
+        pending_work: PendingWork,
         executor: &tokio::runtime::TaskExecutor,
         log: slog::Logger,
-    ) -> error::Result<mpsc::UnboundedSender<HandlerMessage>> {
+    ) -> error::Result<HandlerSend> {
         let message_handler_log = log.new(o!("service"=> "msg_handler"));
         trace!(message_handler_log, "Service starting");
 
         let (handler_send, handler_recv) = mpsc::unbounded_channel();
 
         // Initialise a message instance, which itself spawns the syncing thread.
-        let message_processor =
-            MessageProcessor::new(executor, beacon_chain, network_send.clone(), &log);
+        let message_processor = MessageProcessor::new(
+            executor,
+            beacon_chain,
+            network_send.clone(),
+            sync_progress,
+            pending_work.clone(),
+            &log,
+        );
 
         // generate the Message handler
         let mut handler = MessageHandler {
@@ -68,15 +100,23 @@ impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
         };
 
         // spawn handler task and move the message handler instance into the spawned thread
+        let handled_work = pending_work.clone();
         executor.spawn(
             handler_recv
-                .for_each(move |msg| Ok(handler.handle_message(msg)))
+                .for_each(move |msg| {
+                    handler.handle_message(msg);
+                    handled_work.done(1);
+                    Ok(())
+                })
                 .map_err(move |_| {
                     debug!(log, "Network message handler terminated.");
                 }),
         );
 
-        Ok(handler_send)
+        Ok(HandlerSend {
+            send: handler_send,
+            pending_work,
+        })
     }
 
     /// Handle all messages incoming from the network service.
@@ -115,9 +155,9 @@ impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
     /// A new RPC request has been received from the network.
     fn handle_rpc_request(&mut self, peer_id: PeerId, request_id: RequestId, request: RPCRequest) {
         match request {
-            RPCRequest::Hello(hello_message) => {
+            RPCRequest::Status(status_message) => {
                 self.message_processor
-                    .on_hello_request(peer_id, request_id, hello_message)
+                    .on_status_request(peer_id, request_id, status_message)
             }
             RPCRequest::Goodbye(goodbye_reason) => {
                 debug!(
@@ -127,12 +167,14 @@ impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
                 );
                 self.message_processor.on_disconnect(peer_id);
             }
-            RPCRequest::BeaconBlocks(request) => self
+            RPCRequest::BlocksByRange(request) => self
                 .message_processor
-                .on_beacon_blocks_request(peer_id, request_id, request),
-            RPCRequest::RecentBeaconBlocks(request) => self
+                .on_blocks_by_range_request(peer_id, request_id, request),
+            RPCRequest::BlocksByRoot(request) => self
                 .message_processor
-                .on_recent_beacon_blocks_request(peer_id, request_id, request),
+                .on_blocks_by_root_request(peer_id, request_id, request),
+            // pings and metadata requests are answered by the libp2p behaviour
+            RPCRequest::Ping(_) | RPCRequest::MetaData => {}
         }
     }
 
@@ -147,75 +189,83 @@ impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
         // an error could have occurred.
         match error_response {
             RPCErrorResponse::InvalidRequest(error) => {
-                warn!(self.log, "Peer indicated invalid request";"peer_id" => format!("{:?}", peer_id), "error" => error.as_string())
+                warn!(self.log, "Peer indicated invalid request";"peer_id" => format!("{:?}", peer_id), "error" => error.as_string());
+                self.message_processor.on_rpc_error(peer_id, request_id);
             }
             RPCErrorResponse::ServerError(error) => {
-                warn!(self.log, "Peer internal server error";"peer_id" => format!("{:?}", peer_id), "error" => error.as_string())
+                warn!(self.log, "Peer internal server error";"peer_id" => format!("{:?}", peer_id), "error" => error.as_string());
+                self.message_processor.on_rpc_error(peer_id, request_id);
             }
             RPCErrorResponse::Unknown(error) => {
-                warn!(self.log, "Unknown peer error";"peer" => format!("{:?}", peer_id), "error" => error.as_string())
+                warn!(self.log, "Unknown peer error";"peer" => format!("{:?}", peer_id), "error" => error.as_string());
+                self.message_processor.on_rpc_error(peer_id, request_id);
             }
-            RPCErrorResponse::Success(response) => {
-                match response {
-                    RPCResponse::Hello(hello_message) => {
-                        self.message_processor
-                            .on_hello_response(peer_id, hello_message);
-                    }
-                    RPCResponse::BeaconBlocks(response) => {
-                        match self.decode_beacon_blocks(&response) {
-                            Ok(beacon_blocks) => {
-                                self.message_processor.on_beacon_blocks_response(
-                                    peer_id,
-                                    request_id,
-                                    beacon_blocks,
-                                );
-                            }
-                            Err(e) => {
-                                // TODO: Down-vote Peer
-                                warn!(self.log, "Peer sent invalid BEACON_BLOCKS response";"peer" => format!("{:?}", peer_id), "error" => format!("{:?}", e));
-                            }
+            RPCErrorResponse::Success(response) => match response {
+                RPCResponse::Status(status_message) => {
+                    self.message_processor
+                        .on_status_response(peer_id, status_message);
+                }
+                RPCResponse::BlocksByRange(response) => {
+                    match self.decode_beacon_blocks(&response) {
+                        Ok(beacon_blocks) => {
+                            self.message_processor.on_blocks_by_range_response(
+                                peer_id,
+                                request_id,
+                                beacon_blocks,
+                            );
                         }
-                    }
-                    RPCResponse::RecentBeaconBlocks(response) => {
-                        match self.decode_beacon_blocks(&response) {
-                            Ok(beacon_blocks) => {
-                                self.message_processor.on_recent_beacon_blocks_response(
-                                    peer_id,
-                                    request_id,
-                                    beacon_blocks,
-                                );
-                            }
-                            Err(e) => {
-                                // TODO: Down-vote Peer
-                                warn!(self.log, "Peer sent invalid BEACON_BLOCKS response";"peer" => format!("{:?}", peer_id), "error" => format!("{:?}", e));
-                            }
+                        Err(e) => {
+                            self.report_peer(peer_id.clone(), PeerAction::RpcError);
+                            warn!(self.log, "Peer sent invalid BLOCKS_BY_RANGE response";"peer" => format!("{:?}", peer_id), "error" => format!("{:?}", e));
+                            self.message_processor.on_rpc_error(peer_id, request_id);
                         }
                     }
                 }
-            }
+                RPCResponse::BlocksByRoot(response) => match self.decode_beacon_blocks(&response) {
+                    Ok(beacon_blocks) => {
+                        self.message_processor.on_blocks_by_root_response(
+                            peer_id,
+                            request_id,
+                            beacon_blocks,
+                        );
+                    }
+                    Err(e) => {
+                        self.report_peer(peer_id.clone(), PeerAction::RpcError);
+                        warn!(self.log, "Peer sent invalid BLOCKS_BY_ROOT response";"peer" => format!("{:?}", peer_id), "error" => format!("{:?}", e));
+                        self.message_processor.on_rpc_error(peer_id, request_id);
+                    }
+                },
+                // pongs and metadata are processed by the libp2p behaviour
+                RPCResponse::Pong(_) | RPCResponse::MetaData(_) => {}
+            },
         }
     }
 
     /// Handle various RPC errors
     fn handle_rpc_error(&mut self, peer_id: PeerId, request_id: RequestId, error: RPCError) {
-        //TODO: Handle error correctly
         warn!(self.log, "RPC Error"; "Peer" => format!("{:?}", peer_id), "request_id" => format!("{}", request_id), "Error" => format!("{:?}", error));
+
+        let action = match error {
+            RPCError::StreamTimeout => PeerAction::SlowResponse,
+            _ => PeerAction::RpcError,
+        };
+        self.report_peer(peer_id.clone(), action);
+
+        // rate limited requests are inbound, all other errors relate to our outbound requests.
+        match error {
+            RPCError::RateLimited => {}
+            _ => self.message_processor.on_rpc_error(peer_id, request_id),
+        }
     }
 
     /// Handle RPC messages
     fn handle_gossip(&mut self, id: String, peer_id: PeerId, gossip_message: PubsubMessage) {
         match gossip_message {
             PubsubMessage::Block(message) => match self.decode_gossip_block(message) {
-                Ok(block) => {
-                    let should_forward_on = self
-                        .message_processor
-                        .on_block_gossip(peer_id.clone(), block);
-                    // TODO: Apply more sophisticated validation and decoding logic
-                    if should_forward_on {
-                        self.propagate_message(id, peer_id.clone());
-                    }
-                }
+                // the block is propagated by the beacon processor once it has been verified
+                Ok(block) => self.message_processor.on_block_gossip(id, peer_id, block),
                 Err(e) => {
+                    self.report_peer(peer_id.clone(), PeerAction::InvalidGossip);
                     debug!(self.log, "Invalid gossiped beacon block"; "peer_id" => format!("{}", peer_id), "Error" => format!("{:?}", e));
                 }
             },
@@ -227,42 +277,66 @@ impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
                         .on_attestation_gossip(peer_id, attestation);
                 }
                 Err(e) => {
+                    self.report_peer(peer_id.clone(), PeerAction::InvalidGossip);
                     debug!(self.log, "Invalid gossiped attestation"; "peer_id" => format!("{}", peer_id), "Error" => format!("{:?}", e));
                 }
             },
+            PubsubMessage::AggregateAndProof(message) => {
+                match self.decode_gossip_aggregate_and_proof(message) {
+                    // the aggregate is propagated by the beacon processor once it has been
+                    // verified
+                    Ok(aggregate_and_proof) => {
+                        self.message_processor
+                            .on_aggregate_gossip(id, peer_id, aggregate_and_proof)
+                    }
+                    Err(e) => {
+                        self.report_peer(peer_id.clone(), PeerAction::InvalidGossip);
+                        debug!(self.log, "Invalid gossiped aggregate and proof"; "peer_id" => format!("{}", peer_id), "Error" => format!("{:?}", e));
+                    }
+                }
+            }
             PubsubMessage::VoluntaryExit(message) => match self.decode_gossip_exit(message) {
-                Ok(_exit) => {
-                    // TODO: Apply more sophisticated validation and decoding logic
-                    self.propagate_message(id, peer_id.clone());
-                    // TODO: Handle exits
-                    debug!(self.log, "Received a voluntary exit"; "peer_id" => format!("{}", peer_id) );
+                Ok(exit) => {
+                    let should_forward_on = self
+                        .message_processor
+                        .on_voluntary_exit_gossip(peer_id.clone(), exit);
+                    if should_forward_on {
+                        self.propagate_message(id, peer_id);
+                    }
                 }
                 Err(e) => {
+                    self.report_peer(peer_id.clone(), PeerAction::InvalidGossip);
                     debug!(self.log, "Invalid gossiped exit"; "peer_id" => format!("{}", peer_id), "Error" => format!("{:?}", e));
                 }
             },
             PubsubMessage::ProposerSlashing(message) => {
                 match self.decode_gossip_proposer_slashing(message) {
-                    Ok(_slashing) => {
-                        // TODO: Apply more sophisticated validation and decoding logic
-                        self.propagate_message(id, peer_id.clone());
-                        // TODO: Handle proposer slashings
-                        debug!(self.log, "Received a proposer slashing"; "peer_id" => format!("{}", peer_id) );
+                    Ok(slashing) => {
+                        let should_forward_on = self
+                            .message_processor
+                            .on_proposer_slashing_gossip(peer_id.clone(), slashing);
+                        if should_forward_on {
+                            self.propagate_message(id, peer_id);
+                        }
                     }
                     Err(e) => {
+                        self.report_peer(peer_id.clone(), PeerAction::InvalidGossip);
                         debug!(self.log, "Invalid gossiped proposer slashing"; "peer_id" => format!("{}", peer_id), "Error" => format!("{:?}", e));
                     }
                 }
             }
             PubsubMessage::AttesterSlashing(message) => {
                 match self.decode_gossip_attestation_slashing(message) {
-                    Ok(_slashing) => {
-                        // TODO: Apply more sophisticated validation and decoding logic
-                        self.propagate_message(id, peer_id.clone());
-                        // TODO: Handle attester slashings
-                        debug!(self.log, "Received an attester slashing"; "peer_id" => format!("{}", peer_id) );
+                    Ok(slashing) => {
+                        let should_forward_on = self
+                            .message_processor
+                            .on_attester_slashing_gossip(peer_id.clone(), slashing);
+                        if should_forward_on {
+                            self.propagate_message(id, peer_id);
+                        }
                     }
                     Err(e) => {
+                        self.report_peer(peer_id.clone(), PeerAction::InvalidGossip);
                         debug!(self.log, "Invalid gossiped attester slashing"; "peer_id" => format!("{}", peer_id), "Error" => format!("{:?}", e));
                     }
                 }
@@ -274,6 +348,18 @@ impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
         }
     }
 
+    /// Informs the network service of the behaviour of a peer, adjusting its score.
+    fn report_peer(&mut self, peer_id: PeerId, action: PeerAction) {
+        self.network_send
+            .try_send(NetworkMessage::ReportPeer { peer_id, action })
+            .unwrap_or_else(|_| {
+                warn!(
+                    self.log,
+                    "Could not send peer report to the network service"
+                )
+            });
+    }
+
     /// Informs the network service that the message should be forwarded to other peers.
     fn propagate_message(&mut self, message_id: String, propagation_source: PeerId) {
         self.network_send
@@ -315,6 +401,14 @@ impl<T: BeaconChainTypes + 'static> MessageHandler<T> {
         Attestation::from_ssz_bytes(&beacon_block)
     }
 
+    fn decode_gossip_aggregate_and_proof(
+        &self,
+        aggregate_and_proof: Vec<u8>,
+    ) -> Result<AggregateAndProof<T::EthSpec>, DecodeError> {
+        //TODO: Apply verification before decoding.
+        AggregateAndProof::from_ssz_bytes(&aggregate_and_proof)
+    }
+
     fn decode_gossip_exit(&self, voluntary_exit: Vec<u8>) -> Result<VoluntaryExit, DecodeError> {
         //TODO: Apply verification before decoding.
         VoluntaryExit::from_ssz_bytes(&voluntary_exit)
@@ -0,0 +1,43 @@
+pub use lighthouse_metrics::*;
+
+lazy_static! {
+    /*
+     * Beacon processor
+     */
+    pub static ref BEACON_PROCESSOR_WORKERS_ACTIVE_TOTAL: Result<IntGauge> = try_create_int_gauge(
+        "beacon_processor_workers_active_total",
+        "Count of beacon processor workers that are presently processing work"
+    );
+    pub static ref BEACON_PROCESSOR_GOSSIP_BLOCK_QUEUE_TOTAL: Result<IntGauge> = try_create_int_gauge(
+        "beacon_processor_gossip_block_queue_total",
+        "Count of gossip blocks waiting to be processed"
+    );
+    pub static ref BEACON_PROCESSOR_GOSSIP_BLOCK_DROPPED_TOTAL: Result<IntCounter> = try_create_int_counter(
+        "beacon_processor_gossip_block_dropped_total",
+        "Count of gossip blocks dropped because the queue was full"
+    );
+    pub static ref BEACON_PROCESSOR_AGGREGATE_QUEUE_TOTAL: Result<IntGauge> = try_create_int_gauge(
+        "beacon_processor_aggregate_queue_total",
+        "Count of gossip aggregates waiting to be processed"
+    );
+    pub static ref BEACON_PROCESSOR_AGGREGATE_DROPPED_TOTAL: Result<IntCounter> = try_create_int_counter(
+        "beacon_processor_aggregate_dropped_total",
+        "Count of gossip aggregates dropped because the queue was full"
+    );
+    pub static ref BEACON_PROCESSOR_UNAGGREGATED_ATTESTATION_QUEUE_TOTAL: Result<IntGauge> = try_create_int_gauge(
+        "beacon_processor_unaggregated_attestation_queue_total",
+        "Count of gossip unaggregated attestations waiting to be processed"
+    );
+    pub static ref BEACON_PROCESSOR_UNAGGREGATED_ATTESTATION_DROPPED_TOTAL: Result<IntCounter> = try_create_int_counter(
+        "beacon_processor_unaggregated_attestation_dropped_total",
+        "Count of gossip unaggregated attestations dropped because the queue was full"
+    );
+    pub static ref BEACON_PROCESSOR_RPC_BLOCK_QUEUE_TOTAL: Result<IntGauge> = try_create_int_gauge(
+        "beacon_processor_rpc_block_queue_total",
+        "Count of blocks from sync waiting to be processed"
+    );
+    pub static ref BEACON_PROCESSOR_RPC_BLOCK_DROPPED_TOTAL: Result<IntCounter> = try_create_int_counter(
+        "beacon_processor_rpc_block_dropped_total",
+        "Count of blocks from sync dropped because the queue was full"
+    );
+}
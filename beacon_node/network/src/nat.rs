@@ -0,0 +1,714 @@
+//! Maps the libp2p and discovery ports on the local gateway using UPnP or NAT-PMP, so that peers
+//! outside the local network can reach this node.
+//!
+//! The mappings are maintained on a separate thread, as gateway discovery and the mapping requests
+//! are blocking. Once the ports are mapped, the external sockets are sent to the network service
+//! so that they can be advertised in the local ENR.
+//!
+//! Mappings are leased rather than permanent, so that they lapse if the node stops without
+//! removing them. They are renewed whilst the node runs, and removed when the network service
+//! stops.
+
+use crate::NetworkMessage;
+use eth2_libp2p::NetworkConfig;
+use igd::{Gateway, PortMappingProtocol, SearchOptions};
+use slog::{debug, info, warn};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// The lease duration requested for port mappings. Mappings are renewed after half of the lease
+/// has elapsed.
+const MAPPING_LEASE_DURATION: Duration = Duration::from_secs(3600);
+/// The port on which a NAT-PMP gateway listens for requests.
+const NAT_PMP_PORT: u16 = 5351;
+/// The time to wait for a NAT-PMP gateway to respond to a request.
+const NAT_PMP_TIMEOUT: Duration = Duration::from_millis(500);
+/// The version of NAT-PMP requests and responses.
+const NAT_PMP_VERSION: u8 = 0;
+/// The opcode of a NAT-PMP request for the external address of the gateway.
+const NAT_PMP_OP_EXTERNAL_ADDRESS: u8 = 0;
+/// Added to the opcode of a NAT-PMP request to give the opcode of its response.
+const NAT_PMP_RESPONSE_OFFSET: u8 = 128;
+/// The number of times a NAT-PMP request is sent before the gateway is deemed unresponsive.
+const NAT_PMP_ATTEMPTS: u32 = 3;
+/// The file listing the kernel's IPv4 routes, from which the default gateway is read.
+const ROUTE_TABLE_PATH: &str = "/proc/net/route";
+
+/// The transport protocol of a port mapping.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Protocol {
+    Tcp,
+    Udp,
+}
+
+impl Protocol {
+    fn description(self) -> &'static str {
+        match self {
+            Protocol::Tcp => "lighthouse-tcp",
+            Protocol::Udp => "lighthouse-udp",
+        }
+    }
+}
+
+/// The ports to map on the local gateway, and the methods with which to map them.
+#[derive(Debug, Clone)]
+pub struct NatConfig {
+    /// The local TCP port that libp2p listens on.
+    tcp_port: u16,
+    /// The local UDP port that discovery listens on.
+    udp_port: u16,
+    /// Whether the external address should be advertised in the local ENR. This is disabled when
+    /// the ENR address is pinned by the user.
+    update_enr: bool,
+    /// Whether to search for a UPnP gateway.
+    upnp_enabled: bool,
+    /// Whether to use NAT-PMP, if no UPnP gateway is used.
+    nat_pmp_enabled: bool,
+}
+
+impl NatConfig {
+    /// Returns `true` if any method of mapping ports is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.upnp_enabled || self.nat_pmp_enabled
+    }
+}
+
+impl From<&NetworkConfig> for NatConfig {
+    fn from(config: &NetworkConfig) -> Self {
+        NatConfig {
+            tcp_port: config.libp2p_port,
+            udp_port: config.discovery_port,
+            update_enr: !config.enr_address_pinned,
+            upnp_enabled: config.upnp_enabled,
+            nat_pmp_enabled: config.nat_pmp_enabled,
+        }
+    }
+}
+
+/// A gateway on which ports may be mapped.
+trait PortMapper {
+    /// The name of the mapping method, for logging.
+    fn name(&self) -> &'static str;
+
+    /// Returns the external address of the gateway.
+    fn external_ip(&self) -> Result<Ipv4Addr, String>;
+
+    /// Maps `external_port` on the gateway to `internal_port` on this host for `lease`, returning
+    /// the external port that was mapped (which may differ from the one requested).
+    fn add_port(
+        &self,
+        protocol: Protocol,
+        internal_port: u16,
+        external_port: u16,
+        lease: Duration,
+    ) -> Result<u16, String>;
+
+    /// Removes the mapping of `external_port` on the gateway to `internal_port` on this host.
+    fn remove_port(
+        &self,
+        protocol: Protocol,
+        internal_port: u16,
+        external_port: u16,
+    ) -> Result<(), String>;
+}
+
+/// Searches for a gateway using the methods enabled in `config`, then maps its TCP and UDP ports
+/// to the same external ports. Unless the ENR address is pinned, sends
+/// `NetworkMessage::NatMappingEstablished` with the sockets that were mapped.
+///
+/// The mappings are renewed until a message is received on `shutdown` (or its sender is
+/// dropped), whereupon they are removed.
+///
+/// This function blocks and should be run on its own thread.
+pub fn construct_nat_mappings(
+    config: NatConfig,
+    network_send: mpsc::UnboundedSender<NetworkMessage>,
+    shutdown: Receiver<()>,
+    log: slog::Logger,
+) {
+    let mapper: Box<dyn PortMapper> = match find_gateway(&config, &log) {
+        Some(mapper) => mapper,
+        None => return,
+    };
+
+    maintain_mappings(
+        &*mapper,
+        &config,
+        MAPPING_LEASE_DURATION,
+        &network_send,
+        &shutdown,
+        &log,
+    );
+}
+
+/// Returns a gateway using the first of the methods enabled in `config` that succeeds.
+fn find_gateway(config: &NatConfig, log: &slog::Logger) -> Option<Box<dyn PortMapper>> {
+    if config.upnp_enabled {
+        debug!(log, "UPnP searching for a gateway");
+        match UPnPGateway::search() {
+            Ok(gateway) => return Some(Box::new(gateway)),
+            Err(e) => debug!(log, "UPnP gateway not found"; "error" => e),
+        }
+    }
+
+    if config.nat_pmp_enabled {
+        match std::fs::read_to_string(ROUTE_TABLE_PATH)
+            .ok()
+            .and_then(|routes| parse_default_gateway(&routes))
+        {
+            Some(ip) => {
+                return Some(Box::new(NatPmpGateway {
+                    addr: SocketAddrV4::new(ip, NAT_PMP_PORT),
+                }))
+            }
+            None => warn!(log, "NAT-PMP could not determine the default gateway"),
+        }
+    }
+
+    None
+}
+
+/// Maps the ports of `config` on `mapper`, renewing the mappings every half `lease` until
+/// `shutdown` receives a message or is disconnected, then removes them.
+fn maintain_mappings(
+    mapper: &dyn PortMapper,
+    config: &NatConfig,
+    lease: Duration,
+    network_send: &mpsc::UnboundedSender<NetworkMessage>,
+    shutdown: &Receiver<()>,
+    log: &slog::Logger,
+) {
+    let external_ip = match mapper.external_ip() {
+        Ok(ip) => ip,
+        Err(e) => {
+            warn!(log, "Could not determine the external address";
+                "method" => mapper.name(),
+                "error" => e,
+            );
+            return;
+        }
+    };
+
+    // The internal and external port of each mapping which was established.
+    let mappings = [
+        (Protocol::Tcp, config.tcp_port),
+        (Protocol::Udp, config.udp_port),
+    ]
+    .iter()
+    .filter_map(
+        |&(protocol, port)| match mapper.add_port(protocol, port, port, lease) {
+            Ok(external_port) => Some((protocol, port, external_port)),
+            Err(e) => {
+                warn!(log, "Could not map port";
+                    "method" => mapper.name(),
+                    "protocol" => protocol.description(),
+                    "port" => port,
+                    "error" => e,
+                );
+                None
+            }
+        },
+    )
+    .collect::<Vec<_>>();
+
+    if mappings.is_empty() {
+        return;
+    }
+
+    let external_socket = |protocol| {
+        mappings
+            .iter()
+            .find(|(mapped, _, _)| *mapped == protocol)
+            .map(|(_, _, external_port)| SocketAddr::new(IpAddr::V4(external_ip), *external_port))
+    };
+    let tcp_socket = external_socket(Protocol::Tcp);
+    let udp_socket = external_socket(Protocol::Udp);
+
+    info!(log, "Port mappings established";
+        "method" => mapper.name(),
+        "external_ip" => format!("{}", external_ip),
+        "tcp_port" => tcp_socket.map(|socket| socket.port()),
+        "udp_port" => udp_socket.map(|socket| socket.port()),
+    );
+
+    if config.update_enr
+        && network_send
+            .clone()
+            .try_send(NetworkMessage::NatMappingEstablished {
+                tcp_socket,
+                udp_socket,
+            })
+            .is_err()
+    {
+        warn!(
+            log,
+            "Could not send the port mappings to the network service"
+        );
+    }
+
+    while let Err(RecvTimeoutError::Timeout) = shutdown.recv_timeout(lease / 2) {
+        for (protocol, internal_port, external_port) in &mappings {
+            if let Err(e) = mapper.add_port(*protocol, *internal_port, *external_port, lease) {
+                warn!(log, "Could not renew port mapping";
+                    "method" => mapper.name(),
+                    "protocol" => protocol.description(),
+                    "port" => external_port,
+                    "error" => e,
+                );
+            }
+        }
+    }
+
+    for (protocol, internal_port, external_port) in &mappings {
+        match mapper.remove_port(*protocol, *internal_port, *external_port) {
+            Ok(()) => debug!(log, "Removed port mapping";
+                "method" => mapper.name(),
+                "protocol" => protocol.description(),
+                "port" => external_port,
+            ),
+            Err(e) => warn!(log, "Could not remove port mapping";
+                "method" => mapper.name(),
+                "protocol" => protocol.description(),
+                "port" => external_port,
+                "error" => e,
+            ),
+        }
+    }
+}
+
+/// A UPnP internet gateway device.
+struct UPnPGateway {
+    gateway: Gateway,
+    /// The local address of the interface used to reach the gateway, to which ports are mapped.
+    local_ip: Ipv4Addr,
+}
+
+impl UPnPGateway {
+    fn search() -> Result<Self, String> {
+        let gateway =
+            igd::search_gateway(SearchOptions::default()).map_err(|e| format!("{}", e))?;
+        let local_ip = local_ip_towards(&gateway).ok_or_else(|| {
+            "Could not determine the local address used to reach the gateway".to_string()
+        })?;
+        Ok(Self { gateway, local_ip })
+    }
+}
+
+impl PortMapper for UPnPGateway {
+    fn name(&self) -> &'static str {
+        "UPnP"
+    }
+
+    fn external_ip(&self) -> Result<Ipv4Addr, String> {
+        self.gateway.get_external_ip().map_err(|e| format!("{}", e))
+    }
+
+    fn add_port(
+        &self,
+        protocol: Protocol,
+        internal_port: u16,
+        external_port: u16,
+        lease: Duration,
+    ) -> Result<u16, String> {
+        self.gateway
+            .add_port(
+                upnp_protocol(protocol),
+                external_port,
+                SocketAddrV4::new(self.local_ip, internal_port),
+                lease.as_secs() as u32,
+                protocol.description(),
+            )
+            .map(|()| external_port)
+            .map_err(|e| format!("{}", e))
+    }
+
+    fn remove_port(
+        &self,
+        protocol: Protocol,
+        _internal_port: u16,
+        external_port: u16,
+    ) -> Result<(), String> {
+        self.gateway
+            .remove_port(upnp_protocol(protocol), external_port)
+            .map_err(|e| format!("{}", e))
+    }
+}
+
+fn upnp_protocol(protocol: Protocol) -> PortMappingProtocol {
+    match protocol {
+        Protocol::Tcp => PortMappingProtocol::TCP,
+        Protocol::Udp => PortMappingProtocol::UDP,
+    }
+}
+
+/// Returns the local IPv4 address of the interface used to reach `gateway`.
+///
+/// Connecting a UDP socket sends no packets, but selects the local address the operating system
+/// routes to the gateway from.
+fn local_ip_towards(gateway: &Gateway) -> Option<Ipv4Addr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect(gateway.addr).ok()?;
+    match socket.local_addr().ok()?.ip() {
+        IpAddr::V4(ip) if !ip.is_unspecified() => Some(ip),
+        _ => None,
+    }
+}
+
+/// A NAT-PMP gateway, as specified by RFC 6886.
+struct NatPmpGateway {
+    addr: SocketAddrV4,
+}
+
+impl NatPmpGateway {
+    /// Sends `request` to the gateway, returning the response.
+    ///
+    /// Requests are resent if no response is received, since they are sent over UDP.
+    fn request(&self, request: &[u8]) -> Result<Vec<u8>, String> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+            .and_then(|socket| {
+                socket.connect(self.addr)?;
+                socket.set_read_timeout(Some(NAT_PMP_TIMEOUT))?;
+                Ok(socket)
+            })
+            .map_err(|e| format!("Unable to open socket: {}", e))?;
+
+        let mut buf = [0; 16];
+        for _ in 0..NAT_PMP_ATTEMPTS {
+            socket
+                .send(request)
+                .map_err(|e| format!("Unable to send request: {}", e))?;
+            if let Ok(len) = socket.recv(&mut buf) {
+                return Ok(buf[..len].to_vec());
+            }
+        }
+
+        Err("No response from the gateway".to_string())
+    }
+}
+
+impl PortMapper for NatPmpGateway {
+    fn name(&self) -> &'static str {
+        "NAT-PMP"
+    }
+
+    fn external_ip(&self) -> Result<Ipv4Addr, String> {
+        let response = self.request(&[NAT_PMP_VERSION, NAT_PMP_OP_EXTERNAL_ADDRESS])?;
+        parse_nat_pmp_external_ip(&response)
+    }
+
+    fn add_port(
+        &self,
+        protocol: Protocol,
+        internal_port: u16,
+        external_port: u16,
+        lease: Duration,
+    ) -> Result<u16, String> {
+        let request = nat_pmp_mapping_request(
+            protocol,
+            internal_port,
+            external_port,
+            lease.as_secs() as u32,
+        );
+        let response = self.request(&request)?;
+        parse_nat_pmp_mapping(protocol, &response)
+    }
+
+    fn remove_port(
+        &self,
+        protocol: Protocol,
+        internal_port: u16,
+        _external_port: u16,
+    ) -> Result<(), String> {
+        // A mapping is removed by requesting a lifetime of zero, with an external port of zero.
+        let request = nat_pmp_mapping_request(protocol, internal_port, 0, 0);
+        let response = self.request(&request)?;
+        parse_nat_pmp_mapping(protocol, &response).map(|_| ())
+    }
+}
+
+fn nat_pmp_mapping_opcode(protocol: Protocol) -> u8 {
+    match protocol {
+        Protocol::Udp => 1,
+        Protocol::Tcp => 2,
+    }
+}
+
+/// Returns a request to map `external_port` on the gateway to `internal_port` on this host for
+/// `lifetime` seconds.
+fn nat_pmp_mapping_request(
+    protocol: Protocol,
+    internal_port: u16,
+    external_port: u16,
+    lifetime: u32,
+) -> [u8; 12] {
+    let mut request = [0; 12];
+    request[0] = NAT_PMP_VERSION;
+    request[1] = nat_pmp_mapping_opcode(protocol);
+    request[4..6].copy_from_slice(&internal_port.to_be_bytes());
+    request[6..8].copy_from_slice(&external_port.to_be_bytes());
+    request[8..12].copy_from_slice(&lifetime.to_be_bytes());
+    request
+}
+
+/// Checks the header of a response to a request with `opcode`, returning an error if the request
+/// failed.
+fn check_nat_pmp_response(response: &[u8], opcode: u8, len: usize) -> Result<(), String> {
+    if response.len() < len {
+        return Err(format!("Response too short: {} bytes", response.len()));
+    }
+    if response[0] != NAT_PMP_VERSION || response[1] != opcode + NAT_PMP_RESPONSE_OFFSET {
+        return Err(format!(
+            "Unexpected response version {} and opcode {}",
+            response[0], response[1]
+        ));
+    }
+    match u16::from_be_bytes([response[2], response[3]]) {
+        0 => Ok(()),
+        code => Err(format!("Request failed with result code {}", code)),
+    }
+}
+
+/// Returns the external address from a response to an external address request.
+fn parse_nat_pmp_external_ip(response: &[u8]) -> Result<Ipv4Addr, String> {
+    check_nat_pmp_response(response, NAT_PMP_OP_EXTERNAL_ADDRESS, 12)?;
+    Ok(Ipv4Addr::new(
+        response[8],
+        response[9],
+        response[10],
+        response[11],
+    ))
+}
+
+/// Returns the mapped external port from a response to a mapping request.
+fn parse_nat_pmp_mapping(protocol: Protocol, response: &[u8]) -> Result<u16, String> {
+    check_nat_pmp_response(response, nat_pmp_mapping_opcode(protocol), 16)?;
+    Ok(u16::from_be_bytes([response[10], response[11]]))
+}
+
+/// Returns the gateway of the default route from the contents of `/proc/net/route`.
+///
+/// Addresses are listed as the hexadecimal value of their in-memory (network order) bytes, read as
+/// a little-endian integer.
+fn parse_default_gateway(routes: &str) -> Option<Ipv4Addr> {
+    routes.lines().skip(1).find_map(|line| {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        match (fields.get(1), fields.get(2)) {
+            (Some(&"00000000"), Some(gateway)) => u32::from_str_radix(gateway, 16)
+                .ok()
+                .map(|gateway| Ipv4Addr::from(gateway.to_le_bytes()))
+                .filter(|gateway| !gateway.is_unspecified()),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::Stream;
+    use std::cell::RefCell;
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    /// A gateway which records the requests made of it.
+    struct MockMapper {
+        fail_udp: bool,
+        requests: RefCell<Vec<(&'static str, Protocol)>>,
+    }
+
+    impl MockMapper {
+        fn new(fail_udp: bool) -> Self {
+            Self {
+                fail_udp,
+                requests: RefCell::new(vec![]),
+            }
+        }
+
+        fn count(&self, request: &str) -> usize {
+            self.requests
+                .borrow()
+                .iter()
+                .filter(|(made, _)| *made == request)
+                .count()
+        }
+    }
+
+    impl PortMapper for MockMapper {
+        fn name(&self) -> &'static str {
+            "mock"
+        }
+
+        fn external_ip(&self) -> Result<Ipv4Addr, String> {
+            Ok(Ipv4Addr::new(203, 0, 113, 1))
+        }
+
+        fn add_port(
+            &self,
+            protocol: Protocol,
+            _internal_port: u16,
+            external_port: u16,
+            _lease: Duration,
+        ) -> Result<u16, String> {
+            self.requests.borrow_mut().push(("add", protocol));
+            if self.fail_udp && protocol == Protocol::Udp {
+                Err("refused".to_string())
+            } else {
+                Ok(external_port)
+            }
+        }
+
+        fn remove_port(
+            &self,
+            protocol: Protocol,
+            _internal_port: u16,
+            _external_port: u16,
+        ) -> Result<(), String> {
+            self.requests.borrow_mut().push(("remove", protocol));
+            Ok(())
+        }
+    }
+
+    fn config(update_enr: bool) -> NatConfig {
+        NatConfig {
+            tcp_port: 9000,
+            udp_port: 9001,
+            update_enr,
+            upnp_enabled: true,
+            nat_pmp_enabled: false,
+        }
+    }
+
+    fn null_logger() -> slog::Logger {
+        slog::Logger::root(slog::Discard, slog::o!())
+    }
+
+    #[test]
+    fn nat_is_opt_in() {
+        assert!(!NatConfig::from(&NetworkConfig::default()).is_enabled());
+    }
+
+    #[test]
+    fn mappings_are_advertised_renewed_and_removed() {
+        let mapper = MockMapper::new(false);
+        let (network_send, network_recv) = mpsc::unbounded_channel();
+        let (shutdown_send, shutdown_recv) = channel();
+
+        let shutdown = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            shutdown_send.send(()).unwrap();
+        });
+        maintain_mappings(
+            &mapper,
+            &config(true),
+            Duration::from_millis(20),
+            &network_send,
+            &shutdown_recv,
+            &null_logger(),
+        );
+        shutdown.join().unwrap();
+
+        assert!(
+            mapper.count("add") > 2,
+            "mappings should be renewed before their lease expires"
+        );
+        let requests = mapper.requests.borrow();
+        assert_eq!(
+            &requests[requests.len() - 2..],
+            &[("remove", Protocol::Tcp), ("remove", Protocol::Udp)][..],
+            "mappings should be removed on shutdown"
+        );
+
+        drop(network_send);
+        match network_recv.wait().next() {
+            Some(Ok(NetworkMessage::NatMappingEstablished {
+                tcp_socket,
+                udp_socket,
+            })) => {
+                assert_eq!(tcp_socket, Some("203.0.113.1:9000".parse().unwrap()));
+                assert_eq!(udp_socket, Some("203.0.113.1:9001".parse().unwrap()));
+            }
+            _ => panic!("the external sockets should be sent to the network service"),
+        }
+    }
+
+    #[test]
+    fn only_established_mappings_are_removed() {
+        let mapper = MockMapper::new(true);
+        let (network_send, network_recv) = mpsc::unbounded_channel();
+        let (shutdown_send, shutdown_recv) = channel();
+
+        // The shutdown sender is dropped, so the mappings are removed without being renewed.
+        drop(shutdown_send);
+        maintain_mappings(
+            &mapper,
+            &config(false),
+            MAPPING_LEASE_DURATION,
+            &network_send,
+            &shutdown_recv,
+            &null_logger(),
+        );
+
+        assert_eq!(
+            *mapper.requests.borrow(),
+            vec![
+                ("add", Protocol::Tcp),
+                ("add", Protocol::Udp),
+                ("remove", Protocol::Tcp)
+            ]
+        );
+
+        drop(network_send);
+        assert!(
+            network_recv.wait().next().is_none(),
+            "the sockets should not be sent when the ENR address is pinned"
+        );
+    }
+
+    #[test]
+    fn nat_pmp_requests() {
+        assert_eq!(
+            nat_pmp_mapping_request(Protocol::Tcp, 9000, 9001, 3600),
+            [0, 2, 0, 0, 0x23, 0x28, 0x23, 0x29, 0, 0, 0x0e, 0x10]
+        );
+        assert_eq!(
+            nat_pmp_mapping_request(Protocol::Udp, 9000, 0, 0),
+            [0, 1, 0, 0, 0x23, 0x28, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn nat_pmp_responses() {
+        let external_ip = [0, 128, 0, 0, 0, 0, 0, 1, 203, 0, 113, 1];
+        assert_eq!(
+            parse_nat_pmp_external_ip(&external_ip),
+            Ok(Ipv4Addr::new(203, 0, 113, 1))
+        );
+        assert!(parse_nat_pmp_external_ip(&external_ip[..8]).is_err());
+
+        let mapping = [
+            0, 130, 0, 0, 0, 0, 0, 1, 0x23, 0x28, 0x23, 0x2a, 0, 0, 0x0e, 0x10,
+        ];
+        assert_eq!(parse_nat_pmp_mapping(Protocol::Tcp, &mapping), Ok(9002));
+        assert!(
+            parse_nat_pmp_mapping(Protocol::Udp, &mapping).is_err(),
+            "the opcode should match the protocol"
+        );
+
+        let refused = [0, 130, 0, 2, 0, 0, 0, 1, 0x23, 0x28, 0, 0, 0, 0, 0, 0];
+        assert!(parse_nat_pmp_mapping(Protocol::Tcp, &refused).is_err());
+    }
+
+    #[test]
+    fn default_gateway() {
+        let routes = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\n\
+                      eth0\t0000A8C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\n\
+                      eth0\t00000000\t0101A8C0\t0003\t0\t0\t0\t00000000\n";
+        assert_eq!(
+            parse_default_gateway(routes),
+            Some(Ipv4Addr::new(192, 168, 1, 1))
+        );
+        assert_eq!(parse_default_gateway("Iface\tDestination\tGateway\n"), None);
+    }
+}
@@ -0,0 +1,32 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Counts the messages and items of work which have been passed between the stages of a node's
+/// network stack (the message handler, the beacon processor and the sync manager), but which have
+/// not yet been processed.
+///
+/// Each item is counted before it is sent to the next stage, and is only marked as done once the
+/// work it caused has itself been counted. The count is therefore zero only when the node has
+/// nothing left to do until it receives another message, which allows a simulated network to be
+/// stepped one round at a time.
+#[derive(Debug, Clone, Default)]
+pub struct PendingWork(Arc<AtomicUsize>);
+
+impl PendingWork {
+    /// Returns `true` if no work is in flight.
+    pub fn is_idle(&self) -> bool {
+        self.0.load(Ordering::SeqCst) == 0
+    }
+
+    /// Counts an item of work which is about to be sent to a stage.
+    pub(crate) fn add(&self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Marks `count` items of work as processed (or dropped).
+    pub(crate) fn done(&self, count: usize) {
+        if count > 0 {
+            self.0.fetch_sub(count, Ordering::SeqCst);
+        }
+    }
+}
@@ -1,19 +1,30 @@
+use crate::attestation_service::{AttServiceMessage, AttestationService};
 use crate::error;
-use crate::message_handler::{HandlerMessage, MessageHandler};
+use crate::message_handler::{HandlerMessage, HandlerSend, MessageHandler};
+use crate::nat::{construct_nat_mappings, NatConfig};
+use crate::pending_work::PendingWork;
+use crate::sync::{ChainProgress, SyncProgress, SyncState};
 use crate::NetworkConfig;
 use beacon_chain::{BeaconChain, BeaconChainTypes};
 use core::marker::PhantomData;
 use eth2_libp2p::Service as LibP2PService;
-use eth2_libp2p::Topic;
-use eth2_libp2p::{Enr, Libp2pEvent, Multiaddr, PeerId, Swarm};
+use eth2_libp2p::{Enr, Libp2pEvent, Multiaddr, PeerId, PeerInfo, Swarm};
+use eth2_libp2p::{GossipKind, PeerAction};
 use eth2_libp2p::{PubsubMessage, RPCEvent};
 use futures::prelude::*;
 use futures::Stream;
-use parking_lot::Mutex;
-use slog::{debug, info, trace};
+use parking_lot::{Mutex, RwLock};
+use slog::{debug, info, trace, warn};
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::runtime::TaskExecutor;
 use tokio::sync::{mpsc, oneshot};
+use tokio::timer::Interval;
+use types::{Epoch, EthSpec, ForkDigest, ValidatorSubscription};
+
+/// The number of slots for which the ids of received gossip messages are remembered.
+const GOSSIP_DUPLICATE_CACHE_SLOTS: u32 = 4;
 
 /// Service that handles communication between internal services and the eth2_libp2p network service.
 pub struct Service<T: BeaconChainTypes> {
@@ -21,6 +32,13 @@ pub struct Service<T: BeaconChainTypes> {
     libp2p_port: u16,
     _libp2p_exit: oneshot::Sender<()>,
     _network_send: mpsc::UnboundedSender<NetworkMessage>,
+    /// Signals the thread maintaining the port mappings on the local gateway to remove them, and
+    /// its handle.
+    nat_task: Option<(std::sync::mpsc::Sender<()>, std::thread::JoinHandle<()>)>,
+    /// The sync state and the progress of each chain that is being range synced, updated by the
+    /// sync manager.
+    sync_progress: Arc<RwLock<SyncProgress>>,
+    log: slog::Logger,
     _phantom: PhantomData<T>,
 }
 
@@ -31,34 +49,74 @@ impl<T: BeaconChainTypes + 'static> Service<T> {
         executor: &TaskExecutor,
         network_log: slog::Logger,
     ) -> error::Result<(Arc<Self>, mpsc::UnboundedSender<NetworkMessage>)> {
+        let fork_digests = active_fork_digests(&beacon_chain);
+        let enr_fork_id = beacon_chain.enr_fork_id(current_epoch(&beacon_chain));
+
         // build the network channel
         let (network_send, network_recv) = mpsc::unbounded_channel::<NetworkMessage>();
+        let sync_progress = Arc::new(RwLock::new(SyncProgress::default()));
         // launch message handler thread
         let message_handler_send = MessageHandler::spawn(
-            beacon_chain,
+            beacon_chain.clone(),
             network_send.clone(),
+            sync_progress.clone(),
+            PendingWork::default(),
             executor,
             network_log.clone(),
         )?;
 
+        // remember gossip messages for long enough to drop the duplicates of a slot's attestations
+        let mut libp2p_config = config.clone();
+        libp2p_config.duplicate_cache_time =
+            Duration::from_millis(beacon_chain.spec.milliseconds_per_slot)
+                * GOSSIP_DUPLICATE_CACHE_SLOTS;
+
         // launch libp2p service
         let libp2p_service = Arc::new(Mutex::new(LibP2PService::new(
-            config.clone(),
+            libp2p_config,
+            enr_fork_id,
             network_log.clone(),
         )?));
+        libp2p_service
+            .lock()
+            .swarm
+            .update_fork_digests(fork_digests);
+
+        // map the listening ports on the local gateway, off the async runtime as the requests
+        // block. the mappings are removed when the service is dropped.
+        let nat_config = NatConfig::from(config);
+        let mut nat_task = None;
+        if nat_config.is_enabled() {
+            let nat_send = network_send.clone();
+            let nat_log = network_log.clone();
+            let (shutdown_send, shutdown_recv) = std::sync::mpsc::channel();
+            match std::thread::Builder::new()
+                .name("nat".into())
+                .spawn(move || construct_nat_mappings(nat_config, nat_send, shutdown_recv, nat_log))
+            {
+                Ok(handle) => nat_task = Some((shutdown_send, handle)),
+                Err(e) => {
+                    warn!(network_log, "Could not start the NAT task"; "error" => format!("{}", e))
+                }
+            }
+        }
 
         let libp2p_exit = spawn_service(
+            beacon_chain,
             libp2p_service.clone(),
             network_recv,
             message_handler_send,
             executor,
-            network_log,
+            network_log.clone(),
         )?;
         let network_service = Service {
             libp2p_service,
             libp2p_port: config.libp2p_port,
             _libp2p_exit: libp2p_exit,
             _network_send: network_send.clone(),
+            nat_task,
+            sync_progress,
+            log: network_log,
             _phantom: PhantomData,
         };
 
@@ -105,21 +163,70 @@ impl<T: BeaconChainTypes + 'static> Service<T> {
             .swarm
             .discovery()
             .connected_peer_set()
-            .iter()
+            .keys()
             .cloned()
             .collect()
     }
 
+    /// Returns the `PeerInfo` of each peer that is connected via libp2p, including its current
+    /// score.
+    pub fn connected_peer_info(&self) -> Vec<(PeerId, PeerInfo)> {
+        let libp2p_service = self.libp2p_service.lock();
+        let peer_manager = libp2p_service.swarm.peer_manager();
+
+        libp2p_service
+            .swarm
+            .discovery()
+            .connected_peer_set()
+            .iter()
+            .map(|(peer_id, info)| {
+                let mut info = info.clone();
+                info.score = peer_manager.score(peer_id);
+                (peer_id.clone(), info)
+            })
+            .collect()
+    }
+
+    /// Disconnects from `peer_id` and refuses any future connections from it.
+    pub fn ban_peer(&self, peer_id: PeerId) {
+        info!(self.log, "Banning peer"; "peer_id" => format!("{}", peer_id));
+        Swarm::ban_peer_id(&mut self.libp2p_service.lock().swarm, peer_id);
+    }
+
+    /// Returns the progress of each chain that is currently being range synced.
+    pub fn sync_progress(&self) -> Vec<ChainProgress> {
+        self.sync_progress.read().chains.clone()
+    }
+
+    /// Returns the current sync state of the node.
+    pub fn sync_state(&self) -> SyncState {
+        self.sync_progress.read().state
+    }
+
     /// Provides a reference to the underlying libp2p service.
     pub fn libp2p_service(&self) -> Arc<Mutex<LibP2PService>> {
         self.libp2p_service.clone()
     }
 }
 
-fn spawn_service(
+impl<T: BeaconChainTypes> Drop for Service<T> {
+    /// Removes the port mappings on the local gateway, waiting for the gateway to respond so that
+    /// they are not left in place if the process exits.
+    fn drop(&mut self) {
+        if let Some((shutdown, handle)) = self.nat_task.take() {
+            let _ = shutdown.send(());
+            if handle.join().is_err() {
+                warn!(self.log, "The NAT task panicked");
+            }
+        }
+    }
+}
+
+fn spawn_service<T: BeaconChainTypes + 'static>(
+    beacon_chain: Arc<BeaconChain<T>>,
     libp2p_service: Arc<Mutex<LibP2PService>>,
     network_recv: mpsc::UnboundedReceiver<NetworkMessage>,
-    message_handler_send: mpsc::UnboundedSender<HandlerMessage>,
+    message_handler_send: HandlerSend,
     executor: &TaskExecutor,
     log: slog::Logger,
 ) -> error::Result<tokio::sync::oneshot::Sender<()>> {
@@ -128,6 +235,7 @@ fn spawn_service(
     // spawn on the current executor
     executor.spawn(
         network_service(
+            beacon_chain,
             libp2p_service,
             network_recv,
             message_handler_send,
@@ -144,14 +252,84 @@ fn spawn_service(
     Ok(network_exit)
 }
 
+/// Returns the current epoch of `beacon_chain`. Prior to genesis, the genesis epoch is used.
+pub(crate) fn current_epoch<T: BeaconChainTypes>(beacon_chain: &BeaconChain<T>) -> Epoch {
+    beacon_chain.epoch().unwrap_or_else(|_| {
+        beacon_chain
+            .spec
+            .genesis_slot
+            .epoch(T::EthSpec::slots_per_epoch())
+    })
+}
+
+/// Returns the fork digests whose topics should be subscribed to in the current epoch, according
+/// to the head of `beacon_chain`.
+///
+/// The digest of the fork at the current epoch is always first. In the epochs either side of a
+/// fork boundary, the digest on the other side of the boundary is included too, so that messages
+/// from peers which transition at slightly different times are not missed.
+fn active_fork_digests<T: BeaconChainTypes>(beacon_chain: &BeaconChain<T>) -> Vec<ForkDigest> {
+    let epoch = current_epoch(beacon_chain);
+
+    let mut fork_digests = vec![beacon_chain.fork_digest(epoch)];
+
+    for adjacent_epoch in &[epoch.saturating_sub(1_u64), epoch + 1] {
+        let fork_digest = beacon_chain.fork_digest(*adjacent_epoch);
+        if !fork_digests.contains(&fork_digest) {
+            fork_digests.push(fork_digest);
+        }
+    }
+
+    fork_digests
+}
+
 //TODO: Potentially handle channel errors
-fn network_service(
+fn network_service<T: BeaconChainTypes + 'static>(
+    beacon_chain: Arc<BeaconChain<T>>,
     libp2p_service: Arc<Mutex<LibP2PService>>,
     mut network_recv: mpsc::UnboundedReceiver<NetworkMessage>,
-    mut message_handler_send: mpsc::UnboundedSender<HandlerMessage>,
+    mut message_handler_send: HandlerSend,
     log: slog::Logger,
 ) -> impl futures::Future<Item = (), Error = eth2_libp2p::error::Error> {
+    // Check for fork boundaries, and changes to the next scheduled fork, once per slot.
+    let slot_duration = Duration::from_millis(beacon_chain.spec.milliseconds_per_slot);
+    let mut fork_digest_update = Interval::new(Instant::now() + slot_duration, slot_duration);
+
+    let node_id = libp2p_service
+        .lock()
+        .swarm
+        .discovery()
+        .local_enr()
+        .node_id()
+        .raw();
+    let mut attestation_service = AttestationService::new(beacon_chain.clone(), node_id, &log);
+
     futures::future::poll_fn(move || -> Result<_, eth2_libp2p::error::Error> {
+        // resubscribe to the topics of the active forks, if they have changed
+        loop {
+            match fork_digest_update.poll() {
+                Ok(Async::Ready(Some(_))) => {
+                    let fork_digests = active_fork_digests(&beacon_chain);
+                    let mut libp2p_service = libp2p_service.lock();
+                    if libp2p_service.swarm.fork_digests() != fork_digests.as_slice() {
+                        info!(log, "Updating gossip topics for fork";
+                            "fork_digest" => hex::encode(fork_digests[0]),
+                            "active_fork_digests" => fork_digests.len(),
+                        );
+                        libp2p_service.swarm.update_fork_digests(fork_digests);
+                    }
+                    libp2p_service
+                        .swarm
+                        .update_eth2_enr(beacon_chain.enr_fork_id(current_epoch(&beacon_chain)));
+                }
+                Ok(Async::Ready(None)) | Ok(Async::NotReady) => break,
+                Err(e) => {
+                    warn!(log, "Fork digest timer failed"; "error" => format!("{:?}", e));
+                    break;
+                }
+            }
+        }
+
         // if the network channel is not ready, try the swarm
         loop {
             // poll the network channel
@@ -178,6 +356,28 @@ fn network_service(
                         debug!(log, "Sending pubsub message"; "topics" => format!("{:?}",topics));
                         libp2p_service.lock().swarm.publish(&topics, message);
                     }
+                    NetworkMessage::ReportPeer { peer_id, action } => {
+                        libp2p_service
+                            .lock()
+                            .swarm
+                            .peer_manager_mut()
+                            .report_peer(&peer_id, action);
+                    }
+                    NetworkMessage::NatMappingEstablished {
+                        tcp_socket,
+                        udp_socket,
+                    } => {
+                        let mut libp2p_service = libp2p_service.lock();
+                        if let Some(socket) = tcp_socket {
+                            libp2p_service.swarm.update_local_enr_socket(socket, true);
+                        }
+                        if let Some(socket) = udp_socket {
+                            libp2p_service.swarm.update_local_enr_socket(socket, false);
+                        }
+                    }
+                    NetworkMessage::Subscribe { subscriptions } => {
+                        attestation_service.validator_subscriptions(subscriptions);
+                    }
                 },
                 Ok(Async::NotReady) => break,
                 Ok(Async::Ready(None)) => {
@@ -189,6 +389,32 @@ fn network_service(
             }
         }
 
+        // apply changes to the attestation subnets
+        while let Ok(Async::Ready(Some(message))) = attestation_service.poll() {
+            let mut libp2p_service = libp2p_service.lock();
+            match message {
+                AttServiceMessage::Subscribe(subnet_id) => {
+                    libp2p_service
+                        .swarm
+                        .subscribe_kind(GossipKind::CommitteeIndex(subnet_id));
+                }
+                AttServiceMessage::Unsubscribe(subnet_id) => {
+                    libp2p_service
+                        .swarm
+                        .unsubscribe_kind(GossipKind::CommitteeIndex(subnet_id));
+                }
+                AttServiceMessage::EnrAdd(subnet_id) => {
+                    libp2p_service.swarm.update_enr_subnet(subnet_id, true);
+                }
+                AttServiceMessage::EnrRemove(subnet_id) => {
+                    libp2p_service.swarm.update_enr_subnet(subnet_id, false);
+                }
+                AttServiceMessage::DiscoverPeers(subnet_id) => {
+                    libp2p_service.swarm.discover_subnet_peers(subnet_id);
+                }
+            }
+        }
+
         loop {
             // poll the swarm
             match libp2p_service.lock().poll() {
@@ -237,14 +463,26 @@ fn network_service(
 pub enum NetworkMessage {
     /// Send an RPC message to the libp2p service.
     RPC(PeerId, RPCEvent),
-    /// Publish a message to gossipsub.
+    /// Publish a message to gossipsub, on the topics of the given kinds for the current fork.
     Publish {
-        topics: Vec<Topic>,
+        topics: Vec<GossipKind>,
         message: PubsubMessage,
     },
+    /// Subscribe to the attestation subnets required by a set of validator duties.
+    Subscribe {
+        subscriptions: Vec<ValidatorSubscription>,
+    },
     /// Propagate a received gossipsub message
     Propagate {
         propagation_source: PeerId,
         message_id: String,
     },
+    /// Adjust the score of a peer according to its behaviour.
+    ReportPeer { peer_id: PeerId, action: PeerAction },
+    /// The listening ports have been mapped on the local gateway. The external sockets are
+    /// advertised in the local ENR.
+    NatMappingEstablished {
+        tcp_socket: Option<SocketAddr>,
+        udp_socket: Option<SocketAddr>,
+    },
 }
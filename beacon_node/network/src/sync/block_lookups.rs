@@ -0,0 +1,382 @@
+//! Parent lookups of blocks whose parent is unknown to us.
+//!
+//! When a gossiped block references an unknown parent, the ancestors of the block are requested
+//! by root, one at a time, from the peer that sent the block. Downloaded blocks are buffered until
+//! an ancestor that descends from a known block is found, at which point the chain segment is
+//! imported in order, oldest block first. Each block is queued on the beacon processor and its
+//! outcome is polled by the sync manager, so a lookup never blocks sync.
+//!
+//! A lookup fails if the peer repeatedly fails to provide a parent, provides a block that is not
+//! the requested parent, provides an invalid block, or if the chain of unknown ancestors exceeds
+//! `PARENT_DEPTH_TOLERANCE`. In each case the peer is penalized for leading us to a dead end.
+
+use super::manager::SLOT_IMPORT_TOLERANCE;
+use super::simple_sync::NetworkContext;
+use crate::beacon_processor::{BeaconProcessorSend, RpcBlockImport, RpcBlockResult};
+use beacon_chain::{BeaconChain, BeaconChainTypes, BlockProcessingOutcome};
+use eth2_libp2p::rpc::methods::BlocksByRootRequest;
+use eth2_libp2p::rpc::RequestId;
+use eth2_libp2p::{PeerAction, PeerId};
+use futures::{Async, Future};
+use slog::{debug, trace, warn, Logger};
+use smallvec::SmallVec;
+use std::sync::Weak;
+use types::{BeaconBlock, EthSpec, Hash256};
+
+/// How many attempts we try to find a parent of a block before we give up trying.
+const PARENT_FAIL_TOLERANCE: usize = 3;
+/// The maximum depth we will search for a parent block. In principle we should have sync'd any
+/// canonical chain to its head once the peer connects. A chain should not appear where it's depth
+/// is further back than the most recent head slot.
+const PARENT_DEPTH_TOLERANCE: usize = SLOT_IMPORT_TOLERANCE * 2;
+/// The maximum number of parent lookups that may run at once. Blocks with unknown parents that
+/// arrive while the queue is full are dropped.
+const MAX_PARENT_LOOKUPS: usize = 3;
+
+/// The outcome of importing the oldest downloaded block of a parent lookup.
+enum ProcessingOutcome {
+    /// The block was imported, or was already known.
+    Imported,
+    /// The block still has an unknown parent, which must be requested.
+    ParentUnknown,
+    /// The block was dropped by the beacon processor before it could be imported.
+    Dropped,
+    /// The block was invalid or could not be processed. The peer is penalized with the action, if
+    /// any.
+    Failed(Option<PeerAction>),
+}
+
+/// A search for the ancestors of a block with an unknown parent.
+struct ParentLookup<E: EthSpec> {
+    /// The root of the block that triggered the lookup.
+    chain_hash: Hash256,
+    /// The downloaded blocks, from the block that triggered the lookup (first) to its oldest
+    /// downloaded ancestor (last).
+    downloaded_blocks: Vec<BeaconBlock<E>>,
+    /// The number of failed attempts to retrieve a parent block. If too many attempts occur, this
+    /// lookup is failed and rejected.
+    failed_attempts: usize,
+    /// The peer that sent us the block, from which the ancestors are requested.
+    peer_id: PeerId,
+    /// The id of the outstanding parent request, if any.
+    pending: Option<RequestId>,
+    /// The outcome of the import of the oldest downloaded block, if it is being imported.
+    processing: Option<RpcBlockImport>,
+}
+
+impl<E: EthSpec> ParentLookup<E> {
+    /// Returns the root of the parent of the oldest downloaded block.
+    fn parent_root(&self) -> Hash256 {
+        self.downloaded_blocks
+            .last()
+            .expect("A lookup always contains at least one block")
+            .parent_root
+    }
+
+    /// Returns `true` if `block_root` is the root of a block of this lookup.
+    fn contains(&self, block_root: Hash256) -> bool {
+        self.chain_hash == block_root
+            || self
+                .downloaded_blocks
+                .iter()
+                .any(|block| block.canonical_root() == block_root)
+    }
+}
+
+/// Searches for the ancestors of blocks whose parents are unknown to us.
+pub struct BlockLookups<T: BeaconChainTypes> {
+    /// The parent lookups that are in progress.
+    parent_queue: SmallVec<[ParentLookup<T::EthSpec>; MAX_PARENT_LOOKUPS]>,
+    /// A weak reference to the beacon chain.
+    chain: Weak<BeaconChain<T>>,
+    /// Imports the downloaded blocks.
+    beacon_processor: BeaconProcessorSend<T::EthSpec>,
+    log: Logger,
+}
+
+impl<T: BeaconChainTypes> BlockLookups<T> {
+    pub fn new(
+        chain: Weak<BeaconChain<T>>,
+        beacon_processor: BeaconProcessorSend<T::EthSpec>,
+        log: Logger,
+    ) -> Self {
+        BlockLookups {
+            parent_queue: SmallVec::new(),
+            chain,
+            beacon_processor,
+            log,
+        }
+    }
+
+    /// Returns `true` if any parent lookup is in progress.
+    pub fn is_searching(&self) -> bool {
+        !self.parent_queue.is_empty()
+    }
+
+    /// Starts searching for the ancestors of `block`, which was received from `peer_id` and whose
+    /// parent is unknown.
+    pub fn search_parent(
+        &mut self,
+        network: &mut NetworkContext,
+        peer_id: PeerId,
+        block: BeaconBlock<T::EthSpec>,
+    ) {
+        let block_root = block.canonical_root();
+
+        // make sure this block is not already being searched for
+        if self
+            .parent_queue
+            .iter()
+            .any(|lookup| lookup.contains(block_root) || lookup.contains(block.parent_root))
+        {
+            return;
+        }
+
+        if self.parent_queue.len() >= MAX_PARENT_LOOKUPS {
+            debug!(self.log, "Too many parent lookups, ignoring block";
+                "block_root" => format!("{}", block_root),
+                "peer" => format!("{:?}", peer_id),
+            );
+            return;
+        }
+
+        debug!(self.log, "Searching for block parent";
+            "block_root" => format!("{}", block_root),
+            "parent_root" => format!("{}", block.parent_root),
+            "peer" => format!("{:?}", peer_id),
+        );
+
+        let mut lookup = ParentLookup {
+            chain_hash: block_root,
+            downloaded_blocks: vec![block],
+            failed_attempts: 0,
+            peer_id,
+            pending: None,
+            processing: None,
+        };
+        request_parent(network, &mut lookup);
+        self.parent_queue.push(lookup);
+    }
+
+    /// Handles a `BlocksByRoot` response. Returns `false` if the request is not a parent request.
+    pub fn parent_lookup_response(
+        &mut self,
+        network: &mut NetworkContext,
+        peer_id: PeerId,
+        request_id: RequestId,
+        mut blocks: Vec<BeaconBlock<T::EthSpec>>,
+    ) -> bool {
+        let index = match self
+            .parent_queue
+            .iter()
+            .position(|lookup| lookup.pending == Some(request_id))
+        {
+            Some(index) => index,
+            None => return false,
+        };
+        let mut lookup = self.parent_queue.remove(index);
+        lookup.pending = None;
+
+        let expected_root = lookup.parent_root();
+        let block = match blocks.pop() {
+            Some(block) if blocks.is_empty() && block.canonical_root() == expected_root => block,
+            Some(_) => {
+                // the peer sent a block we did not ask for
+                debug!(self.log, "Peer sent invalid parent";
+                    "peer" => format!("{:?}", peer_id),
+                    "expected_parent" => format!("{}", expected_root),
+                );
+                network.report_peer(peer_id, PeerAction::RpcError);
+                self.retry_lookup(network, lookup);
+                return true;
+            }
+            None => {
+                // the peer did not have the requested block
+                trace!(self.log, "Peer did not return parent";
+                    "peer" => format!("{:?}", peer_id),
+                    "expected_parent" => format!("{}", expected_root),
+                );
+                self.retry_lookup(network, lookup);
+                return true;
+            }
+        };
+
+        lookup.downloaded_blocks.push(block);
+        self.process_next_block(lookup);
+
+        true
+    }
+
+    /// Continues the lookups whose oldest downloaded block has been processed by the beacon
+    /// processor. Must be called from the sync manager's task, which is notified once an outcome
+    /// is available.
+    ///
+    /// Returns the number of outcomes received, which the caller must mark as done in its
+    /// `PendingWork` once it has sent any work they caused.
+    pub fn poll_processing(&mut self, network: &mut NetworkContext) -> usize {
+        let mut received = 0;
+        let mut index = 0;
+        while index < self.parent_queue.len() {
+            let outcome = match self.parent_queue[index]
+                .processing
+                .as_mut()
+                .map(|result_rx| result_rx.poll())
+            {
+                Some(Ok(Async::Ready(result))) => self.processing_outcome(result),
+                Some(Err(_)) => ProcessingOutcome::Dropped,
+                Some(Ok(Async::NotReady)) | None => {
+                    index += 1;
+                    continue;
+                }
+            };
+            received += 1;
+
+            let mut lookup = self.parent_queue.remove(index);
+            lookup.processing = None;
+
+            match outcome {
+                ProcessingOutcome::Imported => {
+                    if let Some(block) = lookup.downloaded_blocks.pop() {
+                        trace!(self.log, "Imported parent block";
+                            "slot" => block.slot,
+                            "block_root" => format!("{}", block.canonical_root()),
+                        );
+                    }
+                    self.process_next_block(lookup);
+                }
+                ProcessingOutcome::ParentUnknown => {
+                    if lookup.downloaded_blocks.len() >= PARENT_DEPTH_TOLERANCE {
+                        debug!(self.log, "Parent lookup exceeded maximum depth";
+                            "block_root" => format!("{}", lookup.chain_hash),
+                            "peer" => format!("{:?}", lookup.peer_id),
+                        );
+                        network.report_peer(lookup.peer_id, PeerAction::RpcError);
+                    } else {
+                        request_parent(network, &mut lookup);
+                        self.parent_queue.push(lookup);
+                    }
+                }
+                ProcessingOutcome::Dropped => {
+                    // the processor is overloaded or shutting down, which is not the peer's fault
+                    debug!(self.log, "Parent block dropped by the beacon processor";
+                        "block_root" => format!("{}", lookup.chain_hash),
+                    );
+                }
+                ProcessingOutcome::Failed(action) => {
+                    debug!(self.log, "Parent lookup failed";
+                        "block_root" => format!("{}", lookup.chain_hash),
+                        "peer" => format!("{:?}", lookup.peer_id),
+                    );
+                    if let Some(action) = action {
+                        network.report_peer(lookup.peer_id, action);
+                    }
+                }
+            }
+        }
+
+        received
+    }
+
+    /// Handles a failed `BlocksByRoot` request. Returns `false` if the request is not a parent
+    /// request.
+    pub fn parent_lookup_failed(
+        &mut self,
+        network: &mut NetworkContext,
+        request_id: RequestId,
+    ) -> bool {
+        match self
+            .parent_queue
+            .iter()
+            .position(|lookup| lookup.pending == Some(request_id))
+        {
+            Some(index) => {
+                let mut lookup = self.parent_queue.remove(index);
+                lookup.pending = None;
+                self.retry_lookup(network, lookup);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drops the lookups of a disconnected peer, as their ancestors can no longer be requested.
+    pub fn peer_disconnected(&mut self, peer_id: &PeerId) {
+        self.parent_queue
+            .retain(|lookup| &lookup.peer_id != peer_id);
+    }
+
+    /// Re-requests the parent of a lookup after a failed attempt, or drops the lookup if it has
+    /// failed too many times.
+    fn retry_lookup(&mut self, network: &mut NetworkContext, mut lookup: ParentLookup<T::EthSpec>) {
+        lookup.failed_attempts += 1;
+        if lookup.failed_attempts >= PARENT_FAIL_TOLERANCE {
+            debug!(self.log, "Parent lookup failed too many times";
+                "block_root" => format!("{}", lookup.chain_hash),
+                "peer" => format!("{:?}", lookup.peer_id),
+            );
+            network.report_peer(lookup.peer_id, PeerAction::RpcError);
+            return;
+        }
+
+        request_parent(network, &mut lookup);
+        self.parent_queue.push(lookup);
+    }
+
+    /// Queues the oldest downloaded block of a lookup for import. The lookup is completed once all
+    /// of its blocks have been imported.
+    fn process_next_block(&mut self, mut lookup: ParentLookup<T::EthSpec>) {
+        if self.chain.upgrade().is_none() {
+            return;
+        }
+
+        match lookup.downloaded_blocks.last() {
+            Some(block) => {
+                lookup.processing = Some(self.beacon_processor.import_rpc_block(block.clone()));
+                self.parent_queue.push(lookup);
+            }
+            None => {
+                debug!(self.log, "Parent lookup completed";
+                    "block_root" => format!("{}", lookup.chain_hash),
+                );
+            }
+        }
+    }
+
+    /// Classifies the outcome of importing the oldest downloaded block of a lookup.
+    fn processing_outcome(&self, result: RpcBlockResult) -> ProcessingOutcome {
+        match result {
+            // need to keep looking for parents
+            Ok(BlockProcessingOutcome::ParentUnknown { .. }) => ProcessingOutcome::ParentUnknown,
+            Ok(BlockProcessingOutcome::Processed { .. })
+            | Ok(BlockProcessingOutcome::BlockIsAlreadyKnown) => ProcessingOutcome::Imported,
+            Ok(outcome) => {
+                // it's a future slot or an invalid block
+                debug!(self.log, "Invalid parent block";
+                    "outcome" => outcome.to_string(),
+                );
+                ProcessingOutcome::Failed(if outcome.is_invalid_block() {
+                    Some(PeerAction::InvalidBlock)
+                } else {
+                    None
+                })
+            }
+            Err(e) => {
+                // the block could not be processed, which is not the peer's fault
+                warn!(self.log, "Parent processing error";
+                    "error" => format!("{:?}", e),
+                );
+                ProcessingOutcome::Failed(None)
+            }
+        }
+    }
+}
+
+/// Requests the parent of the oldest downloaded block of `lookup` from the peer that sent the
+/// block.
+fn request_parent<E: EthSpec>(network: &mut NetworkContext, lookup: &mut ParentLookup<E>) {
+    let request = BlocksByRootRequest {
+        block_roots: vec![lookup.parent_root()],
+    };
+    let request_id = network.blocks_by_root_request(lookup.peer_id.clone(), request);
+    lookup.pending = Some(request_id);
+}
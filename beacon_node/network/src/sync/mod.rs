@@ -1,10 +1,16 @@
+mod block_lookups;
 mod manager;
+mod range_sync;
 /// Syncing for lighthouse.
 ///
 /// Stores the various syncing methods for the beacon chain.
 mod simple_sync;
 
+pub(crate) use manager::SyncMessage;
+pub use manager::{SyncProgress, SyncState};
+pub use range_sync::{ChainProgress, RangeSyncType};
 pub use simple_sync::MessageProcessor;
+pub(crate) use simple_sync::{NetworkContext, FUTURE_SLOT_TOLERANCE};
 
 /// Currently implemented sync methods.
 pub enum SyncMethod {
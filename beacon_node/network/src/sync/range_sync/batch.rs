@@ -0,0 +1,75 @@
+use eth2_libp2p::rpc::methods::BlocksByRangeRequest;
+use eth2_libp2p::PeerId;
+use types::{BeaconBlock, EthSpec, Hash256, Slot};
+
+/// The number of blocks requested in each batch.
+pub const BLOCKS_PER_BATCH: u64 = 50;
+
+/// A sequential identifier of a batch within a `SyncingChain`. Batches are processed in order of
+/// their id.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BatchId(pub u64);
+
+impl BatchId {
+    /// Returns the id of the following batch.
+    pub fn next(self) -> BatchId {
+        BatchId(self.0 + 1)
+    }
+}
+
+/// A range of blocks that is downloaded from a single peer and processed as a unit.
+pub struct Batch<T: EthSpec> {
+    /// The position of this batch within its chain.
+    pub id: BatchId,
+    /// The first slot of the batch (inclusive).
+    pub start_slot: Slot,
+    /// The end slot of the batch (exclusive).
+    pub end_slot: Slot,
+    /// The head of the chain the blocks are requested from.
+    pub head_root: Hash256,
+    /// The peer the batch is currently requested from.
+    pub current_peer: PeerId,
+    /// The number of times this batch has been re-requested after a failed download or
+    /// processing attempt.
+    pub retries: u8,
+    /// The number of times the blocks of this batch have failed processing.
+    pub reprocess_retries: u8,
+    /// The blocks that have been downloaded for this batch.
+    pub downloaded_blocks: Vec<BeaconBlock<T>>,
+}
+
+impl<T: EthSpec> Batch<T> {
+    pub fn new(
+        id: BatchId,
+        start_slot: Slot,
+        end_slot: Slot,
+        head_root: Hash256,
+        peer_id: PeerId,
+    ) -> Self {
+        Batch {
+            id,
+            start_slot,
+            end_slot,
+            head_root,
+            current_peer: peer_id,
+            retries: 0,
+            reprocess_retries: 0,
+            downloaded_blocks: Vec::new(),
+        }
+    }
+
+    /// Returns the `BlocksByRange` request that downloads this batch.
+    pub fn to_blocks_by_range_request(&self) -> BlocksByRangeRequest {
+        BlocksByRangeRequest {
+            head_block_root: self.head_root,
+            start_slot: self.start_slot.as_u64(),
+            count: (self.end_slot - self.start_slot).as_u64(),
+            step: 1,
+        }
+    }
+
+    /// Returns `true` if `slot` is within the range of this batch.
+    pub fn contains(&self, slot: Slot) -> bool {
+        self.start_slot <= slot && slot < self.end_slot
+    }
+}
@@ -0,0 +1,654 @@
+use super::batch::{Batch, BatchId, BLOCKS_PER_BATCH};
+use super::{ChainProgress, RangeSyncType};
+use crate::beacon_processor::BeaconProcessorSend;
+use crate::sync::simple_sync::{status_message, NetworkContext, FUTURE_SLOT_TOLERANCE};
+use beacon_chain::{BeaconChain, BeaconChainTypes, BlockProcessingOutcome, ChainSegmentResult};
+use eth2_libp2p::rpc::{RPCRequest, RequestId};
+use eth2_libp2p::{PeerAction, PeerId};
+use slog::{debug, info, trace, warn, Logger};
+use std::collections::{HashMap, HashSet};
+use std::sync::Weak;
+use types::{BeaconBlock, Hash256, Slot};
+
+/// The maximum number of batches that may be downloading or awaiting processing at once. This
+/// bounds the memory used by a chain whose peers are faster than block processing.
+///
+/// The limit is reduced when the `ResourceBudget` of the beacon chain is in low-power mode.
+const BATCH_BUFFER_SIZE: usize = 5;
+
+/// The number of times a batch may be re-requested before the chain is considered failed.
+const MAX_BATCH_RETRIES: u8 = 5;
+
+/// The number of times the blocks of a batch may fail processing before the chain is considered
+/// failed.
+const MAX_BATCH_PROCESSING_ATTEMPTS: u8 = 3;
+
+/// The result of updating a `SyncingChain`.
+#[derive(PartialEq, Debug)]
+pub enum ProcessingResult {
+    /// The chain is still syncing.
+    KeepChain,
+    /// The chain has either completed or failed and should be removed.
+    RemoveChain,
+}
+
+/// Whether a `SyncingChain` is currently downloading batches.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ChainSyncingState {
+    /// Batches are not requested. Downloads in flight are still processed.
+    Stopped,
+    /// Batches are being requested from the peer pool.
+    Syncing,
+}
+
+/// A chain of blocks that a pool of peers claim to have, which is downloaded in batches from
+/// `start_slot` up to and including `target_head_slot`.
+pub struct SyncingChain<T: BeaconChainTypes> {
+    /// Whether the chain targets a finalized checkpoint or a head block.
+    pub sync_type: RangeSyncType,
+    /// The slot the chain starts syncing from.
+    pub start_slot: Slot,
+    /// The slot of the target block.
+    pub target_head_slot: Slot,
+    /// The root of the target block.
+    pub target_head_root: Hash256,
+    /// The peers that have claimed to have the target block.
+    pub peer_pool: HashSet<PeerId>,
+    /// Whether the chain is currently requesting batches.
+    pub state: ChainSyncingState,
+    /// Batches that have been requested, by the id of their request.
+    pending_batches: HashMap<RequestId, Batch<T::EthSpec>>,
+    /// Downloaded batches that are waiting for their predecessors to be processed, ordered by id.
+    completed_batches: Vec<Batch<T::EthSpec>>,
+    /// The id of the next batch to request.
+    to_be_downloaded_id: BatchId,
+    /// The id of the next batch to process.
+    to_be_processed_id: BatchId,
+    /// A weak reference to the beacon chain.
+    chain: Weak<BeaconChain<T>>,
+    log: Logger,
+}
+
+impl<T: BeaconChainTypes> SyncingChain<T> {
+    pub fn new(
+        sync_type: RangeSyncType,
+        start_slot: Slot,
+        target_head_slot: Slot,
+        target_head_root: Hash256,
+        peer_id: PeerId,
+        chain: Weak<BeaconChain<T>>,
+        log: Logger,
+    ) -> Self {
+        let mut peer_pool = HashSet::new();
+        peer_pool.insert(peer_id);
+
+        SyncingChain {
+            sync_type,
+            start_slot,
+            target_head_slot,
+            target_head_root,
+            peer_pool,
+            state: ChainSyncingState::Stopped,
+            pending_batches: HashMap::new(),
+            completed_batches: Vec::new(),
+            to_be_downloaded_id: BatchId(0),
+            to_be_processed_id: BatchId(0),
+            chain,
+            log,
+        }
+    }
+
+    /// Returns the progress of this chain for reporting.
+    pub fn progress(&self) -> ChainProgress {
+        ChainProgress {
+            sync_type: self.sync_type,
+            syncing: self.state == ChainSyncingState::Syncing,
+            start_slot: self.start_slot,
+            processed_slot: self.batch_start_slot(self.to_be_processed_id),
+            target_head_slot: self.target_head_slot,
+            target_head_root: self.target_head_root,
+            peers: self.peer_pool.len(),
+        }
+    }
+
+    /// Begins requesting batches from the peer pool.
+    pub fn start_syncing(&mut self, network: &mut NetworkContext) {
+        if self.state != ChainSyncingState::Syncing {
+            debug!(self.log, "Syncing chain started";
+                "sync_type" => format!("{:?}", self.sync_type),
+                "start_slot" => self.start_slot,
+                "target_head_slot" => self.target_head_slot,
+                "target_head_root" => format!("{}", self.target_head_root),
+                "peers" => self.peer_pool.len(),
+            );
+            self.state = ChainSyncingState::Syncing;
+        }
+        self.request_batches(network);
+    }
+
+    /// Stops requesting new batches. Batches that are already requested are still processed.
+    pub fn stop_syncing(&mut self) {
+        self.state = ChainSyncingState::Stopped;
+    }
+
+    /// Adds a peer to the pool, assigning it a batch if the chain is syncing.
+    pub fn add_peer(&mut self, network: &mut NetworkContext, peer_id: PeerId) {
+        self.peer_pool.insert(peer_id);
+        self.request_batches(network);
+    }
+
+    /// Removes a peer from the pool and re-requests its pending batches from the remaining
+    /// peers.
+    pub fn remove_peer(
+        &mut self,
+        network: &mut NetworkContext,
+        peer_id: &PeerId,
+    ) -> ProcessingResult {
+        self.peer_pool.remove(peer_id);
+
+        let request_ids = self
+            .pending_batches
+            .iter()
+            .filter(|(_, batch)| &batch.current_peer == peer_id)
+            .map(|(request_id, _)| *request_id)
+            .collect::<Vec<_>>();
+
+        for request_id in request_ids {
+            if let Some(batch) = self.pending_batches.remove(&request_id) {
+                if self.retry_batch(network, batch) == ProcessingResult::RemoveChain {
+                    return ProcessingResult::RemoveChain;
+                }
+            }
+        }
+
+        if self.peer_pool.is_empty() {
+            ProcessingResult::RemoveChain
+        } else {
+            ProcessingResult::KeepChain
+        }
+    }
+
+    /// Handles a `BlocksByRange` response. Returns `None` if the request does not belong to this
+    /// chain.
+    pub fn on_block_response(
+        &mut self,
+        network: &mut NetworkContext,
+        beacon_processor: &BeaconProcessorSend<T::EthSpec>,
+        request_id: RequestId,
+        blocks: Vec<BeaconBlock<T::EthSpec>>,
+    ) -> Option<ProcessingResult> {
+        let mut batch = self.pending_batches.remove(&request_id)?;
+
+        // The order of the blocks is verified during processing.
+        if let Some(block) = blocks.iter().find(|block| !batch.contains(block.slot)) {
+            warn!(self.log, "BlocksByRange response returned out of range blocks";
+                "peer" => format!("{:?}", batch.current_peer),
+                "block_slot" => block.slot,
+                "batch_start_slot" => batch.start_slot,
+                "batch_end_slot" => batch.end_slot,
+            );
+            network.report_peer(batch.current_peer.clone(), PeerAction::RpcError);
+            return Some(self.retry_batch(network, batch));
+        }
+
+        trace!(self.log, "Batch downloaded";
+            "id" => batch.id.0,
+            "peer" => format!("{:?}", batch.current_peer),
+            "blocks" => blocks.len(),
+        );
+        batch.downloaded_blocks = blocks;
+
+        let position = self
+            .completed_batches
+            .iter()
+            .position(|completed| completed.id > batch.id)
+            .unwrap_or_else(|| self.completed_batches.len());
+        self.completed_batches.insert(position, batch);
+
+        let result = self.process_completed_batches(network, beacon_processor);
+        if result == ProcessingResult::KeepChain {
+            self.request_batches(network);
+        }
+        Some(result)
+    }
+
+    /// Handles a failed `BlocksByRange` request. Returns `None` if the request does not belong
+    /// to this chain.
+    pub fn inject_error(
+        &mut self,
+        network: &mut NetworkContext,
+        request_id: RequestId,
+    ) -> Option<ProcessingResult> {
+        let batch = self.pending_batches.remove(&request_id)?;
+        debug!(self.log, "Batch download failed";
+            "id" => batch.id.0,
+            "peer" => format!("{:?}", batch.current_peer),
+        );
+        Some(self.retry_batch(network, batch))
+    }
+
+    /// Returns `true` if the request with `request_id` was sent by this chain.
+    pub fn has_pending_request(&self, request_id: RequestId) -> bool {
+        self.pending_batches.contains_key(&request_id)
+    }
+
+    /// Sends a `Status` to each peer of the pool, so that peers with a later head are re-assigned
+    /// to a new chain.
+    pub fn status_peers(&self, network: &mut NetworkContext) {
+        if let Some(chain) = self.chain.upgrade() {
+            for peer_id in self.peer_pool.iter() {
+                network.send_rpc_request(
+                    None,
+                    peer_id.clone(),
+                    RPCRequest::Status(status_message(&chain)),
+                );
+            }
+        }
+    }
+
+    /// Returns the first slot of the batch with `id`.
+    fn batch_start_slot(&self, id: BatchId) -> Slot {
+        self.start_slot + id.0 * BLOCKS_PER_BATCH
+    }
+
+    /// Returns a peer of the pool that is not downloading a batch.
+    fn idle_peer(&self) -> Option<PeerId> {
+        self.peer_pool
+            .iter()
+            .find(|peer_id| {
+                !self
+                    .pending_batches
+                    .values()
+                    .any(|batch| &batch.current_peer == *peer_id)
+            })
+            .cloned()
+    }
+
+    /// Assigns new batches to idle peers until the batch buffer is full or all batches up to the
+    /// target head have been requested.
+    fn request_batches(&mut self, network: &mut NetworkContext) {
+        if self.state != ChainSyncingState::Syncing {
+            return;
+        }
+
+        let batch_buffer_size = match self.chain.upgrade() {
+            Some(chain) => chain
+                .resource_budget
+                .sync_batch_buffer_size(BATCH_BUFFER_SIZE),
+            None => return,
+        };
+
+        while self.pending_batches.len() + self.completed_batches.len() < batch_buffer_size {
+            let start_slot = self.batch_start_slot(self.to_be_downloaded_id);
+            if start_slot > self.target_head_slot {
+                break;
+            }
+
+            let peer_id = match self.idle_peer() {
+                Some(peer_id) => peer_id,
+                None => break,
+            };
+
+            let batch = Batch::new(
+                self.to_be_downloaded_id,
+                start_slot,
+                start_slot + BLOCKS_PER_BATCH,
+                self.target_head_root,
+                peer_id,
+            );
+            self.to_be_downloaded_id = self.to_be_downloaded_id.next();
+            self.send_batch(network, batch);
+        }
+    }
+
+    /// Requests `batch` from its current peer.
+    fn send_batch(&mut self, network: &mut NetworkContext, batch: Batch<T::EthSpec>) {
+        let request = batch.to_blocks_by_range_request();
+        let request_id = network.blocks_by_range_request(batch.current_peer.clone(), request);
+        self.pending_batches.insert(request_id, batch);
+    }
+
+    /// Re-requests `batch`, preferring a different peer to the one that failed.
+    fn retry_batch(
+        &mut self,
+        network: &mut NetworkContext,
+        mut batch: Batch<T::EthSpec>,
+    ) -> ProcessingResult {
+        batch.retries += 1;
+        if batch.retries > MAX_BATCH_RETRIES {
+            warn!(self.log, "Batch failed too many times, dropping chain";
+                "id" => batch.id.0,
+                "start_slot" => batch.start_slot,
+                "target_head_root" => format!("{}", self.target_head_root),
+            );
+            return ProcessingResult::RemoveChain;
+        }
+
+        let peer_id = self
+            .peer_pool
+            .iter()
+            .find(|peer_id| **peer_id != batch.current_peer)
+            .or_else(|| self.peer_pool.iter().next())
+            .cloned();
+
+        match peer_id {
+            Some(peer_id) => {
+                debug!(self.log, "Re-requesting batch";
+                    "id" => batch.id.0,
+                    "retries" => batch.retries,
+                    "peer" => format!("{:?}", peer_id),
+                );
+                batch.current_peer = peer_id;
+                batch.downloaded_blocks.clear();
+                self.send_batch(network, batch);
+                ProcessingResult::KeepChain
+            }
+            None => ProcessingResult::RemoveChain,
+        }
+    }
+
+    /// Processes the downloaded batches in order, stopping at the first batch that has not been
+    /// downloaded yet.
+    fn process_completed_batches(
+        &mut self,
+        network: &mut NetworkContext,
+        beacon_processor: &BeaconProcessorSend<T::EthSpec>,
+    ) -> ProcessingResult {
+        while self.completed_batches.first().map(|batch| batch.id) == Some(self.to_be_processed_id)
+        {
+            let mut batch = self.completed_batches.remove(0);
+            let blocks = std::mem::replace(&mut batch.downloaded_blocks, Vec::new());
+            let block_count = blocks.len();
+
+            match process_blocks(&self.chain, beacon_processor, blocks, &self.log) {
+                Ok(()) => {
+                    debug!(self.log, "Batch processed";
+                        "id" => batch.id.0,
+                        "start_slot" => batch.start_slot,
+                        "end_slot" => batch.end_slot,
+                        "blocks" => block_count,
+                        "peer" => format!("{:?}", batch.current_peer),
+                    );
+                    self.to_be_processed_id = self.to_be_processed_id.next();
+
+                    if batch.end_slot > self.target_head_slot {
+                        info!(self.log, "Syncing chain completed";
+                            "sync_type" => format!("{:?}", self.sync_type),
+                            "target_head_slot" => self.target_head_slot,
+                            "target_head_root" => format!("{}", self.target_head_root),
+                        );
+                        // re-status the peers to ensure we are up to their latest head
+                        self.status_peers(network);
+                        return ProcessingResult::RemoveChain;
+                    }
+                }
+                Err((e, action)) => {
+                    warn!(self.log, "Batch processing failed";
+                        "id" => batch.id.0,
+                        "start_slot" => batch.start_slot,
+                        "end_slot" => batch.end_slot,
+                        "peer" => format!("{:?}", batch.current_peer),
+                        "error" => e,
+                    );
+                    if let Some(action) = action {
+                        network.report_peer(batch.current_peer.clone(), action);
+                    }
+
+                    batch.reprocess_retries += 1;
+                    if batch.reprocess_retries >= MAX_BATCH_PROCESSING_ATTEMPTS {
+                        warn!(self.log, "Batch failed processing too many times, dropping chain";
+                            "id" => batch.id.0,
+                            "target_head_root" => format!("{}", self.target_head_root),
+                        );
+                        return ProcessingResult::RemoveChain;
+                    }
+                    return self.retry_batch(network, batch);
+                }
+            }
+        }
+
+        ProcessingResult::KeepChain
+    }
+}
+
+// Helper function to import the blocks of a batch via the beacon processor, as a chain segment.
+// On failure, returns the error and the action with which to penalize the peer, if any.
+fn process_blocks<T: BeaconChainTypes>(
+    weak_chain: &Weak<BeaconChain<T>>,
+    beacon_processor: &BeaconProcessorSend<T::EthSpec>,
+    blocks: Vec<BeaconBlock<T::EthSpec>>,
+    log: &Logger,
+) -> Result<(), (String, Option<PeerAction>)> {
+    if weak_chain.upgrade().is_none() {
+        return Ok(()); // terminate early due to dropped beacon chain
+    }
+
+    match beacon_processor.import_chain_segment(blocks) {
+        Ok(ChainSegmentResult::Successful { imported_blocks }) => {
+            trace!(
+                log, "Imported chain segment from network";
+                "imported_blocks" => imported_blocks,
+            );
+            Ok(())
+        }
+        Ok(ChainSegmentResult::Failed {
+            imported_blocks,
+            outcome,
+        }) => match outcome {
+            BlockProcessingOutcome::ParentUnknown { parent } => {
+                // blocks should be sequential and all parents should exist
+                trace!(
+                    log, "Parent block is unknown";
+                    "parent_root" => format!("{}", parent),
+                    "imported_blocks" => imported_blocks,
+                );
+                Err((
+                    format!("Block with parent {} has an unknown parent.", parent),
+                    Some(PeerAction::InvalidBlock),
+                ))
+            }
+            BlockProcessingOutcome::FutureSlot {
+                present_slot,
+                block_slot,
+            } => {
+                if block_slot > present_slot + FUTURE_SLOT_TOLERANCE {
+                    // The block is too far in the future, drop it.
+                    trace!(
+                        log, "Block is ahead of our slot clock";
+                        "msg" => "block for future slot rejected, check your time",
+                        "present_slot" => present_slot,
+                        "block_slot" => block_slot,
+                        "FUTURE_SLOT_TOLERANCE" => FUTURE_SLOT_TOLERANCE,
+                    );
+                    // Our clock may be behind, so the peer is not penalized.
+                    Err((
+                        format!("Block at slot {} is too far in the future", block_slot),
+                        None,
+                    ))
+                } else {
+                    // The block is in the future, but not too far.
+                    trace!(
+                        log, "Block is slightly ahead of our slot clock, ignoring.";
+                        "present_slot" => present_slot,
+                        "block_slot" => block_slot,
+                        "FUTURE_SLOT_TOLERANCE" => FUTURE_SLOT_TOLERANCE,
+                    );
+                    Ok(())
+                }
+            }
+            outcome => {
+                warn!(
+                    log, "Invalid block received";
+                    "msg" => "peer sent invalid block",
+                    "outcome" => outcome.to_string(),
+                    "imported_blocks" => imported_blocks,
+                );
+                let action = if outcome.is_invalid_block() {
+                    Some(PeerAction::InvalidBlock)
+                } else {
+                    None
+                };
+                Err((
+                    format!("Invalid block in chain segment: {}", outcome),
+                    action,
+                ))
+            }
+        },
+        Err(e) => {
+            warn!(
+                log, "BlockProcessingFailure";
+                "msg" => "unexpected condition in processing chain segment.",
+                "error" => &e,
+            );
+            Err((
+                format!("Unexpected chain segment processing error: {}", e),
+                None,
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beacon_processor::BeaconProcessor;
+    use crate::pending_work::PendingWork;
+    use beacon_chain::test_utils::{BeaconChainHarness, HarnessType};
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+    use types::test_utils::generate_deterministic_keypairs;
+    use types::{EthSpec, MinimalEthSpec};
+
+    type TestChain = BeaconChain<HarnessType<MinimalEthSpec>>;
+
+    fn null_logger() -> Logger {
+        Logger::root(slog::Discard, slog::o!())
+    }
+
+    fn network() -> NetworkContext {
+        let (network_send, _network_recv) = mpsc::unbounded_channel();
+        NetworkContext::new(network_send, null_logger())
+    }
+
+    /// Returns a syncing chain of ten batches, with a pool of `peers`.
+    fn syncing_chain(
+        beacon_chain: &Arc<TestChain>,
+        peers: &[PeerId],
+    ) -> SyncingChain<HarnessType<MinimalEthSpec>> {
+        let mut syncing_chain = SyncingChain::new(
+            RangeSyncType::Finalized,
+            Slot::new(0),
+            Slot::new(BLOCKS_PER_BATCH * 10),
+            Hash256::repeat_byte(1),
+            peers[0].clone(),
+            Arc::downgrade(beacon_chain),
+            null_logger(),
+        );
+        syncing_chain.peer_pool.extend(peers[1..].iter().cloned());
+        syncing_chain
+    }
+
+    /// Returns the request id, peer and retries of the pending batch with `id`.
+    fn pending_batch(
+        syncing_chain: &SyncingChain<HarnessType<MinimalEthSpec>>,
+        id: u64,
+    ) -> (RequestId, PeerId, u8) {
+        syncing_chain
+            .pending_batches
+            .iter()
+            .find(|(_, batch)| batch.id == BatchId(id))
+            .map(|(request_id, batch)| (*request_id, batch.current_peer.clone(), batch.retries))
+            .expect("batch should be pending")
+    }
+
+    fn beacon_chain() -> Arc<TestChain> {
+        Arc::new(BeaconChainHarness::new(MinimalEthSpec, generate_deterministic_keypairs(8)).chain)
+    }
+
+    #[test]
+    fn failed_batches_are_retried_from_another_peer() {
+        let beacon_chain = beacon_chain();
+        let mut network = network();
+        let beacon_processor = BeaconProcessor::new(PendingWork::default(), null_logger());
+        let peers = vec![PeerId::random(), PeerId::random()];
+        let mut syncing_chain = syncing_chain(&beacon_chain, &peers);
+
+        syncing_chain.start_syncing(&mut network);
+        assert_eq!(
+            syncing_chain.pending_batches.len(),
+            2,
+            "should request a batch from each peer"
+        );
+
+        let (request_id, peer_id, retries) = pending_batch(&syncing_chain, 0);
+        assert_eq!(retries, 0);
+        assert_eq!(
+            syncing_chain.inject_error(&mut network, request_id),
+            Some(ProcessingResult::KeepChain)
+        );
+        let (request_id, retry_peer_id, retries) = pending_batch(&syncing_chain, 0);
+        assert_ne!(retry_peer_id, peer_id, "should retry from the other peer");
+        assert_eq!(retries, 1);
+
+        // A response with blocks outside the batch is treated as a failure.
+        let mut block = BeaconBlock::empty(&MinimalEthSpec::default_spec());
+        block.slot = Slot::new(BLOCKS_PER_BATCH * 5);
+        assert_eq!(
+            syncing_chain.on_block_response(
+                &mut network,
+                &beacon_processor.sender(),
+                request_id,
+                vec![block]
+            ),
+            Some(ProcessingResult::KeepChain)
+        );
+        let (mut request_id, _, retries) = pending_batch(&syncing_chain, 0);
+        assert_eq!(retries, 2);
+
+        for _ in retries..MAX_BATCH_RETRIES {
+            assert_eq!(
+                syncing_chain.inject_error(&mut network, request_id),
+                Some(ProcessingResult::KeepChain)
+            );
+            request_id = pending_batch(&syncing_chain, 0).0;
+        }
+        assert_eq!(
+            syncing_chain.inject_error(&mut network, request_id),
+            Some(ProcessingResult::RemoveChain),
+            "should fail the chain once a batch fails too many times"
+        );
+        assert_eq!(
+            syncing_chain.inject_error(&mut network, request_id),
+            None,
+            "should not handle an unknown request"
+        );
+    }
+
+    #[test]
+    fn batches_of_removed_peers_are_reassigned() {
+        let beacon_chain = beacon_chain();
+        let mut network = network();
+        let peers = vec![PeerId::random(), PeerId::random()];
+        let mut syncing_chain = syncing_chain(&beacon_chain, &peers);
+
+        syncing_chain.start_syncing(&mut network);
+        assert_eq!(
+            syncing_chain.remove_peer(&mut network, &peers[0]),
+            ProcessingResult::KeepChain
+        );
+        assert_eq!(syncing_chain.pending_batches.len(), 2);
+        assert!(
+            syncing_chain
+                .pending_batches
+                .values()
+                .all(|batch| batch.current_peer == peers[1]),
+            "the batch of the removed peer should be requested from the remaining peer"
+        );
+
+        assert_eq!(
+            syncing_chain.remove_peer(&mut network, &peers[1]),
+            ProcessingResult::RemoveChain,
+            "should fail the chain once it has no peers"
+        );
+    }
+}
@@ -0,0 +1,463 @@
+//! Long-range (batch) syncing of chains that are far ahead of the local head.
+//!
+//! Peers that are ahead of us are grouped into `SyncingChain`s by the block they claim to have:
+//! - Peers that have finalized beyond our finalized epoch form a `Finalized` chain, targeting
+//!   their finalized checkpoint.
+//! - Peers that share our finalized epoch but have a later head form a `Head` chain, targeting
+//!   their head block.
+//!
+//! Each chain splits the distance from our finalized slot to its target into batches of
+//! `BLOCKS_PER_BATCH` blocks and distributes the batches among its pool of peers. Downloaded
+//! batches are processed in order. A batch that fails to download or process is re-requested
+//! from another peer of the pool, and the chain is dropped once a batch fails too many times.
+//!
+//! Only the finalized chain with the most peers is synced at a time. Head chains are synced once
+//! no finalized chain remains. Once a chain reaches its target, its peers are sent a `Status` so
+//! that any peer with a later head is assigned to a new chain.
+
+mod batch;
+mod chain;
+
+use self::chain::{ChainSyncingState, ProcessingResult, SyncingChain};
+use super::simple_sync::{NetworkContext, PeerSyncInfo};
+use crate::beacon_processor::BeaconProcessorSend;
+use beacon_chain::{BeaconChain, BeaconChainTypes};
+use eth2_libp2p::rpc::RequestId;
+use eth2_libp2p::PeerId;
+use slog::{debug, trace, Logger};
+use std::sync::Weak;
+use types::{BeaconBlock, EthSpec, Hash256, Slot};
+
+/// The kind of target a `SyncingChain` is syncing towards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RangeSyncType {
+    /// The chain targets a finalized checkpoint later than our own.
+    Finalized,
+    /// The chain targets a head block that descends from our finalized checkpoint.
+    Head,
+}
+
+/// A snapshot of the progress of a single syncing chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainProgress {
+    /// The kind of target the chain is syncing towards.
+    pub sync_type: RangeSyncType,
+    /// Whether batches are currently being requested for this chain.
+    pub syncing: bool,
+    /// The slot the chain started syncing from.
+    pub start_slot: Slot,
+    /// All blocks prior to this slot have been processed.
+    pub processed_slot: Slot,
+    /// The slot of the target block.
+    pub target_head_slot: Slot,
+    /// The root of the target block.
+    pub target_head_root: Hash256,
+    /// The number of peers in the chain's peer pool.
+    pub peers: usize,
+}
+
+/// Manages the set of chains that are being synced from peers that are ahead of us.
+pub struct RangeSync<T: BeaconChainTypes> {
+    /// A weak reference to the beacon chain.
+    chain: Weak<BeaconChain<T>>,
+    /// Imports the downloaded batches.
+    beacon_processor: BeaconProcessorSend<T::EthSpec>,
+    /// Chains of peers that have finalized beyond our finalized epoch.
+    finalized_chains: Vec<SyncingChain<T>>,
+    /// Chains of peers that share our finalized epoch but have a later head.
+    head_chains: Vec<SyncingChain<T>>,
+    log: Logger,
+}
+
+impl<T: BeaconChainTypes> RangeSync<T> {
+    pub fn new(
+        chain: Weak<BeaconChain<T>>,
+        beacon_processor: BeaconProcessorSend<T::EthSpec>,
+        log: Logger,
+    ) -> Self {
+        RangeSync {
+            chain,
+            beacon_processor,
+            finalized_chains: Vec::new(),
+            head_chains: Vec::new(),
+            log,
+        }
+    }
+
+    /// Returns the kind of chain being synced, if any. Finalized chains are synced before head
+    /// chains.
+    pub fn sync_type(&self) -> Option<RangeSyncType> {
+        if !self.finalized_chains.is_empty() {
+            Some(RangeSyncType::Finalized)
+        } else if !self.head_chains.is_empty() {
+            Some(RangeSyncType::Head)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the progress of each chain, finalized chains first.
+    pub fn progress(&self) -> Vec<ChainProgress> {
+        self.finalized_chains
+            .iter()
+            .chain(self.head_chains.iter())
+            .map(SyncingChain::progress)
+            .collect()
+    }
+
+    /// A peer that is ahead of us has connected or sent a new `Status`.
+    ///
+    /// The peer is added to the chain of its finalized checkpoint if it has finalized beyond our
+    /// finalized epoch, otherwise to the chain of its head. A peer only belongs to one chain.
+    pub fn add_peer(
+        &mut self,
+        network: &mut NetworkContext,
+        peer_id: PeerId,
+        remote: PeerSyncInfo,
+    ) {
+        let chain = match self.chain.upgrade() {
+            Some(chain) => chain,
+            None => return,
+        };
+        let local = PeerSyncInfo::from(&chain);
+        let slots_per_epoch = T::EthSpec::slots_per_epoch();
+        let start_slot = local.finalized_epoch.start_slot(slots_per_epoch);
+
+        let (sync_type, target_head_slot, target_head_root) =
+            if remote.finalized_epoch > local.finalized_epoch {
+                (
+                    RangeSyncType::Finalized,
+                    remote.finalized_epoch.start_slot(slots_per_epoch),
+                    remote.finalized_root,
+                )
+            } else {
+                (RangeSyncType::Head, remote.head_slot, remote.head_root)
+            };
+
+        if self
+            .finalized_chains
+            .iter()
+            .chain(self.head_chains.iter())
+            .any(|syncing_chain| {
+                syncing_chain.target_head_root == target_head_root
+                    && syncing_chain.peer_pool.contains(&peer_id)
+            })
+        {
+            // the peer is already syncing its chain
+            return;
+        }
+
+        // the peer's target has changed, remove it from its previous chain
+        self.remove_peer(network, &peer_id);
+
+        let chains = match sync_type {
+            RangeSyncType::Finalized => &mut self.finalized_chains,
+            RangeSyncType::Head => &mut self.head_chains,
+        };
+
+        if let Some(syncing_chain) = chains
+            .iter_mut()
+            .find(|syncing_chain| syncing_chain.target_head_root == target_head_root)
+        {
+            debug!(self.log, "Adding peer to syncing chain";
+                "peer" => format!("{:?}", peer_id),
+                "sync_type" => format!("{:?}", sync_type),
+                "target_head_root" => format!("{}", target_head_root),
+            );
+            syncing_chain.add_peer(network, peer_id);
+        } else {
+            debug!(self.log, "New syncing chain";
+                "peer" => format!("{:?}", peer_id),
+                "sync_type" => format!("{:?}", sync_type),
+                "start_slot" => start_slot,
+                "target_head_slot" => target_head_slot,
+                "target_head_root" => format!("{}", target_head_root),
+            );
+            chains.push(SyncingChain::new(
+                sync_type,
+                start_slot,
+                target_head_slot,
+                target_head_root,
+                peer_id,
+                self.chain.clone(),
+                self.log.clone(),
+            ));
+        }
+
+        self.update_chains(network);
+    }
+
+    /// A `BlocksByRange` response has been received.
+    pub fn blocks_by_range_response(
+        &mut self,
+        network: &mut NetworkContext,
+        peer_id: PeerId,
+        request_id: RequestId,
+        blocks: Vec<BeaconBlock<T::EthSpec>>,
+    ) {
+        let mut blocks = Some(blocks);
+        let beacon_processor = &self.beacon_processor;
+        let result = find_and_update(
+            &mut self.finalized_chains,
+            &mut self.head_chains,
+            |syncing_chain| {
+                let blocks = blocks
+                    .take()
+                    .expect("Only the matching chain consumes blocks");
+                syncing_chain.on_block_response(network, beacon_processor, request_id, blocks)
+            },
+            |syncing_chain| syncing_chain.has_pending_request(request_id),
+        );
+
+        if result.is_none() {
+            trace!(self.log, "BlocksByRange response for an unknown request";
+                "peer" => format!("{:?}", peer_id),
+                "request_id" => request_id,
+            );
+        }
+
+        self.update_chains(network);
+    }
+
+    /// An RPC request has failed.
+    pub fn inject_error(
+        &mut self,
+        network: &mut NetworkContext,
+        peer_id: PeerId,
+        request_id: RequestId,
+    ) {
+        let result = find_and_update(
+            &mut self.finalized_chains,
+            &mut self.head_chains,
+            |syncing_chain| syncing_chain.inject_error(network, request_id),
+            |syncing_chain| syncing_chain.has_pending_request(request_id),
+        );
+
+        if result.is_some() {
+            trace!(self.log, "Batch request failed";
+                "peer" => format!("{:?}", peer_id),
+                "request_id" => request_id,
+            );
+            self.update_chains(network);
+        }
+    }
+
+    /// A peer has disconnected, or no longer belongs to its chain.
+    pub fn remove_peer(&mut self, network: &mut NetworkContext, peer_id: &PeerId) {
+        // a peer belongs to at most one chain
+        let result = find_and_update(
+            &mut self.finalized_chains,
+            &mut self.head_chains,
+            |syncing_chain| Some(syncing_chain.remove_peer(network, peer_id)),
+            |syncing_chain| syncing_chain.peer_pool.contains(peer_id),
+        );
+
+        if result == Some(ProcessingResult::RemoveChain) {
+            debug!(self.log, "Syncing chain removed";
+                "peer" => format!("{:?}", peer_id),
+            );
+            // another chain may need to be synced in place of the removed chain
+            self.update_chains(network);
+        }
+    }
+
+    /// Removes chains whose target is already known to us and selects the chains to sync.
+    ///
+    /// The finalized chain with the most peers is synced, unless the currently syncing finalized
+    /// chain has as many peers. Head chains are only synced once no finalized chain remains.
+    fn update_chains(&mut self, network: &mut NetworkContext) {
+        let chain = match self.chain.upgrade() {
+            Some(chain) => chain,
+            None => return,
+        };
+
+        let log = &self.log;
+        for chains in [&mut self.finalized_chains, &mut self.head_chains].iter_mut() {
+            chains.retain(|syncing_chain| {
+                if let Ok(Some(_)) = chain.block_at_root(syncing_chain.target_head_root) {
+                    debug!(log,"Syncing chain target is known, removing chain";
+                        "target_head_root" => format!("{}", syncing_chain.target_head_root),
+                    );
+                    syncing_chain.status_peers(network);
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+
+        let most_peers = self
+            .finalized_chains
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, syncing_chain)| syncing_chain.peer_pool.len())
+            .map(|(index, _)| index);
+        let syncing = self
+            .finalized_chains
+            .iter()
+            .position(|syncing_chain| syncing_chain.state == ChainSyncingState::Syncing);
+
+        let selected = match (syncing, most_peers) {
+            (Some(syncing), Some(most_peers))
+                if self.finalized_chains[syncing].peer_pool.len()
+                    >= self.finalized_chains[most_peers].peer_pool.len() =>
+            {
+                Some(syncing)
+            }
+            (_, most_peers) => most_peers,
+        };
+
+        match selected {
+            Some(selected) => {
+                for (index, syncing_chain) in self.finalized_chains.iter_mut().enumerate() {
+                    if index == selected {
+                        syncing_chain.start_syncing(network);
+                    } else {
+                        syncing_chain.stop_syncing();
+                    }
+                }
+                for syncing_chain in self.head_chains.iter_mut() {
+                    syncing_chain.stop_syncing();
+                }
+            }
+            None => {
+                for syncing_chain in self.head_chains.iter_mut() {
+                    syncing_chain.start_syncing(network);
+                }
+            }
+        }
+    }
+}
+
+/// Applies `update` to the first chain (finalized chains first) that satisfies `matches`,
+/// removing the chain if it completed or failed. Returns `None` if no chain matched.
+fn find_and_update<T, F, M>(
+    finalized_chains: &mut Vec<SyncingChain<T>>,
+    head_chains: &mut Vec<SyncingChain<T>>,
+    mut update: F,
+    matches: M,
+) -> Option<ProcessingResult>
+where
+    T: BeaconChainTypes,
+    F: FnMut(&mut SyncingChain<T>) -> Option<ProcessingResult>,
+    M: Fn(&SyncingChain<T>) -> bool,
+{
+    for chains in [finalized_chains, head_chains].iter_mut() {
+        if let Some(index) = chains
+            .iter()
+            .position(|syncing_chain| matches(syncing_chain))
+        {
+            let result = update(&mut chains[index]);
+            if result == Some(ProcessingResult::RemoveChain) {
+                chains.remove(index);
+            }
+            return result;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beacon_processor::BeaconProcessor;
+    use crate::pending_work::PendingWork;
+    use beacon_chain::test_utils::{BeaconChainHarness, HarnessType};
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+    use types::test_utils::generate_deterministic_keypairs;
+    use types::{Epoch, MinimalEthSpec};
+
+    type TestChain = BeaconChain<HarnessType<MinimalEthSpec>>;
+
+    fn null_logger() -> Logger {
+        Logger::root(slog::Discard, slog::o!())
+    }
+
+    /// Adds a peer which claims to have finalized the block with `root`, beyond our finalized
+    /// epoch.
+    fn add_finalized_peer(
+        range_sync: &mut RangeSync<HarnessType<MinimalEthSpec>>,
+        network: &mut NetworkContext,
+        beacon_chain: &Arc<TestChain>,
+        peer_id: &PeerId,
+        root: u8,
+    ) {
+        let mut remote = PeerSyncInfo::from(beacon_chain);
+        remote.finalized_epoch = Epoch::new(4);
+        remote.finalized_root = Hash256::repeat_byte(root);
+        range_sync.add_peer(network, peer_id.clone(), remote);
+    }
+
+    /// Returns the target root and whether it is syncing, of each chain.
+    fn chains(range_sync: &RangeSync<HarnessType<MinimalEthSpec>>) -> Vec<(Hash256, bool)> {
+        range_sync
+            .progress()
+            .into_iter()
+            .map(|progress| (progress.target_head_root, progress.syncing))
+            .collect()
+    }
+
+    #[test]
+    fn syncs_the_finalized_chain_with_the_most_peers() {
+        let beacon_chain = Arc::new(
+            BeaconChainHarness::new(MinimalEthSpec, generate_deterministic_keypairs(8)).chain,
+        );
+        let (network_send, _network_recv) = mpsc::unbounded_channel();
+        let mut network = NetworkContext::new(network_send, null_logger());
+        let beacon_processor = BeaconProcessor::new(PendingWork::default(), null_logger());
+        let mut range_sync = RangeSync::new(
+            Arc::downgrade(&beacon_chain),
+            beacon_processor.sender(),
+            null_logger(),
+        );
+        let peers = (0..4).map(|_| PeerId::random()).collect::<Vec<_>>();
+        let (root_a, root_b) = (Hash256::repeat_byte(1), Hash256::repeat_byte(2));
+
+        add_finalized_peer(&mut range_sync, &mut network, &beacon_chain, &peers[0], 1);
+        add_finalized_peer(&mut range_sync, &mut network, &beacon_chain, &peers[1], 2);
+        assert_eq!(
+            chains(&range_sync),
+            vec![(root_a, true), (root_b, false)],
+            "the syncing chain should be kept while it has as many peers"
+        );
+
+        add_finalized_peer(&mut range_sync, &mut network, &beacon_chain, &peers[2], 2);
+        assert_eq!(
+            chains(&range_sync),
+            vec![(root_a, false), (root_b, true)],
+            "should switch to the chain with the most peers"
+        );
+
+        let mut remote = PeerSyncInfo::from(&beacon_chain);
+        remote.head_slot = Slot::new(100);
+        remote.head_root = Hash256::repeat_byte(3);
+        range_sync.add_peer(&mut network, peers[3].clone(), remote);
+        assert_eq!(
+            range_sync.sync_type(),
+            Some(RangeSyncType::Finalized),
+            "finalized chains should be synced first"
+        );
+        assert_eq!(
+            chains(&range_sync)[2],
+            (Hash256::repeat_byte(3), false),
+            "the head chain should wait for the finalized chains"
+        );
+
+        range_sync.remove_peer(&mut network, &peers[1]);
+        range_sync.remove_peer(&mut network, &peers[2]);
+        assert_eq!(
+            chains(&range_sync),
+            vec![(root_a, true), (Hash256::repeat_byte(3), false)],
+            "should switch back once the chain without peers is removed"
+        );
+
+        range_sync.remove_peer(&mut network, &peers[0]);
+        assert_eq!(range_sync.sync_type(), Some(RangeSyncType::Head));
+        assert_eq!(
+            chains(&range_sync),
+            vec![(Hash256::repeat_byte(3), true)],
+            "should sync the head chain once no finalized chain remains"
+        );
+    }
+}
@@ -1,54 +1,61 @@
 use super::manager::SyncMessage;
-use crate::service::NetworkMessage;
-use beacon_chain::{
-    AttestationProcessingOutcome, BeaconChain, BeaconChainTypes, BlockProcessingOutcome,
-};
+use super::manager::SyncProgress;
+use crate::beacon_processor::{BeaconProcessor, Work};
+use crate::pending_work::PendingWork;
+use crate::service::{current_epoch, NetworkMessage};
+use beacon_chain::{BeaconChain, BeaconChainTypes, OperationProcessingOutcome};
 use eth2_libp2p::rpc::methods::*;
-use eth2_libp2p::rpc::{RPCEvent, RPCRequest, RPCResponse, RequestId};
-use eth2_libp2p::PeerId;
-use slog::{debug, error, info, o, trace, warn};
+use eth2_libp2p::rpc::{RPCEvent, RPCRequest, RPCResponse, RequestId, MAX_RPC_SIZE};
+use eth2_libp2p::{PeerAction, PeerId};
+use parking_lot::RwLock;
+use slog::{debug, info, o, trace, warn};
+use slot_clock::ClockDriftCheck;
 use ssz::Encode;
+use std::fmt::Debug;
 use std::sync::Arc;
 use store::Store;
 use tokio::sync::{mpsc, oneshot};
-use tree_hash::SignedRoot;
-use types::{Attestation, BeaconBlock, Epoch, EthSpec, Hash256, Slot};
-
-//TODO: Put a maximum limit on the number of block that can be requested.
-//TODO: Rate limit requests
+use types::{
+    AggregateAndProof, Attestation, AttesterSlashing, BeaconBlock, Epoch, EthSpec, ForkDigest,
+    Hash256, ProposerSlashing, Slot, VoluntaryExit,
+};
 
 /// If a block is more than `FUTURE_SLOT_TOLERANCE` slots ahead of our slot clock, we drop it.
 /// Otherwise we queue it.
 pub(crate) const FUTURE_SLOT_TOLERANCE: u64 = 1;
 
-const SHOULD_FORWARD_GOSSIP_BLOCK: bool = true;
-const SHOULD_NOT_FORWARD_GOSSIP_BLOCK: bool = false;
+/// If the head slots reported by peers are typically more than `CLOCK_DRIFT_TOLERANCE` slots
+/// ahead of our slot clock, we warn that the system clock may be out of sync.
+const CLOCK_DRIFT_TOLERANCE: u64 = 2;
+
+const SHOULD_FORWARD_GOSSIP_OPERATION: bool = true;
+const SHOULD_NOT_FORWARD_GOSSIP_OPERATION: bool = false;
 
 /// Keeps track of syncing information for known connected peers.
 #[derive(Clone, Copy, Debug)]
 pub struct PeerSyncInfo {
-    fork_version: [u8; 4],
+    fork_digest: ForkDigest,
     pub finalized_root: Hash256,
     pub finalized_epoch: Epoch,
     pub head_root: Hash256,
     pub head_slot: Slot,
 }
 
-impl From<HelloMessage> for PeerSyncInfo {
-    fn from(hello: HelloMessage) -> PeerSyncInfo {
+impl From<StatusMessage> for PeerSyncInfo {
+    fn from(status: StatusMessage) -> PeerSyncInfo {
         PeerSyncInfo {
-            fork_version: hello.fork_version,
-            finalized_root: hello.finalized_root,
-            finalized_epoch: hello.finalized_epoch,
-            head_root: hello.head_root,
-            head_slot: hello.head_slot,
+            fork_digest: status.fork_digest,
+            finalized_root: status.finalized_root,
+            finalized_epoch: status.finalized_epoch,
+            head_root: status.head_root,
+            head_slot: status.head_slot,
         }
     }
 }
 
 impl<T: BeaconChainTypes> From<&Arc<BeaconChain<T>>> for PeerSyncInfo {
     fn from(chain: &Arc<BeaconChain<T>>) -> PeerSyncInfo {
-        Self::from(hello_message(chain))
+        Self::from(status_message(chain))
     }
 }
 
@@ -61,8 +68,14 @@ pub struct MessageProcessor<T: BeaconChainTypes> {
     sync_send: mpsc::UnboundedSender<SyncMessage<T::EthSpec>>,
     /// A oneshot channel for destroying the sync thread.
     _sync_exit: oneshot::Sender<()>,
+    /// Counts the messages sent to the sync thread until they have been handled.
+    pending_work: PendingWork,
+    /// The queue of work for the beacon chain, which stops its workers once dropped.
+    beacon_processor: BeaconProcessor<T::EthSpec>,
     /// A nextwork context to return and handle RPC requests.
     network: NetworkContext,
+    /// Compares our slot clock to the head slots reported by peers.
+    clock_drift: ClockDriftCheck,
     /// The `RPCHandler` logger.
     log: slog::Logger,
 }
@@ -73,30 +86,50 @@ impl<T: BeaconChainTypes> MessageProcessor<T> {
         executor: &tokio::runtime::TaskExecutor,
         beacon_chain: Arc<BeaconChain<T>>,
         network_send: mpsc::UnboundedSender<NetworkMessage>,
+        sync_progress: Arc<RwLock<SyncProgress>>,
+        pending_work: PendingWork,
         log: &slog::Logger,
     ) -> Self {
         let sync_logger = log.new(o!("service"=> "sync"));
         let sync_network_context = NetworkContext::new(network_send.clone(), sync_logger.clone());
+        let beacon_processor = BeaconProcessor::new(
+            pending_work.clone(),
+            log.new(o!("service" => "beacon_processor")),
+        );
 
         // spawn the sync thread
         let (sync_send, _sync_exit) = super::manager::spawn(
             executor,
             Arc::downgrade(&beacon_chain),
             sync_network_context,
+            beacon_processor.sender(),
+            sync_progress,
+            pending_work.clone(),
             sync_logger,
         );
 
+        beacon_processor.spawn_workers(
+            beacon_chain.clone(),
+            network_send.clone(),
+            sync_send.clone(),
+        );
+
         MessageProcessor {
             chain: beacon_chain,
             sync_send,
             _sync_exit,
+            pending_work,
+            beacon_processor,
             network: NetworkContext::new(network_send, log.clone()),
+            clock_drift: ClockDriftCheck::new(CLOCK_DRIFT_TOLERANCE),
             log: log.clone(),
         }
     }
 
     fn send_to_sync(&mut self, message: SyncMessage<T::EthSpec>) {
+        self.pending_work.add();
         self.sync_send.try_send(message).unwrap_or_else(|_| {
+            self.pending_work.done(1);
             warn!(
                 self.log,
                 "Could not send message to the sync service";
@@ -113,57 +146,88 @@ impl<T: BeaconChainTypes> MessageProcessor<T> {
 
     /// Handle the connection of a new peer.
     ///
-    /// Sends a `Hello` message to the peer.
+    /// Sends a `Status` message to the peer.
     pub fn on_connect(&mut self, peer_id: PeerId) {
-        self.network
-            .send_rpc_request(None, peer_id, RPCRequest::Hello(hello_message(&self.chain)));
+        self.network.send_rpc_request(
+            None,
+            peer_id,
+            RPCRequest::Status(status_message(&self.chain)),
+        );
     }
 
-    /// Handle a `Hello` request.
+    /// Handle a `Status` request.
     ///
-    /// Processes the `HelloMessage` from the remote peer and sends back our `Hello`.
-    pub fn on_hello_request(
+    /// Processes the `StatusMessage` from the remote peer and sends back our `Status`.
+    pub fn on_status_request(
         &mut self,
         peer_id: PeerId,
         request_id: RequestId,
-        hello: HelloMessage,
+        status: StatusMessage,
     ) {
-        // ignore hello responses if we are shutting down
-        trace!(self.log, "HelloRequest"; "peer" => format!("{:?}", peer_id));
+        trace!(self.log, "StatusRequest"; "peer" => format!("{:?}", peer_id));
 
-        // Say hello back.
+        // Respond with our status, even if the peer is about to be disconnected, so that it
+        // learns why.
         self.network.send_rpc_response(
             peer_id.clone(),
             request_id,
-            RPCResponse::Hello(hello_message(&self.chain)),
+            RPCResponse::Status(status_message(&self.chain)),
         );
 
-        self.process_hello(peer_id, hello);
+        self.process_status(peer_id, status);
     }
 
-    /// Process a `Hello` response from a peer.
-    pub fn on_hello_response(&mut self, peer_id: PeerId, hello: HelloMessage) {
-        trace!(self.log, "HelloResponse"; "peer" => format!("{:?}", peer_id));
+    /// Process a `Status` response from a peer.
+    pub fn on_status_response(&mut self, peer_id: PeerId, status: StatusMessage) {
+        trace!(self.log, "StatusResponse"; "peer" => format!("{:?}", peer_id));
 
-        // Process the hello message, without sending back another hello.
-        self.process_hello(peer_id, hello);
+        // Process the status message, without sending back another status.
+        self.process_status(peer_id, status);
     }
 
-    /// Process a `Hello` message, requesting new blocks if appropriate.
+    /// Process a `Status` message, requesting new blocks if appropriate.
     ///
-    /// Disconnects the peer if required.
-    fn process_hello(&mut self, peer_id: PeerId, hello: HelloMessage) {
-        let remote = PeerSyncInfo::from(hello);
+    /// Peers on a different fork, or with a finalized checkpoint that conflicts with our chain,
+    /// are sent a `Goodbye` which closes the connection.
+    fn process_status(&mut self, peer_id: PeerId, status: StatusMessage) {
+        let remote = PeerSyncInfo::from(status);
         let local = PeerSyncInfo::from(&self.chain);
+        let current_epoch = current_epoch(&self.chain);
 
         let start_slot = |epoch: Epoch| epoch.start_slot(T::EthSpec::slots_per_epoch());
 
-        if local.fork_version != remote.fork_version {
+        if let Ok(current_slot) = self.chain.slot() {
+            if let Some(slots_behind) = self.clock_drift.observe(current_slot, remote.head_slot) {
+                warn!(
+                    self.log, "System clock may be behind the network";
+                    "slots_behind_peers" => slots_behind,
+                    "local_slot" => current_slot,
+                    "msg" => "ensure the system clock is synchronized (e.g., via NTP)"
+                );
+            }
+        }
+
+        if local.fork_digest != remote.fork_digest {
             // The node is on a different network/fork, disconnect them.
             debug!(
                 self.log, "HandshakeFailure";
                 "peer" => format!("{:?}", peer_id),
-                "reason" => "network_id"
+                "reason" => "fork_digest",
+                "local_fork_digest" => hex::encode(local.fork_digest),
+                "remote_fork_digest" => hex::encode(remote.fork_digest),
+            );
+
+            self.network
+                .disconnect(peer_id.clone(), GoodbyeReason::IrrelevantNetwork);
+        } else if remote.finalized_epoch > current_epoch {
+            // The remote claims to have finalized an epoch which has not yet started, it cannot
+            // be on our chain.
+            debug!(
+                self.log, "HandshakeFailure";
+                "peer" => format!("{:?}", peer_id),
+                "reason" => "future finalized epoch",
+                "remote_finalized_epoch" => remote.finalized_epoch,
+                "current_epoch" => current_epoch,
             );
 
             self.network
@@ -236,16 +300,20 @@ impl<T: BeaconChainTypes> MessageProcessor<T> {
         }
     }
 
-    /// Handle a `RecentBeaconBlocks` request from the peer.
-    pub fn on_recent_beacon_blocks_request(
+    /// Handle a `BlocksByRoot` request from the peer.
+    ///
+    /// At most `MAX_REQUEST_BLOCKS` blocks are returned, further limited to fit within
+    /// `MAX_RPC_SIZE`.
+    pub fn on_blocks_by_root_request(
         &mut self,
         peer_id: PeerId,
         request_id: RequestId,
-        request: RecentBeaconBlocksRequest,
+        request: BlocksByRootRequest,
     ) {
         let blocks: Vec<BeaconBlock<_>> = request
             .block_roots
             .iter()
+            .take(MAX_REQUEST_BLOCKS as usize)
             .filter_map(|root| {
                 if let Ok(Some(block)) = self.chain.store.get::<BeaconBlock<T::EthSpec>>(root) {
                     Some(block)
@@ -261,10 +329,11 @@ impl<T: BeaconChainTypes> MessageProcessor<T> {
                 }
             })
             .collect();
+        let blocks = limit_response_size(blocks);
 
         debug!(
             self.log,
-            "RecentBeaconBlocksRequest";
+            "BlocksByRootRequest";
             "peer" => format!("{:?}", peer_id),
             "requested" => request.block_roots.len(),
             "returned" => blocks.len(),
@@ -273,39 +342,62 @@ impl<T: BeaconChainTypes> MessageProcessor<T> {
         self.network.send_rpc_response(
             peer_id,
             request_id,
-            RPCResponse::BeaconBlocks(blocks.as_ssz_bytes()),
+            RPCResponse::BlocksByRoot(blocks.as_ssz_bytes()),
         )
     }
 
-    /// Handle a `BeaconBlocks` request from the peer.
-    pub fn on_beacon_blocks_request(
+    /// Handle a `BlocksByRange` request from the peer.
+    ///
+    /// Blocks are read from the chain of `head_block_root`, or from our canonical chain if that
+    /// block is unknown. At most `MAX_REQUEST_BLOCKS` blocks are returned, further limited to fit
+    /// within `MAX_RPC_SIZE`.
+    pub fn on_blocks_by_range_request(
         &mut self,
         peer_id: PeerId,
         request_id: RequestId,
-        req: BeaconBlocksRequest,
+        req: BlocksByRangeRequest,
     ) {
         debug!(
             self.log,
-            "BeaconBlocksRequest";
+            "BlocksByRangeRequest";
             "peer" => format!("{:?}", peer_id),
             "count" => req.count,
             "start_slot" => req.start_slot,
+            "step" => req.step,
         );
 
-        //TODO: Optimize this
-        // Currently for skipped slots, the blocks returned could be less than the requested range.
-        // In the current implementation we read from the db then filter out out-of-range blocks.
-        // Improving the db schema to prevent this would be ideal.
+        // a step of 0 would request the same slot repeatedly, treat it as 1.
+        let step = std::cmp::max(req.step, 1);
+        let count = std::cmp::min(req.count, MAX_REQUEST_BLOCKS);
+        let start_slot = req.start_slot;
+        let end_slot = start_slot.saturating_add(count.saturating_mul(step));
 
-        let mut blocks: Vec<BeaconBlock<T::EthSpec>> = self
-            .chain
-            .rev_iter_block_roots()
-            .filter(|(_root, slot)| {
-                req.start_slot <= slot.as_u64() && req.start_slot + req.count > slot.as_u64()
-            })
-            .take_while(|(_root, slot)| req.start_slot <= slot.as_u64())
-            .filter_map(|(root, _slot)| {
-                if let Ok(Some(block)) = self.chain.store.get::<BeaconBlock<T::EthSpec>>(&root) {
+        let iter = match self.chain.rev_iter_block_roots_from(req.head_block_root) {
+            Ok(iter) => iter,
+            Err(_) => {
+                debug!(
+                    self.log,
+                    "Peer requested blocks from an unknown head";
+                    "peer" => format!("{:?}", peer_id),
+                    "head_block_root" => format!("{}", req.head_block_root),
+                );
+                self.chain.rev_iter_block_roots()
+            }
+        };
+
+        let mut block_roots: Vec<Hash256> = iter
+            .skip_while(|(_root, slot)| slot.as_u64() >= end_slot)
+            .take_while(|(_root, slot)| slot.as_u64() >= start_slot)
+            .map(|(root, _slot)| root)
+            .collect();
+        // skipped slots repeat the root of the prior block, only read each block once.
+        block_roots.dedup();
+        block_roots.reverse();
+
+        let blocks: Vec<BeaconBlock<T::EthSpec>> = block_roots
+            .iter()
+            .filter_map(|root| {
+                if let Ok(Some(block)) = self.chain.store.get::<BeaconBlock<T::EthSpec>>(root) {
                     Some(block)
                 } else {
                     warn!(
@@ -317,17 +409,18 @@ impl<T: BeaconChainTypes> MessageProcessor<T> {
                     None
                 }
             })
-            .filter(|block| block.slot >= req.start_slot)
+            // a skipped slot may return a block prior to the range or off the step.
+            .filter(|block| {
+                let slot = block.slot.as_u64();
+                slot >= start_slot && (slot - start_slot) % step == 0
+            })
             .collect();
-
-        blocks.reverse();
-        blocks.dedup_by_key(|brs| brs.slot);
+        let blocks = limit_response_size(blocks);
 
         debug!(
             self.log,
-            "BeaconBlocksRequest response";
+            "BlocksByRangeRequest response";
             "peer" => format!("{:?}", peer_id),
-            "msg" => "Failed to return all requested hashes",
             "start_slot" => req.start_slot,
             "current_slot" => self.chain.slot().unwrap_or_else(|_| Slot::from(0_u64)).as_u64(),
             "requested" => req.count,
@@ -337,12 +430,12 @@ impl<T: BeaconChainTypes> MessageProcessor<T> {
         self.network.send_rpc_response(
             peer_id,
             request_id,
-            RPCResponse::BeaconBlocks(blocks.as_ssz_bytes()),
+            RPCResponse::BlocksByRange(blocks.as_ssz_bytes()),
         )
     }
 
-    /// Handle a `BeaconBlocks` response from the peer.
-    pub fn on_beacon_blocks_response(
+    /// Handle a `BlocksByRange` response from the peer.
+    pub fn on_blocks_by_range_response(
         &mut self,
         peer_id: PeerId,
         request_id: RequestId,
@@ -350,20 +443,20 @@ impl<T: BeaconChainTypes> MessageProcessor<T> {
     ) {
         debug!(
             self.log,
-            "BeaconBlocksResponse";
+            "BlocksByRangeResponse";
             "peer" => format!("{:?}", peer_id),
             "count" => beacon_blocks.len(),
         );
 
-        self.send_to_sync(SyncMessage::BeaconBlocksResponse {
+        self.send_to_sync(SyncMessage::BlocksByRangeResponse {
             peer_id,
             request_id,
             beacon_blocks,
         });
     }
 
-    /// Handle a `RecentBeaconBlocks` response from the peer.
-    pub fn on_recent_beacon_blocks_response(
+    /// Handle a `BlocksByRoot` response from the peer.
+    pub fn on_blocks_by_root_response(
         &mut self,
         peer_id: PeerId,
         request_id: RequestId,
@@ -371,118 +464,160 @@ impl<T: BeaconChainTypes> MessageProcessor<T> {
     ) {
         debug!(
             self.log,
-            "RecentBeaconBlocksResponse";
+            "BlocksByRootResponse";
             "peer" => format!("{:?}", peer_id),
             "count" => beacon_blocks.len(),
         );
 
-        self.send_to_sync(SyncMessage::RecentBeaconBlocksResponse {
+        self.send_to_sync(SyncMessage::BlocksByRootResponse {
             peer_id,
             request_id,
             beacon_blocks,
         });
     }
 
-    /// Process a gossip message declaring a new block.
+    /// An outbound RPC request to `peer_id` has failed.
+    pub fn on_rpc_error(&mut self, peer_id: PeerId, request_id: RequestId) {
+        self.send_to_sync(SyncMessage::RPCError(peer_id, request_id));
+    }
+
+    /// Queues a gossip block for processing. The block is propagated once it has been verified.
+    pub fn on_block_gossip(
+        &mut self,
+        message_id: String,
+        peer_id: PeerId,
+        block: BeaconBlock<T::EthSpec>,
+    ) {
+        self.beacon_processor.send(Work::GossipBlock {
+            message_id,
+            peer_id,
+            block,
+        });
+    }
+
+    /// Queues a gossip aggregate attestation for processing. The aggregate is propagated once it
+    /// has been verified.
+    pub fn on_aggregate_gossip(
+        &mut self,
+        message_id: String,
+        peer_id: PeerId,
+        aggregate_and_proof: AggregateAndProof<T::EthSpec>,
+    ) {
+        self.beacon_processor.send(Work::GossipAggregate {
+            message_id,
+            peer_id,
+            aggregate_and_proof,
+        });
+    }
+
+    /// Queues a gossip unaggregated attestation for processing.
+    pub fn on_attestation_gossip(&mut self, peer_id: PeerId, attestation: Attestation<T::EthSpec>) {
+        self.beacon_processor.send(Work::GossipAttestation {
+            peer_id,
+            attestation,
+        });
+    }
+
+    /// Process a gossip message declaring a new voluntary exit.
     ///
-    /// Attempts to apply to block to the beacon chain. May queue the block for later processing.
+    /// Returns a `bool` which, if `true`, indicates we should forward the exit to our peers.
+    pub fn on_voluntary_exit_gossip(&mut self, peer_id: PeerId, exit: VoluntaryExit) -> bool {
+        let validator_index = exit.validator_index;
+        let result = self.chain.process_voluntary_exit(exit);
+
+        self.on_operation_outcome(peer_id, "voluntary exit", validator_index, result)
+    }
+
+    /// Process a gossip message declaring a new proposer slashing.
     ///
-    /// Returns a `bool` which, if `true`, indicates we should forward the block to our peers.
-    pub fn on_block_gossip(&mut self, peer_id: PeerId, block: BeaconBlock<T::EthSpec>) -> bool {
-        match self.chain.process_block(block.clone()) {
-            Ok(outcome) => match outcome {
-                BlockProcessingOutcome::Processed { .. } => {
-                    trace!(self.log, "Gossipsub block processed";
-                            "peer_id" => format!("{:?}",peer_id));
-                    SHOULD_FORWARD_GOSSIP_BLOCK
-                }
-                BlockProcessingOutcome::ParentUnknown { parent: _ } => {
-                    // Inform the sync manager to find parents for this block
-                    trace!(self.log, "Block with unknown parent received";
-                            "peer_id" => format!("{:?}",peer_id));
-                    self.send_to_sync(SyncMessage::UnknownBlock(peer_id, block.clone()));
-                    SHOULD_FORWARD_GOSSIP_BLOCK
-                }
-                BlockProcessingOutcome::FutureSlot {
-                    present_slot,
-                    block_slot,
-                } if present_slot + FUTURE_SLOT_TOLERANCE >= block_slot => {
-                    //TODO: Decide the logic here
-                    SHOULD_FORWARD_GOSSIP_BLOCK
-                }
-                BlockProcessingOutcome::BlockIsAlreadyKnown => SHOULD_FORWARD_GOSSIP_BLOCK,
-                other => {
-                    warn!(
-                        self.log,
-                        "Invalid gossip beacon block";
-                        "outcome" => format!("{:?}", other),
-                        "block root" => format!("{}", Hash256::from_slice(&block.signed_root()[..])),
-                        "block slot" => block.slot
-                    );
-                    trace!(
-                        self.log,
-                        "Invalid gossip beacon block ssz";
-                        "ssz" => format!("0x{}", hex::encode(block.as_ssz_bytes())),
-                    );
-                    SHOULD_NOT_FORWARD_GOSSIP_BLOCK //TODO: Decide if we want to forward these
-                }
-            },
-            Err(e) => {
-                error!(
-                    self.log,
-                    "Error processing gossip beacon block";
-                    "error" => format!("{:?}", e),
-                    "block slot" => block.slot
-                );
-                trace!(
-                    self.log,
-                    "Erroneous gossip beacon block ssz";
-                    "ssz" => format!("0x{}", hex::encode(block.as_ssz_bytes())),
-                );
-                SHOULD_NOT_FORWARD_GOSSIP_BLOCK
-            }
-        }
+    /// Returns a `bool` which, if `true`, indicates we should forward the slashing to our peers.
+    pub fn on_proposer_slashing_gossip(
+        &mut self,
+        peer_id: PeerId,
+        slashing: ProposerSlashing,
+    ) -> bool {
+        let validator_index = slashing.proposer_index;
+        let result = self.chain.process_proposer_slashing(slashing);
+
+        self.on_operation_outcome(peer_id, "proposer slashing", validator_index, result)
     }
 
-    /// Process a gossip message declaring a new attestation.
+    /// Process a gossip message declaring a new attester slashing.
     ///
-    /// Not currently implemented.
-    pub fn on_attestation_gossip(&mut self, _peer_id: PeerId, msg: Attestation<T::EthSpec>) {
-        match self.chain.process_attestation(msg.clone()) {
-            Ok(outcome) => {
+    /// Returns a `bool` which, if `true`, indicates we should forward the slashing to our peers.
+    pub fn on_attester_slashing_gossip(
+        &mut self,
+        peer_id: PeerId,
+        slashing: AttesterSlashing<T::EthSpec>,
+    ) -> bool {
+        // Log the first of the attesters, as an aid to identifying the slashing.
+        let validator_index = slashing
+            .attestation_1
+            .attesting_indices
+            .first()
+            .copied()
+            .unwrap_or(0);
+        let result = self.chain.process_attester_slashing(slashing);
+
+        self.on_operation_outcome(peer_id, "attester slashing", validator_index, result)
+    }
+
+    /// Logs the `result` of processing some gossip operation, returning `true` if the operation
+    /// should be forwarded to our peers.
+    ///
+    /// Operations which are already known are not forwarded, our peers will have received them
+    /// from the same source as us.
+    fn on_operation_outcome<E: Debug>(
+        &mut self,
+        peer_id: PeerId,
+        kind: &str,
+        validator_index: u64,
+        result: Result<OperationProcessingOutcome, E>,
+    ) -> bool {
+        match result {
+            Ok(OperationProcessingOutcome::Processed) => {
                 info!(
                     self.log,
-                    "Processed attestation";
-                    "source" => "gossip",
-                    "outcome" => format!("{:?}", outcome)
+                    "Processed gossip operation";
+                    "kind" => kind,
+                    "validator_index" => validator_index,
+                    "peer_id" => format!("{:?}", peer_id),
                 );
-
-                if outcome != AttestationProcessingOutcome::Processed {
-                    trace!(
-                        self.log,
-                        "Invalid gossip attestation ssz";
-                        "ssz" => format!("0x{}", hex::encode(msg.as_ssz_bytes())),
-                    );
-                }
+                SHOULD_FORWARD_GOSSIP_OPERATION
             }
-            Err(e) => {
+            Ok(OperationProcessingOutcome::AlreadyKnown) => {
                 trace!(
                     self.log,
-                    "Erroneous gossip attestation ssz";
-                    "ssz" => format!("0x{}", hex::encode(msg.as_ssz_bytes())),
+                    "Gossip operation already known";
+                    "kind" => kind,
+                    "validator_index" => validator_index,
+                );
+                SHOULD_NOT_FORWARD_GOSSIP_OPERATION
+            }
+            Err(e) => {
+                debug!(
+                    self.log,
+                    "Invalid gossip operation";
+                    "kind" => kind,
+                    "validator_index" => validator_index,
+                    "peer_id" => format!("{:?}", peer_id),
+                    "error" => format!("{:?}", e),
                 );
-                error!(self.log, "Invalid gossip attestation"; "error" => format!("{:?}", e));
+                SHOULD_NOT_FORWARD_GOSSIP_OPERATION
             }
         }
     }
 }
 
-/// Build a `HelloMessage` representing the state of the given `beacon_chain`.
-pub(crate) fn hello_message<T: BeaconChainTypes>(beacon_chain: &BeaconChain<T>) -> HelloMessage {
+/// Build a `StatusMessage` representing the state of the given `beacon_chain`.
+///
+/// The fork digest is that of the fork at the current epoch, which may be later than the epoch
+/// of the head.
+pub(crate) fn status_message<T: BeaconChainTypes>(beacon_chain: &BeaconChain<T>) -> StatusMessage {
     let state = &beacon_chain.head().beacon_state;
 
-    HelloMessage {
-        fork_version: state.fork.current_version,
+    StatusMessage {
+        fork_digest: beacon_chain.fork_digest(current_epoch(beacon_chain)),
         finalized_root: state.finalized_checkpoint.root,
         finalized_epoch: state.finalized_checkpoint.epoch,
         head_root: beacon_chain.head().beacon_block_root,
@@ -490,17 +625,37 @@ pub(crate) fn hello_message<T: BeaconChainTypes>(beacon_chain: &BeaconChain<T>)
     }
 }
 
+/// Truncates `blocks` so that their SSZ encoding fits within a single RPC response.
+fn limit_response_size<E: EthSpec>(blocks: Vec<BeaconBlock<E>>) -> Vec<BeaconBlock<E>> {
+    let mut response_size = 0;
+    blocks
+        .into_iter()
+        .take_while(|block| {
+            response_size += block.ssz_bytes_len() + ssz::BYTES_PER_LENGTH_OFFSET;
+            response_size <= MAX_RPC_SIZE
+        })
+        .collect()
+}
+
 /// Wraps a Network Channel to employ various RPC/Sync related network functionality.
 pub struct NetworkContext {
     /// The network channel to relay messages to the Network service.
     network_send: mpsc::UnboundedSender<NetworkMessage>,
+    /// The id of the next request that expects a response. This is an internal accounting
+    /// mechanism, request ids are never sent to any peers. Id 0 is reserved for requests that do
+    /// not require an id.
+    request_id: RequestId,
     /// Logger for the `NetworkContext`.
     log: slog::Logger,
 }
 
 impl NetworkContext {
     pub fn new(network_send: mpsc::UnboundedSender<NetworkMessage>, log: slog::Logger) -> Self {
-        Self { network_send, log }
+        Self {
+            network_send,
+            request_id: 1,
+            log,
+        }
     }
 
     pub fn disconnect(&mut self, peer_id: PeerId, reason: GoodbyeReason) {
@@ -510,8 +665,37 @@ impl NetworkContext {
             "reason" => format!("{:?}", reason),
             "peer_id" => format!("{:?}", peer_id),
         );
+        // the connection is closed once the goodbye has been sent
         self.send_rpc_request(None, peer_id, RPCRequest::Goodbye(reason))
-        // TODO: disconnect peers.
+    }
+
+    /// Informs the peer manager of the behaviour of `peer_id`, which may cause the peer to be
+    /// disconnected or banned.
+    pub fn report_peer(&mut self, peer_id: PeerId, action: PeerAction) {
+        self.network_send
+            .try_send(NetworkMessage::ReportPeer { peer_id, action })
+            .unwrap_or_else(|_| {
+                warn!(
+                    self.log,
+                    "Could not send peer report to the network service"
+                )
+            });
+    }
+
+    /// Informs the network service that the gossip message with `message_id` should be forwarded
+    /// to our peers.
+    pub fn propagate(&mut self, message_id: String, propagation_source: PeerId) {
+        self.network_send
+            .try_send(NetworkMessage::Propagate {
+                propagation_source,
+                message_id,
+            })
+            .unwrap_or_else(|_| {
+                warn!(
+                    self.log,
+                    "Could not send propagation request to the network service"
+                )
+            });
     }
 
     pub fn send_rpc_request(
@@ -525,6 +709,46 @@ impl NetworkContext {
         self.send_rpc_event(peer_id, RPCEvent::Request(request_id, rpc_request));
     }
 
+    /// Sends a `BlocksByRange` request to `peer_id`, returning the id of the request.
+    pub fn blocks_by_range_request(
+        &mut self,
+        peer_id: PeerId,
+        request: BlocksByRangeRequest,
+    ) -> RequestId {
+        trace!(
+            self.log,
+            "Sending BlocksByRange Request";
+            "method" => "BlocksByRange",
+            "count" => request.count,
+            "peer" => format!("{:?}", peer_id)
+        );
+        self.send_rpc_request_with_id(peer_id, RPCRequest::BlocksByRange(request))
+    }
+
+    /// Sends a `BlocksByRoot` request to `peer_id`, returning the id of the request.
+    pub fn blocks_by_root_request(
+        &mut self,
+        peer_id: PeerId,
+        request: BlocksByRootRequest,
+    ) -> RequestId {
+        trace!(
+            self.log,
+            "Sending BlocksByRoot Request";
+            "method" => "BlocksByRoot",
+            "count" => request.block_roots.len(),
+            "peer" => format!("{:?}", peer_id)
+        );
+        self.send_rpc_request_with_id(peer_id, RPCRequest::BlocksByRoot(request))
+    }
+
+    /// Sends `rpc_request` with a new request id, so that its response can be identified.
+    fn send_rpc_request_with_id(&mut self, peer_id: PeerId, rpc_request: RPCRequest) -> RequestId {
+        let request_id = self.request_id;
+        self.request_id += 1;
+        self.send_rpc_request(Some(request_id), peer_id, rpc_request);
+        request_id
+    }
+
     //TODO: Handle Error responses
     pub fn send_rpc_response(
         &mut self,
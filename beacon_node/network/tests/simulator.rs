@@ -0,0 +1,457 @@
+//! Simulates a network of beacon nodes within a single process.
+//!
+//! Each node runs the `MessageHandler` (and hence the sync manager) of the network crate on its own
+//! `BeaconChain`. The libp2p service is replaced by an in-memory router which delivers the RPC and
+//! gossip messages that nodes send to the peers they are linked to. Links can be removed to
+//! partition the network and restored to heal it.
+//!
+//! The simulation is stepped explicitly rather than driven by the wall clock. Each round waits
+//! until every node has processed the messages it was sent (see `PendingWork`), then delivers the
+//! messages the nodes sent in response. Time is mocked: every node uses a `TestingSlotClock`,
+//! which the simulator sets to the slot of the latest block produced.
+
+use beacon_chain::test_utils::{
+    AttestationStrategy, BeaconChainHarness, BlockStrategy, HarnessType,
+};
+use beacon_chain::BeaconChain;
+use eth2_libp2p::{PeerId, PubsubMessage};
+use futures::prelude::*;
+use network::message_handler::{HandlerMessage, HandlerSend, MessageHandler};
+use network::{NetworkMessage, PendingWork, SyncProgress, SyncState};
+use parking_lot::RwLock;
+use sloggers::{null::NullLoggerBuilder, Build};
+use ssz::Encode;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::thread;
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc;
+use types::test_utils::generate_deterministic_keypairs;
+use types::{BeaconBlock, Hash256, MinimalEthSpec, Slot};
+
+type E = MinimalEthSpec;
+
+const VALIDATOR_COUNT: usize = 24;
+/// The maximum number of rounds the network may take to reach a condition, which guards against
+/// nodes that never stop messaging each other.
+const MAX_ROUNDS: usize = 1_000;
+
+/// A beacon node, as seen by the router.
+struct Node {
+    chain: Arc<BeaconChain<HarnessType<E>>>,
+    peer_id: PeerId,
+    /// Delivers messages from the network to the node.
+    handler_send: HandlerSend,
+    /// Counts the messages the node has been sent but not yet processed.
+    pending_work: PendingWork,
+    /// Messages the node sends to the network.
+    network_recv: mpsc::UnboundedReceiver<NetworkMessage>,
+    /// The ids of the gossip messages the node has received.
+    seen_messages: HashSet<String>,
+    /// The sync status reported by the node's sync manager.
+    sync_progress: Arc<RwLock<SyncProgress>>,
+}
+
+impl Node {
+    fn head(&self) -> Hash256 {
+        self.chain.head().beacon_block_root
+    }
+
+    fn sync_state(&self) -> SyncState {
+        self.sync_progress.read().state
+    }
+}
+
+struct Simulator {
+    /// Runs the message handlers and sync managers of the nodes.
+    _runtime: Runtime,
+    nodes: Vec<Node>,
+    /// The pairs of nodes that are connected, lowest index first.
+    links: HashSet<(usize, usize)>,
+    /// The gossip messages that have been published, by message id.
+    messages: HashMap<String, PubsubMessage>,
+}
+
+impl Simulator {
+    /// Starts `node_count` nodes sharing the same genesis, without any links between them.
+    fn new(node_count: usize) -> Self {
+        let runtime = Runtime::new().expect("should start runtime");
+        let log = NullLoggerBuilder.build().expect("should build logger");
+        let keypairs = generate_deterministic_keypairs(VALIDATOR_COUNT);
+
+        let nodes = (0..node_count)
+            .map(|_| {
+                let BeaconChainHarness { chain, .. } =
+                    BeaconChainHarness::new(MinimalEthSpec, keypairs.clone());
+                let chain = Arc::new(chain);
+                let (network_send, network_recv) = mpsc::unbounded_channel();
+                let sync_progress = Arc::new(RwLock::new(SyncProgress::default()));
+                let pending_work = PendingWork::default();
+                let handler_send = MessageHandler::spawn(
+                    chain.clone(),
+                    network_send,
+                    sync_progress.clone(),
+                    pending_work.clone(),
+                    &runtime.executor(),
+                    log.clone(),
+                )
+                .expect("should spawn message handler");
+
+                Node {
+                    chain,
+                    peer_id: PeerId::random(),
+                    handler_send,
+                    pending_work,
+                    network_recv,
+                    seen_messages: HashSet::new(),
+                    sync_progress,
+                }
+            })
+            .collect();
+
+        Simulator {
+            _runtime: runtime,
+            nodes,
+            links: HashSet::new(),
+            messages: HashMap::new(),
+        }
+    }
+
+    /// Connects every pair of nodes in `group`.
+    fn connect_all(&mut self, group: &[usize]) {
+        for (i, a) in group.iter().enumerate() {
+            for b in &group[i + 1..] {
+                self.connect(*a, *b);
+            }
+        }
+    }
+
+    /// Links two nodes. Both nodes are notified of the connection, as with libp2p.
+    fn connect(&mut self, a: usize, b: usize) {
+        if self.links.insert(link(a, b)) {
+            let (peer_a, peer_b) = (self.nodes[a].peer_id.clone(), self.nodes[b].peer_id.clone());
+            self.deliver(a, HandlerMessage::PeerDialed(peer_b));
+            self.deliver(b, HandlerMessage::PeerDialed(peer_a));
+        }
+    }
+
+    /// Removes the links between nodes of `group` and all other nodes.
+    fn partition(&mut self, group: &[usize]) {
+        let cut = self
+            .links
+            .iter()
+            .filter(|(a, b)| group.contains(a) != group.contains(b))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for (a, b) in cut {
+            self.links.remove(&(a, b));
+            let (peer_a, peer_b) = (self.nodes[a].peer_id.clone(), self.nodes[b].peer_id.clone());
+            self.deliver(a, HandlerMessage::PeerDisconnected(peer_b));
+            self.deliver(b, HandlerMessage::PeerDisconnected(peer_a));
+        }
+    }
+
+    /// Imports `blocks` on node `publisher` and publishes them to its peers, advancing the clocks
+    /// of all nodes to the slot of each block.
+    fn publish_blocks(&mut self, publisher: usize, blocks: Vec<BeaconBlock<E>>) {
+        for block in blocks {
+            self.set_slot(block.slot);
+            self.nodes[publisher]
+                .chain
+                .process_block(block.clone())
+                .expect("publisher should process block");
+
+            let message_id = format!("{}", block.canonical_root());
+            self.messages.insert(
+                message_id.clone(),
+                PubsubMessage::Block(block.as_ssz_bytes()),
+            );
+            self.gossip(publisher, message_id);
+        }
+    }
+
+    fn set_slot(&self, slot: Slot) {
+        for node in &self.nodes {
+            node.chain.slot_clock.set_slot(slot.as_u64());
+        }
+    }
+
+    /// Steps the network until `condition` holds. Returns `false` if the network falls silent, or
+    /// `MAX_ROUNDS` pass, without it holding.
+    fn run_until<F: Fn(&[Node]) -> bool>(&mut self, condition: F) -> bool {
+        for _ in 0..MAX_ROUNDS {
+            let routed = self.step();
+            if condition(&self.nodes) {
+                return true;
+            }
+            if !routed {
+                return false;
+            }
+        }
+        false
+    }
+
+    /// Waits for every node to process the messages it has been sent, then delivers the messages
+    /// the nodes have sent since the last round. Returns `false` if there were none.
+    fn step(&mut self) -> bool {
+        self.wait_until_idle();
+        let messages = self.drain();
+        let routed = !messages.is_empty();
+        self.route(messages);
+        routed
+    }
+
+    fn wait_until_idle(&self) {
+        while !self.nodes.iter().all(|node| node.pending_work.is_idle()) {
+            thread::yield_now();
+        }
+    }
+
+    fn route(&mut self, messages: Vec<(usize, NetworkMessage)>) {
+        for (source, message) in messages {
+            match message {
+                NetworkMessage::RPC(peer_id, rpc_event) => {
+                    // messages to unreachable peers are dropped, as by a closed connection
+                    if let Some(dest) = self.linked_node(source, &peer_id) {
+                        let source_peer = self.nodes[source].peer_id.clone();
+                        self.deliver(dest, HandlerMessage::RPC(source_peer, rpc_event));
+                    }
+                }
+                NetworkMessage::Publish { message, .. } => {
+                    let message_id = format!("{}-{}", source, self.messages.len());
+                    self.messages.insert(message_id.clone(), message);
+                    self.gossip(source, message_id);
+                }
+                NetworkMessage::Propagate { message_id, .. } => {
+                    self.gossip(source, message_id);
+                }
+                NetworkMessage::Subscribe { .. }
+                | NetworkMessage::ReportPeer { .. }
+                | NetworkMessage::NatMappingEstablished { .. } => {}
+            }
+        }
+    }
+
+    /// Sends a gossip message from `source` to each of its peers that has not yet received it.
+    fn gossip(&mut self, source: usize, message_id: String) {
+        let message = match self.messages.get(&message_id) {
+            Some(message) => message.clone(),
+            None => return,
+        };
+        self.nodes[source].seen_messages.insert(message_id.clone());
+
+        let source_peer = self.nodes[source].peer_id.clone();
+        for dest in self.peers_of(source) {
+            if self.nodes[dest].seen_messages.insert(message_id.clone()) {
+                self.deliver(
+                    dest,
+                    HandlerMessage::PubsubMessage(
+                        message_id.clone(),
+                        source_peer.clone(),
+                        message.clone(),
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Collects the messages that every node has sent to the network.
+    fn drain(&mut self) -> Vec<(usize, NetworkMessage)> {
+        let nodes = &mut self.nodes;
+        futures::future::poll_fn(|| -> Result<_, ()> {
+            let mut messages = Vec::new();
+            for (index, node) in nodes.iter_mut().enumerate() {
+                while let Ok(Async::Ready(Some(message))) = node.network_recv.poll() {
+                    messages.push((index, message));
+                }
+            }
+            Ok(Async::Ready(messages))
+        })
+        .wait()
+        .expect("draining never fails")
+    }
+
+    fn deliver(&mut self, dest: usize, message: HandlerMessage) {
+        self.nodes[dest]
+            .handler_send
+            .try_send(message)
+            .expect("message handler should be running");
+    }
+
+    fn peers_of(&self, node: usize) -> Vec<usize> {
+        (0..self.nodes.len())
+            .filter(|other| self.links.contains(&link(node, *other)))
+            .collect()
+    }
+
+    /// Returns the node with `peer_id`, if it is linked to `source`.
+    fn linked_node(&self, source: usize, peer_id: &PeerId) -> Option<usize> {
+        self.nodes
+            .iter()
+            .position(|node| &node.peer_id == peer_id)
+            .filter(|dest| self.links.contains(&link(source, *dest)))
+    }
+}
+
+fn link(a: usize, b: usize) -> (usize, usize) {
+    (std::cmp::min(a, b), std::cmp::max(a, b))
+}
+
+/// Produces blocks, and forks of blocks, from the same genesis as the simulated nodes.
+struct BlockProducer {
+    harness: BeaconChainHarness<HarnessType<E>>,
+}
+
+impl BlockProducer {
+    fn new() -> Self {
+        let harness = BeaconChainHarness::new(
+            MinimalEthSpec,
+            generate_deterministic_keypairs(VALIDATOR_COUNT),
+        );
+        harness.advance_slot();
+        BlockProducer { harness }
+    }
+
+    /// Produces `num_blocks` blocks on the producer's head, attested to by all validators.
+    fn extend(&self, num_blocks: usize) -> Vec<BeaconBlock<E>> {
+        let parent_slot = self.harness.chain.best_slot();
+        let head = self.harness.extend_chain(
+            num_blocks,
+            BlockStrategy::OnCanonicalHead,
+            AttestationStrategy::AllValidators,
+        );
+        self.blocks_after(head, parent_slot)
+    }
+
+    /// Produces `num_blocks` blocks that fork from the block at `parent_slot`, skipping
+    /// `skip_slots` slots. The blocks are attested to by `attesters`.
+    fn fork(
+        &self,
+        parent_slot: Slot,
+        skip_slots: u64,
+        num_blocks: usize,
+        attesters: Vec<usize>,
+    ) -> Vec<BeaconBlock<E>> {
+        let head = self.harness.extend_chain(
+            num_blocks,
+            BlockStrategy::ForkCanonicalChainAt {
+                previous_slot: parent_slot,
+                first_slot: parent_slot + 1 + skip_slots,
+            },
+            AttestationStrategy::SomeValidators(attesters),
+        );
+        self.blocks_after(head, parent_slot)
+    }
+
+    /// Returns the ancestors of `head` (inclusive) later than `slot`, oldest first.
+    fn blocks_after(&self, head: Hash256, slot: Slot) -> Vec<BeaconBlock<E>> {
+        let mut blocks = Vec::new();
+        let mut root = head;
+        loop {
+            let block = self
+                .harness
+                .chain
+                .get_block(&root)
+                .expect("should read block")
+                .expect("block should exist");
+            if block.slot <= slot {
+                break;
+            }
+            root = block.parent_root;
+            blocks.push(block);
+        }
+        blocks.reverse();
+        blocks
+    }
+}
+
+fn all_heads_equal(nodes: &[Node], head: Hash256) -> bool {
+    nodes.iter().all(|node| node.head() == head)
+}
+
+#[test]
+fn gossip_blocks_reach_all_nodes() {
+    let mut simulator = Simulator::new(4);
+    let producer = BlockProducer::new();
+
+    // a line topology, so that blocks must be propagated to reach the last node
+    simulator.connect(0, 1);
+    simulator.connect(1, 2);
+    simulator.connect(2, 3);
+
+    let blocks = producer.extend(4);
+    let head = blocks.last().expect("blocks produced").canonical_root();
+    simulator.publish_blocks(0, blocks);
+
+    assert!(
+        simulator.run_until(|nodes| all_heads_equal(nodes, head)),
+        "all nodes should import the published blocks"
+    );
+}
+
+#[test]
+fn late_joiner_syncs_to_head() {
+    let mut simulator = Simulator::new(3);
+    let producer = BlockProducer::new();
+
+    simulator.connect(0, 1);
+    let blocks = producer.extend(24);
+    let head = blocks.last().expect("blocks produced").canonical_root();
+    simulator.publish_blocks(0, blocks);
+    assert!(simulator.run_until(|nodes| nodes[0].head() == head && nodes[1].head() == head));
+
+    assert_eq!(simulator.nodes[2].sync_state(), SyncState::Stalled);
+
+    // node 2 is too far behind to follow gossip and must range sync
+    simulator.connect(1, 2);
+    assert!(
+        simulator.run_until(|nodes| all_heads_equal(nodes, head)),
+        "the late node should sync to the head of its peer"
+    );
+    assert!(
+        simulator.run_until(|nodes| nodes[2].sync_state() == SyncState::Synced),
+        "the late node should be synced once it reaches the head of its peer"
+    );
+}
+
+#[test]
+fn partitions_converge_after_healing() {
+    let mut simulator = Simulator::new(4);
+    let producer = BlockProducer::new();
+
+    simulator.connect_all(&[0, 1, 2, 3]);
+    let common = producer.extend(4);
+    let common_head = common.last().expect("blocks produced");
+    let fork_slot = common_head.slot;
+    let common_root = common_head.canonical_root();
+    simulator.publish_blocks(0, common);
+    assert!(simulator.run_until(|nodes| all_heads_equal(nodes, common_root)));
+
+    simulator.partition(&[0, 1]);
+
+    // a short fork without attestations in one partition, and a long, heavily attested fork in the
+    // other
+    let light_fork = producer.fork(fork_slot, 0, 2, vec![]);
+    let heavy_fork = producer.fork(fork_slot, 2, 20, (0..VALIDATOR_COUNT).collect());
+    let light_head = light_fork.last().expect("fork produced").canonical_root();
+    let heavy_head = heavy_fork.last().expect("fork produced").canonical_root();
+
+    simulator.publish_blocks(2, light_fork);
+    simulator.publish_blocks(0, heavy_fork);
+    assert!(
+        simulator.run_until(|nodes| {
+            nodes[0].head() == heavy_head
+                && nodes[1].head() == heavy_head
+                && nodes[2].head() == light_head
+                && nodes[3].head() == light_head
+        }),
+        "each partition should follow its own fork"
+    );
+
+    simulator.connect_all(&[0, 1, 2, 3]);
+    assert!(
+        simulator.run_until(|nodes| all_heads_equal(nodes, heavy_head)),
+        "all nodes should converge to the heavier fork once the partition heals"
+    );
+}
@@ -0,0 +1,230 @@
+use crate::{ApiError, Config};
+use hyper::header::{self, HeaderValue};
+use hyper::{Body, Method, Request, Response, StatusCode};
+
+/// The methods which may be used in cross-origin requests.
+const CORS_ALLOW_METHODS: &str = "GET, POST, OPTIONS";
+/// The request headers which may be used in cross-origin requests.
+const CORS_ALLOW_HEADERS: &str = "Accept, Authorization, Content-Type";
+
+/// Returns the token from the `Authorization: Bearer <token>` header of `req`, if any.
+fn bearer_token(req: &Request<Body>) -> Option<&str> {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            if value.starts_with("Bearer ") {
+                Some(value.trim_start_matches("Bearer "))
+            } else {
+                None
+            }
+        })
+}
+
+/// Returns `true` if `a` and `b` are equal, in a time which depends only on their lengths.
+///
+/// Used to compare secrets, so that the time taken doesn't reveal how much of a guess is correct.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Returns `Ok(())` if `req` carries an `Authorization: Bearer <token>` header matching `token`.
+pub fn check_bearer_token(req: &Request<Body>, token: &str) -> Result<(), ApiError> {
+    match bearer_token(req) {
+        Some(provided) if constant_time_eq(provided.as_bytes(), token.as_bytes()) => Ok(()),
+        Some(_) => Err(ApiError::Unauthorized("Invalid bearer token".to_string())),
+        None => Err(ApiError::Unauthorized(
+            "Missing bearer token in authorization header".to_string(),
+        )),
+    }
+}
+
+/// The origins permitted to make cross-origin requests.
+enum AllowOrigin {
+    Any,
+    Origins(Vec<String>),
+}
+
+/// Controls which requests may modify the node and which origins may make cross-origin requests.
+pub struct ApiAccess {
+    /// The bearer token required for `POST` requests, other than those to the administrative
+    /// `/lighthouse` endpoints (which require the admin token instead). If `None`, no token is
+    /// required.
+    api_token: Option<String>,
+    /// If `None`, no cross-origin requests are permitted.
+    allow_origin: Option<AllowOrigin>,
+}
+
+impl ApiAccess {
+    pub fn new(config: &Config) -> Self {
+        let allow_origin = config.allow_origin.as_ref().map(|origins| {
+            if origins.trim() == "*" {
+                AllowOrigin::Any
+            } else {
+                AllowOrigin::Origins(
+                    origins
+                        .split(',')
+                        .map(|origin| origin.trim().to_string())
+                        .filter(|origin| !origin.is_empty())
+                        .collect(),
+                )
+            }
+        });
+
+        Self {
+            api_token: config.api_token.clone(),
+            allow_origin,
+        }
+    }
+
+    /// Returns `Ok(())` if `req` may be served.
+    ///
+    /// Only `POST` requests publish to the network or modify the node, so all other requests are
+    /// permitted.
+    pub fn authorize(&self, req: &Request<Body>) -> Result<(), ApiError> {
+        match &self.api_token {
+            Some(token)
+                if req.method() == Method::POST
+                    && !req.uri().path().starts_with("/lighthouse/") =>
+            {
+                check_bearer_token(req, token)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the response to a CORS preflight (`OPTIONS`) request.
+    ///
+    /// The `Access-Control-Allow-Origin` header is added separately, by `add_cors_headers`.
+    pub fn preflight_response(&self) -> Result<Response<Body>, ApiError> {
+        Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .header(header::ALLOW, CORS_ALLOW_METHODS)
+            .header(header::ACCESS_CONTROL_ALLOW_METHODS, CORS_ALLOW_METHODS)
+            .header(header::ACCESS_CONTROL_ALLOW_HEADERS, CORS_ALLOW_HEADERS)
+            .body(Body::empty())
+            .map_err(|e| ApiError::ServerError(format!("Failed to build response: {:?}", e)))
+    }
+
+    /// Permits the browser to share `response` with `origin`, if it is an allowed origin.
+    pub fn add_cors_headers(&self, origin: Option<&HeaderValue>, response: &mut Response<Body>) {
+        let headers = response.headers_mut();
+
+        match (&self.allow_origin, origin) {
+            (Some(AllowOrigin::Any), _) => {
+                headers.insert(
+                    header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                    HeaderValue::from_static("*"),
+                );
+            }
+            (Some(AllowOrigin::Origins(origins)), Some(origin)) => {
+                // The response differs between origins, so must not be cached for another.
+                headers.insert(header::VARY, HeaderValue::from_static("Origin"));
+
+                if origins
+                    .iter()
+                    .any(|allowed| allowed.as_bytes() == origin.as_bytes())
+                {
+                    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config(api_token: Option<&str>, allow_origin: Option<&str>) -> Config {
+        Config {
+            api_token: api_token.map(String::from),
+            allow_origin: allow_origin.map(String::from),
+            ..Config::default()
+        }
+    }
+
+    fn request(method: Method, path: &str, auth: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder();
+        builder.method(method).uri(path);
+        if let Some(auth) = auth {
+            builder.header(header::AUTHORIZATION, auth);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    fn allowed_origin(access: &ApiAccess, origin: &'static str) -> Option<HeaderValue> {
+        let mut response = Response::new(Body::empty());
+        access.add_cors_headers(Some(&HeaderValue::from_static(origin)), &mut response);
+        response
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .cloned()
+    }
+
+    #[test]
+    fn authorize() {
+        let open = ApiAccess::new(&config(None, None));
+        assert!(open
+            .authorize(&request(Method::POST, "/validator/block", None))
+            .is_ok());
+
+        let access = ApiAccess::new(&config(Some("secret"), None));
+        assert!(access
+            .authorize(&request(Method::GET, "/beacon/head", None))
+            .is_ok());
+        assert!(access
+            .authorize(&request(Method::POST, "/validator/block", None))
+            .is_err());
+        assert!(access
+            .authorize(&request(
+                Method::POST,
+                "/validator/block",
+                Some("Bearer wrong")
+            ))
+            .is_err());
+        assert!(access
+            .authorize(&request(
+                Method::POST,
+                "/validator/block",
+                Some("Bearer secret")
+            ))
+            .is_ok());
+        // Administrative endpoints are authorized with the admin token.
+        assert!(access
+            .authorize(&request(Method::POST, "/lighthouse/compaction", None))
+            .is_ok());
+    }
+
+    #[test]
+    fn constant_time_eq_compares_bytes() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(constant_time_eq(b"", b""));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+        assert!(!constant_time_eq(b"secret", b""));
+    }
+
+    #[test]
+    fn cors() {
+        let none = ApiAccess::new(&config(None, None));
+        assert_eq!(allowed_origin(&none, "http://localhost:3000"), None);
+
+        let any = ApiAccess::new(&config(None, Some("*")));
+        assert_eq!(
+            allowed_origin(&any, "http://localhost:3000"),
+            Some(HeaderValue::from_static("*"))
+        );
+
+        let list = ApiAccess::new(&config(
+            None,
+            Some("http://localhost:3000, https://dashboard.example"),
+        ));
+        assert_eq!(
+            allowed_origin(&list, "https://dashboard.example"),
+            Some(HeaderValue::from_static("https://dashboard.example"))
+        );
+        assert_eq!(allowed_origin(&list, "https://evil.example"), None);
+    }
+}
@@ -0,0 +1,276 @@
+use crate::access::check_bearer_token;
+use crate::response_builder::ResponseBuilder;
+use crate::{ApiError, ApiResult, UrlQuery};
+use beacon_chain::{BeaconChain, BeaconChainTypes};
+use hyper::{Body, Request};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use store::KeyValueStore;
+
+/// The number of finished tasks whose status is retained. When exceeded, the statuses of the
+/// oldest finished tasks are forgotten.
+pub const MAX_FINISHED_TASKS: usize = 64;
+
+/// The status of a long-running administrative task.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    Running,
+    Complete,
+    Failed(String),
+}
+
+/// Returned when a long-running administrative task is started. The `task_id` may be used to
+/// poll the status of the task via `/lighthouse/tasks`.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct TaskHandle {
+    pub task_id: u64,
+}
+
+/// Shared state for the administrative endpoints.
+pub struct Admin {
+    /// The bearer token required to access administrative endpoints. If `None`, administrative
+    /// endpoints are disabled.
+    token: Option<String>,
+    next_task_id: AtomicU64,
+    tasks: Arc<RwLock<HashMap<u64, TaskStatus>>>,
+}
+
+impl Admin {
+    pub fn new(token: Option<String>) -> Self {
+        Self {
+            token,
+            next_task_id: AtomicU64::new(0),
+            tasks: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Returns `Ok(())` if `req` carries the correct `Authorization: Bearer <token>` header.
+    pub fn authorize(&self, req: &Request<Body>) -> Result<(), ApiError> {
+        let token = self.token.as_ref().ok_or_else(|| {
+            ApiError::Forbidden(
+                "Administrative endpoints are disabled. Enable them with --api-admin-token."
+                    .to_string(),
+            )
+        })?;
+
+        check_bearer_token(req, token)
+    }
+
+    /// Runs `task` on a new thread, returning a handle that may be used to poll its status.
+    pub fn spawn<F>(&self, name: &str, task: F) -> Result<TaskHandle, ApiError>
+    where
+        F: FnOnce() -> Result<(), String> + Send + 'static,
+    {
+        let task_id = self.next_task_id.fetch_add(1, Ordering::Relaxed);
+        let tasks = self.tasks.clone();
+
+        {
+            let mut tasks = tasks.write();
+            evict_finished_tasks(&mut tasks);
+            tasks.insert(task_id, TaskStatus::Running);
+        }
+
+        thread::Builder::new()
+            .name(format!("admin_{}", name))
+            .spawn(move || {
+                let status = match task() {
+                    Ok(()) => TaskStatus::Complete,
+                    Err(e) => TaskStatus::Failed(e),
+                };
+                tasks.write().insert(task_id, status);
+            })
+            .map_err(|e| {
+                self.tasks.write().remove(&task_id);
+                ApiError::ServerError(format!("Unable to spawn {} task: {:?}", name, e))
+            })?;
+
+        Ok(TaskHandle { task_id })
+    }
+
+    /// Returns the status of the task with the given `task_id`, if it exists.
+    pub fn task_status(&self, task_id: u64) -> Option<TaskStatus> {
+        self.tasks.read().get(&task_id).cloned()
+    }
+}
+
+/// Forget the statuses of the oldest finished tasks in `tasks`, such that at most
+/// `MAX_FINISHED_TASKS` remain. Running tasks are never forgotten.
+fn evict_finished_tasks(tasks: &mut HashMap<u64, TaskStatus>) {
+    let mut finished = tasks
+        .iter()
+        .filter(|(_, status)| **status != TaskStatus::Running)
+        .map(|(task_id, _)| *task_id)
+        .collect::<Vec<_>>();
+
+    if finished.len() > MAX_FINISHED_TASKS {
+        finished.sort_unstable();
+        for task_id in &finished[..finished.len() - MAX_FINISHED_TASKS] {
+            tasks.remove(task_id);
+        }
+    }
+}
+
+/// HTTP handler to prune the fork choice tree, operation pool and database against the finalized
+/// checkpoint.
+pub fn post_database_prune<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+    admin: Arc<Admin>,
+) -> ApiResult {
+    admin.authorize(&req)?;
+
+    let handle = admin.spawn("prune", move || {
+        beacon_chain
+            .prune()
+            .map_err(|e| format!("Unable to prune: {:?}", e))
+    })?;
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&handle)
+}
+
+/// HTTP handler to clear the shuffling and state caches, and rebuild the caches of the head state.
+pub fn post_caches_clear<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+    admin: Arc<Admin>,
+) -> ApiResult {
+    admin.authorize(&req)?;
+
+    let handle = admin.spawn("clear_caches", move || {
+        beacon_chain
+            .clear_caches()
+            .map_err(|e| format!("Unable to clear caches: {:?}", e))
+    })?;
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&handle)
+}
+
+/// HTTP handler to compact the database.
+pub fn post_compaction<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+    admin: Arc<Admin>,
+) -> ApiResult {
+    admin.authorize(&req)?;
+
+    let handle = admin.spawn("compaction", move || {
+        beacon_chain
+            .store
+            .compact()
+            .map_err(|e| format!("Unable to compact database: {:?}", e))
+    })?;
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&handle)
+}
+
+/// HTTP handler to return the status of a task started by another administrative endpoint.
+pub fn get_task(req: Request<Body>, admin: Arc<Admin>) -> ApiResult {
+    admin.authorize(&req)?;
+
+    let task_id = UrlQuery::from_request(&req)?
+        .only_one("task_id")?
+        .parse::<u64>()
+        .map_err(|e| ApiError::BadRequest(format!("Unable to parse task_id: {:?}", e)))?;
+
+    let status = admin
+        .task_status(task_id)
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown task_id: {}", task_id)))?;
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&status)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hyper::header;
+    use std::time::Duration;
+
+    fn request_with_auth(value: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder();
+        if let Some(value) = value {
+            builder.header(header::AUTHORIZATION, value);
+        }
+        builder
+            .uri("http://localhost/lighthouse/tasks")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn authorize() {
+        let admin = Admin::new(Some("secret".to_string()));
+
+        assert_eq!(
+            admin.authorize(&request_with_auth(Some("Bearer secret"))),
+            Ok(())
+        );
+        assert!(admin
+            .authorize(&request_with_auth(Some("Bearer wrong")))
+            .is_err());
+        assert!(admin.authorize(&request_with_auth(Some("secret"))).is_err());
+        assert!(admin.authorize(&request_with_auth(None)).is_err());
+
+        let disabled = Admin::new(None);
+        assert!(disabled
+            .authorize(&request_with_auth(Some("Bearer secret")))
+            .is_err());
+    }
+
+    #[test]
+    fn spawn_reports_status() {
+        let admin = Admin::new(None);
+
+        let ok = admin.spawn("ok", || Ok(())).unwrap();
+        let err = admin.spawn("err", || Err("failed".to_string())).unwrap();
+
+        assert_ne!(ok.task_id, err.task_id, "task ids should be unique");
+        assert_eq!(admin.task_status(u64::max_value()), None);
+
+        let wait_for = |task_id| loop {
+            match admin.task_status(task_id) {
+                Some(TaskStatus::Running) => thread::sleep(Duration::from_millis(10)),
+                other => break other,
+            }
+        };
+
+        assert_eq!(wait_for(ok.task_id), Some(TaskStatus::Complete));
+        assert_eq!(
+            wait_for(err.task_id),
+            Some(TaskStatus::Failed("failed".to_string()))
+        );
+    }
+
+    #[test]
+    fn finished_tasks_are_evicted() {
+        let mut tasks = HashMap::new();
+        tasks.insert(0, TaskStatus::Running);
+        for task_id in 1..=MAX_FINISHED_TASKS as u64 + 2 {
+            tasks.insert(task_id, TaskStatus::Complete);
+        }
+
+        evict_finished_tasks(&mut tasks);
+
+        assert_eq!(tasks.len(), MAX_FINISHED_TASKS + 1);
+        assert_eq!(
+            tasks.get(&0),
+            Some(&TaskStatus::Running),
+            "running tasks should be retained"
+        );
+        assert_eq!(
+            tasks.get(&1),
+            None,
+            "the oldest finished tasks should be evicted"
+        );
+        assert_eq!(
+            tasks.get(&2),
+            None,
+            "the oldest finished tasks should be evicted"
+        );
+        assert!(tasks.contains_key(&(MAX_FINISHED_TASKS as u64 + 2)));
+    }
+}
@@ -1,13 +1,24 @@
 use crate::helpers::*;
 use crate::response_builder::ResponseBuilder;
-use crate::{ApiError, ApiResult, UrlQuery};
-use beacon_chain::{BeaconChain, BeaconChainTypes};
+use crate::{ApiError, ApiResult, BoxFut, NetworkChannel, UrlQuery};
+use beacon_chain::{BeaconChain, BeaconChainTypes, OperationProcessingOutcome};
+use eth2_libp2p::{GossipKind, PubsubMessage};
+use futures::future::Future;
+use futures::stream::Stream;
 use hyper::{Body, Request};
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use ssz_derive::Encode;
+use std::fmt::Debug;
+use std::str::FromStr;
 use std::sync::Arc;
 use store::Store;
-use types::{BeaconBlock, BeaconState, Epoch, EthSpec, Hash256, Slot, Validator};
+use types::{
+    AttesterSlashing, BeaconBlock, BeaconState, BeaconStateError, Epoch, EthSpec, Hash256,
+    ProposerSlashing, PublicKey, Slot, Validator, VoluntaryExit,
+};
+
+/// The maximum number of validators that will be returned in a single page.
+pub const MAX_VALIDATORS_PER_PAGE: usize = 4_096;
 
 #[derive(Serialize, Deserialize, Encode)]
 pub struct HeadResponse {
@@ -74,6 +85,7 @@ pub fn get_block<T: BeaconChainTypes>(
     let block_root = match (key.as_ref(), value) {
         ("slot", value) => {
             let target = parse_slot(&value)?;
+            ensure_block_available(&beacon_chain, target)?;
 
             block_root_at_slot(&beacon_chain, target).ok_or_else(|| {
                 ApiError::NotFound(format!("Unable to find BeaconBlock for slot {:?}", target))
@@ -142,9 +154,7 @@ pub fn get_validators<T: BeaconChainTypes>(
                 ApiError::BadRequest(format!("Invalid epoch parameter, must be a u64. {:?}", e))
             })?,
         // In this case, our url query did not contain any parameters, so we take the default
-        Err(_) => beacon_chain.epoch().map_err(|e| {
-            ApiError::ServerError(format!("Unable to determine current epoch: {:?}", e))
-        })?,
+        Err(_) => beacon_chain.epoch()?,
     };
 
     let all_validators = &beacon_chain.head().beacon_state.validators;
@@ -157,6 +167,296 @@ pub fn get_validators<T: BeaconChainTypes>(
     ResponseBuilder::new(&req)?.body(&active_vals)
 }
 
+/// The status of a validator, relative to some epoch.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidatorStatus {
+    PendingInitialized,
+    PendingQueued,
+    ActiveOngoing,
+    ActiveExiting,
+    ActiveSlashed,
+    ExitedUnslashed,
+    ExitedSlashed,
+    WithdrawalPossible,
+}
+
+impl ValidatorStatus {
+    /// Returns the status of `validator` at `epoch`.
+    pub fn from_validator(validator: &Validator, epoch: Epoch, far_future_epoch: Epoch) -> Self {
+        if validator.is_active_at(epoch) {
+            if validator.exit_epoch == far_future_epoch {
+                ValidatorStatus::ActiveOngoing
+            } else if validator.slashed {
+                ValidatorStatus::ActiveSlashed
+            } else {
+                ValidatorStatus::ActiveExiting
+            }
+        } else if epoch < validator.activation_epoch {
+            if validator.activation_eligibility_epoch == far_future_epoch {
+                ValidatorStatus::PendingInitialized
+            } else {
+                ValidatorStatus::PendingQueued
+            }
+        } else if !validator.is_withdrawable_at(epoch) {
+            if validator.slashed {
+                ValidatorStatus::ExitedSlashed
+            } else {
+                ValidatorStatus::ExitedUnslashed
+            }
+        } else {
+            ValidatorStatus::WithdrawalPossible
+        }
+    }
+}
+
+impl FromStr for ValidatorStatus {
+    type Err = ApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending_initialized" => Ok(ValidatorStatus::PendingInitialized),
+            "pending_queued" => Ok(ValidatorStatus::PendingQueued),
+            "active_ongoing" => Ok(ValidatorStatus::ActiveOngoing),
+            "active_exiting" => Ok(ValidatorStatus::ActiveExiting),
+            "active_slashed" => Ok(ValidatorStatus::ActiveSlashed),
+            "exited_unslashed" => Ok(ValidatorStatus::ExitedUnslashed),
+            "exited_slashed" => Ok(ValidatorStatus::ExitedSlashed),
+            "withdrawal_possible" => Ok(ValidatorStatus::WithdrawalPossible),
+            other => Err(ApiError::BadRequest(format!(
+                "Unknown validator status: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// The balance of a single validator.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct ValidatorBalanceData {
+    pub index: u64,
+    pub balance: u64,
+}
+
+/// A summary of a single validator.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct ValidatorData {
+    pub index: u64,
+    pub pubkey: PublicKey,
+    pub balance: u64,
+    pub effective_balance: u64,
+    pub status: ValidatorStatus,
+}
+
+/// A Merkle proof of a node in the tree hash of a state.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct StateProofData {
+    pub state_root: Hash256,
+    pub generalized_index: u64,
+    /// The node at `generalized_index`, e.g., the tree hash root of a field.
+    pub leaf: Hash256,
+    /// The sibling nodes from the leaf up to (but excluding) the state root.
+    pub proof: Vec<Hash256>,
+}
+
+/// Paging parameters shared by the `/eth/v1/beacon/states/{state_id}` validator endpoints.
+struct Page {
+    offset: usize,
+    limit: usize,
+}
+
+impl Page {
+    /// Parses the `offset` and `limit` query parameters of `req`, which default to the first page.
+    ///
+    /// Returns a `BadRequest` error if a parameter is malformed or repeated, or if `limit` is zero.
+    /// A `limit` greater than `MAX_VALIDATORS_PER_PAGE` is reduced to it.
+    fn from_request(req: &Request<Body>) -> Result<Self, ApiError> {
+        let parse = |key: &str, default: usize| -> Result<usize, ApiError> {
+            let values = match req.uri().query() {
+                Some(_) => UrlQuery::from_request(req)?.all_of(key)?,
+                None => vec![],
+            };
+
+            match values.as_slice() {
+                [] => Ok(default),
+                [value] => value
+                    .parse::<usize>()
+                    .map_err(|e| ApiError::BadRequest(format!("Unable to parse {}: {:?}", key, e))),
+                _ => Err(ApiError::BadRequest(format!(
+                    "The {} query parameter may only be supplied once",
+                    key
+                ))),
+            }
+        };
+
+        let offset = parse("offset", 0)?;
+        let limit = parse("limit", MAX_VALIDATORS_PER_PAGE)?;
+        if limit == 0 {
+            return Err(ApiError::BadRequest(
+                "The limit query parameter must be greater than zero".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            offset,
+            limit: std::cmp::min(limit, MAX_VALIDATORS_PER_PAGE),
+        })
+    }
+}
+
+/// Routes requests to `/eth/v1/beacon/states/{state_id}/*`.
+pub fn route_state_request<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+    path: &str,
+) -> ApiResult {
+    let parts: Vec<&str> = path
+        .trim_start_matches("/eth/v1/beacon/states/")
+        .split('/')
+        .collect();
+
+    match parts.as_slice() {
+        [state_id, "validator_balances"] => {
+            get_state_validator_balances(req, beacon_chain, state_id)
+        }
+        [state_id, "validators"] => get_state_validators(req, beacon_chain, state_id),
+        [state_id, "proof"] => get_state_proof(req, beacon_chain, state_id),
+        _ => Err(ApiError::NotFound(
+            "Request path and/or method not found.".to_owned(),
+        )),
+    }
+}
+
+/// HTTP handler to return the balances of the validators in the state identified by `state_id`.
+///
+/// Supports paging via the `offset` and `limit` query parameters.
+pub fn get_state_validator_balances<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+    state_id: &str,
+) -> ApiResult {
+    let page = Page::from_request(&req)?;
+    let state = partial_state_from_id(&beacon_chain, state_id)?;
+
+    let balances: Vec<ValidatorBalanceData> = state
+        .balances
+        .iter()
+        .enumerate()
+        .skip(page.offset)
+        .take(page.limit)
+        .map(|(index, balance)| ValidatorBalanceData {
+            index: index as u64,
+            balance: *balance,
+        })
+        .collect();
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&balances)
+}
+
+/// HTTP handler to return a summary of the validators in the state identified by `state_id`.
+///
+/// Supports filtering via a comma-separated `status` query parameter (e.g.,
+/// `status=active_ongoing,active_exiting`) and paging via the `offset` and `limit` query
+/// parameters. Paging is applied after filtering.
+pub fn get_state_validators<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+    state_id: &str,
+) -> ApiResult {
+    let page = Page::from_request(&req)?;
+    let statuses: Option<Vec<ValidatorStatus>> =
+        match UrlQuery::from_request(&req).and_then(|query| query.first_of(&["status"])) {
+            Ok((_key, value)) => Some(
+                value
+                    .split(',')
+                    .map(ValidatorStatus::from_str)
+                    .collect::<Result<_, _>>()?,
+            ),
+            Err(_) => None,
+        };
+
+    let state = partial_state_from_id(&beacon_chain, state_id)?;
+    let epoch = state.current_epoch::<T::EthSpec>();
+    let far_future_epoch = beacon_chain.spec.far_future_epoch;
+
+    let validators = state
+        .validators
+        .iter()
+        .zip(state.balances.iter())
+        .enumerate()
+        .filter_map(|(index, (validator, balance))| {
+            let status = ValidatorStatus::from_validator(validator, epoch, far_future_epoch);
+
+            match &statuses {
+                Some(statuses) if !statuses.contains(&status) => None,
+                _ => Some((index, validator, *balance, status)),
+            }
+        })
+        .skip(page.offset)
+        .take(page.limit)
+        .map(|(index, validator, balance, status)| ValidatorData {
+            index: index as u64,
+            pubkey: validator.pubkey.clone(),
+            balance,
+            effective_balance: validator.effective_balance,
+            status,
+        })
+        .collect::<Vec<_>>();
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&validators)
+}
+
+/// HTTP handler to return a Merkle proof of a node in the state identified by `state_id`.
+///
+/// The node is given by one of the `field` (e.g., `finalized_checkpoint`),
+/// `validator_index` or `generalized_index` query parameters.
+pub fn get_state_proof<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+    state_id: &str,
+) -> ApiResult {
+    let (key, value) = UrlQuery::from_request(&req)?.first_of(&[
+        "field",
+        "validator_index",
+        "generalized_index",
+    ])?;
+
+    let generalized_index = match key.as_ref() {
+        "field" => BeaconState::<T::EthSpec>::field_generalized_index(&value)
+            .ok_or_else(|| ApiError::BadRequest(format!("Unknown state field: {}", value)))?,
+        "validator_index" => value
+            .parse::<usize>()
+            .map(BeaconState::<T::EthSpec>::validator_generalized_index)
+            .map_err(|e| {
+                ApiError::BadRequest(format!("Unable to parse validator index: {:?}", e))
+            })?,
+        "generalized_index" => value.parse::<usize>().map_err(|e| {
+            ApiError::BadRequest(format!("Unable to parse generalized index: {:?}", e))
+        })?,
+        _ => return Err(ApiError::ServerError("Unexpected query parameter".into())),
+    };
+
+    let mut state = state_from_id(&beacon_chain, state_id)?;
+    let (leaf, proof) = state
+        .compute_merkle_proof(generalized_index)
+        .map_err(|e| match e {
+            BeaconStateError::UnsupportedGeneralizedIndex(_)
+            | BeaconStateError::UnknownValidator => ApiError::BadRequest(format!(
+                "Unable to prove generalized index {}: {:?}",
+                generalized_index, e
+            )),
+            e => e.into(),
+        })?;
+    let state_root = state.update_tree_hash_cache()?;
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&StateProofData {
+        state_root,
+        generalized_index: generalized_index as u64,
+        leaf,
+        proof,
+    })
+}
+
 #[derive(Serialize, Encode)]
 #[serde(bound = "T: EthSpec")]
 pub struct StateResponse<T: EthSpec> {
@@ -196,7 +496,7 @@ pub fn get_state<T: BeaconChainTypes>(
 
             let state = beacon_chain
                 .store
-                .get(root)?
+                .get_state(root, None)?
                 .ok_or_else(|| ApiError::NotFound(format!("No state for root: {:?}", root)))?;
 
             (*root, state)
@@ -257,3 +557,148 @@ pub fn get_genesis_time<T: BeaconChainTypes>(
 ) -> ApiResult {
     ResponseBuilder::new(&req)?.body(&beacon_chain.head().beacon_state.genesis_time)
 }
+
+/// HTTP handler to verify a `VoluntaryExit`, add it to the op pool and publish it to the network.
+pub fn post_voluntary_exit<T: BeaconChainTypes + 'static>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+    network_chan: NetworkChannel,
+) -> BoxFut {
+    post_operation::<T, VoluntaryExit, _, _>(
+        req,
+        beacon_chain,
+        network_chan,
+        GossipKind::VoluntaryExit,
+        PubsubMessage::VoluntaryExit,
+        |chain, exit| chain.process_voluntary_exit(exit),
+    )
+}
+
+/// HTTP handler to verify a `ProposerSlashing`, add it to the op pool and publish it to the
+/// network.
+pub fn post_proposer_slashing<T: BeaconChainTypes + 'static>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+    network_chan: NetworkChannel,
+) -> BoxFut {
+    post_operation::<T, ProposerSlashing, _, _>(
+        req,
+        beacon_chain,
+        network_chan,
+        GossipKind::ProposerSlashing,
+        PubsubMessage::ProposerSlashing,
+        |chain, slashing| chain.process_proposer_slashing(slashing),
+    )
+}
+
+/// HTTP handler to verify an `AttesterSlashing`, add it to the op pool and publish it to the
+/// network.
+pub fn post_attester_slashing<T: BeaconChainTypes + 'static>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+    network_chan: NetworkChannel,
+) -> BoxFut {
+    post_operation::<T, AttesterSlashing<T::EthSpec>, _, _>(
+        req,
+        beacon_chain,
+        network_chan,
+        GossipKind::AttesterSlashing,
+        PubsubMessage::AttesterSlashing,
+        |chain, slashing| chain.process_attester_slashing(slashing),
+    )
+}
+
+/// Parses an operation from the JSON request body and passes it to `process`.
+///
+/// Operations which are new to the op pool are published to the network, operations which are
+/// already known are silently accepted and invalid operations are rejected with a 400.
+fn post_operation<T, O, E, F>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+    network_chan: NetworkChannel,
+    topic: GossipKind,
+    message: fn(Vec<u8>) -> PubsubMessage,
+    process: F,
+) -> BoxFut
+where
+    T: BeaconChainTypes + 'static,
+    O: DeserializeOwned + ssz::Encode + Send + 'static,
+    E: Debug,
+    F: FnOnce(&BeaconChain<T>, O) -> Result<OperationProcessingOutcome, E> + Send + 'static,
+{
+    try_future!(check_content_type_for_json(&req));
+    let response_builder = ResponseBuilder::new(&req);
+
+    let future = req
+        .into_body()
+        .concat2()
+        .map_err(|e| ApiError::ServerError(format!("Unable to get request body: {:?}", e)))
+        .and_then(move |chunks| {
+            serde_json::from_slice::<O>(&chunks).map_err(|e| {
+                ApiError::BadRequest(format!("Unable to parse JSON into {:?}: {:?}", topic, e))
+            })
+        })
+        .and_then(move |operation| {
+            let bytes = operation.as_ssz_bytes();
+
+            match process(&beacon_chain, operation) {
+                Ok(OperationProcessingOutcome::Processed) => {
+                    publish_operation_to_network(network_chan, topic, message(bytes))
+                }
+                Ok(OperationProcessingOutcome::AlreadyKnown) => Ok(()),
+                Err(e) => Err(ApiError::BadRequest(format!(
+                    "Invalid {:?}: {:?}",
+                    topic, e
+                ))),
+            }
+        })
+        .and_then(|_| response_builder?.body_no_ssz(&()));
+
+    Box::new(future)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn validator_status() {
+        let far_future_epoch = Epoch::new(u64::max_value());
+        let status = |validator: &Validator, epoch: u64| {
+            ValidatorStatus::from_validator(validator, Epoch::new(epoch), far_future_epoch)
+        };
+
+        let mut validator = Validator::default();
+        assert_eq!(status(&validator, 0), ValidatorStatus::PendingInitialized);
+
+        validator.activation_eligibility_epoch = Epoch::new(1);
+        assert_eq!(status(&validator, 0), ValidatorStatus::PendingQueued);
+
+        validator.activation_epoch = Epoch::new(2);
+        assert_eq!(status(&validator, 2), ValidatorStatus::ActiveOngoing);
+
+        validator.exit_epoch = Epoch::new(4);
+        assert_eq!(status(&validator, 3), ValidatorStatus::ActiveExiting);
+
+        validator.withdrawable_epoch = Epoch::new(6);
+        assert_eq!(status(&validator, 4), ValidatorStatus::ExitedUnslashed);
+        assert_eq!(status(&validator, 6), ValidatorStatus::WithdrawalPossible);
+
+        validator.slashed = true;
+        assert_eq!(status(&validator, 3), ValidatorStatus::ActiveSlashed);
+        assert_eq!(status(&validator, 5), ValidatorStatus::ExitedSlashed);
+    }
+
+    #[test]
+    fn validator_status_from_str() {
+        assert_eq!(
+            "active_ongoing".parse::<ValidatorStatus>(),
+            Ok(ValidatorStatus::ActiveOngoing)
+        );
+        assert_eq!(
+            "withdrawal_possible".parse::<ValidatorStatus>(),
+            Ok(ValidatorStatus::WithdrawalPossible)
+        );
+        assert!("cats".parse::<ValidatorStatus>().is_err());
+    }
+}
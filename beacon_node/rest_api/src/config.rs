@@ -39,6 +39,15 @@ pub struct Config {
     pub listen_address: Ipv4Addr,
     /// The port the REST API HTTP server will listen on.
     pub port: u16,
+    /// The bearer token required to access the administrative `/lighthouse` endpoints. If
+    /// `None`, the administrative endpoints are disabled.
+    pub admin_token: Option<String>,
+    /// The bearer token required for `POST` requests outside of the administrative `/lighthouse`
+    /// endpoints (e.g., publishing blocks). If `None`, no token is required.
+    pub api_token: Option<String>,
+    /// The origins permitted to make cross-origin requests, as a comma-separated list or `*` for
+    /// any origin. If `None`, cross-origin requests are not permitted.
+    pub allow_origin: Option<String>,
 }
 
 impl Default for Config {
@@ -47,6 +56,9 @@ impl Default for Config {
             enabled: true,
             listen_address: Ipv4Addr::new(127, 0, 0, 1),
             port: 5052,
+            admin_token: None,
+            api_token: None,
+            allow_origin: None,
         }
     }
 }
@@ -69,6 +81,18 @@ impl Config {
                 .map_err(|_| "api-port is not a valid u16.")?;
         }
 
+        if let Some(admin_token) = args.value_of("api-admin-token") {
+            self.admin_token = Some(admin_token.to_string());
+        }
+
+        if let Some(api_token) = args.value_of("api-token") {
+            self.api_token = Some(api_token.to_string());
+        }
+
+        if let Some(allow_origin) = args.value_of("api-allow-origin") {
+            self.allow_origin = Some(allow_origin.to_string());
+        }
+
         Ok(())
     }
 }
@@ -1,6 +1,9 @@
 use crate::BoxFut;
+use beacon_chain::BeaconChainError;
 use hyper::{Body, Response, StatusCode};
+use serde::{Deserialize, Serialize};
 use std::error::Error as StdError;
+use store::HotColdDBError;
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum ApiError {
@@ -8,10 +11,58 @@ pub enum ApiError {
     ServerError(String),
     NotImplemented(String),
     BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
     NotFound(String),
+    ServiceUnavailable(String),
     UnsupportedType(String),
     ImATeapot(String),       // Just in case.
     ProcessingError(String), // A 202 error, for when a block/attestation cannot be processed, but still transmitted.
+    /// The node is syncing and cannot yet serve the request.
+    Syncing(String),
+    /// The request cannot be served until the genesis time has passed.
+    PreGenesis(String),
+    /// The requested data existed, but has since been pruned from the database.
+    PrunedData(String),
+    /// The requested byte range lies outside of the response body.
+    RangeNotSatisfiable(String),
+    /// A different block from the same proposer at the same slot has already been imported, so
+    /// publishing the block would be a slashable offence.
+    ProposerEquivocation(String),
+}
+
+/// A machine-readable identifier for an `ApiError`, allowing clients to distinguish between errors
+/// that share a HTTP status code.
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    MethodNotAllowed,
+    ServerError,
+    NotImplemented,
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    ServiceUnavailable,
+    UnsupportedType,
+    ImATeapot,
+    ProcessingError,
+    Syncing,
+    PreGenesis,
+    PrunedData,
+    RangeNotSatisfiable,
+    ProposerEquivocation,
+}
+
+/// The JSON body of every error response from the HTTP API.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorMessage {
+    pub code: ErrorCode,
+    /// A human-readable description of the error.
+    pub message: String,
+    /// Any additional debugging information about the cause of the error.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stacktraces: Option<Vec<String>>,
 }
 
 pub type ApiResult = Result<Response<Body>, ApiError>;
@@ -23,21 +74,58 @@ impl ApiError {
             ApiError::ServerError(desc) => (StatusCode::INTERNAL_SERVER_ERROR, desc),
             ApiError::NotImplemented(desc) => (StatusCode::NOT_IMPLEMENTED, desc),
             ApiError::BadRequest(desc) => (StatusCode::BAD_REQUEST, desc),
+            ApiError::Unauthorized(desc) => (StatusCode::UNAUTHORIZED, desc),
+            ApiError::Forbidden(desc) => (StatusCode::FORBIDDEN, desc),
             ApiError::NotFound(desc) => (StatusCode::NOT_FOUND, desc),
+            ApiError::ServiceUnavailable(desc) => (StatusCode::SERVICE_UNAVAILABLE, desc),
             ApiError::UnsupportedType(desc) => (StatusCode::UNSUPPORTED_MEDIA_TYPE, desc),
             ApiError::ImATeapot(desc) => (StatusCode::IM_A_TEAPOT, desc),
             ApiError::ProcessingError(desc) => (StatusCode::ACCEPTED, desc),
+            ApiError::Syncing(desc) => (StatusCode::SERVICE_UNAVAILABLE, desc),
+            ApiError::PreGenesis(desc) => (StatusCode::SERVICE_UNAVAILABLE, desc),
+            ApiError::PrunedData(desc) => (StatusCode::GONE, desc),
+            ApiError::RangeNotSatisfiable(desc) => (StatusCode::RANGE_NOT_SATISFIABLE, desc),
+            ApiError::ProposerEquivocation(desc) => (StatusCode::CONFLICT, desc),
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ApiError::MethodNotAllowed(_) => ErrorCode::MethodNotAllowed,
+            ApiError::ServerError(_) => ErrorCode::ServerError,
+            ApiError::NotImplemented(_) => ErrorCode::NotImplemented,
+            ApiError::BadRequest(_) => ErrorCode::BadRequest,
+            ApiError::Unauthorized(_) => ErrorCode::Unauthorized,
+            ApiError::Forbidden(_) => ErrorCode::Forbidden,
+            ApiError::NotFound(_) => ErrorCode::NotFound,
+            ApiError::ServiceUnavailable(_) => ErrorCode::ServiceUnavailable,
+            ApiError::UnsupportedType(_) => ErrorCode::UnsupportedType,
+            ApiError::ImATeapot(_) => ErrorCode::ImATeapot,
+            ApiError::ProcessingError(_) => ErrorCode::ProcessingError,
+            ApiError::Syncing(_) => ErrorCode::Syncing,
+            ApiError::PreGenesis(_) => ErrorCode::PreGenesis,
+            ApiError::PrunedData(_) => ErrorCode::PrunedData,
+            ApiError::RangeNotSatisfiable(_) => ErrorCode::RangeNotSatisfiable,
+            ApiError::ProposerEquivocation(_) => ErrorCode::ProposerEquivocation,
         }
     }
 }
 
 impl Into<Response<Body>> for ApiError {
     fn into(self) -> Response<Body> {
-        let status_code = self.status_code();
+        let code = self.code();
+        let (status, message) = self.status_code();
+        let body = serde_json::to_string(&ErrorMessage {
+            code,
+            message,
+            stacktraces: None,
+        })
+        .expect("ErrorMessage should always serialize.");
+
         Response::builder()
-            .status(status_code.0)
-            .header("content-type", "text/plain; charset=utf-8")
-            .body(Body::from(status_code.1))
+            .status(status)
+            .header("content-type", "application/json")
+            .body(Body::from(body))
             .expect("Response should always be created.")
     }
 }
@@ -50,7 +138,38 @@ impl Into<BoxFut> for ApiError {
 
 impl From<store::Error> for ApiError {
     fn from(e: store::Error) -> ApiError {
-        ApiError::ServerError(format!("Database error: {:?}", e))
+        match e {
+            store::Error::HotColdDBError(HotColdDBError::MissingRestorePoint(_))
+            | store::Error::HotColdDBError(HotColdDBError::MissingFrozenBlockRoot(_)) => {
+                ApiError::PrunedData(format!("Data has been pruned from the database: {:?}", e))
+            }
+            e => ApiError::ServerError(format!("Database error: {:?}", e)),
+        }
+    }
+}
+
+impl From<BeaconChainError> for ApiError {
+    fn from(e: BeaconChainError) -> ApiError {
+        match e {
+            BeaconChainError::UnableToReadSlot | BeaconChainError::SlotClockDidNotStart => {
+                ApiError::PreGenesis("The genesis time has not yet been reached".to_string())
+            }
+            BeaconChainError::DBError(e) => e.into(),
+            BeaconChainError::StateSkipTooLarge {
+                head_slot,
+                requested_slot,
+            } => ApiError::BadRequest(format!(
+                "Requested slot {} is too far beyond the head slot {}",
+                requested_slot, head_slot
+            )),
+            BeaconChainError::EpochInclusionUnavailable { epoch, head_epoch } => {
+                ApiError::BadRequest(format!(
+                    "Epoch {} has not been processed, the head is at epoch {}",
+                    epoch, head_epoch
+                ))
+            }
+            e => ApiError::ServerError(format!("BeaconChain error: {:?}", e)),
+        }
     }
 }
 
@@ -84,3 +203,42 @@ impl std::fmt::Display for ApiError {
         write!(f, "{:?}: {:?}", status.0, status.1)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn error_codes() {
+        let pre_genesis: ApiError = BeaconChainError::UnableToReadSlot.into();
+        assert_eq!(pre_genesis.code(), ErrorCode::PreGenesis);
+        assert_eq!(pre_genesis.status_code().0, StatusCode::SERVICE_UNAVAILABLE);
+
+        let pruned: ApiError =
+            store::Error::HotColdDBError(HotColdDBError::MissingRestorePoint(1)).into();
+        assert_eq!(pruned.code(), ErrorCode::PrunedData);
+        assert_eq!(pruned.status_code().0, StatusCode::GONE);
+
+        let other: ApiError = store::Error::DBError {
+            message: "bad".to_string(),
+        }
+        .into();
+        assert_eq!(other.code(), ErrorCode::ServerError);
+    }
+
+    #[test]
+    fn error_message_json() {
+        let message = ErrorMessage {
+            code: ErrorCode::Syncing,
+            message: "syncing".to_string(),
+            stacktraces: None,
+        };
+        let json = serde_json::to_string(&message).expect("should serialize");
+
+        assert_eq!(json, r#"{"code":"syncing","message":"syncing"}"#);
+        assert_eq!(
+            serde_json::from_str::<ErrorMessage>(&json).expect("should deserialize"),
+            message
+        );
+    }
+}
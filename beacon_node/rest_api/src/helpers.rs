@@ -1,22 +1,20 @@
-use crate::{ApiError, ApiResult};
+use crate::{ApiEncodingFormat, ApiError, ApiResult};
 use beacon_chain::{BeaconChain, BeaconChainTypes};
 use bls::PublicKey;
-use eth2_libp2p::{PubsubMessage, Topic};
-use eth2_libp2p::{
-    BEACON_ATTESTATION_TOPIC, BEACON_BLOCK_TOPIC, TOPIC_ENCODING_POSTFIX, TOPIC_PREFIX,
-};
+use eth2_libp2p::{GossipKind, PubsubMessage};
 use hex;
 use http::header;
 use hyper::{Body, Request};
 use network::NetworkMessage;
 use parking_lot::RwLock;
 use ssz::{Decode, Encode};
+use std::ops::Range;
 use std::sync::Arc;
-use store::{iter::AncestorIter, Store};
+use store::{iter::AncestorIter, PartialBeaconState, Store};
 use tokio::sync::mpsc;
 use types::{
-    Attestation, BeaconBlock, BeaconState, CommitteeIndex, Epoch, EthSpec, Hash256, RelativeEpoch,
-    Signature, Slot,
+    AggregateAndProof, Attestation, BeaconBlock, BeaconState, CommitteeIndex, Epoch, EthSpec,
+    Hash256, RelativeEpoch, Signature, Slot,
 };
 
 /// Parse a slot.
@@ -63,6 +61,22 @@ pub fn check_content_type_for_json(req: &Request<Body>) -> Result<(), ApiError>
     }
 }
 
+/// Returns the encoding of the body of `req`, which must be either JSON (the default) or SSZ.
+pub fn check_content_type_for_json_or_ssz(
+    req: &Request<Body>,
+) -> Result<ApiEncodingFormat, ApiError> {
+    match req.headers().get(header::CONTENT_TYPE) {
+        Some(h) if h == "application/json" => Ok(ApiEncodingFormat::JSON),
+        Some(h) if h == "application/ssz" => Ok(ApiEncodingFormat::SSZ),
+        Some(h) => Err(ApiError::BadRequest(format!(
+            "The provided content-type {:?} is not available, this endpoint only supports json \
+             and ssz.",
+            h
+        ))),
+        _ => Ok(ApiEncodingFormat::JSON),
+    }
+}
+
 /// Parse a signature from a `0x` preixed string.
 pub fn parse_signature(string: &str) -> Result<Signature, ApiError> {
     const PREFIX: &str = "0x";
@@ -80,6 +94,23 @@ pub fn parse_signature(string: &str) -> Result<Signature, ApiError> {
     }
 }
 
+/// Parse an SSZ object from a `0x` prefixed hex string.
+pub fn parse_hex_ssz_bytes<T: Decode>(string: &str) -> Result<T, ApiError> {
+    const PREFIX: &str = "0x";
+
+    if string.starts_with(PREFIX) {
+        let trimmed = string.trim_start_matches(PREFIX);
+        let bytes = hex::decode(trimmed)
+            .map_err(|e| ApiError::BadRequest(format!("Unable to parse SSZ hex: {:?}", e)))?;
+        T::from_ssz_bytes(&bytes)
+            .map_err(|e| ApiError::BadRequest(format!("Unable to parse SSZ bytes: {:?}", e)))
+    } else {
+        Err(ApiError::BadRequest(
+            "Hex bytes must have a 0x prefix".to_string(),
+        ))
+    }
+}
+
 /// Parse a root from a `0x` preixed string.
 ///
 /// E.g., `"0x0000000000000000000000000000000000000000000000000000000000000000"`
@@ -130,6 +161,36 @@ pub fn block_root_at_slot<T: BeaconChainTypes>(
         .map(|(root, _slot)| root)
 }
 
+/// Returns a `PrunedData` error if the block at `slot` has been pruned from the database of
+/// `beacon_chain`.
+pub fn ensure_block_available<T: BeaconChainTypes>(
+    beacon_chain: &BeaconChain<T>,
+    slot: Slot,
+) -> Result<(), ApiError> {
+    match beacon_chain.store.get_anchor_info() {
+        Some(anchor) if !anchor.block_available(slot) => Err(ApiError::PrunedData(format!(
+            "The block at slot {} has been pruned, blocks are available from slot {}",
+            slot, anchor.oldest_block_slot
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Returns a `PrunedData` error if the state at `slot` has been pruned from the database of
+/// `beacon_chain`.
+pub fn ensure_state_available<T: BeaconChainTypes>(
+    beacon_chain: &BeaconChain<T>,
+    slot: Slot,
+) -> Result<(), ApiError> {
+    match beacon_chain.store.get_anchor_info() {
+        Some(anchor) if !anchor.state_available(slot) => Err(ApiError::PrunedData(format!(
+            "The state at slot {} has been pruned, states are available from slot {}",
+            slot, anchor.oldest_state_slot
+        ))),
+        _ => Ok(()),
+    }
+}
+
 /// Returns a `BeaconState` and it's root in the canonical chain of `beacon_chain` at the given
 /// `slot`, if possible.
 ///
@@ -150,17 +211,112 @@ pub fn state_at_slot<T: BeaconChainTypes>(
             beacon_chain.head().beacon_state.clone(),
         ))
     } else {
+        ensure_state_available(beacon_chain, slot)?;
+
         let root = state_root_at_slot(beacon_chain, slot)?;
 
         let state: BeaconState<T::EthSpec> = beacon_chain
             .store
-            .get(&root)?
+            .get_state(&root, Some(slot))?
             .ok_or_else(|| ApiError::NotFound(format!("Unable to find state at root {}", root)))?;
 
         Ok((root, state))
     }
 }
 
+/// Returns a `BeaconState` from `beacon_chain`, as identified by a `state_id` path parameter.
+///
+/// The `state_id` may be one of `head`, `genesis`, `finalized`, `justified`, a slot or a
+/// `0x`-prefixed state root.
+pub fn state_from_id<T: BeaconChainTypes>(
+    beacon_chain: &BeaconChain<T>,
+    state_id: &str,
+) -> Result<BeaconState<T::EthSpec>, ApiError> {
+    if state_id == "head" {
+        return Ok(beacon_chain.head().beacon_state);
+    }
+
+    let (root, slot) = state_root_from_id(beacon_chain, state_id)?;
+
+    beacon_chain
+        .store
+        .get_state(&root, slot)?
+        .ok_or_else(|| ApiError::NotFound(format!("No state for root: {:?}", root)))
+}
+
+/// Returns the validators and balances of a `BeaconState` from `beacon_chain`, as identified by a
+/// `state_id` path parameter (see `state_from_id`).
+///
+/// The rest of the state is neither cloned nor (where possible) decoded.
+pub fn partial_state_from_id<T: BeaconChainTypes>(
+    beacon_chain: &BeaconChain<T>,
+    state_id: &str,
+) -> Result<PartialBeaconState, ApiError> {
+    if state_id == "head" {
+        return Ok(beacon_chain.head_partial_state());
+    }
+
+    let (root, slot) = state_root_from_id(beacon_chain, state_id)?;
+
+    beacon_chain
+        .store
+        .get_partial_state::<T::EthSpec>(&root, slot)?
+        .ok_or_else(|| ApiError::NotFound(format!("No state for root: {:?}", root)))
+}
+
+/// Returns the root of a `BeaconState` from `beacon_chain`, as identified by a `state_id` path
+/// parameter, along with the slot of the state if it is known.
+///
+/// The state itself is not loaded, other than to compute the root of a state later than the head
+/// (see `state_root_at_slot`).
+pub fn state_root_from_id<T: BeaconChainTypes>(
+    beacon_chain: &BeaconChain<T>,
+    state_id: &str,
+) -> Result<(Hash256, Option<Slot>), ApiError> {
+    let checkpoint_state_root = |block_root: Hash256| -> Result<(Hash256, Option<Slot>), ApiError> {
+        // The zero root is an alias for the genesis block.
+        if block_root == Hash256::zero() {
+            return state_root_from_slot(beacon_chain, Slot::new(0));
+        }
+
+        let block = beacon_chain
+            .store
+            .get::<BeaconBlock<T::EthSpec>>(&block_root)?
+            .ok_or_else(|| ApiError::NotFound(format!("No block for root: {:?}", block_root)))?;
+
+        Ok((block.state_root, Some(block.slot)))
+    };
+
+    match state_id {
+        "head" => {
+            let head = beacon_chain.head();
+            Ok((head.beacon_state_root, Some(head.beacon_state.slot)))
+        }
+        "genesis" => state_root_from_slot(beacon_chain, Slot::new(0)),
+        "finalized" => {
+            checkpoint_state_root(beacon_chain.head().beacon_state.finalized_checkpoint.root)
+        }
+        "justified" => checkpoint_state_root(
+            beacon_chain
+                .head()
+                .beacon_state
+                .current_justified_checkpoint
+                .root,
+        ),
+        other if other.starts_with("0x") => Ok((parse_root(other)?, None)),
+        other => state_root_from_slot(beacon_chain, parse_slot(other)?),
+    }
+}
+
+/// Returns the root of the state at `slot` in the canonical chain, if the state is available.
+fn state_root_from_slot<T: BeaconChainTypes>(
+    beacon_chain: &BeaconChain<T>,
+    slot: Slot,
+) -> Result<(Hash256, Option<Slot>), ApiError> {
+    ensure_state_available(beacon_chain, slot)?;
+    state_root_at_slot(beacon_chain, slot).map(|root| (root, Some(slot)))
+}
+
 /// Returns the root of the `BeaconState` in the canonical chain of `beacon_chain` at the given
 /// `slot`, if possible.
 ///
@@ -171,9 +327,7 @@ pub fn state_root_at_slot<T: BeaconChainTypes>(
     slot: Slot,
 ) -> Result<Hash256, ApiError> {
     let head_state = &beacon_chain.head().beacon_state;
-    let current_slot = beacon_chain
-        .slot()
-        .map_err(|_| ApiError::ServerError("Unable to read slot clock".to_string()))?;
+    let current_slot = beacon_chain.slot()?;
 
     // There are four scenarios when obtaining a state for a given slot:
     //
@@ -227,6 +381,70 @@ pub fn state_root_at_slot<T: BeaconChainTypes>(
     }
 }
 
+/// Parses the value of a `Range` header (e.g., `bytes=0-499`, `bytes=500-` or `bytes=-500`),
+/// returning the requested range of a body of `len` bytes.
+///
+/// Returns `None` if the header requests multiple ranges, in which case the whole body should be
+/// returned.
+pub fn parse_byte_range(string: &str, len: usize) -> Result<Option<Range<usize>>, ApiError> {
+    let spec = if string.starts_with("bytes=") {
+        &string["bytes=".len()..]
+    } else {
+        return Err(ApiError::BadRequest(format!(
+            "Range must be in bytes: {}",
+            string
+        )));
+    };
+
+    if spec.contains(',') {
+        return Ok(None);
+    }
+
+    let parse = |s: &str| {
+        s.trim()
+            .parse::<usize>()
+            .map_err(|e| ApiError::BadRequest(format!("Unable to parse range: {:?}", e)))
+    };
+    let not_satisfiable = || {
+        ApiError::RangeNotSatisfiable(format!(
+            "Range {} is not satisfiable for a body of {} bytes",
+            string, len
+        ))
+    };
+
+    let mut bounds = spec.splitn(2, '-');
+    let (first, last) = match (bounds.next(), bounds.next()) {
+        (Some(first), Some(last)) => (first.trim(), last.trim()),
+        _ => return Err(ApiError::BadRequest(format!("Invalid range: {}", string))),
+    };
+
+    let range = match (first.is_empty(), last.is_empty()) {
+        // A suffix, e.g., the last 500 bytes.
+        (true, false) => {
+            let suffix = parse(last)?;
+            if suffix == 0 {
+                return Err(not_satisfiable());
+            }
+            len.saturating_sub(suffix)..len
+        }
+        (false, true) => parse(first)?..len,
+        (false, false) => {
+            let (first, last) = (parse(first)?, parse(last)?);
+            if last < first {
+                return Err(ApiError::BadRequest(format!("Invalid range: {}", string)));
+            }
+            first..std::cmp::min(last + 1, len)
+        }
+        (true, true) => return Err(ApiError::BadRequest(format!("Invalid range: {}", string))),
+    };
+
+    if range.start >= len {
+        Err(not_satisfiable())
+    } else {
+        Ok(Some(range))
+    }
+}
+
 pub fn implementation_pending_response(_req: Request<Body>) -> ApiResult {
     Err(ApiError::NotImplemented(
         "API endpoint has not yet been implemented, but is planned to be soon.".to_owned(),
@@ -237,17 +455,11 @@ pub fn publish_beacon_block_to_network<T: BeaconChainTypes + 'static>(
     chan: Arc<RwLock<mpsc::UnboundedSender<NetworkMessage>>>,
     block: BeaconBlock<T::EthSpec>,
 ) -> Result<(), ApiError> {
-    // create the network topic to send on
-    let topic_string = format!(
-        "/{}/{}/{}",
-        TOPIC_PREFIX, BEACON_BLOCK_TOPIC, TOPIC_ENCODING_POSTFIX
-    );
-    let topic = Topic::new(topic_string);
     let message = PubsubMessage::Block(block.as_ssz_bytes());
 
     // Publish the block to the p2p network via gossipsub.
     if let Err(e) = chan.write().try_send(NetworkMessage::Publish {
-        topics: vec![topic],
+        topics: vec![GossipKind::BeaconBlock],
         message,
     }) {
         return Err(ApiError::ServerError(format!(
@@ -263,17 +475,11 @@ pub fn publish_attestation_to_network<T: BeaconChainTypes + 'static>(
     chan: Arc<RwLock<mpsc::UnboundedSender<NetworkMessage>>>,
     attestation: Attestation<T::EthSpec>,
 ) -> Result<(), ApiError> {
-    // create the network topic to send on
-    let topic_string = format!(
-        "/{}/{}/{}",
-        TOPIC_PREFIX, BEACON_ATTESTATION_TOPIC, TOPIC_ENCODING_POSTFIX
-    );
-    let topic = Topic::new(topic_string);
     let message = PubsubMessage::Attestation(attestation.as_ssz_bytes());
 
     // Publish the attestation to the p2p network via gossipsub.
     if let Err(e) = chan.write().try_send(NetworkMessage::Publish {
-        topics: vec![topic],
+        topics: vec![GossipKind::BeaconAttestation],
         message,
     }) {
         return Err(ApiError::ServerError(format!(
@@ -285,9 +491,49 @@ pub fn publish_attestation_to_network<T: BeaconChainTypes + 'static>(
     Ok(())
 }
 
+pub fn publish_aggregate_and_proof_to_network<T: BeaconChainTypes + 'static>(
+    chan: Arc<RwLock<mpsc::UnboundedSender<NetworkMessage>>>,
+    aggregate_and_proof: AggregateAndProof<T::EthSpec>,
+) -> Result<(), ApiError> {
+    let message = PubsubMessage::AggregateAndProof(aggregate_and_proof.as_ssz_bytes());
+
+    // Publish the aggregate to the p2p network via gossipsub.
+    if let Err(e) = chan.write().try_send(NetworkMessage::Publish {
+        topics: vec![GossipKind::BeaconAggregateAndProof],
+        message,
+    }) {
+        return Err(ApiError::ServerError(format!(
+            "Unable to send new aggregate and proof to network: {:?}",
+            e
+        )));
+    }
+
+    Ok(())
+}
+
+/// Publishes a voluntary exit or slashing to the p2p network via gossipsub, on the given `topic`.
+pub fn publish_operation_to_network(
+    chan: Arc<RwLock<mpsc::UnboundedSender<NetworkMessage>>>,
+    topic: GossipKind,
+    message: PubsubMessage,
+) -> Result<(), ApiError> {
+    if let Err(e) = chan.write().try_send(NetworkMessage::Publish {
+        topics: vec![topic],
+        message,
+    }) {
+        return Err(ApiError::ServerError(format!(
+            "Unable to send new {:?} to network: {:?}",
+            topic, e
+        )));
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::error::ErrorCode;
 
     #[test]
     fn parse_root_works() {
@@ -313,4 +559,26 @@ mod test {
         assert_eq!(parse_slot("10000000"), Ok(Slot::new(10_000_000)));
         assert!(parse_slot("cats").is_err());
     }
+
+    #[test]
+    fn parse_byte_range_works() {
+        assert_eq!(parse_byte_range("bytes=0-499", 1000), Ok(Some(0..500)));
+        assert_eq!(parse_byte_range("bytes=500-", 1000), Ok(Some(500..1000)));
+        assert_eq!(parse_byte_range("bytes=-100", 1000), Ok(Some(900..1000)));
+        assert_eq!(parse_byte_range("bytes=-2000", 1000), Ok(Some(0..1000)));
+        assert_eq!(
+            parse_byte_range("bytes=900-1999", 1000),
+            Ok(Some(900..1000))
+        );
+        assert_eq!(parse_byte_range("bytes=0-1,5-6", 1000), Ok(None));
+
+        assert_eq!(
+            parse_byte_range("bytes=1000-", 1000).map_err(|e| e.code()),
+            Err(ErrorCode::RangeNotSatisfiable)
+        );
+        assert!(parse_byte_range("bytes=-0", 1000).is_err());
+        assert!(parse_byte_range("bytes=5-1", 1000).is_err());
+        assert!(parse_byte_range("bytes=-", 1000).is_err());
+        assert!(parse_byte_range("items=0-1", 1000).is_err());
+    }
 }
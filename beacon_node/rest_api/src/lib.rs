@@ -4,13 +4,18 @@ mod macros;
 extern crate lazy_static;
 extern crate network as client_network;
 
+mod access;
+mod admin;
 mod beacon;
 pub mod config;
 mod error;
 mod helpers;
+mod light_client;
+mod lighthouse;
 mod metrics;
 mod network;
 mod node;
+mod pre_genesis;
 mod response_builder;
 mod router;
 mod spec;
@@ -38,9 +43,28 @@ use tokio::sync::mpsc;
 use url_query::UrlQuery;
 
 pub use crate::helpers::parse_pubkey;
-pub use beacon::{BlockResponse, HeadResponse, StateResponse};
+pub use admin::{TaskHandle, TaskStatus};
+pub use beacon::{
+    BlockResponse, HeadResponse, StateProofData, StateResponse, ValidatorBalanceData,
+    ValidatorData, ValidatorStatus,
+};
+pub use client_network::SyncState;
 pub use config::Config;
-pub use validator::{BulkValidatorDutiesRequest, ValidatorDuty};
+pub use error::{ErrorCode, ErrorMessage};
+pub use light_client::FinalityProof;
+pub use lighthouse::{
+    ChainQualityResponse, DepositLogResponse, Eth1StatusResponse, ForkChoiceNodeResponse,
+    ForkChoiceResponse, ValidatorInclusionRequest, ValidatorInclusionResponse,
+};
+pub use network::{BanPeerRequest, NodeIdentity, PeerCount, PeerData};
+pub use node::{
+    GenesisState, GenesisStatusResponse, SyncStatus, SyncingChainData, SyncingResponse,
+};
+pub use pre_genesis::{start_pre_genesis_server, PreGenesisServer};
+pub use validator::{
+    AttesterDutyData, BulkValidatorDutiesRequest, DutiesResponse, ProposerDutyData, ValidatorDuty,
+    ValidatorSlashingStatus,
+};
 
 pub type BoxFut = Box<dyn Future<Item = Response<Body>, Error = ApiError> + Send>;
 pub type NetworkChannel = Arc<RwLock<mpsc::UnboundedSender<NetworkMessage>>>;
@@ -50,8 +74,13 @@ pub struct NetworkInfo<T: BeaconChainTypes> {
     pub network_chan: mpsc::UnboundedSender<NetworkMessage>,
 }
 
+/// Starts serving the HTTP API.
+///
+/// If `listener` is supplied (e.g., from `PreGenesisServer::shutdown`) the server accepts
+/// connections on it, rather than binding to the address in `config`.
 pub fn start_server<T: BeaconChainTypes>(
     config: &Config,
+    listener: Option<std::net::TcpListener>,
     executor: &TaskExecutor,
     beacon_chain: Arc<BeaconChain<T>>,
     network_info: NetworkInfo<T>,
@@ -61,6 +90,9 @@ pub fn start_server<T: BeaconChainTypes>(
 ) -> Result<(exit_future::Signal, SocketAddr), hyper::Error> {
     let inner_log = log.clone();
     let eth2_config = Arc::new(eth2_config);
+    let admin = Arc::new(admin::Admin::new(config.admin_token.clone()));
+    let access = Arc::new(access::ApiAccess::new(config));
+    let finality_proof_cache = Arc::new(light_client::FinalityProofCache::default());
 
     // Define the function that will build the request handler.
     let make_service = make_service_fn(move |_socket: &AddrStream| {
@@ -70,6 +102,9 @@ pub fn start_server<T: BeaconChainTypes>(
         let network_service = network_info.network_service.clone();
         let network_channel = Arc::new(RwLock::new(network_info.network_chan.clone()));
         let db_path = db_path.clone();
+        let admin = admin.clone();
+        let access = access.clone();
+        let finality_proof_cache = finality_proof_cache.clone();
 
         service_fn(move |req: Request<Body>| {
             router::route(
@@ -80,12 +115,21 @@ pub fn start_server<T: BeaconChainTypes>(
                 eth2_config.clone(),
                 log.clone(),
                 db_path.clone(),
+                admin.clone(),
+                access.clone(),
+                finality_proof_cache.clone(),
             )
         })
     });
 
-    let bind_addr = (config.listen_address, config.port).into();
-    let server = Server::bind(&bind_addr).serve(make_service);
+    let server = match listener {
+        Some(listener) => Server::from_tcp(listener)?,
+        None => {
+            let bind_addr = (config.listen_address, config.port).into();
+            Server::bind(&bind_addr)
+        }
+    }
+    .serve(make_service);
 
     // Determine the address the server is actually listening on.
     //
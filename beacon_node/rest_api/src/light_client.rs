@@ -0,0 +1,306 @@
+//! Serves proofs of finality to light clients, derived from the states in the database.
+//!
+//! A finality proof consists of the header of a block whose post-state records some finalized
+//! checkpoint, a Merkle proof of that checkpoint against the state root of the header, and the
+//! chain of headers from the finalized block up to that header. A client which trusts the header
+//! (e.g., by verifying the signatures of the attestations to it) can then trust every header in the
+//! chain and their state roots.
+//!
+//! Proofs of finalized epochs do not change, so computed proofs are cached.
+use crate::helpers::{block_root_at_slot, parse_epoch};
+use crate::response_builder::ResponseBuilder;
+use crate::{ApiError, ApiResult, UrlQuery};
+use beacon_chain::{BeaconChain, BeaconChainTypes};
+use hyper::{Body, Request};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use store::Store;
+use types::{BeaconBlock, BeaconBlockHeader, BeaconState, Checkpoint, Epoch, EthSpec, Hash256};
+
+/// The maximum number of finality proofs which are cached. The proofs of the lowest epochs are
+/// dropped first.
+pub const MAX_CACHED_FINALITY_PROOFS: usize = 64;
+
+/// A proof that `finalized_checkpoint` was finalized.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct FinalityProof {
+    pub finalized_checkpoint: Checkpoint,
+    /// The header of a block whose post-state records `finalized_checkpoint`.
+    pub attested_header: BeaconBlockHeader,
+    /// The generalized index of the `finalized_checkpoint` field of a `BeaconState`.
+    pub generalized_index: u64,
+    /// The Merkle proof of `finalized_checkpoint` against the state root of `attested_header`.
+    pub finality_branch: Vec<Hash256>,
+    /// The headers from the finalized block up to `attested_header`, each the parent of the next.
+    pub headers: Vec<BeaconBlockHeader>,
+}
+
+/// A cache of the finality proofs of finalized epochs.
+#[derive(Default)]
+pub struct FinalityProofCache {
+    proofs: RwLock<BTreeMap<Epoch, FinalityProof>>,
+}
+
+impl FinalityProofCache {
+    /// Returns the cached proof of the finality of `epoch`, if any.
+    pub fn get(&self, epoch: Epoch) -> Option<FinalityProof> {
+        self.proofs.read().get(&epoch).cloned()
+    }
+
+    /// Caches the proof of the finality of `epoch`, dropping the proof of the lowest epoch if the
+    /// cache is full.
+    pub fn insert(&self, epoch: Epoch, proof: FinalityProof) {
+        let mut proofs = self.proofs.write();
+        proofs.insert(epoch, proof);
+
+        while proofs.len() > MAX_CACHED_FINALITY_PROOFS {
+            if let Some(lowest) = proofs.keys().next().copied() {
+                proofs.remove(&lowest);
+            }
+        }
+    }
+
+    /// The number of cached proofs.
+    pub fn len(&self) -> usize {
+        self.proofs.read().len()
+    }
+
+    /// Returns `true` if no proofs are cached.
+    pub fn is_empty(&self) -> bool {
+        self.proofs.read().is_empty()
+    }
+}
+
+/// HTTP handler to return a proof of the finality of the checkpoint at `epoch`.
+pub fn get_finality_proof<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+    cache: Arc<FinalityProofCache>,
+) -> ApiResult {
+    let epoch = parse_epoch(&UrlQuery::from_request(&req)?.only_one("epoch")?)?;
+
+    let proof = match cache.get(epoch) {
+        Some(proof) => proof,
+        None => {
+            let proof = compute_finality_proof(&beacon_chain, epoch)?;
+            cache.insert(epoch, proof.clone());
+            proof
+        }
+    };
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&proof)
+}
+
+/// Computes the proof of the finality of the checkpoint at `epoch` in the canonical chain.
+///
+/// Returns an error if `epoch` is not finalized, or if finality skipped over it.
+pub fn compute_finality_proof<T: BeaconChainTypes>(
+    beacon_chain: &BeaconChain<T>,
+    epoch: Epoch,
+) -> Result<FinalityProof, ApiError> {
+    let slots_per_epoch = T::EthSpec::slots_per_epoch();
+    let head = beacon_chain.head();
+
+    if epoch == T::EthSpec::genesis_epoch() {
+        return Err(ApiError::BadRequest(
+            "The genesis epoch is finalized by definition".into(),
+        ));
+    }
+    if epoch > head.beacon_state.finalized_checkpoint.epoch {
+        return Err(ApiError::NotFound(format!(
+            "Epoch {} is not finalized, the finalized epoch is {}",
+            epoch, head.beacon_state.finalized_checkpoint.epoch
+        )));
+    }
+
+    // The finalized checkpoint changes only at epoch boundaries, so any block in an epoch
+    // records the same finalized checkpoint. An epoch can finalize the epoch two before it at the
+    // earliest.
+    for attested_epoch in
+        (epoch.as_u64() + 2..=head.beacon_state.current_epoch().as_u64()).map(Epoch::new)
+    {
+        let last_slot = std::cmp::min(
+            attested_epoch.end_slot(slots_per_epoch),
+            head.beacon_block.slot,
+        );
+        let block_root = block_root_at_slot(beacon_chain, last_slot).ok_or_else(|| {
+            ApiError::ServerError(format!("No canonical block root at slot {}", last_slot))
+        })?;
+        let block = get_block(beacon_chain, &block_root)?;
+
+        if block.slot < attested_epoch.start_slot(slots_per_epoch) {
+            // There are no blocks in the epoch.
+            continue;
+        }
+
+        let mut state: BeaconState<T::EthSpec> = beacon_chain
+            .store
+            .get_state(&block.state_root, Some(block.slot))?
+            .ok_or_else(|| {
+                ApiError::NotFound(format!("No state for root: {:?}", block.state_root))
+            })?;
+
+        if state.finalized_checkpoint.epoch < epoch {
+            continue;
+        } else if state.finalized_checkpoint.epoch > epoch {
+            return Err(ApiError::NotFound(format!(
+                "Epoch {} was not finalized by a checkpoint, finality advanced to epoch {}",
+                epoch, state.finalized_checkpoint.epoch
+            )));
+        }
+
+        let generalized_index =
+            BeaconState::<T::EthSpec>::field_generalized_index("finalized_checkpoint").ok_or_else(
+                || ApiError::ServerError("The state has no finalized checkpoint field".into()),
+            )?;
+        let (_leaf, finality_branch) = state.compute_merkle_proof(generalized_index)?;
+
+        let finalized_checkpoint = state.finalized_checkpoint.clone();
+        let headers = header_chain(beacon_chain, finalized_checkpoint.root, block)?;
+        let attested_header = headers
+            .last()
+            .cloned()
+            .ok_or_else(|| ApiError::ServerError("Empty header chain".into()))?;
+
+        return Ok(FinalityProof {
+            finalized_checkpoint,
+            attested_header,
+            generalized_index: generalized_index as u64,
+            finality_branch,
+            headers,
+        });
+    }
+
+    Err(ApiError::NotFound(format!(
+        "No block records the finality of epoch {}",
+        epoch
+    )))
+}
+
+/// Returns the headers of the chain of blocks from the block at `ancestor_root` up to `block`.
+fn header_chain<T: BeaconChainTypes>(
+    beacon_chain: &BeaconChain<T>,
+    ancestor_root: Hash256,
+    block: BeaconBlock<T::EthSpec>,
+) -> Result<Vec<BeaconBlockHeader>, ApiError> {
+    let mut headers = vec![block.block_header()];
+    let mut block = block;
+
+    while block.canonical_root() != ancestor_root {
+        block = get_block(beacon_chain, &block.parent_root)?;
+        headers.push(block.block_header());
+    }
+    headers.reverse();
+
+    Ok(headers)
+}
+
+fn get_block<T: BeaconChainTypes>(
+    beacon_chain: &BeaconChain<T>,
+    block_root: &Hash256,
+) -> Result<BeaconBlock<T::EthSpec>, ApiError> {
+    beacon_chain
+        .store
+        .get::<BeaconBlock<T::EthSpec>>(block_root)?
+        .ok_or_else(|| ApiError::NotFound(format!("No block for root: {:?}", block_root)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use beacon_chain::test_utils::{AttestationStrategy, BeaconChainHarness, BlockStrategy};
+    use merkle_proof::verify_merkle_proof;
+    use tree_hash::TreeHash;
+    use types::test_utils::generate_deterministic_keypairs;
+    use types::MinimalEthSpec;
+
+    fn proof(epoch: u64) -> FinalityProof {
+        let spec = MinimalEthSpec::default_spec();
+        let header = BeaconBlock::<MinimalEthSpec>::empty(&spec).block_header();
+
+        FinalityProof {
+            finalized_checkpoint: Checkpoint {
+                epoch: Epoch::new(epoch),
+                root: Hash256::zero(),
+            },
+            attested_header: header.clone(),
+            generalized_index: 0,
+            finality_branch: vec![],
+            headers: vec![header],
+        }
+    }
+
+    #[test]
+    fn finality_proof_cache_drops_lowest_epochs() {
+        let cache = FinalityProofCache::default();
+        assert!(cache.is_empty());
+
+        let count = MAX_CACHED_FINALITY_PROOFS as u64 + 2;
+        for epoch in (1..=count).rev() {
+            cache.insert(Epoch::new(epoch), proof(epoch));
+        }
+
+        assert_eq!(cache.len(), MAX_CACHED_FINALITY_PROOFS);
+        assert_eq!(cache.get(Epoch::new(1)), None);
+        assert_eq!(cache.get(Epoch::new(2)), None);
+        assert_eq!(cache.get(Epoch::new(count)), Some(proof(count)));
+    }
+
+    #[test]
+    fn finality_proof_verifies_against_the_chain() {
+        let harness = BeaconChainHarness::new(MinimalEthSpec, generate_deterministic_keypairs(24));
+        harness.advance_slot();
+        harness.extend_chain(
+            MinimalEthSpec::slots_per_epoch() as usize * 5,
+            BlockStrategy::OnCanonicalHead,
+            AttestationStrategy::AllValidators,
+        );
+
+        let finalized_checkpoint = harness.chain.head().beacon_state.finalized_checkpoint;
+        assert!(
+            finalized_checkpoint.epoch > Epoch::new(0),
+            "the chain should have finalized"
+        );
+
+        let proof = compute_finality_proof(&harness.chain, finalized_checkpoint.epoch)
+            .expect("should compute finality proof");
+        assert_eq!(proof.finalized_checkpoint, finalized_checkpoint);
+
+        let depth = proof.finality_branch.len();
+        assert!(
+            verify_merkle_proof(
+                Hash256::from_slice(&proof.finalized_checkpoint.tree_hash_root()),
+                &proof.finality_branch,
+                depth,
+                proof.generalized_index as usize - (1 << depth),
+                proof.attested_header.state_root,
+            ),
+            "finality branch should prove the checkpoint against the attested state root"
+        );
+
+        assert_eq!(
+            proof.headers.first().map(BeaconBlockHeader::canonical_root),
+            Some(finalized_checkpoint.root),
+            "headers should start at the finalized block"
+        );
+        assert_eq!(
+            proof.headers.last(),
+            Some(&proof.attested_header),
+            "headers should end at the attested header"
+        );
+        for pair in proof.headers.windows(2) {
+            assert_eq!(
+                pair[1].parent_root,
+                pair[0].canonical_root(),
+                "each header should be the parent of the next"
+            );
+        }
+
+        assert!(
+            compute_finality_proof(&harness.chain, finalized_checkpoint.epoch + 1).is_err(),
+            "should not prove an epoch which is not finalized"
+        );
+    }
+}
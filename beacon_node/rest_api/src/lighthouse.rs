@@ -0,0 +1,529 @@
+use crate::admin::Admin;
+use crate::helpers::{
+    parse_byte_range, parse_pubkey, parse_slot, state_from_id, state_root_from_id,
+};
+use crate::response_builder::ResponseBuilder;
+use crate::{ApiError, ApiResult, BoxFut, UrlQuery};
+use beacon_chain::{
+    BeaconChain, BeaconChainTypes, ChainQuality, EpochInclusionSummary, ValidatorInclusion,
+};
+use bls::{PublicKey, PublicKeyBytes};
+use futures::{stream, Future, Stream};
+use http::header;
+use hyper::{Body, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use snap::write::FrameEncoder;
+use ssz::Encode;
+use std::io::Write;
+use std::sync::Arc;
+use types::{Checkpoint, Epoch, Hash256, Slot};
+
+/// The maximum number of slots which may be requested from `/lighthouse/block_rewards`, since each
+/// block in the range must be re-applied to the state of its parent.
+pub const MAX_BLOCK_REWARDS_SLOTS: u64 = 1_024;
+
+/// The number of epochs returned by `/lighthouse/chain_quality` if none is specified.
+pub const DEFAULT_CHAIN_QUALITY_EPOCHS: usize = 8;
+
+/// The size of the chunks in which `/lighthouse/debug/states/{state_id}/ssz` streams a state.
+pub const STATE_SSZ_CHUNK_BYTES: usize = 1 << 20;
+
+/// A single node in the fork choice block tree.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct ForkChoiceNodeResponse {
+    pub root: Hash256,
+    pub parent_root: Option<Hash256>,
+    pub children: Vec<Hash256>,
+    /// The justified epoch of the state of the block. Nodes which do not agree with the justified
+    /// and finalized epochs of the fork choice are not viable heads.
+    pub justified_epoch: Epoch,
+    /// The finalized epoch of the state of the block.
+    pub finalized_epoch: Epoch,
+    pub weight: u64,
+    pub best_descendant: Hash256,
+}
+
+/// A dump of the fork choice block tree.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct ForkChoiceResponse {
+    /// The root of the current canonical head block.
+    pub head_root: Hash256,
+    /// The fork choice rule's view of the justified checkpoint.
+    pub justified_checkpoint: Checkpoint,
+    /// The finalized checkpoint of the head state.
+    pub finalized_checkpoint: Checkpoint,
+    pub nodes: Vec<ForkChoiceNodeResponse>,
+}
+
+/// HTTP handler to return the nodes of the fork choice block tree, for debugging and
+/// visualisation.
+pub fn get_fork_choice<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+) -> ApiResult {
+    let head = beacon_chain.head();
+
+    let nodes = beacon_chain
+        .fork_choice
+        .nodes()
+        .map_err(|e| ApiError::ServerError(format!("Unable to read fork choice: {:?}", e)))?
+        .into_iter()
+        .map(|node| ForkChoiceNodeResponse {
+            root: node.root,
+            parent_root: node.parent_root,
+            children: node.children,
+            justified_epoch: node.justified_epoch,
+            finalized_epoch: node.finalized_epoch,
+            weight: node.weight,
+            best_descendant: node.best_descendant,
+        })
+        .collect();
+
+    let response = ForkChoiceResponse {
+        head_root: head.beacon_block_root,
+        justified_checkpoint: beacon_chain.fork_choice.justified_checkpoint(),
+        finalized_checkpoint: head.beacon_state.finalized_checkpoint.clone(),
+        nodes,
+    };
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&response)
+}
+
+/// The attestation inclusion of a single validator, identified by its public key.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct ValidatorInclusionResponse {
+    pub validator_pubkey: PublicKey,
+    /// `None` if the validator was not in the registry during the requested epoch.
+    pub inclusion: Option<ValidatorInclusion>,
+}
+
+/// HTTP handler to return the attestation inclusion of all validators during some `epoch`,
+/// summed across the validator registry.
+pub fn get_validator_inclusion<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+) -> ApiResult {
+    let epoch = UrlQuery::from_request(&req)?.epoch()?;
+
+    let (summary, _validators) = validator_inclusion(&beacon_chain, epoch)?;
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&summary)
+}
+
+/// HTTP handler to return the attestation inclusion of each of the given `validator_pubkeys`
+/// during some `epoch`.
+pub fn get_validator_inclusion_validators<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+) -> ApiResult {
+    let query = UrlQuery::from_request(&req)?;
+
+    let epoch = query.epoch()?;
+    let validator_pubkeys = query
+        .all_of("validator_pubkeys")?
+        .iter()
+        .map(|validator_pubkey_str| parse_pubkey(validator_pubkey_str))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let response = validator_inclusion_responses(&beacon_chain, epoch, validator_pubkeys)?;
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&response)
+}
+
+/// A request for the attestation inclusion of some validators during `epoch`.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct ValidatorInclusionRequest {
+    pub epoch: Epoch,
+    pub pubkeys: Vec<PublicKey>,
+}
+
+/// HTTP handler to return the attestation inclusion of each of the validators in the JSON
+/// `ValidatorInclusionRequest` in the request body.
+///
+/// Equivalent to `get_validator_inclusion_validators`, but not limited by the length of the URL.
+pub fn post_validator_inclusion_validators<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+) -> BoxFut {
+    let response_builder = ResponseBuilder::new(&req);
+
+    let future = req
+        .into_body()
+        .concat2()
+        .map_err(|e| ApiError::ServerError(format!("Unable to get request body: {:?}", e)))
+        .and_then(|chunks| {
+            serde_json::from_slice::<ValidatorInclusionRequest>(&chunks).map_err(|e| {
+                ApiError::BadRequest(format!(
+                    "Unable to parse JSON into ValidatorInclusionRequest: {:?}",
+                    e
+                ))
+            })
+        })
+        .and_then(move |request| {
+            validator_inclusion_responses(&beacon_chain, request.epoch, request.pubkeys)
+        })
+        .and_then(|response| response_builder?.body_no_ssz(&response));
+
+    Box::new(future)
+}
+
+fn validator_inclusion_responses<T: BeaconChainTypes>(
+    beacon_chain: &BeaconChain<T>,
+    epoch: Epoch,
+    validator_pubkeys: Vec<PublicKey>,
+) -> Result<Vec<ValidatorInclusionResponse>, ApiError> {
+    let (_summary, validators) = validator_inclusion(beacon_chain, epoch)?;
+
+    Ok(validator_pubkeys
+        .into_iter()
+        .map(|validator_pubkey| ValidatorInclusionResponse {
+            inclusion: beacon_chain
+                .validator_index(&validator_pubkey)
+                .and_then(|i| validators.get(i))
+                .cloned(),
+            validator_pubkey,
+        })
+        .collect())
+}
+
+fn validator_inclusion<T: BeaconChainTypes>(
+    beacon_chain: &BeaconChain<T>,
+    epoch: Epoch,
+) -> Result<(EpochInclusionSummary, Vec<ValidatorInclusion>), ApiError> {
+    beacon_chain.validator_inclusion(epoch).map_err(Into::into)
+}
+
+/// HTTP handler to return the rewards earned by the proposer of each block in the canonical chain
+/// between the `start_slot` and `end_slot` (inclusive).
+pub fn get_block_rewards<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+) -> ApiResult {
+    let query = UrlQuery::from_request(&req)?;
+
+    let start_slot = query
+        .first_of(&["start_slot"])
+        .and_then(|(_key, value)| parse_slot(&value))?;
+    let end_slot = query
+        .first_of(&["end_slot"])
+        .and_then(|(_key, value)| parse_slot(&value))?;
+
+    if end_slot < start_slot {
+        return Err(ApiError::BadRequest(format!(
+            "end_slot ({}) must not be less than start_slot ({})",
+            end_slot, start_slot
+        )));
+    } else if end_slot.as_u64() - start_slot.as_u64() >= MAX_BLOCK_REWARDS_SLOTS {
+        return Err(ApiError::BadRequest(format!(
+            "At most {} slots may be requested",
+            MAX_BLOCK_REWARDS_SLOTS
+        )));
+    }
+
+    let rewards = beacon_chain.block_rewards(start_slot, end_slot)?;
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&rewards)
+}
+
+/// The timeliness of the head and the quality of recent epochs of the canonical chain, as returned
+/// by `/lighthouse/chain_quality`.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct ChainQualityResponse {
+    /// The present slot, according to the clock of the node.
+    pub current_slot: Slot,
+    pub head_slot: Slot,
+    pub head_root: Hash256,
+    /// The number of slots by which the head lags the present slot.
+    pub head_distance: u64,
+    /// The most recently processed epochs, in ascending epoch order.
+    pub epochs: Vec<ChainQuality>,
+}
+
+/// HTTP handler to return the timeliness of the head and the participation, missed proposals,
+/// inclusion delay and justification of recent epochs.
+///
+/// The number of epochs may be given by the optional `epochs` query parameter.
+pub fn get_chain_quality<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+) -> ApiResult {
+    let epochs = if req.uri().query().is_some() {
+        UrlQuery::from_request(&req)?
+            .only_one("epochs")?
+            .parse::<usize>()
+            .map_err(|e| ApiError::BadRequest(format!("Unable to parse epochs: {:?}", e)))?
+    } else {
+        DEFAULT_CHAIN_QUALITY_EPOCHS
+    };
+
+    let current_slot = beacon_chain.slot()?;
+    let head = beacon_chain.head();
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&ChainQualityResponse {
+        current_slot,
+        head_slot: head.beacon_block.slot,
+        head_root: head.beacon_block_root,
+        head_distance: current_slot
+            .as_u64()
+            .saturating_sub(head.beacon_block.slot.as_u64()),
+        epochs: beacon_chain.chain_quality(epochs),
+    })
+}
+
+/// HTTP handler to return the root of the `BeaconState` identified by the `state_id` in `path`
+/// (e.g., `/lighthouse/debug/states/finalized/root`).
+///
+/// Checkpoint sync resolves the root first and then downloads the state by root, so that a resumed
+/// download refers to the same state.
+pub fn get_debug_state_root<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+    path: &str,
+) -> ApiResult {
+    let parts: Vec<&str> = path
+        .trim_start_matches("/lighthouse/debug/states/")
+        .split('/')
+        .collect();
+    let state_id = match parts.as_slice() {
+        [state_id, "root"] => state_id,
+        _ => {
+            return Err(ApiError::NotFound(
+                "Request path and/or method not found.".to_owned(),
+            ))
+        }
+    };
+
+    let (root, _slot) = state_root_from_id(&beacon_chain, state_id)?;
+
+    ResponseBuilder::new(&req)?.body(&root)
+}
+
+/// HTTP handler to return the SSZ bytes of the `BeaconState` identified by the `state_id` in
+/// `path` (e.g., `/lighthouse/debug/states/finalized/ssz`), as used by checkpoint sync.
+///
+/// The state is compressed with the snappy frame format if `compression=snappy` is supplied. A
+/// single range of the (possibly compressed) bytes may be requested via the `Range` header, so that
+/// an interrupted download can be resumed. The body is streamed in chunks.
+pub fn get_debug_state_ssz<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+    path: &str,
+) -> ApiResult {
+    let parts: Vec<&str> = path
+        .trim_start_matches("/lighthouse/debug/states/")
+        .split('/')
+        .collect();
+    let state_id = match parts.as_slice() {
+        [state_id, "ssz"] => state_id,
+        _ => {
+            return Err(ApiError::NotFound(
+                "Request path and/or method not found.".to_owned(),
+            ))
+        }
+    };
+
+    let snappy = if req.uri().query().is_some() {
+        match UrlQuery::from_request(&req)?
+            .only_one("compression")?
+            .as_str()
+        {
+            "snappy" => true,
+            "none" => false,
+            other => {
+                return Err(ApiError::BadRequest(format!(
+                    "Unsupported compression: {}. Expected \"snappy\" or \"none\"",
+                    other
+                )))
+            }
+        }
+    } else {
+        false
+    };
+
+    let state = state_from_id(&beacon_chain, state_id)?;
+
+    let (bytes, content_type) = if snappy {
+        let mut encoder = FrameEncoder::new(vec![]);
+        encoder
+            .write_all(&state.as_ssz_bytes())
+            .map_err(|e| ApiError::ServerError(format!("Unable to compress state: {:?}", e)))?;
+        let bytes = encoder
+            .into_inner()
+            .map_err(|e| ApiError::ServerError(format!("Unable to compress state: {:?}", e)))?;
+
+        (bytes, "application/x-snappy-framed")
+    } else {
+        (state.as_ssz_bytes(), "application/ssz")
+    };
+
+    let range = req
+        .headers()
+        .get(header::RANGE)
+        .map(|value| {
+            value
+                .to_str()
+                .map_err(|e| ApiError::BadRequest(format!("Invalid Range header: {:?}", e)))
+                .and_then(|value| parse_byte_range(value, bytes.len()))
+        })
+        .transpose()?
+        .and_then(|range| range);
+
+    let mut response = Response::builder();
+    response
+        .header("content-type", content_type)
+        .header("accept-ranges", "bytes");
+
+    let body = match range {
+        Some(range) => {
+            response.status(StatusCode::PARTIAL_CONTENT).header(
+                "content-range",
+                format!("bytes {}-{}/{}", range.start, range.end - 1, bytes.len()),
+            );
+            &bytes[range]
+        }
+        None => {
+            response.status(StatusCode::OK);
+            &bytes[..]
+        }
+    };
+
+    let chunks: Vec<Vec<u8>> = body
+        .chunks(STATE_SSZ_CHUNK_BYTES)
+        .map(|chunk| chunk.to_vec())
+        .collect();
+
+    response
+        .body(Body::wrap_stream(stream::iter_ok::<_, std::io::Error>(
+            chunks,
+        )))
+        .map_err(|e| ApiError::ServerError(format!("Failed to build response: {:?}", e)))
+}
+
+/// The result of importing a deposit cache via `/lighthouse/eth1/deposit_cache`.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct DepositCacheImportResponse {
+    /// The number of deposits that were not already known to the node.
+    pub deposits_imported: usize,
+}
+
+/// The eth1 backend of the node, as returned by `/lighthouse/eth1/status`.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct Eth1StatusResponse {
+    /// Either "eth1_node", "dummy" or "disabled".
+    pub backend: String,
+    /// `true` if the node is able to produce blocks.
+    pub block_production: bool,
+    /// `true` if produced blocks include deposits from the deposit contract.
+    pub deposits_included: bool,
+}
+
+/// HTTP handler to return the eth1 backend of the node, so that it can be seen whether the node
+/// is following the deposit contract or running without an eth1 node.
+pub fn get_eth1_status<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+) -> ApiResult {
+    let (backend, block_production, deposits_included) = match beacon_chain.eth1_chain.as_ref() {
+        Some(eth1_chain) if eth1_chain.use_dummy_backend => ("dummy", true, false),
+        Some(_) => ("eth1_node", true, true),
+        None => ("disabled", false, false),
+    };
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&Eth1StatusResponse {
+        backend: backend.to_string(),
+        block_production,
+        deposits_included,
+    })
+}
+
+/// HTTP handler to return the SSZ-encoded eth1 deposit cache, so that it may be imported by
+/// another beacon node.
+pub fn get_deposit_cache<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+) -> ApiResult {
+    let eth1_chain = beacon_chain
+        .eth1_chain
+        .as_ref()
+        .ok_or_else(|| ApiError::NotFound("The node does not have an eth1 backend".into()))?;
+
+    let bytes = eth1_chain
+        .deposit_cache_as_ssz_bytes()
+        .map_err(ApiError::NotFound)?;
+
+    ResponseBuilder::new(&req)?.body_ssz_bytes(bytes)
+}
+
+/// A deposit to a validator, as observed in the eth1 deposit contract logs.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct DepositLogResponse {
+    /// The index of the deposit in the deposit contract.
+    pub index: u64,
+    /// The number of the eth1 block which included the deposit.
+    pub block_number: u64,
+    /// The amount deposited, in Gwei.
+    pub amount: u64,
+    /// `false` if the deposit signature is invalid, in which case the deposit cannot create a
+    /// validator.
+    pub signature_is_valid: bool,
+}
+
+/// HTTP handler to return the deposits to the validator with the given `pubkey` that are known to
+/// the eth1 deposit cache of the node.
+pub fn get_deposits_for_pubkey<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+) -> ApiResult {
+    let pubkey = parse_pubkey(&UrlQuery::from_request(&req)?.only_one("pubkey")?)?;
+
+    let eth1_chain = beacon_chain
+        .eth1_chain
+        .as_ref()
+        .ok_or_else(|| ApiError::NotFound("The node does not have an eth1 backend".into()))?;
+
+    let deposits = eth1_chain
+        .deposits_for_pubkey(&PublicKeyBytes::from(pubkey))
+        .map_err(ApiError::NotFound)?
+        .into_iter()
+        .map(|log| DepositLogResponse {
+            index: log.index,
+            block_number: log.block_number,
+            amount: log.deposit_data.amount,
+            signature_is_valid: log.signature_is_valid,
+        })
+        .collect::<Vec<_>>();
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&deposits)
+}
+
+/// HTTP handler to import an SSZ-encoded eth1 deposit cache, as returned by `get_deposit_cache`.
+///
+/// Allows a new beacon node to bootstrap its deposit cache from another node in the same
+/// deployment, rather than downloading all deposit logs from the eth1 node.
+pub fn post_deposit_cache<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+    admin: Arc<Admin>,
+) -> BoxFut {
+    try_future!(admin.authorize(&req));
+    let response_builder = ResponseBuilder::new(&req);
+
+    let future = req
+        .into_body()
+        .concat2()
+        .map_err(|e| ApiError::ServerError(format!("Unable to get request body: {:?}", e)))
+        .and_then(move |chunks| {
+            let eth1_chain = beacon_chain.eth1_chain.as_ref().ok_or_else(|| {
+                ApiError::NotFound("The node does not have an eth1 backend".into())
+            })?;
+
+            eth1_chain
+                .import_deposit_cache(&chunks)
+                .map_err(|e| ApiError::BadRequest(format!("Unable to import deposit cache: {}", e)))
+        })
+        .and_then(|deposits_imported| {
+            response_builder?.body_no_ssz(&DepositCacheImportResponse { deposits_imported })
+        });
+
+    Box::new(future)
+}
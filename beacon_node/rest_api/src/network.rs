@@ -1,11 +1,56 @@
-use crate::error::ApiResult;
+use crate::admin::Admin;
+use crate::error::{ApiError, ApiResult};
+use crate::helpers::check_content_type_for_json;
 use crate::response_builder::ResponseBuilder;
-use crate::NetworkService;
+use crate::{BoxFut, NetworkService};
 use beacon_chain::BeaconChainTypes;
-use eth2_libp2p::{Multiaddr, PeerId};
+use eth2_libp2p::{ConnectionDirection, Multiaddr, PeerId};
+use futures::{Future, Stream};
 use hyper::{Body, Request};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+/// The identity of the local node on the libp2p network.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct NodeIdentity {
+    /// The local libp2p `PeerId`, base58 encoded.
+    pub peer_id: String,
+    /// The local ENR, base64 encoded.
+    pub enr: String,
+    /// The addresses that libp2p is listening on.
+    pub p2p_addresses: Vec<Multiaddr>,
+}
+
+/// A peer that is connected to the local node.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct PeerData {
+    /// The libp2p `PeerId` of the peer, base58 encoded.
+    pub peer_id: String,
+    /// The ENR of the peer, base64 encoded, if it is known.
+    pub enr: Option<String>,
+    /// The address that the connection to the peer was established with.
+    pub address: Multiaddr,
+    /// Indicates which side initiated the connection.
+    pub direction: ConnectionDirection,
+    /// The reputation of the peer. Lower is worse.
+    pub score: f64,
+}
+
+/// The number of peers connected to the local node.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct PeerCount {
+    pub connected: usize,
+    pub inbound: usize,
+    pub outbound: usize,
+}
+
+/// A request to ban a peer.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct BanPeerRequest {
+    /// The libp2p `PeerId` of the peer to ban, base58 encoded.
+    pub peer_id: String,
+}
+
 /// HTTP handler to return the list of libp2p multiaddr the client is listening on.
 ///
 /// Returns a list of `Multiaddr`, serialized according to their `serde` impl.
@@ -69,3 +114,91 @@ pub fn get_peer_list<T: BeaconChainTypes>(
         .collect();
     ResponseBuilder::new(&req)?.body_no_ssz(&connected_peers)
 }
+
+/// HTTP handler to return the identity of the local node on the libp2p network.
+pub fn get_identity<T: BeaconChainTypes>(
+    req: Request<Body>,
+    network: Arc<NetworkService<T>>,
+) -> ApiResult {
+    let identity = NodeIdentity {
+        peer_id: network.local_peer_id().to_base58(),
+        enr: network.local_enr().to_base64(),
+        p2p_addresses: network.listen_multiaddrs(),
+    };
+    ResponseBuilder::new(&req)?.body_no_ssz(&identity)
+}
+
+/// HTTP handler to return detailed information about each peer connected to the client's libp2p
+/// service.
+pub fn get_peers<T: BeaconChainTypes>(
+    req: Request<Body>,
+    network: Arc<NetworkService<T>>,
+) -> ApiResult {
+    let peers: Vec<PeerData> = network
+        .connected_peer_info()
+        .into_iter()
+        .map(|(peer_id, info)| PeerData {
+            peer_id: peer_id.to_base58(),
+            enr: info.enr.map(|enr| enr.to_base64()),
+            address: info.address,
+            direction: info.direction,
+            score: info.score,
+        })
+        .collect();
+    ResponseBuilder::new(&req)?.body_no_ssz(&peers)
+}
+
+/// HTTP handler to return the number of connected peers, split by connection direction.
+pub fn get_peer_counts<T: BeaconChainTypes>(
+    req: Request<Body>,
+    network: Arc<NetworkService<T>>,
+) -> ApiResult {
+    let peers = network.connected_peer_info();
+    let inbound = peers
+        .iter()
+        .filter(|(_, info)| info.direction == ConnectionDirection::Inbound)
+        .count();
+
+    let count = PeerCount {
+        connected: peers.len(),
+        inbound,
+        outbound: peers.len() - inbound,
+    };
+    ResponseBuilder::new(&req)?.body_no_ssz(&count)
+}
+
+/// HTTP handler to disconnect from a peer and refuse any future connections from it.
+///
+/// This is an administrative endpoint, the request must be authorized.
+pub fn post_ban_peer<T: BeaconChainTypes>(
+    req: Request<Body>,
+    network: Arc<NetworkService<T>>,
+    admin: Arc<Admin>,
+) -> BoxFut {
+    try_future!(admin.authorize(&req));
+    try_future!(check_content_type_for_json(&req));
+    let response_builder = ResponseBuilder::new(&req);
+
+    let future = req
+        .into_body()
+        .concat2()
+        .map_err(|e| ApiError::ServerError(format!("Unable to get request body: {:?}", e)))
+        .and_then(|chunks| {
+            serde_json::from_slice::<BanPeerRequest>(&chunks).map_err(|e| {
+                ApiError::BadRequest(format!("Unable to parse JSON into BanPeerRequest: {:?}", e))
+            })
+        })
+        .and_then(|request| {
+            request
+                .peer_id
+                .parse::<PeerId>()
+                .map_err(|e| ApiError::BadRequest(format!("Unable to parse peer id: {:?}", e)))
+        })
+        .and_then(move |peer_id| {
+            network.ban_peer(peer_id);
+            Ok(())
+        })
+        .and_then(|_| response_builder?.body_no_ssz(&()));
+
+    Box::new(future)
+}
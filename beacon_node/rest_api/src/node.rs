@@ -1,9 +1,195 @@
 use crate::response_builder::ResponseBuilder;
-use crate::ApiResult;
+use crate::{ApiError, ApiResult, NetworkService};
+use beacon_chain::{BeaconChain, BeaconChainTypes};
+use client_network::{ChainProgress, RangeSyncType, SyncState};
 use hyper::{Body, Request};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use types::{Hash256, Slot};
 use version;
 
+/// The syncing status of the node.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct SyncingResponse {
+    /// Is the node syncing.
+    pub is_syncing: bool,
+    /// The sync state of the node.
+    pub sync_state: SyncState,
+    /// The current sync status.
+    pub sync_status: SyncStatus,
+    /// The progress of each chain that is being synced from peers.
+    pub chains: Vec<SyncingChainData>,
+}
+
+/// The slots the node is syncing between.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct SyncStatus {
+    /// The slot the node started syncing from.
+    pub starting_slot: Slot,
+    /// The slot of the head of the node.
+    pub current_slot: Slot,
+    /// The highest slot the node is syncing towards.
+    pub highest_slot: Slot,
+}
+
+/// The progress of a chain that is being synced from a pool of peers.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct SyncingChainData {
+    /// Either "finalized" or "head".
+    pub sync_type: String,
+    /// Whether blocks are currently being downloaded for this chain.
+    pub syncing: bool,
+    /// The slot the chain started syncing from.
+    pub start_slot: Slot,
+    /// All blocks prior to this slot have been processed.
+    pub processed_slot: Slot,
+    /// The slot of the block the chain is syncing towards.
+    pub target_slot: Slot,
+    /// The root of the block the chain is syncing towards.
+    pub target_root: Hash256,
+    /// The number of peers the chain is downloaded from.
+    pub peers: usize,
+}
+
+impl From<ChainProgress> for SyncingChainData {
+    fn from(progress: ChainProgress) -> Self {
+        let sync_type = match progress.sync_type {
+            RangeSyncType::Finalized => "finalized",
+            RangeSyncType::Head => "head",
+        };
+
+        SyncingChainData {
+            sync_type: sync_type.to_string(),
+            syncing: progress.syncing,
+            start_slot: progress.start_slot,
+            processed_slot: progress.processed_slot,
+            target_slot: progress.target_head_slot,
+            target_root: progress.target_head_root,
+            peers: progress.peers,
+        }
+    }
+}
+
+/// The progress of the node towards genesis.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum GenesisState {
+    /// The genesis state has not yet been found in the eth1 deposit contract.
+    WaitingForEth1,
+    /// The genesis state is known, but the genesis time has not yet been reached.
+    PreGenesis,
+    /// The genesis time has passed.
+    PostGenesis,
+}
+
+/// The progress of the node towards genesis.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct GenesisStatusResponse {
+    pub state: GenesisState,
+    /// The genesis time in seconds since the UNIX epoch, if it is known.
+    pub genesis_time: Option<u64>,
+    /// The number of seconds until the genesis time, if it is known and has not yet passed.
+    pub seconds_to_genesis: Option<u64>,
+}
+
+impl GenesisStatusResponse {
+    /// The status of a node with a known `genesis_time`, at `now` (both in seconds since the UNIX
+    /// epoch).
+    pub fn new(genesis_time: u64, now: u64) -> Self {
+        if now < genesis_time {
+            Self {
+                state: GenesisState::PreGenesis,
+                genesis_time: Some(genesis_time),
+                seconds_to_genesis: Some(genesis_time - now),
+            }
+        } else {
+            Self {
+                state: GenesisState::PostGenesis,
+                genesis_time: Some(genesis_time),
+                seconds_to_genesis: None,
+            }
+        }
+    }
+
+    /// The status of a node which is waiting for the eth1 deposit contract to reach genesis.
+    pub fn waiting_for_eth1() -> Self {
+        Self {
+            state: GenesisState::WaitingForEth1,
+            genesis_time: None,
+            seconds_to_genesis: None,
+        }
+    }
+}
+
 /// Read the version string from the current Lighthouse build.
 pub fn get_version(req: Request<Body>) -> ApiResult {
     ResponseBuilder::new(&req)?.body_no_ssz(&version::version())
 }
+
+/// HTTP handler to return the syncing status of the node, including the progress of each chain
+/// that is being synced.
+pub fn get_syncing<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+    network: Arc<NetworkService<T>>,
+) -> ApiResult {
+    let current_slot = beacon_chain.best_slot();
+    let sync_state = network.sync_state();
+    let chains = network.sync_progress();
+
+    let starting_slot = chains
+        .iter()
+        .map(|chain| chain.start_slot)
+        .min()
+        .unwrap_or(current_slot);
+    let highest_slot = chains
+        .iter()
+        .map(|chain| chain.target_head_slot)
+        .max()
+        .map_or(current_slot, |slot| std::cmp::max(slot, current_slot));
+
+    let response = SyncingResponse {
+        is_syncing: sync_state.is_syncing(),
+        sync_state,
+        sync_status: SyncStatus {
+            starting_slot,
+            current_slot,
+            highest_slot,
+        },
+        chains: chains.into_iter().map(Into::into).collect(),
+    };
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&response)
+}
+
+/// HTTP handler to return the progress of the node towards genesis.
+pub fn get_genesis_status<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+) -> ApiResult {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| ApiError::ServerError(format!("Unable to read system time: {:?}", e)))?
+        .as_secs();
+    let genesis_time = beacon_chain.head().beacon_state.genesis_time;
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&GenesisStatusResponse::new(genesis_time, now))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn genesis_status() {
+        let pre_genesis = GenesisStatusResponse::new(100, 40);
+        assert_eq!(pre_genesis.state, GenesisState::PreGenesis);
+        assert_eq!(pre_genesis.seconds_to_genesis, Some(60));
+
+        let post_genesis = GenesisStatusResponse::new(100, 100);
+        assert_eq!(post_genesis.state, GenesisState::PostGenesis);
+        assert_eq!(post_genesis.genesis_time, Some(100));
+        assert_eq!(post_genesis.seconds_to_genesis, None);
+    }
+}
@@ -0,0 +1,96 @@
+//! A minimal HTTP server which runs whilst the node waits for the eth1 deposit contract to reach
+//! genesis, before the `BeaconChain` (and therefore the full API) exists.
+//!
+//! It reports the progress towards genesis and responds to every other request with a
+//! `pre_genesis` error. Once the chain has been built, its socket is handed to `start_server`, so
+//! the API remains reachable at the same address throughout.
+
+use crate::error::ApiError;
+use crate::node::{self, GenesisStatusResponse};
+use crate::response_builder::ResponseBuilder;
+use crate::{ApiResult, Config};
+use futures::future;
+use hyper::rt::Future;
+use hyper::server::conn::AddrStream;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server};
+use slog::{info, warn, Logger};
+use std::net::{SocketAddr, TcpListener};
+use tokio::runtime::TaskExecutor;
+
+/// A running pre-genesis server, as started by `start_pre_genesis_server`.
+pub struct PreGenesisServer {
+    exit_signal: exit_future::Signal,
+    listener: TcpListener,
+}
+
+impl PreGenesisServer {
+    /// Returns the address the server is listening on.
+    pub fn local_addr(&self) -> Result<SocketAddr, String> {
+        self.listener
+            .local_addr()
+            .map_err(|e| format!("Unable to read listen address: {:?}", e))
+    }
+
+    /// Stops the server from accepting connections, returning its socket so that it may be passed
+    /// to `start_server`.
+    pub fn shutdown(self) -> TcpListener {
+        self.exit_signal.fire();
+        self.listener
+    }
+}
+
+/// Starts serving the pre-genesis API on the address in `config`.
+pub fn start_pre_genesis_server(
+    config: &Config,
+    executor: &TaskExecutor,
+    log: Logger,
+) -> Result<PreGenesisServer, String> {
+    let listener = TcpListener::bind((config.listen_address, config.port))
+        .map_err(|e| format!("Unable to bind HTTP API: {:?}", e))?;
+    let server_listener = listener
+        .try_clone()
+        .map_err(|e| format!("Unable to clone HTTP API listener: {:?}", e))?;
+
+    let server = Server::from_tcp(server_listener)
+        .map_err(|e| format!("Unable to start HTTP API: {:?}", e))?
+        .serve(make_service_fn(|_socket: &AddrStream| service_fn(route)));
+    let actual_listen_addr = server.local_addr();
+
+    let (exit_signal, exit) = exit_future::signal();
+    let shutdown_log = log.clone();
+    let error_log = log.clone();
+    let server_future = server
+        .with_graceful_shutdown(exit)
+        .map(move |_| info!(shutdown_log, "Pre-genesis API service shutdown"))
+        .map_err(
+            move |e| warn!(error_log, "Pre-genesis API failed"; "error" => format!("{:?}", e)),
+        );
+
+    info!(
+        log,
+        "Pre-genesis REST API started";
+        "address" => format!("{}", actual_listen_addr.ip()),
+        "port" => actual_listen_addr.port(),
+    );
+
+    executor.spawn(server_future);
+
+    Ok(PreGenesisServer {
+        exit_signal,
+        listener,
+    })
+}
+
+fn route(req: Request<Body>) -> impl Future<Item = Response<Body>, Error = hyper::Error> {
+    let result: ApiResult = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/node/version") => node::get_version(req),
+        (&Method::GET, "/node/genesis_status") => ResponseBuilder::new(&req)
+            .and_then(|builder| builder.body_no_ssz(&GenesisStatusResponse::waiting_for_eth1())),
+        _ => Err(ApiError::PreGenesis(
+            "The node is waiting for the eth1 deposit contract to reach genesis".to_string(),
+        )),
+    };
+
+    future::ok(result.unwrap_or_else(Into::into))
+}
@@ -73,6 +73,15 @@ impl ResponseBuilder {
             .map_err(|e| ApiError::ServerError(format!("Failed to build response: {:?}", e)))
     }
 
+    /// Returns `bytes`, which are already SSZ-encoded, regardless of the requested encoding.
+    pub fn body_ssz_bytes(self, bytes: Vec<u8>) -> ApiResult {
+        Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "application/ssz")
+            .body(Body::from(bytes))
+            .map_err(|e| ApiError::ServerError(format!("Failed to build response: {:?}", e)))
+    }
+
     pub fn body_text(self, text: String) -> ApiResult {
         Response::builder()
             .status(StatusCode::OK)
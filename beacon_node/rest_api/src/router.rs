@@ -1,15 +1,43 @@
 use crate::{
-    beacon, error::ApiError, helpers, metrics, network, node, spec, validator, BoxFut,
+    access::ApiAccess, admin, admin::Admin, beacon, error::ApiError, helpers, light_client,
+    light_client::FinalityProofCache, lighthouse, metrics, network, node, spec, validator, BoxFut,
     NetworkChannel,
 };
 use beacon_chain::{BeaconChain, BeaconChainTypes};
 use client_network::Service as NetworkService;
 use eth2_config::Eth2Config;
-use futures::{Future, IntoFuture};
+use futures::{Future, IntoFuture, Poll};
+use hyper::header::{self, HeaderValue};
 use hyper::{Body, Error, Method, Request, Response};
 use slog::debug;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use tracing::info_span;
+use tracing_futures::Instrument;
+
+/// The ID of the next request. Each request is logged with its ID, which is also recorded in its
+/// `tracing` span and returned in the `X-Request-Id` response header.
+///
+/// The ID is the current request ID (see `logging::RequestId`) whilst the request is served, so
+/// that the beacon chain and database logs made for the request include it.
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Sets the current request ID of the thread whilst `inner` is polled.
+struct WithRequestId<F> {
+    request_id: u64,
+    inner: F,
+}
+
+impl<F: Future> Future for WithRequestId<F> {
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        let _request_id_guard = logging::enter_request(self.request_id);
+        self.inner.poll()
+    }
+}
 
 fn into_boxfut<F: IntoFuture + 'static>(item: F) -> BoxFut
 where
@@ -27,19 +55,44 @@ pub fn route<T: BeaconChainTypes>(
     eth2_config: Arc<Eth2Config>,
     local_log: slog::Logger,
     db_path: PathBuf,
+    admin: Arc<Admin>,
+    access: Arc<ApiAccess>,
+    finality_proof_cache: Arc<FinalityProofCache>,
 ) -> impl Future<Item = Response<Body>, Error = Error> {
     metrics::inc_counter(&metrics::REQUEST_COUNT);
     let timer = metrics::start_timer(&metrics::REQUEST_RESPONSE_TIME);
 
     let path = req.uri().path().to_string();
+    let origin = req.headers().get(header::ORIGIN).cloned();
+    let request_id = NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+    let request_span = info_span!(
+        "http_request",
+        request_id,
+        method = %req.method(),
+        path = %path
+    );
+    // Handlers which do not read the request body do all their work before returning, within
+    // the span.
+    let request_guard = request_span.enter();
+    let request_id_guard = logging::enter_request(request_id);
 
     let log = local_log.clone();
-    let request_result: Box<dyn Future<Item = Response<_>, Error = _> + Send> =
+    let request_result: Box<dyn Future<Item = Response<_>, Error = _> + Send> = if let Err(e) =
+        access.authorize(&req)
+    {
+        Box::new(futures::future::err(e))
+    } else {
         match (req.method(), path.as_ref()) {
+            // Answers CORS preflight requests for every path.
+            (&Method::OPTIONS, _) => into_boxfut(access.preflight_response()),
+
             // Methods for Client
             (&Method::GET, "/node/version") => into_boxfut(node::get_version(req)),
             (&Method::GET, "/node/syncing") => {
-                into_boxfut(helpers::implementation_pending_response(req))
+                into_boxfut(node::get_syncing::<T>(req, beacon_chain, network_service))
+            }
+            (&Method::GET, "/node/genesis_status") => {
+                into_boxfut(node::get_genesis_status::<T>(req, beacon_chain))
             }
 
             // Methods for Network
@@ -62,6 +115,76 @@ pub fn route<T: BeaconChainTypes>(
                 into_boxfut(network::get_listen_addresses::<T>(req, network_service))
             }
 
+            (&Method::GET, "/eth/v1/node/identity") => {
+                into_boxfut(network::get_identity::<T>(req, network_service))
+            }
+            (&Method::GET, "/eth/v1/node/peers") => {
+                into_boxfut(network::get_peers::<T>(req, network_service))
+            }
+            (&Method::GET, "/eth/v1/node/peer_count") => {
+                into_boxfut(network::get_peer_counts::<T>(req, network_service))
+            }
+
+            // Lighthouse-specific administrative methods
+            (&Method::POST, "/lighthouse/peers/ban") => {
+                network::post_ban_peer::<T>(req, network_service, admin)
+            }
+            (&Method::GET, "/lighthouse/fork_choice") => {
+                into_boxfut(lighthouse::get_fork_choice::<T>(req, beacon_chain))
+            }
+            (&Method::GET, "/lighthouse/validator_inclusion") => {
+                into_boxfut(lighthouse::get_validator_inclusion::<T>(req, beacon_chain))
+            }
+            (&Method::GET, "/lighthouse/validator_inclusion/validators") => into_boxfut(
+                lighthouse::get_validator_inclusion_validators::<T>(req, beacon_chain),
+            ),
+            (&Method::POST, "/lighthouse/validator_inclusion/validators") => {
+                lighthouse::post_validator_inclusion_validators::<T>(req, beacon_chain)
+            }
+            (&Method::GET, "/lighthouse/block_rewards") => {
+                into_boxfut(lighthouse::get_block_rewards::<T>(req, beacon_chain))
+            }
+            (&Method::GET, "/lighthouse/chain_quality") => {
+                into_boxfut(lighthouse::get_chain_quality::<T>(req, beacon_chain))
+            }
+            (&Method::GET, path)
+                if path.starts_with("/lighthouse/debug/states/") && path.ends_with("/root") =>
+            {
+                into_boxfut(lighthouse::get_debug_state_root::<T>(
+                    req,
+                    beacon_chain,
+                    path,
+                ))
+            }
+            (&Method::GET, path) if path.starts_with("/lighthouse/debug/states/") => into_boxfut(
+                lighthouse::get_debug_state_ssz::<T>(req, beacon_chain, path),
+            ),
+            (&Method::GET, "/lighthouse/proofs/finality") => into_boxfut(
+                light_client::get_finality_proof::<T>(req, beacon_chain, finality_proof_cache),
+            ),
+            (&Method::GET, "/lighthouse/eth1/status") => {
+                into_boxfut(lighthouse::get_eth1_status::<T>(req, beacon_chain))
+            }
+            (&Method::GET, "/lighthouse/eth1/deposit_cache") => {
+                into_boxfut(lighthouse::get_deposit_cache::<T>(req, beacon_chain))
+            }
+            (&Method::GET, "/lighthouse/eth1/deposits") => {
+                into_boxfut(lighthouse::get_deposits_for_pubkey::<T>(req, beacon_chain))
+            }
+            (&Method::POST, "/lighthouse/eth1/deposit_cache") => {
+                lighthouse::post_deposit_cache::<T>(req, beacon_chain, admin)
+            }
+            (&Method::POST, "/lighthouse/database/prune") => {
+                into_boxfut(admin::post_database_prune::<T>(req, beacon_chain, admin))
+            }
+            (&Method::POST, "/lighthouse/caches/clear") => {
+                into_boxfut(admin::post_caches_clear::<T>(req, beacon_chain, admin))
+            }
+            (&Method::POST, "/lighthouse/compaction") => {
+                into_boxfut(admin::post_compaction::<T>(req, beacon_chain, admin))
+            }
+            (&Method::GET, "/lighthouse/tasks") => into_boxfut(admin::get_task(req, admin)),
+
             // Methods for Beacon Node
             (&Method::GET, "/beacon/head") => into_boxfut(beacon::get_head::<T>(req, beacon_chain)),
             (&Method::GET, "/beacon/block") => {
@@ -101,9 +224,18 @@ pub fn route<T: BeaconChainTypes>(
             (&Method::POST, "/validator/duties") => {
                 validator::post_validator_duties::<T>(req, beacon_chain)
             }
-            (&Method::GET, "/validator/block") => {
-                into_boxfut(validator::get_new_beacon_block::<T>(req, beacon_chain))
+            (&Method::POST, path) if path.starts_with("/eth/v1/validator/duties/attester/") => {
+                validator::post_attester_duties::<T>(req, beacon_chain, path)
+            }
+            (&Method::GET, path) if path.starts_with("/eth/v1/validator/duties/proposer/") => {
+                into_boxfut(validator::get_proposer_duties::<T>(req, beacon_chain, path))
+            }
+            (&Method::POST, "/validator/slashings") => {
+                validator::post_validator_slashings::<T>(req, beacon_chain)
             }
+            (&Method::GET, "/validator/block") => into_boxfut(
+                validator::get_new_beacon_block::<T>(req, beacon_chain, network_service),
+            ),
             (&Method::POST, "/validator/block") => {
                 validator::publish_beacon_block::<T>(req, beacon_chain, network_channel, log)
             }
@@ -113,7 +245,30 @@ pub fn route<T: BeaconChainTypes>(
             (&Method::POST, "/validator/attestation") => {
                 validator::publish_attestation::<T>(req, beacon_chain, network_channel, log)
             }
+            (&Method::GET, "/validator/aggregate_attestation") => {
+                into_boxfut(validator::get_aggregate_attestation::<T>(req, beacon_chain))
+            }
+            (&Method::POST, "/validator/aggregate_and_proofs") => {
+                validator::publish_aggregate_and_proofs::<T>(
+                    req,
+                    beacon_chain,
+                    network_channel,
+                    log,
+                )
+            }
+            (&Method::POST, "/validator/beacon_committee_subscriptions") => {
+                validator::post_beacon_committee_subscriptions::<T>(req, network_channel)
+            }
 
+            (&Method::POST, "/beacon/voluntary_exit") => {
+                beacon::post_voluntary_exit::<T>(req, beacon_chain, network_channel)
+            }
+            (&Method::POST, "/beacon/proposer_slashing") => {
+                beacon::post_proposer_slashing::<T>(req, beacon_chain, network_channel)
+            }
+            (&Method::POST, "/beacon/attester_slashing") => {
+                beacon::post_attester_slashing::<T>(req, beacon_chain, network_channel)
+            }
             (&Method::GET, "/beacon/state") => {
                 into_boxfut(beacon::get_state::<T>(req, beacon_chain))
             }
@@ -126,7 +281,9 @@ pub fn route<T: BeaconChainTypes>(
             (&Method::GET, "/beacon/state/genesis") => {
                 into_boxfut(beacon::get_genesis_state::<T>(req, beacon_chain))
             }
-            //TODO: Add aggreggate/filtered state lookups here, e.g. /beacon/validators/balances
+            (&Method::GET, path) if path.starts_with("/eth/v1/beacon/states/") => {
+                into_boxfut(beacon::route_state_request::<T>(req, beacon_chain, path))
+            }
 
             // Methods for bootstrap and checking configuration
             (&Method::GET, "/spec") => into_boxfut(spec::get_spec::<T>(req, beacon_chain)),
@@ -147,26 +304,46 @@ pub fn route<T: BeaconChainTypes>(
             _ => Box::new(futures::future::err(ApiError::NotFound(
                 "Request path and/or method not found.".to_owned(),
             ))),
-        };
+        }
+    };
 
     // Map the Rust-friendly `Result` in to a http-friendly response. In effect, this ensures that
     // any `Err` returned from our response handlers becomes a valid http response to the client
     // (e.g., a response with a 404 or 500 status).
-    request_result.then(move |result| match result {
-        Ok(response) => {
-            debug!(local_log, "Request successful: {:?}", path);
-            metrics::inc_counter(&metrics::SUCCESS_COUNT);
-            metrics::stop_timer(timer);
+    drop(request_id_guard);
+    drop(request_guard);
+    WithRequestId {
+        request_id,
+        inner: request_result,
+    }
+    .instrument(request_span)
+    .then(move |result| {
+        let mut response = match result {
+            Ok(response) => {
+                debug!(local_log, "Request successful"; "path" => &path, "request_id" => request_id);
+                metrics::inc_counter(&metrics::SUCCESS_COUNT);
+                metrics::stop_timer(timer);
 
-            Ok(response)
-        }
-        Err(e) => {
-            let error_response = e.into();
+                response
+            }
+            Err(e) => {
+                debug!(
+                    local_log,
+                    "Request failure";
+                    "path" => &path,
+                    "request_id" => request_id,
+                    "error" => format!("{:?}", e)
+                );
+                metrics::stop_timer(timer);
 
-            debug!(local_log, "Request failure: {:?}", path);
-            metrics::stop_timer(timer);
+                e.into()
+            }
+        };
+        response
+            .headers_mut()
+            .insert("X-Request-Id", HeaderValue::from(request_id));
+        access.add_cors_headers(origin.as_ref(), &mut response);
 
-            Ok(error_response)
-        }
+        Ok(response)
     })
 }
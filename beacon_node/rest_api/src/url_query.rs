@@ -1,7 +1,9 @@
-use crate::helpers::{parse_committee_index, parse_epoch, parse_signature, parse_slot};
+use crate::helpers::{
+    parse_committee_index, parse_epoch, parse_hex_ssz_bytes, parse_signature, parse_slot,
+};
 use crate::ApiError;
 use hyper::Request;
-use types::{CommitteeIndex, Epoch, Signature, Slot};
+use types::{AttestationData, CommitteeIndex, Epoch, Signature, Slot};
 
 /// Provides handy functions for parsing the query parameters of a URL.
 
@@ -103,6 +105,14 @@ impl<'a> UrlQuery<'a> {
         self.first_of(&["randao_reveal"])
             .and_then(|(_key, value)| parse_signature(&value))
     }
+
+    /// Returns the value of the first occurrence of the `attestation_data` key.
+    ///
+    /// The value is expected to be the `0x` prefixed hex encoding of the SSZ bytes.
+    pub fn attestation_data(self) -> Result<AttestationData, ApiError> {
+        self.first_of(&["attestation_data"])
+            .and_then(|(_key, value)| parse_hex_ssz_bytes(&value))
+    }
 }
 
 #[cfg(test)]
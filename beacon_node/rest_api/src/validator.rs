@@ -1,22 +1,31 @@
 use crate::helpers::{
-    check_content_type_for_json, parse_pubkey, publish_attestation_to_network,
+    check_content_type_for_json, check_content_type_for_json_or_ssz, parse_pubkey,
+    publish_aggregate_and_proof_to_network, publish_attestation_to_network,
     publish_beacon_block_to_network,
 };
 use crate::response_builder::ResponseBuilder;
-use crate::{ApiError, ApiResult, BoxFut, NetworkChannel, UrlQuery};
+use crate::{
+    ApiEncodingFormat, ApiError, ApiResult, BoxFut, NetworkChannel, NetworkService, UrlQuery,
+};
 use beacon_chain::{
     AttestationProcessingOutcome, BeaconChain, BeaconChainTypes, BlockProcessingOutcome,
+    ProducerStatus,
 };
 use bls::PublicKey;
 use futures::future::Future;
 use futures::stream::Stream;
 use hyper::{Body, Request};
+use network::NetworkMessage;
 use serde::{Deserialize, Serialize};
 use slog::{info, warn, Logger};
+use ssz::Decode;
 use ssz_derive::{Decode, Encode};
 use std::sync::Arc;
 use types::beacon_state::EthSpec;
-use types::{Attestation, BeaconBlock, CommitteeIndex, Epoch, RelativeEpoch, Slot};
+use types::{
+    AggregateAndProof, Attestation, BeaconBlock, CommitteeIndex, Epoch, Hash256, RelativeEpoch,
+    Slot, ValidatorSubscription,
+};
 
 #[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
 pub struct ValidatorDuty {
@@ -38,6 +47,186 @@ pub struct BulkValidatorDutiesRequest {
     pub pubkeys: Vec<PublicKey>,
 }
 
+/// Duties for some epoch, along with the root of the block upon which they depend.
+///
+/// If the dependent root later differs from the value returned by the beacon node (e.g., due to a
+/// re-org), the duties are stale and must be fetched again.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct DutiesResponse<T> {
+    pub dependent_root: Hash256,
+    pub data: Vec<T>,
+}
+
+/// The attestation duty of a validator, as returned by
+/// `POST /eth/v1/validator/duties/attester/{epoch}`.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct AttesterDutyData {
+    pub pubkey: PublicKey,
+    pub validator_index: u64,
+    /// The index of the committee within `slot` of which the validator is a member.
+    pub committee_index: CommitteeIndex,
+    pub committee_length: u64,
+    /// The number of committees in `slot`.
+    pub committees_at_slot: u64,
+    /// The position of the validator in the committee.
+    pub validator_committee_index: u64,
+    /// The slot at which the validator must attest.
+    pub slot: Slot,
+}
+
+/// Whether a validator has been slashed, as returned by `POST /validator/slashings`.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct ValidatorSlashingStatus {
+    pub pubkey: PublicKey,
+    /// `None` if the validator is not in the registry of the head state.
+    pub validator_index: Option<u64>,
+    /// True if the validator is slashed in the head state.
+    pub slashed: bool,
+    /// True if a slashing of the validator has been received (e.g., via gossip) but is not yet
+    /// included in the canonical chain.
+    pub slashing_pending: bool,
+}
+
+/// The block proposal duty of a validator, as returned by
+/// `GET /eth/v1/validator/duties/proposer/{epoch}`.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct ProposerDutyData {
+    pub pubkey: PublicKey,
+    pub validator_index: u64,
+    /// The slot at which the validator must propose a block.
+    pub slot: Slot,
+}
+
+/// Parses the epoch from the final segment of a `/eth/v1/validator/duties/*/{epoch}` path.
+fn duties_epoch_from_path(path: &str, prefix: &str) -> Result<Epoch, ApiError> {
+    path.trim_start_matches(prefix)
+        .parse::<u64>()
+        .map(Epoch::new)
+        .map_err(|e| ApiError::BadRequest(format!("Unable to parse epoch: {:?}", e)))
+}
+
+/// Returns an error if `epoch` is more than `lookahead` epochs later than the current epoch.
+fn check_duties_epoch<T: BeaconChainTypes>(
+    beacon_chain: &BeaconChain<T>,
+    epoch: Epoch,
+    lookahead: u64,
+) -> Result<(), ApiError> {
+    let current_epoch = beacon_chain
+        .epoch()
+        .map_err(|e| ApiError::ServerError(format!("Unable to read current epoch: {:?}", e)))?;
+
+    if epoch > current_epoch + lookahead {
+        Err(ApiError::BadRequest(format!(
+            "Duties for epoch {} are unknown, the current epoch is {}",
+            epoch, current_epoch
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// HTTP handler to return the attestation duties of the validators with the indices in the
+/// request body (a JSON array) during `epoch`, which may be at most one epoch in the future.
+///
+/// Validators which are unknown or have no duties in `epoch` are omitted. The duties are read from
+/// the shuffling cache where possible, rather than loading a state for each request.
+pub fn post_attester_duties<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+    path: &str,
+) -> BoxFut {
+    let response_builder = ResponseBuilder::new(&req);
+    let epoch = try_future!(duties_epoch_from_path(
+        path,
+        "/eth/v1/validator/duties/attester/"
+    ));
+    try_future!(check_duties_epoch(&beacon_chain, epoch, 1));
+
+    let future = req
+        .into_body()
+        .concat2()
+        .map_err(|e| ApiError::ServerError(format!("Unable to get request body: {:?}", e)))
+        .and_then(|chunks| {
+            serde_json::from_slice::<Vec<u64>>(&chunks).map_err(|e| {
+                ApiError::BadRequest(format!(
+                    "Unable to parse JSON into validator indices: {:?}",
+                    e
+                ))
+            })
+        })
+        .and_then(move |validator_indices| {
+            let (committee_cache, dependent_root) = beacon_chain.attester_shuffling(epoch)?;
+
+            let data = validator_indices
+                .into_iter()
+                .filter_map(|validator_index| {
+                    let duty = committee_cache.get_attestation_duties(validator_index as usize)?;
+                    let pubkey = beacon_chain.validator_pubkey(validator_index as usize)?;
+
+                    Some(AttesterDutyData {
+                        pubkey,
+                        validator_index,
+                        committee_index: duty.index,
+                        committee_length: duty.committee_len as u64,
+                        committees_at_slot: committee_cache.committees_per_slot(),
+                        validator_committee_index: duty.committee_position as u64,
+                        slot: duty.slot,
+                    })
+                })
+                .collect();
+
+            Ok(DutiesResponse {
+                dependent_root,
+                data,
+            })
+        })
+        .and_then(|duties| response_builder?.body_no_ssz(&duties));
+
+    Box::new(future)
+}
+
+/// HTTP handler to return the block proposer of each slot of `epoch`, which must not be later
+/// than the current epoch.
+///
+/// The proposers are read from the shuffling cache where possible, rather than loading a state for
+/// each request.
+pub fn get_proposer_duties<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+    path: &str,
+) -> ApiResult {
+    let epoch = duties_epoch_from_path(path, "/eth/v1/validator/duties/proposer/")?;
+    check_duties_epoch(&beacon_chain, epoch, 0)?;
+
+    let (proposers, dependent_root) = beacon_chain.block_proposers(epoch)?;
+
+    let data = epoch
+        .slot_iter(T::EthSpec::slots_per_epoch())
+        .zip(proposers.into_iter())
+        .map(|(slot, validator_index)| {
+            let pubkey = beacon_chain
+                .validator_pubkey(validator_index)
+                .ok_or_else(|| {
+                    ApiError::ServerError(format!(
+                        "Unknown pubkey for proposer {}",
+                        validator_index
+                    ))
+                })?;
+
+            Ok(ProposerDutyData {
+                pubkey,
+                validator_index: validator_index as u64,
+                slot,
+            })
+        })
+        .collect::<Result<Vec<_>, ApiError>>()?;
+
+    ResponseBuilder::new(&req)?.body_no_ssz(&DutiesResponse {
+        dependent_root,
+        data,
+    })
+}
+
 /// HTTP Handler to retrieve a the duties for a set of validators during a particular epoch. This
 /// method allows for collecting bulk sets of validator duties without risking exceeding the max
 /// URL length with query pairs.
@@ -67,6 +256,53 @@ pub fn post_validator_duties<T: BeaconChainTypes>(
     Box::new(future)
 }
 
+/// HTTP handler to return whether each of the validators in the JSON list of pubkeys in the request
+/// body has been slashed, either in the head state or by a slashing in the op pool.
+///
+/// Allows a validator client to stop signing for a key which has been slashed, e.g., because it is
+/// in use by another validator client.
+pub fn post_validator_slashings<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+) -> BoxFut {
+    let response_builder = ResponseBuilder::new(&req);
+
+    let future = req
+        .into_body()
+        .concat2()
+        .map_err(|e| ApiError::ServerError(format!("Unable to get request body: {:?}", e)))
+        .and_then(|chunks| {
+            serde_json::from_slice::<Vec<PublicKey>>(&chunks).map_err(|e| {
+                ApiError::BadRequest(format!("Unable to parse JSON into pubkeys: {:?}", e))
+            })
+        })
+        .and_then(move |pubkeys| {
+            let head = beacon_chain.head();
+
+            let statuses = pubkeys
+                .into_iter()
+                .map(|pubkey| {
+                    let validator_index = beacon_chain.validator_index(&pubkey);
+
+                    ValidatorSlashingStatus {
+                        validator_index: validator_index.map(|i| i as u64),
+                        slashed: validator_index
+                            .and_then(|i| head.beacon_state.validators.get(i))
+                            .map_or(false, |validator| validator.slashed),
+                        slashing_pending: validator_index.map_or(false, |i| {
+                            beacon_chain.op_pool.has_pending_slashing(i as u64)
+                        }),
+                        pubkey,
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            response_builder?.body_no_ssz(&statuses)
+        });
+
+    Box::new(future)
+}
+
 /// HTTP Handler to retrieve a the duties for a set of validators during a particular epoch
 ///
 /// The given `epoch` must be within one epoch of the current epoch.
@@ -93,11 +329,7 @@ fn return_validator_duties<T: BeaconChainTypes>(
     epoch: Epoch,
     validator_pubkeys: Vec<PublicKey>,
 ) -> Result<Vec<ValidatorDuty>, ApiError> {
-    let mut state = beacon_chain
-        .state_at_slot(epoch.start_slot(T::EthSpec::slots_per_epoch()))
-        .map_err(|e| {
-            ApiError::ServerError(format!("Unable to load state for epoch {}: {:?}", epoch, e))
-        })?;
+    let mut state = beacon_chain.state_at_slot(epoch.start_slot(T::EthSpec::slots_per_epoch()))?;
 
     let current_epoch = state.current_epoch();
     let relative_epoch = RelativeEpoch::from_epoch(current_epoch, epoch).map_err(|_| {
@@ -174,10 +406,22 @@ fn return_validator_duties<T: BeaconChainTypes>(
 }
 
 /// HTTP Handler to produce a new BeaconBlock from the current state, ready to be signed by a validator.
+///
+/// Returns an error whilst the node is range syncing.
 pub fn get_new_beacon_block<T: BeaconChainTypes>(
     req: Request<Body>,
     beacon_chain: Arc<BeaconChain<T>>,
+    network: Arc<NetworkService<T>>,
 ) -> ApiResult {
+    // a block built on a head that is far behind our peers would most likely be orphaned
+    let sync_state = network.sync_state();
+    if sync_state.is_syncing() {
+        return Err(ApiError::Syncing(format!(
+            "Beacon node is syncing and will not produce a block: {:?}",
+            sync_state
+        )));
+    }
+
     let query = UrlQuery::from_request(&req)?;
 
     let slot = query.slot()?;
@@ -202,18 +446,39 @@ pub fn publish_beacon_block<T: BeaconChainTypes>(
     network_chan: NetworkChannel,
     log: Logger,
 ) -> BoxFut {
-    try_future!(check_content_type_for_json(&req));
+    let encoding = try_future!(check_content_type_for_json_or_ssz(&req));
     let response_builder = ResponseBuilder::new(&req);
 
     let body = req.into_body();
     Box::new(body
         .concat2()
         .map_err(|e| ApiError::ServerError(format!("Unable to get request body: {:?}",e)))
-        .and_then(|chunks| {
-            serde_json::from_slice(&chunks).map_err(|e| ApiError::BadRequest(format!("Unable to parse JSON into BeaconBlock: {:?}",e)))
+        .and_then(move |chunks| match encoding {
+            ApiEncodingFormat::SSZ => BeaconBlock::from_ssz_bytes(&chunks).map_err(|e| ApiError::BadRequest(format!("Unable to parse SSZ into BeaconBlock: {:?}",e))),
+            _ => serde_json::from_slice(&chunks).map_err(|e| ApiError::BadRequest(format!("Unable to parse JSON into BeaconBlock: {:?}",e))),
         })
         .and_then(move |block: BeaconBlock<T::EthSpec>| {
             let slot = block.slot;
+
+            // Refuse to publish a second block from the same proposer at the same slot, since
+            // doing so would get the proposer slashed.
+            match beacon_chain.block_producer_status(&block) {
+                Ok(ProducerStatus::Equivocation { existing_block_root }) => {
+                    warn!(log, "Refusing to publish equivocating block from API"; "block_slot" => slot, "existing_block_root" => format!("{}", existing_block_root));
+                    return Err(ApiError::ProposerEquivocation(format!(
+                        "A different block from the same proposer has already been imported at slot {}: {}",
+                        slot, existing_block_root
+                    )));
+                }
+                Ok(_) => (),
+                Err(e) => {
+                    return Err(ApiError::ServerError(format!(
+                        "Unable to check block for equivocation: {:?}",
+                        e
+                    )))
+                }
+            }
+
             match beacon_chain.process_block(block.clone()) {
                 Ok(BlockProcessingOutcome::Processed { block_root }) => {
                     // Block was processed, publish via gossipsub
@@ -221,10 +486,10 @@ pub fn publish_beacon_block<T: BeaconChainTypes>(
                     publish_beacon_block_to_network::<T>(network_chan, block)
                 }
                 Ok(outcome) => {
-                    warn!(log, "BeaconBlock could not be processed, but is being sent to the network anyway."; "outcome" => format!("{:?}", outcome));
+                    warn!(log, "BeaconBlock could not be processed, but is being sent to the network anyway."; "outcome" => outcome.to_string());
                     publish_beacon_block_to_network::<T>(network_chan, block)?;
                     Err(ApiError::ProcessingError(format!(
-                        "The BeaconBlock could not be processed, but has still been published: {:?}",
+                        "The BeaconBlock could not be processed, but has still been published: {}",
                         outcome
                     )))
                 }
@@ -306,3 +571,130 @@ pub fn publish_attestation<T: BeaconChainTypes>(
         response_builder?.body_no_ssz(&())
     }))
 }
+
+/// HTTP Handler to retrieve the best aggregate attestation known to the beacon node for the given
+/// `AttestationData`, ready to be signed and published by an aggregator.
+pub fn get_aggregate_attestation<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+) -> ApiResult {
+    let query = UrlQuery::from_request(&req)?;
+
+    let attestation_data = query.attestation_data()?;
+
+    match beacon_chain.get_aggregated_attestation(&attestation_data) {
+        Ok(Some(attestation)) => ResponseBuilder::new(&req)?.body(&attestation),
+        Ok(None) => Err(ApiError::NotFound(
+            "No matching aggregate attestation is known".to_string(),
+        )),
+        Err(e) => Err(ApiError::ServerError(format!(
+            "Unable to obtain aggregate attestation: {:?}",
+            e
+        ))),
+    }
+}
+
+/// HTTP Handler to publish a list of `AggregateAndProof`s, which have been signed by aggregators.
+///
+/// Each aggregate is imported into the local aggregation pool before being published on the
+/// aggregate gossip topic.
+pub fn publish_aggregate_and_proofs<T: BeaconChainTypes>(
+    req: Request<Body>,
+    beacon_chain: Arc<BeaconChain<T>>,
+    network_chan: NetworkChannel,
+    log: Logger,
+) -> BoxFut {
+    try_future!(check_content_type_for_json(&req));
+    let response_builder = ResponseBuilder::new(&req);
+
+    Box::new(req
+        .into_body()
+        .concat2()
+        .map_err(|e| ApiError::ServerError(format!("Unable to get request body: {:?}",e)))
+        .and_then(|chunks| {
+            serde_json::from_slice(&chunks).map_err(|e| {
+                ApiError::BadRequest(format!(
+                    "Unable to deserialize JSON into a list of AggregateAndProof: {:?}",
+                    e
+                ))
+            })
+        })
+        .and_then(move |aggregates: Vec<AggregateAndProof<T::EthSpec>>| {
+            let mut invalid = vec![];
+
+            for aggregate_and_proof in aggregates {
+                match beacon_chain.process_attestation(aggregate_and_proof.aggregate.clone()) {
+                    Ok(AttestationProcessingOutcome::Processed) => {
+                        info!(
+                            log,
+                            "Processed valid aggregate from API, transmitting to network.";
+                            "aggregator_index" => aggregate_and_proof.aggregator_index
+                        );
+                    }
+                    Ok(outcome) => {
+                        warn!(log, "Aggregate could not be processed, but is being sent to the network anyway."; "outcome" => format!("{:?}", outcome));
+                        invalid.push(outcome);
+                    }
+                    Err(e) => {
+                        return Err(ApiError::ServerError(format!(
+                            "Error while processing aggregate: {:?}",
+                            e
+                        )))
+                    }
+                }
+
+                publish_aggregate_and_proof_to_network::<T>(
+                    network_chan.clone(),
+                    aggregate_and_proof,
+                )?;
+            }
+
+            if invalid.is_empty() {
+                Ok(())
+            } else {
+                Err(ApiError::ProcessingError(format!(
+                    "Some aggregates could not be processed, but have still been published: {:?}",
+                    invalid
+                )))
+            }
+        }).and_then(|_| {
+        response_builder?.body_no_ssz(&())
+    }))
+}
+
+/// HTTP Handler to subscribe the beacon node to the attestation subnets required by a list of
+/// validator duties.
+pub fn post_beacon_committee_subscriptions<T: BeaconChainTypes>(
+    req: Request<Body>,
+    network_chan: NetworkChannel,
+) -> BoxFut {
+    try_future!(check_content_type_for_json(&req));
+    let response_builder = ResponseBuilder::new(&req);
+
+    let future = req
+        .into_body()
+        .concat2()
+        .map_err(|e| ApiError::ServerError(format!("Unable to get request body: {:?}", e)))
+        .and_then(|chunks| {
+            serde_json::from_slice::<Vec<ValidatorSubscription>>(&chunks).map_err(|e| {
+                ApiError::BadRequest(format!(
+                    "Unable to parse JSON into a list of ValidatorSubscription: {:?}",
+                    e
+                ))
+            })
+        })
+        .and_then(move |subscriptions| {
+            network_chan
+                .write()
+                .try_send(NetworkMessage::Subscribe { subscriptions })
+                .map_err(|e| {
+                    ApiError::ServerError(format!(
+                        "Unable to send subscriptions to the network: {:?}",
+                        e
+                    ))
+                })
+        })
+        .and_then(|_| response_builder?.body_no_ssz(&()));
+
+    Box::new(future)
+}
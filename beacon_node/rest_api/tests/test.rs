@@ -1,16 +1,26 @@
 #![cfg(test)]
 
 use beacon_chain::{BeaconChain, BeaconChainTypes};
+use merkle_proof::verify_merkle_proof;
 use node_test_rig::{
     environment::{Environment, EnvironmentBuilder},
     testing_client_config, ClientGenesis, LocalBeaconNode,
 };
-use remote_beacon_node::{PublishStatus, ValidatorDuty};
+use remote_beacon_node::{
+    ErrorCode, ExpectedRoot, PublishStatus, RemoteBeaconNode, SyncState, TaskStatus, ValidatorDuty,
+    ValidatorStatus,
+};
 use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use store::Store;
 use tree_hash::TreeHash;
 use types::{
-    test_utils::generate_deterministic_keypair, BeaconBlock, ChainSpec, Domain, Epoch, EthSpec,
-    MinimalEthSpec, PublicKey, RelativeEpoch, Signature, Slot,
+    test_utils::{
+        generate_deterministic_keypair, ProposerSlashingTestTask, TestingProposerSlashingBuilder,
+    },
+    BeaconBlock, ChainSpec, Domain, Epoch, EthSpec, Hash256, MinimalEthSpec, PublicKey,
+    RelativeEpoch, Signature, Slot, ValidatorSubscription,
 };
 use version;
 
@@ -152,6 +162,142 @@ fn validator_produce_attestation() {
         publish_status.is_valid(),
         "the signed published attestation should be valid"
     );
+
+    // Ensure the published attestation is available as an aggregate.
+    let aggregate = env
+        .runtime()
+        .block_on(
+            remote_node
+                .http
+                .validator()
+                .produce_aggregate_attestation(&attestation.data),
+        )
+        .expect("should fetch aggregate attestation from http api");
+    assert_eq!(
+        aggregate, attestation,
+        "the aggregate should be the published attestation"
+    );
+}
+
+#[test]
+fn validator_beacon_committee_subscriptions() {
+    let mut env = build_env();
+
+    let node = LocalBeaconNode::production(env.core_context(), testing_client_config());
+    let remote_node = node.remote_node().expect("should produce remote node");
+
+    let beacon_chain = node
+        .client
+        .beacon_chain()
+        .expect("client should have beacon chain");
+    let state = beacon_chain.head().beacon_state.clone();
+
+    let subscriptions = (0..4)
+        .map(|validator_index| {
+            let duties = state
+                .get_attestation_duties(validator_index, RelativeEpoch::Current)
+                .expect("should have attestation duties cache")
+                .expect("should have attestation duties");
+
+            ValidatorSubscription {
+                validator_index: validator_index as u64,
+                attestation_committee_index: duties.index,
+                slot: duties.slot,
+                is_aggregator: false,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    env.runtime()
+        .block_on(remote_node.http.validator().subscribe(subscriptions))
+        .expect("should subscribe via http api");
+}
+
+#[test]
+fn beacon_proposer_slashing() {
+    let mut env = build_env();
+
+    let spec = &E::default_spec();
+
+    let node = LocalBeaconNode::production(env.core_context(), testing_client_config());
+    let remote_node = node.remote_node().expect("should produce remote node");
+
+    let beacon_chain = node
+        .client
+        .beacon_chain()
+        .expect("client should have beacon chain");
+    let fork = beacon_chain.head().beacon_state.fork.clone();
+
+    let proposer_index = 1;
+    let keypair = generate_deterministic_keypair(proposer_index as usize);
+    let signer = |_validator_index: u64, message: &[u8], epoch: Epoch, domain: Domain| {
+        let domain = spec.get_domain(epoch, domain, &fork);
+        Signature::new(message, domain, &keypair.sk)
+    };
+    let slashing = TestingProposerSlashingBuilder::double_vote::<E, _>(
+        ProposerSlashingTestTask::Valid,
+        proposer_index,
+        signer,
+    );
+
+    env.runtime()
+        .block_on(
+            remote_node
+                .http
+                .beacon()
+                .publish_proposer_slashing(slashing.clone()),
+        )
+        .expect("should publish proposer slashing via http api");
+
+    assert!(
+        beacon_chain
+            .op_pool
+            .contains_proposer_slashing(proposer_index),
+        "op pool should contain the slashing"
+    );
+
+    let statuses = env
+        .runtime()
+        .block_on(
+            remote_node
+                .http
+                .validator()
+                .get_slashings(&[keypair.pk.clone(), generate_deterministic_keypair(2).pk]),
+        )
+        .expect("should get slashings via http api");
+    assert_eq!(statuses.len(), 2);
+    assert_eq!(statuses[0].validator_index, Some(proposer_index));
+    assert!(
+        statuses[0].slashing_pending && !statuses[0].slashed,
+        "the slashing should be pending"
+    );
+    assert!(
+        !statuses[1].slashing_pending && !statuses[1].slashed,
+        "another validator should not be slashed"
+    );
+
+    // Re-publishing a known slashing should succeed without error.
+    env.runtime()
+        .block_on(
+            remote_node
+                .http
+                .beacon()
+                .publish_proposer_slashing(slashing),
+        )
+        .expect("should re-publish known proposer slashing via http api");
+
+    let invalid = TestingProposerSlashingBuilder::double_vote::<E, _>(
+        ProposerSlashingTestTask::ProposalsIdentical,
+        2,
+        |_, _, _, _| Signature::empty_signature(),
+    );
+
+    assert!(
+        env.runtime()
+            .block_on(remote_node.http.beacon().publish_proposer_slashing(invalid))
+            .is_err(),
+        "should not publish an invalid proposer slashing"
+    );
 }
 
 #[test]
@@ -288,6 +434,103 @@ fn check_duties<T: BeaconChainTypes>(
         });
 }
 
+#[test]
+fn validator_duties_with_dependent_root() {
+    let mut env = build_env();
+
+    let spec = &E::default_spec();
+
+    let node = LocalBeaconNode::production(env.core_context(), testing_client_config());
+    let remote_node = node.remote_node().expect("should produce remote node");
+
+    let beacon_chain = node
+        .client
+        .beacon_chain()
+        .expect("client should have beacon chain");
+
+    let epoch = Epoch::new(0);
+    let state = beacon_chain.head().beacon_state.clone();
+    let validator_indices = (0..state.validators.len() as u64).collect::<Vec<_>>();
+
+    let attester_duties = env
+        .runtime()
+        .block_on(
+            remote_node
+                .http
+                .validator()
+                .get_attester_duties(epoch, validator_indices.clone()),
+        )
+        .expect("should fetch attester duties from http api");
+
+    assert_eq!(
+        attester_duties.dependent_root, beacon_chain.genesis_block_root,
+        "attester duties of the first epoch should depend upon genesis"
+    );
+    assert_eq!(
+        attester_duties.data.len(),
+        validator_indices.len(),
+        "every validator should have an attester duty"
+    );
+
+    for duty in &attester_duties.data {
+        let expected = state
+            .get_attestation_duties(duty.validator_index as usize, RelativeEpoch::Current)
+            .expect("should have attestation duties cache")
+            .expect("should have attestation duties");
+
+        assert_eq!(
+            duty.pubkey, state.validators[duty.validator_index as usize].pubkey,
+            "pubkey should match"
+        );
+        assert_eq!(duty.slot, expected.slot, "attestation slot should match");
+        assert_eq!(
+            duty.committee_index, expected.index,
+            "committee index should match"
+        );
+        assert_eq!(
+            duty.validator_committee_index, expected.committee_position as u64,
+            "committee position should match"
+        );
+    }
+
+    let proposer_duties = env
+        .runtime()
+        .block_on(remote_node.http.validator().get_proposer_duties(epoch))
+        .expect("should fetch proposer duties from http api");
+
+    assert_eq!(
+        proposer_duties.dependent_root, beacon_chain.genesis_block_root,
+        "proposer duties of the first epoch should depend upon genesis"
+    );
+    assert_eq!(
+        proposer_duties.data.len() as u64,
+        E::slots_per_epoch(),
+        "there should be a proposer for each slot"
+    );
+
+    for (duty, slot) in proposer_duties
+        .data
+        .iter()
+        .zip(epoch.slot_iter(E::slots_per_epoch()))
+    {
+        assert_eq!(duty.slot, slot, "proposal slot should match");
+        assert_eq!(
+            duty.validator_index as usize,
+            state
+                .get_beacon_proposer_index(slot, spec)
+                .expect("should know proposer"),
+            "proposer should match"
+        );
+    }
+
+    assert!(
+        env.runtime()
+            .block_on(remote_node.http.validator().get_proposer_duties(epoch + 2))
+            .is_err(),
+        "should not return proposer duties for a future epoch"
+    );
+}
+
 #[test]
 fn validator_block_post() {
     let mut env = build_env();
@@ -355,6 +598,80 @@ fn validator_block_post() {
     );
 }
 
+#[test]
+fn validator_block_post_equivocation() {
+    let mut env = build_env();
+
+    let spec = &E::default_spec();
+
+    let mut config = testing_client_config();
+    config.genesis = ClientGenesis::Interop {
+        validator_count: 8,
+        genesis_time: 13_371_337,
+    };
+
+    let node = LocalBeaconNode::production(env.core_context(), config);
+    let remote_node = node.remote_node().expect("should produce remote node");
+
+    let beacon_chain = node
+        .client
+        .beacon_chain()
+        .expect("client should have beacon chain");
+
+    let slot = Slot::new(1);
+    let randao_reveal = get_randao_reveal(beacon_chain.clone(), slot, spec);
+
+    let mut block = env
+        .runtime()
+        .block_on(
+            remote_node
+                .http
+                .validator()
+                .produce_block(slot, randao_reveal),
+        )
+        .expect("should fetch block from http api");
+    let mut conflicting_block = block.clone();
+
+    sign_block(beacon_chain.clone(), &mut block, spec);
+    let publish_status = env
+        .runtime()
+        .block_on(remote_node.http.validator().publish_block(block.clone()))
+        .expect("should publish block");
+    assert_eq!(
+        publish_status,
+        PublishStatus::Valid,
+        "the first block should be valid"
+    );
+
+    // Publishing the same block again is not an equivocation.
+    env.runtime()
+        .block_on(remote_node.http.validator().publish_block(block.clone()))
+        .expect("should republish block");
+
+    conflicting_block.body.graffiti = [42; 32];
+    sign_block(beacon_chain, &mut conflicting_block, spec);
+    let error = env
+        .runtime()
+        .block_on(
+            remote_node
+                .http
+                .validator()
+                .publish_block(conflicting_block),
+        )
+        .expect_err("should refuse to publish a conflicting block");
+    assert_eq!(error.error_code(), Some(ErrorCode::ProposerEquivocation));
+
+    let head = env
+        .runtime()
+        .block_on(remote_node.http.beacon().get_head())
+        .expect("should get head");
+    assert_eq!(
+        head.block_root,
+        block.canonical_root(),
+        "the first block should remain the head block"
+    );
+}
+
 #[test]
 fn validator_block_get() {
     let mut env = build_env();
@@ -439,6 +756,127 @@ fn beacon_state() {
     );
 }
 
+#[test]
+fn checkpoint_sync() {
+    let mut env = build_env();
+
+    let node = LocalBeaconNode::production(env.core_context(), testing_client_config());
+    let remote_node = node.remote_node().expect("should produce remote node");
+    let checkpoint_sync = remote_node.http.checkpoint_sync();
+
+    let checkpoint = env
+        .runtime()
+        .block_on(checkpoint_sync.get_checkpoint("genesis", None))
+        .expect("should fetch checkpoint from http api");
+
+    let beacon_chain = node
+        .client
+        .beacon_chain()
+        .expect("client should have beacon chain");
+    let mut db_state = beacon_chain
+        .state_at_slot(Slot::new(0))
+        .expect("should find state");
+    db_state.drop_all_caches();
+    let state_root = db_state.canonical_root();
+
+    assert_eq!(checkpoint.state, db_state, "state should match the DB");
+    assert_eq!(
+        checkpoint.block_root, beacon_chain.genesis_block_root,
+        "block should be the genesis block"
+    );
+
+    assert_eq!(
+        env.runtime()
+            .block_on(checkpoint_sync.get_state_root("genesis"))
+            .expect("should fetch state root from http api"),
+        state_root,
+        "state root should match the DB"
+    );
+
+    for expected_root in &[
+        ExpectedRoot::Block(beacon_chain.genesis_block_root),
+        ExpectedRoot::State(state_root),
+    ] {
+        let checkpoint = env
+            .runtime()
+            .block_on(checkpoint_sync.get_checkpoint("head", Some(*expected_root)))
+            .expect("should fetch checkpoint by expected root");
+        assert_eq!(
+            checkpoint.state, db_state,
+            "state should match the expected root"
+        );
+    }
+    assert!(
+        env.runtime()
+            .block_on(checkpoint_sync.get_checkpoint(
+                "genesis",
+                Some(ExpectedRoot::State(Hash256::repeat_byte(42)))
+            ))
+            .is_err(),
+        "an unknown expected root should be rejected"
+    );
+
+    let compressed = env
+        .runtime()
+        .block_on(checkpoint_sync.get_compressed_state_bytes(state_root, 0))
+        .expect("should fetch state bytes from http api");
+    let resumed = env
+        .runtime()
+        .block_on(checkpoint_sync.get_compressed_state_bytes(state_root, 100))
+        .expect("should fetch a range of state bytes from http api");
+
+    assert_eq!(
+        &compressed[100..],
+        &resumed[..],
+        "a download resumed from an offset should match the full download"
+    );
+    assert!(
+        env.runtime()
+            .block_on(checkpoint_sync.get_compressed_state_bytes(state_root, compressed.len()))
+            .is_err(),
+        "a range beyond the end of the state should be rejected"
+    );
+}
+
+#[test]
+fn request_ids() {
+    let mut env = build_env();
+
+    let node = LocalBeaconNode::production(env.core_context(), testing_client_config());
+    let listen_addr = node
+        .client
+        .http_listen_addr()
+        .expect("should have http api");
+    let client = reqwest::r#async::Client::new();
+
+    let mut request_id = |path: &str| -> u64 {
+        let response = env
+            .runtime()
+            .block_on(
+                client
+                    .get(&format!("http://{}/{}", listen_addr, path))
+                    .send(),
+            )
+            .expect("should send request");
+        response
+            .headers()
+            .get("X-Request-Id")
+            .expect("response should have a request id")
+            .to_str()
+            .expect("request id should be a string")
+            .parse()
+            .expect("request id should be an integer")
+    };
+
+    let first = request_id("node/version");
+    // Failed requests are also assigned an ID.
+    let second = request_id("not/a/path");
+    let third = request_id("node/version");
+
+    assert!(first < second, "request ids should increase");
+    assert!(second < third, "request ids should increase");
+}
+
 #[test]
 fn beacon_block() {
     let mut env = build_env();
@@ -531,6 +969,181 @@ fn fork() {
     );
 }
 
+#[test]
+fn validator_balances_and_status() {
+    let mut env = build_env();
+
+    let node = LocalBeaconNode::production(env.core_context(), testing_client_config());
+    let remote_node = node.remote_node().expect("should produce remote node");
+
+    let state = node
+        .client
+        .beacon_chain()
+        .expect("should have beacon chain")
+        .head()
+        .beacon_state;
+
+    let balances = env
+        .runtime()
+        .block_on(
+            remote_node
+                .http
+                .beacon()
+                .get_validator_balances("head".to_string(), 2, 3),
+        )
+        .expect("should fetch balances from http api");
+
+    assert_eq!(balances.len(), 3, "should respect the limit");
+    assert!(
+        env.runtime()
+            .block_on(
+                remote_node
+                    .http
+                    .beacon()
+                    .get_validator_balances("head".to_string(), 0, 0),
+            )
+            .is_err(),
+        "should reject a zero limit"
+    );
+    for (i, balance) in balances.iter().enumerate() {
+        assert_eq!(balance.index, i as u64 + 2, "should respect the offset");
+        assert_eq!(
+            balance.balance,
+            state.balances[i + 2],
+            "should match head state balance"
+        );
+    }
+
+    // States identified by root are read from the store, rather than from the head.
+    let state_root = node
+        .client
+        .beacon_chain()
+        .expect("should have beacon chain")
+        .head()
+        .beacon_state_root;
+    let stored_balances = env
+        .runtime()
+        .block_on(remote_node.http.beacon().get_validator_balances(
+            format!("{:?}", state_root),
+            0,
+            usize::max_value(),
+        ))
+        .expect("should fetch balances by state root from http api");
+
+    assert_eq!(
+        stored_balances
+            .iter()
+            .map(|balance| balance.balance)
+            .collect::<Vec<_>>(),
+        state.balances.to_vec(),
+        "should match stored state balances"
+    );
+
+    let validators = env
+        .runtime()
+        .block_on(remote_node.http.beacon().get_validators(
+            "genesis".to_string(),
+            vec![ValidatorStatus::ActiveOngoing],
+            0,
+            usize::max_value(),
+        ))
+        .expect("should fetch validators from http api");
+
+    assert_eq!(
+        validators.len(),
+        state.validators.len(),
+        "all genesis validators should be active"
+    );
+    for validator in &validators {
+        assert_eq!(
+            validator.pubkey, state.validators[validator.index as usize].pubkey,
+            "should match head state pubkey"
+        );
+    }
+
+    let pending = env
+        .runtime()
+        .block_on(remote_node.http.beacon().get_validators(
+            "head".to_string(),
+            vec![
+                ValidatorStatus::PendingInitialized,
+                ValidatorStatus::PendingQueued,
+            ],
+            0,
+            usize::max_value(),
+        ))
+        .expect("should fetch validators from http api");
+
+    assert!(pending.is_empty(), "should be no pending validators");
+}
+
+#[test]
+fn state_proofs() {
+    let mut env = build_env();
+
+    let node = LocalBeaconNode::production(env.core_context(), testing_client_config());
+    let remote_node = node.remote_node().expect("should produce remote node");
+
+    let state = node
+        .client
+        .beacon_chain()
+        .expect("should have beacon chain")
+        .head()
+        .beacon_state;
+
+    let mut get_proof = |key: &str, value: &str| {
+        let proof = env
+            .runtime()
+            .block_on(remote_node.http.beacon().get_state_proof(
+                "head".to_string(),
+                key.to_string(),
+                value.to_string(),
+            ))
+            .expect("should fetch proof from http api");
+
+        let depth = proof.proof.len();
+        assert!(
+            verify_merkle_proof(
+                proof.leaf,
+                &proof.proof,
+                depth,
+                proof.generalized_index as usize - (1 << depth),
+                proof.state_root
+            ),
+            "proof should be valid"
+        );
+        assert_eq!(
+            proof.state_root.as_bytes(),
+            &state.tree_hash_root()[..],
+            "should prove against the head state"
+        );
+        proof
+    };
+
+    let proof = get_proof("field", "finalized_checkpoint");
+    assert_eq!(
+        proof.leaf.as_bytes(),
+        &state.finalized_checkpoint.tree_hash_root()[..],
+        "should prove the finalized checkpoint"
+    );
+
+    let proof = get_proof("validator_index", "3");
+    assert_eq!(
+        proof.leaf.as_bytes(),
+        &state.validators[3].tree_hash_root()[..],
+        "should prove the validator record"
+    );
+
+    let unknown_field = env
+        .runtime()
+        .block_on(remote_node.http.beacon().get_state_proof(
+            "head".to_string(),
+            "field".to_string(),
+            "cats".to_string(),
+        ));
+    assert!(unknown_field.is_err(), "should not prove an unknown field");
+}
+
 #[test]
 fn eth2_config() {
     let mut env = build_env();
@@ -569,3 +1182,231 @@ fn get_version() {
 
     assert_eq!(version::version(), version, "result should be as expected");
 }
+
+#[test]
+fn get_syncing() {
+    let mut env = build_env();
+
+    let node = LocalBeaconNode::production(env.core_context(), testing_client_config());
+    let remote_node = node.remote_node().expect("should produce remote node");
+
+    let syncing = env
+        .runtime()
+        .block_on(remote_node.http.node().get_syncing())
+        .expect("should fetch syncing status from http api");
+
+    let head_slot = node
+        .client
+        .beacon_chain()
+        .expect("should have beacon chain")
+        .best_slot();
+
+    assert!(!syncing.is_syncing, "a lone node should not be syncing");
+    assert_eq!(
+        syncing.sync_state,
+        SyncState::Stalled,
+        "a lone node has no peers to sync from"
+    );
+    assert!(
+        syncing.chains.is_empty(),
+        "there should be no syncing chains"
+    );
+    assert_eq!(
+        syncing.sync_status.current_slot, head_slot,
+        "current slot should be the head slot"
+    );
+    assert_eq!(
+        syncing.sync_status.highest_slot, head_slot,
+        "highest slot should be the head slot"
+    );
+}
+
+#[test]
+fn node_identity_and_peers() {
+    let mut env = build_env();
+
+    let node = LocalBeaconNode::production(env.core_context(), testing_client_config());
+    let remote_node = node.remote_node().expect("should produce remote node");
+
+    let identity = env
+        .runtime()
+        .block_on(remote_node.http.node().get_identity())
+        .expect("should fetch identity from http api");
+
+    assert_eq!(
+        Some(identity.enr),
+        node.client.enr().map(|enr| enr.to_base64()),
+        "enr should match network service"
+    );
+    assert_eq!(
+        Some(identity.p2p_addresses),
+        node.client.libp2p_listen_addresses(),
+        "addresses should match network service"
+    );
+
+    let peers = env
+        .runtime()
+        .block_on(remote_node.http.node().get_peers())
+        .expect("should fetch peers from http api");
+    let peer_count = env
+        .runtime()
+        .block_on(remote_node.http.node().get_peer_count())
+        .expect("should fetch peer count from http api");
+
+    assert_eq!(
+        peer_count.connected,
+        peers.len(),
+        "peer counts should agree"
+    );
+    assert_eq!(
+        peer_count.inbound + peer_count.outbound,
+        peer_count.connected,
+        "directions should sum to total"
+    );
+}
+
+/// Polls the administrative task with `task_id` until it is no longer running.
+fn wait_for_task(
+    env: &mut Environment<E>,
+    remote_node: &RemoteBeaconNode<E>,
+    task_id: u64,
+) -> TaskStatus {
+    loop {
+        let status = env
+            .runtime()
+            .block_on(remote_node.http.lighthouse().get_task(task_id))
+            .expect("should fetch task status from http api");
+        if status != TaskStatus::Running {
+            return status;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn admin_prune_and_clear_caches() {
+    let mut env = build_env();
+
+    let spec = &E::default_spec();
+
+    let mut config = testing_client_config();
+    config.genesis = ClientGenesis::Interop {
+        validator_count: 8,
+        genesis_time: 13_371_337,
+    };
+    config.rest_api.admin_token = Some("secret".to_string());
+
+    let node = LocalBeaconNode::production(env.core_context(), config);
+    let remote_node = node
+        .remote_node()
+        .expect("should produce remote node")
+        .api_token(Some("secret".to_string()));
+
+    let beacon_chain = node
+        .client
+        .beacon_chain()
+        .expect("client should have beacon chain");
+
+    // Import a block, so that there is a head other than genesis.
+    let slot = Slot::new(1);
+    let randao_reveal = get_randao_reveal(beacon_chain.clone(), slot, spec);
+    let (mut block, _state) = beacon_chain
+        .produce_block(randao_reveal, slot)
+        .expect("should produce block");
+    sign_block(beacon_chain.clone(), &mut block, spec);
+    let block_root = block.canonical_root();
+    beacon_chain
+        .process_block(block)
+        .expect("should process block");
+
+    // Clearing the caches drops the shuffling cache and the state cache of the store.
+    let (shuffling, _) = beacon_chain
+        .attester_shuffling(Epoch::new(0))
+        .expect("should compute shuffling");
+    let (cached_shuffling, _) = beacon_chain
+        .attester_shuffling(Epoch::new(0))
+        .expect("should read shuffling");
+    assert!(
+        Arc::ptr_eq(&shuffling, &cached_shuffling),
+        "the shuffling should be cached"
+    );
+    assert!(
+        beacon_chain.store.state_cache_len() > 0,
+        "states should be cached"
+    );
+
+    let handle = env
+        .runtime()
+        .block_on(remote_node.http.lighthouse().post_caches_clear())
+        .expect("should clear caches via http api");
+    assert_eq!(
+        wait_for_task(&mut env, &remote_node, handle.task_id),
+        TaskStatus::Complete
+    );
+
+    assert_eq!(
+        beacon_chain.store.state_cache_len(),
+        0,
+        "the state cache should be cleared"
+    );
+    let (recomputed_shuffling, _) = beacon_chain
+        .attester_shuffling(Epoch::new(0))
+        .expect("should recompute shuffling");
+    assert!(
+        !Arc::ptr_eq(&shuffling, &recomputed_shuffling),
+        "the shuffling cache should be cleared"
+    );
+    assert_eq!(
+        *recomputed_shuffling, *shuffling,
+        "the recomputed shuffling should match"
+    );
+    assert!(
+        beacon_chain
+            .head()
+            .beacon_state
+            .committee_cache(RelativeEpoch::Current)
+            .is_ok(),
+        "the head state caches should be rebuilt"
+    );
+
+    // Remove the head block from the database, as if by an interrupted pruning run. Pruning the
+    // database should then remove the head from the head tracker.
+    beacon_chain
+        .store
+        .delete::<BeaconBlock<E>>(&block_root)
+        .expect("should delete block");
+    assert!(beacon_chain
+        .heads()
+        .iter()
+        .any(|(head_root, _)| *head_root == block_root));
+
+    let handle = env
+        .runtime()
+        .block_on(remote_node.http.lighthouse().post_database_prune())
+        .expect("should prune via http api");
+    assert_eq!(
+        wait_for_task(&mut env, &remote_node, handle.task_id),
+        TaskStatus::Complete
+    );
+
+    // The database is pruned by the migrator in a background thread.
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while beacon_chain
+        .heads()
+        .iter()
+        .any(|(head_root, _)| *head_root == block_root)
+    {
+        assert!(
+            Instant::now() < deadline,
+            "the abandoned head should be pruned by the store migrator"
+        );
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    // Administrative endpoints require the admin token.
+    let unauthorized = node.remote_node().expect("should produce remote node");
+    assert!(env
+        .runtime()
+        .block_on(unauthorized.http.lighthouse().post_database_prune())
+        .is_err());
+}
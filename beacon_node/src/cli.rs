@@ -17,6 +17,63 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                 .takes_value(true)
                 .global(true)
         )
+        .arg(
+            Arg::with_name("strict-config")
+                .long("strict-config")
+                .help("If present, refuses to start when the configuration differs from that of \
+                       the previous run of the node in a way that is unsafe to change in place \
+                       (e.g., the deposit contract or the specification constants), rather than \
+                       logging a warning.")
+        )
+        .arg(
+            Arg::with_name("freezer-dir")
+                .long("freezer-dir")
+                .value_name("DIR")
+                .help("Data directory for the freezer database, which stores finalized states.")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("slots-per-restore-point")
+                .long("slots-per-restore-point")
+                .value_name("SLOT_COUNT")
+                .help("Specifies how often a full state is stored in the freezer database. Lower \
+                       values use more disk space but reconstruct historical states faster.")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("db-backend")
+                .long("db-backend")
+                .value_name("BACKEND")
+                .help("The key-value database used to store the chain (default leveldb). Must be \
+                       the same backend that was used to create an existing database.")
+                .takes_value(true)
+                .possible_values(&["leveldb", "rocksdb"])
+        )
+        .arg(
+            Arg::with_name("archive")
+                .long("archive")
+                .value_name("BOOL")
+                .help("If false, the blocks and states of finalized slots older than the history \
+                       horizon are deleted, retaining only block roots and headers (default true).")
+                .takes_value(true)
+                .possible_values(&["true", "false"])
+        )
+        .arg(
+            Arg::with_name("history-horizon")
+                .long("history-horizon")
+                .value_name("SLOT_COUNT")
+                .help("The number of finalized slots for which full blocks and states are retained \
+                       when --archive=false.")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("state-cache-size")
+                .long("state-cache-size")
+                .value_name("MEGABYTES")
+                .help("The maximum total size of the recently used states which are kept in memory \
+                       to avoid re-reading them from the database (default 256).")
+                .takes_value(true)
+        )
         /*
          * Network parameters.
          */
@@ -48,15 +105,35 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
         .arg(
             Arg::with_name("maxpeers")
                 .long("maxpeers")
-                .help("The maximum number of peers (default 10).")
+                .help("The maximum number of peers. Peers which connect beyond this are \
+                      disconnected (default 15).")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("target-peers")
+                .long("target-peers")
+                .help("The target number of peers. When more peers are connected, the peers \
+                      with the lowest scores are disconnected (default 10).")
                 .takes_value(true),
         )
         .arg(
             Arg::with_name("boot-nodes")
                 .long("boot-nodes")
                 .allow_hyphen_values(true)
-                .value_name("ENR-LIST")
-                .help("One or more comma-delimited base64-encoded ENR's to bootstrap the p2p network.")
+                .value_name("ENR/MULTIADDR LIST")
+                .help("One or more comma-delimited base64-encoded ENR's or multiaddrs to bootstrap \
+                       the p2p network. Multiaddrs are dialed but not added to discovery.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("trusted-peers")
+                .long("trusted-peers")
+                .allow_hyphen_values(true)
+                .value_name("ENR/MULTIADDR LIST")
+                .help("One or more comma-delimited base64-encoded ENR's or multiaddrs of peers \
+                       which are never disconnected or banned for their score, and which are \
+                       redialed whenever they disconnect. Multiaddrs must end with a /p2p/ \
+                       peer id.")
                 .takes_value(true),
         )
         .arg(
@@ -71,9 +148,26 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
             Arg::with_name("discovery-address")
                 .long("discovery-address")
                 .value_name("ADDRESS")
-                .help("The IP address to broadcast to other peers on how to reach this node.")
+                .help("The IP address to broadcast to other peers on how to reach this node. \
+                       Setting this prevents the address being replaced by one discovered via UPnP \
+                       or NAT-PMP.")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("enable-upnp")
+                .long("enable-upnp")
+                .help("Attempts to map the listening ports on the local gateway using UPnP and \
+                       advertise the external address. The mappings are leased, renewed whilst the \
+                       node runs and removed when it stops.")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("enable-nat-pmp")
+                .long("enable-nat-pmp")
+                .help("Attempts to map the listening ports on the default gateway using NAT-PMP, \
+                       if UPnP is disabled or no UPnP gateway is found.")
+                .takes_value(false),
+        )
         .arg(
             Arg::with_name("topics")
                 .long("topics")
@@ -95,6 +189,52 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                 .help("A secp256k1 secret key, represented as ASCII-encoded hex bytes (with or without 0x prefix).")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("gossipsub-profile")
+                .long("gossipsub-profile")
+                .value_name("PROFILE")
+                .help("The set of gossipsub mesh parameters to use. \"low-bandwidth\" trades \
+                       propagation latency for bandwidth, \"aggressive\" the reverse.")
+                .possible_values(&["default", "low-bandwidth", "aggressive"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("gossipsub-d")
+                .long("gossipsub-d")
+                .value_name("INTEGER")
+                .help("Overrides the target number of peers in each gossipsub topic mesh (D).")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("gossipsub-d-low")
+                .long("gossipsub-d-low")
+                .value_name("INTEGER")
+                .help("Overrides the number of gossipsub mesh peers below which more peers \
+                       are grafted (D_low).")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("gossipsub-d-high")
+                .long("gossipsub-d-high")
+                .value_name("INTEGER")
+                .help("Overrides the number of gossipsub mesh peers above which peers are \
+                       pruned (D_high).")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("gossipsub-heartbeat-interval")
+                .long("gossipsub-heartbeat-interval")
+                .value_name("MILLISECONDS")
+                .help("Overrides the time between gossipsub heartbeats.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("gossipsub-history-length")
+                .long("gossipsub-history-length")
+                .value_name("INTEGER")
+                .help("Overrides the number of heartbeats that gossipsub messages are cached for.")
+                .takes_value(true),
+        )
         /* REST API related arguments */
         .arg(
             Arg::with_name("no-api")
@@ -117,6 +257,57 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                 .conflicts_with("port-bump")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("api-admin-token")
+                .long("api-admin-token")
+                .value_name("TOKEN")
+                .help("Enables the administrative /lighthouse endpoints of the HTTP API, which \
+                       must then be accessed with an `Authorization: Bearer <TOKEN>` header.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("api-token")
+                .long("api-token")
+                .value_name("TOKEN")
+                .help("Requires an `Authorization: Bearer <TOKEN>` header on HTTP API POST \
+                       requests, which publish to the network or modify the node. The \
+                       administrative /lighthouse endpoints use --api-admin-token instead.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("api-allow-origin")
+                .long("api-allow-origin")
+                .value_name("ORIGINS")
+                .help("Permits cross-origin (CORS) requests to the HTTP API from a \
+                       comma-separated list of origins, or `*` for any origin.")
+                .takes_value(true),
+        )
+        /* gRPC related arguments */
+        .arg(
+            Arg::with_name("grpc")
+                .long("grpc")
+                .help("Enable the gRPC server, which provides an alternative to the validator \
+                       endpoints of the HTTP API. Requires Lighthouse to be compiled with the \
+                       grpc feature.")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("grpc-address")
+                .long("grpc-address")
+                .value_name("ADDRESS")
+                .help("Set the listen address for the gRPC server.")
+                .requires("grpc")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("grpc-port")
+                .long("grpc-port")
+                .value_name("PORT")
+                .help("Set the listen TCP port for the gRPC server.")
+                .requires("grpc")
+                .conflicts_with("port-bump")
+                .takes_value(true),
+        )
         /* Websocket related arguments */
         .arg(
             Arg::with_name("no-ws")
@@ -141,9 +332,69 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                 .takes_value(true),
         )
 
+        /*
+         * Weak subjectivity
+         */
+        .arg(
+            Arg::with_name("wss-checkpoint")
+                .long("wss-checkpoint")
+                .value_name("ROOT:EPOCH")
+                .help("A weak subjectivity checkpoint, as the hex block root and the epoch. \
+                      The node refuses to follow any chain that finalizes the epoch without \
+                      including the block, and refuses to start if it has already done so.")
+                .takes_value(true)
+        )
+
+        /*
+         * Tracing
+         */
+        .arg(
+            Arg::with_name("tracing-endpoint")
+                .long("tracing-endpoint")
+                .value_name("ADDRESS")
+                .help("The address of an OpenTelemetry collector (e.g., localhost:55680) to which \
+                      the spans of HTTP API requests and the beacon chain and database calls \
+                      they make are exported via OTLP. Requires Lighthouse to be compiled with \
+                      the otlp-tracing feature.")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("execution-mode")
+                .long("execution-mode")
+                .value_name("MODE")
+                .help("Determines how aggressively background services use the resources of the \
+                      host. In low-power mode the eth1 node is polled less frequently, fewer sync \
+                      batches are requested at once, the state cache is smaller and database \
+                      migrations are batched. Intended for nodes sharing hardware with other \
+                      workloads.")
+                .takes_value(true)
+                .possible_values(&["default", "low-power"])
+                .default_value("default")
+        )
+        .arg(
+            Arg::with_name("webhooks")
+                .long("webhooks")
+                .value_name("URL LIST")
+                .help("One or more comma-delimited URLs to which critical events (a stall in \
+                      finality, an unreachable eth1 node or a low peer count) are POSTed as JSON.")
+                .takes_value(true)
+        )
+
         /*
          * Eth1 Integration
          */
+        .arg(
+            Arg::with_name("eth1")
+                .long("eth1")
+                .value_name("BOOL")
+                .help("If true, follows the deposit contract via the eth1 node at --eth1-endpoint, \
+                      which is required to include deposits in produced blocks. If false, no eth1 \
+                      node is required; blocks are produced with the dummy eth1 backend of \
+                      --dummy-eth1 and never include deposits.")
+                .takes_value(true)
+                .possible_values(&["true", "false"])
+                .conflicts_with("dummy-eth1")
+        )
         .arg(
             Arg::with_name("dummy-eth1")
                 .long("dummy-eth1")
@@ -167,6 +418,15 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                 // TODO: set this higher once we're not using testnets all the time.
                 .default_value("0")
         )
+        .arg(
+            Arg::with_name("network")
+                .long("network")
+                .value_name("NAME")
+                .help("Configures the deposit contract of a known network (e.g., prysm). It is an \
+                       error to also supply a different --deposit-contract or \
+                       --deposit-contract-deploy.")
+                .takes_value(true)
+        )
         .arg(
             Arg::with_name("deposit-contract")
                 .long("deposit-contract")
@@ -1,22 +1,126 @@
 use clap::ArgMatches;
 use client::{ClientConfig, ClientGenesis, Eth2Config};
-use eth2_config::{read_from_file, write_to_file};
+use eth2_config::{read_from_file, read_testnet_dir, write_to_file, Eth2Network};
 use genesis::recent_genesis_time;
 use lighthouse_bootstrap::Bootstrapper;
 use rand::{distributions::Alphanumeric, Rng};
+use serde_derive::{Deserialize, Serialize};
 use slog::{crit, info, warn, Logger};
 use std::fs;
 use std::net::Ipv4Addr;
 use std::path::{Path, PathBuf};
-use types::{Address, Epoch, Fork};
+use store::DBBackend;
+use types::{Address, ChainSpec, Checkpoint, Epoch, Fork, Hash256};
 
-pub const DEFAULT_DATA_DIR: &str = ".lighthouse";
 pub const CLIENT_CONFIG_FILENAME: &str = "beacon-node.toml";
 pub const ETH2_CONFIG_FILENAME: &str = "eth2-spec.toml";
+pub const EFFECTIVE_CONFIG_FILENAME: &str = "effective-config.toml";
 
 type Result<T> = std::result::Result<T, String>;
 type Config = (ClientConfig, Eth2Config, Logger);
 
+/// The configuration that the node last started with, after all CLI flags were applied.
+///
+/// Persisted in the datadir on each startup, so that the next startup may detect changes which are
+/// unsafe to make to an existing datadir. Only the fields compared by `unsafe_changes` are kept,
+/// so that secrets such as the API tokens are never written to the datadir.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct EffectiveConfig {
+    spec_constants: String,
+    spec: ChainSpec,
+    deposit_contract_address: String,
+    deposit_contract_deploy_block: u64,
+    db_type: String,
+    store_backend: DBBackend,
+    slots_per_restore_point: u64,
+    archive: bool,
+}
+
+/// A configuration value which differs from that of the previous startup.
+struct ConfigChange {
+    field: &'static str,
+    previous: String,
+    current: String,
+}
+
+impl EffectiveConfig {
+    fn new(client: &ClientConfig, eth2: &Eth2Config) -> Self {
+        Self {
+            spec_constants: eth2.spec_constants.clone(),
+            spec: eth2.spec.clone(),
+            deposit_contract_address: client.eth1.deposit_contract_address.clone(),
+            deposit_contract_deploy_block: client.eth1.deposit_contract_deploy_block,
+            db_type: client.db_type.clone(),
+            store_backend: client.store.backend,
+            slots_per_restore_point: client.store.slots_per_restore_point,
+            archive: client.store.archive,
+        }
+    }
+
+    /// Returns the fields which differ between `previous` and `self` which cannot be safely
+    /// changed without creating a new datadir.
+    fn unsafe_changes(&self, previous: &Self) -> Vec<ConfigChange> {
+        let mut changes = vec![];
+        let mut compare = |field: &'static str, previous: String, current: String| {
+            if previous != current {
+                changes.push(ConfigChange {
+                    field,
+                    previous,
+                    current,
+                })
+            }
+        };
+
+        compare(
+            "spec_constants",
+            previous.spec_constants.clone(),
+            self.spec_constants.clone(),
+        );
+
+        // Boot nodes may be changed freely, any other change to the spec is unsafe. The spec is
+        // too large to log, refer the user to the persisted file instead.
+        let without_boot_nodes = |spec: &ChainSpec| ChainSpec {
+            boot_nodes: vec![],
+            ..spec.clone()
+        };
+        if without_boot_nodes(&previous.spec) != without_boot_nodes(&self.spec) {
+            compare(
+                "spec",
+                format!("as per {}", EFFECTIVE_CONFIG_FILENAME),
+                "modified".to_string(),
+            );
+        }
+        compare(
+            "eth1.deposit_contract_address",
+            previous.deposit_contract_address.clone(),
+            self.deposit_contract_address.clone(),
+        );
+        compare(
+            "eth1.deposit_contract_deploy_block",
+            previous.deposit_contract_deploy_block.to_string(),
+            self.deposit_contract_deploy_block.to_string(),
+        );
+        compare("db_type", previous.db_type.clone(), self.db_type.clone());
+        compare(
+            "store.backend",
+            format!("{:?}", previous.store_backend),
+            format!("{:?}", self.store_backend),
+        );
+        compare(
+            "store.slots_per_restore_point",
+            previous.slots_per_restore_point.to_string(),
+            self.slots_per_restore_point.to_string(),
+        );
+        compare(
+            "store.archive",
+            previous.archive.to_string(),
+            self.archive.to_string(),
+        );
+
+        changes
+    }
+}
+
 /// Gets the fully-initialized global client and eth2 configuration objects.
 ///
 /// The top-level `clap` arguments should be provied as `cli_args`.
@@ -29,14 +133,47 @@ pub fn get_configs(cli_args: &ArgMatches, core_log: Logger) -> Result<Config> {
 
     let mut builder = ConfigBuilder::new(cli_args, core_log)?;
 
+    if let Some(val) = cli_args.value_of("testnet-dir") {
+        builder.load_testnet_dir(
+            val.parse::<PathBuf>()
+                .map_err(|e| format!("Unable to parse testnet-dir path: {:?}", e))?,
+        )?;
+    }
+
     if cli_args.is_present("dummy-eth1") {
         builder.client_config.dummy_eth1_backend = true;
     }
 
+    match cli_args.value_of("eth1") {
+        Some("true") => builder.client_config.sync_eth1_chain = true,
+        Some("false") => {
+            builder.client_config.sync_eth1_chain = false;
+            builder.client_config.dummy_eth1_backend = true;
+        }
+        Some(other) => return Err(format!("Invalid --eth1 value: {}", other)),
+        None => {}
+    }
+
     if let Some(val) = cli_args.value_of("eth1-endpoint") {
         builder.set_eth1_endpoint(val)
     }
 
+    if let Some(val) = cli_args.value_of("wss-checkpoint") {
+        builder.client_config.weak_subjectivity_checkpoint = Some(parse_wss_checkpoint(val)?);
+    }
+
+    if let Some(val) = cli_args.value_of("tracing-endpoint") {
+        builder.client_config.tracing_endpoint = Some(val.to_string());
+    }
+
+    if let Some(val) = cli_args.value_of("execution-mode") {
+        builder.client_config.execution_mode = val.parse()?;
+    }
+
+    if let Some(val) = cli_args.value_of("webhooks") {
+        builder.client_config.webhooks = val.split(',').map(String::from).collect();
+    }
+
     if let Some(val) = cli_args.value_of("deposit-contract") {
         builder.set_deposit_contract(
             val.parse::<Address>()
@@ -51,6 +188,15 @@ pub fn get_configs(cli_args: &ArgMatches, core_log: Logger) -> Result<Config> {
         )
     }
 
+    let network = cli_args
+        .value_of("network")
+        .map(Eth2Network::from_name)
+        .transpose()?;
+
+    if let Some(network) = network {
+        builder.set_network(network)?;
+    }
+
     if let Some(val) = cli_args.value_of("eth1-follow") {
         builder.set_eth1_follow(
             val.parse::<u64>()
@@ -87,6 +233,11 @@ pub fn get_configs(cli_args: &ArgMatches, core_log: Logger) -> Result<Config> {
             // If the `testnet` command was not provided, attempt to load an existing datadir and
             // continue with an existing chain.
             builder.load_from_datadir()?;
+
+            // The datadir replaces the client config, so the network must also match it.
+            if let Some(network) = network {
+                builder.set_network(network)?;
+            }
         }
     };
 
@@ -118,6 +269,10 @@ fn process_testnet_subcommand(
             .parse::<PathBuf>()
             .map_err(|e| format!("Unable to parse eth2-config path: {:?}", e))?;
         builder.load_eth2_config(path)?;
+    } else if cli_args.is_present("testnet-dir") {
+        if is_bootstrap {
+            return Err("Cannot supply --testnet-dir when using bootstrap".to_string());
+        }
     } else {
         builder.update_spec_from_subcommand(&cli_args)?;
     }
@@ -243,12 +398,10 @@ fn process_testnet_subcommand(
                 epoch: Epoch::new(0),
             };
 
-            client_config.eth1.deposit_contract_address =
-                "0x802dF6aAaCe28B2EEb1656bb18dF430dDC42cc2e".to_string();
-            client_config.eth1.deposit_contract_deploy_block = 1487270;
             client_config.eth1.follow_distance = 16;
             client_config.dummy_eth1_backend = false;
 
+            builder.set_network(Eth2Network::from_name("prysm")?)?;
             builder.set_genesis(ClientGenesis::DepositContract)
         }
         (cmd, Some(_)) => {
@@ -279,16 +432,7 @@ impl ConfigBuilder {
         //
         // If it's not present, try and find the home directory (`~`) and push the default data
         // directory onto it.
-        let data_dir: PathBuf = cli_args
-            .value_of("datadir")
-            .map(PathBuf::from)
-            .or_else(|| {
-                dirs::home_dir().map(|mut home| {
-                    home.push(DEFAULT_DATA_DIR);
-                    home
-                })
-            })
-            .ok_or_else(|| "Unable to find a home directory for the datadir".to_string())?;
+        let data_dir = clap_utils::parse_data_dir(cli_args)?;
 
         let mut client_config = ClientConfig::default();
         client_config.data_dir = data_dir;
@@ -306,6 +450,7 @@ impl ConfigBuilder {
     ///
     /// - Client config
     /// - Eth2 config
+    /// - Effective config of the previous startup
     /// - The entire database directory
     pub fn clean_datadir(&mut self) -> Result<()> {
         let backup_dir = {
@@ -334,11 +479,16 @@ impl ConfigBuilder {
 
         move_to_backup_dir(&self.client_config.data_dir.join(CLIENT_CONFIG_FILENAME))?;
         move_to_backup_dir(&self.client_config.data_dir.join(ETH2_CONFIG_FILENAME))?;
+        move_to_backup_dir(&self.client_config.data_dir.join(EFFECTIVE_CONFIG_FILENAME))?;
 
         if let Some(db_path) = self.client_config.db_path() {
             move_to_backup_dir(&db_path)?;
         }
 
+        if let Some(freezer_db_path) = self.client_config.get_freezer_db_path() {
+            move_to_backup_dir(&freezer_db_path)?;
+        }
+
         Ok(())
     }
 
@@ -354,6 +504,39 @@ impl ConfigBuilder {
         self.client_config.eth1.deposit_contract_deploy_block = eth1_block_number;
     }
 
+    /// Configures the deposit contract of `network`.
+    ///
+    /// Returns an error if a different deposit contract or deploy block has already been
+    /// configured (e.g., by `--deposit-contract`, the testnet directory or an existing datadir).
+    pub fn set_network(&mut self, network: &Eth2Network) -> Result<()> {
+        let eth1 = &mut self.client_config.eth1;
+
+        if eth1.deposit_contract_address != ClientConfig::default().eth1.deposit_contract_address
+            && !network.is_deposit_contract(&eth1.deposit_contract_address)
+        {
+            return Err(format!(
+                "Deposit contract {} does not match the {} network deposit contract {}",
+                eth1.deposit_contract_address, network.name, network.deposit_contract_address
+            ));
+        }
+
+        if eth1.deposit_contract_deploy_block != 0
+            && eth1.deposit_contract_deploy_block != network.deposit_contract_deploy_block
+        {
+            return Err(format!(
+                "Deposit contract deploy block {} does not match the {} network deploy block {}",
+                eth1.deposit_contract_deploy_block,
+                network.name,
+                network.deposit_contract_deploy_block
+            ));
+        }
+
+        eth1.deposit_contract_address = network.deposit_contract_address.to_string();
+        eth1.deposit_contract_deploy_block = network.deposit_contract_deploy_block;
+
+        Ok(())
+    }
+
     pub fn set_eth1_follow(&mut self, distance: u64) {
         self.client_config.eth1.follow_distance = distance;
     }
@@ -437,6 +620,20 @@ impl ConfigBuilder {
         Ok(())
     }
 
+    /// Reads the chain spec file of a testnet directory into `self.eth2_config`, along with the
+    /// deposit contract address it specifies (if any).
+    pub fn load_testnet_dir(&mut self, testnet_dir: PathBuf) -> Result<()> {
+        let file = read_testnet_dir(&testnet_dir)?;
+
+        if let Some(deposit_contract) = file.deposit_contract_address {
+            self.set_deposit_contract(deposit_contract);
+        }
+        self.client_config.spec_constants = file.preset_base.clone();
+        self.eth2_config = Eth2Config::from_chain_spec_file(&file)?;
+
+        Ok(())
+    }
+
     fn update_eth2_config(&mut self, eth2_config: Eth2Config) {
         self.eth2_config = eth2_config;
     }
@@ -588,6 +785,7 @@ impl ConfigBuilder {
 
         self.client_config.network.listen_address = addr.into();
         self.client_config.rest_api.listen_address = addr;
+        self.client_config.grpc.listen_address = addr;
 
         Ok(())
     }
@@ -607,6 +805,7 @@ impl ConfigBuilder {
             self.client_config.network.libp2p_port += bump;
             self.client_config.network.discovery_port += bump;
             self.client_config.rest_api.port += bump;
+            self.client_config.grpc.port += bump;
             self.client_config.websocket_server.port += bump;
         }
 
@@ -618,8 +817,64 @@ impl ConfigBuilder {
             return Err("Specification constant mismatch".into());
         }
 
+        self.persist_effective_config(cli_args.is_present("strict-config"))?;
+
         Ok((self.client_config, self.eth2_config, self.log))
     }
+
+    /// Compares the configs in `self` to those of the previous startup, then persists them in
+    /// `self.data_dir` for comparison by the next startup.
+    ///
+    /// Changes which are unsafe to make to an existing datadir are logged, or cause an error if
+    /// `strict` is `true`, in which case the persisted configs are left unchanged.
+    fn persist_effective_config(&self, strict: bool) -> Result<()> {
+        let path = self.client_config.data_dir.join(EFFECTIVE_CONFIG_FILENAME);
+        let current = EffectiveConfig::new(&self.client_config, &self.eth2_config);
+
+        match read_from_file::<EffectiveConfig>(path.clone()) {
+            Ok(Some(previous)) => {
+                let changes = current.unsafe_changes(&previous);
+
+                for change in &changes {
+                    crit!(
+                        self.log,
+                        "Configuration changed since the previous run";
+                        "msg" => "this field cannot be safely changed for an existing datadir",
+                        "field" => change.field,
+                        "previous" => &change.previous,
+                        "current" => &change.current,
+                    );
+                }
+
+                if strict && !changes.is_empty() {
+                    return Err(format!(
+                        "Refusing to start with {} unsafe configuration change(s) due to \
+                         --strict-config. Revert the changes or remove {:?}",
+                        changes.len(),
+                        path
+                    ));
+                }
+            }
+            Ok(None) => {}
+            // The previous config may have been written by an incompatible version of the node,
+            // it is replaced below.
+            Err(e) => warn!(
+                self.log,
+                "Unable to read previous configuration";
+                "error" => e,
+            ),
+        }
+
+        fs::create_dir_all(&self.client_config.data_dir)
+            .map_err(|e| format!("Unable to create datadir: {:?}", e))?;
+
+        write_to_file(path, &current).map_err(|e| {
+            format!(
+                "Unable to write {} file: {:?}",
+                EFFECTIVE_CONFIG_FILENAME, e
+            )
+        })
+    }
 }
 
 fn random_string(len: usize) -> String {
@@ -628,3 +883,121 @@ fn random_string(len: usize) -> String {
         .take(len)
         .collect::<String>()
 }
+
+/// Parses a weak subjectivity checkpoint from `ROOT:EPOCH`, where `ROOT` is a hex block root
+/// (optionally `0x`-prefixed) and `EPOCH` is a decimal epoch.
+fn parse_wss_checkpoint(string: &str) -> Result<Checkpoint> {
+    let mut split = string.split(':');
+    let (root, epoch) = match (split.next(), split.next(), split.next()) {
+        (Some(root), Some(epoch), None) => (root, epoch),
+        _ => {
+            return Err(format!(
+                "Invalid --wss-checkpoint {}, expected ROOT:EPOCH",
+                string
+            ))
+        }
+    };
+
+    let root = root.trim_start_matches("0x");
+    if root.len() != 64 {
+        return Err(format!(
+            "Invalid --wss-checkpoint root {}, expected 32 hex bytes",
+            root
+        ));
+    }
+
+    Ok(Checkpoint {
+        root: root
+            .parse::<Hash256>()
+            .map_err(|e| format!("Invalid --wss-checkpoint root: {:?}", e))?,
+        epoch: epoch
+            .parse::<u64>()
+            .map(Epoch::new)
+            .map_err(|e| format!("Invalid --wss-checkpoint epoch: {:?}", e))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slog::{o, Discard};
+    use tempfile::tempdir;
+
+    fn builder(data_dir: &Path) -> ConfigBuilder {
+        let mut client_config = ClientConfig::default();
+        client_config.data_dir = data_dir.to_path_buf();
+
+        ConfigBuilder {
+            log: Logger::root(Discard, o!()),
+            eth2_config: Eth2Config::minimal(),
+            client_config,
+        }
+    }
+
+    fn changed_fields(previous: &ConfigBuilder, current: &ConfigBuilder) -> Vec<&'static str> {
+        EffectiveConfig::new(&current.client_config, &current.eth2_config)
+            .unsafe_changes(&EffectiveConfig::new(
+                &previous.client_config,
+                &previous.eth2_config,
+            ))
+            .into_iter()
+            .map(|change| change.field)
+            .collect()
+    }
+
+    #[test]
+    fn unsafe_changes() {
+        let dir = tempdir().unwrap();
+        let previous = builder(dir.path());
+
+        let mut current = builder(dir.path());
+        current.client_config.rest_api.port += 1;
+        current.eth2_config.spec.boot_nodes = vec!["enr:-abc".to_string()];
+        assert!(
+            changed_fields(&previous, &current).is_empty(),
+            "ports and boot nodes may be changed"
+        );
+
+        current.client_config.store.slots_per_restore_point += 1;
+        current.client_config.store.archive = !previous.client_config.store.archive;
+        current.eth2_config.spec.min_genesis_time += 1;
+        assert_eq!(
+            changed_fields(&previous, &current),
+            vec!["spec", "store.slots_per_restore_point", "store.archive"]
+        );
+    }
+
+    #[test]
+    fn effective_config_excludes_secrets() {
+        let dir = tempdir().unwrap();
+        let mut builder = builder(dir.path());
+        builder.client_config.rest_api.admin_token = Some("admin-secret".to_string());
+        builder.client_config.rest_api.api_token = Some("api-secret".to_string());
+
+        builder.persist_effective_config(false).unwrap();
+
+        let persisted = fs::read_to_string(dir.path().join(EFFECTIVE_CONFIG_FILENAME)).unwrap();
+        assert!(!persisted.contains("admin-secret"));
+        assert!(!persisted.contains("api-secret"));
+    }
+
+    #[test]
+    fn strict_config_refuses_unsafe_changes() {
+        let dir = tempdir().unwrap();
+        builder(dir.path()).persist_effective_config(true).unwrap();
+
+        let mut changed = builder(dir.path());
+        changed.client_config.store.slots_per_restore_point += 1;
+        assert!(
+            changed.persist_effective_config(true).is_err(),
+            "should refuse an unsafe change"
+        );
+
+        // The refused configuration is not persisted, so the change is refused again.
+        assert!(changed.persist_effective_config(true).is_err());
+
+        // Without --strict-config the change is logged and persisted.
+        changed.persist_effective_config(false).unwrap();
+        changed.persist_effective_config(true).unwrap();
+    }
+}
@@ -11,12 +11,13 @@ pub use eth2_config::Eth2Config;
 
 use beacon_chain::{
     builder::Witness, eth1_chain::CachingEth1Backend, events::WebSocketSender,
-    lmd_ghost::ThreadSafeReducedTree, slot_clock::SystemTimeSlotClock,
+    migrate::BackgroundMigrator, resource_budget::ResourceBudget, slot_clock::SystemTimeSlotClock,
 };
 use clap::ArgMatches;
 use config::get_configs;
 use environment::RuntimeContext;
 use futures::{Future, IntoFuture};
+use notifier::Notifier;
 use slog::{info, warn};
 use std::ops::{Deref, DerefMut};
 use store::DiskStore;
@@ -26,8 +27,8 @@ use types::EthSpec;
 pub type ProductionClient<E> = Client<
     Witness<
         DiskStore,
+        BackgroundMigrator<DiskStore, E>,
         SystemTimeSlotClock,
-        ThreadSafeReducedTree<DiskStore, E>,
         CachingEth1Backend<E, DiskStore>,
         E,
         WebSocketSender<E>,
@@ -78,17 +79,50 @@ impl<E: EthSpec> ProductionBeaconNode<E> {
         let spec = context.eth2_config().spec.clone();
         let genesis_eth1_config = client_config.eth1.clone();
         let client_genesis = client_config.genesis.clone();
+        // Serve the HTTP API whilst waiting for the deposit contract to reach genesis, which may
+        // take some time.
+        let pre_genesis_http_config = match client_genesis {
+            ClientGenesis::DepositContract => Some(client_config.rest_api.clone()),
+            _ => None,
+        };
         let log = context.log.clone();
 
+        let store_config = client_config.store.clone();
+        let resource_budget = ResourceBudget::new(client_config.execution_mode);
+        let notifier = Notifier::new(
+            client_config.webhooks.clone(),
+            "beacon_node",
+            context.log.clone(),
+        );
+
         client_config
             .db_path()
             .ok_or_else(|| "Unable to access database path".to_string())
+            .and_then(|db_path| {
+                client_config
+                    .get_freezer_db_path()
+                    .ok_or_else(|| "Unable to access freezer database path".to_string())
+                    .map(|freezer_db_path| (db_path, freezer_db_path))
+            })
+            .and_then(|(db_path, freezer_db_path)| {
+                client_config
+                    .data_dir()
+                    .ok_or_else(|| "Unable to access data directory".to_string())
+                    .map(|data_dir| (db_path, freezer_db_path, data_dir))
+            })
             .into_future()
-            .and_then(move |db_path| {
+            .and_then(move |(db_path, freezer_db_path, data_dir)| {
                 Ok(ClientBuilder::new(context.eth_spec_instance.clone())
                     .runtime_context(context)
-                    .disk_store(&db_path)?
-                    .chain_spec(spec))
+                    .resource_budget(resource_budget)
+                    .notifier(notifier)
+                    .chain_spec(spec)
+                    .data_dir(data_dir)
+                    .disk_store(&db_path, &freezer_db_path, store_config)?)
+            })
+            .and_then(move |builder| match pre_genesis_http_config {
+                Some(config) => builder.pre_genesis_http_server(&config),
+                None => Ok(builder),
             })
             .and_then(move |builder| {
                 builder.beacon_chain_builder(client_genesis, genesis_eth1_config)
@@ -119,12 +153,27 @@ impl<E: EthSpec> ProductionBeaconNode<E> {
                     builder.no_eth1_backend()?
                 };
 
+                let builder =
+                    if let Some(checkpoint) = client_config.weak_subjectivity_checkpoint.clone() {
+                        info!(
+                            log,
+                            "Weak subjectivity checkpoint enabled";
+                            "root" => format!("{:?}", checkpoint.root),
+                            "epoch" => checkpoint.epoch,
+                        );
+                        builder.weak_subjectivity_checkpoint(checkpoint)?
+                    } else {
+                        builder
+                    };
+
                 let builder = builder
+                    .tracing_exporter(&client_config)?
                     .system_time_slot_clock()?
                     .websocket_event_handler(client_config.websocket_server.clone())?
                     .build_beacon_chain()?
                     .libp2p_network(&client_config.network)?
                     .http_server(&client_config, &http_eth2_config)?
+                    .grpc_server(&client_config)?
                     .peer_count_notifier()?
                     .slot_notifier()?;
 
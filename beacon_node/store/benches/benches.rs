@@ -0,0 +1,109 @@
+use criterion::Criterion;
+use criterion::{criterion_group, criterion_main, Benchmark};
+use ssz::Encode;
+use std::fs;
+use std::path::Path;
+use store::{DBBackend, DBColumn, DiskDB, KeyValueStore};
+use tempfile::tempdir;
+use types::test_utils::TestingBeaconStateBuilder;
+use types::{EthSpec, Hash256, MinimalEthSpec};
+
+pub const VALIDATOR_COUNT: usize = 4_096;
+/// The number of slots worth of states written during each simulated sync.
+pub const SLOTS_PER_SYNC: u64 = 64;
+
+fn all_benches(c: &mut Criterion) {
+    let state_bytes = get_state_bytes::<MinimalEthSpec>(VALIDATOR_COUNT);
+
+    for backend in &[DBBackend::LevelDB, DBBackend::RocksDB] {
+        report_write_amplification(*backend, &state_bytes);
+        bench_sync_writes(c, *backend, state_bytes.clone());
+    }
+}
+
+/// Returns the SSZ bytes of a state with `validator_count` validators.
+fn get_state_bytes<T: EthSpec>(validator_count: usize) -> Vec<u8> {
+    let spec = T::default_spec();
+    let (state, _keypairs) =
+        TestingBeaconStateBuilder::<T>::from_deterministic_keypairs(validator_count, &spec).build();
+
+    state.as_ssz_bytes()
+}
+
+/// Simulates the writes performed whilst syncing: one state per slot, where each state differs
+/// slightly from its predecessor.
+///
+/// Returns the number of bytes written.
+fn sync_writes(db: &DiskDB, state_bytes: &[u8]) -> u64 {
+    let column: &str = DBColumn::BeaconState.into();
+    let mut bytes = state_bytes.to_vec();
+    let mut written = 0;
+
+    for slot in 0..SLOTS_PER_SYNC {
+        bytes[0..8].copy_from_slice(&slot.to_le_bytes());
+
+        db.put_bytes(column, Hash256::from_low_u64_be(slot).as_bytes(), &bytes)
+            .expect("should write state");
+
+        written += bytes.len() as u64;
+    }
+
+    written
+}
+
+/// Returns the total size of all files in `path`.
+fn dir_size(path: &Path) -> u64 {
+    fs::read_dir(path)
+        .expect("should read dir")
+        .map(|entry| {
+            let entry = entry.expect("should read dir entry");
+            let metadata = entry.metadata().expect("should read metadata");
+
+            if metadata.is_dir() {
+                dir_size(&entry.path())
+            } else {
+                metadata.len()
+            }
+        })
+        .sum()
+}
+
+/// Prints the ratio of on-disk bytes to logical bytes after a simulated sync and compaction.
+fn report_write_amplification(backend: DBBackend, state_bytes: &[u8]) {
+    let dir = tempdir().expect("should create temp dir");
+    let db = DiskDB::open(backend, dir.path()).expect("should open db");
+
+    let written = sync_writes(&db, state_bytes);
+    db.compact().expect("should compact db");
+
+    let on_disk = dir_size(dir.path());
+
+    println!(
+        "{:?}: wrote {} bytes, {} bytes on disk after compaction ({:.2}x)",
+        backend,
+        written,
+        on_disk,
+        on_disk as f64 / written as f64
+    );
+}
+
+fn bench_sync_writes(c: &mut Criterion, backend: DBBackend, state_bytes: Vec<u8>) {
+    c.bench(
+        &format!("{:?}/{}_validators", backend, VALIDATOR_COUNT),
+        Benchmark::new("sync_writes", move |b| {
+            b.iter_batched(
+                || {
+                    let dir = tempdir().expect("should create temp dir");
+                    let db = DiskDB::open(backend, dir.path()).expect("should open db");
+                    (dir, db)
+                },
+                |(_dir, db)| sync_writes(&db, &state_bytes),
+                criterion::BatchSize::PerIteration,
+            )
+        })
+        .sample_size(10),
+    );
+}
+
+criterion_group!(benches, all_benches,);
+criterion_main!(benches);
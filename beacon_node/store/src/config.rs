@@ -0,0 +1,41 @@
+use crate::DBBackend;
+use serde_derive::{Deserialize, Serialize};
+
+/// The default number of slots between restore points in the freezer database.
+pub const DEFAULT_SLOTS_PER_RESTORE_POINT: u64 = 2048;
+/// The default number of slots of full history retained prior to the split when not archiving.
+pub const DEFAULT_HISTORY_HORIZON: u64 = 8192;
+/// The default maximum total size of the states held in the in-memory state cache.
+pub const DEFAULT_STATE_CACHE_SIZE: usize = 256 * 1024 * 1024;
+
+/// Database configuration parameters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StoreConfig {
+    /// Number of slots between restore point states in the freezer database.
+    pub slots_per_restore_point: u64,
+    /// The key-value database used for the hot and freezer databases.
+    pub backend: DBBackend,
+    /// If `true`, all finalized blocks and states are retained in the freezer database.
+    ///
+    /// Otherwise, only the block roots and block headers are retained for slots more than
+    /// `history_horizon` slots prior to the split, with the block bodies and states deleted.
+    pub archive: bool,
+    /// The number of slots prior to the split for which full history is retained when not
+    /// archiving.
+    pub history_horizon: u64,
+    /// The maximum total size of the states held in the in-memory state cache, in bytes.
+    pub state_cache_size: usize,
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        Self {
+            slots_per_restore_point: DEFAULT_SLOTS_PER_RESTORE_POINT,
+            backend: DBBackend::default(),
+            archive: true,
+            history_horizon: DEFAULT_HISTORY_HORIZON,
+            state_cache_size: DEFAULT_STATE_CACHE_SIZE,
+        }
+    }
+}
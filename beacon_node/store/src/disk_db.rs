@@ -0,0 +1,115 @@
+use crate::leveldb_store::LevelDB;
+use crate::rocksdb_store::RocksDB;
+use crate::{ColumnIter, Error, KeyValueStore, KeyValueStoreOp, Store};
+use serde_derive::{Deserialize, Serialize};
+use std::path::Path;
+use std::str::FromStr;
+
+/// The key-value database used to back an on-disk store.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DBBackend {
+    LevelDB,
+    RocksDB,
+}
+
+impl Default for DBBackend {
+    fn default() -> Self {
+        DBBackend::LevelDB
+    }
+}
+
+impl FromStr for DBBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "leveldb" => Ok(DBBackend::LevelDB),
+            "rocksdb" => Ok(DBBackend::RocksDB),
+            other => Err(format!("Unknown database backend: {}", other)),
+        }
+    }
+}
+
+/// An on-disk key-value database, using the backend selected at runtime.
+#[derive(Clone)]
+pub enum DiskDB {
+    LevelDB(LevelDB),
+    RocksDB(RocksDB),
+}
+
+impl DiskDB {
+    /// Open a database of type `backend` at `path`, creating a new database if one does not
+    /// already exist.
+    pub fn open(backend: DBBackend, path: &Path) -> Result<Self, Error> {
+        match backend {
+            DBBackend::LevelDB => LevelDB::open(path).map(DiskDB::LevelDB),
+            DBBackend::RocksDB => RocksDB::open(path).map(DiskDB::RocksDB),
+        }
+    }
+}
+
+impl KeyValueStore for DiskDB {
+    fn get_bytes(&self, column: &str, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        match self {
+            DiskDB::LevelDB(db) => db.get_bytes(column, key),
+            DiskDB::RocksDB(db) => db.get_bytes(column, key),
+        }
+    }
+
+    fn put_bytes(&self, column: &str, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        match self {
+            DiskDB::LevelDB(db) => db.put_bytes(column, key, value),
+            DiskDB::RocksDB(db) => db.put_bytes(column, key, value),
+        }
+    }
+
+    fn key_exists(&self, column: &str, key: &[u8]) -> Result<bool, Error> {
+        match self {
+            DiskDB::LevelDB(db) => db.key_exists(column, key),
+            DiskDB::RocksDB(db) => db.key_exists(column, key),
+        }
+    }
+
+    fn key_delete(&self, column: &str, key: &[u8]) -> Result<(), Error> {
+        match self {
+            DiskDB::LevelDB(db) => db.key_delete(column, key),
+            DiskDB::RocksDB(db) => db.key_delete(column, key),
+        }
+    }
+
+    fn write_batch(&self, batch: Vec<KeyValueStoreOp>) -> Result<(), Error> {
+        match self {
+            DiskDB::LevelDB(db) => db.write_batch(batch),
+            DiskDB::RocksDB(db) => db.write_batch(batch),
+        }
+    }
+
+    fn iter_column<'a>(&'a self, column: &str) -> Result<ColumnIter<'a>, Error> {
+        match self {
+            DiskDB::LevelDB(db) => db.iter_column(column),
+            DiskDB::RocksDB(db) => db.iter_column(column),
+        }
+    }
+
+    fn compact(&self) -> Result<(), Error> {
+        match self {
+            DiskDB::LevelDB(db) => db.compact(),
+            DiskDB::RocksDB(db) => db.compact(),
+        }
+    }
+}
+
+impl Store for DiskDB {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backend_from_str() {
+        assert_eq!("leveldb".parse(), Ok(DBBackend::LevelDB));
+        assert_eq!("rocksdb".parse(), Ok(DBBackend::RocksDB));
+        assert!("sled".parse::<DBBackend>().is_err());
+    }
+}
@@ -1,9 +1,17 @@
+use crate::hot_cold_store::HotColdDBError;
 use ssz::DecodeError;
+use types::BeaconStateError;
 
 #[derive(Debug, PartialEq)]
 pub enum Error {
     SszDecodeError(DecodeError),
-    DBError { message: String },
+    DBError {
+        message: String,
+    },
+    HotColdDBError(HotColdDBError),
+    BeaconStateError(BeaconStateError),
+    /// An item could not be upgraded to a new schema version.
+    SchemaMigrationError(String),
 }
 
 impl From<DecodeError> for Error {
@@ -12,6 +20,18 @@ impl From<DecodeError> for Error {
     }
 }
 
+impl From<HotColdDBError> for Error {
+    fn from(e: HotColdDBError) -> Error {
+        Error::HotColdDBError(e)
+    }
+}
+
+impl From<BeaconStateError> for Error {
+    fn from(e: BeaconStateError) -> Error {
+        Error::BeaconStateError(e)
+    }
+}
+
 impl From<DBError> for Error {
     fn from(e: DBError) -> Error {
         Error::DBError { message: e.message }
@@ -0,0 +1,1610 @@
+use crate::iter::{BlockRootsIterator, StateRootsIterator};
+use crate::metadata::{
+    AnchorInfo, SchemaVersion, ANCHOR_INFO_KEY, CURRENT_SCHEMA_VERSION, SCHEMA_VERSION_KEY,
+};
+use crate::state_cache::StateCache;
+use crate::state_diff::BeaconStateDiff;
+use crate::{
+    metrics, ColumnIter, DBColumn, DiskDB, Error, KeyValueStore, KeyValueStoreOp,
+    PartialBeaconState, Store, StoreConfig, StoreItem, StoreOp,
+};
+use parking_lot::{Mutex, RwLock};
+use ssz::{Decode, Encode};
+use ssz_derive::{Decode, Encode};
+use state_processing::{BlockReplayError, BlockReplayer};
+use std::any::Any;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::debug_span;
+use types::*;
+
+/// 32-byte key for accessing the `split` of the freezer DB.
+pub const SPLIT_DB_KEY: &str = "FREEZERDBSPLITFREEZERDBSPLITFREE";
+
+/// On-disk database that stores finalized states efficiently.
+///
+/// States prior to the split slot are moved to a "freezer" database which stores the block and
+/// state root of every slot, but only stores a full "restore point" state every
+/// `slots_per_restore_point` slots. Other frozen states are reconstructed on demand by replaying
+/// blocks on top of the preceding restore point.
+///
+/// Within the hot database, only the first stored state of each epoch is stored in full. Later
+/// states of the same epoch are stored as a `BeaconStateDiff` against that state, and are
+/// reassembled transparently when loaded.
+///
+/// Unless `config.archive` is set, the blocks and states of frozen slots which fall outside the
+/// history horizon are deleted, with only the block roots and headers retained.
+pub struct HotColdDB {
+    /// The slot and state root at the point where the database is split between hot and cold.
+    ///
+    /// States with slots less than `split.slot` are in the cold DB, while states with slots
+    /// greater than or equal are in the hot DB.
+    split: RwLock<Split>,
+    /// The oldest blocks and states which have not been pruned, or `None` if no history has been
+    /// pruned.
+    anchor_info: RwLock<Option<AnchorInfo>>,
+    /// Database configuration, including the number of slots per restore point.
+    config: StoreConfig,
+    /// Cold database containing compact historical data.
+    cold_db: DiskDB,
+    /// Hot database containing duplicated but quick-to-access recent data.
+    hot_db: DiskDB,
+    /// Recently read and written states, from both databases.
+    ///
+    /// Shared by all readers of the store, so that states which are requested repeatedly (e.g.,
+    /// the parent state during block processing, or historical states whilst iterating backwards
+    /// through the chain) don't need to be reassembled or reconstructed each time.
+    state_cache: Mutex<StateCache>,
+    /// The root of the base state of the most recent `BeaconStateDiff`, along with the decoded
+    /// state.
+    ///
+    /// Every hot state of an epoch after the first is diffed against the same base, which is kept
+    /// decoded so that it needn't be read and decoded again for each of them. `HotColdDB` is not
+    /// generic over the `EthSpec`, so the state is downcast when it is used.
+    diff_base: Mutex<Option<(Hash256, Arc<dyn Any + Send + Sync>)>>,
+    /// Chain spec, required to replay blocks when reconstructing states.
+    spec: ChainSpec,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum HotColdDBError {
+    /// Attempted to move the split to a slot prior to the current split.
+    FreezeSlotError {
+        current_split_slot: Slot,
+        proposed_split_slot: Slot,
+    },
+    /// A state that was due to be moved to the freezer was missing from the hot database.
+    MissingStateToFreeze(Hash256),
+    /// The restore point required to reconstruct a frozen state is missing.
+    MissingRestorePoint(u64),
+    /// The block root of a frozen slot is missing.
+    MissingFrozenBlockRoot(Slot),
+    /// The state root of a frozen slot is missing.
+    MissingFrozenStateRoot(Slot),
+    /// A block required to reconstruct a frozen state is missing.
+    MissingBlock(Hash256),
+    /// The base state required to reassemble a diffed hot state is missing.
+    MissingStateDiffBase(Hash256),
+    InvalidSlotsPerRestorePoint(u64),
+    /// The database was created by a newer release, and cannot be opened by this one.
+    UnsupportedSchemaVersion {
+        found: SchemaVersion,
+        supported: SchemaVersion,
+    },
+    /// The database was created prior to schema versioning, and its persisted beacon chain cannot
+    /// be decoded by this release. It must be deleted and the chain synced again.
+    UnversionedDatabase,
+    /// There is no migration from the schema version `from` to the next version.
+    MissingSchemaMigration {
+        from: SchemaVersion,
+    },
+    BlockReplayError(BlockReplayError),
+}
+
+impl KeyValueStore for HotColdDB {
+    // Defer to the hot database for basic operations (including blocks for now)
+    fn get_bytes(&self, column: &str, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        self.hot_db.get_bytes(column, key)
+    }
+
+    fn put_bytes(&self, column: &str, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        self.hot_db.put_bytes(column, key, value)
+    }
+
+    fn key_exists(&self, column: &str, key: &[u8]) -> Result<bool, Error> {
+        self.hot_db.key_exists(column, key)
+    }
+
+    fn key_delete(&self, column: &str, key: &[u8]) -> Result<(), Error> {
+        self.hot_db.key_delete(column, key)
+    }
+
+    fn write_batch(&self, batch: Vec<KeyValueStoreOp>) -> Result<(), Error> {
+        self.hot_db.write_batch(batch)
+    }
+
+    fn iter_column<'a>(&'a self, column: &str) -> Result<ColumnIter<'a>, Error> {
+        self.hot_db.iter_column(column)
+    }
+
+    /// Compact both the hot and cold databases.
+    fn compact(&self) -> Result<(), Error> {
+        self.hot_db.compact()?;
+        self.cold_db.compact()
+    }
+}
+
+impl Store for HotColdDB {
+    /// Store a state in the store.
+    fn put_state<E: EthSpec>(
+        &self,
+        state_root: &Hash256,
+        state: &BeaconState<E>,
+    ) -> Result<(), Error> {
+        if state.slot < self.get_split_slot() {
+            self.store_cold_state(state_root, state)?;
+        } else {
+            self.store_hot_state(state_root, state)?;
+        }
+
+        self.cache_state(state_root, state);
+
+        Ok(())
+    }
+
+    /// Fetch a state from the store, via the state cache.
+    fn get_state<E: EthSpec>(
+        &self,
+        state_root: &Hash256,
+        slot: Option<Slot>,
+    ) -> Result<Option<BeaconState<E>>, Error> {
+        let span = debug_span!("store_get_state", state_root = ?state_root);
+        let _enter = span.enter();
+
+        if let Some(state) = self.get_cached_state(state_root)? {
+            return Ok(Some(state));
+        }
+
+        let state = if let Some(slot) = slot {
+            if slot < self.get_split_slot() {
+                self.load_cold_state(state_root, slot)?
+            } else {
+                self.load_hot_state(state_root)?
+            }
+        } else {
+            match self.load_hot_state(state_root)? {
+                Some(state) => Some(state),
+                None => match self.load_cold_state_slot(state_root)? {
+                    Some(slot) => self.load_cold_state(state_root, slot)?,
+                    None => None,
+                },
+            }
+        };
+
+        if let Some(state) = &state {
+            self.cache_state(state_root, state);
+        }
+
+        Ok(state)
+    }
+
+    /// Fetch the validators and balances of a state from the store, via the state cache.
+    ///
+    /// Only the validators and balances of hot states and restore points are decoded. Other
+    /// frozen states must be reconstructed in full by replaying blocks.
+    fn get_partial_state<E: EthSpec>(
+        &self,
+        state_root: &Hash256,
+        slot: Option<Slot>,
+    ) -> Result<Option<PartialBeaconState>, Error> {
+        // Release the lock before decoding, so that concurrent readers aren't blocked.
+        let bytes = self.state_cache.lock().get(state_root);
+        if let Some(bytes) = bytes {
+            return PartialBeaconState::from_store_bytes::<E>(&bytes).map(Some);
+        }
+
+        if let Some(slot) = slot {
+            if slot < self.get_split_slot() {
+                self.load_cold_partial_state::<E>(state_root, slot)
+            } else {
+                self.load_hot_partial_state::<E>(state_root)
+            }
+        } else {
+            match self.load_hot_partial_state::<E>(state_root)? {
+                Some(state) => Ok(Some(state)),
+                None => match self.load_cold_state_slot(state_root)? {
+                    Some(slot) => self.load_cold_partial_state::<E>(state_root, slot),
+                    None => Ok(None),
+                },
+            }
+        }
+    }
+
+    /// Advance the split point of the store, moving all finalized states below `frozen_head`
+    /// to the freezer database.
+    fn freeze_to_state<E: EthSpec>(
+        store: Arc<Self>,
+        frozen_head_root: Hash256,
+        frozen_head: &BeaconState<E>,
+    ) -> Result<(), Error> {
+        let current_split_slot = store.get_split_slot();
+
+        if frozen_head.slot < current_split_slot {
+            return Err(HotColdDBError::FreezeSlotError {
+                current_split_slot,
+                proposed_split_slot: frozen_head.slot,
+            }
+            .into());
+        }
+
+        let state_roots = StateRootsIterator::new(store.clone(), frozen_head);
+        let block_roots = BlockRootsIterator::new(store.clone(), frozen_head);
+
+        let mut to_delete = vec![];
+        for ((state_root, slot), (block_root, _)) in state_roots
+            .zip(block_roots)
+            .take_while(|((_, slot), _)| *slot >= current_split_slot)
+        {
+            if slot % store.config.slots_per_restore_point == 0 {
+                let state: BeaconState<E> = store
+                    .load_hot_state(&state_root)?
+                    .ok_or_else(|| HotColdDBError::MissingStateToFreeze(state_root))?;
+
+                store.store_restore_point(&state)?;
+            }
+
+            store.store_cold_state_summary(&state_root, slot)?;
+            store.store_cold_block_root(slot, &block_root)?;
+
+            to_delete.push(state_root);
+        }
+
+        // Update the split before deleting the hot states, so that they are always available from
+        // one of the databases.
+        let split = Split {
+            slot: frozen_head.slot,
+            state_root: frozen_head_root,
+        };
+        store
+            .hot_db
+            .put(&Hash256::from_slice(SPLIT_DB_KEY.as_bytes()), &split)?;
+        *store.split.write() = split;
+
+        store.write_batch(to_delete.iter().flat_map(delete_hot_state_ops).collect())?;
+
+        if !store.config.archive {
+            store.prune_history::<E>(current_split_slot, frozen_head.slot, frozen_head_root)?;
+        }
+
+        Ok(())
+    }
+
+    /// Delete a hot state, whether it is stored in full or as a diff.
+    fn delete_state(&self, state_root: &Hash256) -> Result<(), Error> {
+        self.hot_db.write_batch(delete_hot_state_ops(state_root))?;
+        self.state_cache.lock().remove(state_root);
+
+        Ok(())
+    }
+
+    /// Apply all of the operations in `batch` atomically.
+    ///
+    /// Only the hot database takes part in the batch. States prior to the split slot cannot be
+    /// stored atomically with other items, so they are written to the freezer immediately.
+    fn do_atomically<E: EthSpec>(&self, batch: Vec<StoreOp<E>>) -> Result<(), Error> {
+        let mut kv_batch = Vec::with_capacity(batch.len());
+        let mut to_cache = vec![];
+        let mut to_uncache = vec![];
+
+        for op in batch {
+            match op {
+                StoreOp::PutBlock(block_root, block) => {
+                    kv_batch.push(block.as_kv_store_op(&block_root));
+                }
+                StoreOp::PutState(state_root, state) => {
+                    if state.slot < self.get_split_slot() {
+                        self.store_cold_state(&state_root, state)?;
+                    } else {
+                        kv_batch.push(self.hot_state_as_kv_store_op(&state_root, state)?);
+                    }
+                    to_cache.push((state_root, state));
+                }
+                StoreOp::DeleteBlock(block_root) => {
+                    kv_batch.push(KeyValueStoreOp::delete(DBColumn::BeaconBlock, &block_root));
+                }
+                StoreOp::DeleteState(state_root) => {
+                    kv_batch.append(&mut delete_hot_state_ops(&state_root));
+                    to_uncache.push(state_root);
+                }
+                StoreOp::KeyValueOp(op) => kv_batch.push(op),
+            }
+        }
+
+        self.hot_db.write_batch(kv_batch)?;
+
+        // Only update the cache once the batch has been written, so it never contains states
+        // which aren't in the database.
+        for (state_root, state) in to_cache {
+            self.cache_state(&state_root, state);
+        }
+        let mut state_cache = self.state_cache.lock();
+        for state_root in &to_uncache {
+            state_cache.remove(state_root);
+        }
+
+        Ok(())
+    }
+
+    fn get_anchor_info(&self) -> Option<AnchorInfo> {
+        *self.anchor_info.read()
+    }
+
+    /// Drop the state cache and the cached base of the most recent state diff.
+    fn clear_caches(&self) {
+        self.state_cache.lock().clear();
+        *self.diff_base.lock() = None;
+    }
+}
+
+impl HotColdDB {
+    /// Open a new or existing database, with the hot database at `hot_path` and the freezer at
+    /// `cold_path`.
+    ///
+    /// Items which are stored by the beacon chain (rather than the store itself) are upgraded
+    /// between schema versions by `migrate_chain`, which is called with the versions to migrate
+    /// from and to.
+    pub fn open(
+        hot_path: &Path,
+        cold_path: &Path,
+        config: StoreConfig,
+        spec: ChainSpec,
+        migrate_chain: impl Fn(&Self, SchemaVersion, SchemaVersion) -> Result<(), Error>,
+    ) -> Result<Self, Error> {
+        if config.slots_per_restore_point == 0 {
+            return Err(HotColdDBError::InvalidSlotsPerRestorePoint(
+                config.slots_per_restore_point,
+            )
+            .into());
+        }
+
+        let db = HotColdDB {
+            split: RwLock::new(Split::default()),
+            anchor_info: RwLock::new(None),
+            cold_db: DiskDB::open(config.backend, cold_path)?,
+            hot_db: DiskDB::open(config.backend, hot_path)?,
+            state_cache: Mutex::new(StateCache::new(config.state_cache_size)),
+            diff_base: Mutex::new(None),
+            config,
+            spec,
+        };
+
+        // Ensure the database schema is supported before reading anything else from it.
+        let schema_version = db.load_schema_version()?;
+        if schema_version > CURRENT_SCHEMA_VERSION {
+            return Err(HotColdDBError::UnsupportedSchemaVersion {
+                found: schema_version,
+                supported: CURRENT_SCHEMA_VERSION,
+            }
+            .into());
+        }
+
+        // Load the previous split slot from the database (if any). This ensures we can
+        // stop and restart correctly, and that migrations which read states find them.
+        if let Some(split) = db.load_split()? {
+            *db.split.write() = split;
+        }
+
+        // Upgrade the database to the current schema before reading any other items.
+        db.migrate_schema(schema_version, CURRENT_SCHEMA_VERSION, migrate_chain)?;
+
+        *db.anchor_info.write() = db.load_anchor_info()?;
+
+        Ok(db)
+    }
+
+    /// Returns the slot before which all states are stored in the freezer database.
+    pub fn get_split_slot(&self) -> Slot {
+        self.split.read().slot
+    }
+
+    /// Returns the number of states in the state cache.
+    pub fn state_cache_len(&self) -> usize {
+        self.state_cache.lock().len()
+    }
+
+    /// Returns the number of slots between restore points in the freezer database.
+    pub fn slots_per_restore_point(&self) -> u64 {
+        self.config.slots_per_restore_point
+    }
+
+    /// Returns `true` if the state with `state_root` is stored in either database.
+    ///
+    /// Unlike `get_state`, the state is not loaded or reconstructed.
+    pub fn state_exists(&self, state_root: &Hash256) -> Result<bool, Error> {
+        let key = state_root.as_bytes();
+
+        Ok(self.hot_db.key_exists(DBColumn::BeaconState.into(), key)?
+            || self
+                .hot_db
+                .key_exists(DBColumn::BeaconStateDiff.into(), key)?
+            || self.load_cold_state_slot(state_root)?.is_some())
+    }
+
+    /// Delete all states from the hot database with slots prior to the split, returning the number
+    /// of states deleted.
+    ///
+    /// Such states are never read, but may be left behind by an interrupted migration to the
+    /// freezer or by forks which were abandoned prior to finalization.
+    pub fn delete_stale_hot_states<E: EthSpec>(&self) -> Result<usize, Error> {
+        let split_slot = self.get_split_slot();
+        let mut stale = vec![];
+
+        for (key, mut value) in self.hot_db.iter_column(DBColumn::BeaconState.into())? {
+            if BeaconState::<E>::from_store_bytes(&mut value)?.slot < split_slot {
+                stale.push(Hash256::from_slice(&key));
+            }
+        }
+        for (key, mut value) in self.hot_db.iter_column(DBColumn::BeaconStateDiff.into())? {
+            if BeaconStateDiff::<E>::from_store_bytes(&mut value)?.slot() < split_slot {
+                stale.push(Hash256::from_slice(&key));
+            }
+        }
+
+        self.hot_db
+            .write_batch(stale.iter().flat_map(delete_hot_state_ops).collect())?;
+
+        let mut state_cache = self.state_cache.lock();
+        for state_root in &stale {
+            state_cache.remove(state_root);
+        }
+
+        Ok(stale.len())
+    }
+
+    /// Returns the header of the block with `block_root`.
+    ///
+    /// Unlike the block itself, the header remains available once the block has been pruned.
+    pub fn get_block_header<E: EthSpec>(
+        &self,
+        block_root: &Hash256,
+    ) -> Result<Option<BeaconBlockHeader>, Error> {
+        match self.hot_db.get::<BeaconBlock<E>>(block_root)? {
+            Some(block) => Ok(Some(block.block_header())),
+            None => self.cold_db.get(block_root),
+        }
+    }
+
+    /// Returns the finalized state at `slot`, reconstructing it from the nearest restore point if
+    /// required.
+    ///
+    /// Only finalized states (i.e., those with a slot less than or equal to the split slot) can
+    /// be found by slot, since the store is not aware of which non-finalized states are
+    /// canonical. Returns `None` for later slots.
+    pub fn get_state_at_slot<E: EthSpec>(
+        &self,
+        slot: Slot,
+    ) -> Result<Option<BeaconState<E>>, Error> {
+        let split = *self.split.read();
+
+        if slot == split.slot {
+            self.get_state(&split.state_root, Some(slot))
+        } else if slot < split.slot {
+            match self.get_cold_state_root(slot)? {
+                Some(state_root) => self.get_state(&state_root, Some(slot)),
+                None => Ok(None),
+            }
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Returns the root of the latest block at or before `slot`, if `slot` has been frozen.
+    pub fn get_cold_block_root(&self, slot: Slot) -> Result<Option<Hash256>, Error> {
+        Ok(self
+            .cold_db
+            .get_bytes(DBColumn::BeaconBlockRoots.into(), &slot_key(slot))?
+            .map(|bytes| Hash256::from_slice(&bytes)))
+    }
+
+    /// Returns the root of the state at `slot`, if `slot` has been frozen.
+    pub fn get_cold_state_root(&self, slot: Slot) -> Result<Option<Hash256>, Error> {
+        Ok(self
+            .cold_db
+            .get_bytes(DBColumn::BeaconStateRoots.into(), &slot_key(slot))?
+            .map(|bytes| Hash256::from_slice(&bytes)))
+    }
+
+    /// Store a post-finalization state in the hot database.
+    fn store_hot_state<E: EthSpec>(
+        &self,
+        state_root: &Hash256,
+        state: &BeaconState<E>,
+    ) -> Result<(), Error> {
+        let op = self.hot_state_as_kv_store_op(state_root, state)?;
+        self.hot_db.write_batch(vec![op])
+    }
+
+    /// Returns the operation which stores a post-finalization state in the hot database.
+    ///
+    /// The state is stored as a diff against the first state of its epoch that is stored in full,
+    /// or in full if there is no such state. States which are yet to be written (e.g., those
+    /// earlier in the same batch) are not considered as a base.
+    fn hot_state_as_kv_store_op<E: EthSpec>(
+        &self,
+        state_root: &Hash256,
+        state: &BeaconState<E>,
+    ) -> Result<KeyValueStoreOp, Error> {
+        Ok(match self.load_diff_base(state)? {
+            Some((base_state_root, base)) => {
+                BeaconStateDiff::new(base_state_root, &base, state).as_kv_store_op(state_root)
+            }
+            None => {
+                // The state is the base of the later states of its epoch.
+                self.cache_diff_base(*state_root, Arc::new(state.clone()));
+                state.as_kv_store_op(state_root)
+            }
+        })
+    }
+
+    /// Returns the decoded base state with `state_root`, if it is the cached diff base.
+    fn get_cached_diff_base<E: EthSpec>(
+        &self,
+        state_root: &Hash256,
+    ) -> Option<Arc<BeaconState<E>>> {
+        match self.diff_base.lock().as_ref() {
+            Some((base_state_root, base)) if base_state_root == state_root => {
+                base.clone().downcast::<BeaconState<E>>().ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// Replace the cached diff base with `base`.
+    fn cache_diff_base<E: EthSpec>(&self, state_root: Hash256, base: Arc<BeaconState<E>>) {
+        *self.diff_base.lock() = Some((state_root, base));
+    }
+
+    /// Returns the root of the earliest state in the same epoch as `state` which is stored in full
+    /// in the hot database, along with the state itself.
+    fn load_diff_base<E: EthSpec>(
+        &self,
+        state: &BeaconState<E>,
+    ) -> Result<Option<(Hash256, Arc<BeaconState<E>>)>, Error> {
+        let epoch_start_slot = state.current_epoch().start_slot(E::slots_per_epoch());
+
+        for slot in epoch_start_slot.as_u64()..state.slot.as_u64() {
+            let base_state_root = *state.get_state_root(Slot::new(slot))?;
+
+            if self.hot_db.exists::<BeaconState<E>>(&base_state_root)? {
+                if let Some(base) = self.get_cached_diff_base(&base_state_root) {
+                    return Ok(Some((base_state_root, base)));
+                }
+
+                let base = match self.get_cached_state(&base_state_root)? {
+                    Some(base) => Some(base),
+                    None => self.hot_db.get_state(&base_state_root, None)?,
+                };
+                return Ok(base.map(|base| {
+                    let base = Arc::new(base);
+                    self.cache_diff_base(base_state_root, base.clone());
+                    (base_state_root, base)
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Load a post-finalization state from the hot database, reassembling it from its base state
+    /// if it is stored as a diff.
+    fn load_hot_state<E: EthSpec>(
+        &self,
+        state_root: &Hash256,
+    ) -> Result<Option<BeaconState<E>>, Error> {
+        if let Some(state) = self.hot_db.get_state(state_root, None)? {
+            return Ok(Some(state));
+        }
+
+        let diff = match self.hot_db.get::<BeaconStateDiff<E>>(state_root)? {
+            Some(diff) => diff,
+            None => return Ok(None),
+        };
+
+        // The base state may have been moved to the freezer since the diff was stored.
+        let base = match self.get_cached_diff_base::<E>(&diff.base_state_root) {
+            Some(base) => Some((*base).clone()),
+            None => match self.get_cached_state(&diff.base_state_root)? {
+                Some(base) => Some(base),
+                None => match self.hot_db.get_state(&diff.base_state_root, None)? {
+                    Some(base) => Some(base),
+                    None => self.load_cold_state(&diff.base_state_root, diff.base_slot)?,
+                },
+            },
+        }
+        .ok_or_else(|| HotColdDBError::MissingStateDiffBase(diff.base_state_root))?;
+
+        diff.apply(base).map(Some)
+    }
+
+    /// Load the validators and balances of a post-finalization state from the hot database,
+    /// applying its diff to those of its base state if it is stored as a diff.
+    fn load_hot_partial_state<E: EthSpec>(
+        &self,
+        state_root: &Hash256,
+    ) -> Result<Option<PartialBeaconState>, Error> {
+        if let Some(state) = self.hot_db.get_partial_state::<E>(state_root, None)? {
+            return Ok(Some(state));
+        }
+
+        let diff = match self.hot_db.get::<BeaconStateDiff<E>>(state_root)? {
+            Some(diff) => diff,
+            None => return Ok(None),
+        };
+
+        // The base state may have been moved to the freezer since the diff was stored.
+        let base = match self.get_cached_diff_base::<E>(&diff.base_state_root) {
+            Some(base) => Some(PartialBeaconState::from_state(&base)),
+            None => self.get_partial_state::<E>(&diff.base_state_root, Some(diff.base_slot))?,
+        }
+        .ok_or_else(|| HotColdDBError::MissingStateDiffBase(diff.base_state_root))?;
+
+        diff.apply_partial(base).map(Some)
+    }
+
+    /// Store a pre-finalization state in the freezer database.
+    fn store_cold_state<E: EthSpec>(
+        &self,
+        state_root: &Hash256,
+        state: &BeaconState<E>,
+    ) -> Result<(), Error> {
+        if state.slot % self.config.slots_per_restore_point == 0 {
+            self.store_restore_point(state)?;
+        }
+
+        self.store_cold_state_summary(state_root, state.slot)
+    }
+
+    /// Store `state` as the restore point for its slot.
+    fn store_restore_point<E: EthSpec>(&self, state: &BeaconState<E>) -> Result<(), Error> {
+        let restore_point_index = state.slot.as_u64() / self.config.slots_per_restore_point;
+
+        // Restore points are stored without their caches, which are rebuilt upon replay.
+        self.cold_db.put_bytes(
+            DBColumn::BeaconRestorePoint.into(),
+            &restore_point_index.to_be_bytes(),
+            &state.as_ssz_bytes(),
+        )
+    }
+
+    /// Record the slot of the frozen state with `state_root`, so it may be reconstructed.
+    fn store_cold_state_summary(&self, state_root: &Hash256, slot: Slot) -> Result<(), Error> {
+        self.cold_db.put(state_root, &ColdStateSummary { slot })?;
+        self.cold_db.put_bytes(
+            DBColumn::BeaconStateRoots.into(),
+            &slot_key(slot),
+            state_root.as_bytes(),
+        )
+    }
+
+    /// Record the root of the latest block at or before `slot`.
+    fn store_cold_block_root(&self, slot: Slot, block_root: &Hash256) -> Result<(), Error> {
+        self.cold_db.put_bytes(
+            DBColumn::BeaconBlockRoots.into(),
+            &slot_key(slot),
+            block_root.as_bytes(),
+        )
+    }
+
+    /// Returns the slot of the frozen state with `state_root`, if it is known.
+    fn load_cold_state_slot(&self, state_root: &Hash256) -> Result<Option<Slot>, Error> {
+        Ok(self
+            .cold_db
+            .get::<ColdStateSummary>(state_root)?
+            .map(|summary| summary.slot))
+    }
+
+    /// Load the restore point with the given index.
+    fn load_restore_point<E: EthSpec>(
+        &self,
+        restore_point_index: u64,
+    ) -> Result<BeaconState<E>, Error> {
+        let bytes = self
+            .cold_db
+            .get_bytes(
+                DBColumn::BeaconRestorePoint.into(),
+                &restore_point_index.to_be_bytes(),
+            )?
+            .ok_or_else(|| HotColdDBError::MissingRestorePoint(restore_point_index))?;
+
+        Ok(BeaconState::from_ssz_bytes(&bytes)?)
+    }
+
+    /// Load a frozen state, replaying blocks on top of the preceding restore point if `slot` is
+    /// not itself a restore point.
+    fn load_cold_state<E: EthSpec>(
+        &self,
+        state_root: &Hash256,
+        slot: Slot,
+    ) -> Result<Option<BeaconState<E>>, Error> {
+        // Ensure the state actually exists in the freezer, rather than returning whichever
+        // state happens to be at `slot`.
+        if self.get_cold_state_root(slot)? != Some(*state_root) {
+            return Ok(None);
+        }
+
+        let restore_point_index = slot.as_u64() / self.config.slots_per_restore_point;
+        let restore_point = self.load_restore_point(restore_point_index)?;
+
+        if restore_point.slot == slot {
+            return Ok(Some(restore_point));
+        }
+
+        let timer = metrics::start_timer(&metrics::FREEZER_STATE_RECONSTRUCTION_TIMES);
+        let span = debug_span!(
+            "replay_blocks",
+            from_slot = restore_point.slot.as_u64(),
+            to_slot = slot.as_u64()
+        );
+        let _enter = span.enter();
+
+        let blocks = self.load_blocks_to_replay(restore_point.slot, slot)?;
+        let state = BlockReplayer::new(restore_point, &self.spec)
+            .no_signature_verification()
+            .apply_blocks(blocks, Some(slot))
+            .map_err(HotColdDBError::BlockReplayError)?
+            .into_state();
+
+        metrics::stop_timer(timer);
+
+        Ok(Some(state))
+    }
+
+    /// Load the validators and balances of a frozen state.
+    ///
+    /// They are decoded directly from the restore point if `slot` is a restore point. Otherwise,
+    /// the state is reconstructed in full (and cached), since block and epoch processing may
+    /// modify any validator or balance.
+    fn load_cold_partial_state<E: EthSpec>(
+        &self,
+        state_root: &Hash256,
+        slot: Slot,
+    ) -> Result<Option<PartialBeaconState>, Error> {
+        if slot % self.config.slots_per_restore_point != 0 {
+            return Ok(self.load_cold_state::<E>(state_root, slot)?.map(|state| {
+                self.cache_state(state_root, &state);
+                PartialBeaconState::from_state(&state)
+            }));
+        }
+
+        if self.get_cold_state_root(slot)? != Some(*state_root) {
+            return Ok(None);
+        }
+
+        let restore_point_index = slot.as_u64() / self.config.slots_per_restore_point;
+        let bytes = self
+            .cold_db
+            .get_bytes(
+                DBColumn::BeaconRestorePoint.into(),
+                &restore_point_index.to_be_bytes(),
+            )?
+            .ok_or_else(|| HotColdDBError::MissingRestorePoint(restore_point_index))?;
+
+        Ok(Some(PartialBeaconState::from_state_ssz_bytes::<E>(&bytes)?))
+    }
+
+    /// Returns the slot prior to which history is pruned when not archiving, for the given
+    /// `split_slot`.
+    ///
+    /// The slot is a multiple of `slots_per_restore_point`, so that the restore point and blocks
+    /// required to reconstruct any later frozen state are always retained.
+    fn history_prune_slot(&self, split_slot: Slot) -> Slot {
+        let slot = split_slot
+            .as_u64()
+            .saturating_sub(self.config.history_horizon);
+        Slot::new(slot - slot % self.config.slots_per_restore_point)
+    }
+
+    /// Delete the blocks and states of the frozen slots which passed out of the history horizon
+    /// as the split advanced from `old_split_slot` to `new_split_slot`.
+    ///
+    /// The block root of each slot is retained, along with the header of each deleted block.
+    fn prune_history<E: EthSpec>(
+        &self,
+        old_split_slot: Slot,
+        new_split_slot: Slot,
+        new_split_state_root: Hash256,
+    ) -> Result<(), Error> {
+        let start_slot = self.history_prune_slot(old_split_slot);
+        let end_slot = self.history_prune_slot(new_split_slot);
+
+        if end_slot <= start_slot {
+            return Ok(());
+        }
+
+        // Record the new anchor before deleting anything, so that the data it describes as
+        // available is always present.
+        let anchor_root = if end_slot == new_split_slot {
+            new_split_state_root
+        } else {
+            self.get_cold_state_root(end_slot)?
+                .ok_or_else(|| HotColdDBError::MissingFrozenStateRoot(end_slot))?
+        };
+        self.store_anchor_info(AnchorInfo {
+            anchor_root,
+            oldest_block_slot: end_slot,
+            oldest_state_slot: end_slot,
+        })?;
+
+        let mut hot_ops = vec![];
+        let mut cold_ops = vec![];
+        let mut prev_block_root = None;
+        let genesis_block_root = self.get_cold_block_root(Slot::new(0))?;
+
+        for slot in start_slot.as_u64()..end_slot.as_u64() {
+            let slot = Slot::new(slot);
+
+            // Skip slots repeat the previous block root. The genesis block is always retained.
+            if let Some(block_root) = self.get_cold_block_root(slot)? {
+                if prev_block_root != Some(block_root) && Some(block_root) != genesis_block_root {
+                    if let Some(block) = self.hot_db.get::<BeaconBlock<E>>(&block_root)? {
+                        cold_ops.push(block.block_header().as_kv_store_op(&block_root));
+                        hot_ops.push(KeyValueStoreOp::delete(DBColumn::BeaconBlock, &block_root));
+                    }
+                    prev_block_root = Some(block_root);
+                }
+            }
+
+            if let Some(state_root) = self.get_cold_state_root(slot)? {
+                cold_ops.push(KeyValueStoreOp::delete(
+                    DBColumn::BeaconStateSummary,
+                    &state_root,
+                ));
+                cold_ops.push(KeyValueStoreOp::DeleteKey {
+                    column: DBColumn::BeaconStateRoots.into(),
+                    key: slot_key(slot).to_vec(),
+                });
+            }
+
+            if slot % self.config.slots_per_restore_point == 0 {
+                let restore_point_index = slot.as_u64() / self.config.slots_per_restore_point;
+                cold_ops.push(KeyValueStoreOp::DeleteKey {
+                    column: DBColumn::BeaconRestorePoint.into(),
+                    key: restore_point_index.to_be_bytes().to_vec(),
+                });
+            }
+        }
+
+        // Store the block headers before deleting the blocks, so they are always available.
+        self.cold_db.write_batch(cold_ops)?;
+        self.hot_db.write_batch(hot_ops)?;
+
+        self.state_cache.lock().prune(end_slot);
+
+        Ok(())
+    }
+
+    /// Returns the state with `state_root` from the state cache, if it is present.
+    fn get_cached_state<E: EthSpec>(
+        &self,
+        state_root: &Hash256,
+    ) -> Result<Option<BeaconState<E>>, Error> {
+        // Release the lock before decoding, so that concurrent readers aren't blocked.
+        let bytes = self.state_cache.lock().get(state_root);
+        bytes
+            .map(|mut bytes| BeaconState::from_store_bytes(&mut bytes))
+            .transpose()
+    }
+
+    /// Add `state` to the state cache.
+    fn cache_state<E: EthSpec>(&self, state_root: &Hash256, state: &BeaconState<E>) {
+        let bytes = state.as_store_bytes();
+        self.state_cache.lock().put(*state_root, state.slot, bytes);
+    }
+
+    /// Load the blocks between `start_slot` (exclusive) and `end_slot` (inclusive), in ascending
+    /// order of slot.
+    fn load_blocks_to_replay<E: EthSpec>(
+        &self,
+        start_slot: Slot,
+        end_slot: Slot,
+    ) -> Result<Vec<BeaconBlock<E>>, Error> {
+        let mut block_roots = (start_slot.as_u64()..=end_slot.as_u64())
+            .map(|slot| {
+                let slot = Slot::new(slot);
+                self.get_cold_block_root(slot)?
+                    .ok_or_else(|| HotColdDBError::MissingFrozenBlockRoot(slot).into())
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        // Skip slots repeat the previous block root. The first root is that of the block already
+        // applied to the restore point.
+        block_roots.dedup();
+
+        block_roots
+            .into_iter()
+            .skip(1)
+            .map(|block_root| {
+                self.get::<BeaconBlock<E>>(&block_root)?
+                    .ok_or_else(|| HotColdDBError::MissingBlock(block_root).into())
+            })
+            .collect()
+    }
+
+    /// Load the schema version from disk, returning version `0` for databases created prior to
+    /// schema versioning.
+    pub fn load_schema_version(&self) -> Result<SchemaVersion, Error> {
+        let key = Hash256::from_slice(SCHEMA_VERSION_KEY.as_bytes());
+        Ok(self.hot_db.get(&key)?.unwrap_or(SchemaVersion(0)))
+    }
+
+    /// Store the schema version to disk.
+    fn store_schema_version(&self, schema_version: SchemaVersion) -> Result<(), Error> {
+        let key = Hash256::from_slice(SCHEMA_VERSION_KEY.as_bytes());
+        self.hot_db.put(&key, &schema_version)
+    }
+
+    /// Upgrade the database from schema version `from` to `to`, one version at a time.
+    ///
+    /// The schema version is stored after each step, so an interrupted migration resumes from the
+    /// last completed step.
+    fn migrate_schema(
+        &self,
+        from: SchemaVersion,
+        to: SchemaVersion,
+        migrate_chain: impl Fn(&Self, SchemaVersion, SchemaVersion) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mut version = from;
+
+        while version < to {
+            let next = SchemaVersion(version.as_u64() + 1);
+
+            match (version, next) {
+                // Databases created prior to schema versioning store a `PersistedBeaconChain`
+                // without the head tracker, which cannot be recovered from the rest of the
+                // database. Only new databases (which have no genesis block) can be upgraded.
+                (SchemaVersion(0), SchemaVersion(1)) => {
+                    if self
+                        .hot_db
+                        .key_exists(DBColumn::BeaconBlock.into(), Hash256::zero().as_bytes())?
+                    {
+                        return Err(HotColdDBError::UnversionedDatabase.into());
+                    }
+                }
+                // The fork choice is added to the `PersistedBeaconChain`.
+                (SchemaVersion(1), SchemaVersion(2)) => migrate_chain(self, version, next)?,
+                // The eth1 voting period cache is added to the `PersistedBeaconChain`.
+                (SchemaVersion(2), SchemaVersion(3)) => migrate_chain(self, version, next)?,
+                // History pruned prior to the anchor info being stored is described by an anchor
+                // derived from the split.
+                (SchemaVersion(3), SchemaVersion(4)) => self.derive_anchor_info()?,
+                _ => return Err(HotColdDBError::MissingSchemaMigration { from: version }.into()),
+            }
+
+            self.store_schema_version(next)?;
+            version = next;
+        }
+
+        Ok(())
+    }
+
+    /// Store the anchor info of a database which pruned its history before the anchor info was
+    /// stored, along with the genesis block which was pruned with it.
+    ///
+    /// The history was pruned up to the same slot as it would be now, so the anchor is derived
+    /// from the split in the same way as `prune_history`.
+    fn derive_anchor_info(&self) -> Result<(), Error> {
+        if self.config.archive || self.load_anchor_info()?.is_some() {
+            return Ok(());
+        }
+        let split = match self.load_split()? {
+            Some(split) => split,
+            None => return Ok(()),
+        };
+
+        let end_slot = self.history_prune_slot(split.slot);
+        if end_slot == 0 {
+            return Ok(());
+        }
+
+        let anchor_root = if end_slot == split.slot {
+            split.state_root
+        } else {
+            self.get_cold_state_root(end_slot)?
+                .ok_or_else(|| HotColdDBError::MissingFrozenStateRoot(end_slot))?
+        };
+        self.store_anchor_info(AnchorInfo {
+            anchor_root,
+            oldest_block_slot: end_slot,
+            oldest_state_slot: end_slot,
+        })?;
+
+        // The genesis block is also stored under the zero hash, which was never pruned.
+        if let Some(genesis_block_root) = self.get_cold_block_root(Slot::new(0))? {
+            if !self
+                .hot_db
+                .key_exists(DBColumn::BeaconBlock.into(), genesis_block_root.as_bytes())?
+            {
+                if let Some(bytes) = self
+                    .hot_db
+                    .get_bytes(DBColumn::BeaconBlock.into(), Hash256::zero().as_bytes())?
+                {
+                    self.hot_db.put_bytes(
+                        DBColumn::BeaconBlock.into(),
+                        genesis_block_root.as_bytes(),
+                        &bytes,
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Load the anchor info from disk, returning `None` if no history has been pruned.
+    fn load_anchor_info(&self) -> Result<Option<AnchorInfo>, Error> {
+        let key = Hash256::from_slice(ANCHOR_INFO_KEY.as_bytes());
+        self.hot_db.get(&key)
+    }
+
+    /// Store the anchor info to disk and update the in-memory copy.
+    fn store_anchor_info(&self, anchor_info: AnchorInfo) -> Result<(), Error> {
+        let key = Hash256::from_slice(ANCHOR_INFO_KEY.as_bytes());
+        self.hot_db.put(&key, &anchor_info)?;
+        *self.anchor_info.write() = Some(anchor_info);
+        Ok(())
+    }
+
+    /// Load the split point from disk.
+    fn load_split(&self) -> Result<Option<Split>, Error> {
+        let key = Hash256::from_slice(SPLIT_DB_KEY.as_bytes());
+        self.hot_db.get(&key)
+    }
+}
+
+/// Returns the operations which delete a hot state, whether it is stored in full or as a diff.
+fn delete_hot_state_ops(state_root: &Hash256) -> Vec<KeyValueStoreOp> {
+    vec![
+        KeyValueStoreOp::delete(DBColumn::BeaconState, state_root),
+        KeyValueStoreOp::delete(DBColumn::BeaconStateDiff, state_root),
+    ]
+}
+
+/// Returns the key used to index slot-indexed columns of the freezer database.
+fn slot_key(slot: Slot) -> [u8; 8] {
+    slot.as_u64().to_be_bytes()
+}
+
+/// Struct for storing the split slot and state root in the database.
+#[derive(Clone, Copy, Default, Encode, Decode)]
+struct Split {
+    slot: Slot,
+    state_root: Hash256,
+}
+
+impl StoreItem for Split {
+    fn db_column() -> DBColumn {
+        DBColumn::BeaconMeta
+    }
+
+    fn as_store_bytes(&self) -> Vec<u8> {
+        self.as_ssz_bytes()
+    }
+
+    fn from_store_bytes(bytes: &mut [u8]) -> Result<Self, Error> {
+        Self::from_ssz_bytes(bytes).map_err(Into::into)
+    }
+}
+
+/// Records the slot of a state in the freezer database, allowing it to be found by root.
+#[derive(Clone, Copy, Default, Encode, Decode)]
+struct ColdStateSummary {
+    slot: Slot,
+}
+
+impl StoreItem for ColdStateSummary {
+    fn db_column() -> DBColumn {
+        DBColumn::BeaconStateSummary
+    }
+
+    fn as_store_bytes(&self) -> Vec<u8> {
+        self.as_ssz_bytes()
+    }
+
+    fn from_store_bytes(bytes: &mut [u8]) -> Result<Self, Error> {
+        Self::from_ssz_bytes(bytes).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DBBackend;
+    use state_processing::per_slot_processing;
+    use tempfile::tempdir;
+    use types::test_utils::TestingBeaconStateBuilder;
+
+    type E = MinimalEthSpec;
+
+    /// The state cache is disabled, so that states are always read from the databases.
+    fn config(slots_per_restore_point: u64, backend: DBBackend) -> StoreConfig {
+        StoreConfig {
+            slots_per_restore_point,
+            backend,
+            state_cache_size: 0,
+            ..StoreConfig::default()
+        }
+    }
+
+    #[test]
+    fn schema_version() {
+        let spec = E::default_spec();
+        let hot_dir = tempdir().unwrap();
+        let cold_dir = tempdir().unwrap();
+        let open = || {
+            HotColdDB::open(
+                hot_dir.path(),
+                cold_dir.path(),
+                config(4, DBBackend::LevelDB),
+                spec.clone(),
+                |_, _, _| Ok(()),
+            )
+        };
+
+        let store = open().expect("should open new database");
+        assert_eq!(store.load_schema_version(), Ok(CURRENT_SCHEMA_VERSION));
+
+        // Unversioned databases without a chain are migrated to the current version.
+        store
+            .hot_db
+            .key_delete(DBColumn::BeaconMeta.into(), SCHEMA_VERSION_KEY.as_bytes())
+            .unwrap();
+        assert_eq!(store.load_schema_version(), Ok(SchemaVersion(0)));
+        drop(store);
+
+        let store = open().expect("should open unversioned database");
+        assert_eq!(store.load_schema_version(), Ok(CURRENT_SCHEMA_VERSION));
+
+        // Unversioned databases holding a chain are refused.
+        store
+            .put(&Hash256::zero(), &BeaconBlock::<E>::empty(&spec))
+            .unwrap();
+        store
+            .hot_db
+            .key_delete(DBColumn::BeaconMeta.into(), SCHEMA_VERSION_KEY.as_bytes())
+            .unwrap();
+        drop(store);
+
+        assert_eq!(
+            open().err(),
+            Some(HotColdDBError::UnversionedDatabase.into())
+        );
+
+        // Databases from newer releases are refused.
+        let future_version = SchemaVersion(CURRENT_SCHEMA_VERSION.as_u64() + 1);
+        DiskDB::open(DBBackend::LevelDB, hot_dir.path())
+            .unwrap()
+            .put(
+                &Hash256::from_slice(SCHEMA_VERSION_KEY.as_bytes()),
+                &future_version,
+            )
+            .unwrap();
+
+        assert_eq!(
+            open().err(),
+            Some(
+                HotColdDBError::UnsupportedSchemaVersion {
+                    found: future_version,
+                    supported: CURRENT_SCHEMA_VERSION,
+                }
+                .into()
+            )
+        );
+    }
+
+    #[test]
+    fn prune_history_outside_horizon() {
+        let spec = E::default_spec();
+        let hot_dir = tempdir().unwrap();
+        let cold_dir = tempdir().unwrap();
+        let store = Arc::new(
+            HotColdDB::open(
+                hot_dir.path(),
+                cold_dir.path(),
+                StoreConfig {
+                    archive: false,
+                    history_horizon: 4,
+                    ..config(4, DBBackend::LevelDB)
+                },
+                spec.clone(),
+                |_, _, _| Ok(()),
+            )
+            .expect("should open store"),
+        );
+
+        let (mut state, _keypairs) =
+            TestingBeaconStateBuilder::<E>::from_deterministic_keypairs(8, &spec).build();
+        state.build_all_caches(&spec).unwrap();
+
+        let mut states = vec![];
+        for _ in 0..20 {
+            let state_root = state.canonical_root();
+            store.put_state(&state_root, &state).unwrap();
+            states.push((state_root, state.clone()));
+            per_slot_processing(&mut state, &spec).unwrap();
+        }
+
+        // Store a block as the genesis block, so that its pruning can be observed.
+        let genesis_block_root = *states[1].1.get_block_root(Slot::new(0)).unwrap();
+        let genesis_block = BeaconBlock::<E>::empty(&spec);
+        store.put(&genesis_block_root, &genesis_block).unwrap();
+
+        assert_eq!(
+            store.get_anchor_info(),
+            None,
+            "nothing should be pruned yet"
+        );
+
+        let (frozen_head_root, frozen_head) = states.last().cloned().unwrap();
+        HotColdDB::freeze_to_state(store.clone(), frozen_head_root, &frozen_head).unwrap();
+
+        // History is retained from the restore point at or before `19 - 4`.
+        let prune_slot = Slot::new(12);
+        let anchor_info = AnchorInfo {
+            anchor_root: states[prune_slot.as_usize()].0,
+            oldest_block_slot: prune_slot,
+            oldest_state_slot: prune_slot,
+        };
+        assert_eq!(store.get_anchor_info(), Some(anchor_info));
+        assert!(!anchor_info.state_available(prune_slot - 1));
+        assert!(anchor_info.block_available(prune_slot));
+        for (state_root, state) in &states {
+            let loaded = store.get_state::<E>(state_root, Some(state.slot)).unwrap();
+            if state.slot < prune_slot {
+                assert_eq!(
+                    loaded, None,
+                    "state at slot {} should be pruned",
+                    state.slot
+                );
+            } else {
+                assert_eq!(
+                    loaded.map(|state| state.canonical_root()),
+                    Some(*state_root),
+                    "state at slot {} should be retained",
+                    state.slot
+                );
+            }
+        }
+
+        assert_eq!(
+            store.get_cold_block_root(Slot::new(0)),
+            Ok(Some(genesis_block_root)),
+            "block roots should be retained"
+        );
+        assert!(anchor_info.block_available(Slot::new(0)));
+        assert_eq!(
+            store.get::<BeaconBlock<E>>(&genesis_block_root),
+            Ok(Some(genesis_block.clone())),
+            "the genesis block should be retained"
+        );
+
+        drop(store);
+        let reopen = || {
+            HotColdDB::open(
+                hot_dir.path(),
+                cold_dir.path(),
+                StoreConfig {
+                    archive: false,
+                    history_horizon: 4,
+                    ..config(4, DBBackend::LevelDB)
+                },
+                spec.clone(),
+                |_, _, _| Ok(()),
+            )
+            .expect("should reopen store")
+        };
+        let store = reopen();
+        assert_eq!(
+            store.get_anchor_info(),
+            Some(anchor_info),
+            "anchor info should persist across restarts"
+        );
+
+        // Databases pruned prior to schema version 4 have no anchor info, and only have the
+        // genesis block under the zero hash.
+        store
+            .hot_db
+            .key_delete(DBColumn::BeaconMeta.into(), ANCHOR_INFO_KEY.as_bytes())
+            .unwrap();
+        store
+            .hot_db
+            .key_delete(DBColumn::BeaconBlock.into(), genesis_block_root.as_bytes())
+            .unwrap();
+        store.put(&Hash256::zero(), &genesis_block).unwrap();
+        store.store_schema_version(SchemaVersion(3)).unwrap();
+        drop(store);
+
+        let store = reopen();
+        assert_eq!(
+            store.get_anchor_info(),
+            Some(anchor_info),
+            "anchor info should be derived from the split"
+        );
+        assert_eq!(
+            store.get::<BeaconBlock<E>>(&genesis_block_root),
+            Ok(Some(genesis_block)),
+            "the genesis block should be restored"
+        );
+    }
+
+    #[test]
+    fn state_cache() {
+        let spec = E::default_spec();
+        let hot_dir = tempdir().unwrap();
+        let cold_dir = tempdir().unwrap();
+        let store = HotColdDB::open(
+            hot_dir.path(),
+            cold_dir.path(),
+            StoreConfig {
+                state_cache_size: usize::max_value(),
+                ..config(4, DBBackend::LevelDB)
+            },
+            spec.clone(),
+            |_, _, _| Ok(()),
+        )
+        .expect("should open store");
+
+        let (mut state, _keypairs) =
+            TestingBeaconStateBuilder::<E>::from_deterministic_keypairs(8, &spec).build();
+        state.build_all_caches(&spec).unwrap();
+        let state_root = state.canonical_root();
+
+        store.put_state(&state_root, &state).unwrap();
+        assert_eq!(store.state_cache.lock().len(), 1);
+
+        // Reads are served from the cache, even once the state is removed from the database
+        // behind the cache's back.
+        store
+            .hot_db
+            .key_delete(DBColumn::BeaconState.into(), state_root.as_bytes())
+            .unwrap();
+        assert_eq!(
+            store
+                .get_state::<E>(&state_root, None)
+                .unwrap()
+                .map(|state| state.canonical_root()),
+            Some(state_root)
+        );
+
+        // Deleting the state through the store also removes it from the cache.
+        store.put_state(&state_root, &state).unwrap();
+        store.delete_state(&state_root).unwrap();
+        assert!(store.state_cache.lock().is_empty());
+        assert_eq!(store.get_state::<E>(&state_root, None), Ok(None));
+
+        // States read from the database are added to the cache.
+        per_slot_processing(&mut state, &spec).unwrap();
+        let state_root = state.canonical_root();
+        store.store_hot_state(&state_root, &state).unwrap();
+        assert!(store.state_cache.lock().is_empty());
+        assert!(store.get_state::<E>(&state_root, None).unwrap().is_some());
+        assert_eq!(store.state_cache.lock().len(), 1);
+    }
+
+    #[test]
+    fn freeze_and_reconstruct_states_leveldb() {
+        freeze_and_reconstruct_states(DBBackend::LevelDB)
+    }
+
+    #[test]
+    fn freeze_and_reconstruct_states_rocksdb() {
+        freeze_and_reconstruct_states(DBBackend::RocksDB)
+    }
+
+    #[test]
+    fn store_hot_states_as_diffs() {
+        let spec = E::default_spec();
+        let hot_dir = tempdir().unwrap();
+        let cold_dir = tempdir().unwrap();
+        let store = Arc::new(
+            HotColdDB::open(
+                hot_dir.path(),
+                cold_dir.path(),
+                config(4, DBBackend::LevelDB),
+                spec.clone(),
+                |_, _, _| Ok(()),
+            )
+            .expect("should open store"),
+        );
+
+        let (mut state, _keypairs) =
+            TestingBeaconStateBuilder::<E>::from_deterministic_keypairs(8, &spec).build();
+        state.build_all_caches(&spec).unwrap();
+
+        let mut states = vec![];
+        for _ in 0..20 {
+            let state_root = state.canonical_root();
+            store.put_state(&state_root, &state).unwrap();
+            states.push((state_root, state.clone()));
+            per_slot_processing(&mut state, &spec).unwrap();
+        }
+
+        for (state_root, state) in &states {
+            let is_epoch_start = state.slot % E::slots_per_epoch() == 0;
+            assert_eq!(
+                store.hot_db.exists::<BeaconState<E>>(state_root),
+                Ok(is_epoch_start),
+                "only the first state of each epoch should be stored in full"
+            );
+            assert_eq!(
+                store.hot_db.exists::<BeaconStateDiff<E>>(state_root),
+                Ok(!is_epoch_start),
+                "later states of each epoch should be stored as diffs"
+            );
+        }
+
+        // The base of the latest epoch is kept decoded.
+        let (latest_base_root, _) = states
+            .iter()
+            .rev()
+            .find(|(_, state)| state.slot % E::slots_per_epoch() == 0)
+            .unwrap();
+        assert!(store.get_cached_diff_base::<E>(latest_base_root).is_some());
+
+        // Freeze part-way through an epoch, so that some diffs refer to a frozen base state.
+        let (frozen_head_root, frozen_head) = states[10].clone();
+        HotColdDB::freeze_to_state(store.clone(), frozen_head_root, &frozen_head).unwrap();
+
+        for (state_root, state) in &states {
+            let loaded = store
+                .get_state::<E>(state_root, None)
+                .unwrap()
+                .expect("should load state");
+            assert_eq!(
+                loaded.canonical_root(),
+                *state_root,
+                "state at slot {} should be reassembled",
+                state.slot
+            );
+        }
+
+        let (state_root, _) = states.last().unwrap();
+        store.delete_state(state_root).unwrap();
+        assert_eq!(store.get_state::<E>(state_root, None), Ok(None));
+    }
+
+    #[test]
+    fn partial_states() {
+        let spec = E::default_spec();
+        let hot_dir = tempdir().unwrap();
+        let cold_dir = tempdir().unwrap();
+        let store = Arc::new(
+            HotColdDB::open(
+                hot_dir.path(),
+                cold_dir.path(),
+                config(4, DBBackend::LevelDB),
+                spec.clone(),
+                |_, _, _| Ok(()),
+            )
+            .expect("should open store"),
+        );
+
+        let (mut state, _keypairs) =
+            TestingBeaconStateBuilder::<E>::from_deterministic_keypairs(8, &spec).build();
+        state.build_all_caches(&spec).unwrap();
+
+        let mut states = vec![];
+        for i in 0..20 {
+            state.balances[i % 8] += 1;
+            let state_root = state.canonical_root();
+            store.put_state(&state_root, &state).unwrap();
+            states.push((state_root, state.clone()));
+            per_slot_processing(&mut state, &spec).unwrap();
+        }
+
+        // Freeze part-way through an epoch, so that some diffs refer to a frozen base state.
+        let (frozen_head_root, frozen_head) = states[10].clone();
+        HotColdDB::freeze_to_state(store.clone(), frozen_head_root, &frozen_head).unwrap();
+        store.clear_caches();
+
+        for (state_root, state) in &states {
+            for slot in vec![None, Some(state.slot)] {
+                assert_eq!(
+                    store.get_partial_state::<E>(state_root, slot),
+                    Ok(Some(PartialBeaconState::from_state(state))),
+                    "partial state at slot {} should be loaded",
+                    state.slot
+                );
+            }
+        }
+
+        assert_eq!(
+            store.get_partial_state::<E>(&Hash256::repeat_byte(42), None),
+            Ok(None),
+            "unknown state should not be found"
+        );
+    }
+
+    fn freeze_and_reconstruct_states(backend: DBBackend) {
+        let spec = E::default_spec();
+        let hot_dir = tempdir().unwrap();
+        let cold_dir = tempdir().unwrap();
+        let open = || {
+            HotColdDB::open(
+                hot_dir.path(),
+                cold_dir.path(),
+                config(4, backend),
+                spec.clone(),
+                |_, _, _| Ok(()),
+            )
+            .expect("should open store")
+        };
+        let store = Arc::new(open());
+
+        let (mut state, _keypairs) =
+            TestingBeaconStateBuilder::<E>::from_deterministic_keypairs(8, &spec).build();
+        state.build_all_caches(&spec).unwrap();
+
+        let mut states = vec![];
+        for _ in 0..11 {
+            let state_root = state.canonical_root();
+            store.put_state(&state_root, &state).unwrap();
+            states.push((state_root, state.clone()));
+            per_slot_processing(&mut state, &spec).unwrap();
+        }
+
+        let (frozen_head_root, frozen_head) = states.last().cloned().unwrap();
+        HotColdDB::freeze_to_state(store.clone(), frozen_head_root, &frozen_head).unwrap();
+
+        assert_eq!(store.get_split_slot(), frozen_head.slot);
+
+        for (state_root, state) in &states {
+            for slot in vec![None, Some(state.slot)] {
+                let loaded = store
+                    .get_state::<E>(state_root, slot)
+                    .unwrap()
+                    .expect("should load state");
+                assert_eq!(
+                    loaded.canonical_root(),
+                    *state_root,
+                    "state at slot {} should be reconstructed",
+                    state.slot
+                );
+            }
+        }
+
+        for (state_root, state) in &states {
+            let loaded = store
+                .get_state_at_slot::<E>(state.slot)
+                .unwrap()
+                .expect("should load finalized state by slot");
+            assert_eq!(
+                loaded.canonical_root(),
+                *state_root,
+                "state at slot {} should be found by slot",
+                state.slot
+            );
+        }
+        assert_eq!(
+            store.get_state_at_slot::<E>(frozen_head.slot + 1),
+            Ok(None),
+            "should not find non-finalized state by slot"
+        );
+
+        assert_eq!(
+            store.get_state::<E>(&Hash256::repeat_byte(42), Some(Slot::new(1))),
+            Ok(None),
+            "unknown frozen state should not be found"
+        );
+        assert!(
+            HotColdDB::freeze_to_state(store.clone(), states[0].0, &states[0].1).is_err(),
+            "should not move the split backwards"
+        );
+
+        drop(store);
+        assert_eq!(
+            open().get_split_slot(),
+            frozen_head.slot,
+            "split should persist across restarts"
+        );
+    }
+}
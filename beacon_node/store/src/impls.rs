@@ -32,3 +32,17 @@ impl<T: EthSpec> StoreItem for BeaconBlock<T> {
         result
     }
 }
+
+impl StoreItem for BeaconBlockHeader {
+    fn db_column() -> DBColumn {
+        DBColumn::BeaconBlockHeader
+    }
+
+    fn as_store_bytes(&self) -> Vec<u8> {
+        self.as_ssz_bytes()
+    }
+
+    fn from_store_bytes(bytes: &mut [u8]) -> Result<Self, Error> {
+        Self::from_ssz_bytes(bytes).map_err(Into::into)
+    }
+}
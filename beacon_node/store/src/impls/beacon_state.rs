@@ -1,5 +1,5 @@
 use crate::*;
-use ssz::{Decode, DecodeError, Encode};
+use ssz::{Decode, DecodeError, Encode, SszDecoderBuilder};
 use ssz_derive::{Decode, Encode};
 use std::convert::TryInto;
 use types::beacon_state::{BeaconTreeHashCache, CommitteeCache, CACHED_EPOCHS};
@@ -86,3 +86,20 @@ impl<T: EthSpec> StoreItem for BeaconState<T> {
         result
     }
 }
+
+impl PartialBeaconState {
+    /// Decodes the validators and balances of a `BeaconState<E>` from the bytes it was stored
+    /// with, without decoding its caches or any other fields.
+    pub fn from_store_bytes<E: EthSpec>(bytes: &[u8]) -> Result<Self, Error> {
+        let mut builder = SszDecoderBuilder::new(bytes);
+
+        builder.register_type::<Vec<u8>>()?;
+        builder.register_type::<Vec<Vec<u8>>>()?;
+        builder.register_type::<Vec<u8>>()?;
+
+        let mut decoder = builder.build()?;
+        let state_bytes: Vec<u8> = decoder.decode_next()?;
+
+        Ok(Self::from_state_ssz_bytes::<E>(&state_bytes)?)
+    }
+}
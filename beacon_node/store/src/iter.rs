@@ -18,7 +18,9 @@ impl<'a, U: Store, E: EthSpec> AncestorIter<U, BlockRootsIterator<'a, E, U>> for
     /// Iterates across all available prior block roots of `self`, starting at the most recent and ending
     /// at genesis.
     fn try_iter_ancestor_roots(&self, store: Arc<U>) -> Option<BlockRootsIterator<'a, E, U>> {
-        let state = store.get::<BeaconState<E>>(&self.state_root).ok()??;
+        let state = store
+            .get_state::<E>(&self.state_root, Some(self.slot))
+            .ok()??;
 
         Some(BlockRootsIterator::owned(store, state))
     }
@@ -74,8 +76,10 @@ impl<'a, T: EthSpec, U: Store> Iterator for StateRootsIterator<'a, T, U> {
                 // Read a `BeaconState` from the store that has access to prior historical root.
                 let beacon_state: BeaconState<T> = {
                     let new_state_root = self.beacon_state.get_oldest_state_root().ok()?;
+                    let new_slot =
+                        self.beacon_state.slot - Slot::from(T::slots_per_historical_root());
 
-                    self.store.get(&new_state_root).ok()?
+                    self.store.get_state(&new_state_root, Some(new_slot)).ok()?
                 }?;
 
                 self.beacon_state = Cow::Owned(beacon_state);
@@ -172,8 +176,10 @@ impl<'a, T: EthSpec, U: Store> Iterator for BlockRootsIterator<'a, T, U> {
                 let beacon_state: BeaconState<T> = {
                     // Load the earliest state from disk.
                     let new_state_root = self.beacon_state.get_oldest_state_root().ok()?;
+                    let new_slot =
+                        self.beacon_state.slot - Slot::from(T::slots_per_historical_root());
 
-                    self.store.get(&new_state_root).ok()?
+                    self.store.get_state(&new_state_root, Some(new_slot)).ok()?
                 }?;
 
                 self.beacon_state = Cow::Owned(beacon_state);
@@ -225,7 +231,7 @@ mod test {
 
         let state_a_root = hashes.next().unwrap();
         state_b.state_roots[0] = state_a_root;
-        store.put(&state_a_root, &state_a).unwrap();
+        store.put_state(&state_a_root, &state_a).unwrap();
 
         let iter = BlockRootsIterator::new(store.clone(), &state_b);
 
@@ -273,8 +279,8 @@ mod test {
         let state_a_root = Hash256::from_low_u64_be(slots_per_historical_root as u64);
         let state_b_root = Hash256::from_low_u64_be(slots_per_historical_root as u64 * 2);
 
-        store.put(&state_a_root, &state_a).unwrap();
-        store.put(&state_b_root, &state_b).unwrap();
+        store.put_state(&state_a_root, &state_a).unwrap();
+        store.put_state(&state_b_root, &state_b).unwrap();
 
         let iter = StateRootsIterator::new(store.clone(), &state_b);
 
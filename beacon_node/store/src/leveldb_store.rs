@@ -1,9 +1,12 @@
 use super::*;
 use crate::metrics;
 use db_key::Key;
+use leveldb::batch::{Batch, Writebatch};
+use leveldb::database::compaction::Compaction;
 use leveldb::database::kv::KV;
 use leveldb::database::Database;
 use leveldb::error::Error as LevelDBError;
+use leveldb::iterator::{Iterable, LevelDBIterator};
 use leveldb::options::{Options, ReadOptions, WriteOptions};
 use std::path::Path;
 use std::sync::Arc;
@@ -58,7 +61,7 @@ impl Key for BytesKey {
     }
 }
 
-impl Store for LevelDB {
+impl KeyValueStore for LevelDB {
     /// Retrieve some bytes in `column` with `key`.
     fn get_bytes(&self, col: &str, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
         let column_key = Self::get_key_for_col(col, key);
@@ -111,8 +114,62 @@ impl Store for LevelDB {
             .delete(self.write_options(), column_key)
             .map_err(Into::into)
     }
+
+    /// Apply all of the operations in `batch` as a single leveldb write batch.
+    fn write_batch(&self, batch: Vec<KeyValueStoreOp>) -> Result<(), Error> {
+        let mut leveldb_batch = Writebatch::new();
+
+        for op in batch {
+            match op {
+                KeyValueStoreOp::PutKeyValue { column, key, value } => {
+                    metrics::inc_counter(&metrics::DISK_DB_WRITE_COUNT);
+                    metrics::inc_counter_by(&metrics::DISK_DB_WRITE_BYTES, value.len() as i64);
+
+                    leveldb_batch.put(Self::get_key_for_col(column, &key), &value);
+                }
+                KeyValueStoreOp::DeleteKey { column, key } => {
+                    metrics::inc_counter(&metrics::DISK_DB_DELETE_COUNT);
+
+                    leveldb_batch.delete(Self::get_key_for_col(column, &key));
+                }
+            }
+        }
+
+        self.db
+            .write(self.write_options(), &leveldb_batch)
+            .map_err(Into::into)
+    }
+
+    /// Iterate over the keys with the prefix of `column`, which are stored contiguously.
+    fn iter_column<'a>(&'a self, column: &str) -> Result<ColumnIter<'a>, Error> {
+        let column_len = column.len();
+        let prefix = column.as_bytes().to_vec();
+
+        // Keys are sorted, so the keys of the column follow the first key with its prefix.
+        let iter = self.db.iter(self.read_options());
+        iter.seek(&BytesKey::from_u8(&prefix));
+
+        Ok(Box::new(
+            iter.take_while(move |(key, _)| key.key.starts_with(&prefix))
+                .map(move |(key, value)| (key.key[column_len..].to_vec(), value)),
+        ))
+    }
+
+    /// Compact all values in the database.
+    fn compact(&self) -> Result<(), Error> {
+        // All keys are prefixed with an ASCII column name, so `[]..[255]` spans every column.
+        let start_key = BytesKey::from_u8(&[]);
+        let end_key = BytesKey::from_u8(&[std::u8::MAX]);
+
+        metrics::inc_counter(&metrics::DISK_DB_COMPACTION_COUNT);
+
+        self.db.compact(&start_key, &end_key);
+        Ok(())
+    }
 }
 
+impl Store for LevelDB {}
+
 impl From<LevelDBError> for Error {
     fn from(e: LevelDBError) -> Error {
         Error::DBError {
@@ -2,7 +2,9 @@
 //!
 //! Provides the following stores:
 //!
-//! - `DiskStore`: an on-disk store backed by leveldb. Used in production.
+//! - `DiskStore`: an on-disk store backed by leveldb or rocksdb (see `DBBackend`). Used in
+//! production.
+//! - `SimpleDiskStore`: an on-disk store backed by leveldb, without a freezer database.
 //! - `MemoryStore`: an in-memory store backed by a hash-map. Used for testing.
 //!
 //! Provides a simple API for storing/retrieving all types that sometimes needs type-hints. See
@@ -11,26 +13,118 @@
 extern crate lazy_static;
 
 mod block_at_slot;
+mod config;
+mod disk_db;
 mod errors;
+mod hot_cold_store;
 mod impls;
 mod leveldb_store;
 mod memory_store;
+mod metadata;
 mod metrics;
+mod partial_state;
+mod rocksdb_store;
+mod state_cache;
+mod state_diff;
 
 pub mod iter;
 
-pub use self::leveldb_store::LevelDB as DiskStore;
+pub use self::config::{
+    StoreConfig, DEFAULT_HISTORY_HORIZON, DEFAULT_SLOTS_PER_RESTORE_POINT, DEFAULT_STATE_CACHE_SIZE,
+};
+pub use self::disk_db::{DBBackend, DiskDB};
+pub use self::hot_cold_store::{HotColdDB as DiskStore, HotColdDBError};
+pub use self::leveldb_store::LevelDB as SimpleDiskStore;
 pub use self::memory_store::MemoryStore;
+pub use self::metadata::{
+    AnchorInfo, SchemaVersion, ANCHOR_INFO_KEY, CURRENT_SCHEMA_VERSION, SCHEMA_VERSION_KEY,
+};
+pub use self::partial_state::PartialBeaconState;
+pub use self::rocksdb_store::RocksDB;
+pub use self::state_cache::StateCache;
+pub use self::state_diff::BeaconStateDiff;
 pub use errors::Error;
 pub use metrics::scrape_for_metrics;
 pub use types::*;
 
+use std::sync::Arc;
+
+/// A key-value database with support for columns.
+///
+/// A simple column implementation might involve prefixing a key with some bytes unique to each
+/// column, whilst other databases (e.g., rocksdb) provide native column families.
+pub trait KeyValueStore: Sync + Send {
+    /// Retrieve some bytes in `column` with `key`.
+    fn get_bytes(&self, column: &str, key: &[u8]) -> Result<Option<Vec<u8>>, Error>;
+
+    /// Store some `value` in `column`, indexed with `key`.
+    fn put_bytes(&self, column: &str, key: &[u8], value: &[u8]) -> Result<(), Error>;
+
+    /// Return `true` if `key` exists in `column`.
+    fn key_exists(&self, column: &str, key: &[u8]) -> Result<bool, Error>;
+
+    /// Removes `key` from `column`.
+    fn key_delete(&self, column: &str, key: &[u8]) -> Result<(), Error>;
+
+    /// Apply all of the operations in `batch`, such that either all or none of them are applied.
+    fn write_batch(&self, batch: Vec<KeyValueStoreOp>) -> Result<(), Error>;
+
+    /// Iterate over every key and value in `column`, in no particular order.
+    ///
+    /// The returned keys do not include any column prefix.
+    fn iter_column<'a>(&'a self, column: &str) -> Result<ColumnIter<'a>, Error>;
+
+    /// Compact the underlying database, reclaiming the space used by deleted and overwritten
+    /// values.
+    ///
+    /// The default implementation is a no-op, suitable for stores without compaction.
+    fn compact(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// An iterator over the keys and values of a single column of a `KeyValueStore`.
+pub type ColumnIter<'a> = Box<dyn Iterator<Item = (Vec<u8>, Vec<u8>)> + 'a>;
+
+/// A single operation on a `KeyValueStore`, for inclusion in an atomic batch.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyValueStoreOp {
+    PutKeyValue {
+        column: &'static str,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    },
+    DeleteKey {
+        column: &'static str,
+        key: Vec<u8>,
+    },
+}
+
+impl KeyValueStoreOp {
+    /// Returns an operation which deletes `key` from `column`.
+    pub fn delete(column: DBColumn, key: &Hash256) -> Self {
+        KeyValueStoreOp::DeleteKey {
+            column: column.into(),
+            key: key.as_bytes().to_vec(),
+        }
+    }
+}
+
+/// A typed operation on a `Store`, for inclusion in an atomic batch (see `Store::do_atomically`).
+pub enum StoreOp<'a, E: EthSpec> {
+    PutBlock(Hash256, &'a BeaconBlock<E>),
+    PutState(Hash256, &'a BeaconState<E>),
+    DeleteBlock(Hash256),
+    DeleteState(Hash256),
+    /// Any other operation, e.g., storing some other `StoreItem` (see `StoreItem::as_kv_store_op`).
+    KeyValueOp(KeyValueStoreOp),
+}
+
 /// An object capable of storing and retrieving objects implementing `StoreItem`.
 ///
-/// A `Store` is fundamentally backed by a key-value database, however it provides support for
-/// columns. A simple column implementation might involve prefixing a key with some bytes unique to
-/// each column.
-pub trait Store: Sync + Send + Sized {
+/// A `Store` is fundamentally backed by a `KeyValueStore`, upon which it provides typed access to
+/// blocks, states and other items.
+pub trait Store: KeyValueStore + Sized {
     /// Store an item in `Self`.
     fn put(&self, key: &Hash256, item: &impl StoreItem) -> Result<(), Error> {
         item.db_put(self, key)
@@ -51,6 +145,92 @@ pub trait Store: Sync + Send + Sized {
         I::db_delete(self, key)
     }
 
+    /// Store a state in the store.
+    fn put_state<E: EthSpec>(
+        &self,
+        state_root: &Hash256,
+        state: &BeaconState<E>,
+    ) -> Result<(), Error> {
+        self.put(state_root, state)
+    }
+
+    /// Fetch a state from the store.
+    ///
+    /// The `slot` of the state should be provided if it is known, since it allows the state to
+    /// be located more efficiently.
+    fn get_state<E: EthSpec>(
+        &self,
+        state_root: &Hash256,
+        _slot: Option<Slot>,
+    ) -> Result<Option<BeaconState<E>>, Error> {
+        self.get(state_root)
+    }
+
+    /// Fetch the validators and balances of a state from the store, without decoding the rest of
+    /// the state.
+    ///
+    /// The `slot` of the state should be provided if it is known, since it allows the state to
+    /// be located more efficiently.
+    fn get_partial_state<E: EthSpec>(
+        &self,
+        state_root: &Hash256,
+        _slot: Option<Slot>,
+    ) -> Result<Option<PartialBeaconState>, Error> {
+        self.get_bytes(DBColumn::BeaconState.into(), state_root.as_bytes())?
+            .map(|bytes| PartialBeaconState::from_store_bytes::<E>(&bytes))
+            .transpose()
+    }
+
+    /// Delete a state from the store.
+    fn delete_state(&self, state_root: &Hash256) -> Result<(), Error> {
+        self.key_delete(DBColumn::BeaconState.into(), state_root.as_bytes())
+    }
+
+    /// Apply all of the operations in `batch` atomically, such that a crash can never leave the
+    /// database containing only some of them (e.g., a block without its state).
+    fn do_atomically<E: EthSpec>(&self, batch: Vec<StoreOp<E>>) -> Result<(), Error> {
+        let kv_batch = batch
+            .into_iter()
+            .map(|op| match op {
+                StoreOp::PutBlock(block_root, block) => block.as_kv_store_op(&block_root),
+                StoreOp::PutState(state_root, state) => state.as_kv_store_op(&state_root),
+                StoreOp::DeleteBlock(block_root) => {
+                    KeyValueStoreOp::delete(DBColumn::BeaconBlock, &block_root)
+                }
+                StoreOp::DeleteState(state_root) => {
+                    KeyValueStoreOp::delete(DBColumn::BeaconState, &state_root)
+                }
+                StoreOp::KeyValueOp(op) => op,
+            })
+            .collect();
+
+        self.write_batch(kv_batch)
+    }
+
+    /// Move all states prior to `frozen_head` into long-term storage, if the store supports it.
+    ///
+    /// The default implementation is a no-op, suitable for stores without a freezer database.
+    fn freeze_to_state<E: EthSpec>(
+        _store: Arc<Self>,
+        _frozen_head_root: Hash256,
+        _frozen_head: &BeaconState<E>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Returns the oldest blocks and states which are available, or `None` if the store holds the
+    /// complete history back to genesis.
+    ///
+    /// The default implementation returns `None`, suitable for stores which never prune history.
+    fn get_anchor_info(&self) -> Option<AnchorInfo> {
+        None
+    }
+
+    /// Drop the in-memory caches of the store, which are refilled as items are read.
+    ///
+    /// The default implementation is a no-op, suitable for stores without caches.
+    fn clear_caches(&self) {}
+
     /// Given the root of an existing block in the store (`start_block_root`), return a parent
     /// block with the specified `slot`.
     ///
@@ -63,34 +243,60 @@ pub trait Store: Sync + Send + Sized {
     ) -> Result<Option<(Hash256, BeaconBlock<E>)>, Error> {
         block_at_slot::get_block_at_preceeding_slot::<_, E>(self, slot, start_block_root)
     }
-
-    /// Retrieve some bytes in `column` with `key`.
-    fn get_bytes(&self, column: &str, key: &[u8]) -> Result<Option<Vec<u8>>, Error>;
-
-    /// Store some `value` in `column`, indexed with `key`.
-    fn put_bytes(&self, column: &str, key: &[u8], value: &[u8]) -> Result<(), Error>;
-
-    /// Return `true` if `key` exists in `column`.
-    fn key_exists(&self, column: &str, key: &[u8]) -> Result<bool, Error>;
-
-    /// Removes `key` from `column`.
-    fn key_delete(&self, column: &str, key: &[u8]) -> Result<(), Error>;
 }
 
 /// A unique column identifier.
 pub enum DBColumn {
+    BeaconMeta,
     BeaconBlock,
     BeaconState,
     BeaconChain,
+    /// Full states stored every `slots_per_restore_point` slots in the freezer database.
+    BeaconRestorePoint,
+    /// The slot of each state in the freezer database, keyed by state root.
+    BeaconStateSummary,
+    /// The block root of each slot in the freezer database, keyed by slot.
+    BeaconBlockRoots,
+    /// The state root of each slot in the freezer database, keyed by slot.
+    BeaconStateRoots,
+    /// Hot states stored as a diff against an earlier state, keyed by state root.
+    BeaconStateDiff,
+    /// The headers of blocks pruned from the freezer database, keyed by block root.
+    BeaconBlockHeader,
+}
+
+impl DBColumn {
+    /// Returns every column, e.g., so that a column family may be created for each.
+    pub fn all() -> Vec<DBColumn> {
+        vec![
+            DBColumn::BeaconMeta,
+            DBColumn::BeaconBlock,
+            DBColumn::BeaconState,
+            DBColumn::BeaconChain,
+            DBColumn::BeaconRestorePoint,
+            DBColumn::BeaconStateSummary,
+            DBColumn::BeaconBlockRoots,
+            DBColumn::BeaconStateRoots,
+            DBColumn::BeaconStateDiff,
+            DBColumn::BeaconBlockHeader,
+        ]
+    }
 }
 
 impl<'a> Into<&'a str> for DBColumn {
     /// Returns a `&str` that can be used for keying a key-value data base.
     fn into(self) -> &'a str {
         match self {
+            DBColumn::BeaconMeta => &"bma",
             DBColumn::BeaconBlock => &"blk",
             DBColumn::BeaconState => &"ste",
             DBColumn::BeaconChain => &"bch",
+            DBColumn::BeaconRestorePoint => &"brp",
+            DBColumn::BeaconStateSummary => &"bss",
+            DBColumn::BeaconBlockRoots => &"bbr",
+            DBColumn::BeaconStateRoots => &"bsr",
+            DBColumn::BeaconStateDiff => &"bsd",
+            DBColumn::BeaconBlockHeader => &"bbh",
         }
     }
 }
@@ -109,6 +315,15 @@ pub trait StoreItem: Sized {
     /// De-serialize `self` from bytes.
     fn from_store_bytes(bytes: &mut [u8]) -> Result<Self, Error>;
 
+    /// Returns an operation which stores `self` with `key`, for inclusion in an atomic batch.
+    fn as_kv_store_op(&self, key: &Hash256) -> KeyValueStoreOp {
+        KeyValueStoreOp::PutKeyValue {
+            column: Self::db_column().into(),
+            key: key.as_bytes().to_vec(),
+            value: self.as_store_bytes(),
+        }
+    }
+
     /// Store `self`.
     fn db_put(&self, store: &impl Store, key: &Hash256) -> Result<(), Error> {
         let column = Self::db_column().into();
@@ -194,20 +409,62 @@ mod tests {
         assert_eq!(store.get::<StorableThing>(&key), Ok(None));
     }
 
+    fn test_batch(store: impl Store) {
+        let existing_key = Hash256::random();
+        let new_key = Hash256::random();
+        let item = StorableThing { a: 1, b: 42 };
+
+        store.put(&existing_key, &item).unwrap();
+
+        store
+            .do_atomically::<MinimalEthSpec>(vec![
+                StoreOp::KeyValueOp(KeyValueStoreOp::delete(
+                    StorableThing::db_column(),
+                    &existing_key,
+                )),
+                StoreOp::KeyValueOp(item.as_kv_store_op(&new_key)),
+            ])
+            .unwrap();
+
+        assert_eq!(store.get::<StorableThing>(&existing_key), Ok(None));
+
+        let entries = store
+            .iter_column(StorableThing::db_column().into())
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            vec![(new_key.as_bytes().to_vec(), item.as_store_bytes())]
+        );
+        assert_eq!(store.get::<StorableThing>(&new_key), Ok(Some(item)));
+    }
+
     #[test]
     fn diskdb() {
         let dir = tempdir().unwrap();
         let path = dir.path();
-        let store = DiskStore::open(&path).unwrap();
+        let store = SimpleDiskStore::open(&path).unwrap();
+
+        test_impl(store.clone());
+        test_batch(store);
+    }
+
+    #[test]
+    fn rocksdb() {
+        let dir = tempdir().unwrap();
+        let path = dir.path();
+        let store = RocksDB::open(&path).unwrap();
 
-        test_impl(store);
+        test_impl(store.clone());
+        test_batch(store);
     }
 
     #[test]
     fn memorydb() {
         let store = MemoryStore::open();
 
-        test_impl(store);
+        test_impl(store.clone());
+        test_batch(store);
     }
 
     #[test]
@@ -1,4 +1,4 @@
-use super::{Error, Store};
+use super::{ColumnIter, Error, KeyValueStore, KeyValueStoreOp, Store};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -28,7 +28,7 @@ impl MemoryStore {
     }
 }
 
-impl Store for MemoryStore {
+impl KeyValueStore for MemoryStore {
     /// Get the value of some key from the database. Returns `None` if the key does not exist.
     fn get_bytes(&self, col: &str, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
         let column_key = MemoryStore::get_key_for_col(col, key);
@@ -64,4 +64,38 @@ impl Store for MemoryStore {
 
         Ok(())
     }
+
+    /// Iterate over a copy of the entries in `column`, so that the lock is not held.
+    fn iter_column<'a>(&'a self, column: &str) -> Result<ColumnIter<'a>, Error> {
+        let prefix = column.as_bytes();
+        let entries = self
+            .db
+            .read()
+            .iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key[prefix.len()..].to_vec(), value.clone()))
+            .collect::<Vec<_>>();
+
+        Ok(Box::new(entries.into_iter()))
+    }
+
+    /// Apply all of the operations in `batch` whilst holding the write lock.
+    fn write_batch(&self, batch: Vec<KeyValueStoreOp>) -> Result<(), Error> {
+        let mut db = self.db.write();
+
+        for op in batch {
+            match op {
+                KeyValueStoreOp::PutKeyValue { column, key, value } => {
+                    db.insert(MemoryStore::get_key_for_col(column, &key), value);
+                }
+                KeyValueStoreOp::DeleteKey { column, key } => {
+                    db.remove(&MemoryStore::get_key_for_col(column, &key));
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
+
+impl Store for MemoryStore {}
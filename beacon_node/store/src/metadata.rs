@@ -0,0 +1,89 @@
+use crate::{DBColumn, Error, StoreItem};
+use ssz::{Decode, Encode};
+use ssz_derive::{Decode, Encode};
+use types::{Hash256, Slot};
+
+/// The version of the on-disk database layout supported by this release.
+///
+/// Increment this (and add a migration to `HotColdDB::migrate_schema`) whenever a change is made
+/// which makes the layout incompatible with existing databases. Items stored by the beacon chain
+/// are migrated by its `schema_change` module.
+pub const CURRENT_SCHEMA_VERSION: SchemaVersion = SchemaVersion(4);
+
+/// 32-byte key for accessing the `SchemaVersion` of the database.
+pub const SCHEMA_VERSION_KEY: &str = "SCHEMAVERSIONSCHEMAVERSIONSCHEMA";
+
+/// The version of the on-disk database layout.
+///
+/// Databases created prior to the introduction of schema versioning do not store a version, and
+/// are considered to be version `0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SchemaVersion(pub u64);
+
+impl SchemaVersion {
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl StoreItem for SchemaVersion {
+    fn db_column() -> DBColumn {
+        DBColumn::BeaconMeta
+    }
+
+    fn as_store_bytes(&self) -> Vec<u8> {
+        self.0.as_ssz_bytes()
+    }
+
+    fn from_store_bytes(bytes: &mut [u8]) -> Result<Self, Error> {
+        Ok(SchemaVersion(u64::from_ssz_bytes(bytes)?))
+    }
+}
+
+/// 32-byte key for accessing the `AnchorInfo` of the database.
+pub const ANCHOR_INFO_KEY: &str = "ANCHORINFOANCHORINFOANCHORINFOAN";
+
+/// The oldest blocks and states which are available in the database.
+///
+/// Only stored once history has been pruned (see `StoreConfig::archive`). A database without an
+/// `AnchorInfo` holds the complete history back to genesis.
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode)]
+pub struct AnchorInfo {
+    /// The root of the state at `oldest_state_slot`, from which every later frozen state can be
+    /// reconstructed.
+    pub anchor_root: Hash256,
+    /// Blocks with slots prior to this slot have been pruned, leaving only their headers.
+    ///
+    /// A backfill of historical blocks should stop at this slot.
+    pub oldest_block_slot: Slot,
+    /// States with slots prior to this slot have been pruned.
+    pub oldest_state_slot: Slot,
+}
+
+impl AnchorInfo {
+    /// Returns `true` if the block at `slot` has not been pruned.
+    ///
+    /// The genesis block is never pruned.
+    pub fn block_available(&self, slot: Slot) -> bool {
+        slot == 0 || slot >= self.oldest_block_slot
+    }
+
+    /// Returns `true` if the state at `slot` has not been pruned.
+    pub fn state_available(&self, slot: Slot) -> bool {
+        slot >= self.oldest_state_slot
+    }
+}
+
+impl StoreItem for AnchorInfo {
+    fn db_column() -> DBColumn {
+        DBColumn::BeaconMeta
+    }
+
+    fn as_store_bytes(&self) -> Vec<u8> {
+        self.as_ssz_bytes()
+    }
+
+    fn from_store_bytes(bytes: &mut [u8]) -> Result<Self, Error> {
+        Self::from_ssz_bytes(bytes).map_err(Into::into)
+    }
+}
@@ -33,6 +33,36 @@ lazy_static! {
         "store_disk_db_delete_count_total",
         "Total number of deletions from the on-disk DB"
     );
+    pub static ref DISK_DB_COMPACTION_COUNT: Result<IntCounter> = try_create_int_counter(
+        "store_disk_db_compaction_count_total",
+        "Total number of manual compactions of the on-disk DB"
+    );
+    /*
+     * Freezer DB
+     */
+    pub static ref FREEZER_STATE_RECONSTRUCTION_TIMES: Result<Histogram> = try_create_histogram(
+        "store_freezer_state_reconstruction_seconds",
+        "Time taken to reconstruct a state from a restore point in the freezer DB"
+    );
+    /*
+     * State cache
+     */
+    pub static ref STATE_CACHE_HIT_COUNT: Result<IntCounter> = try_create_int_counter(
+        "store_state_cache_hit_total",
+        "Number of state reads served from the state cache"
+    );
+    pub static ref STATE_CACHE_MISS_COUNT: Result<IntCounter> = try_create_int_counter(
+        "store_state_cache_miss_total",
+        "Number of state reads which missed the state cache"
+    );
+    pub static ref STATE_CACHE_SIZE: Result<IntGauge> = try_create_int_gauge(
+        "store_state_cache_size",
+        "Total size of the states in the state cache (bytes)"
+    );
+    pub static ref STATE_CACHE_LEN: Result<IntGauge> = try_create_int_gauge(
+        "store_state_cache_len",
+        "Number of states in the state cache"
+    );
     /*
      * Beacon State
      */
@@ -0,0 +1,113 @@
+use crate::*;
+use ssz::{DecodeError, SszDecoderBuilder};
+
+/// The validator registry and balances of a `BeaconState`, along with its slot.
+///
+/// Decoded directly from the SSZ bytes of a stored state, without decoding its other fields, so
+/// that the validators of a historical state may be served without materializing the entire
+/// state.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PartialBeaconState {
+    pub slot: Slot,
+    pub validators: Vec<Validator>,
+    pub balances: Vec<u64>,
+}
+
+impl PartialBeaconState {
+    /// Copies the required fields from a full `state`.
+    pub fn from_state<E: EthSpec>(state: &BeaconState<E>) -> Self {
+        Self {
+            slot: state.slot,
+            validators: state.validators.to_vec(),
+            balances: state.balances.to_vec(),
+        }
+    }
+
+    /// Decodes the required fields from the SSZ bytes of a `BeaconState<E>`, skipping all others.
+    pub fn from_state_ssz_bytes<E: EthSpec>(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut builder = SszDecoderBuilder::new(bytes);
+
+        builder.register_type::<u64>()?;
+        builder.register_type::<Slot>()?;
+        builder.register_type::<Fork>()?;
+        builder.register_type::<BeaconBlockHeader>()?;
+        builder.register_type::<FixedVector<Hash256, E::SlotsPerHistoricalRoot>>()?;
+        builder.register_type::<FixedVector<Hash256, E::SlotsPerHistoricalRoot>>()?;
+        builder.register_type::<VariableList<Hash256, E::HistoricalRootsLimit>>()?;
+        builder.register_type::<Eth1Data>()?;
+        builder.register_type::<VariableList<Eth1Data, E::SlotsPerEth1VotingPeriod>>()?;
+        builder.register_type::<u64>()?;
+        builder.register_type::<VariableList<Validator, E::ValidatorRegistryLimit>>()?;
+        builder.register_type::<VariableList<u64, E::ValidatorRegistryLimit>>()?;
+        builder.register_type::<FixedVector<Hash256, E::EpochsPerHistoricalVector>>()?;
+        builder.register_type::<FixedVector<u64, E::EpochsPerSlashingsVector>>()?;
+        builder
+            .register_type::<VariableList<PendingAttestation<E>, E::MaxPendingAttestations>>()?;
+        builder
+            .register_type::<VariableList<PendingAttestation<E>, E::MaxPendingAttestations>>()?;
+        builder.register_type::<BitVector<E::JustificationBitsLength>>()?;
+        builder.register_type::<Checkpoint>()?;
+        builder.register_type::<Checkpoint>()?;
+        builder.register_type::<Checkpoint>()?;
+
+        let mut decoder = builder.build()?;
+
+        // genesis_time
+        decoder.skip_next();
+        let slot = decoder.decode_next()?;
+        // fork, latest_block_header, block_roots, state_roots, historical_roots, eth1_data,
+        // eth1_data_votes, eth1_deposit_index
+        for _ in 0..8 {
+            decoder.skip_next();
+        }
+        let validators: VariableList<Validator, E::ValidatorRegistryLimit> =
+            decoder.decode_next()?;
+        let balances: VariableList<u64, E::ValidatorRegistryLimit> = decoder.decode_next()?;
+
+        Ok(Self {
+            slot,
+            validators: validators.into(),
+            balances: balances.into(),
+        })
+    }
+
+    /// Returns the epoch of the state.
+    pub fn current_epoch<E: EthSpec>(&self) -> Epoch {
+        self.slot.epoch(E::slots_per_epoch())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ssz::Encode;
+    use types::test_utils::TestingBeaconStateBuilder;
+
+    type E = MinimalEthSpec;
+
+    fn state() -> BeaconState<E> {
+        let spec = E::default_spec();
+        let (mut state, _keypairs) =
+            TestingBeaconStateBuilder::<E>::from_deterministic_keypairs(8, &spec).build();
+        state.slot = Slot::new(42);
+        state.balances[3] += 1;
+        state.validators[5].slashed = true;
+        state
+    }
+
+    #[test]
+    fn decodes_validators_and_balances() {
+        let state = state();
+        let partial = PartialBeaconState::from_state_ssz_bytes::<E>(&state.as_ssz_bytes());
+
+        assert_eq!(partial, Ok(PartialBeaconState::from_state(&state)));
+        assert_eq!(partial.unwrap().current_epoch::<E>(), state.current_epoch());
+    }
+
+    #[test]
+    fn rejects_truncated_state() {
+        let bytes = state().as_ssz_bytes();
+
+        assert!(PartialBeaconState::from_state_ssz_bytes::<E>(&bytes[0..100]).is_err());
+    }
+}
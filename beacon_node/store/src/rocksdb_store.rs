@@ -0,0 +1,148 @@
+use super::*;
+use crate::metrics;
+use rocksdb::{Error as RocksDBError, IteratorMode, Options, WriteBatch, DB};
+use std::path::Path;
+use std::sync::Arc;
+
+/// A wrapped rocksdb database.
+///
+/// Unlike `LevelDB`, each `DBColumn` is stored in its own column family, allowing rocksdb to
+/// compact each column independently.
+#[derive(Clone)]
+pub struct RocksDB {
+    db: Arc<DB>,
+}
+
+impl RocksDB {
+    /// Open a database at `path`, creating a new database (and any missing column families) if
+    /// one does not already exist.
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let mut options = Options::default();
+
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+
+        let columns = DBColumn::all()
+            .into_iter()
+            .map(Into::into)
+            .collect::<Vec<&str>>();
+
+        let db = Arc::new(DB::open_cf(&options, path, columns)?);
+
+        Ok(Self { db })
+    }
+
+    fn column_family(&self, col: &str) -> Result<&rocksdb::ColumnFamily, Error> {
+        self.db.cf_handle(col).ok_or_else(|| Error::DBError {
+            message: format!("Unknown rocksdb column family: {}", col),
+        })
+    }
+}
+
+impl KeyValueStore for RocksDB {
+    /// Retrieve some bytes in `column` with `key`.
+    fn get_bytes(&self, col: &str, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        let cf = self.column_family(col)?;
+
+        metrics::inc_counter(&metrics::DISK_DB_READ_COUNT);
+
+        let result = self
+            .db
+            .get_cf(cf, key)
+            .map(|bytes| bytes.map(|bytes| bytes.to_vec()))
+            .map_err(Into::into);
+
+        if let Ok(Some(bytes)) = &result {
+            metrics::inc_counter_by(&metrics::DISK_DB_READ_BYTES, bytes.len() as i64)
+        }
+
+        result
+    }
+
+    /// Store some `value` in `column`, indexed with `key`.
+    fn put_bytes(&self, col: &str, key: &[u8], val: &[u8]) -> Result<(), Error> {
+        let cf = self.column_family(col)?;
+
+        metrics::inc_counter(&metrics::DISK_DB_WRITE_COUNT);
+        metrics::inc_counter_by(&metrics::DISK_DB_WRITE_BYTES, val.len() as i64);
+
+        self.db.put_cf(cf, key, val).map_err(Into::into)
+    }
+
+    /// Return `true` if `key` exists in `column`.
+    fn key_exists(&self, col: &str, key: &[u8]) -> Result<bool, Error> {
+        let cf = self.column_family(col)?;
+
+        metrics::inc_counter(&metrics::DISK_DB_EXISTS_COUNT);
+
+        self.db
+            .get_cf(cf, key)
+            .map(|val| val.is_some())
+            .map_err(Into::into)
+    }
+
+    /// Removes `key` from `column`.
+    fn key_delete(&self, col: &str, key: &[u8]) -> Result<(), Error> {
+        let cf = self.column_family(col)?;
+
+        metrics::inc_counter(&metrics::DISK_DB_DELETE_COUNT);
+
+        self.db.delete_cf(cf, key).map_err(Into::into)
+    }
+
+    /// Apply all of the operations in `batch` as a single rocksdb write batch.
+    fn write_batch(&self, batch: Vec<KeyValueStoreOp>) -> Result<(), Error> {
+        let mut rocksdb_batch = WriteBatch::default();
+
+        for op in batch {
+            match op {
+                KeyValueStoreOp::PutKeyValue { column, key, value } => {
+                    metrics::inc_counter(&metrics::DISK_DB_WRITE_COUNT);
+                    metrics::inc_counter_by(&metrics::DISK_DB_WRITE_BYTES, value.len() as i64);
+
+                    rocksdb_batch.put_cf(self.column_family(column)?, key, value)?;
+                }
+                KeyValueStoreOp::DeleteKey { column, key } => {
+                    metrics::inc_counter(&metrics::DISK_DB_DELETE_COUNT);
+
+                    rocksdb_batch.delete_cf(self.column_family(column)?, key)?;
+                }
+            }
+        }
+
+        self.db.write(rocksdb_batch).map_err(Into::into)
+    }
+
+    /// Iterate over the column family of `column`.
+    fn iter_column<'a>(&'a self, column: &str) -> Result<ColumnIter<'a>, Error> {
+        let cf = self.column_family(column)?;
+
+        Ok(Box::new(
+            self.db
+                .iterator_cf(cf, IteratorMode::Start)?
+                .map(|(key, value)| (key.to_vec(), value.to_vec())),
+        ))
+    }
+
+    /// Compact every column family in the database.
+    fn compact(&self) -> Result<(), Error> {
+        metrics::inc_counter(&metrics::DISK_DB_COMPACTION_COUNT);
+
+        for column in DBColumn::all() {
+            let cf = self.column_family(column.into())?;
+            self.db.compact_range_cf(cf, None::<&[u8]>, None::<&[u8]>);
+        }
+
+        Ok(())
+    }
+}
+
+impl Store for RocksDB {}
+
+impl From<RocksDBError> for Error {
+    fn from(e: RocksDBError) -> Error {
+        Error::DBError {
+            message: format!("{:?}", e),
+        }
+    }
+}
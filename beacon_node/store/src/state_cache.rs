@@ -0,0 +1,194 @@
+use crate::metrics;
+use lru::LruCache;
+use types::{Hash256, Slot};
+
+/// A cached state, stored in its on-disk representation (i.e., including its caches).
+struct CachedState {
+    slot: Slot,
+    bytes: Vec<u8>,
+}
+
+/// An in-memory cache of recently read and written states, keyed by state root.
+///
+/// The cache is bounded by the total size of the states it holds rather than by their number,
+/// since the size of a state varies greatly between networks and over the life of a chain. When
+/// the capacity is exceeded, the least-recently used states are evicted until it is not.
+pub struct StateCache {
+    /// The maximum total size of the cached states, in bytes.
+    capacity: usize,
+    /// The total size of the cached states, in bytes.
+    size: usize,
+    states: LruCache<Hash256, CachedState>,
+}
+
+impl StateCache {
+    /// Create an empty cache which holds at most `capacity` bytes of states.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            size: 0,
+            states: LruCache::unbounded(),
+        }
+    }
+
+    /// Returns the bytes of the state with the given root, if it is cached.
+    ///
+    /// The bytes are copied so that they can be decoded without holding a lock on the cache.
+    pub fn get(&mut self, state_root: &Hash256) -> Option<Vec<u8>> {
+        match self.states.get(state_root) {
+            Some(cached) => {
+                metrics::inc_counter(&metrics::STATE_CACHE_HIT_COUNT);
+                Some(cached.bytes.clone())
+            }
+            None => {
+                metrics::inc_counter(&metrics::STATE_CACHE_MISS_COUNT);
+                None
+            }
+        }
+    }
+
+    /// Add the bytes of the state with the given root and slot to the cache, evicting the
+    /// least-recently used states if the cache is full.
+    ///
+    /// States larger than the entire cache are not added.
+    pub fn put(&mut self, state_root: Hash256, slot: Slot, bytes: Vec<u8>) {
+        self.remove(&state_root);
+
+        if bytes.len() > self.capacity {
+            return;
+        }
+
+        self.size += bytes.len();
+        self.states.put(state_root, CachedState { slot, bytes });
+
+        while self.size > self.capacity {
+            match self.states.pop_lru() {
+                Some((_, evicted)) => self.size -= evicted.bytes.len(),
+                None => break,
+            }
+        }
+
+        self.update_metrics();
+    }
+
+    /// Remove the state with the given root from the cache, if it is present.
+    pub fn remove(&mut self, state_root: &Hash256) {
+        if let Some(removed) = self.states.pop(state_root) {
+            self.size -= removed.bytes.len();
+            self.update_metrics();
+        }
+    }
+
+    /// Remove all states with a slot prior to `slot` from the cache.
+    pub fn prune(&mut self, slot: Slot) {
+        let to_remove = self
+            .states
+            .iter()
+            .filter(|(_, cached)| cached.slot < slot)
+            .map(|(state_root, _)| *state_root)
+            .collect::<Vec<_>>();
+
+        for state_root in &to_remove {
+            self.remove(state_root);
+        }
+    }
+
+    /// Remove all states from the cache.
+    pub fn clear(&mut self) {
+        self.states.clear();
+        self.size = 0;
+        self.update_metrics();
+    }
+
+    /// Returns the number of cached states.
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Returns `true` if there are no cached states.
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// Returns the total size of the cached states, in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    fn update_metrics(&self) {
+        metrics::set_gauge(&metrics::STATE_CACHE_SIZE, self.size as i64);
+        metrics::set_gauge(&metrics::STATE_CACHE_LEN, self.states.len() as i64);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn root(i: u64) -> Hash256 {
+        Hash256::from_low_u64_be(i)
+    }
+
+    #[test]
+    fn evicts_least_recently_used_by_size() {
+        let mut cache = StateCache::new(10);
+
+        cache.put(root(1), Slot::new(1), vec![1; 4]);
+        cache.put(root(2), Slot::new(2), vec![2; 4]);
+        assert_eq!(cache.size(), 8);
+
+        // Reading the first state makes the second the least-recently used.
+        assert_eq!(cache.get(&root(1)), Some(vec![1; 4]));
+
+        cache.put(root(3), Slot::new(3), vec![3; 4]);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.size(), 8);
+        assert_eq!(cache.get(&root(2)), None);
+        assert!(cache.get(&root(1)).is_some());
+        assert!(cache.get(&root(3)).is_some());
+
+        // A large state may evict several smaller ones.
+        cache.put(root(4), Slot::new(4), vec![4; 9]);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.size(), 9);
+    }
+
+    #[test]
+    fn ignores_states_larger_than_capacity() {
+        let mut cache = StateCache::new(10);
+
+        cache.put(root(1), Slot::new(1), vec![1; 4]);
+        cache.put(root(2), Slot::new(2), vec![2; 11]);
+
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&root(2)), None);
+    }
+
+    #[test]
+    fn replace_and_remove() {
+        let mut cache = StateCache::new(10);
+
+        cache.put(root(1), Slot::new(1), vec![1; 4]);
+        cache.put(root(1), Slot::new(1), vec![1; 6]);
+        assert_eq!(cache.size(), 6);
+
+        cache.remove(&root(1));
+        assert!(cache.is_empty());
+        assert_eq!(cache.size(), 0);
+    }
+
+    #[test]
+    fn prune_before_slot() {
+        let mut cache = StateCache::new(100);
+
+        for i in 0..5 {
+            cache.put(root(i), Slot::new(i), vec![0; 4]);
+        }
+
+        cache.prune(Slot::new(3));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.size(), 8);
+        assert!(cache.get(&root(2)).is_none());
+        assert!(cache.get(&root(3)).is_some());
+    }
+}
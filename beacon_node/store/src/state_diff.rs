@@ -0,0 +1,306 @@
+use crate::*;
+use ssz::{Decode, DecodeError, Encode};
+use ssz_derive::{Decode, Encode};
+
+/// The changes required to transform one list (`base`) into another.
+///
+/// Only the elements that differ from `base` are stored, so lists which are mostly unchanged
+/// between states (e.g., `balances`, `validators`, `state_roots`) are stored very compactly.
+#[derive(Debug, PartialEq, Clone, Encode, Decode)]
+pub struct ListDiff<T: Encode + Decode> {
+    /// The length of the resulting list.
+    len: u64,
+    /// The indices of the elements which differ from the base, in ascending order.
+    indices: Vec<u64>,
+    /// The new values of the elements at `indices`.
+    values: Vec<T>,
+}
+
+impl<T: Encode + Decode + PartialEq + Clone> ListDiff<T> {
+    /// Returns the diff which transforms `base` into `list`.
+    pub fn new(base: &[T], list: &[T]) -> Self {
+        let (indices, values) = list
+            .iter()
+            .enumerate()
+            .filter(|(i, value)| base.get(*i) != Some(*value))
+            .map(|(i, value)| (i as u64, value.clone()))
+            .unzip();
+
+        Self {
+            len: list.len() as u64,
+            indices,
+            values,
+        }
+    }
+
+    /// Returns the list obtained by applying `self` to `base`.
+    pub fn apply(self, base: &[T]) -> Result<Vec<T>, Error> {
+        if self.indices.len() != self.values.len() {
+            return Err(invalid_diff("mismatched indices and values"));
+        }
+
+        let len = self.len as usize;
+        let mut list = base.to_vec();
+        list.truncate(len);
+
+        for (i, value) in self.indices.into_iter().zip(self.values.into_iter()) {
+            let i = i as usize;
+
+            if i < list.len() {
+                list[i] = value;
+            } else if i == list.len() {
+                list.push(value);
+            } else {
+                return Err(invalid_diff("non-contiguous list index"));
+            }
+        }
+
+        if list.len() != len {
+            return Err(invalid_diff("incorrect list length"));
+        }
+
+        Ok(list)
+    }
+}
+
+/// A `BeaconState`, stored as the fields which differ from some earlier (base) state.
+///
+/// Small fields are stored in full, whilst large lists are stored as a `ListDiff` against the
+/// corresponding list of the base state. The `genesis_time` never changes, so it is always taken
+/// from the base state.
+#[derive(Debug, PartialEq, Clone, Encode, Decode)]
+pub struct BeaconStateDiff<E: EthSpec> {
+    /// The root of the state which this diff is applied to.
+    pub base_state_root: Hash256,
+    /// The slot of the state which this diff is applied to.
+    pub base_slot: Slot,
+    slot: Slot,
+    fork: Fork,
+    latest_block_header: BeaconBlockHeader,
+    block_roots: ListDiff<Hash256>,
+    state_roots: ListDiff<Hash256>,
+    historical_roots: ListDiff<Hash256>,
+    eth1_data: Eth1Data,
+    eth1_data_votes: ListDiff<Eth1Data>,
+    eth1_deposit_index: u64,
+    validators: ListDiff<Validator>,
+    balances: ListDiff<u64>,
+    randao_mixes: ListDiff<Hash256>,
+    slashings: ListDiff<u64>,
+    previous_epoch_attestations: ListDiff<PendingAttestation<E>>,
+    current_epoch_attestations: ListDiff<PendingAttestation<E>>,
+    justification_bits: BitVector<E::JustificationBitsLength>,
+    previous_justified_checkpoint: Checkpoint,
+    current_justified_checkpoint: Checkpoint,
+    finalized_checkpoint: Checkpoint,
+}
+
+impl<E: EthSpec> BeaconStateDiff<E> {
+    /// Returns the diff which transforms `base` (with root `base_state_root`) into `state`.
+    pub fn new(base_state_root: Hash256, base: &BeaconState<E>, state: &BeaconState<E>) -> Self {
+        Self {
+            base_state_root,
+            base_slot: base.slot,
+            slot: state.slot,
+            fork: state.fork.clone(),
+            latest_block_header: state.latest_block_header.clone(),
+            block_roots: ListDiff::new(&base.block_roots, &state.block_roots),
+            state_roots: ListDiff::new(&base.state_roots, &state.state_roots),
+            historical_roots: ListDiff::new(&base.historical_roots, &state.historical_roots),
+            eth1_data: state.eth1_data.clone(),
+            eth1_data_votes: ListDiff::new(&base.eth1_data_votes, &state.eth1_data_votes),
+            eth1_deposit_index: state.eth1_deposit_index,
+            validators: ListDiff::new(&base.validators, &state.validators),
+            balances: ListDiff::new(&base.balances, &state.balances),
+            randao_mixes: ListDiff::new(&base.randao_mixes, &state.randao_mixes),
+            slashings: ListDiff::new(&base.slashings, &state.slashings),
+            previous_epoch_attestations: ListDiff::new(
+                &base.previous_epoch_attestations,
+                &state.previous_epoch_attestations,
+            ),
+            current_epoch_attestations: ListDiff::new(
+                &base.current_epoch_attestations,
+                &state.current_epoch_attestations,
+            ),
+            justification_bits: state.justification_bits.clone(),
+            previous_justified_checkpoint: state.previous_justified_checkpoint.clone(),
+            current_justified_checkpoint: state.current_justified_checkpoint.clone(),
+            finalized_checkpoint: state.finalized_checkpoint.clone(),
+        }
+    }
+
+    /// Returns the slot of the diffed state.
+    pub fn slot(&self) -> Slot {
+        self.slot
+    }
+
+    /// Reassembles the full state by applying `self` to `base`.
+    ///
+    /// Only the committee and tree hash caches of `base` are retained: the committees are fixed
+    /// for the epoch shared by `base` and the diffed state, and the tree hash cache detects
+    /// modified leaves. The exit and pubkey caches are dropped, since they may describe a
+    /// different validator registry and are extended (rather than rebuilt) by
+    /// `build_all_caches`.
+    pub fn apply(self, mut base: BeaconState<E>) -> Result<BeaconState<E>, Error> {
+        if base.slot != self.base_slot {
+            return Err(invalid_diff("incorrect base state"));
+        }
+
+        base.exit_cache = Default::default();
+        base.drop_pubkey_cache();
+
+        base.slot = self.slot;
+        base.fork = self.fork;
+        base.latest_block_header = self.latest_block_header;
+        base.block_roots = FixedVector::new(self.block_roots.apply(&base.block_roots)?)
+            .map_err(|e| invalid_diff(&format!("block_roots: {:?}", e)))?;
+        base.state_roots = FixedVector::new(self.state_roots.apply(&base.state_roots)?)
+            .map_err(|e| invalid_diff(&format!("state_roots: {:?}", e)))?;
+        base.historical_roots =
+            VariableList::new(self.historical_roots.apply(&base.historical_roots)?)
+                .map_err(|e| invalid_diff(&format!("historical_roots: {:?}", e)))?;
+        base.eth1_data = self.eth1_data;
+        base.eth1_data_votes =
+            VariableList::new(self.eth1_data_votes.apply(&base.eth1_data_votes)?)
+                .map_err(|e| invalid_diff(&format!("eth1_data_votes: {:?}", e)))?;
+        base.eth1_deposit_index = self.eth1_deposit_index;
+        base.validators = VariableList::new(self.validators.apply(&base.validators)?)
+            .map_err(|e| invalid_diff(&format!("validators: {:?}", e)))?;
+        base.balances = VariableList::new(self.balances.apply(&base.balances)?)
+            .map_err(|e| invalid_diff(&format!("balances: {:?}", e)))?;
+        base.randao_mixes = FixedVector::new(self.randao_mixes.apply(&base.randao_mixes)?)
+            .map_err(|e| invalid_diff(&format!("randao_mixes: {:?}", e)))?;
+        base.slashings = FixedVector::new(self.slashings.apply(&base.slashings)?)
+            .map_err(|e| invalid_diff(&format!("slashings: {:?}", e)))?;
+        base.previous_epoch_attestations = VariableList::new(
+            self.previous_epoch_attestations
+                .apply(&base.previous_epoch_attestations)?,
+        )
+        .map_err(|e| invalid_diff(&format!("previous_epoch_attestations: {:?}", e)))?;
+        base.current_epoch_attestations = VariableList::new(
+            self.current_epoch_attestations
+                .apply(&base.current_epoch_attestations)?,
+        )
+        .map_err(|e| invalid_diff(&format!("current_epoch_attestations: {:?}", e)))?;
+        base.justification_bits = self.justification_bits;
+        base.previous_justified_checkpoint = self.previous_justified_checkpoint;
+        base.current_justified_checkpoint = self.current_justified_checkpoint;
+        base.finalized_checkpoint = self.finalized_checkpoint;
+
+        Ok(base)
+    }
+
+    /// Returns the validators and balances of the diffed state, by applying `self` to those of
+    /// the base state.
+    pub fn apply_partial(self, base: PartialBeaconState) -> Result<PartialBeaconState, Error> {
+        if base.slot != self.base_slot {
+            return Err(invalid_diff("incorrect base state"));
+        }
+
+        Ok(PartialBeaconState {
+            slot: self.slot,
+            validators: self.validators.apply(&base.validators)?,
+            balances: self.balances.apply(&base.balances)?,
+        })
+    }
+}
+
+impl<E: EthSpec> StoreItem for BeaconStateDiff<E> {
+    fn db_column() -> DBColumn {
+        DBColumn::BeaconStateDiff
+    }
+
+    fn as_store_bytes(&self) -> Vec<u8> {
+        self.as_ssz_bytes()
+    }
+
+    fn from_store_bytes(bytes: &mut [u8]) -> Result<Self, Error> {
+        Self::from_ssz_bytes(bytes).map_err(Into::into)
+    }
+}
+
+fn invalid_diff(message: &str) -> Error {
+    Error::SszDecodeError(DecodeError::BytesInvalid(format!(
+        "Invalid BeaconStateDiff: {}",
+        message
+    )))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use state_processing::per_slot_processing;
+    use types::test_utils::TestingBeaconStateBuilder;
+
+    type E = MinimalEthSpec;
+
+    #[test]
+    fn list_diff_round_trip() {
+        let base = vec![1_u64, 2, 3, 4];
+
+        for list in vec![vec![1, 2, 3, 4], vec![1, 5, 3, 4, 6, 7], vec![], vec![0, 2]] {
+            let diff = ListDiff::new(&base, &list);
+            let decoded = ListDiff::<u64>::from_ssz_bytes(&diff.as_ssz_bytes()).unwrap();
+            assert_eq!(decoded.apply(&base), Ok(list));
+        }
+    }
+
+    #[test]
+    fn list_diff_stores_only_changes() {
+        let base = vec![0_u64; 1024];
+        let mut list = base.clone();
+        list[42] = 1;
+        list.push(2);
+
+        let diff = ListDiff::new(&base, &list);
+        assert_eq!(diff.indices, vec![42, 1024]);
+        assert_eq!(diff.values, vec![1, 2]);
+    }
+
+    #[test]
+    fn state_diff_round_trip() {
+        let spec = E::default_spec();
+        let (base, _keypairs) =
+            TestingBeaconStateBuilder::<E>::from_deterministic_keypairs(8, &spec).build();
+        let base_root = base.canonical_root();
+
+        let mut state = base.clone();
+        per_slot_processing(&mut state, &spec).unwrap();
+        state.balances[3] += 1;
+        state.validators[5].slashed = true;
+
+        let diff = BeaconStateDiff::new(base_root, &base, &state);
+        assert!(diff.as_ssz_bytes().len() < state.as_ssz_bytes().len());
+
+        let decoded = BeaconStateDiff::<E>::from_ssz_bytes(&diff.as_ssz_bytes()).unwrap();
+        let reassembled = decoded.apply(base).unwrap();
+        assert_eq!(reassembled.canonical_root(), state.canonical_root());
+    }
+
+    #[test]
+    fn state_diff_rebuilds_exit_cache() {
+        let spec = E::default_spec();
+        let (mut base, _keypairs) =
+            TestingBeaconStateBuilder::<E>::from_deterministic_keypairs(8, &spec).build();
+        base.validators[1].exit_epoch = Epoch::new(5);
+        base.build_all_caches(&spec).unwrap();
+        let base_root = base.canonical_root();
+
+        // A validator exits between the base and the diffed state.
+        let mut state = base.clone();
+        state.validators[2].exit_epoch = Epoch::new(5);
+        state.validators[3].exit_epoch = Epoch::new(6);
+
+        let diff = BeaconStateDiff::new(base_root, &base, &state);
+        let mut reassembled = diff.apply(base).unwrap();
+        reassembled.build_all_caches(&spec).unwrap();
+
+        let mut fresh = state.clone();
+        fresh.drop_all_caches();
+        fresh.build_all_caches(&spec).unwrap();
+
+        assert_eq!(reassembled.exit_cache, fresh.exit_cache);
+        assert_eq!(reassembled.exit_cache.get_churn_at(Epoch::new(5)), 2);
+        assert_eq!(reassembled.exit_cache.get_churn_at(Epoch::new(6)), 1);
+    }
+}
@@ -0,0 +1,41 @@
+use clap::{App, Arg, SubCommand};
+
+pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
+    App::new("database_manager")
+        .visible_aliases(&["db", "database"])
+        .about(
+            "Utilities for inspecting and repairing a beacon node database, whilst the beacon \
+                node is not running.",
+        )
+        .arg(
+            Arg::with_name("db-backend")
+                .long("db-backend")
+                .value_name("BACKEND")
+                .help(
+                    "The key-value database used by the beacon node. Overrides the value in the \
+                       datadir's configuration file, if any.",
+                )
+                .takes_value(true)
+                .possible_values(&["leveldb", "rocksdb"]),
+        )
+        .subcommand(SubCommand::with_name("inspect").about(
+            "Print the number of items, and their total size, in each column of the \
+                        hot and freezer databases.",
+        ))
+        .subcommand(
+            SubCommand::with_name("version")
+                .about("Print the schema version of the database, without upgrading it."),
+        )
+        .subcommand(SubCommand::with_name("prune-states").about(
+            "Delete states from the hot database which precede the split with the \
+                        freezer database, and can never be read.",
+        ))
+        .subcommand(SubCommand::with_name("compact").about(
+            "Compact the hot and freezer databases, reclaiming the space used by \
+                        deleted items.",
+        ))
+        .subcommand(SubCommand::with_name("verify").about(
+            "Verify that the state of every block, and the base of every diffed \
+                        state, is present in the database.",
+        ))
+}
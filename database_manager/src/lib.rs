@@ -0,0 +1,277 @@
+mod cli;
+
+use beacon_chain::schema_change::migrate_schema;
+use clap::ArgMatches;
+use client::ClientConfig;
+use environment::RuntimeContext;
+use eth2_config::read_from_file;
+use slog::{crit, info, warn, Logger};
+use std::path::Path;
+use store::{
+    BeaconStateDiff, DBBackend, DBColumn, DiskDB, DiskStore, KeyValueStore, SchemaVersion, Store,
+    StoreItem, CURRENT_SCHEMA_VERSION, SCHEMA_VERSION_KEY,
+};
+use types::{BeaconBlock, EthSpec, Hash256};
+
+pub use cli::cli_app;
+
+pub const CLIENT_CONFIG_FILENAME: &str = "beacon-node.toml";
+
+/// Run the database manager, logging and returning an error if the operation did not succeed.
+pub fn run<T: EthSpec>(matches: &ArgMatches, context: RuntimeContext<T>) -> Result<(), String> {
+    let log = context.log.clone();
+    run_database_manager(matches, context).map_err(|e| {
+        crit!(log, "Database manager failed"; "error" => &e);
+        format!("Database manager failed: {}", e)
+    })
+}
+
+/// Run the database manager, returning an error if the operation did not succeed.
+fn run_database_manager<T: EthSpec>(
+    matches: &ArgMatches,
+    context: RuntimeContext<T>,
+) -> Result<(), String> {
+    let log = context.log.clone();
+    let config = load_client_config(matches)?;
+
+    let hot_path = config
+        .db_path()
+        .ok_or_else(|| "Unable to access database path".to_string())?;
+    let cold_path = config
+        .get_freezer_db_path()
+        .ok_or_else(|| "Unable to access freezer database path".to_string())?;
+
+    if !hot_path.exists() {
+        return Err(format!("No database found at {:?}", hot_path));
+    }
+
+    info!(
+        log,
+        "Located database";
+        "hot_path" => format!("{:?}", hot_path),
+        "freezer_path" => format!("{:?}", cold_path),
+        "backend" => format!("{:?}", config.store.backend),
+    );
+
+    let open_store = || {
+        DiskStore::open(
+            &hot_path,
+            &cold_path,
+            config.store.clone(),
+            context.eth2_config.spec.clone(),
+            migrate_schema::<T>,
+        )
+        .map_err(|e| format!("Unable to open database: {:?}", e))
+    };
+
+    match matches.subcommand() {
+        ("inspect", Some(_)) => inspect(&hot_path, &cold_path, config.store.backend, &log),
+        ("version", Some(_)) => version(&hot_path, config.store.backend, &log),
+        ("prune-states", Some(_)) => prune_states::<T>(&open_store()?, &log),
+        ("compact", Some(_)) => compact(&hot_path, &cold_path, config.store.backend, &log),
+        ("verify", Some(_)) => verify::<T>(&open_store()?, &log),
+        _ => Err("Invalid 'database_manager' command. See --help.".to_string()),
+    }
+}
+
+/// Load the beacon node configuration from the datadir, falling back to the defaults if the
+/// datadir has no configuration file.
+fn load_client_config(matches: &ArgMatches) -> Result<ClientConfig, String> {
+    let data_dir = clap_utils::parse_data_dir(matches)?;
+
+    if !data_dir.exists() {
+        return Err(format!("No datadir found at {:?}", data_dir));
+    }
+
+    let mut config =
+        read_from_file::<ClientConfig>(data_dir.join(CLIENT_CONFIG_FILENAME))?.unwrap_or_default();
+    config.data_dir = data_dir;
+
+    if let Some(backend) = matches.value_of("db-backend") {
+        config.store.backend = backend.parse()?;
+    }
+
+    Ok(config)
+}
+
+/// Open the database at `path`, if it exists.
+fn open_disk_db(backend: DBBackend, path: &Path) -> Result<Option<DiskDB>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    DiskDB::open(backend, path)
+        .map(Some)
+        .map_err(|e| format!("Unable to open database at {:?}: {:?}", path, e))
+}
+
+/// Log the number of items, and the total size of their keys and values, in each column.
+fn inspect(
+    hot_path: &Path,
+    cold_path: &Path,
+    backend: DBBackend,
+    log: &Logger,
+) -> Result<(), String> {
+    for (name, path) in &[("hot", hot_path), ("freezer", cold_path)] {
+        let db = match open_disk_db(backend, path)? {
+            Some(db) => db,
+            None => {
+                warn!(log, "Database not found"; "database" => *name);
+                continue;
+            }
+        };
+
+        for column in DBColumn::all() {
+            let column: &str = column.into();
+            let (items, bytes) = db
+                .iter_column(column)
+                .map_err(|e| format!("Unable to read {} column: {:?}", column, e))?
+                .fold((0_usize, 0_usize), |(items, bytes), (key, value)| {
+                    (items + 1, bytes + key.len() + value.len())
+                });
+
+            info!(
+                log,
+                "Column";
+                "database" => *name,
+                "column" => column,
+                "items" => items,
+                "bytes" => bytes,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Log the schema version of the database, without applying any migrations.
+fn version(hot_path: &Path, backend: DBBackend, log: &Logger) -> Result<(), String> {
+    let db = open_disk_db(backend, hot_path)?
+        .ok_or_else(|| format!("No database found at {:?}", hot_path))?;
+
+    let schema_version = db
+        .get::<SchemaVersion>(&Hash256::from_slice(SCHEMA_VERSION_KEY.as_bytes()))
+        .map_err(|e| format!("Unable to read schema version: {:?}", e))?
+        .unwrap_or(SchemaVersion(0));
+
+    info!(
+        log,
+        "Database schema version";
+        "version" => schema_version.as_u64(),
+        "supported" => CURRENT_SCHEMA_VERSION.as_u64(),
+    );
+
+    Ok(())
+}
+
+/// Delete the hot states which precede the split, and can never be read.
+fn prune_states<T: EthSpec>(store: &DiskStore, log: &Logger) -> Result<(), String> {
+    let deleted = store
+        .delete_stale_hot_states::<T>()
+        .map_err(|e| format!("Unable to prune states: {:?}", e))?;
+
+    info!(
+        log,
+        "Pruned stale states";
+        "deleted" => deleted,
+        "split_slot" => store.get_split_slot().as_u64(),
+    );
+
+    Ok(())
+}
+
+/// Compact the hot and freezer databases.
+fn compact(
+    hot_path: &Path,
+    cold_path: &Path,
+    backend: DBBackend,
+    log: &Logger,
+) -> Result<(), String> {
+    for (name, path) in &[("hot", hot_path), ("freezer", cold_path)] {
+        if let Some(db) = open_disk_db(backend, path)? {
+            db.compact()
+                .map_err(|e| format!("Unable to compact {} database: {:?}", name, e))?;
+
+            info!(log, "Compacted database"; "database" => *name);
+        }
+    }
+
+    Ok(())
+}
+
+/// Verify that the state of each block, and the base of each diffed state, is present.
+fn verify<T: EthSpec>(store: &DiskStore, log: &Logger) -> Result<(), String> {
+    let mut blocks = 0;
+    let mut state_diffs = 0;
+    let mut inconsistencies = 0;
+
+    let block_iter = store
+        .iter_column(DBColumn::BeaconBlock.into())
+        .map_err(|e| format!("Unable to read blocks: {:?}", e))?;
+
+    for (key, mut value) in block_iter {
+        let block_root = Hash256::from_slice(&key);
+
+        // The genesis block is duplicated under the zero hash.
+        if block_root == Hash256::zero() {
+            continue;
+        }
+
+        let block = BeaconBlock::<T>::from_store_bytes(&mut value)
+            .map_err(|e| format!("Unable to decode block {:?}: {:?}", block_root, e))?;
+        blocks += 1;
+
+        if !store
+            .state_exists(&block.state_root)
+            .map_err(|e| format!("Unable to read state: {:?}", e))?
+        {
+            warn!(
+                log,
+                "Missing state for block";
+                "block_root" => format!("{:?}", block_root),
+                "state_root" => format!("{:?}", block.state_root),
+                "slot" => block.slot.as_u64(),
+            );
+            inconsistencies += 1;
+        }
+    }
+
+    let state_diff_iter = store
+        .iter_column(DBColumn::BeaconStateDiff.into())
+        .map_err(|e| format!("Unable to read state diffs: {:?}", e))?;
+
+    for (key, mut value) in state_diff_iter {
+        let state_root = Hash256::from_slice(&key);
+        let diff = BeaconStateDiff::<T>::from_store_bytes(&mut value)
+            .map_err(|e| format!("Unable to decode state diff {:?}: {:?}", state_root, e))?;
+        state_diffs += 1;
+
+        if !store
+            .state_exists(&diff.base_state_root)
+            .map_err(|e| format!("Unable to read state: {:?}", e))?
+        {
+            warn!(
+                log,
+                "Missing base state for state diff";
+                "state_root" => format!("{:?}", state_root),
+                "base_state_root" => format!("{:?}", diff.base_state_root),
+                "slot" => diff.slot().as_u64(),
+            );
+            inconsistencies += 1;
+        }
+    }
+
+    info!(
+        log,
+        "Verified database";
+        "blocks" => blocks,
+        "state_diffs" => state_diffs,
+        "inconsistencies" => inconsistencies,
+    );
+
+    if inconsistencies == 0 {
+        Ok(())
+    } else {
+        Err(format!("Found {} inconsistencies", inconsistencies))
+    }
+}
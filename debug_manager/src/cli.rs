@@ -0,0 +1,65 @@
+use clap::{App, Arg, SubCommand};
+
+pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
+    App::new("debug_manager")
+        .visible_aliases(&["debug"])
+        .about("Utilities for reproducing consensus bugs from SSZ-encoded states and blocks.")
+        .subcommand(
+            SubCommand::with_name("replay-block")
+                .about(
+                    "Apply a block to a state, as the beacon node would, and print a JSON report \
+                        of each field of the state that the block changed, along with any error. \
+                        The files and report may be attached to a bug report.",
+                )
+                .arg(
+                    Arg::with_name("state")
+                        .long("state")
+                        .value_name("PATH")
+                        .help("An SSZ-encoded BeaconState, prior to the block.")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("block")
+                        .long("block")
+                        .value_name("PATH")
+                        .help("An SSZ-encoded BeaconBlock to apply to the state.")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("signatures")
+                        .long("signatures")
+                        .value_name("STRATEGY")
+                        .help(
+                            "Verify each signature as its object is processed, all signatures in \
+                                bulk before processing, or no signatures at all.",
+                        )
+                        .takes_value(true)
+                        .possible_values(&["individual", "bulk", "none"])
+                        .default_value("individual"),
+                )
+                .arg(
+                    Arg::with_name("no-state-root-check")
+                        .long("no-state-root-check")
+                        .help(
+                            "Do not check that the state root of the block matches the post-state.",
+                        )
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .value_name("PATH")
+                        .help("Write the JSON report to this file, rather than to stdout.")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("post-state")
+                        .long("post-state")
+                        .value_name("PATH")
+                        .help("Write the SSZ-encoded post-state to this file.")
+                        .takes_value(true),
+                ),
+        )
+}
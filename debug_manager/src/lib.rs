@@ -0,0 +1,208 @@
+mod cli;
+
+use clap::ArgMatches;
+use compare_fields::{CompareFields, Comparison};
+use environment::RuntimeContext;
+use serde_derive::Serialize;
+use slog::{crit, info, Logger};
+use ssz::{Decode, Encode};
+use state_processing::{per_block_processing, per_slot_processing, BlockSignatureStrategy};
+use std::fs;
+use std::path::{Path, PathBuf};
+use types::{BeaconBlock, BeaconState, ChainSpec, EthSpec, Hash256, Slot};
+
+pub use cli::cli_app;
+
+/// The outcome of applying a block to a state, as printed by `replay-block`.
+#[derive(Debug, Serialize)]
+struct ReplayReport {
+    pre_state_slot: Slot,
+    block_slot: Slot,
+    block_root: Hash256,
+    /// The reason the block could not be applied, if any.
+    error: Option<String>,
+    /// The state root committed to by the block.
+    expected_state_root: Hash256,
+    /// The root of the state after applying the block (or as far as processing got, upon error).
+    post_state_root: Hash256,
+    /// `None` if the state root check was disabled.
+    state_root_matches: Option<bool>,
+    /// Every field, or element of a list field, which differs between the pre and post states.
+    changed_fields: Vec<FieldDiff>,
+}
+
+/// A field of the state which was changed by the block.
+#[derive(Debug, Serialize)]
+struct FieldDiff {
+    /// The name of the field, suffixed with the index for elements of lists (e.g., `balances.3`).
+    field: String,
+    pre: String,
+    post: String,
+}
+
+/// Run the debug manager, logging an error if the operation did not succeed.
+pub fn run<T: EthSpec>(matches: &ArgMatches, context: RuntimeContext<T>) {
+    let log = context.log.clone();
+    match run_debug_manager::<T>(matches, &context.eth2_config().spec, &log) {
+        Ok(()) => (),
+        Err(e) => crit!(log, "Debug manager failed"; "error" => e),
+    }
+}
+
+/// Run the debug manager, returning an error if the operation did not succeed.
+fn run_debug_manager<T: EthSpec>(
+    matches: &ArgMatches,
+    spec: &ChainSpec,
+    log: &Logger,
+) -> Result<(), String> {
+    match matches.subcommand() {
+        ("replay-block", Some(matches)) => replay_block::<T>(matches, spec, log),
+        _ => Err("Invalid 'debug_manager' command. See --help.".to_string()),
+    }
+}
+
+/// Apply the block to the state and report the fields of the state which changed, returning an
+/// error if the block is invalid or the post-state does not match its state root.
+///
+/// The report is written even if processing fails, so that it can be attached to a bug report.
+fn replay_block<T: EthSpec>(
+    matches: &ArgMatches,
+    spec: &ChainSpec,
+    log: &Logger,
+) -> Result<(), String> {
+    let state_path: PathBuf = clap_utils::parse_required(matches, "state")?;
+    let block_path: PathBuf = clap_utils::parse_required(matches, "block")?;
+    let signatures = matches.value_of("signatures").unwrap_or("individual");
+    let signature_strategy = match signatures {
+        "bulk" => BlockSignatureStrategy::VerifyBulk,
+        "none" => BlockSignatureStrategy::NoVerification,
+        _ => BlockSignatureStrategy::VerifyIndividual,
+    };
+    let check_state_root = !matches.is_present("no-state-root-check");
+
+    let pre_state: BeaconState<T> = load_ssz(&state_path)?;
+    let block: BeaconBlock<T> = load_ssz(&block_path)?;
+
+    info!(
+        log,
+        "Replaying block";
+        "block_slot" => block.slot,
+        "state_slot" => pre_state.slot,
+        "signatures" => signatures,
+    );
+
+    let mut post_state = pre_state.clone();
+    let error = apply_block(&mut post_state, &block, signature_strategy, spec).err();
+
+    // The caches are not part of the consensus state, so are excluded from the comparison.
+    post_state.drop_all_caches();
+    let post_state_root = post_state.canonical_root();
+
+    let report = ReplayReport {
+        pre_state_slot: pre_state.slot,
+        block_slot: block.slot,
+        block_root: block.canonical_root(),
+        error: error.clone(),
+        expected_state_root: block.state_root,
+        post_state_root,
+        state_root_matches: if check_state_root {
+            Some(block.state_root == post_state_root)
+        } else {
+            None
+        },
+        changed_fields: changed_fields(&pre_state, &post_state),
+    };
+
+    let json = serde_json::to_string_pretty(&report)
+        .map_err(|e| format!("Unable to serialize report: {:?}", e))?;
+
+    match matches.value_of("output") {
+        Some(path) => {
+            fs::write(path, json).map_err(|e| format!("Unable to write report: {:?}", e))?
+        }
+        None => println!("{}", json),
+    }
+
+    if let Some(path) = matches.value_of("post-state") {
+        fs::write(path, post_state.as_ssz_bytes())
+            .map_err(|e| format!("Unable to write post-state: {:?}", e))?;
+    }
+
+    if let Some(e) = error {
+        return Err(format!("Block processing failed: {}", e));
+    }
+
+    if report.state_root_matches == Some(false) {
+        return Err(format!(
+            "Post-state root {:?} does not match the block state root {:?}",
+            post_state_root, block.state_root
+        ));
+    }
+
+    info!(
+        log,
+        "Block replayed successfully";
+        "changed_fields" => report.changed_fields.len(),
+        "post_state_root" => format!("{:?}", post_state_root),
+    );
+
+    Ok(())
+}
+
+/// Advances `state` to the slot of `block` and applies the block.
+fn apply_block<T: EthSpec>(
+    state: &mut BeaconState<T>,
+    block: &BeaconBlock<T>,
+    signature_strategy: BlockSignatureStrategy,
+    spec: &ChainSpec,
+) -> Result<(), String> {
+    state
+        .build_all_caches(spec)
+        .map_err(|e| format!("Unable to build caches: {:?}", e))?;
+
+    while state.slot < block.slot {
+        per_slot_processing(state, spec)
+            .map_err(|e| format!("Failed to advance to slot {}: {:?}", state.slot + 1, e))?;
+    }
+
+    state
+        .build_all_caches(spec)
+        .map_err(|e| format!("Unable to build caches: {:?}", e))?;
+
+    per_block_processing(state, block, None, signature_strategy, spec)
+        .map_err(|e| format!("{:?}", e))
+}
+
+/// Returns each field of the state, or element of a list field, which differs between `pre` and
+/// `post`.
+fn changed_fields<T: EthSpec>(pre: &BeaconState<T>, post: &BeaconState<T>) -> Vec<FieldDiff> {
+    pre.compare_fields(post)
+        .into_iter()
+        .filter(Comparison::not_equal)
+        .flat_map(|comparison| match comparison {
+            Comparison::Child(field) => vec![FieldDiff {
+                field: field.field_name,
+                pre: field.a,
+                post: field.b,
+            }],
+            Comparison::Parent {
+                field_name,
+                children,
+                ..
+            } => children
+                .into_iter()
+                .filter(|child| child.not_equal())
+                .map(|child| FieldDiff {
+                    field: format!("{}.{}", field_name, child.field_name),
+                    pre: child.a,
+                    post: child.b,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+fn load_ssz<T: Decode>(path: &Path) -> Result<T, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Unable to read file {:?}: {:?}", path, e))?;
+    T::from_ssz_bytes(&bytes).map_err(|e| format!("Unable to decode {:?}: {:?}", path, e))
+}
@@ -0,0 +1,55 @@
+use clap::{App, Arg, SubCommand};
+
+pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
+    App::new("eth1_manager")
+        .visible_aliases(&["eth1"])
+        .about("Utilities for inspecting the eth1 deposit contract via an eth1 node.")
+        .subcommand(
+            SubCommand::with_name("verify-deposits")
+                .about(
+                    "Download every deposit log from the deposit contract, rebuild the deposit \
+                        tree and check its root and deposit count against the contract at a \
+                        number of block heights. Prints the first divergent deposit index, if any.",
+                )
+                .arg(
+                    Arg::with_name("endpoint")
+                        .long("endpoint")
+                        .value_name("HTTP-ENDPOINT")
+                        .help("The HTTP JSON-RPC endpoint of an eth1 node.")
+                        .takes_value(true)
+                        .default_value("http://localhost:8545"),
+                )
+                .arg(
+                    Arg::with_name("deposit-contract")
+                        .long("deposit-contract")
+                        .value_name("ADDRESS")
+                        .help("The address of the deposit contract.")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("deploy-block")
+                        .long("deploy-block")
+                        .value_name("BLOCK_NUMBER")
+                        .help("The block at which the deposit contract was deployed.")
+                        .takes_value(true)
+                        .default_value("0"),
+                )
+                .arg(
+                    Arg::with_name("checkpoints")
+                        .long("checkpoints")
+                        .value_name("INTEGER")
+                        .help("The number of block heights at which to check the deposit tree.")
+                        .takes_value(true)
+                        .default_value("16"),
+                )
+                .arg(
+                    Arg::with_name("blocks-per-log-query")
+                        .long("blocks-per-log-query")
+                        .value_name("INTEGER")
+                        .help("The number of blocks to request deposit logs for at once.")
+                        .takes_value(true)
+                        .default_value("1000"),
+                ),
+        )
+}
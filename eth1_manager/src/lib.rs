@@ -0,0 +1,399 @@
+mod cli;
+
+use clap::ArgMatches;
+use environment::RuntimeContext;
+use eth1::http::{
+    get_block_number, get_deposit_count, get_deposit_logs_in_range, get_deposit_root,
+};
+use eth1::{DepositCache, DepositLog};
+use slog::{crit, debug, info, Logger};
+use std::cmp::min;
+use std::time::Duration;
+use tokio::runtime::current_thread::Runtime;
+use types::{ChainSpec, EthSpec, DEPOSIT_TREE_DEPTH};
+
+pub use cli::cli_app;
+
+/// The timeout for each request to the eth1 node.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The eth1 node and the deposit contract it is queried for.
+struct Contract {
+    endpoint: String,
+    address: String,
+}
+
+/// The first block at which the deposit tree rebuilt from the logs differs from the contract.
+#[derive(Debug, PartialEq)]
+struct Divergence {
+    /// The highest block at which the tree matches the contract, if any.
+    last_match: Option<u64>,
+    /// The lowest block at which the tree differs from the contract.
+    block: u64,
+    /// A description of the difference at `block`.
+    difference: String,
+}
+
+/// Run the eth1 manager, logging and returning an error if the operation did not succeed.
+pub fn run<T: EthSpec>(matches: &ArgMatches, context: RuntimeContext<T>) -> Result<(), String> {
+    let log = context.log.clone();
+    run_eth1_manager(matches, &context.eth2_config().spec, &log).map_err(|e| {
+        crit!(log, "Eth1 manager failed"; "error" => &e);
+        format!("Eth1 manager failed: {}", e)
+    })
+}
+
+/// Run the eth1 manager, returning an error if the operation did not succeed.
+fn run_eth1_manager(matches: &ArgMatches, spec: &ChainSpec, log: &Logger) -> Result<(), String> {
+    // The eth1 HTTP functions are async, a dedicated runtime allows them to be run one-by-one.
+    let mut runtime =
+        Runtime::new().map_err(|e| format!("Unable to start tokio runtime: {:?}", e))?;
+
+    match matches.subcommand() {
+        ("verify-deposits", Some(matches)) => verify_deposits(matches, &mut runtime, spec, log),
+        _ => Err("Invalid 'eth1_manager' command. See --help.".to_string()),
+    }
+}
+
+/// Parses the eth1 node endpoint and deposit contract address.
+fn parse_contract(matches: &ArgMatches) -> Result<Contract, String> {
+    Ok(Contract {
+        endpoint: clap_utils::parse_required(matches, "endpoint")?,
+        address: clap_utils::parse_required(matches, "deposit-contract")?,
+    })
+}
+
+/// Rebuild the deposit tree from the deposit logs and compare it to the deposit contract at a
+/// number of block heights.
+///
+/// If the tree diverges from the contract, the blocks between the last matching height and the
+/// first divergent height are searched for the first block at which they differ.
+fn verify_deposits(
+    matches: &ArgMatches,
+    runtime: &mut Runtime,
+    spec: &ChainSpec,
+    log: &Logger,
+) -> Result<(), String> {
+    let contract = &parse_contract(matches)?;
+    let deploy_block: u64 = clap_utils::parse_required(matches, "deploy-block")?;
+    let checkpoints: u64 = clap_utils::parse_required(matches, "checkpoints")?;
+    let blocks_per_log_query: u64 = clap_utils::parse_required(matches, "blocks-per-log-query")?;
+
+    if checkpoints == 0 || blocks_per_log_query == 0 {
+        return Err("--checkpoints and --blocks-per-log-query must be non-zero".to_string());
+    }
+
+    let head = runtime.block_on(get_block_number(&contract.endpoint, TIMEOUT))?;
+
+    if head < deploy_block {
+        return Err(format!(
+            "Eth1 node head {} is prior to the deploy block {}",
+            head, deploy_block
+        ));
+    }
+
+    let cache = download_deposits(
+        contract,
+        deploy_block,
+        head,
+        blocks_per_log_query,
+        runtime,
+        spec,
+        log,
+    )?;
+
+    info!(
+        log,
+        "Rebuilt deposit tree from logs";
+        "deposits" => cache.len(),
+        "head_block" => head,
+    );
+
+    let divergence = find_divergence(
+        deploy_block,
+        &checkpoint_heights(deploy_block, head, checkpoints),
+        |height| compare_at_height(&cache, contract, height, runtime),
+        log,
+    )?;
+
+    if let Some(divergence) = divergence {
+        let first_divergent_index = divergence
+            .last_match
+            .map_or(0, |block| deposits_at_height(&cache, block));
+
+        return Err(format!(
+            "Deposit tree diverges from the contract at block {}, {}. First divergent deposit \
+             index: {}",
+            divergence.block, divergence.difference, first_divergent_index
+        ));
+    }
+
+    info!(
+        log,
+        "Deposit tree matches the deposit contract";
+        "deposits" => cache.len(),
+        "checkpoints" => checkpoints,
+    );
+
+    Ok(())
+}
+
+/// Compares the deposit tree with the contract at each of the ascending `checkpoints`, using
+/// `compare` (which returns a description of the difference at a block, if any).
+///
+/// Assuming the tree matches the contract at every block prior to the first divergence, the
+/// blocks between the last matching checkpoint and the first divergent checkpoint are then
+/// bisected to find the lowest divergent block.
+fn find_divergence<F>(
+    deploy_block: u64,
+    checkpoints: &[u64],
+    mut compare: F,
+    log: &Logger,
+) -> Result<Option<Divergence>, String>
+where
+    F: FnMut(u64) -> Result<Option<String>, String>,
+{
+    // The highest block at which the tree is known to match the contract.
+    let mut last_match = None;
+
+    for &height in checkpoints {
+        let mut difference = match compare(height)? {
+            None => {
+                debug!(log, "Deposit tree matches contract"; "block" => height);
+                last_match = Some(height);
+                continue;
+            }
+            Some(difference) => difference,
+        };
+
+        let mut high = height;
+
+        loop {
+            let start = last_match.map_or(deploy_block, |block| block + 1);
+            if start >= high {
+                break;
+            }
+
+            let mid = start + (high - start) / 2;
+            match compare(mid)? {
+                None => last_match = Some(mid),
+                Some(mid_difference) => {
+                    high = mid;
+                    difference = mid_difference;
+                }
+            }
+        }
+
+        return Ok(Some(Divergence {
+            last_match,
+            block: high,
+            difference,
+        }));
+    }
+
+    Ok(None)
+}
+
+/// Download and parse every deposit log between `deploy_block` and `head`, inserting them into a
+/// new `DepositCache`.
+fn download_deposits(
+    contract: &Contract,
+    deploy_block: u64,
+    head: u64,
+    blocks_per_log_query: u64,
+    runtime: &mut Runtime,
+    spec: &ChainSpec,
+    log: &Logger,
+) -> Result<DepositCache, String> {
+    let mut cache = DepositCache::default();
+    let mut start = deploy_block;
+
+    while start <= head {
+        let end = min(start + blocks_per_log_query, head + 1);
+
+        let logs = runtime.block_on(get_deposit_logs_in_range(
+            &contract.endpoint,
+            &contract.address,
+            start..end,
+            TIMEOUT,
+        ))?;
+
+        for raw_log in logs {
+            let deposit_log = DepositLog::from_log(&raw_log, spec).map_err(|e| {
+                format!(
+                    "Unable to parse deposit log in blocks {}..{}: {}",
+                    start, end, e
+                )
+            })?;
+            cache
+                .insert_log(deposit_log)
+                .map_err(|e| format!("Unable to insert deposit log: {:?}", e))?;
+        }
+
+        debug!(
+            log,
+            "Downloaded deposit logs";
+            "up_to_block" => end - 1,
+            "deposits" => cache.len(),
+        );
+
+        start = end;
+    }
+
+    Ok(cache)
+}
+
+/// Compares the deposit count and root of the contract at the given block `height` with the tree
+/// rebuilt from the logs, returning a description of the difference if they do not match.
+fn compare_at_height(
+    cache: &DepositCache,
+    contract: &Contract,
+    height: u64,
+    runtime: &mut Runtime,
+) -> Result<Option<String>, String> {
+    let contract_count = runtime
+        .block_on(get_deposit_count(
+            &contract.endpoint,
+            &contract.address,
+            height,
+            TIMEOUT,
+        ))?
+        .unwrap_or(0);
+    let contract_root = runtime.block_on(get_deposit_root(
+        &contract.endpoint,
+        &contract.address,
+        height,
+        TIMEOUT,
+    ))?;
+
+    let local_count = deposits_at_height(cache, height);
+
+    if local_count != contract_count {
+        return Ok(Some(format!(
+            "the contract has {} deposits whilst the logs have {}",
+            contract_count, local_count
+        )));
+    }
+
+    let local_root = cache
+        .get_deposit_root(local_count, DEPOSIT_TREE_DEPTH)
+        .map_err(|e| format!("Unable to compute deposit root: {:?}", e))?;
+
+    match contract_root {
+        Some(contract_root) if contract_root != local_root => Ok(Some(format!(
+            "the contract root is {:?} whilst the logs produce {:?}",
+            contract_root, local_root
+        ))),
+        _ => Ok(None),
+    }
+}
+
+/// Returns the number of deposits in `cache` which were made at or before the given block
+/// `height`.
+fn deposits_at_height(cache: &DepositCache, height: u64) -> u64 {
+    cache
+        .iter()
+        .take_while(|deposit_log| deposit_log.block_number <= height)
+        .count() as u64
+}
+
+/// Returns up to `count` block heights, evenly spaced between `first` and `last`. The final
+/// height is always `last`.
+fn checkpoint_heights(first: u64, last: u64, count: u64) -> Vec<u64> {
+    let span = last - first;
+    let mut heights = (1..=count)
+        .map(|i| first + span * i / count)
+        .collect::<Vec<_>>();
+    heights.dedup();
+    heights
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slog::o;
+
+    const DEPLOY_BLOCK: u64 = 100;
+
+    fn null_logger() -> Logger {
+        Logger::root(slog::Discard, o!())
+    }
+
+    #[test]
+    fn checkpoint_heights_end_at_the_last_block() {
+        assert_eq!(checkpoint_heights(100, 200, 4), vec![125, 150, 175, 200]);
+        assert_eq!(checkpoint_heights(100, 200, 1), vec![200]);
+        assert_eq!(checkpoint_heights(100, 102, 5), vec![100, 101, 102]);
+        assert_eq!(checkpoint_heights(100, 100, 3), vec![100]);
+    }
+
+    /// Finds the divergence from a contract which differs from the tree at and after the
+    /// `diverges_at` block, returning it with the number of blocks compared.
+    fn divergence(diverges_at: u64, head: u64) -> (Option<Divergence>, usize) {
+        let mut comparisons = 0;
+        let divergence = find_divergence(
+            DEPLOY_BLOCK,
+            &checkpoint_heights(DEPLOY_BLOCK, head, 4),
+            |height| {
+                comparisons += 1;
+                if height >= diverges_at {
+                    Ok(Some(format!("differs at {}", height)))
+                } else {
+                    Ok(None)
+                }
+            },
+            &null_logger(),
+        )
+        .expect("should compare");
+
+        (divergence, comparisons)
+    }
+
+    #[test]
+    fn no_divergence() {
+        let (divergence, comparisons) = divergence(u64::max_value(), 200);
+        assert_eq!(divergence, None);
+        assert_eq!(comparisons, 4, "only the checkpoints should be compared");
+    }
+
+    #[test]
+    fn bisects_to_the_first_divergent_block() {
+        for &diverges_at in &[101, 126, 149, 150, 163, 200] {
+            let (divergence, comparisons) = divergence(diverges_at, 200);
+            assert_eq!(
+                divergence,
+                Some(Divergence {
+                    last_match: Some(diverges_at - 1),
+                    block: diverges_at,
+                    difference: format!("differs at {}", diverges_at),
+                })
+            );
+            // At most 4 checkpoints and a bisection of the 25 blocks between two of them.
+            assert!(comparisons <= 4 + 5, "{} comparisons", comparisons);
+        }
+    }
+
+    #[test]
+    fn divergence_at_the_deploy_block() {
+        let (divergence, _) = divergence(DEPLOY_BLOCK, 200);
+        assert_eq!(
+            divergence,
+            Some(Divergence {
+                last_match: None,
+                block: DEPLOY_BLOCK,
+                difference: format!("differs at {}", DEPLOY_BLOCK),
+            })
+        );
+    }
+
+    #[test]
+    fn comparison_errors_are_returned() {
+        let result = find_divergence(
+            DEPLOY_BLOCK,
+            &[150, 200],
+            |_| Err("eth1 node unavailable".to_string()),
+            &null_logger(),
+        );
+        assert_eq!(result, Err("eth1 node unavailable".to_string()));
+    }
+}
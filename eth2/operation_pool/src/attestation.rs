@@ -1,4 +1,5 @@
 use crate::max_cover::MaxCover;
+use std::collections::HashMap;
 use types::{Attestation, BeaconState, BitList, EthSpec};
 
 pub struct AttMaxCover<'a, T: EthSpec> {
@@ -6,17 +7,51 @@ pub struct AttMaxCover<'a, T: EthSpec> {
     att: &'a Attestation<T>,
     /// Bitfield of validators that are covered by this attestation.
     fresh_validators: BitList<T::MaxValidatorsPerCommittee>,
+    /// The validator index and effective balance of each member of the attesting committee.
+    committee: Vec<(usize, u64)>,
+    /// The total effective balance of the fresh validators.
+    fresh_balance: u64,
 }
 
 impl<'a, T: EthSpec> AttMaxCover<'a, T> {
-    pub fn new(
-        att: &'a Attestation<T>,
-        fresh_validators: BitList<T::MaxValidatorsPerCommittee>,
-    ) -> Self {
-        Self {
+    /// Returns `None` if the committee of `att` is not known to `state`.
+    pub fn new(att: &'a Attestation<T>, state: &BeaconState<T>) -> Option<Self> {
+        let committee = state
+            .get_beacon_committee(att.data.slot, att.data.index)
+            .ok()?
+            .committee
+            .iter()
+            .map(|&index| {
+                let effective_balance = state.validators.get(index)?.effective_balance;
+                Some((index, effective_balance))
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let mut att_max_cover = Self {
             att,
-            fresh_validators,
-        }
+            fresh_validators: earliest_attestation_validators(att, state),
+            committee,
+            fresh_balance: 0,
+        };
+        att_max_cover.fresh_balance = att_max_cover.compute_fresh_balance();
+
+        Some(att_max_cover)
+    }
+
+    /// Returns the validator index and effective balance of each validator for which this
+    /// attestation would be their first in the epoch.
+    pub fn fresh_attesters<'b>(&'b self) -> impl Iterator<Item = (usize, u64)> + 'b {
+        self.committee
+            .iter()
+            .enumerate()
+            .filter(move |(i, _)| self.fresh_validators.get(*i).unwrap_or(false))
+            .map(|(_, attester)| *attester)
+    }
+
+    fn compute_fresh_balance(&self) -> u64 {
+        self.fresh_attesters()
+            .map(|(_, effective_balance)| effective_balance)
+            .sum()
     }
 }
 
@@ -44,14 +79,28 @@ impl<'a, T: EthSpec> MaxCover for AttMaxCover<'a, T> {
     ) {
         if self.att.data.slot == best_att.data.slot && self.att.data.index == best_att.data.index {
             self.fresh_validators.difference_inplace(covered_validators);
+            self.fresh_balance = self.compute_fresh_balance();
         }
     }
 
-    fn score(&self) -> usize {
-        self.fresh_validators.num_set_bits()
+    /// The total effective balance of the fresh validators, which is proportional to the reward
+    /// for including the attestation.
+    fn score(&self) -> u64 {
+        self.fresh_balance
     }
 }
 
+/// Returns the total effective balance of the validators which have a fresh attestation in any of
+/// `attestations`, counting each validator once.
+pub fn fresh_attesting_balance<T: EthSpec>(attestations: &[AttMaxCover<T>]) -> u64 {
+    attestations
+        .iter()
+        .flat_map(|att| att.fresh_attesters())
+        .collect::<HashMap<_, _>>()
+        .values()
+        .sum()
+}
+
 /// Extract the validators for which `attestation` would be their earliest in the epoch.
 ///
 /// The reward paid to a proposer for including an attestation is proportional to the number
@@ -5,7 +5,7 @@ mod persistence;
 
 pub use persistence::PersistedOperationPool;
 
-use attestation::{earliest_attestation_validators, AttMaxCover};
+use attestation::{earliest_attestation_validators, fresh_attesting_balance, AttMaxCover};
 use attestation_id::AttestationId;
 use max_cover::maximum_cover;
 use parking_lot::RwLock;
@@ -21,10 +21,35 @@ use state_processing::per_block_processing::{
 use std::collections::{btree_map::Entry, hash_map, BTreeMap, HashMap, HashSet};
 use std::marker::PhantomData;
 use types::{
-    typenum::Unsigned, Attestation, AttesterSlashing, BeaconState, ChainSpec, Deposit, EthSpec,
-    ProposerSlashing, Validator, VoluntaryExit,
+    typenum::Unsigned, Attestation, AttestationData, AttesterSlashing, BeaconState, ChainSpec,
+    Deposit, EthSpec, ProposerSlashing, Validator, VoluntaryExit,
 };
 
+/// The attestations chosen for inclusion in a block, as returned by
+/// `OperationPool::get_attestation_packing`.
+#[derive(Debug, PartialEq)]
+pub struct AttestationPacking<T: EthSpec> {
+    pub attestations: Vec<Attestation<T>>,
+    /// The total effective balance of the validators with a fresh attestation in the pool.
+    pub available_balance: u64,
+    /// The total effective balance of the validators with a fresh attestation in `attestations`.
+    pub included_balance: u64,
+}
+
+impl<T: EthSpec> AttestationPacking<T> {
+    /// The fraction of the available attesting balance which is included, between `0.0` and
+    /// `1.0`.
+    ///
+    /// Returns `1.0` if there were no fresh attestations available.
+    pub fn efficiency(&self) -> f64 {
+        if self.available_balance == 0 {
+            1.0
+        } else {
+            self.included_balance as f64 / self.available_balance as f64
+        }
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct OperationPool<T: EthSpec + Default> {
     /// Map from attestation ID (see below) to vectors of attestations.
@@ -101,6 +126,27 @@ impl<T: EthSpec> OperationPool<T> {
         Ok(())
     }
 
+    /// Returns the attestation in the pool for the given `data` which has the most signers, if
+    /// any.
+    ///
+    /// Attestations are aggregated on insertion, so this will typically be the aggregate of all
+    /// the non-overlapping attestations received for `data`.
+    pub fn get_aggregated_attestation(
+        &self,
+        data: &AttestationData,
+        state: &BeaconState<T>,
+        spec: &ChainSpec,
+    ) -> Option<Attestation<T>> {
+        let id = AttestationId::from_data(data, state, spec);
+
+        self.attestations.read().get(&id).and_then(|attestations| {
+            attestations
+                .iter()
+                .max_by_key(|attestation| attestation.aggregation_bits.num_set_bits())
+                .cloned()
+        })
+    }
+
     /// Total number of attestations in the pool, including attestations for the same data.
     pub fn num_attestations(&self) -> usize {
         self.attestations.read().values().map(Vec::len).sum()
@@ -112,6 +158,19 @@ impl<T: EthSpec> OperationPool<T> {
         state: &BeaconState<T>,
         spec: &ChainSpec,
     ) -> Vec<Attestation<T>> {
+        self.get_attestation_packing(state, spec).attestations
+    }
+
+    /// Get a list of attestations for inclusion in a block, along with the attesting balance they
+    /// cover.
+    ///
+    /// Attestations are chosen greedily to maximise the total effective balance of the validators
+    /// for which they are the first attestation of the epoch, up to `MAX_ATTESTATIONS`.
+    pub fn get_attestation_packing(
+        &self,
+        state: &BeaconState<T>,
+        spec: &ChainSpec,
+    ) -> AttestationPacking<T> {
         // Attestations for the current fork, which may be from the current or previous epoch.
         let prev_epoch = state.previous_epoch();
         let current_epoch = state.current_epoch();
@@ -135,9 +194,25 @@ impl<T: EthSpec> OperationPool<T> {
                 )
                 .is_ok()
             })
-            .map(|att| AttMaxCover::new(att, earliest_attestation_validators(att, state)));
+            .filter_map(|att| AttMaxCover::new(att, state))
+            .collect::<Vec<_>>();
+
+        let available_balance = fresh_attesting_balance(&valid_attestations);
+
+        let attestations = maximum_cover(valid_attestations, T::MaxAttestations::to_usize());
+
+        let included_balance = fresh_attesting_balance(
+            &attestations
+                .iter()
+                .filter_map(|att| AttMaxCover::new(att, state))
+                .collect::<Vec<_>>(),
+        );
 
-        maximum_cover(valid_attestations, T::MaxAttestations::to_usize())
+        AttestationPacking {
+            attestations,
+            available_balance,
+            included_balance,
+        }
     }
 
     /// Remove attestations which are too old to be included in a block.
@@ -223,6 +298,27 @@ impl<T: EthSpec> OperationPool<T> {
         Ok(())
     }
 
+    /// Returns `true` if the pool contains a proposer slashing for the given proposer.
+    pub fn contains_proposer_slashing(&self, proposer_index: u64) -> bool {
+        self.proposer_slashings.read().contains_key(&proposer_index)
+    }
+
+    /// Returns `true` if the pool contains a proposer or attester slashing which slashes the given
+    /// validator.
+    pub fn has_pending_slashing(&self, validator_index: u64) -> bool {
+        self.contains_proposer_slashing(validator_index)
+            || self.attester_slashings.read().values().any(|slashing| {
+                slashing
+                    .attestation_1
+                    .attesting_indices
+                    .contains(&validator_index)
+                    && slashing
+                        .attestation_2
+                        .attesting_indices
+                        .contains(&validator_index)
+            })
+    }
+
     /// Compute the tuple ID that is used to identify an attester slashing.
     ///
     /// Depends on the fork field of the state, but not on the state's epoch.
@@ -250,6 +346,51 @@ impl<T: EthSpec> OperationPool<T> {
         Ok(())
     }
 
+    /// Returns `true` if every validator that could be slashed by `slashing` is either already
+    /// slashed in `state` or would be slashed by an operation in the pool.
+    ///
+    /// Does not verify `slashing`. A slashing without any slashable validators is never
+    /// redundant, so that it is rejected by verification instead.
+    pub fn attester_slashing_is_redundant(
+        &self,
+        slashing: &AttesterSlashing<T>,
+        state: &BeaconState<T>,
+    ) -> bool {
+        let attesters_1 = slashing
+            .attestation_1
+            .attesting_indices
+            .iter()
+            .collect::<HashSet<_>>();
+        let candidates = slashing
+            .attestation_2
+            .attesting_indices
+            .iter()
+            .filter(|index| attesters_1.contains(index))
+            .copied()
+            .collect::<Vec<_>>();
+
+        if candidates.is_empty() {
+            return false;
+        }
+
+        let proposer_slashings = self.proposer_slashings.read();
+        let attester_slashings = self.attester_slashings.read();
+
+        candidates.into_iter().all(|index| {
+            let slashed_in_state = state
+                .validators
+                .get(index as usize)
+                .map_or(false, |validator| validator.slashed);
+            let slashed_by_pool = proposer_slashings.contains_key(&index)
+                || attester_slashings.values().any(|pooled| {
+                    pooled.attestation_1.attesting_indices.contains(&index)
+                        && pooled.attestation_2.attesting_indices.contains(&index)
+                });
+
+            slashed_in_state || slashed_by_pool
+        })
+    }
+
     /// Get proposer and attester slashings for inclusion in a block.
     ///
     /// This function computes both types of slashings together, because
@@ -349,6 +490,11 @@ impl<T: EthSpec> OperationPool<T> {
         Ok(())
     }
 
+    /// Returns `true` if the pool contains a voluntary exit for the given validator.
+    pub fn contains_voluntary_exit(&self, validator_index: u64) -> bool {
+        self.voluntary_exits.read().contains_key(&validator_index)
+    }
+
     /// Get a list of voluntary exits for inclusion in a block.
     pub fn get_voluntary_exits(
         &self,
@@ -549,6 +695,37 @@ mod tests {
             .collect()
     }
 
+    #[test]
+    fn attester_slashing_redundancy() {
+        let rng = &mut XorShiftRng::from_seed([42; 16]);
+        let spec = MinimalEthSpec::default_spec();
+        let (mut state, _keypairs) =
+            TestingBeaconStateBuilder::from_deterministic_keypairs(4, &spec).build();
+        let op_pool = OperationPool::<MinimalEthSpec>::new();
+
+        let mut slashing = AttesterSlashing::<MinimalEthSpec>::random_for_test(rng);
+        slashing.attestation_1.attesting_indices = vec![0, 1, 2].into();
+        slashing.attestation_2.attesting_indices = vec![1, 2, 3].into();
+
+        assert!(!op_pool.attester_slashing_is_redundant(&slashing, &state));
+
+        state.validators[1].slashed = true;
+        assert!(!op_pool.attester_slashing_is_redundant(&slashing, &state));
+
+        op_pool
+            .proposer_slashings
+            .write()
+            .insert(2, ProposerSlashing::random_for_test(rng));
+        assert!(op_pool.contains_proposer_slashing(2));
+        assert!(op_pool.attester_slashing_is_redundant(&slashing, &state));
+
+        slashing.attestation_2.attesting_indices = vec![3].into();
+        assert!(
+            !op_pool.attester_slashing_is_redundant(&slashing, &state),
+            "a slashing without slashable validators is never redundant"
+        );
+    }
+
     fn test_state(rng: &mut XorShiftRng) -> (ChainSpec, BeaconState<MainnetEthSpec>) {
         let spec = MainnetEthSpec::default_spec();
 
@@ -749,6 +926,116 @@ mod tests {
             assert_eq!(op_pool.num_attestations(), 0);
         }
 
+        /// The aggregated attestation for some data should include all the inserted signers.
+        #[test]
+        fn attestation_get_aggregated() {
+            let (ref mut state, ref keypairs, ref spec) =
+                attestation_test_state::<MainnetEthSpec>(1);
+
+            let op_pool = OperationPool::new();
+
+            let slot = state.slot - 1;
+            let committees = state
+                .get_beacon_committees_at_slot(slot)
+                .unwrap()
+                .into_iter()
+                .map(BeaconCommittee::into_owned)
+                .collect::<Vec<_>>();
+
+            for bc in &committees {
+                let mut data = None;
+                let step_size = 2;
+                for i in (0..bc.committee.len()).step_by(step_size) {
+                    let att = signed_attestation(
+                        &bc.committee,
+                        bc.index,
+                        keypairs,
+                        i..i + step_size,
+                        slot,
+                        state,
+                        spec,
+                        None,
+                    );
+                    data = Some(att.data.clone());
+                    op_pool.insert_attestation(att, state, spec).unwrap();
+                }
+
+                let data = data.expect("committee should not be empty");
+                let aggregate = op_pool
+                    .get_aggregated_attestation(&data, state, spec)
+                    .expect("should have an aggregate for known data");
+                assert_eq!(
+                    aggregate.aggregation_bits.num_set_bits(),
+                    bc.committee.len()
+                );
+
+                let mut unknown_data = data;
+                unknown_data.slot += 1;
+                assert_eq!(
+                    op_pool.get_aggregated_attestation(&unknown_data, state, spec),
+                    None
+                );
+            }
+        }
+
+        /// The packing should cover the balance of every fresh attester in the pool when all of
+        /// the attestations fit in a block.
+        #[test]
+        fn attestation_packing_balance() {
+            let (ref mut state, ref keypairs, ref spec) =
+                attestation_test_state::<MainnetEthSpec>(1);
+
+            let op_pool = OperationPool::new();
+
+            let slot = state.slot - 1;
+            let committees = state
+                .get_beacon_committees_at_slot(slot)
+                .unwrap()
+                .into_iter()
+                .map(BeaconCommittee::into_owned)
+                .collect::<Vec<_>>();
+
+            for bc in &committees {
+                let att = signed_attestation(
+                    &bc.committee,
+                    bc.index,
+                    keypairs,
+                    ..,
+                    slot,
+                    state,
+                    spec,
+                    None,
+                );
+                op_pool.insert_attestation(att, state, spec).unwrap();
+            }
+
+            let committee_balance = committees
+                .iter()
+                .flat_map(|bc| &bc.committee)
+                .map(|&i| state.validators[i].effective_balance)
+                .sum::<u64>();
+
+            let packing = op_pool.get_attestation_packing(state, spec);
+            assert_eq!(packing.attestations.len(), committees.len());
+            assert_eq!(packing.available_balance, committee_balance);
+            assert_eq!(packing.included_balance, committee_balance);
+
+            // An attester whose attestation is already in the state is not counted again.
+            state
+                .current_epoch_attestations
+                .push(PendingAttestation {
+                    aggregation_bits: packing.attestations[0].aggregation_bits.clone(),
+                    data: packing.attestations[0].data.clone(),
+                    inclusion_delay: 0,
+                    proposer_index: 0,
+                })
+                .unwrap();
+
+            let packing = op_pool.get_attestation_packing(state, spec);
+            assert!(packing.attestations.is_empty());
+            assert_eq!(packing.available_balance, 0);
+        }
+
         /// Adding an attestation already in the pool should not increase the size of the pool.
         #[test]
         fn attestation_duplicate() {
@@ -18,8 +18,8 @@ pub trait MaxCover {
     fn covering_set(&self) -> &Self::Set;
     /// Update the set of items covered, for the inclusion of some object in the solution.
     fn update_covering_set(&mut self, max_obj: &Self::Object, max_set: &Self::Set);
-    /// The quality of this item's covering set, usually its cardinality.
-    fn score(&self) -> usize;
+    /// The quality of this item's covering set, usually its cardinality or total weight.
+    fn score(&self) -> u64;
 }
 
 /// Helper struct to track which items of the input are still available for inclusion.
@@ -109,8 +109,33 @@ mod test {
             std::mem::swap(self, &mut difference);
         }
 
-        fn score(&self) -> usize {
-            self.len()
+        fn score(&self) -> u64 {
+            self.len() as u64
+        }
+    }
+
+    /// A set of integers, each weighted by its own value.
+    #[derive(Debug, Clone, PartialEq)]
+    struct WeightedSet(HashSet<u64>);
+
+    impl MaxCover for WeightedSet {
+        type Object = Self;
+        type Set = HashSet<u64>;
+
+        fn object(&self) -> Self {
+            self.clone()
+        }
+
+        fn covering_set(&self) -> &HashSet<u64> {
+            &self.0
+        }
+
+        fn update_covering_set(&mut self, _: &Self, other: &HashSet<u64>) {
+            self.0 = &self.0 - other;
+        }
+
+        fn score(&self) -> u64 {
+            self.0.iter().sum()
         }
     }
 
@@ -150,6 +175,22 @@ mod test {
         }
     }
 
+    // A single heavy element should be preferred to many light ones.
+    #[test]
+    fn weighted() {
+        let sets = vec![
+            WeightedSet(HashSet::from_iter(vec![1, 2, 3])),
+            WeightedSet(HashSet::from_iter(vec![10])),
+            WeightedSet(HashSet::from_iter(vec![1, 2, 3, 4])),
+        ];
+
+        let cover = maximum_cover(sets.clone(), 1);
+        assert_eq!(cover, vec![sets[1].clone()]);
+
+        let cover = maximum_cover(sets.clone(), 3);
+        assert_eq!(cover, vec![sets[1].clone(), sets[2].clone()]);
+    }
+
     fn quality<T: Eq + Hash>(solution: &[HashSet<T>]) -> usize {
         solution.iter().map(HashSet::len).sum()
     }
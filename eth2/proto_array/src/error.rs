@@ -0,0 +1,26 @@
+use types::{Epoch, Hash256};
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Error {
+    FinalizedNodeUnknown(Hash256),
+    JustifiedNodeUnknown(Hash256),
+    InvalidNodeIndex(usize),
+    InvalidJustifiedIndex(usize),
+    InvalidBestDescendant(usize),
+    InvalidParentDelta(usize),
+    InvalidNodeDelta(usize),
+    DeltaOverflow(usize),
+    IndexOverflow(&'static str),
+    InvalidDeltaLen {
+        deltas: usize,
+        indices: usize,
+    },
+    InvalidBestNode {
+        start_root: Hash256,
+        justified_epoch: Epoch,
+        finalized_epoch: Epoch,
+        head_root: Hash256,
+        head_justified_epoch: Epoch,
+        head_finalized_epoch: Epoch,
+    },
+}
@@ -0,0 +1,34 @@
+//! An array-based implementation of LMD GHOST fork choice.
+//!
+//! Blocks are stored in a flat array where each node holds the index of its parent, along with
+//! the index of its best child and best descendant. Applying new votes updates these pointers in
+//! a single pass over the array, after which the head can be found without searching the tree.
+mod error;
+mod proto_array;
+mod proto_array_fork_choice;
+mod ssz_container;
+
+pub use crate::proto_array_fork_choice::{ProtoArrayForkChoice, DEFAULT_PRUNE_THRESHOLD};
+pub use crate::ssz_container::SszContainer;
+pub use error::Error;
+
+use types::{Epoch, Hash256};
+
+/// A snapshot of a single node in the fork choice block tree, used for introspection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForkChoiceNode {
+    /// The block root of this node.
+    pub root: Hash256,
+    /// The root of the parent node, if it is in the tree.
+    pub parent_root: Option<Hash256>,
+    /// The roots of the child nodes.
+    pub children: Vec<Hash256>,
+    /// The justified epoch of the state of this block.
+    pub justified_epoch: Epoch,
+    /// The finalized epoch of the state of this block.
+    pub finalized_epoch: Epoch,
+    /// The weight of this node, as at the last call to `find_head`.
+    pub weight: u64,
+    /// The head that would be selected if the search was started at this node.
+    pub best_descendant: Hash256,
+}
@@ -0,0 +1,899 @@
+use crate::error::Error;
+use crate::proto_array::ProtoArray;
+use crate::ssz_container::SszContainer;
+use crate::ForkChoiceNode;
+use parking_lot::RwLock;
+use ssz::{Decode, Encode};
+use ssz_derive::{Decode, Encode};
+use std::collections::HashMap;
+use types::{Epoch, Hash256, Slot};
+
+pub const DEFAULT_PRUNE_THRESHOLD: usize = 256;
+
+/// The latest message of some validator.
+///
+/// The `current_root` is the root that has been applied to the weights of the tree, whilst the
+/// `next_root` is the most recent root, which will be applied at the next call to `find_head`.
+#[derive(Default, PartialEq, Clone, Debug, Encode, Decode)]
+pub struct VoteTracker {
+    current_root: Hash256,
+    next_root: Hash256,
+    next_epoch: Epoch,
+}
+
+/// A Vec-wrapper which will grow to match any request.
+///
+/// E.g., a `get` or `insert` to an out-of-bounds element will cause the Vec to grow (using
+/// Default) to the smallest size required to fulfill the request.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct ElasticList<T>(pub Vec<T>);
+
+impl<T> ElasticList<T>
+where
+    T: Default,
+{
+    fn ensure(&mut self, i: usize) {
+        if self.0.len() <= i {
+            self.0.resize_with(i + 1, Default::default);
+        }
+    }
+
+    pub fn get_mut(&mut self, i: usize) -> &mut T {
+        self.ensure(i);
+        &mut self.0[i]
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.0.iter_mut()
+    }
+}
+
+/// A thread-safe LMD GHOST fork choice, backed by a `ProtoArray`.
+///
+/// Votes are recorded as they arrive, but they are only applied to the tree when `find_head` is
+/// called. At that point, the difference between each validator's previously-applied vote (and
+/// balance) and their latest vote (and balance) is computed and applied to the tree in a single
+/// pass.
+pub struct ProtoArrayForkChoice {
+    pub(crate) proto_array: RwLock<ProtoArray>,
+    pub(crate) votes: RwLock<ElasticList<VoteTracker>>,
+    pub(crate) balances: RwLock<Vec<u64>>,
+}
+
+impl PartialEq for ProtoArrayForkChoice {
+    fn eq(&self, other: &Self) -> bool {
+        *self.proto_array.read() == *other.proto_array.read()
+            && *self.votes.read() == *other.votes.read()
+            && *self.balances.read() == *other.balances.read()
+    }
+}
+
+impl ProtoArrayForkChoice {
+    /// Instantiate a new fork choice, with the given finalized block as the root of the tree.
+    pub fn new(
+        finalized_block_slot: Slot,
+        justified_epoch: Epoch,
+        finalized_epoch: Epoch,
+        finalized_root: Hash256,
+    ) -> Result<Self, String> {
+        let mut proto_array = ProtoArray {
+            prune_threshold: DEFAULT_PRUNE_THRESHOLD,
+            justified_epoch,
+            finalized_epoch,
+            nodes: Vec::with_capacity(1),
+            indices: HashMap::with_capacity(1),
+        };
+
+        proto_array
+            .on_block(
+                finalized_block_slot,
+                finalized_root,
+                None,
+                justified_epoch,
+                finalized_epoch,
+            )
+            .map_err(|e| format!("Failed to add finalized block to proto_array: {:?}", e))?;
+
+        Ok(Self {
+            proto_array: RwLock::new(proto_array),
+            votes: RwLock::new(ElasticList::default()),
+            balances: RwLock::new(vec![]),
+        })
+    }
+
+    /// Record that the validator with `validator_index` voted for `block_root` in the given
+    /// `target_epoch`.
+    ///
+    /// Votes from earlier epochs than the validator's latest vote are ignored.
+    pub fn process_attestation(
+        &self,
+        validator_index: usize,
+        block_root: Hash256,
+        target_epoch: Epoch,
+    ) -> Result<(), String> {
+        let mut votes = self.votes.write();
+        let vote = votes.get_mut(validator_index);
+
+        if target_epoch > vote.next_epoch || *vote == VoteTracker::default() {
+            vote.next_root = block_root;
+            vote.next_epoch = target_epoch;
+        }
+
+        Ok(())
+    }
+
+    /// Register a block with the fork choice.
+    ///
+    /// The `justified_epoch` and `finalized_epoch` must be those of the block's post-state.
+    pub fn process_block(
+        &self,
+        slot: Slot,
+        block_root: Hash256,
+        parent_root: Hash256,
+        justified_epoch: Epoch,
+        finalized_epoch: Epoch,
+    ) -> Result<(), String> {
+        self.proto_array
+            .write()
+            .on_block(
+                slot,
+                block_root,
+                Some(parent_root),
+                justified_epoch,
+                finalized_epoch,
+            )
+            .map_err(|e| format!("process_block_error: {:?}", e))
+    }
+
+    /// Apply all new votes and balance changes to the tree, then return the head of the chain,
+    /// starting the search at `justified_root`.
+    ///
+    /// `justified_state_balances` must contain the effective balance of each validator in the
+    /// justified state, with a balance of zero for any validator that is not active.
+    pub fn find_head(
+        &self,
+        justified_epoch: Epoch,
+        justified_root: Hash256,
+        finalized_epoch: Epoch,
+        justified_state_balances: &[u64],
+    ) -> Result<Hash256, String> {
+        let mut proto_array = self.proto_array.write();
+        let mut votes = self.votes.write();
+        let mut old_balances = self.balances.write();
+
+        let new_balances = justified_state_balances;
+
+        let deltas = compute_deltas(
+            &proto_array.indices,
+            &mut votes,
+            &old_balances,
+            new_balances,
+        )
+        .map_err(|e| format!("find_head compute_deltas failed: {:?}", e))?;
+
+        proto_array
+            .apply_score_changes(deltas, justified_epoch, finalized_epoch)
+            .map_err(|e| format!("find_head apply_score_changes failed: {:?}", e))?;
+
+        *old_balances = new_balances.to_vec();
+
+        proto_array
+            .find_head(&justified_root)
+            .map_err(|e| format!("find_head failed: {:?}", e))
+    }
+
+    /// Prune all nodes prior to the `finalized_root`.
+    pub fn update_finalized_root(&self, finalized_root: Hash256) -> Result<(), String> {
+        self.proto_array
+            .write()
+            .maybe_prune(finalized_root)
+            .map_err(|e| format!("update_finalized_root failed: {:?}", e))
+    }
+
+    /// Sets the minimum number of nodes that must precede the finalized node before the tree is
+    /// pruned.
+    pub fn set_prune_threshold(&self, prune_threshold: usize) {
+        self.proto_array.write().prune_threshold = prune_threshold;
+    }
+
+    /// Returns the number of blocks in the tree.
+    pub fn len(&self) -> usize {
+        self.proto_array.read().nodes.len()
+    }
+
+    /// Returns `true` if there are no blocks in the tree.
+    pub fn is_empty(&self) -> bool {
+        self.proto_array.read().nodes.is_empty()
+    }
+
+    /// Returns `true` if the block with `block_root` is in the tree.
+    pub fn contains_block(&self, block_root: &Hash256) -> bool {
+        self.proto_array.read().indices.contains_key(block_root)
+    }
+
+    /// Returns the slot of the block with `block_root`, if it is in the tree.
+    pub fn block_slot(&self, block_root: &Hash256) -> Option<Slot> {
+        let proto_array = self.proto_array.read();
+
+        let i = proto_array.indices.get(block_root)?;
+        let block = proto_array.nodes.get(*i)?;
+
+        Some(block.slot)
+    }
+
+    /// Returns the latest message for a given validator index, as `(block_root, target_epoch)`.
+    pub fn latest_message(&self, validator_index: usize) -> Option<(Hash256, Epoch)> {
+        let votes = self.votes.read();
+
+        votes
+            .0
+            .get(validator_index)
+            .filter(|vote| **vote != VoteTracker::default())
+            .map(|vote| (vote.next_root, vote.next_epoch))
+    }
+
+    /// Returns a snapshot of every node in the block tree, for debugging and visualisation.
+    ///
+    /// Weights are only as fresh as the last call to `find_head`.
+    pub fn nodes(&self) -> Vec<ForkChoiceNode> {
+        let proto_array = self.proto_array.read();
+
+        let mut children = vec![vec![]; proto_array.nodes.len()];
+        for node in &proto_array.nodes {
+            if let Some(parent) = node.parent.and_then(|i| children.get_mut(i)) {
+                parent.push(node.root);
+            }
+        }
+
+        proto_array
+            .nodes
+            .iter()
+            .zip(children.into_iter())
+            .map(|(node, children)| ForkChoiceNode {
+                root: node.root,
+                parent_root: node
+                    .parent
+                    .and_then(|i| proto_array.nodes.get(i))
+                    .map(|parent| parent.root),
+                children,
+                justified_epoch: node.justified_epoch,
+                finalized_epoch: node.finalized_epoch,
+                weight: node.weight,
+                best_descendant: node
+                    .best_descendant
+                    .and_then(|i| proto_array.nodes.get(i))
+                    .map_or(node.root, |descendant| descendant.root),
+            })
+            .collect()
+    }
+
+    /// Returns an `SszContainer`, which contains all necessary information to restore the state
+    /// of `Self` at some later point.
+    pub fn to_ssz_container(&self) -> SszContainer {
+        SszContainer::from(self)
+    }
+
+    /// Creates a new `Self` from the given `SszContainer`, restoring `Self` to the same state of
+    /// the `Self` that created the `SszContainer`.
+    pub fn from_ssz_container(ssz_container: SszContainer) -> Self {
+        ssz_container.into()
+    }
+
+    /// Returns the SSZ encoding of `self`.
+    pub fn as_bytes(&self) -> Vec<u8> {
+        self.to_ssz_container().as_ssz_bytes()
+    }
+
+    /// Decodes `Self` from bytes produced by `Self::as_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        SszContainer::from_ssz_bytes(bytes)
+            .map(Self::from_ssz_container)
+            .map_err(|e| format!("Failed to decode ProtoArrayForkChoice: {:?}", e))
+    }
+}
+
+/// Returns a list of `deltas`, where there is one delta for each of the indices in
+/// `0..indices.len()`.
+///
+/// The deltas are formed by a change between `old_balances` and `new_balances`, and/or a change of
+/// vote in `votes`.
+///
+/// ## Errors
+///
+/// - If a value in `indices` is greater to or equal to `indices.len()`.
+/// - If some `Hash256` in `votes` is not a key in `indices` (except for `Hash256::zero()`, this is
+///   always valid).
+fn compute_deltas(
+    indices: &HashMap<Hash256, usize>,
+    votes: &mut ElasticList<VoteTracker>,
+    old_balances: &[u64],
+    new_balances: &[u64],
+) -> Result<Vec<i64>, Error> {
+    let mut deltas = vec![0_i64; indices.len()];
+
+    for (val_index, vote) in votes.iter_mut().enumerate() {
+        // There is no need to create a score change if the validator has never voted or both their
+        // votes are for the zero hash (alias to the genesis block).
+        if vote.current_root == Hash256::zero() && vote.next_root == Hash256::zero() {
+            continue;
+        }
+
+        // If the validator was not included in the _old_ balances (i.e., it did not exist yet)
+        // then say its balance was zero.
+        let old_balance = old_balances.get(val_index).copied().unwrap_or(0);
+
+        // If the validators vote is not known in the _new_ balances, then use a balance of zero.
+        //
+        // It is possible that there is a vote for an unknown validator if we change our justified
+        // state to a new state with a higher epoch that is on a different fork because that fork
+        // may have on-boarded less validators than the prior fork.
+        let new_balance = new_balances.get(val_index).copied().unwrap_or(0);
+
+        if vote.current_root != vote.next_root || old_balance != new_balance {
+            // We ignore the vote if it is not known in `indices`. We assume that it is outside
+            // of our tree (i.e., pre-finalization) and therefore not interesting.
+            if let Some(current_delta_index) = indices.get(&vote.current_root).copied() {
+                let delta = deltas
+                    .get(current_delta_index)
+                    .ok_or_else(|| Error::InvalidNodeDelta(current_delta_index))?
+                    .checked_sub(old_balance as i64)
+                    .ok_or_else(|| Error::DeltaOverflow(current_delta_index))?;
+
+                // Array access safe due to check on previous line.
+                deltas[current_delta_index] = delta;
+            }
+
+            // We ignore the vote if it is not known in `indices`. We assume that it is outside
+            // of our tree (i.e., pre-finalization) and therefore not interesting.
+            if let Some(next_delta_index) = indices.get(&vote.next_root).copied() {
+                let delta = deltas
+                    .get(next_delta_index)
+                    .ok_or_else(|| Error::InvalidNodeDelta(next_delta_index))?
+                    .checked_add(new_balance as i64)
+                    .ok_or_else(|| Error::DeltaOverflow(next_delta_index))?;
+
+                // Array access safe due to check on previous line.
+                deltas[next_delta_index] = delta;
+            }
+
+            vote.current_root = vote.next_root;
+        }
+    }
+
+    Ok(deltas)
+}
+
+#[cfg(test)]
+mod test_compute_deltas {
+    use super::*;
+
+    /// Gives a hash that is not the zero hash (unless i is `usize::max_value)`.
+    fn hash_from_index(i: usize) -> Hash256 {
+        Hash256::from_low_u64_be(i as u64 + 1)
+    }
+
+    #[test]
+    fn zero_hash() {
+        let validator_count: usize = 16;
+
+        let mut indices = HashMap::new();
+        let mut votes = ElasticList::default();
+        let mut old_balances = vec![];
+        let mut new_balances = vec![];
+
+        for i in 0..validator_count {
+            indices.insert(hash_from_index(i), i);
+            votes.0.push(VoteTracker {
+                current_root: Hash256::zero(),
+                next_root: Hash256::zero(),
+                next_epoch: Epoch::new(0),
+            });
+            old_balances.push(0);
+            new_balances.push(0);
+        }
+
+        let deltas = compute_deltas(&indices, &mut votes, &old_balances, &new_balances)
+            .expect("should compute deltas");
+
+        assert_eq!(
+            deltas.len(),
+            validator_count,
+            "deltas should have expected length"
+        );
+        assert_eq!(
+            deltas,
+            vec![0; validator_count],
+            "deltas should all be zero"
+        );
+
+        for vote in votes.0 {
+            assert_eq!(
+                vote.current_root, vote.next_root,
+                "the vote shoulds should have been updated"
+            );
+        }
+    }
+
+    #[test]
+    fn all_voted_the_same() {
+        const BALANCE: u64 = 42;
+
+        let validator_count: usize = 16;
+
+        let mut indices = HashMap::new();
+        let mut votes = ElasticList::default();
+        let mut old_balances = vec![];
+        let mut new_balances = vec![];
+
+        for i in 0..validator_count {
+            indices.insert(hash_from_index(i), i);
+            votes.0.push(VoteTracker {
+                current_root: Hash256::zero(),
+                next_root: hash_from_index(0),
+                next_epoch: Epoch::new(0),
+            });
+            old_balances.push(BALANCE);
+            new_balances.push(BALANCE);
+        }
+
+        let deltas = compute_deltas(&indices, &mut votes, &old_balances, &new_balances)
+            .expect("should compute deltas");
+
+        assert_eq!(
+            deltas.len(),
+            validator_count,
+            "deltas should have expected length"
+        );
+
+        for (i, delta) in deltas.into_iter().enumerate() {
+            if i == 0 {
+                assert_eq!(
+                    delta,
+                    BALANCE as i64 * validator_count as i64,
+                    "zero'th root should have a delta"
+                );
+            } else {
+                assert_eq!(delta, 0, "all other deltas should be zero");
+            }
+        }
+
+        for vote in votes.0 {
+            assert_eq!(
+                vote.current_root, vote.next_root,
+                "the vote shoulds should have been updated"
+            );
+        }
+    }
+
+    #[test]
+    fn moving_votes() {
+        const BALANCE: u64 = 42;
+
+        let validator_count: usize = 16;
+
+        let mut indices = HashMap::new();
+        let mut votes = ElasticList::default();
+        let mut old_balances = vec![];
+        let mut new_balances = vec![];
+
+        for i in 0..validator_count {
+            indices.insert(hash_from_index(i), i);
+            votes.0.push(VoteTracker {
+                current_root: hash_from_index(0),
+                next_root: hash_from_index(1),
+                next_epoch: Epoch::new(0),
+            });
+            old_balances.push(BALANCE);
+            new_balances.push(BALANCE);
+        }
+
+        let deltas = compute_deltas(&indices, &mut votes, &old_balances, &new_balances)
+            .expect("should compute deltas");
+
+        assert_eq!(
+            deltas.len(),
+            validator_count,
+            "deltas should have expected length"
+        );
+
+        let total_delta = BALANCE as i64 * validator_count as i64;
+
+        for (i, delta) in deltas.into_iter().enumerate() {
+            if i == 0 {
+                assert_eq!(
+                    delta,
+                    0 - total_delta,
+                    "zero'th root should have a negative delta"
+                );
+            } else if i == 1 {
+                assert_eq!(delta, total_delta, "first root should have positive delta");
+            } else {
+                assert_eq!(delta, 0, "all other deltas should be zero");
+            }
+        }
+
+        for vote in votes.0 {
+            assert_eq!(
+                vote.current_root, vote.next_root,
+                "the vote shoulds should have been updated"
+            );
+        }
+    }
+
+    #[test]
+    fn move_out_of_tree() {
+        const BALANCE: u64 = 42;
+
+        // There is only one block.
+        let mut indices = HashMap::new();
+        indices.insert(hash_from_index(1), 0);
+
+        // There are two validators.
+        let old_balances = vec![BALANCE; 2];
+        let new_balances = vec![BALANCE; 2];
+
+        // One validator moves their vote from the block to the zero hash.
+        let mut votes = ElasticList(vec![
+            VoteTracker {
+                current_root: hash_from_index(1),
+                next_root: Hash256::zero(),
+                next_epoch: Epoch::new(0),
+            },
+            // One validator moves their vote from the block to something outside the tree.
+            VoteTracker {
+                current_root: hash_from_index(1),
+                next_root: Hash256::from_low_u64_be(1337),
+                next_epoch: Epoch::new(0),
+            },
+        ]);
+
+        let deltas = compute_deltas(&indices, &mut votes, &old_balances, &new_balances)
+            .expect("should compute deltas");
+
+        assert_eq!(deltas.len(), 1, "deltas should have expected length");
+
+        assert_eq!(
+            deltas[0],
+            0 - BALANCE as i64 * 2,
+            "the block should have lost both balances"
+        );
+
+        for vote in votes.0 {
+            assert_eq!(
+                vote.current_root, vote.next_root,
+                "the vote shoulds should have been updated"
+            );
+        }
+    }
+
+    #[test]
+    fn changing_balances() {
+        const OLD_BALANCE: u64 = 42;
+        const NEW_BALANCE: u64 = OLD_BALANCE * 2;
+
+        let validator_count: usize = 16;
+
+        let mut indices = HashMap::new();
+        let mut votes = ElasticList::default();
+        let mut old_balances = vec![];
+        let mut new_balances = vec![];
+
+        for i in 0..validator_count {
+            indices.insert(hash_from_index(i), i);
+            votes.0.push(VoteTracker {
+                current_root: hash_from_index(0),
+                next_root: hash_from_index(1),
+                next_epoch: Epoch::new(0),
+            });
+            old_balances.push(OLD_BALANCE);
+            new_balances.push(NEW_BALANCE);
+        }
+
+        let deltas = compute_deltas(&indices, &mut votes, &old_balances, &new_balances)
+            .expect("should compute deltas");
+
+        assert_eq!(
+            deltas.len(),
+            validator_count,
+            "deltas should have expected length"
+        );
+
+        for (i, delta) in deltas.into_iter().enumerate() {
+            if i == 0 {
+                assert_eq!(
+                    delta,
+                    0 - OLD_BALANCE as i64 * validator_count as i64,
+                    "zero'th root should have a negative delta"
+                );
+            } else if i == 1 {
+                assert_eq!(
+                    delta,
+                    NEW_BALANCE as i64 * validator_count as i64,
+                    "first root should have positive delta"
+                );
+            } else {
+                assert_eq!(delta, 0, "all other deltas should be zero");
+            }
+        }
+
+        for vote in votes.0 {
+            assert_eq!(
+                vote.current_root, vote.next_root,
+                "the vote shoulds should have been updated"
+            );
+        }
+    }
+
+    #[test]
+    fn validator_appears() {
+        const BALANCE: u64 = 42;
+
+        // There are two blocks.
+        let mut indices = HashMap::new();
+        indices.insert(hash_from_index(1), 0);
+        indices.insert(hash_from_index(2), 1);
+
+        // There is only one validator in the old balances.
+        let old_balances = vec![BALANCE; 1];
+        // There are two validators in the new balances.
+        let new_balances = vec![BALANCE; 2];
+
+        // Both validator move votes from block 1 to block 2.
+        let mut votes = ElasticList(vec![
+            VoteTracker {
+                current_root: hash_from_index(1),
+                next_root: hash_from_index(2),
+                next_epoch: Epoch::new(0),
+            },
+            VoteTracker {
+                current_root: hash_from_index(1),
+                next_root: hash_from_index(2),
+                next_epoch: Epoch::new(0),
+            },
+        ]);
+
+        let deltas = compute_deltas(&indices, &mut votes, &old_balances, &new_balances)
+            .expect("should compute deltas");
+
+        assert_eq!(deltas.len(), 2, "deltas should have expected length");
+
+        assert_eq!(
+            deltas[0],
+            0 - BALANCE as i64,
+            "block 1 should have only lost one balance"
+        );
+        assert_eq!(
+            deltas[1],
+            2 * BALANCE as i64,
+            "block 2 should have gained two balances"
+        );
+
+        for vote in votes.0 {
+            assert_eq!(
+                vote.current_root, vote.next_root,
+                "the vote shoulds should have been updated"
+            );
+        }
+    }
+
+    #[test]
+    fn validator_disappears() {
+        const BALANCE: u64 = 42;
+
+        // There are two blocks.
+        let mut indices = HashMap::new();
+        indices.insert(hash_from_index(1), 0);
+        indices.insert(hash_from_index(2), 1);
+
+        // There are two validators in the old balances.
+        let old_balances = vec![BALANCE; 2];
+        // There is only one validator in the new balances.
+        let new_balances = vec![BALANCE; 1];
+
+        // Both validator move votes from block 1 to block 2.
+        let mut votes = ElasticList(vec![
+            VoteTracker {
+                current_root: hash_from_index(1),
+                next_root: hash_from_index(2),
+                next_epoch: Epoch::new(0),
+            },
+            VoteTracker {
+                current_root: hash_from_index(1),
+                next_root: hash_from_index(2),
+                next_epoch: Epoch::new(0),
+            },
+        ]);
+
+        let deltas = compute_deltas(&indices, &mut votes, &old_balances, &new_balances)
+            .expect("should compute deltas");
+
+        assert_eq!(deltas.len(), 2, "deltas should have expected length");
+
+        assert_eq!(
+            deltas[0],
+            0 - BALANCE as i64 * 2,
+            "block 1 should have lost both balances"
+        );
+        assert_eq!(
+            deltas[1], BALANCE as i64,
+            "block 2 should have only gained one balance"
+        );
+
+        for vote in votes.0 {
+            assert_eq!(
+                vote.current_root, vote.next_root,
+                "the vote should have been updated"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_fork_choice {
+    use super::*;
+
+    const BALANCE: u64 = 1;
+
+    fn root(i: u64) -> Hash256 {
+        Hash256::from_low_u64_be(i)
+    }
+
+    /// Returns a fork choice with the following blocks, all at justified and finalized epoch 1:
+    ///
+    /// ```ignore
+    ///       0
+    ///      / \
+    ///     1   2
+    ///     |
+    ///     3
+    /// ```
+    fn get_fork_choice() -> ProtoArrayForkChoice {
+        let epoch = Epoch::new(1);
+        let fork_choice = ProtoArrayForkChoice::new(Slot::new(0), epoch, epoch, root(0))
+            .expect("should create fork choice");
+
+        for (slot, block, parent) in &[(1, 1, 0), (1, 2, 0), (2, 3, 1)] {
+            fork_choice
+                .process_block(Slot::new(*slot), root(*block), root(*parent), epoch, epoch)
+                .expect("should process block");
+        }
+
+        fork_choice
+    }
+
+    fn find_head(fork_choice: &ProtoArrayForkChoice, balances: &[u64]) -> Hash256 {
+        let epoch = Epoch::new(1);
+        fork_choice
+            .find_head(epoch, root(0), epoch, balances)
+            .expect("should find head")
+    }
+
+    #[test]
+    fn no_votes() {
+        let fork_choice = get_fork_choice();
+
+        // With no votes, the tie between 1 and 2 is broken by the higher root.
+        assert_eq!(find_head(&fork_choice, &[]), root(2));
+    }
+
+    #[test]
+    fn votes_move_head() {
+        let fork_choice = get_fork_choice();
+        let balances = vec![BALANCE; 3];
+
+        fork_choice
+            .process_attestation(0, root(3), Epoch::new(2))
+            .expect("should process attestation");
+        assert_eq!(find_head(&fork_choice, &balances), root(3));
+
+        fork_choice
+            .process_attestation(1, root(2), Epoch::new(2))
+            .expect("should process attestation");
+        fork_choice
+            .process_attestation(2, root(2), Epoch::new(2))
+            .expect("should process attestation");
+        assert_eq!(find_head(&fork_choice, &balances), root(2));
+
+        // A vote from an earlier epoch should be ignored.
+        fork_choice
+            .process_attestation(1, root(3), Epoch::new(1))
+            .expect("should process attestation");
+        assert_eq!(find_head(&fork_choice, &balances), root(2));
+        assert_eq!(
+            fork_choice.latest_message(1),
+            Some((root(2), Epoch::new(2)))
+        );
+
+        // Validators 1 and 2 moving to block 3 moves the head.
+        fork_choice
+            .process_attestation(1, root(3), Epoch::new(3))
+            .expect("should process attestation");
+        assert_eq!(find_head(&fork_choice, &balances), root(3));
+
+        // A change in balances can move the head back.
+        assert_eq!(find_head(&fork_choice, &[0, 0, 10]), root(2));
+    }
+
+    #[test]
+    fn ffg_filter() {
+        let fork_choice = get_fork_choice();
+        let balances = vec![BALANCE; 1];
+
+        // Block 4 is a child of 3 with a higher justified epoch.
+        fork_choice
+            .process_block(Slot::new(3), root(4), root(3), Epoch::new(2), Epoch::new(1))
+            .expect("should process block");
+
+        fork_choice
+            .process_attestation(0, root(4), Epoch::new(2))
+            .expect("should process attestation");
+
+        // When fork choice is at justified epoch 1, the block at justified epoch 2 is not viable.
+        assert_eq!(find_head(&fork_choice, &balances), root(3));
+
+        // When fork choice moves to justified epoch 2, only the branch leading to 4 is viable.
+        let head = fork_choice
+            .find_head(Epoch::new(2), root(0), Epoch::new(1), &balances)
+            .expect("should find head");
+        assert_eq!(head, root(4));
+    }
+
+    #[test]
+    fn nodes() {
+        let fork_choice = get_fork_choice();
+        fork_choice
+            .process_block(Slot::new(3), root(4), root(3), Epoch::new(2), Epoch::new(1))
+            .expect("should process block");
+
+        let nodes = fork_choice.nodes();
+        assert_eq!(nodes.len(), 5);
+
+        assert_eq!(nodes[0].root, root(0));
+        assert_eq!(nodes[0].parent_root, None);
+        assert_eq!(nodes[0].children, vec![root(1), root(2)]);
+
+        assert_eq!(nodes[4].root, root(4));
+        assert_eq!(nodes[4].parent_root, Some(root(3)));
+        assert_eq!(nodes[4].justified_epoch, Epoch::new(2));
+        assert_eq!(nodes[4].finalized_epoch, Epoch::new(1));
+        assert_eq!(nodes[3].justified_epoch, Epoch::new(1));
+    }
+
+    #[test]
+    fn prune() {
+        let fork_choice = get_fork_choice();
+        fork_choice.set_prune_threshold(1);
+
+        assert_eq!(fork_choice.len(), 4);
+
+        fork_choice
+            .update_finalized_root(root(1))
+            .expect("should prune");
+
+        // Only the nodes prior to the finalized node are pruned.
+        assert_eq!(fork_choice.len(), 3);
+        assert!(!fork_choice.contains_block(&root(0)));
+        assert!(fork_choice.contains_block(&root(2)));
+        assert_eq!(fork_choice.block_slot(&root(3)), Some(Slot::new(2)));
+
+        let epoch = Epoch::new(1);
+        let head = fork_choice
+            .find_head(epoch, root(1), epoch, &[])
+            .expect("should find head after pruning");
+        assert_eq!(head, root(3));
+    }
+
+    #[test]
+    fn ssz_round_trip() {
+        let fork_choice = get_fork_choice();
+        fork_choice
+            .process_attestation(0, root(3), Epoch::new(2))
+            .expect("should process attestation");
+        find_head(&fork_choice, &[BALANCE]);
+
+        let bytes = fork_choice.as_bytes();
+        let decoded = ProtoArrayForkChoice::from_bytes(&bytes).expect("should decode fork choice");
+
+        assert!(decoded == fork_choice, "decoded fork choice should match");
+    }
+}
@@ -4,6 +4,7 @@ use criterion::Criterion;
 use criterion::{black_box, criterion_group, criterion_main, Benchmark};
 use ssz::Encode;
 use state_processing::{test_utils::BlockBuilder, BlockSignatureStrategy, VerifySignatures};
+use types::test_utils::TestingBeaconStateBuilder;
 use types::{BeaconBlock, BeaconState, ChainSpec, EthSpec, MainnetEthSpec, MinimalEthSpec, Slot};
 
 pub const VALIDATORS_LOW: usize = 32_768;
@@ -19,6 +20,9 @@ fn all_benches(c: &mut Criterion) {
     worst_bench::<MinimalEthSpec>(c, "minimal", VALIDATORS_LOW);
     worst_bench::<MainnetEthSpec>(c, "mainnet", VALIDATORS_LOW);
     worst_bench::<MainnetEthSpec>(c, "mainnet", VALIDATORS_HIGH);
+
+    epoch_bench::<MainnetEthSpec>(c, "mainnet", VALIDATORS_LOW);
+    epoch_bench::<MainnetEthSpec>(c, "mainnet", VALIDATORS_HIGH);
 }
 
 /// Run a bench with a average complexity block.
@@ -40,6 +44,65 @@ fn worst_bench<T: EthSpec>(c: &mut Criterion, spec_desc: &str, validator_count:
     bench_block::<T>(c, block, state, spec, spec_desc, "high_complexity_block");
 }
 
+/// Run per-epoch processing on the default `rayon` thread pool and on a single thread, which is
+/// equivalent to processing the validators sequentially.
+fn epoch_bench<T: EthSpec>(c: &mut Criterion, spec_desc: &str, validator_count: usize) {
+    let spec = &T::default_spec();
+
+    let mut builder: TestingBeaconStateBuilder<T> =
+        TestingBeaconStateBuilder::from_default_keypairs_file_if_exists(validator_count, spec);
+    builder.teleport_to_slot((T::genesis_epoch() + 4).end_slot(T::slots_per_epoch()));
+    builder.insert_attestations(spec);
+    builder
+        .build_caches(spec)
+        .expect("should build state caches");
+    let (state, _keypairs) = builder.build();
+
+    let title = &format!("{}/{}_validators", spec_desc, validator_count);
+
+    let local_state = state.clone();
+    let local_spec = spec.clone();
+    c.bench(
+        &title,
+        Benchmark::new("per_epoch_processing/parallel", move |b| {
+            b.iter_batched_ref(
+                || (local_spec.clone(), local_state.clone()),
+                |(spec, ref mut state)| {
+                    black_box(
+                        state_processing::per_epoch_processing(state, &spec)
+                            .expect("epoch processing should succeed"),
+                    )
+                },
+                criterion::BatchSize::SmallInput,
+            )
+        })
+        .sample_size(10),
+    );
+
+    let local_state = state.clone();
+    let local_spec = spec.clone();
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .expect("should build thread pool");
+    c.bench(
+        &title,
+        Benchmark::new("per_epoch_processing/sequential", move |b| {
+            b.iter_batched_ref(
+                || (local_spec.clone(), local_state.clone()),
+                |(spec, ref mut state)| {
+                    black_box(
+                        pool.install(|| state_processing::per_epoch_processing(state, &spec))
+                            .expect("epoch processing should succeed"),
+                    )
+                },
+                criterion::BatchSize::SmallInput,
+            )
+        })
+        .sample_size(10),
+    );
+}
+
 /// Return a block and state where the block has "average" complexity. I.e., the number of
 /// operations we'd generally expect to see.
 fn get_average_block<T: EthSpec>(
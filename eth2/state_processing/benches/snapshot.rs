@@ -0,0 +1,292 @@
+//! Benchmarks the hot paths of the state transition against a single, realistic `BeaconState`.
+//!
+//! The state is read from the SSZ file at `$BENCH_STATE_FILE`, if it exists. Otherwise, a state
+//! with `$BENCH_VALIDATOR_COUNT` validators (default: 16,384) and full attestation participation
+//! is generated and, if `$BENCH_STATE_FILE` is set, saved so that subsequent runs skip the slow
+//! generation step and always measure identical inputs.
+//!
+//! Saved states must have been generated with deterministic keypairs (e.g., by this bench), since
+//! the keypairs are regenerated in order to sign blocks.
+//!
+//! ```bash
+//! BENCH_STATE_FILE=/tmp/state.ssz BENCH_VALIDATOR_COUNT=65536 cargo bench --bench snapshot
+//! ```
+
+use criterion::{black_box, criterion_group, criterion_main, Benchmark, Criterion};
+use ssz::{Decode, Encode};
+use state_processing::{per_block_processing, per_epoch_processing, BlockSignatureStrategy};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use swap_or_not_shuffle::shuffle_list;
+use tree_hash::TreeHash;
+use types::test_utils::{
+    generate_deterministic_keypairs, AttestationTestTask, TestingBeaconBlockBuilder,
+    TestingBeaconStateBuilder,
+};
+use types::{
+    BeaconBlock, BeaconState, ChainSpec, Domain, EthSpec, Keypair, MainnetEthSpec, RelativeEpoch,
+    SecretKey,
+};
+
+pub const STATE_FILE_ENV: &str = "BENCH_STATE_FILE";
+pub const VALIDATOR_COUNT_ENV: &str = "BENCH_VALIDATOR_COUNT";
+pub const DEFAULT_VALIDATOR_COUNT: usize = 16_384;
+
+/// The number of attestations in the benchmarked block, approximately that of a block on a
+/// healthy network.
+pub const NUM_ATTESTATIONS: usize = 16;
+
+type E = MainnetEthSpec;
+
+fn all_benches(c: &mut Criterion) {
+    let spec = &E::default_spec();
+
+    let (state, keypairs) = load_or_generate_state::<E>(spec);
+    let block = build_block(&state, &keypairs, spec);
+
+    let title = &format!("snapshot/{}_validators", state.validators.len());
+
+    block_benches(c, title, &block, &state, spec);
+    epoch_benches(c, title, &state, spec);
+    shuffling_benches(c, title, &state, spec);
+    tree_hash_benches(c, title, &state);
+}
+
+/// Reads the state from `$BENCH_STATE_FILE`, generating (and saving) it if the file is absent.
+fn load_or_generate_state<T: EthSpec>(spec: &ChainSpec) -> (BeaconState<T>, Vec<Keypair>) {
+    let path = env::var(STATE_FILE_ENV).ok().map(PathBuf::from);
+
+    if let Some(path) = path.as_ref().filter(|path| path.exists()) {
+        let bytes = fs::read(path).expect("should read state file");
+        let mut state =
+            BeaconState::from_ssz_bytes(&bytes).expect("state file should be valid SSZ");
+        state
+            .build_all_caches(spec)
+            .expect("should build state caches");
+
+        let keypairs = generate_deterministic_keypairs(state.validators.len());
+        assert!(
+            state
+                .validators
+                .iter()
+                .zip(keypairs.iter())
+                .all(|(validator, keypair)| validator.pubkey == keypair.pk),
+            "state file must use deterministic keypairs"
+        );
+
+        return (state, keypairs);
+    }
+
+    let validator_count = env::var(VALIDATOR_COUNT_ENV)
+        .map(|count| count.parse().expect("validator count should be an integer"))
+        .unwrap_or(DEFAULT_VALIDATOR_COUNT);
+
+    let mut builder: TestingBeaconStateBuilder<T> =
+        TestingBeaconStateBuilder::from_deterministic_keypairs(validator_count, spec);
+    builder.teleport_to_slot((T::genesis_epoch() + 4).end_slot(T::slots_per_epoch()));
+    builder.insert_attestations(spec);
+    builder
+        .build_caches(spec)
+        .expect("should build state caches");
+    let (state, keypairs) = builder.build();
+
+    if let Some(path) = path {
+        fs::write(&path, state.as_ssz_bytes()).expect("should write state file");
+    }
+
+    (state, keypairs)
+}
+
+/// Builds a block at the slot of `state`, with `NUM_ATTESTATIONS` attestations.
+fn build_block<T: EthSpec>(
+    state: &BeaconState<T>,
+    keypairs: &[Keypair],
+    spec: &ChainSpec,
+) -> BeaconBlock<T> {
+    let mut builder = TestingBeaconBlockBuilder::new(spec);
+    builder.set_slot(state.slot);
+    builder.set_parent_root(state.latest_block_header.canonical_root());
+
+    let proposer_index = state
+        .get_beacon_proposer_index(state.slot, spec)
+        .expect("should get proposer index");
+    let proposer_keypair = &keypairs[proposer_index];
+    builder.set_randao_reveal(&proposer_keypair.sk, &state.fork, spec);
+
+    let secret_keys: Vec<&SecretKey> = keypairs.iter().map(|keypair| &keypair.sk).collect();
+    builder
+        .insert_attestations(
+            AttestationTestTask::Valid,
+            state,
+            &secret_keys,
+            NUM_ATTESTATIONS,
+            spec,
+        )
+        .expect("should insert attestations");
+
+    builder.build(&proposer_keypair.sk, &state.fork, spec)
+}
+
+fn block_benches<T: EthSpec>(
+    c: &mut Criterion,
+    title: &str,
+    block: &BeaconBlock<T>,
+    state: &BeaconState<T>,
+    spec: &ChainSpec,
+) {
+    for (desc, strategy) in &[
+        (
+            "per_block_processing/bulk_signature_verification",
+            BlockSignatureStrategy::VerifyBulk,
+        ),
+        (
+            "per_block_processing/no_signature_verification",
+            BlockSignatureStrategy::NoVerification,
+        ),
+    ] {
+        let strategy = *strategy;
+        let local_block = block.clone();
+        let local_state = state.clone();
+        let local_spec = spec.clone();
+        c.bench(
+            title,
+            Benchmark::new(*desc, move |b| {
+                b.iter_batched_ref(
+                    || (local_spec.clone(), local_state.clone(), local_block.clone()),
+                    |(spec, ref mut state, block)| {
+                        black_box(
+                            per_block_processing::<T>(state, &block, None, strategy, &spec)
+                                .expect("block processing should succeed"),
+                        )
+                    },
+                    criterion::BatchSize::SmallInput,
+                )
+            })
+            .sample_size(10),
+        );
+    }
+}
+
+fn epoch_benches<T: EthSpec>(
+    c: &mut Criterion,
+    title: &str,
+    state: &BeaconState<T>,
+    spec: &ChainSpec,
+) {
+    let local_state = state.clone();
+    let local_spec = spec.clone();
+    c.bench(
+        title,
+        Benchmark::new("per_epoch_processing", move |b| {
+            b.iter_batched_ref(
+                || (local_spec.clone(), local_state.clone()),
+                |(spec, ref mut state)| {
+                    black_box(
+                        per_epoch_processing(state, &spec)
+                            .expect("epoch processing should succeed"),
+                    )
+                },
+                criterion::BatchSize::SmallInput,
+            )
+        })
+        .sample_size(10),
+    );
+}
+
+fn shuffling_benches<T: EthSpec>(
+    c: &mut Criterion,
+    title: &str,
+    state: &BeaconState<T>,
+    spec: &ChainSpec,
+) {
+    let local_state = state.clone();
+    let local_spec = spec.clone();
+    c.bench(
+        title,
+        Benchmark::new("build_committee_cache", move |b| {
+            b.iter_batched_ref(
+                || {
+                    let mut state = local_state.clone();
+                    state.drop_all_caches();
+                    (local_spec.clone(), state)
+                },
+                |(spec, ref mut state)| {
+                    black_box(
+                        state
+                            .build_committee_cache(RelativeEpoch::Current, &spec)
+                            .expect("should build committee cache"),
+                    )
+                },
+                criterion::BatchSize::SmallInput,
+            )
+        })
+        .sample_size(10),
+    );
+
+    let active_indices = state.get_active_validator_indices(state.current_epoch());
+    let seed = state
+        .get_seed(state.current_epoch(), Domain::BeaconAttester, spec)
+        .expect("should get seed");
+    let shuffle_round_count = spec.shuffle_round_count;
+    c.bench(
+        title,
+        Benchmark::new("shuffle_list", move |b| {
+            b.iter_batched(
+                || active_indices.clone(),
+                |indices| {
+                    black_box(
+                        shuffle_list(indices, shuffle_round_count, &seed[..], false)
+                            .expect("should shuffle active indices"),
+                    )
+                },
+                criterion::BatchSize::SmallInput,
+            )
+        })
+        .sample_size(10),
+    );
+}
+
+fn tree_hash_benches<T: EthSpec>(c: &mut Criterion, title: &str, state: &BeaconState<T>) {
+    let local_state = state.clone();
+    c.bench(
+        title,
+        Benchmark::new("tree_hash_root/uncached", move |b| {
+            b.iter_batched_ref(
+                || (),
+                |_| black_box(local_state.tree_hash_root()),
+                criterion::BatchSize::SmallInput,
+            )
+        })
+        .sample_size(10),
+    );
+
+    let mut cached_state = state.clone();
+    cached_state
+        .update_tree_hash_cache()
+        .expect("should build tree hash cache");
+    c.bench(
+        title,
+        Benchmark::new("update_tree_hash_cache/one_balance_changed", move |b| {
+            b.iter_batched_ref(
+                || {
+                    let mut state = cached_state.clone();
+                    state.balances[0] += 1;
+                    state
+                },
+                |state| {
+                    black_box(
+                        state
+                            .update_tree_hash_cache()
+                            .expect("should update tree hash cache"),
+                    )
+                },
+                criterion::BatchSize::SmallInput,
+            )
+        })
+        .sample_size(10),
+    );
+}
+
+criterion_group!(benches, all_benches);
+criterion_main!(benches);
@@ -0,0 +1,111 @@
+use crate::{
+    per_block_processing, per_slot_processing, BlockProcessingError, BlockSignatureStrategy,
+    SlotProcessingError,
+};
+use types::{BeaconBlock, BeaconState, BeaconStateError, ChainSpec, EthSpec, Slot};
+
+#[derive(Debug, PartialEq)]
+pub enum BlockReplayError {
+    BeaconStateError(BeaconStateError),
+    SlotProcessing(SlotProcessingError),
+    BlockProcessing(BlockProcessingError),
+    /// A block was provided with a slot that is not greater than the slot of the state.
+    BlockFromThePast {
+        block_slot: Slot,
+        state_slot: Slot,
+    },
+}
+
+impl From<BeaconStateError> for BlockReplayError {
+    fn from(e: BeaconStateError) -> Self {
+        BlockReplayError::BeaconStateError(e)
+    }
+}
+
+impl From<SlotProcessingError> for BlockReplayError {
+    fn from(e: SlotProcessingError) -> Self {
+        BlockReplayError::SlotProcessing(e)
+    }
+}
+
+impl From<BlockProcessingError> for BlockReplayError {
+    fn from(e: BlockProcessingError) -> Self {
+        BlockReplayError::BlockProcessing(e)
+    }
+}
+
+/// Applies a sequence of already-known blocks (and the skip slots between them) to a state.
+///
+/// Used to reconstruct historical states from an earlier state, e.g., a restore point in the
+/// freezer database.
+pub struct BlockReplayer<'a, E: EthSpec> {
+    state: BeaconState<E>,
+    spec: &'a ChainSpec,
+    block_signature_strategy: BlockSignatureStrategy,
+}
+
+impl<'a, E: EthSpec> BlockReplayer<'a, E> {
+    /// Create a new replayer that will apply blocks on top of `state`.
+    ///
+    /// Signatures are verified by default.
+    pub fn new(state: BeaconState<E>, spec: &'a ChainSpec) -> Self {
+        Self {
+            state,
+            spec,
+            block_signature_strategy: BlockSignatureStrategy::VerifyBulk,
+        }
+    }
+
+    /// Disable signature verification, which is appropriate for blocks that are known to be
+    /// valid (e.g., finalized blocks).
+    pub fn no_signature_verification(mut self) -> Self {
+        self.block_signature_strategy = BlockSignatureStrategy::NoVerification;
+        self
+    }
+
+    /// Apply `blocks` in order, followed by any skip slots required to reach `target_slot`.
+    ///
+    /// The `blocks` must be in ascending order of slot and each must have a slot greater than the
+    /// slot of the state.
+    pub fn apply_blocks(
+        mut self,
+        blocks: Vec<BeaconBlock<E>>,
+        target_slot: Option<Slot>,
+    ) -> Result<Self, BlockReplayError> {
+        self.state.build_all_caches(self.spec)?;
+
+        for block in blocks {
+            if block.slot <= self.state.slot {
+                return Err(BlockReplayError::BlockFromThePast {
+                    block_slot: block.slot,
+                    state_slot: self.state.slot,
+                });
+            }
+
+            while self.state.slot < block.slot {
+                per_slot_processing(&mut self.state, self.spec)?;
+            }
+
+            per_block_processing(
+                &mut self.state,
+                &block,
+                None,
+                self.block_signature_strategy,
+                self.spec,
+            )?;
+        }
+
+        if let Some(target_slot) = target_slot {
+            while self.state.slot < target_slot {
+                per_slot_processing(&mut self.state, self.spec)?;
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Consume `self`, returning the resulting state.
+    pub fn into_state(self) -> BeaconState<E> {
+        self.state
+    }
+}
@@ -1,6 +1,7 @@
 #[macro_use]
 mod macros;
 
+pub mod block_replayer;
 pub mod common;
 pub mod genesis;
 pub mod per_block_processing;
@@ -8,6 +9,7 @@ pub mod per_epoch_processing;
 pub mod per_slot_processing;
 pub mod test_utils;
 
+pub use block_replayer::{BlockReplayError, BlockReplayer};
 pub use genesis::{initialize_beacon_state_from_eth1, is_valid_genesis_state, process_activations};
 pub use per_block_processing::{
     errors::BlockProcessingError, per_block_processing, BlockSignatureStrategy, VerifySignatures,
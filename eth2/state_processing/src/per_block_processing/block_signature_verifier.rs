@@ -77,19 +77,51 @@ impl<'a, T: EthSpec> BlockSignatureVerifier<'a, T> {
     ) -> Result<()> {
         let mut verifier = Self::new(state, block, spec);
 
-        verifier.include_block_proposal(None)?;
-        verifier.include_randao_reveal()?;
-        verifier.include_proposer_slashings()?;
-        verifier.include_attester_slashings()?;
-        verifier.include_attestations()?;
-        /*
-         * Deposits are not included because they can legally have invalid signatures.
-         */
-        verifier.include_exits()?;
+        verifier.include_all_signatures()?;
 
         verifier.verify()
     }
 
+    /// Verify all* the signatures in each of the given `blocks` in a single batch, returning
+    /// `Ok(())` if the signatures of every block are valid.
+    ///
+    /// All of the `blocks` must be from the current epoch of `state`. Since the proposers and
+    /// committees of an epoch are fixed at its start, `state` need not have had any of the
+    /// `blocks` applied to it.
+    ///
+    /// * : _Does not verify any signatures in `block.body.deposits`._
+    ///
+    /// See `Self::verify` for more detail.
+    pub fn verify_entire_blocks(
+        state: &'a BeaconState<T>,
+        blocks: impl IntoIterator<Item = &'a BeaconBlock<T>>,
+        spec: &'a ChainSpec,
+    ) -> Result<()> {
+        let mut sets = vec![];
+
+        for block in blocks {
+            let mut verifier = Self::new(state, block, spec);
+            verifier.include_all_signatures()?;
+            sets.append(&mut verifier.sets);
+        }
+
+        verify_sets(sets)
+    }
+
+    /// Includes all* the signatures in `self.block` for verification.
+    ///
+    /// * : _Deposits are not included because they can legally have invalid signatures._
+    fn include_all_signatures(&mut self) -> Result<()> {
+        self.include_block_proposal(None)?;
+        self.include_randao_reveal()?;
+        self.include_proposer_slashings()?;
+        self.include_attester_slashings()?;
+        self.include_attestations()?;
+        self.include_exits()?;
+
+        Ok(())
+    }
+
     /// Verify all* the signatures that have been included in `self`, returning `Ok(())` if the
     /// signatures are all valid.
     ///
@@ -104,20 +136,7 @@ impl<'a, T: EthSpec> BlockSignatureVerifier<'a, T> {
     ///
     /// Uses `rayon` to do a map-reduce of Vitalik's method across multiple cores.
     pub fn verify(self) -> Result<()> {
-        let num_sets = self.sets.len();
-        let num_chunks = std::cmp::max(1, num_sets / rayon::current_num_threads());
-        let result: bool = self
-            .sets
-            .into_par_iter()
-            .chunks(num_chunks)
-            .map(|chunk| verify_signature_sets(chunk.into_iter()))
-            .reduce(|| true, |current, this| current && this);
-
-        if result {
-            Ok(())
-        } else {
-            Err(Error::SignatureInvalid)
-        }
+        verify_sets(self.sets)
     }
 
     /// Includes the block signature for `self.block` for verification.
@@ -209,3 +228,20 @@ impl<'a, T: EthSpec> BlockSignatureVerifier<'a, T> {
         Ok(())
     }
 }
+
+/// Verifies `sets` as per `BlockSignatureVerifier::verify`.
+fn verify_sets(sets: Vec<SignatureSet>) -> Result<()> {
+    let num_sets = sets.len();
+    let num_chunks = std::cmp::max(1, num_sets / rayon::current_num_threads());
+    let result: bool = sets
+        .into_par_iter()
+        .chunks(num_chunks)
+        .map(|chunk| verify_signature_sets(chunk.into_iter()))
+        .reduce(|| true, |current, this| current && this);
+
+    if result {
+        Ok(())
+    } else {
+        Err(Error::SignatureInvalid)
+    }
+}
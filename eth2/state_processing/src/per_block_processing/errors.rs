@@ -1,9 +1,13 @@
 use super::signature_sets::Error as SignatureSetError;
+use std::fmt;
 use types::*;
 
 /// The error returned from the `per_block_processing` function. Indicates that a block is either
 /// invalid, or we were unable to determine it's validity (we encountered an unexpected error).
 ///
+/// The `...Invalid` variants of block operations carry the index of the invalid operation within
+/// the block, which (along with its kind) is returned by `BlockProcessingError::operation`.
+///
 /// Any of the `...Error` variants indicate that at some point during block (and block operation)
 /// verification, there was an error. There is no indication as to _where_ that error happened
 /// (e.g., when processing attestations instead of when processing deposits).
@@ -48,6 +52,111 @@ pub enum BlockProcessingError {
     SszTypesError(ssz_types::Error),
 }
 
+impl BlockProcessingError {
+    /// Returns the kind of the block operation that was invalid and its index within the block,
+    /// if the error was caused by a single invalid operation.
+    pub fn operation(&self) -> Option<(BlockOperation, usize)> {
+        match self {
+            BlockProcessingError::ProposerSlashingInvalid { index, .. } => {
+                Some((BlockOperation::ProposerSlashing, *index))
+            }
+            BlockProcessingError::AttesterSlashingInvalid { index, .. } => {
+                Some((BlockOperation::AttesterSlashing, *index))
+            }
+            // The indexed attestations of all attester slashings are verified together, two per
+            // attester slashing.
+            BlockProcessingError::IndexedAttestationInvalid { index, .. } => {
+                Some((BlockOperation::AttesterSlashing, index / 2))
+            }
+            BlockProcessingError::AttestationInvalid { index, .. } => {
+                Some((BlockOperation::Attestation, *index))
+            }
+            BlockProcessingError::DepositInvalid { index, .. } => {
+                Some((BlockOperation::Deposit, *index))
+            }
+            BlockProcessingError::ExitInvalid { index, .. } => Some((BlockOperation::Exit, *index)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for BlockProcessingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BlockProcessingError::HeaderInvalid { reason } => {
+                write!(f, "block header invalid: {:?}", reason)
+            }
+            BlockProcessingError::ProposerSlashingInvalid { index, reason } => write!(
+                f,
+                "{} #{} invalid: {:?}",
+                BlockOperation::ProposerSlashing,
+                index,
+                reason
+            ),
+            BlockProcessingError::AttesterSlashingInvalid { index, reason } => write!(
+                f,
+                "{} #{} invalid: {:?}",
+                BlockOperation::AttesterSlashing,
+                index,
+                reason
+            ),
+            BlockProcessingError::IndexedAttestationInvalid { index, reason } => write!(
+                f,
+                "{} #{} invalid: attestation {} {:?}",
+                BlockOperation::AttesterSlashing,
+                index / 2,
+                index % 2 + 1,
+                reason
+            ),
+            BlockProcessingError::AttestationInvalid { index, reason } => write!(
+                f,
+                "{} #{} invalid: {:?}",
+                BlockOperation::Attestation,
+                index,
+                reason
+            ),
+            BlockProcessingError::DepositInvalid { index, reason } => write!(
+                f,
+                "{} #{} invalid: {:?}",
+                BlockOperation::Deposit,
+                index,
+                reason
+            ),
+            BlockProcessingError::ExitInvalid { index, reason } => write!(
+                f,
+                "{} #{} invalid: {:?}",
+                BlockOperation::Exit,
+                index,
+                reason
+            ),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+/// The kinds of operations that are included in the body of a block.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BlockOperation {
+    ProposerSlashing,
+    AttesterSlashing,
+    Attestation,
+    Deposit,
+    Exit,
+}
+
+impl fmt::Display for BlockOperation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            BlockOperation::ProposerSlashing => "proposer slashing",
+            BlockOperation::AttesterSlashing => "attester slashing",
+            BlockOperation::Attestation => "attestation",
+            BlockOperation::Deposit => "deposit",
+            BlockOperation::Exit => "voluntary exit",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 impl From<BeaconStateError> for BlockProcessingError {
     fn from(e: BeaconStateError) -> Self {
         BlockProcessingError::BeaconStateError(e)
@@ -654,6 +654,13 @@ fn invalid_attestation_bad_target_too_low() {
             reason: AttestationInvalid::BadTargetEpoch
         })
     );
+
+    let error = result.unwrap_err();
+    assert_eq!(error.operation(), Some((BlockOperation::Attestation, 0)));
+    assert_eq!(
+        error.to_string(),
+        "attestation #0 invalid: BadTargetEpoch".to_string()
+    );
 }
 
 #[test]
@@ -988,6 +995,10 @@ fn invalid_attester_slashing_2_invalid() {
             reason: IndexedAttestationInvalid::BadValidatorIndicesOrdering(0)
         })
     );
+    assert_eq!(
+        result.unwrap_err().operation(),
+        Some((BlockOperation::AttesterSlashing, 0))
+    );
 }
 
 #[test]
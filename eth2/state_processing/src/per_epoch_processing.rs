@@ -1,4 +1,5 @@
 use errors::EpochProcessingError as Error;
+use rayon::prelude::*;
 use tree_hash::TreeHash;
 use types::*;
 use validator_statuses::{TotalBalances, ValidatorStatuses};
@@ -19,11 +20,17 @@ pub use registry_updates::process_registry_updates;
 /// Mutates the given `BeaconState`, returning early if an error is encountered. If an error is
 /// returned, a state might be "half-processed" and therefore in an invalid state.
 ///
+/// Per-validator work (participation, rewards and penalties, effective balances) is spread across
+/// the `rayon` thread pool. The result does not depend upon the number of threads.
+///
+/// Returns the `ValidatorStatuses` computed from the state prior to processing, so that callers
+/// can inspect validator participation without recomputing it.
+///
 /// Spec v0.9.1
 pub fn per_epoch_processing<T: EthSpec>(
     state: &mut BeaconState<T>,
     spec: &ChainSpec,
-) -> Result<(), Error> {
+) -> Result<ValidatorStatuses, Error> {
     // Ensure the committee caches are built.
     state.build_committee_cache(RelativeEpoch::Previous, spec)?;
     state.build_committee_cache(RelativeEpoch::Current, spec)?;
@@ -53,7 +60,7 @@ pub fn per_epoch_processing<T: EthSpec>(
     // Rotate the epoch caches to suit the epoch transition.
     state.advance_caches();
 
-    Ok(())
+    Ok(validator_statuses)
 }
 
 /// Update the following fields on the `BeaconState`:
@@ -148,18 +155,24 @@ pub fn process_final_updates<T: EthSpec>(
     }
 
     // Update effective balances with hysteresis (lag).
-    for (index, validator) in state.validators.iter_mut().enumerate() {
-        let balance = state.balances[index];
-        let half_increment = spec.effective_balance_increment / 2;
-        if balance < validator.effective_balance
-            || validator.effective_balance + 3 * half_increment < balance
-        {
-            validator.effective_balance = std::cmp::min(
-                balance - balance % spec.effective_balance_increment,
-                spec.max_effective_balance,
-            );
-        }
+    if state.validators.len() != state.balances.len() {
+        return Err(Error::ValidatorStatusesInconsistent);
     }
+    let half_increment = spec.effective_balance_increment / 2;
+    state
+        .validators
+        .par_iter_mut()
+        .zip(state.balances.par_iter())
+        .for_each(|(validator, &balance)| {
+            if balance < validator.effective_balance
+                || validator.effective_balance + 3 * half_increment < balance
+            {
+                validator.effective_balance = std::cmp::min(
+                    balance - balance % spec.effective_balance_increment,
+                    spec.max_effective_balance,
+                );
+            }
+        });
 
     // Reset slashings
     state.set_slashings(next_epoch, 0)?;
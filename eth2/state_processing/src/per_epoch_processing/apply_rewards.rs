@@ -1,6 +1,7 @@
 use super::validator_statuses::{TotalBalances, ValidatorStatus, ValidatorStatuses};
 use super::Error;
 use integer_sqrt::IntegerSquareRoot;
+use rayon::prelude::*;
 use types::*;
 
 /// Use to track the changes to a validators balance.
@@ -32,6 +33,9 @@ impl std::ops::AddAssign for Delta {
 
 /// Apply attester and proposer rewards.
 ///
+/// The deltas of each validator are computed in parallel. As each delta only depends upon the
+/// validator's own status, the result is identical to computing them sequentially.
+///
 /// Spec v0.9.1
 pub fn process_rewards_and_penalties<T: EthSpec>(
     state: &mut BeaconState<T>,
@@ -56,10 +60,14 @@ pub fn process_rewards_and_penalties<T: EthSpec>(
     get_proposer_deltas(&mut deltas, state, validator_statuses, spec)?;
 
     // Apply the deltas, over-flowing but not under-flowing (saturating at 0 instead).
-    for (i, delta) in deltas.iter().enumerate() {
-        state.balances[i] += delta.rewards;
-        state.balances[i] = state.balances[i].saturating_sub(delta.penalties);
-    }
+    state
+        .balances
+        .par_iter_mut()
+        .zip(deltas.par_iter())
+        .for_each(|(balance, delta)| {
+            *balance += delta.rewards;
+            *balance = balance.saturating_sub(delta.penalties);
+        });
 
     Ok(())
 }
@@ -99,6 +107,9 @@ fn get_proposer_deltas<T: EthSpec>(
 
 /// Apply rewards for participation in attestations during the previous epoch.
 ///
+/// The delta of each validator is computed in parallel, then added to `deltas` in validator index
+/// order.
+///
 /// Spec v0.9.1
 fn get_attestation_deltas<T: EthSpec>(
     deltas: &mut Vec<Delta>,
@@ -108,23 +119,34 @@ fn get_attestation_deltas<T: EthSpec>(
 ) -> Result<(), Error> {
     let finality_delay = (state.previous_epoch() - state.finalized_checkpoint.epoch).as_u64();
 
-    for (index, validator) in validator_statuses.statuses.iter().enumerate() {
-        let base_reward = get_base_reward(
-            state,
-            index,
-            validator_statuses.total_balances.current_epoch,
-            spec,
-        )?;
-
-        let delta = get_attestation_delta::<T>(
-            &validator,
-            &validator_statuses.total_balances,
-            base_reward,
-            finality_delay,
-            spec,
-        );
+    let attestation_deltas = validator_statuses
+        .statuses
+        .par_iter()
+        .enumerate()
+        .map(|(index, validator)| {
+            let base_reward = get_base_reward(
+                state,
+                index,
+                validator_statuses.total_balances.current_epoch,
+                spec,
+            )?;
+
+            Ok(get_attestation_delta::<T>(
+                &validator,
+                &validator_statuses.total_balances,
+                base_reward,
+                finality_delay,
+                spec,
+            ))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    if attestation_deltas.len() != deltas.len() {
+        return Err(Error::DeltasInconsistent);
+    }
 
-        deltas[index] += delta;
+    for (delta, attestation_delta) in deltas.iter_mut().zip(attestation_deltas) {
+        *delta += attestation_delta;
     }
 
     Ok(())
@@ -215,7 +237,7 @@ fn get_attestation_delta<T: EthSpec>(
 /// Returns the base reward for some validator.
 ///
 /// Spec v0.9.1
-fn get_base_reward<T: EthSpec>(
+pub fn get_base_reward<T: EthSpec>(
     state: &BeaconState<T>,
     index: usize,
     // Should be == get_total_active_balance(state, spec)
@@ -232,3 +254,74 @@ fn get_base_reward<T: EthSpec>(
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::per_epoch_processing::tests::state_with_attestations;
+
+    /// Applies the rewards and penalties one validator at a time, as the spec does.
+    fn sequential_rewards_and_penalties<T: EthSpec>(
+        state: &mut BeaconState<T>,
+        validator_statuses: &mut ValidatorStatuses,
+        spec: &ChainSpec,
+    ) {
+        let finality_delay = (state.previous_epoch() - state.finalized_checkpoint.epoch).as_u64();
+        let mut deltas = vec![Delta::default(); state.balances.len()];
+
+        for (index, validator) in validator_statuses.statuses.iter().enumerate() {
+            let base_reward = get_base_reward(
+                state,
+                index,
+                validator_statuses.total_balances.current_epoch,
+                spec,
+            )
+            .unwrap();
+
+            deltas[index] += get_attestation_delta::<T>(
+                validator,
+                &validator_statuses.total_balances,
+                base_reward,
+                finality_delay,
+                spec,
+            );
+        }
+
+        get_proposer_deltas(&mut deltas, state, validator_statuses, spec).unwrap();
+
+        for (i, delta) in deltas.iter().enumerate() {
+            state.balances[i] += delta.rewards;
+            state.balances[i] = state.balances[i].saturating_sub(delta.penalties);
+        }
+    }
+
+    #[test]
+    fn rewards_and_penalties_match_sequential() {
+        let (mut state, spec) = state_with_attestations(64);
+
+        // Vary the effective balances and slash some validators, so that the deltas differ.
+        for (i, validator) in state.validators.iter_mut().enumerate() {
+            validator.effective_balance -= (i as u64 % 4) * spec.effective_balance_increment;
+            validator.slashed = i % 5 == 0;
+        }
+
+        let mut validator_statuses =
+            ValidatorStatuses::new(&state, &spec).expect("should get statuses");
+        validator_statuses
+            .process_attestations(&state, &spec)
+            .expect("should process attestations");
+
+        let mut expected = state.clone();
+        sequential_rewards_and_penalties(&mut expected, &mut validator_statuses.clone(), &spec);
+
+        let initial_balances = state.balances.clone();
+        process_rewards_and_penalties(&mut state, &mut validator_statuses, &spec)
+            .expect("should process rewards and penalties");
+
+        assert_eq!(state.balances, expected.balances);
+        assert_ne!(
+            state.balances, initial_balances,
+            "rewards and penalties should have been applied"
+        );
+    }
+}
@@ -1,5 +1,5 @@
 #![cfg(test)]
-use crate::per_epoch_processing::per_epoch_processing;
+use crate::per_epoch_processing::{per_epoch_processing, process_final_updates};
 use env_logger::{Builder, Env};
 use types::test_utils::TestingBeaconStateBuilder;
 use types::*;
@@ -21,3 +21,58 @@ fn runs_without_error() {
 
     per_epoch_processing(&mut state, &spec).unwrap();
 }
+
+/// Returns a state at the end of epoch 4 with `validator_count` validators, in which every
+/// validator has attested during the previous and current epochs.
+pub fn state_with_attestations(validator_count: usize) -> (BeaconState<MinimalEthSpec>, ChainSpec) {
+    let spec = MinimalEthSpec::default_spec();
+
+    let mut builder: TestingBeaconStateBuilder<MinimalEthSpec> =
+        TestingBeaconStateBuilder::from_deterministic_keypairs(validator_count, &spec);
+
+    let target_slot =
+        (MinimalEthSpec::genesis_epoch() + 4).end_slot(MinimalEthSpec::slots_per_epoch());
+    builder.teleport_to_slot(target_slot);
+    builder.insert_attestations(&spec);
+
+    let (mut state, _keypairs) = builder.build();
+    state.build_all_caches(&spec).expect("should build caches");
+
+    (state, spec)
+}
+
+#[test]
+fn effective_balance_updates_match_sequential() {
+    let (mut state, spec) = state_with_attestations(64);
+    let increment = spec.effective_balance_increment;
+
+    // Spread the balances either side of the effective balances, so that only some of them cross
+    // the hysteresis thresholds.
+    for (i, balance) in state.balances.iter_mut().enumerate() {
+        *balance = spec.max_effective_balance - 2 * increment + i as u64 * increment / 8;
+    }
+
+    // Update the effective balances one validator at a time, as the spec does.
+    let mut expected = state.validators.clone();
+    let half_increment = increment / 2;
+    for (index, validator) in expected.iter_mut().enumerate() {
+        let balance = state.balances[index];
+        if balance < validator.effective_balance
+            || validator.effective_balance + 3 * half_increment < balance
+        {
+            validator.effective_balance =
+                std::cmp::min(balance - balance % increment, spec.max_effective_balance);
+        }
+    }
+
+    process_final_updates(&mut state, &spec).unwrap();
+
+    assert_eq!(state.validators, expected);
+    assert!(
+        state
+            .validators
+            .iter()
+            .any(|validator| validator.effective_balance < spec.max_effective_balance),
+        "some effective balances should have been updated"
+    );
+}
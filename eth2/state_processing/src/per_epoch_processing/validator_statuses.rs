@@ -1,4 +1,5 @@
 use crate::common::get_attesting_indices;
+use rayon::prelude::*;
 use types::*;
 
 /// Sets the boolean `var` on `self` to be true if it is true on `other`. Otherwise leaves `self`
@@ -12,7 +13,7 @@ macro_rules! set_self_if_other_is_true {
 }
 
 /// The information required to reward a block producer for including an attestation in a block.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct InclusionInfo {
     /// The distance between the attestation slot and the slot that attestation was included in a
     /// block.
@@ -43,7 +44,7 @@ impl InclusionInfo {
 }
 
 /// Information required to reward some validator during the current and previous epoch.
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Debug, PartialEq)]
 pub struct ValidatorStatus {
     /// True if the validator has been slashed, ever.
     pub is_slashed: bool,
@@ -107,7 +108,7 @@ impl ValidatorStatus {
 
 /// The total effective balances for different sets of validators during the previous and current
 /// epochs.
-#[derive(Default, Clone, Debug)]
+#[derive(Default, Clone, Debug, PartialEq)]
 pub struct TotalBalances {
     /// The total effective balance of all active validators during the _current_ epoch.
     pub current_epoch: u64,
@@ -149,30 +150,32 @@ impl ValidatorStatuses {
         state: &BeaconState<T>,
         spec: &ChainSpec,
     ) -> Result<Self, BeaconStateError> {
-        let mut statuses = Vec::with_capacity(state.validators.len());
-        let mut total_balances = TotalBalances::default();
-
-        for (i, validator) in state.validators.iter().enumerate() {
-            let effective_balance = state.get_effective_balance(i, spec)?;
-            let mut status = ValidatorStatus {
-                is_slashed: validator.slashed,
-                is_withdrawable_in_current_epoch: validator
-                    .is_withdrawable_at(state.current_epoch()),
-                current_epoch_effective_balance: effective_balance,
-                ..ValidatorStatus::default()
-            };
+        let statuses = state
+            .validators
+            .par_iter()
+            .enumerate()
+            .map(|(i, validator)| {
+                let effective_balance = state.get_effective_balance(i, spec)?;
+                Ok(ValidatorStatus {
+                    is_slashed: validator.slashed,
+                    is_withdrawable_in_current_epoch: validator
+                        .is_withdrawable_at(state.current_epoch()),
+                    is_active_in_current_epoch: validator.is_active_at(state.current_epoch()),
+                    is_active_in_previous_epoch: validator.is_active_at(state.previous_epoch()),
+                    current_epoch_effective_balance: effective_balance,
+                    ..ValidatorStatus::default()
+                })
+            })
+            .collect::<Result<Vec<_>, BeaconStateError>>()?;
 
-            if validator.is_active_at(state.current_epoch()) {
-                status.is_active_in_current_epoch = true;
-                total_balances.current_epoch += effective_balance;
+        let mut total_balances = TotalBalances::default();
+        for status in &statuses {
+            if status.is_active_in_current_epoch {
+                total_balances.current_epoch += status.current_epoch_effective_balance;
             }
-
-            if validator.is_active_at(state.previous_epoch()) {
-                status.is_active_in_previous_epoch = true;
-                total_balances.previous_epoch += effective_balance;
+            if status.is_active_in_previous_epoch {
+                total_balances.previous_epoch += status.current_epoch_effective_balance;
             }
-
-            statuses.push(status);
         }
 
         Ok(Self {
@@ -190,43 +193,51 @@ impl ValidatorStatuses {
         state: &BeaconState<T>,
         spec: &ChainSpec,
     ) -> Result<(), BeaconStateError> {
-        for a in state
+        // Determine the participants of each attestation in parallel. The participants are
+        // collected in attestation order, so the statuses are updated exactly as they would be
+        // sequentially.
+        let participation = state
             .previous_epoch_attestations
-            .iter()
-            .chain(state.current_epoch_attestations.iter())
-        {
-            let attesting_indices = get_attesting_indices(state, &a.data, &a.aggregation_bits)?;
-
-            let mut status = ValidatorStatus::default();
-
-            // Profile this attestation, updating the total balances and generating an
-            // `ValidatorStatus` object that applies to all participants in the attestation.
-            if a.data.target.epoch == state.current_epoch() {
-                status.is_current_epoch_attester = true;
-
-                if target_matches_epoch_start_block(a, state, state.current_epoch())? {
-                    status.is_current_epoch_target_attester = true;
-                }
-            } else if a.data.target.epoch == state.previous_epoch() {
-                status.is_previous_epoch_attester = true;
-
-                // The inclusion delay and proposer index are only required for previous epoch
-                // attesters.
-                status.inclusion_info = Some(InclusionInfo {
-                    delay: a.inclusion_delay,
-                    proposer_index: a.proposer_index as usize,
-                });
-
-                if target_matches_epoch_start_block(a, state, state.previous_epoch())? {
-                    status.is_previous_epoch_target_attester = true;
+            .par_iter()
+            .chain(state.current_epoch_attestations.par_iter())
+            .map(|a| {
+                let attesting_indices = get_attesting_indices(state, &a.data, &a.aggregation_bits)?;
+
+                let mut status = ValidatorStatus::default();
+
+                // Profile this attestation, updating the total balances and generating an
+                // `ValidatorStatus` object that applies to all participants in the attestation.
+                if a.data.target.epoch == state.current_epoch() {
+                    status.is_current_epoch_attester = true;
+
+                    if target_matches_epoch_start_block(a, state, state.current_epoch())? {
+                        status.is_current_epoch_target_attester = true;
+                    }
+                } else if a.data.target.epoch == state.previous_epoch() {
+                    status.is_previous_epoch_attester = true;
+
+                    // The inclusion delay and proposer index are only required for previous epoch
+                    // attesters.
+                    status.inclusion_info = Some(InclusionInfo {
+                        delay: a.inclusion_delay,
+                        proposer_index: a.proposer_index as usize,
+                    });
+
+                    if target_matches_epoch_start_block(a, state, state.previous_epoch())? {
+                        status.is_previous_epoch_target_attester = true;
+                    }
+
+                    if has_common_beacon_block_root(a, state)? {
+                        status.is_previous_epoch_head_attester = true;
+                    }
                 }
 
-                if has_common_beacon_block_root(a, state)? {
-                    status.is_previous_epoch_head_attester = true;
-                }
-            }
+                Ok((status, attesting_indices))
+            })
+            .collect::<Result<Vec<_>, BeaconStateError>>()?;
 
-            // Loop through the participating validator indices and update the status vec.
+        // Loop through the participating validator indices and update the status vec.
+        for (status, attesting_indices) in participation {
             for validator_index in attesting_indices {
                 self.statuses[validator_index].update(&status);
             }
@@ -287,3 +298,98 @@ fn has_common_beacon_block_root<T: EthSpec>(
 
     Ok(a.data.beacon_block_root == state_block_root)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::per_epoch_processing::tests::state_with_attestations;
+
+    /// Returns the statuses of the validators of `state`, determined one validator and one
+    /// attestation at a time, as the spec does.
+    fn sequential_statuses<T: EthSpec>(
+        state: &BeaconState<T>,
+        spec: &ChainSpec,
+    ) -> (Vec<ValidatorStatus>, TotalBalances) {
+        let mut statuses = vec![];
+        let mut total_balances = TotalBalances::default();
+
+        for (i, validator) in state.validators.iter().enumerate() {
+            let effective_balance = state.get_effective_balance(i, spec).unwrap();
+            let mut status = ValidatorStatus {
+                is_slashed: validator.slashed,
+                is_withdrawable_in_current_epoch: validator
+                    .is_withdrawable_at(state.current_epoch()),
+                current_epoch_effective_balance: effective_balance,
+                ..ValidatorStatus::default()
+            };
+
+            if validator.is_active_at(state.current_epoch()) {
+                status.is_active_in_current_epoch = true;
+                total_balances.current_epoch += effective_balance;
+            }
+
+            if validator.is_active_at(state.previous_epoch()) {
+                status.is_active_in_previous_epoch = true;
+                total_balances.previous_epoch += effective_balance;
+            }
+
+            statuses.push(status);
+        }
+
+        for a in state
+            .previous_epoch_attestations
+            .iter()
+            .chain(state.current_epoch_attestations.iter())
+        {
+            let attesting_indices =
+                get_attesting_indices(state, &a.data, &a.aggregation_bits).unwrap();
+
+            let mut status = ValidatorStatus::default();
+
+            if a.data.target.epoch == state.current_epoch() {
+                status.is_current_epoch_attester = true;
+                status.is_current_epoch_target_attester =
+                    target_matches_epoch_start_block(a, state, state.current_epoch()).unwrap();
+            } else if a.data.target.epoch == state.previous_epoch() {
+                status.is_previous_epoch_attester = true;
+                status.inclusion_info = Some(InclusionInfo {
+                    delay: a.inclusion_delay,
+                    proposer_index: a.proposer_index as usize,
+                });
+                status.is_previous_epoch_target_attester =
+                    target_matches_epoch_start_block(a, state, state.previous_epoch()).unwrap();
+                status.is_previous_epoch_head_attester =
+                    has_common_beacon_block_root(a, state).unwrap();
+            }
+
+            for validator_index in attesting_indices {
+                statuses[validator_index].update(&status);
+            }
+        }
+
+        (statuses, total_balances)
+    }
+
+    #[test]
+    fn statuses_match_sequential() {
+        let (state, spec) = state_with_attestations(64);
+
+        let (expected_statuses, expected_total_balances) = sequential_statuses(&state, &spec);
+
+        let mut validator_statuses =
+            ValidatorStatuses::new(&state, &spec).expect("should get statuses");
+        assert_eq!(validator_statuses.total_balances, expected_total_balances);
+
+        validator_statuses
+            .process_attestations(&state, &spec)
+            .expect("should process attestations");
+        assert_eq!(validator_statuses.statuses, expected_statuses);
+        assert!(
+            validator_statuses
+                .statuses
+                .iter()
+                .all(|status| status.is_previous_epoch_attester),
+            "every validator should have attested"
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::per_epoch_processing::validator_statuses::ValidatorStatuses;
 use crate::*;
 use types::*;
 
@@ -9,20 +10,25 @@ pub enum Error {
 
 /// Advances a state forward by one slot, performing per-epoch processing if required.
 ///
+/// Returns the `ValidatorStatuses` of the previous epoch if per-epoch processing was performed.
+///
 /// Spec v0.9.1
 pub fn per_slot_processing<T: EthSpec>(
     state: &mut BeaconState<T>,
     spec: &ChainSpec,
-) -> Result<(), Error> {
+) -> Result<Option<ValidatorStatuses>, Error> {
     cache_state(state)?;
 
-    if state.slot > spec.genesis_slot && (state.slot + 1) % T::slots_per_epoch() == 0 {
-        per_epoch_processing(state, spec)?;
-    }
+    let validator_statuses =
+        if state.slot > spec.genesis_slot && (state.slot + 1) % T::slots_per_epoch() == 0 {
+            Some(per_epoch_processing(state, spec)?)
+        } else {
+            None
+        };
 
     state.slot += 1;
 
-    Ok(())
+    Ok(validator_statuses)
 }
 
 fn cache_state<T: EthSpec>(state: &mut BeaconState<T>) -> Result<(), Error> {
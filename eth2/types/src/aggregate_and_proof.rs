@@ -0,0 +1,89 @@
+use super::{Attestation, ChainSpec, Domain, EthSpec, Fork, PublicKey, SecretKey, Signature, Slot};
+use crate::test_utils::TestRandom;
+
+use eth2_hashing::hash;
+use serde_derive::{Deserialize, Serialize};
+use ssz::Encode;
+use ssz_derive::{Decode, Encode};
+use std::convert::TryInto;
+use test_random_derive::TestRandom;
+use tree_hash::TreeHash;
+use tree_hash_derive::TreeHash;
+
+/// An aggregated attestation, broadcast by an aggregator along with the proof that they were
+/// selected to aggregate for the committee.
+///
+/// Spec v0.10.1
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode, TreeHash, TestRandom)]
+#[serde(bound = "T: EthSpec")]
+pub struct AggregateAndProof<T: EthSpec> {
+    /// The index of the validator that created the aggregate.
+    pub aggregator_index: u64,
+    /// The aggregate attestation.
+    pub aggregate: Attestation<T>,
+    /// A signature of `aggregate.data.slot` by the aggregator, proving that they were selected.
+    pub selection_proof: Signature,
+}
+
+impl<T: EthSpec> AggregateAndProof<T> {
+    /// Produces the proof that the holder of `secret_key` may aggregate attestations at `slot`,
+    /// if `Self::is_aggregator` returns `true` for it.
+    ///
+    /// Spec v0.10.1
+    pub fn produce_selection_proof(
+        slot: Slot,
+        secret_key: &SecretKey,
+        fork: &Fork,
+        spec: &ChainSpec,
+    ) -> Signature {
+        let domain = spec.get_domain(
+            slot.epoch(T::slots_per_epoch()),
+            Domain::SelectionProof,
+            fork,
+        );
+        Signature::new(&slot.tree_hash_root(), domain, secret_key)
+    }
+
+    /// Returns `true` if `self.selection_proof` is a valid signature of the slot of the aggregate
+    /// by `aggregator_pubkey`.
+    ///
+    /// Spec v0.10.1
+    pub fn is_valid_selection_proof(
+        &self,
+        aggregator_pubkey: &PublicKey,
+        fork: &Fork,
+        spec: &ChainSpec,
+    ) -> bool {
+        let slot = self.aggregate.data.slot;
+        let domain = spec.get_domain(
+            slot.epoch(T::slots_per_epoch()),
+            Domain::SelectionProof,
+            fork,
+        );
+        self.selection_proof
+            .verify(&slot.tree_hash_root(), domain, aggregator_pubkey)
+    }
+
+    /// Returns `true` if `self.selection_proof` selects the aggregator to aggregate for a
+    /// committee of `committee_len` members.
+    ///
+    /// Spec v0.10.1
+    pub fn is_aggregator(&self, committee_len: usize, spec: &ChainSpec) -> bool {
+        let modulo = std::cmp::max(
+            1,
+            committee_len as u64 / spec.target_aggregators_per_committee,
+        );
+        let digest = hash(&self.selection_proof.as_ssz_bytes());
+        let bytes: [u8; 8] = digest[0..8].try_into().expect("a hash is at least 8 bytes");
+
+        u64::from_le_bytes(bytes) % modulo == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    ssz_tests!(AggregateAndProof<MainnetEthSpec>);
+}
@@ -2,7 +2,7 @@ use self::committee_cache::get_active_validator_indices;
 use self::exit_cache::ExitCache;
 use crate::test_utils::TestRandom;
 use crate::*;
-use cached_tree_hash::{CachedTreeHash, MultiTreeHashCache, TreeHashCache};
+use cached_tree_hash::{int_log, CachedTreeHash, MultiTreeHashCache, TreeHashCache};
 use compare_fields_derive::CompareFields;
 use eth2_hashing::hash;
 use int_to_bytes::{int_to_bytes4, int_to_bytes8};
@@ -13,7 +13,7 @@ use ssz_derive::{Decode, Encode};
 use ssz_types::{typenum::Unsigned, BitVector, FixedVector};
 use swap_or_not_shuffle::compute_shuffled_index;
 use test_random_derive::TestRandom;
-use tree_hash::TreeHash;
+use tree_hash::{mix_in_length, TreeHash};
 use tree_hash_derive::{CachedTreeHash, TreeHash};
 
 pub use self::committee_cache::CommitteeCache;
@@ -26,6 +26,31 @@ mod pubkey_cache;
 mod tests;
 
 pub const CACHED_EPOCHS: usize = 3;
+
+/// The names of the tree-hashed fields of `BeaconState`, in order.
+pub const BEACON_STATE_FIELD_NAMES: [&str; 20] = [
+    "genesis_time",
+    "slot",
+    "fork",
+    "latest_block_header",
+    "block_roots",
+    "state_roots",
+    "historical_roots",
+    "eth1_data",
+    "eth1_data_votes",
+    "eth1_deposit_index",
+    "validators",
+    "balances",
+    "randao_mixes",
+    "slashings",
+    "previous_epoch_attestations",
+    "current_epoch_attestations",
+    "justification_bits",
+    "previous_justified_checkpoint",
+    "current_justified_checkpoint",
+    "finalized_checkpoint",
+];
+
 const MAX_RANDOM_BYTE: u64 = (1 << 8) - 1;
 
 #[derive(Debug, PartialEq)]
@@ -61,6 +86,9 @@ pub enum Error {
     CommitteeCacheUninitialized(Option<RelativeEpoch>),
     SszTypesError(ssz_types::Error),
     CachedTreeHashError(cached_tree_hash::Error),
+    /// A Merkle proof was requested for a node which is not in the state, or which is within a
+    /// field that does not have a tree hash cache.
+    UnsupportedGeneralizedIndex(usize),
 }
 
 /// Control whether an epoch-indexed field can be indexed at the next epoch or not.
@@ -252,6 +280,9 @@ impl<T: EthSpec> BeaconState<T> {
 
     /// Returns the `tree_hash_root` of the state.
     ///
+    /// Hashes the entire state. Prefer `update_tree_hash_cache` when the state is mutable, which
+    /// only rehashes the fields that have changed since the cache was last updated.
+    ///
     /// Spec v0.9.1
     pub fn canonical_root(&self) -> Hash256 {
         Hash256::from_slice(&self.tree_hash_root()[..])
@@ -388,6 +419,35 @@ impl<T: EthSpec> BeaconState<T> {
         cache.get_beacon_committees_at_slot(slot)
     }
 
+    /// Returns the indices of the validators of the attestation's committee whose bits are set in
+    /// `aggregation_bits`, in committee order.
+    ///
+    /// Utilises the committee cache of the attestation's epoch, which must be the previous,
+    /// current or next epoch.
+    ///
+    /// Spec v0.9.1
+    pub fn get_attestation_participants(
+        &self,
+        attestation_data: &AttestationData,
+        aggregation_bits: &BitList<T::MaxValidatorsPerCommittee>,
+    ) -> Result<Vec<usize>, Error> {
+        let committee = self.get_beacon_committee(attestation_data.slot, attestation_data.index)?;
+
+        if aggregation_bits.len() != committee.committee.len() {
+            return Err(Error::InvalidBitfield);
+        }
+
+        Ok(committee
+            .committee
+            .iter()
+            .enumerate()
+            .filter_map(|(i, validator_index)| match aggregation_bits.get(i) {
+                Ok(true) => Some(*validator_index),
+                _ => None,
+            })
+            .collect())
+    }
+
     /// Compute the proposer (not necessarily for the Beacon chain) from a list of indices.
     ///
     /// Spec v0.9.1
@@ -427,13 +487,19 @@ impl<T: EthSpec> BeaconState<T> {
         }
     }
 
-    /// Returns the beacon proposer index for the `slot` in the given `relative_epoch`.
+    /// Returns the beacon proposer index for the `slot`.
+    ///
+    /// Reads the active validators from the committee cache of the slot's epoch if it is
+    /// initialized, otherwise iterates over the validator registry.
     ///
     /// Spec v0.9.1
     pub fn get_beacon_proposer_index(&self, slot: Slot, spec: &ChainSpec) -> Result<usize, Error> {
         let epoch = slot.epoch(T::slots_per_epoch());
         let seed = self.get_beacon_proposer_seed(slot, spec)?;
-        let indices = self.get_active_validator_indices(epoch);
+        let indices = match self.committee_cache_at_slot(slot) {
+            Ok(cache) => cache.sorted_active_validator_indices(),
+            Err(_) => self.get_active_validator_indices(epoch),
+        };
 
         self.compute_proposer_index(&indices, &seed, spec)
     }
@@ -825,7 +891,7 @@ impl<T: EthSpec> BeaconState<T> {
 
     /// Returns the cache for some `RelativeEpoch`. Returns an error if the cache has not been
     /// initialized.
-    fn committee_cache(&self, relative_epoch: RelativeEpoch) -> Result<&CommitteeCache, Error> {
+    pub fn committee_cache(&self, relative_epoch: RelativeEpoch) -> Result<&CommitteeCache, Error> {
         let cache = &self.committee_caches[Self::committee_cache_index(relative_epoch)];
 
         if cache.is_initialized_at(relative_epoch.into_epoch(self.current_epoch())) {
@@ -901,6 +967,228 @@ impl<T: EthSpec> BeaconState<T> {
     pub fn drop_tree_hash_cache(&mut self) {
         self.tree_hash_cache = BeaconTreeHashCache::default();
     }
+
+    /// Returns the generalized index of the field of the state with the given `name`.
+    pub fn field_generalized_index(name: &str) -> Option<usize> {
+        let depth = int_log(BEACON_STATE_FIELD_NAMES.len());
+        BEACON_STATE_FIELD_NAMES
+            .iter()
+            .position(|field| *field == name)
+            .map(|field_index| (1 << depth) + field_index)
+    }
+
+    /// Returns the generalized index of the root of the validator record at `validator_index`.
+    pub fn validator_generalized_index(validator_index: usize) -> usize {
+        let validators = Self::field_generalized_index("validators")
+            .expect("the state should have a validators field");
+        // The left child of the list root is the root of the tree of values, the right child is
+        // the length of the list.
+        let values_root = validators * 2;
+        let depth = int_log(T::ValidatorRegistryLimit::to_usize());
+
+        (values_root << depth) + validator_index
+    }
+
+    /// Updates the tree hash cache, then computes a Merkle proof of the node at
+    /// `generalized_index` in the tree hash of the state.
+    ///
+    /// Returns the node and the proof, which can be checked against the state root with
+    /// `merkle_proof::verify_merkle_proof`, where the depth is `floor(log2(generalized_index))`
+    /// and the index is `generalized_index - 2^depth`.
+    ///
+    /// Only the fields of the state themselves and the nodes within fields that have a tree hash
+    /// cache (e.g., `validators` and the fields of each validator record) can be proven.
+    pub fn compute_merkle_proof(
+        &mut self,
+        generalized_index: usize,
+    ) -> Result<(Hash256, Vec<Hash256>), Error> {
+        if generalized_index == 0 {
+            return Err(Error::UnsupportedGeneralizedIndex(generalized_index));
+        }
+
+        self.update_tree_hash_cache()?;
+
+        let mut fields_cache = TreeHashCache::new(int_log(BEACON_STATE_FIELD_NAMES.len()));
+        fields_cache
+            .recalculate_merkle_root(self.field_roots().into_iter().map(Hash256::to_fixed_bytes))?;
+        let fields_depth = fields_cache.depth();
+
+        // The depth is `floor(log2(generalized_index))`.
+        let depth = int_log(generalized_index.saturating_add(1)) - 1;
+        let index = generalized_index - (1 << depth);
+
+        if depth <= fields_depth {
+            return Ok((
+                fields_cache.node(depth, index)?,
+                fields_cache.merkle_proof(depth, index)?,
+            ));
+        }
+
+        let subtree_depth = depth - fields_depth;
+        let field_index = index >> subtree_depth;
+        let subtree_index = index - (field_index << subtree_depth);
+
+        let (node, mut proof) = self
+            .field_merkle_proof(field_index, subtree_depth, subtree_index)
+            .map_err(|e| match e {
+                Error::CachedTreeHashError(_) => {
+                    Error::UnsupportedGeneralizedIndex(generalized_index)
+                }
+                e => e,
+            })?
+            .ok_or(Error::UnsupportedGeneralizedIndex(generalized_index))?;
+        proof.extend(fields_cache.merkle_proof(fields_depth, field_index)?);
+
+        Ok((node, proof))
+    }
+
+    /// Computes a Merkle proof of the node at `index` in the layer at `depth` of the subtree of
+    /// the field at `field_index`, up to the root of the field.
+    ///
+    /// Returns `None` if the field does not have a tree hash cache. The cache must be up-to-date.
+    fn field_merkle_proof(
+        &self,
+        field_index: usize,
+        depth: usize,
+        index: usize,
+    ) -> Result<Option<(Hash256, Vec<Hash256>)>, Error> {
+        let cache = &self.tree_hash_cache;
+
+        let proof = match BEACON_STATE_FIELD_NAMES.get(field_index) {
+            Some(&"block_roots") => cache_merkle_proof(&cache.block_roots, depth, index)?,
+            Some(&"state_roots") => cache_merkle_proof(&cache.state_roots, depth, index)?,
+            Some(&"historical_roots") => list_merkle_proof(
+                cache.historical_roots.root(),
+                self.historical_roots.len(),
+                depth,
+                index,
+                |depth, index| cache_merkle_proof(&cache.historical_roots, depth, index),
+            )?,
+            Some(&"validators") => {
+                let list_cache = cache.validators.list_cache();
+                list_merkle_proof(
+                    list_cache.root(),
+                    self.validators.len(),
+                    depth,
+                    index,
+                    |depth, index| {
+                        let list_depth = list_cache.depth();
+                        if depth <= list_depth {
+                            return cache_merkle_proof(list_cache, depth, index);
+                        }
+
+                        // The node is within a validator record.
+                        let validator_depth = depth - list_depth;
+                        let validator_index = index >> validator_depth;
+                        let validator_cache = cache
+                            .validators
+                            .value_cache(validator_index)
+                            .ok_or(Error::UnknownValidator)?;
+                        let (node, mut proof) = cache_merkle_proof(
+                            validator_cache,
+                            validator_depth,
+                            index - (validator_index << validator_depth),
+                        )?;
+                        proof.extend(list_cache.merkle_proof(list_depth, validator_index)?);
+
+                        Ok((node, proof))
+                    },
+                )?
+            }
+            Some(&"balances") => list_merkle_proof(
+                cache.balances.root(),
+                self.balances.len(),
+                depth,
+                index,
+                |depth, index| cache_merkle_proof(&cache.balances, depth, index),
+            )?,
+            Some(&"randao_mixes") => cache_merkle_proof(&cache.randao_mixes, depth, index)?,
+            Some(&"slashings") => cache_merkle_proof(&cache.slashings, depth, index)?,
+            _ => return Ok(None),
+        };
+
+        Ok(Some(proof))
+    }
+
+    /// The tree hash roots of the fields of the state, in order, using the tree hash cache for
+    /// the fields which have one. The cache must be up-to-date.
+    fn field_roots(&self) -> Vec<Hash256> {
+        let cache = &self.tree_hash_cache;
+        let root = |bytes: Vec<u8>| Hash256::from_slice(&bytes);
+
+        vec![
+            root(self.genesis_time.tree_hash_root()),
+            root(self.slot.tree_hash_root()),
+            root(self.fork.tree_hash_root()),
+            root(self.latest_block_header.tree_hash_root()),
+            cache.block_roots.root(),
+            cache.state_roots.root(),
+            root(mix_in_length(
+                cache.historical_roots.root().as_bytes(),
+                self.historical_roots.len(),
+            )),
+            root(self.eth1_data.tree_hash_root()),
+            root(self.eth1_data_votes.tree_hash_root()),
+            root(self.eth1_deposit_index.tree_hash_root()),
+            root(mix_in_length(
+                cache.validators.list_cache().root().as_bytes(),
+                self.validators.len(),
+            )),
+            root(mix_in_length(
+                cache.balances.root().as_bytes(),
+                self.balances.len(),
+            )),
+            cache.randao_mixes.root(),
+            cache.slashings.root(),
+            root(self.previous_epoch_attestations.tree_hash_root()),
+            root(self.current_epoch_attestations.tree_hash_root()),
+            root(self.justification_bits.tree_hash_root()),
+            root(self.previous_justified_checkpoint.tree_hash_root()),
+            root(self.current_justified_checkpoint.tree_hash_root()),
+            root(self.finalized_checkpoint.tree_hash_root()),
+        ]
+    }
+}
+
+/// Returns the node at `index` in the layer at `depth` of `cache`, and its Merkle proof.
+fn cache_merkle_proof(
+    cache: &TreeHashCache,
+    depth: usize,
+    index: usize,
+) -> Result<(Hash256, Vec<Hash256>), Error> {
+    Ok((cache.node(depth, index)?, cache.merkle_proof(depth, index)?))
+}
+
+/// Returns the node at `index` in the layer at `depth` of the subtree of a list, and its Merkle
+/// proof up to the root of the list.
+///
+/// The root of a list is the hash of the root of the tree of values and the length of the list.
+/// `values_proof` computes the proof of a node within the tree of values.
+fn list_merkle_proof<F>(
+    values_root: Hash256,
+    len: usize,
+    depth: usize,
+    index: usize,
+    values_proof: F,
+) -> Result<(Hash256, Vec<Hash256>), Error>
+where
+    F: FnOnce(usize, usize) -> Result<(Hash256, Vec<Hash256>), Error>,
+{
+    let mut length = [0; 32];
+    length[0..8].copy_from_slice(&(len as u64).to_le_bytes());
+    let length = Hash256::from(length);
+
+    match (depth, index) {
+        (1, 0) => Ok((values_root, vec![length])),
+        (1, 1) => Ok((length, vec![values_root])),
+        // The length is a leaf, so all deeper nodes are within the tree of values.
+        (depth, index) if depth > 1 && index < 1 << (depth - 1) => {
+            let (node, mut proof) = values_proof(depth - 1, index)?;
+            proof.push(length);
+            Ok((node, proof))
+        }
+        _ => Err(cached_tree_hash::Error::ProofIndexOutOfBounds.into()),
+    }
 }
 
 impl From<RelativeEpochError> for Error {
@@ -183,6 +183,23 @@ impl CommitteeCache {
         Some((epoch_start_slot + slot_offset, index))
     }
 
+    /// Returns the list of active validator indices for the initialized epoch, in ascending order.
+    ///
+    /// Unlike `get_active_validator_indices`, this does not read the validator registry.
+    ///
+    /// Always returns an empty list for a non-initialized epoch.
+    pub fn sorted_active_validator_indices(&self) -> Vec<usize> {
+        let mut active = Vec::with_capacity(self.shuffling.len());
+
+        for (index, position) in self.shuffling_positions.iter().enumerate() {
+            if position.is_some() {
+                active.push(index)
+            }
+        }
+
+        active
+    }
+
     /// Returns the number of active validators in the initialized epoch.
     ///
     /// Always returns `usize::default()` for a non-initialized epoch.
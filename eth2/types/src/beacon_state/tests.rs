@@ -69,6 +69,89 @@ fn beacon_proposer_index() {
     test_beacon_proposer_index::<MinimalEthSpec>();
 }
 
+#[test]
+fn beacon_proposer_index_with_and_without_cache() {
+    let spec = MinimalEthSpec::default_spec();
+    let builder: TestingBeaconStateBuilder<MinimalEthSpec> =
+        TestingBeaconStateBuilder::from_default_keypairs_file_if_exists(16, &spec);
+    let (mut state, _keypairs) = builder.build();
+
+    // a validator that is not active does not appear in the cache
+    let epoch = state.current_epoch();
+    state.validators[3].activation_epoch = epoch + 1;
+
+    let uncached = epoch
+        .slot_iter(MinimalEthSpec::slots_per_epoch())
+        .map(|slot| state.get_beacon_proposer_index(slot, &spec).unwrap())
+        .collect::<Vec<_>>();
+
+    state
+        .build_committee_cache(RelativeEpoch::Current, &spec)
+        .unwrap();
+    assert_eq!(
+        state.committee_caches[1].sorted_active_validator_indices(),
+        state.get_active_validator_indices(epoch)
+    );
+
+    let cached = epoch
+        .slot_iter(MinimalEthSpec::slots_per_epoch())
+        .map(|slot| state.get_beacon_proposer_index(slot, &spec).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(cached, uncached);
+}
+
+#[test]
+fn attestation_participants() {
+    let spec = MinimalEthSpec::default_spec();
+    let builder: TestingBeaconStateBuilder<MinimalEthSpec> =
+        TestingBeaconStateBuilder::from_default_keypairs_file_if_exists(16, &spec);
+    let (mut state, _keypairs) = builder.build();
+    state
+        .build_committee_cache(RelativeEpoch::Current, &spec)
+        .unwrap();
+
+    let committee = state
+        .get_beacon_committee(state.slot, 0)
+        .unwrap()
+        .into_owned();
+    let data = AttestationData {
+        slot: committee.slot,
+        index: committee.index,
+        beacon_block_root: Hash256::zero(),
+        source: Checkpoint::default(),
+        target: Checkpoint::default(),
+    };
+
+    let mut bits = BitList::with_capacity(committee.committee.len()).unwrap();
+    bits.set(0, true).unwrap();
+    bits.set(committee.committee.len() - 1, true).unwrap();
+    assert_eq!(
+        state.get_attestation_participants(&data, &bits).unwrap(),
+        vec![
+            committee.committee[0],
+            committee.committee[committee.committee.len() - 1]
+        ]
+    );
+
+    let short_bits = BitList::with_capacity(committee.committee.len() - 1).unwrap();
+    assert_eq!(
+        state.get_attestation_participants(&data, &short_bits),
+        Err(BeaconStateError::InvalidBitfield)
+    );
+
+    // the next epoch's committees are not cached
+    let next_data = AttestationData {
+        slot: state.slot + MinimalEthSpec::slots_per_epoch(),
+        ..data.clone()
+    };
+    assert_eq!(
+        state.get_attestation_participants(&next_data, &bits),
+        Err(BeaconStateError::CommitteeCacheUninitialized(Some(
+            RelativeEpoch::Next
+        )))
+    );
+}
+
 /// Test that
 ///
 /// 1. Using the cache before it's built fails.
@@ -144,6 +227,74 @@ fn tree_hash_cache() {
     assert_eq!(root.as_bytes(), &state.tree_hash_root()[..]);
 }
 
+#[test]
+fn merkle_proofs() {
+    use merkle_proof::verify_merkle_proof;
+
+    let spec = MinimalEthSpec::default_spec();
+    let builder: TestingBeaconStateBuilder<MinimalEthSpec> =
+        TestingBeaconStateBuilder::from_default_keypairs_file_if_exists(16, &spec);
+    let (mut state, _keypairs) = builder.build();
+    state.finalized_checkpoint.epoch = Epoch::new(3);
+    state.balances[5] = 42;
+
+    let state_root = Hash256::from_slice(&state.tree_hash_root());
+    let verify = |state: &mut BeaconState<MinimalEthSpec>, generalized_index: usize| {
+        let (node, proof) = state
+            .compute_merkle_proof(generalized_index)
+            .expect("should compute proof");
+        let depth = proof.len();
+        assert!(verify_merkle_proof(
+            node,
+            &proof,
+            depth,
+            generalized_index - (1 << depth),
+            state_root
+        ));
+        node
+    };
+
+    let finalized_checkpoint =
+        BeaconState::<MinimalEthSpec>::field_generalized_index("finalized_checkpoint").unwrap();
+    assert_eq!(
+        verify(&mut state, finalized_checkpoint).as_bytes(),
+        &state.finalized_checkpoint.tree_hash_root()[..]
+    );
+
+    let validator = BeaconState::<MinimalEthSpec>::validator_generalized_index(5);
+    assert_eq!(
+        verify(&mut state, validator).as_bytes(),
+        &state.validators[5].tree_hash_root()[..]
+    );
+
+    // The effective balance is the third of the eight fields of a validator record.
+    assert_eq!(
+        verify(&mut state, validator * 8 + 2).as_bytes(),
+        &state.validators[5].effective_balance.tree_hash_root()[..]
+    );
+
+    // The length of the validators list.
+    let validators = BeaconState::<MinimalEthSpec>::field_generalized_index("validators").unwrap();
+    assert_eq!(
+        verify(&mut state, validators * 2 + 1).as_bytes(),
+        &16u64.tree_hash_root()[..]
+    );
+
+    // Internal nodes of the state and of a vector.
+    verify(&mut state, 1);
+    verify(&mut state, 3);
+    let block_roots =
+        BeaconState::<MinimalEthSpec>::field_generalized_index("block_roots").unwrap();
+    verify(&mut state, block_roots * 4 + 1);
+
+    // Nodes below the length of a list, or within a field without a cache, are unsupported.
+    assert!(state.compute_merkle_proof(validators * 4 + 2).is_err());
+    assert!(state
+        .compute_merkle_proof(finalized_checkpoint * 2)
+        .is_err());
+    assert!(state.compute_merkle_proof(0).is_err());
+}
+
 /// Tests committee-specific components
 #[cfg(test)]
 mod committees {
@@ -1,6 +1,8 @@
 use crate::*;
 use int_to_bytes::int_to_bytes4;
 use serde_derive::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
 use utils::{u8_from_hex_str, u8_to_hex_str};
 
 /// Each of the BLS signature domains.
@@ -12,6 +14,7 @@ pub enum Domain {
     Randao,
     Deposit,
     VoluntaryExit,
+    SelectionProof,
 }
 
 /// Holds all the "constants" for a BeaconChain.
@@ -83,6 +86,7 @@ pub struct ChainSpec {
     domain_randao: u32,
     domain_deposit: u32,
     domain_voluntary_exit: u32,
+    domain_selection_proof: u32,
 
     /*
      * Fork choice
@@ -94,8 +98,13 @@ pub struct ChainSpec {
      */
     pub eth1_follow_distance: u64,
 
+    /*
+     * Networking
+     */
     pub boot_nodes: Vec<String>,
     pub network_id: u8,
+    pub attestation_subnet_count: u64,
+    pub target_aggregators_per_committee: u64,
 
     pub genesis_fork: Fork,
 }
@@ -111,6 +120,7 @@ impl ChainSpec {
             Domain::Randao => self.domain_randao,
             Domain::Deposit => self.domain_deposit,
             Domain::VoluntaryExit => self.domain_voluntary_exit,
+            Domain::SelectionProof => self.domain_selection_proof,
         }
     }
 
@@ -145,6 +155,51 @@ impl ChainSpec {
         u64::from_le_bytes(fork_and_domain)
     }
 
+    /// Return the digest of the fork version at `epoch`, for the chain with the given
+    /// `genesis_validators_root`.
+    ///
+    /// Used to scope gossip topics to a single fork, so that nodes on either side of a fork do
+    /// not exchange messages.
+    ///
+    /// Spec v0.10.1
+    pub fn compute_fork_digest(
+        &self,
+        epoch: Epoch,
+        fork: &Fork,
+        genesis_validators_root: Hash256,
+    ) -> ForkDigest {
+        ForkData {
+            current_version: fork.get_fork_version(epoch),
+            genesis_validators_root,
+        }
+        .fork_digest()
+    }
+
+    /// Returns the `EnrForkId` of a node at `epoch`, which is advertised in its ENR.
+    ///
+    /// If `fork` is scheduled for a later epoch, it is advertised as the next fork. Otherwise, no
+    /// fork is known to be upcoming and the next fork is the current one, at the far future epoch.
+    ///
+    /// Spec v0.10.1
+    pub fn enr_fork_id(
+        &self,
+        epoch: Epoch,
+        fork: &Fork,
+        genesis_validators_root: Hash256,
+    ) -> EnrForkId {
+        let (next_fork_version, next_fork_epoch) = if fork.epoch > epoch {
+            (fork.current_version, fork.epoch)
+        } else {
+            (fork.get_fork_version(epoch), self.far_future_epoch)
+        };
+
+        EnrForkId {
+            fork_digest: self.compute_fork_digest(epoch, fork, genesis_validators_root),
+            next_fork_version,
+            next_fork_epoch,
+        }
+    }
+
     /// Returns a `ChainSpec` compatible with the Ethereum Foundation specification.
     ///
     /// Spec v0.9.1
@@ -211,6 +266,7 @@ impl ChainSpec {
             domain_randao: 2,
             domain_deposit: 3,
             domain_voluntary_exit: 4,
+            domain_selection_proof: 5,
 
             /*
              * Fork choice
@@ -236,6 +292,8 @@ impl ChainSpec {
              */
             boot_nodes: vec![],
             network_id: 1, // mainnet network id
+            attestation_subnet_count: 64,
+            target_aggregators_per_committee: 16,
         }
     }
 
@@ -274,6 +332,74 @@ impl ChainSpec {
             ..ChainSpec::mainnet()
         }
     }
+
+    /// Returns the `ChainSpec` defined by a YAML or TOML file, as described by `ChainSpecFile`.
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        ChainSpecFile::from_file(path)?.chain_spec()
+    }
+
+    /// Returns an error if the parameters of `self` are inconsistent with each other, or would
+    /// cause a division by zero during state processing.
+    pub fn validate(&self) -> Result<(), String> {
+        let non_zero = [
+            ("SECONDS_PER_SLOT", self.milliseconds_per_slot),
+            (
+                "MAX_COMMITTEES_PER_SLOT",
+                self.max_committees_per_slot as u64,
+            ),
+            ("TARGET_COMMITTEE_SIZE", self.target_committee_size as u64),
+            ("CHURN_LIMIT_QUOTIENT", self.churn_limit_quotient),
+            ("SHUFFLE_ROUND_COUNT", u64::from(self.shuffle_round_count)),
+            (
+                "MIN_GENESIS_ACTIVE_VALIDATOR_COUNT",
+                self.min_genesis_active_validator_count,
+            ),
+            (
+                "EFFECTIVE_BALANCE_INCREMENT",
+                self.effective_balance_increment,
+            ),
+            ("BASE_REWARD_FACTOR", self.base_reward_factor),
+            (
+                "WHISTLEBLOWER_REWARD_QUOTIENT",
+                self.whistleblower_reward_quotient,
+            ),
+            ("PROPOSER_REWARD_QUOTIENT", self.proposer_reward_quotient),
+            (
+                "INACTIVITY_PENALTY_QUOTIENT",
+                self.inactivity_penalty_quotient,
+            ),
+            (
+                "MIN_SLASHING_PENALTY_QUOTIENT",
+                self.min_slashing_penalty_quotient,
+            ),
+            (
+                "TARGET_AGGREGATORS_PER_COMMITTEE",
+                self.target_aggregators_per_committee,
+            ),
+        ];
+        for (name, value) in non_zero.iter() {
+            if *value == 0 {
+                return Err(format!("{} must not be zero", name));
+            }
+        }
+
+        if self.min_seed_lookahead > self.max_seed_lookahead {
+            return Err("MIN_SEED_LOOKAHEAD must not exceed MAX_SEED_LOOKAHEAD".into());
+        }
+        if self.max_effective_balance % self.effective_balance_increment != 0 {
+            return Err(
+                "MAX_EFFECTIVE_BALANCE must be a multiple of EFFECTIVE_BALANCE_INCREMENT".into(),
+            );
+        }
+        if self.ejection_balance >= self.max_effective_balance {
+            return Err("EJECTION_BALANCE must be less than MAX_EFFECTIVE_BALANCE".into());
+        }
+        if self.min_deposit_amount > self.max_effective_balance {
+            return Err("MIN_DEPOSIT_AMOUNT must not exceed MAX_EFFECTIVE_BALANCE".into());
+        }
+
+        Ok(())
+    }
 }
 
 impl Default for ChainSpec {
@@ -282,6 +408,149 @@ impl Default for ChainSpec {
     }
 }
 
+/// A chain specification read from a YAML or TOML file.
+///
+/// The file names a preset with `PRESET_BASE` (`mainnet`, `minimal` or `interop`) and may override
+/// any of the parameters below, which are named as in the spec's YAML configs. Parameters that
+/// cannot be overridden (e.g., those that define the sizes of SSZ lists) are ignored.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub struct ChainSpecFile {
+    pub preset_base: String,
+    pub max_committees_per_slot: Option<usize>,
+    pub target_committee_size: Option<usize>,
+    pub min_per_epoch_churn_limit: Option<u64>,
+    pub churn_limit_quotient: Option<u64>,
+    pub shuffle_round_count: Option<u8>,
+    pub min_genesis_active_validator_count: Option<u64>,
+    pub min_genesis_time: Option<u64>,
+    pub min_deposit_amount: Option<u64>,
+    pub max_effective_balance: Option<u64>,
+    pub ejection_balance: Option<u64>,
+    pub effective_balance_increment: Option<u64>,
+    /// The fork version at genesis, as a `0x`-prefixed hex string.
+    pub genesis_fork_version: Option<String>,
+    pub seconds_per_slot: Option<u64>,
+    pub min_attestation_inclusion_delay: Option<u64>,
+    pub min_seed_lookahead: Option<u64>,
+    pub max_seed_lookahead: Option<u64>,
+    pub min_validator_withdrawability_delay: Option<u64>,
+    pub persistent_committee_period: Option<u64>,
+    pub min_epochs_to_inactivity_penalty: Option<u64>,
+    pub base_reward_factor: Option<u64>,
+    pub whistleblower_reward_quotient: Option<u64>,
+    pub proposer_reward_quotient: Option<u64>,
+    pub inactivity_penalty_quotient: Option<u64>,
+    pub min_slashing_penalty_quotient: Option<u64>,
+    pub safe_slots_to_update_justified: Option<u64>,
+    pub eth1_follow_distance: Option<u64>,
+    /// Not part of the `ChainSpec`, but read by the beacon node to follow deposits.
+    pub deposit_contract_address: Option<Address>,
+}
+
+impl ChainSpecFile {
+    /// Reads a file, which is parsed as TOML if it has a `.toml` extension and as YAML otherwise.
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| format!("Unable to read {:?}: {:?}", path, e))?;
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => toml::from_str(&contents)
+                .map_err(|e| format!("Unable to parse {:?}: {:?}", path, e)),
+            _ => serde_yaml::from_str(&contents)
+                .map_err(|e| format!("Unable to parse {:?}: {:?}", path, e)),
+        }
+    }
+
+    /// Returns the preset named by `PRESET_BASE`, with the overrides of `self` applied.
+    ///
+    /// Returns an error if the preset is unknown or the resulting spec fails
+    /// `ChainSpec::validate`.
+    pub fn chain_spec(&self) -> Result<ChainSpec, String> {
+        let mut spec = match self.preset_base.as_str() {
+            "mainnet" => ChainSpec::mainnet(),
+            "minimal" => ChainSpec::minimal(),
+            "interop" => ChainSpec::interop(),
+            other => return Err(format!("Unknown PRESET_BASE: {}", other)),
+        };
+
+        macro_rules! set {
+            ($field: ident) => {
+                if let Some(value) = self.$field {
+                    spec.$field = value;
+                }
+            };
+            ($field: ident, $spec_field: ident, $map: expr) => {
+                if let Some(value) = self.$field {
+                    spec.$spec_field = $map(value);
+                }
+            };
+        }
+
+        set!(max_committees_per_slot);
+        set!(target_committee_size);
+        set!(min_per_epoch_churn_limit);
+        set!(churn_limit_quotient);
+        set!(shuffle_round_count);
+        set!(min_genesis_active_validator_count);
+        set!(min_genesis_time);
+        set!(min_deposit_amount);
+        set!(max_effective_balance);
+        set!(ejection_balance);
+        set!(effective_balance_increment);
+        set!(seconds_per_slot, milliseconds_per_slot, |seconds: u64| {
+            seconds.saturating_mul(1_000)
+        });
+        set!(min_attestation_inclusion_delay);
+        set!(min_seed_lookahead, min_seed_lookahead, Epoch::new);
+        set!(max_seed_lookahead, max_seed_lookahead, Epoch::new);
+        set!(
+            min_validator_withdrawability_delay,
+            min_validator_withdrawability_delay,
+            Epoch::new
+        );
+        set!(persistent_committee_period);
+        set!(min_epochs_to_inactivity_penalty);
+        set!(base_reward_factor);
+        set!(whistleblower_reward_quotient);
+        set!(proposer_reward_quotient);
+        set!(inactivity_penalty_quotient);
+        set!(min_slashing_penalty_quotient);
+        set!(safe_slots_to_update_justified);
+        set!(eth1_follow_distance);
+
+        if let Some(version) = &self.genesis_fork_version {
+            let version = parse_fork_version(version)?;
+            spec.genesis_fork = Fork {
+                previous_version: version,
+                current_version: version,
+                epoch: spec.genesis_fork.epoch,
+            };
+        }
+
+        spec.validate()?;
+
+        Ok(spec)
+    }
+}
+
+/// Parses a `0x`-prefixed, four byte hex string.
+fn parse_fork_version(string: &str) -> Result<[u8; 4], String> {
+    let invalid = || format!("Invalid GENESIS_FORK_VERSION: {}", string);
+
+    if !string.starts_with("0x") {
+        return Err(invalid());
+    }
+    let bytes = hex::decode(&string[2..]).map_err(|_| invalid())?;
+    if bytes.len() != 4 {
+        return Err(invalid());
+    }
+
+    let mut version = [0; 4];
+    version.copy_from_slice(&bytes);
+    Ok(version)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -313,5 +582,74 @@ mod tests {
         test_domain(Domain::Randao, spec.domain_randao, &spec);
         test_domain(Domain::Deposit, spec.domain_deposit, &spec);
         test_domain(Domain::VoluntaryExit, spec.domain_voluntary_exit, &spec);
+        test_domain(Domain::SelectionProof, spec.domain_selection_proof, &spec);
+    }
+
+    #[test]
+    fn presets_are_valid() {
+        assert_eq!(ChainSpec::mainnet().validate(), Ok(()));
+        assert_eq!(ChainSpec::minimal().validate(), Ok(()));
+        assert_eq!(ChainSpec::interop().validate(), Ok(()));
+    }
+
+    #[test]
+    fn yaml_overrides() {
+        let file: ChainSpecFile = serde_yaml::from_str(
+            "PRESET_BASE: minimal\n\
+             SECONDS_PER_SLOT: 6\n\
+             MIN_GENESIS_ACTIVE_VALIDATOR_COUNT: 16\n\
+             GENESIS_FORK_VERSION: 0x00000001\n\
+             DEPOSIT_CONTRACT_ADDRESS: 0x1234567890123456789012345678901234567890\n\
+             HISTORICAL_ROOTS_LIMIT: 16777216\n",
+        )
+        .expect("should parse yaml");
+        let spec = file.chain_spec().expect("should be valid");
+
+        assert_eq!(
+            spec,
+            ChainSpec {
+                milliseconds_per_slot: 6_000,
+                min_genesis_active_validator_count: 16,
+                genesis_fork: Fork {
+                    previous_version: [0, 0, 0, 1],
+                    current_version: [0, 0, 0, 1],
+                    epoch: Epoch::new(0),
+                },
+                ..ChainSpec::minimal()
+            }
+        );
+        assert!(file.deposit_contract_address.is_some());
+    }
+
+    #[test]
+    fn toml_overrides() {
+        let file: ChainSpecFile =
+            toml::from_str("PRESET_BASE = \"mainnet\"\nETH1_FOLLOW_DISTANCE = 8\n")
+                .expect("should parse toml");
+        let spec = file.chain_spec().expect("should be valid");
+
+        assert_eq!(spec.eth1_follow_distance, 8);
+        assert_eq!(spec.target_committee_size, 128);
+    }
+
+    #[test]
+    fn invalid_files() {
+        let parse = |yaml: &str| -> ChainSpecFile { serde_yaml::from_str(yaml).unwrap() };
+
+        assert!(parse("PRESET_BASE: unknown").chain_spec().is_err());
+        assert!(parse("PRESET_BASE: minimal\nSECONDS_PER_SLOT: 0")
+            .chain_spec()
+            .is_err());
+        assert!(
+            parse("PRESET_BASE: minimal\nMIN_SEED_LOOKAHEAD: 5\nMAX_SEED_LOOKAHEAD: 4")
+                .chain_spec()
+                .is_err()
+        );
+        assert!(parse("PRESET_BASE: minimal\nEJECTION_BALANCE: 32000000000")
+            .chain_spec()
+            .is_err());
+        assert!(parse("PRESET_BASE: minimal\nGENESIS_FORK_VERSION: 0x0001")
+            .chain_spec()
+            .is_err());
     }
 }
@@ -0,0 +1,61 @@
+use crate::test_utils::TestRandom;
+use crate::utils::{fork_from_hex_str, fork_to_hex_str};
+use crate::Hash256;
+
+use serde_derive::{Deserialize, Serialize};
+use ssz_derive::{Decode, Encode};
+use test_random_derive::TestRandom;
+use tree_hash::TreeHash;
+use tree_hash_derive::TreeHash;
+
+/// The first four bytes of the root of a `ForkData`, used to scope network messages to a fork.
+pub type ForkDigest = [u8; 4];
+
+/// The fork version and genesis of a chain, from which its `ForkDigest` is computed.
+///
+/// Spec v0.10.1
+#[derive(
+    Debug, Clone, PartialEq, Default, Serialize, Deserialize, Encode, Decode, TreeHash, TestRandom,
+)]
+pub struct ForkData {
+    #[serde(
+        serialize_with = "fork_to_hex_str",
+        deserialize_with = "fork_from_hex_str"
+    )]
+    pub current_version: [u8; 4],
+    pub genesis_validators_root: Hash256,
+}
+
+impl ForkData {
+    /// Return the digest of `self`, which is unique to both the fork and the chain.
+    ///
+    /// Spec v0.10.1
+    pub fn fork_digest(&self) -> ForkDigest {
+        let mut digest = [0; 4];
+        digest.copy_from_slice(&self.tree_hash_root()[0..4]);
+        digest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ssz_tests!(ForkData);
+
+    #[test]
+    fn fork_digest() {
+        let fork_data = |current_version, genesis_validators_root| ForkData {
+            current_version,
+            genesis_validators_root,
+        };
+
+        let digest = fork_data([0; 4], Hash256::zero()).fork_digest();
+        assert_eq!(digest, fork_data([0; 4], Hash256::zero()).fork_digest());
+        assert_ne!(digest, fork_data([1; 4], Hash256::zero()).fork_digest());
+        assert_ne!(
+            digest,
+            fork_data([0; 4], Hash256::from_low_u64_be(1)).fork_digest()
+        );
+    }
+}
@@ -6,6 +6,7 @@
 #[macro_use]
 pub mod test_utils;
 
+pub mod aggregate_and_proof;
 pub mod attestation;
 pub mod attestation_data;
 pub mod attestation_duty;
@@ -19,9 +20,11 @@ pub mod chain_spec;
 pub mod checkpoint;
 pub mod deposit;
 pub mod deposit_data;
+pub mod enr_fork_id;
 pub mod eth1_data;
 pub mod eth_spec;
 pub mod fork;
+pub mod fork_data;
 pub mod free_attestation;
 pub mod historical_batch;
 pub mod indexed_attestation;
@@ -36,9 +39,11 @@ pub mod slot_epoch;
 pub mod slot_height;
 mod tree_hash_impls;
 pub mod validator;
+pub mod validator_subscription;
 
 use ethereum_types::{H160, H256};
 
+pub use crate::aggregate_and_proof::AggregateAndProof;
 pub use crate::attestation::{Attestation, Error as AttestationError};
 pub use crate::attestation_data::AttestationData;
 pub use crate::attestation_duty::AttestationDuty;
@@ -48,12 +53,14 @@ pub use crate::beacon_block_body::BeaconBlockBody;
 pub use crate::beacon_block_header::BeaconBlockHeader;
 pub use crate::beacon_committee::{BeaconCommittee, OwnedBeaconCommittee};
 pub use crate::beacon_state::{Error as BeaconStateError, *};
-pub use crate::chain_spec::{ChainSpec, Domain};
+pub use crate::chain_spec::{ChainSpec, ChainSpecFile, Domain};
 pub use crate::checkpoint::Checkpoint;
 pub use crate::deposit::{Deposit, DEPOSIT_TREE_DEPTH};
 pub use crate::deposit_data::DepositData;
+pub use crate::enr_fork_id::EnrForkId;
 pub use crate::eth1_data::Eth1Data;
 pub use crate::fork::Fork;
+pub use crate::fork_data::{ForkData, ForkDigest};
 pub use crate::free_attestation::FreeAttestation;
 pub use crate::historical_batch::HistoricalBatch;
 pub use crate::indexed_attestation::IndexedAttestation;
@@ -63,6 +70,7 @@ pub use crate::relative_epoch::{Error as RelativeEpochError, RelativeEpoch};
 pub use crate::slot_epoch::{Epoch, Slot};
 pub use crate::slot_height::SlotHeight;
 pub use crate::validator::Validator;
+pub use crate::validator_subscription::ValidatorSubscription;
 pub use crate::voluntary_exit::VoluntaryExit;
 
 pub type CommitteeIndex = u64;
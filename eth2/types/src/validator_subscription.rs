@@ -0,0 +1,26 @@
+use crate::*;
+use serde_derive::{Deserialize, Serialize};
+use ssz_derive::{Decode, Encode};
+
+/// A validator subscription, created when a validator subscribes to a slot to perform optional
+/// aggregation duties.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Encode, Decode)]
+pub struct ValidatorSubscription {
+    /// The validators index.
+    pub validator_index: u64,
+    /// The index of the committee within `slot` of which the validator is a member. Used by the
+    /// beacon node to quickly evaluate the associated `SubnetId`.
+    pub attestation_committee_index: CommitteeIndex,
+    /// The slot in which to subscribe.
+    pub slot: Slot,
+    /// If true, the validator is an aggregator and the beacon node should aggregate attestations
+    /// for this slot.
+    pub is_aggregator: bool,
+}
+
+impl ValidatorSubscription {
+    /// Returns the attestation subnet that `self.attestation_committee_index` maps to.
+    pub fn subnet_id(&self, spec: &ChainSpec) -> u64 {
+        self.attestation_committee_index % spec.attestation_subnet_count
+    }
+}
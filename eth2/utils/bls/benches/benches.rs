@@ -0,0 +1,62 @@
+//! Run with `--features supranational` to measure the `blst` backend.
+
+use bls::{
+    AggregatePublicKey, AggregateSignature, Backend, DefaultBackend, Keypair, PublicKey, Signature,
+    VerifySet,
+};
+use criterion::{black_box, criterion_group, criterion_main, Benchmark, Criterion};
+
+fn signed_set(keypairs: &[Keypair], message: &[u8], domain: u64) -> VerifySet {
+    let mut signature = AggregateSignature::new();
+    let mut public_key = AggregatePublicKey::new();
+    for keypair in keypairs {
+        signature.add(&Signature::new(message, domain, &keypair.sk));
+        public_key.add(&keypair.pk);
+    }
+    public_key.affine();
+
+    (
+        signature.as_raw().point.clone(),
+        vec![public_key.into_raw().point],
+        vec![message.to_vec()],
+        domain,
+    )
+}
+
+fn backend(c: &mut Criterion) {
+    let keypair = Keypair::random();
+    let public_key_bytes = keypair.pk.as_bytes();
+    c.bench_function("decode public key", move |b| {
+        b.iter(|| black_box(PublicKey::from_bytes(&public_key_bytes)))
+    });
+
+    let signature_bytes = Signature::new(b"message", 0, &keypair.sk).as_bytes();
+    c.bench_function("decode signature", move |b| {
+        b.iter(|| black_box(Signature::from_bytes(&signature_bytes)))
+    });
+
+    let keypairs: Vec<Keypair> = (0..64).map(|_| Keypair::random()).collect();
+
+    let set = signed_set(&keypairs[0..1], b"message", 0);
+    c.bench_function("verify signature set", move |b| {
+        b.iter(|| black_box(DefaultBackend::verify_signature_set(set.clone())))
+    });
+
+    for &count in &[1, 8, 64] {
+        let sets: Vec<VerifySet> = keypairs[0..count]
+            .iter()
+            .enumerate()
+            .map(|(i, keypair)| signed_set(&[keypair.clone()], &[i as u8; 32], 0))
+            .collect();
+        c.bench(
+            "verify signature sets",
+            Benchmark::new(format!("{} sets", count), move |b| {
+                b.iter(|| black_box(DefaultBackend::verify_signature_sets(sets.clone())))
+            })
+            .sample_size(10),
+        );
+    }
+}
+
+criterion_group!(benches, backend);
+criterion_main!(benches);
@@ -1,8 +1,5 @@
 use super::*;
-use milagro_bls::{
-    AggregatePublicKey as RawAggregatePublicKey, AggregateSignature as RawAggregateSignature,
-    G2Point,
-};
+use milagro_bls::{AggregateSignature as RawAggregateSignature, G2Point};
 use serde::de::{Deserialize, Deserializer};
 use serde::ser::{Serialize, Serializer};
 use serde_hex::{encode as hex_encode, HexVisitor};
@@ -56,8 +53,12 @@ impl AggregateSignature {
         if self.is_empty {
             return false;
         }
-        self.aggregate_signature
-            .verify(msg, domain, aggregate_public_key.as_raw())
+        DefaultBackend::verify_signature_set((
+            self.aggregate_signature.point.clone(),
+            vec![aggregate_public_key.as_raw().point.clone()],
+            vec![msg.to_vec()],
+            domain,
+        ))
     }
 
     /// Verify this AggregateSignature against multiple AggregatePublickeys with multiple Messages.
@@ -73,17 +74,15 @@ impl AggregateSignature {
         if self.is_empty {
             return false;
         }
-        let aggregate_public_keys: Vec<&RawAggregatePublicKey> =
-            aggregate_public_keys.iter().map(|pk| pk.as_raw()).collect();
-
-        // Messages are concatenated into one long message.
-        let mut msgs: Vec<Vec<u8>> = vec![];
-        for message in messages {
-            msgs.push(message.to_vec());
-        }
-
-        self.aggregate_signature
-            .verify_multiple(&msgs, domain, &aggregate_public_keys[..])
+        DefaultBackend::verify_signature_set((
+            self.aggregate_signature.point.clone(),
+            aggregate_public_keys
+                .iter()
+                .map(|pk| pk.as_raw().point.clone())
+                .collect(),
+            messages.iter().map(|message| message.to_vec()).collect(),
+            domain,
+        ))
     }
 
     /// Return AggregateSignature as bytes
@@ -98,11 +97,15 @@ impl AggregateSignature {
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
         for byte in bytes {
             if *byte != 0 {
-                let sig = RawAggregateSignature::from_bytes(&bytes).map_err(|_| {
+                let invalid = || {
                     DecodeError::BytesInvalid(
                         format!("Invalid AggregateSignature bytes: {:?}", bytes).to_string(),
                     )
-                })?;
+                };
+                if !DefaultBackend::signature_bytes_are_valid(bytes) {
+                    return Err(invalid());
+                }
+                let sig = RawAggregateSignature::from_bytes(&bytes).map_err(|_| invalid())?;
 
                 return Ok(Self {
                     aggregate_signature: sig,
@@ -0,0 +1,512 @@
+//! The BLS12-381 implementations that may perform signature verification.
+//!
+//! Keys and signatures are always held as `milagro_bls` points. A `Backend` performs the expensive
+//! parts of verification: hashing messages onto G2, the pairings of signature sets and the subgroup
+//! checks of deserialized points.
+//!
+//! `MilagroBackend` is used by default. Enabling the `supranational` feature selects
+//! `BlstBackend`, which uses the [blst](https://github.com/supranational/blst) library.
+
+use milagro_bls::{G1Point, G2Point};
+
+/// A signature, the public keys that signed it and the messages they signed (one per key), and
+/// the domain of the messages.
+pub type VerifySet = (G2Point, Vec<G1Point>, Vec<Vec<u8>>, u64);
+
+/// An implementation of the BLS12-381 operations required to verify signatures.
+pub trait Backend {
+    /// Returns `true` if the signature of `set` is the aggregate of the signatures of each of its
+    /// messages by the corresponding public key.
+    fn verify_signature_set(set: VerifySet) -> bool;
+
+    /// Returns `true` if all `sets` are valid.
+    ///
+    /// The sets are verified as a batch, which is faster than verifying each set individually.
+    fn verify_signature_sets(sets: Vec<VerifySet>) -> bool;
+
+    /// Returns `false` if `bytes` are not a compressed point in the G1 subgroup.
+    ///
+    /// Called before public keys are decoded, so that invalid keys are rejected cheaply.
+    fn public_key_bytes_are_valid(bytes: &[u8]) -> bool;
+
+    /// Returns `false` if `bytes` are not a compressed point in the G2 subgroup.
+    ///
+    /// Called before signatures are decoded, so that invalid signatures are rejected cheaply.
+    fn signature_bytes_are_valid(bytes: &[u8]) -> bool;
+}
+
+/// The backend that is selected by the features of this crate.
+#[cfg(not(feature = "supranational"))]
+pub type DefaultBackend = MilagroBackend;
+/// The backend that is selected by the features of this crate.
+#[cfg(feature = "supranational")]
+pub type DefaultBackend = BlstBackend;
+
+/// Verifies signatures with `milagro_bls`.
+pub struct MilagroBackend;
+
+impl Backend for MilagroBackend {
+    fn verify_signature_set(set: VerifySet) -> bool {
+        let (signature, public_keys, messages, domain) = set;
+
+        let signature = milagro_bls::AggregateSignature { point: signature };
+        let public_keys: Vec<milagro_bls::AggregatePublicKey> = public_keys
+            .into_iter()
+            .map(|point| milagro_bls::AggregatePublicKey { point })
+            .collect();
+        let public_key_refs: Vec<&milagro_bls::AggregatePublicKey> = public_keys.iter().collect();
+
+        signature.verify_multiple(&messages, domain, &public_key_refs)
+    }
+
+    fn verify_signature_sets(sets: Vec<VerifySet>) -> bool {
+        let rng = &mut rand::thread_rng();
+        milagro_bls::AggregateSignature::verify_multiple_signatures(rng, sets.into_iter())
+    }
+
+    /// Milagro checks points as they are decoded, so no additional check is made.
+    fn public_key_bytes_are_valid(_bytes: &[u8]) -> bool {
+        true
+    }
+
+    /// Milagro checks points as they are decoded, so no additional check is made.
+    fn signature_bytes_are_valid(_bytes: &[u8]) -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "supranational")]
+pub use blst_backend::BlstBackend;
+
+#[cfg(feature = "supranational")]
+mod blst_backend {
+    use super::{Backend, VerifySet};
+    use blst::{
+        blst_aggregated_in_g2, blst_fp12, blst_p1, blst_p1_affine, blst_p1_affine_in_g1,
+        blst_p1_from_affine, blst_p1_mult, blst_p1_to_affine, blst_p1_uncompress, blst_p2,
+        blst_p2_add_or_double, blst_p2_affine, blst_p2_affine_in_g2, blst_p2_from_affine,
+        blst_p2_mult, blst_p2_to_affine, blst_p2_uncompress, Pairing, BLST_ERROR,
+    };
+    use eth2_hashing::hash;
+    use lru::LruCache;
+    use milagro_bls::{G1Point, G2Point};
+    use rand::Rng;
+    use std::sync::Mutex;
+
+    /// The number of bits of the random scalars that sets are multiplied by in batch verification.
+    const RAND_BITS: usize = 64;
+
+    /// The number of decoded public keys to cache.
+    ///
+    /// Public keys are verified against repeatedly, so this is large enough to hold the keys of
+    /// every active validator.
+    const G1_CACHE_SIZE: usize = 131_072;
+    /// The number of decoded signatures to cache.
+    ///
+    /// A signature is usually only decoded from bytes and then verified, so few are needed.
+    const G2_CACHE_SIZE: usize = 4_096;
+
+    /// The cofactor of G2, as little-endian bytes.
+    const G2_COFACTOR: [u8; 64] = [
+        0xe5, 0x38, 0x72, 0x1c, 0xe3, 0x38, 0x1c, 0xcf, 0x70, 0x0c, 0x6f, 0x78, 0x6e, 0xec, 0x16,
+        0x16, 0xae, 0x91, 0x66, 0x3a, 0x29, 0x7e, 0x53, 0x21, 0xef, 0x82, 0x9e, 0x4d, 0xcb, 0xf1,
+        0x28, 0xa6, 0xdf, 0x7d, 0x5a, 0x2e, 0x5b, 0x20, 0x8a, 0xa6, 0xba, 0x5a, 0x08, 0x47, 0x45,
+        0xde, 0x91, 0xcd, 0x02, 0xa2, 0x76, 0x28, 0x79, 0x50, 0x1d, 0x09, 0xf1, 0xe7, 0x14, 0x54,
+        0xa9, 0x43, 0xd5, 0x05,
+    ];
+    /// The number of bits in `G2_COFACTOR`.
+    const G2_COFACTOR_BITS: usize = 507;
+
+    lazy_static! {
+        /// Decoded public keys, keyed by their compressed bytes.
+        static ref G1_CACHE: Mutex<LruCache<Vec<u8>, blst_p1_affine>> =
+            Mutex::new(LruCache::new(G1_CACHE_SIZE));
+        /// Decoded signatures, keyed by their compressed bytes.
+        static ref G2_CACHE: Mutex<LruCache<Vec<u8>, blst_p2_affine>> =
+            Mutex::new(LruCache::new(G2_CACHE_SIZE));
+    }
+
+    /// Verifies signatures with `blst`.
+    ///
+    /// Points are converted from `milagro_bls` through their compressed bytes. Decompression is
+    /// the most expensive part of the conversion, so decoded points are cached.
+    pub struct BlstBackend;
+
+    impl Backend for BlstBackend {
+        fn verify_signature_set(set: VerifySet) -> bool {
+            let (signature, public_keys, messages, domain) = set;
+
+            if public_keys.len() != messages.len() {
+                return false;
+            }
+
+            let signature = match g2_to_blst(&signature) {
+                Some(signature) => signature,
+                None => return false,
+            };
+
+            let mut pairing = Pairing::new(true, &[]);
+            for (public_key, message) in public_keys.iter().zip(messages.iter()) {
+                let public_key = match g1_to_blst(public_key) {
+                    Some(public_key) => public_key,
+                    None => return false,
+                };
+                pairing.raw_aggregate(&hash_on_g2(message, domain), &public_key);
+            }
+            pairing.commit();
+
+            let mut gtsig = blst_fp12::default();
+            unsafe { blst_aggregated_in_g2(&mut gtsig, &signature) };
+            pairing.finalverify(Some(&gtsig))
+        }
+
+        /// Each set is multiplied by a random non-zero scalar, so that an invalid set cannot be
+        /// cancelled out by another, then all pairings are checked at once.
+        fn verify_signature_sets(sets: Vec<VerifySet>) -> bool {
+            let rng = &mut rand::thread_rng();
+
+            let mut pairing = Pairing::new(true, &[]);
+            let mut aggregate_signature: Option<blst_p2> = None;
+
+            for (signature, public_keys, messages, domain) in sets {
+                if public_keys.len() != messages.len() {
+                    return false;
+                }
+
+                let mut scalar = rng.gen::<u64>().to_le_bytes();
+                scalar[0] |= 1;
+
+                let signature = match g2_to_blst(&signature) {
+                    Some(signature) => p2_mult(&signature, &scalar),
+                    None => return false,
+                };
+                aggregate_signature = Some(match aggregate_signature {
+                    Some(aggregate) => {
+                        let mut sum = blst_p2::default();
+                        unsafe { blst_p2_add_or_double(&mut sum, &aggregate, &signature) };
+                        sum
+                    }
+                    None => signature,
+                });
+
+                for (public_key, message) in public_keys.iter().zip(messages.iter()) {
+                    let public_key = match g1_to_blst(public_key) {
+                        Some(public_key) => p1_affine_mult(&public_key, &scalar),
+                        None => return false,
+                    };
+                    pairing.raw_aggregate(&hash_on_g2(message, domain), &public_key);
+                }
+            }
+
+            let aggregate_signature = match aggregate_signature {
+                Some(aggregate) => {
+                    let mut affine = blst_p2_affine::default();
+                    unsafe { blst_p2_to_affine(&mut affine, &aggregate) };
+                    affine
+                }
+                // There is nothing to verify.
+                None => return true,
+            };
+            pairing.commit();
+
+            let mut gtsig = blst_fp12::default();
+            unsafe { blst_aggregated_in_g2(&mut gtsig, &aggregate_signature) };
+            pairing.finalverify(Some(&gtsig))
+        }
+
+        /// The decoded point is cached, so that it isn't decoded again when it is verified.
+        fn public_key_bytes_are_valid(bytes: &[u8]) -> bool {
+            match g1_from_bytes(bytes) {
+                Some(point) => {
+                    cache_put(&G1_CACHE, bytes, point);
+                    true
+                }
+                None => false,
+            }
+        }
+
+        /// The decoded point is cached, so that it isn't decoded again when it is verified.
+        fn signature_bytes_are_valid(bytes: &[u8]) -> bool {
+            match g2_from_bytes(bytes) {
+                Some(point) => {
+                    cache_put(&G2_CACHE, bytes, point);
+                    true
+                }
+                None => false,
+            }
+        }
+    }
+
+    /// Decompresses `bytes` into a G1 point, returning `None` if it is not in the subgroup.
+    fn g1_from_bytes(bytes: &[u8]) -> Option<blst_p1_affine> {
+        if bytes.len() != 48 {
+            return None;
+        }
+        let mut point = blst_p1_affine::default();
+        if unsafe { blst_p1_uncompress(&mut point, bytes.as_ptr()) } != BLST_ERROR::BLST_SUCCESS {
+            return None;
+        }
+        if !unsafe { blst_p1_affine_in_g1(&point) } {
+            return None;
+        }
+        Some(point)
+    }
+
+    /// Decompresses `bytes` into a G2 point, returning `None` if it is not in the subgroup.
+    fn g2_from_bytes(bytes: &[u8]) -> Option<blst_p2_affine> {
+        if bytes.len() != 96 {
+            return None;
+        }
+        let mut point = blst_p2_affine::default();
+        if unsafe { blst_p2_uncompress(&mut point, bytes.as_ptr()) } != BLST_ERROR::BLST_SUCCESS {
+            return None;
+        }
+        if !unsafe { blst_p2_affine_in_g2(&point) } {
+            return None;
+        }
+        Some(point)
+    }
+
+    /// Converts a `milagro_bls` G1 point into a `blst` point.
+    ///
+    /// Milagro points are already known to be in the subgroup, so the check is skipped.
+    fn g1_to_blst(point: &G1Point) -> Option<blst_p1_affine> {
+        let bytes = milagro_bls::PublicKey {
+            point: point.clone(),
+        }
+        .as_bytes();
+        if let Some(affine) = cache_get(&G1_CACHE, &bytes) {
+            return Some(affine);
+        }
+        let mut affine = blst_p1_affine::default();
+        match unsafe { blst_p1_uncompress(&mut affine, bytes.as_ptr()) } {
+            BLST_ERROR::BLST_SUCCESS => {
+                cache_put(&G1_CACHE, &bytes, affine);
+                Some(affine)
+            }
+            _ => None,
+        }
+    }
+
+    /// Converts a `milagro_bls` G2 point into a `blst` point.
+    ///
+    /// Milagro points are already known to be in the subgroup, so the check is skipped.
+    fn g2_to_blst(point: &G2Point) -> Option<blst_p2_affine> {
+        let bytes = milagro_bls::AggregateSignature {
+            point: point.clone(),
+        }
+        .as_bytes();
+        if let Some(affine) = cache_get(&G2_CACHE, &bytes) {
+            return Some(affine);
+        }
+        let mut affine = blst_p2_affine::default();
+        match unsafe { blst_p2_uncompress(&mut affine, bytes.as_ptr()) } {
+            BLST_ERROR::BLST_SUCCESS => {
+                cache_put(&G2_CACHE, &bytes, affine);
+                Some(affine)
+            }
+            _ => None,
+        }
+    }
+
+    fn cache_get<T: Copy>(cache: &Mutex<LruCache<Vec<u8>, T>>, bytes: &[u8]) -> Option<T> {
+        cache
+            .lock()
+            .ok()
+            .and_then(|mut cache| cache.get(&bytes.to_vec()).copied())
+    }
+
+    fn cache_put<T>(cache: &Mutex<LruCache<Vec<u8>, T>>, bytes: &[u8], point: T) {
+        if let Ok(mut cache) = cache.lock() {
+            cache.put(bytes.to_vec(), point);
+        }
+    }
+
+    /// Hashes `message` and `domain` onto G2 as the spec's `hash_to_G2` (and
+    /// `milagro_bls::hash_on_g2`) does, using `blst` for the field arithmetic.
+    ///
+    /// The x-coordinate is found by "try-and-increment". Each candidate is decompressed with the
+    /// flag for the lexicographically largest y-coordinate, which is the root that the spec's
+    /// `modular_squareroot` chooses.
+    pub fn hash_on_g2(message: &[u8], domain: u64) -> blst_p2_affine {
+        let domain = domain.to_be_bytes();
+
+        // The compressed encoding of an x-coordinate: the imaginary part then the real part, each
+        // as 48 big-endian bytes.
+        let mut x = [0; 96];
+        x[16..48].copy_from_slice(&hash(&[message, &domain[..], &[2]].concat()));
+        x[64..96].copy_from_slice(&hash(&[message, &domain[..], &[1]].concat()));
+
+        let mut affine = blst_p2_affine::default();
+        loop {
+            let mut bytes = x;
+            // Set the compression and "largest y" flags.
+            bytes[0] |= 0xa0;
+            if unsafe { blst_p2_uncompress(&mut affine, bytes.as_ptr()) }
+                == BLST_ERROR::BLST_SUCCESS
+            {
+                break;
+            }
+            increment_be(&mut x[48..96]);
+        }
+
+        let mut projective = blst_p2::default();
+        let mut product = blst_p2::default();
+        unsafe {
+            blst_p2_from_affine(&mut projective, &affine);
+            blst_p2_mult(
+                &mut product,
+                &projective,
+                G2_COFACTOR.as_ptr(),
+                G2_COFACTOR_BITS,
+            );
+            blst_p2_to_affine(&mut affine, &product);
+        }
+        affine
+    }
+
+    /// Adds one to the big-endian integer `bytes`.
+    fn increment_be(bytes: &mut [u8]) {
+        for byte in bytes.iter_mut().rev() {
+            let (sum, overflow) = byte.overflowing_add(1);
+            *byte = sum;
+            if !overflow {
+                break;
+            }
+        }
+    }
+
+    /// Multiplies a G1 point by a little-endian `scalar`.
+    fn p1_affine_mult(point: &blst_p1_affine, scalar: &[u8]) -> blst_p1_affine {
+        let mut projective = blst_p1::default();
+        let mut product = blst_p1::default();
+        let mut affine = blst_p1_affine::default();
+        unsafe {
+            blst_p1_from_affine(&mut projective, point);
+            blst_p1_mult(&mut product, &projective, scalar.as_ptr(), RAND_BITS);
+            blst_p1_to_affine(&mut affine, &product);
+        }
+        affine
+    }
+
+    /// Multiplies a G2 point by a little-endian `scalar`.
+    fn p2_mult(point: &blst_p2_affine, scalar: &[u8]) -> blst_p2 {
+        let mut projective = blst_p2::default();
+        let mut product = blst_p2::default();
+        unsafe {
+            blst_p2_from_affine(&mut projective, point);
+            blst_p2_mult(&mut product, &projective, scalar.as_ptr(), RAND_BITS);
+        }
+        product
+    }
+}
+
+#[cfg(all(test, not(feature = "fake_crypto")))]
+mod tests {
+    use super::*;
+    use crate::{AggregatePublicKey, AggregateSignature, Keypair, Signature};
+
+    fn signed_set(keypairs: &[Keypair], message: &[u8], domain: u64) -> VerifySet {
+        let mut signature = AggregateSignature::new();
+        let mut public_key = AggregatePublicKey::new();
+        for keypair in keypairs {
+            signature.add(&Signature::new(message, domain, &keypair.sk));
+            public_key.add(&keypair.pk);
+        }
+        public_key.affine();
+
+        (
+            signature.as_raw().point.clone(),
+            vec![public_key.into_raw().point],
+            vec![message.to_vec()],
+            domain,
+        )
+    }
+
+    #[test]
+    fn verify_signature_set() {
+        let keypairs: Vec<Keypair> = (0..3).map(|_| Keypair::random()).collect();
+        let set = signed_set(&keypairs, b"message", 42);
+
+        assert!(DefaultBackend::verify_signature_set(set.clone()));
+
+        let (signature, public_keys, _, domain) = set.clone();
+        assert!(!DefaultBackend::verify_signature_set((
+            signature,
+            public_keys,
+            vec![b"other message".to_vec()],
+            domain
+        )));
+
+        let (signature, public_keys, messages, _) = set;
+        assert!(!DefaultBackend::verify_signature_set((
+            signature,
+            public_keys,
+            messages,
+            43
+        )));
+    }
+
+    #[test]
+    fn verify_signature_sets() {
+        let keypairs: Vec<Keypair> = (0..4).map(|_| Keypair::random()).collect();
+        let sets = vec![
+            signed_set(&keypairs[0..1], b"first", 1),
+            signed_set(&keypairs[1..4], b"second", 2),
+        ];
+
+        assert!(DefaultBackend::verify_signature_sets(sets.clone()));
+
+        let mut invalid = sets;
+        invalid[1].3 = 3;
+        assert!(!DefaultBackend::verify_signature_sets(invalid));
+    }
+
+    #[test]
+    fn valid_bytes_are_accepted() {
+        let keypair = Keypair::random();
+        let signature = Signature::new(b"message", 0, &keypair.sk);
+
+        assert!(DefaultBackend::public_key_bytes_are_valid(
+            &keypair.pk.as_bytes()
+        ));
+        assert!(DefaultBackend::signature_bytes_are_valid(
+            &signature.as_bytes()
+        ));
+    }
+
+    #[cfg(feature = "supranational")]
+    #[test]
+    fn blst_hash_on_g2_matches_milagro() {
+        use blst::{blst_p2_affine, blst_p2_affine_compress};
+
+        fn compress(point: &blst_p2_affine) -> Vec<u8> {
+            let mut bytes = vec![0; 96];
+            unsafe { blst_p2_affine_compress(bytes.as_mut_ptr(), point) };
+            bytes
+        }
+
+        for (message, domain) in &[
+            (&[0; 32][..], 0),
+            (&[1; 32][..], 42),
+            (&b"message"[..], u64::max_value()),
+        ] {
+            let expected = milagro_bls::AggregateSignature {
+                point: milagro_bls::hash_on_g2(message, *domain),
+            }
+            .as_bytes();
+            assert_eq!(
+                compress(&blst_backend::hash_on_g2(message, *domain)),
+                expected
+            );
+        }
+    }
+
+    #[cfg(feature = "supranational")]
+    #[test]
+    fn invalid_bytes_are_rejected() {
+        assert!(!DefaultBackend::public_key_bytes_are_valid(&[0xff; 48]));
+        assert!(!DefaultBackend::signature_bytes_are_valid(&[0xff; 96]));
+        assert!(!DefaultBackend::public_key_bytes_are_valid(&[0x80; 47]));
+    }
+}
@@ -55,6 +55,14 @@ impl FakePublicKey {
         })
     }
 
+    /// Does nothing, since `Self::from_bytes` never decompresses. Only for compatibility with
+    /// the "real" `PublicKey`.
+    pub fn register_known_keys<'a, I>(_pubkeys: I)
+    where
+        I: IntoIterator<Item = &'a FakePublicKey>,
+    {
+    }
+
     /// Returns the FakePublicKey as (x, y) bytes
     pub fn as_uncompressed_bytes(&self) -> Vec<u8> {
         self.as_bytes()
@@ -1,14 +1,18 @@
 extern crate milagro_bls;
 extern crate ssz;
+#[macro_use]
+extern crate lazy_static;
 
 #[macro_use]
 mod macros;
+mod backend;
 mod keypair;
 mod public_key_bytes;
 mod secret_key;
 mod signature_bytes;
 mod signature_set;
 
+pub use crate::backend::{Backend, DefaultBackend, MilagroBackend, VerifySet};
 pub use crate::keypair::Keypair;
 pub use crate::public_key_bytes::PublicKeyBytes;
 pub use crate::secret_key::SecretKey;
@@ -16,6 +20,9 @@ pub use crate::signature_bytes::SignatureBytes;
 pub use milagro_bls::{compress_g2, hash_on_g2, G1Point};
 pub use signature_set::{verify_signature_sets, SignatureSet, SignedMessage};
 
+#[cfg(feature = "supranational")]
+pub use crate::backend::BlstBackend;
+
 #[cfg(feature = "fake_crypto")]
 mod fake_aggregate_public_key;
 #[cfg(feature = "fake_crypto")]
@@ -1,12 +1,24 @@
-use super::{SecretKey, BLS_PUBLIC_KEY_BYTE_SIZE};
+use super::{Backend, DefaultBackend, SecretKey, BLS_PUBLIC_KEY_BYTE_SIZE};
 use milagro_bls::PublicKey as RawPublicKey;
 use serde::de::{Deserialize, Deserializer};
 use serde::ser::{Serialize, Serializer};
 use serde_hex::{encode as hex_encode, HexVisitor};
 use ssz::{Decode, DecodeError, Encode};
+use std::collections::HashMap;
 use std::default;
 use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::sync::RwLock;
+
+lazy_static! {
+    /// Decompressed public keys, keyed by their compressed bytes.
+    ///
+    /// Decompressing a public key is far more expensive than decoding any other part of a
+    /// `BeaconState`. Keys registered here (e.g., from the beacon node's persisted pubkey cache)
+    /// are not decompressed again when their bytes are decoded.
+    static ref KNOWN_PUBLIC_KEYS: RwLock<HashMap<Vec<u8>, RawPublicKey>> =
+        RwLock::new(HashMap::new());
+}
 
 /// A single BLS signature.
 ///
@@ -37,14 +49,44 @@ impl PublicKey {
     }
 
     /// Converts compressed bytes to PublicKey
+    ///
+    /// Bytes of a key previously passed to `Self::register_known_keys` are not decompressed.
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
-        let pubkey = RawPublicKey::from_bytes(&bytes).map_err(|_| {
+        if let Some(pubkey) = KNOWN_PUBLIC_KEYS
+            .read()
+            .ok()
+            .and_then(|known| known.get(bytes).cloned())
+        {
+            return Ok(PublicKey(pubkey));
+        }
+
+        let invalid = || {
             DecodeError::BytesInvalid(format!("Invalid PublicKey bytes: {:?}", bytes).to_string())
-        })?;
+        };
+        if !DefaultBackend::public_key_bytes_are_valid(bytes) {
+            return Err(invalid());
+        }
+        let pubkey = RawPublicKey::from_bytes(&bytes).map_err(|_| invalid())?;
 
         Ok(PublicKey(pubkey))
     }
 
+    /// Registers `pubkeys` so that decoding their compressed bytes with `Self::from_bytes` skips
+    /// decompression.
+    ///
+    /// The keys are retained for the lifetime of the process, so this should only be used for
+    /// keys which are expected to be decoded repeatedly (i.e., those of the validator registry).
+    pub fn register_known_keys<'a, I>(pubkeys: I)
+    where
+        I: IntoIterator<Item = &'a PublicKey>,
+    {
+        if let Ok(mut known) = KNOWN_PUBLIC_KEYS.write() {
+            for pubkey in pubkeys {
+                known.insert(pubkey.as_bytes(), pubkey.0.clone());
+            }
+        }
+    }
+
     /// Returns the PublicKey as (x, y) bytes
     pub fn as_uncompressed_bytes(&self) -> Vec<u8> {
         RawPublicKey::as_uncompressed_bytes(&mut self.0.clone())
@@ -151,6 +193,26 @@ mod tests {
         assert_eq!(original, decoded);
     }
 
+    #[test]
+    pub fn test_known_keys_round_trip() {
+        let original = PublicKey::from_secret_key(&SecretKey::random());
+        let bytes = ssz_encode(&original);
+
+        PublicKey::register_known_keys(&[original.clone()]);
+        assert!(KNOWN_PUBLIC_KEYS.read().unwrap().contains_key(&bytes));
+
+        let decoded = PublicKey::from_ssz_bytes(&bytes).unwrap();
+        assert_eq!(original, decoded);
+
+        // Unregistered and invalid keys are unaffected.
+        let other = PublicKey::from_secret_key(&SecretKey::random());
+        assert_eq!(
+            PublicKey::from_ssz_bytes(&ssz_encode(&other)).unwrap(),
+            other
+        );
+        assert!(PublicKey::from_ssz_bytes(&[0; BLS_PUBLIC_KEY_BYTE_SIZE]).is_err());
+    }
+
     #[test]
     pub fn test_byte_size() {
         let sk = SecretKey::random();
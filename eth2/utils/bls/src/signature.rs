@@ -1,4 +1,4 @@
-use super::{PublicKey, SecretKey, BLS_SIG_BYTE_SIZE};
+use super::{Backend, DefaultBackend, PublicKey, SecretKey, BLS_SIG_BYTE_SIZE};
 use milagro_bls::Signature as RawSignature;
 use serde::de::{Deserialize, Deserializer};
 use serde::ser::{Serialize, Serializer};
@@ -38,7 +38,12 @@ impl Signature {
         if self.is_empty {
             return false;
         }
-        self.signature.verify(msg, domain, pk.as_raw())
+        DefaultBackend::verify_signature_set((
+            self.signature.point.clone(),
+            vec![pk.as_raw().point.clone()],
+            vec![msg.to_vec()],
+            domain,
+        ))
     }
 
     /// Verify the Signature against a PublicKey, where the message has already been hashed.
@@ -80,11 +85,15 @@ impl Signature {
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
         for byte in bytes {
             if *byte != 0 {
-                let raw_signature = RawSignature::from_bytes(&bytes).map_err(|_| {
+                let invalid = || {
                     DecodeError::BytesInvalid(
                         format!("Invalid Signature bytes: {:?}", bytes).to_string(),
                     )
-                })?;
+                };
+                if !DefaultBackend::signature_bytes_are_valid(bytes) {
+                    return Err(invalid());
+                }
+                let raw_signature = RawSignature::from_bytes(&bytes).map_err(|_| invalid())?;
                 return Ok(Signature {
                     signature: raw_signature,
                     is_empty: false,
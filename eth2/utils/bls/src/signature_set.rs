@@ -1,9 +1,7 @@
 use crate::{AggregatePublicKey, AggregateSignature, PublicKey, Signature};
+use crate::{Backend, DefaultBackend, VerifySet};
 use milagro_bls::{G1Point, G2Point};
 
-#[cfg(not(feature = "fake_crypto"))]
-use milagro_bls::AggregateSignature as RawAggregateSignature;
-
 type Message = Vec<u8>;
 type Domain = u64;
 
@@ -84,36 +82,13 @@ impl<'a> SignatureSet<'a> {
     }
 
     pub fn is_valid(&self) -> bool {
-        let sig = milagro_bls::AggregateSignature {
-            point: self.signature.clone(),
-        };
-
-        let mut messages: Vec<Vec<u8>> = vec![];
-        let mut pubkeys = vec![];
-
-        self.signed_messages.iter().for_each(|signed_message| {
-            messages.push(signed_message.message.clone());
-
-            let point = if signed_message.signing_keys.len() == 1 {
-                signed_message.signing_keys[0].clone()
-            } else {
-                aggregate_public_keys(&signed_message.signing_keys)
-            };
-
-            pubkeys.push(milagro_bls::AggregatePublicKey { point });
-        });
-
-        let pubkey_refs: Vec<&milagro_bls::AggregatePublicKey> =
-            pubkeys.iter().map(std::borrow::Borrow::borrow).collect();
-
-        sig.verify_multiple(&messages, self.domain, &pubkey_refs)
+        DefaultBackend::verify_signature_set(self.clone().into())
     }
 }
 
 #[cfg(not(feature = "fake_crypto"))]
 pub fn verify_signature_sets<'a>(iter: impl Iterator<Item = SignatureSet<'a>>) -> bool {
-    let rng = &mut rand::thread_rng();
-    RawAggregateSignature::verify_multiple_signatures(rng, iter.map(Into::into))
+    DefaultBackend::verify_signature_sets(iter.map(Into::into).collect())
 }
 
 #[cfg(feature = "fake_crypto")]
@@ -121,10 +96,8 @@ pub fn verify_signature_sets<'a>(_iter: impl Iterator<Item = SignatureSet<'a>>)
     true
 }
 
-type VerifySet<'a> = (G2Point, Vec<G1Point>, Vec<Vec<u8>>, u64);
-
-impl<'a> Into<VerifySet<'a>> for SignatureSet<'a> {
-    fn into(self) -> VerifySet<'a> {
+impl<'a> Into<VerifySet> for SignatureSet<'a> {
+    fn into(self) -> VerifySet {
         let signature = self.signature.clone();
 
         let (pubkeys, messages): (Vec<G1Point>, Vec<Message>) = self
@@ -127,6 +127,43 @@ impl TreeHashCache {
     pub fn leaves(&mut self) -> &mut Vec<Hash256> {
         &mut self.layers[self.depth]
     }
+
+    /// The depth of the tree, such that it has a capacity for 2^depth leaves.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Get the node at `index` in the layer at `depth` (the root is at depth 0), without doing
+    /// any updates/computation.
+    ///
+    /// Nodes beyond the end of a sparse layer are the roots of empty subtrees.
+    pub fn node(&self, depth: usize, index: usize) -> Result<Hash256, Error> {
+        let layer = self.layers.get(depth).ok_or(Error::ProofIndexOutOfBounds)?;
+
+        if (index as u128) >= 1u128 << depth {
+            return Err(Error::ProofIndexOutOfBounds);
+        }
+
+        Ok(layer
+            .get(index)
+            .copied()
+            .unwrap_or_else(|| Hash256::from_slice(&ZERO_HASHES[self.depth - depth])))
+    }
+
+    /// Compute a Merkle proof for the node at `index` in the layer at `depth`, without doing any
+    /// updates/computation.
+    ///
+    /// The proof is the list of sibling nodes from the node's layer up to (but excluding) the
+    /// root, as expected by `merkle_proof::verify_merkle_proof`.
+    pub fn merkle_proof(&self, depth: usize, index: usize) -> Result<Vec<Hash256>, Error> {
+        // Check the bounds of the node itself before computing the siblings of its ancestors.
+        self.node(depth, index)?;
+
+        (1..=depth)
+            .rev()
+            .map(|layer_depth| self.node(layer_depth, (index >> (depth - layer_depth)) ^ 1))
+            .collect()
+    }
 }
 
 /// Compute the dirty indices for one layer up.
@@ -19,6 +19,8 @@ pub enum Error {
     CannotShrink,
     /// Cache is inconsistent with the list of dirty indices provided.
     CacheInconsistent,
+    /// Requesting a Merkle proof for a node which is outside the tree is disallowed.
+    ProofIndexOutOfBounds,
 }
 
 /// Trait for types which can make use of a cache to accelerate calculation of their tree hash root.
@@ -16,6 +16,18 @@ pub struct MultiTreeHashCache {
     value_caches: Vec<TreeHashCache>,
 }
 
+impl MultiTreeHashCache {
+    /// The cache of the list of value roots.
+    pub fn list_cache(&self) -> &TreeHashCache {
+        &self.list_cache
+    }
+
+    /// The cache of the value at `index` of the list, if any.
+    pub fn value_cache(&self, index: usize) -> Option<&TreeHashCache> {
+        self.value_caches.get(index)
+    }
+}
+
 impl<T, N> CachedTreeHash<MultiTreeHashCache> for VariableList<T, N>
 where
     T: CachedTreeHash<TreeHashCache>,
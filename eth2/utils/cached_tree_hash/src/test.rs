@@ -1,6 +1,7 @@
 use crate::impls::hash256_iter;
 use crate::{CachedTreeHash, Error, Hash256, TreeHashCache};
 use eth2_hashing::ZERO_HASHES;
+use merkle_proof::verify_merkle_proof;
 use quickcheck_macros::quickcheck;
 use ssz_types::{
     typenum::{Unsigned, U16, U255, U256, U257},
@@ -64,6 +65,39 @@ fn empty_leaves() {
     );
 }
 
+#[test]
+fn merkle_proofs() {
+    let depth = 4;
+    let leaves = int_hashes(0, 11);
+    let mut cache = TreeHashCache::new(depth);
+    let root = cache
+        .recalculate_merkle_root(hash256_iter(&leaves))
+        .unwrap();
+
+    // Proofs of populated leaves, and of leaves and internal nodes within empty subtrees.
+    for node_depth in 0..=depth {
+        for index in 0..2usize.pow(node_depth as u32) {
+            let node = cache.node(node_depth, index).unwrap();
+            let proof = cache.merkle_proof(node_depth, index).unwrap();
+            assert!(verify_merkle_proof(node, &proof, node_depth, index, root));
+        }
+    }
+    assert_eq!(cache.node(depth, 0), Ok(leaves[0]));
+    assert_eq!(
+        cache.node(depth, 15).unwrap().as_bytes(),
+        &ZERO_HASHES[0][..]
+    );
+
+    assert_eq!(
+        cache.merkle_proof(depth, 16),
+        Err(Error::ProofIndexOutOfBounds)
+    );
+    assert_eq!(
+        cache.merkle_proof(depth + 1, 0),
+        Err(Error::ProofIndexOutOfBounds)
+    );
+}
+
 #[test]
 fn fixed_vector_hash256() {
     let len = 16;
@@ -0,0 +1,118 @@
+//! Command line arguments, and the parsing of them, shared by the subcommands of the `lighthouse`
+//! binary.
+
+use clap::{Arg, ArgMatches};
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// The default datadir, relative to the home directory.
+pub const DEFAULT_DATA_DIR: &str = ".lighthouse";
+/// The directory within the datadir that contains the validator directories.
+pub const VALIDATORS_DIR: &str = "validators";
+
+/// Returns the arguments of the top-level `lighthouse` command, which are shared by all of its
+/// subcommands.
+pub fn global_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("spec")
+            .short("s")
+            .long("spec")
+            .value_name("TITLE")
+            .help("Specifies the default eth2 spec type.")
+            .takes_value(true)
+            .possible_values(&["mainnet", "minimal", "interop"])
+            .global(true)
+            .default_value("minimal"),
+        Arg::with_name("testnet-dir")
+            .long("testnet-dir")
+            .value_name("DIR")
+            .help(
+                "Path to a testnet directory. The chain spec is read from its config.yaml, \
+                 which selects a preset and overrides any of its parameters. Takes \
+                 precedence over --spec.",
+            )
+            .takes_value(true)
+            .global(true),
+        Arg::with_name("logfile")
+            .long("logfile")
+            .value_name("FILE")
+            .help("File path where output will be written, in addition to the terminal.")
+            .takes_value(true)
+            .global(true),
+        Arg::with_name("logfile-max-size")
+            .long("logfile-max-size")
+            .value_name("MEGABYTES")
+            .help("The size at which the logfile is rotated. Zero disables rotation.")
+            .takes_value(true)
+            .global(true)
+            .default_value("200"),
+        Arg::with_name("logfile-max-number")
+            .long("logfile-max-number")
+            .value_name("COUNT")
+            .help("The number of rotated logfiles which are kept.")
+            .takes_value(true)
+            .global(true)
+            .default_value("5"),
+        Arg::with_name("log-level")
+            .long("log-level")
+            .alias("debug-level")
+            .value_name("LEVEL")
+            .help("The minimum level of log messages that are output.")
+            .takes_value(true)
+            .possible_values(&["info", "debug", "trace", "warn", "error", "crit"])
+            .global(true)
+            .default_value("trace"),
+        Arg::with_name("log-filter")
+            .long("log-filter")
+            .value_name("FILTER")
+            .help(
+                "Comma-separated module=level pairs which override --log-level for those \
+                 modules, e.g. \"network=debug,beacon_chain=trace\".",
+            )
+            .takes_value(true)
+            .global(true),
+        Arg::with_name("log-format")
+            .long("log-format")
+            .value_name("FORMAT")
+            .help("The format of log messages, both in the terminal and the logfile.")
+            .takes_value(true)
+            .possible_values(&["text", "json"])
+            .global(true)
+            .default_value("text"),
+        Arg::with_name("datadir")
+            .long("datadir")
+            .short("d")
+            .value_name("DIR")
+            .global(true)
+            .help("Data directory for keys and databases.")
+            .takes_value(true),
+    ]
+}
+
+/// Returns the value of `--datadir`, or `~/.lighthouse` if it was not supplied.
+pub fn parse_data_dir(matches: &ArgMatches) -> Result<PathBuf, String> {
+    matches
+        .value_of("datadir")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(DEFAULT_DATA_DIR)))
+        .ok_or_else(|| "Unable to find a home directory for the datadir".to_string())
+}
+
+/// Returns the directory that contains the validator directories, within the datadir.
+pub fn parse_validators_dir(matches: &ArgMatches) -> Result<PathBuf, String> {
+    parse_data_dir(matches).map(|data_dir| data_dir.join(VALIDATORS_DIR))
+}
+
+/// Parses the value of the `name` argument, which must be present (e.g., due to a default value).
+pub fn parse_required<T>(matches: &ArgMatches, name: &str) -> Result<T, String>
+where
+    T: FromStr,
+    T::Err: Debug,
+{
+    matches
+        .value_of(name)
+        .ok_or_else(|| format!("Expected --{} flag", name))?
+        .parse()
+        .map_err(|e| format!("Unable to parse --{}: {:?}", name, e))
+}
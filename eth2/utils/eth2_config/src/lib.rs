@@ -1,8 +1,11 @@
 use serde_derive::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::prelude::*;
-use std::path::PathBuf;
-use types::ChainSpec;
+use std::path::{Path, PathBuf};
+use types::{ChainSpec, ChainSpecFile};
+
+/// The file in a testnet directory that defines the chain spec of the testnet.
+pub const TESTNET_CONFIG_FILENAME: &str = "config.yaml";
 
 /// The core configuration of a Lighthouse beacon node.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +45,61 @@ impl Eth2Config {
             spec: ChainSpec::interop(),
         }
     }
+
+    /// Returns the config defined by a chain spec file, using its preset as the spec constants.
+    pub fn from_chain_spec_file(file: &ChainSpecFile) -> Result<Self, String> {
+        Ok(Self {
+            spec_constants: file.preset_base.clone(),
+            spec: file.chain_spec()?,
+        })
+    }
+}
+
+/// A public network, selected with `--network`, with a deposit contract known to Lighthouse.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Eth2Network {
+    pub name: &'static str,
+    /// The `0x`-prefixed address of the deposit contract on the eth1 chain.
+    pub deposit_contract_address: &'static str,
+    /// The eth1 block number at which the deposit contract was deployed.
+    pub deposit_contract_deploy_block: u64,
+}
+
+/// The networks which may be selected with `--network`.
+pub const NETWORKS: &[Eth2Network] = &[Eth2Network {
+    name: "prysm",
+    deposit_contract_address: "0x802dF6aAaCe28B2EEb1656bb18dF430dDC42cc2e",
+    deposit_contract_deploy_block: 1_487_270,
+}];
+
+impl Eth2Network {
+    /// Returns the network in `NETWORKS` named `name`.
+    pub fn from_name(name: &str) -> Result<&'static Self, String> {
+        NETWORKS
+            .iter()
+            .find(|network| network.name == name)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown network: {}. Known networks: {}",
+                    name,
+                    NETWORKS
+                        .iter()
+                        .map(|network| network.name)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+
+    /// Returns `true` if `address` is the deposit contract of this network, ignoring case.
+    pub fn is_deposit_contract(&self, address: &str) -> bool {
+        address.to_lowercase() == self.deposit_contract_address.to_lowercase()
+    }
+}
+
+/// Reads the chain spec file of a testnet directory.
+pub fn read_testnet_dir(testnet_dir: &Path) -> Result<ChainSpecFile, String> {
+    ChainSpecFile::from_file(&testnet_dir.join(TESTNET_CONFIG_FILENAME))
 }
 
 /// Write a configuration to file.
@@ -88,6 +146,15 @@ mod tests {
     use super::*;
     use toml;
 
+    #[test]
+    fn networks() {
+        let prysm = Eth2Network::from_name("prysm").expect("should know prysm");
+        assert!(prysm.is_deposit_contract("0x802df6aaace28b2eeb1656bb18df430ddc42cc2e"));
+        assert!(!prysm.is_deposit_contract("0x0000000000000000000000000000000000000000"));
+
+        assert!(Eth2Network::from_name("unknown").is_err());
+    }
+
     #[test]
     fn serde_serialize() {
         let _ =
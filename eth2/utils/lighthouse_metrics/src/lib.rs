@@ -55,7 +55,7 @@
 
 use prometheus::{HistogramOpts, HistogramTimer, Opts};
 
-pub use prometheus::{Encoder, Histogram, IntCounter, IntGauge, Result, TextEncoder};
+pub use prometheus::{Encoder, Histogram, IntCounter, IntGauge, IntGaugeVec, Result, TextEncoder};
 
 /// Collect all the metrics for reporting.
 pub fn gather() -> Vec<prometheus::proto::MetricFamily> {
@@ -80,6 +80,19 @@ pub fn try_create_int_gauge(name: &str, help: &str) -> Result<IntGauge> {
     Ok(gauge)
 }
 
+/// Attempts to crate an `IntGaugeVec`, returning `Err` if the registry does not accept the gauge
+/// (potentially due to naming conflict).
+pub fn try_create_int_gauge_vec(
+    name: &str,
+    help: &str,
+    label_names: &[&str],
+) -> Result<IntGaugeVec> {
+    let opts = Opts::new(name, help);
+    let gauge_vec = IntGaugeVec::new(opts, label_names)?;
+    prometheus::register(Box::new(gauge_vec.clone()))?;
+    Ok(gauge_vec)
+}
+
 /// Attempts to crate a `Histogram`, returning `Err` if the registry does not accept the counter
 /// (potentially due to naming conflict).
 pub fn try_create_histogram(name: &str, help: &str) -> Result<Histogram> {
@@ -123,6 +136,24 @@ pub fn set_gauge(gauge: &Result<IntGauge>, value: i64) {
     }
 }
 
+/// Increments the gauge with the given `label_values` in `gauge_vec`.
+pub fn inc_gauge_vec(gauge_vec: &Result<IntGaugeVec>, label_values: &[&str]) {
+    if let Ok(gauge_vec) = gauge_vec {
+        if let Ok(gauge) = gauge_vec.get_metric_with_label_values(label_values) {
+            gauge.inc();
+        }
+    }
+}
+
+/// Decrements the gauge with the given `label_values` in `gauge_vec`.
+pub fn dec_gauge_vec(gauge_vec: &Result<IntGaugeVec>, label_values: &[&str]) {
+    if let Ok(gauge_vec) = gauge_vec {
+        if let Ok(gauge) = gauge_vec.get_metric_with_label_values(label_values) {
+            gauge.dec();
+        }
+    }
+}
+
 /// Sets the value of a `Histogram` manually.
 pub fn observe(histogram: &Result<Histogram>, value: f64) {
     if let Ok(histogram) = histogram {
@@ -1,3 +1,11 @@
+mod module_filter;
+mod request_id;
+mod rotating_file;
+
+pub use module_filter::ModuleFilter;
+pub use request_id::{current_request_id, enter_request, RequestId, RequestIdGuard};
+pub use rotating_file::RotatingFile;
+
 use std::io::{Result, Write};
 
 pub const MAX_MESSAGE_WIDTH: usize = 40;
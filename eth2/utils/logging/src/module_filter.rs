@@ -0,0 +1,74 @@
+use slog::{Drain, Level, OwnedKVList, Record};
+
+/// A drain which filters records by level, where the level may be overridden for individual
+/// modules (and their sub-modules).
+///
+/// E.g., with a default level of `Info` and a `network` module level of `Debug`, debug logs from
+/// `network::service` are passed through whilst those from `beacon_chain` are dropped.
+pub struct ModuleFilter<D: Drain> {
+    drain: D,
+    default_level: Level,
+    /// Sorted so that the most specific (i.e., longest) module paths come first.
+    module_levels: Vec<(String, Level)>,
+}
+
+impl<D: Drain> ModuleFilter<D> {
+    pub fn new(drain: D, default_level: Level, mut module_levels: Vec<(String, Level)>) -> Self {
+        module_levels.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+
+        Self {
+            drain,
+            default_level,
+            module_levels,
+        }
+    }
+
+    /// Returns the minimum level of records from `module` which are passed through.
+    pub fn level_for(&self, module: &str) -> Level {
+        self.module_levels
+            .iter()
+            .find(|(name, _)| {
+                module == name
+                    || (module.starts_with(name.as_str()) && module[name.len()..].starts_with("::"))
+            })
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default_level)
+    }
+}
+
+impl<D: Drain> Drain for ModuleFilter<D> {
+    type Ok = Option<D::Ok>;
+    type Err = D::Err;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> Result<Self::Ok, Self::Err> {
+        if record.level().is_at_least(self.level_for(record.module())) {
+            self.drain.log(record, values).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slog::Discard;
+
+    #[test]
+    fn most_specific_module_wins() {
+        let filter = ModuleFilter::new(
+            Discard,
+            Level::Info,
+            vec![
+                ("network".to_string(), Level::Debug),
+                ("network::service".to_string(), Level::Trace),
+            ],
+        );
+
+        assert_eq!(filter.level_for("beacon_chain"), Level::Info);
+        assert_eq!(filter.level_for("network"), Level::Debug);
+        assert_eq!(filter.level_for("network::sync"), Level::Debug);
+        assert_eq!(filter.level_for("network::service"), Level::Trace);
+        assert_eq!(filter.level_for("network_foo"), Level::Info);
+    }
+}
@@ -0,0 +1,74 @@
+//! Attributes log messages to the HTTP API request being served, if any.
+//!
+//! The HTTP API assigns each request an ID and sets it as the current request ID of the thread
+//! whilst it serves the request. Loggers created with `RequestId` (e.g., those of the beacon chain
+//! and the database) then include it in their messages, so that the work done for a slow or
+//! failing request can be found in the logs.
+
+use std::cell::Cell;
+
+thread_local! {
+    static CURRENT_REQUEST_ID: Cell<Option<u64>> = Cell::new(None);
+}
+
+/// Returns the ID of the request being served by this thread, if any.
+pub fn current_request_id() -> Option<u64> {
+    CURRENT_REQUEST_ID.with(Cell::get)
+}
+
+/// Sets the current request ID of this thread to `request_id` until the returned guard is dropped.
+pub fn enter_request(request_id: u64) -> RequestIdGuard {
+    let previous = CURRENT_REQUEST_ID.with(|current| current.replace(Some(request_id)));
+    RequestIdGuard { previous }
+}
+
+/// Restores the previous request ID of the thread when dropped.
+pub struct RequestIdGuard {
+    previous: Option<u64>,
+}
+
+impl Drop for RequestIdGuard {
+    fn drop(&mut self) {
+        CURRENT_REQUEST_ID.with(|current| current.set(self.previous));
+    }
+}
+
+/// Adds a `request_id` to each message logged whilst a request is being served.
+///
+/// Use with `slog::Logger::new(slog::OwnedKV(RequestId))`.
+pub struct RequestId;
+
+impl slog::KV for RequestId {
+    fn serialize(
+        &self,
+        _record: &slog::Record,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result {
+        match current_request_id() {
+            Some(request_id) => serializer.emit_u64("request_id", request_id),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn guards_are_nested() {
+        assert_eq!(current_request_id(), None);
+
+        let outer = enter_request(1);
+        assert_eq!(current_request_id(), Some(1));
+
+        let inner = enter_request(2);
+        assert_eq!(current_request_id(), Some(2));
+
+        drop(inner);
+        assert_eq!(current_request_id(), Some(1));
+
+        drop(outer);
+        assert_eq!(current_request_id(), None);
+    }
+}
@@ -0,0 +1,143 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{Result, Write};
+use std::path::{Path, PathBuf};
+
+/// A log file which is moved aside once it exceeds a maximum size.
+///
+/// When rotated, `path` is renamed to `path.1`, any existing `path.1` is renamed to `path.2` and so
+/// on, up to `max_files` rotated files. The oldest file is deleted.
+///
+/// Files are only rotated at the end of a line, so a single log message is never split across two
+/// files.
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    /// The number of bytes in the present file.
+    size: u64,
+    /// The size in bytes at which the file is rotated. Zero disables rotation.
+    max_size: u64,
+    /// The number of rotated files which are kept.
+    max_files: usize,
+    /// True if the last byte written was a newline (or nothing has been written).
+    at_line_start: bool,
+}
+
+impl RotatingFile {
+    /// Opens (or creates) the file at `path`, appending to any existing contents.
+    pub fn open(path: PathBuf, max_size: u64, max_files: usize) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path,
+            file,
+            size,
+            max_size,
+            max_files,
+            at_line_start: true,
+        })
+    }
+
+    /// Returns the path of the present (i.e., not rotated) file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", n));
+        path.into()
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        self.file.flush()?;
+
+        if self.max_files > 0 {
+            let oldest = self.rotated_path(self.max_files);
+            if oldest.exists() {
+                fs::remove_file(oldest)?;
+            }
+
+            for n in (1..self.max_files).rev() {
+                let from = self.rotated_path(n);
+                if from.exists() {
+                    fs::rename(from, self.rotated_path(n + 1))?;
+                }
+            }
+
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?;
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if self.max_size > 0 && self.at_line_start && self.size >= self.max_size {
+            self.rotate()?;
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        if written > 0 {
+            self.at_line_start = buf[written - 1] == b'\n';
+        }
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn read(path: PathBuf) -> String {
+        fs::read_to_string(path).expect("should read file")
+    }
+
+    #[test]
+    fn rotates_at_line_end() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("beacon.log");
+        let mut file = RotatingFile::open(path.clone(), 4, 2).unwrap();
+
+        file.write_all(b"abc").unwrap();
+        file.write_all(b"def\n").unwrap();
+        file.write_all(b"ghi\n").unwrap();
+        file.write_all(b"jkl\n").unwrap();
+        file.write_all(b"mno\n").unwrap();
+        file.flush().unwrap();
+
+        assert_eq!(read(path.clone()), "mno\n");
+        assert_eq!(read(file.rotated_path(1)), "jkl\n");
+        assert_eq!(read(file.rotated_path(2)), "ghi\n");
+        assert!(!file.rotated_path(3).exists());
+    }
+
+    #[test]
+    fn no_rotation_when_disabled() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("beacon.log");
+        let mut file = RotatingFile::open(path.clone(), 0, 2).unwrap();
+
+        file.write_all(b"abc\n").unwrap();
+        file.write_all(b"def\n").unwrap();
+        file.flush().unwrap();
+
+        assert_eq!(read(path), "abc\ndef\n");
+        assert!(!file.rotated_path(1).exists());
+    }
+}
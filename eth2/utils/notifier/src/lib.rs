@@ -0,0 +1,315 @@
+//! Provides a `Notifier`, which POSTs critical events (e.g., a stall in finality or a slashed
+//! validator) as JSON to a list of webhook URLs.
+//!
+//! Intended for operators which do not run Prometheus, but still need to be alerted when their
+//! node requires attention. Delivery is best-effort: each webhook is retried a few times and then
+//! given up on, whilst repeats of the same event are rate-limited so that a persistent fault does
+//! not flood the receiver.
+
+#[macro_use]
+extern crate serde_derive;
+
+use futures::future::{self, loop_fn, Either, Loop};
+use futures::Future;
+use parking_lot::Mutex;
+use reqwest::r#async::Client;
+use slog::{debug, o, warn, Logger};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::timer::Delay;
+
+/// The minimum time between two notifications of the same event.
+pub const MIN_NOTIFICATION_INTERVAL: Duration = Duration::from_secs(300);
+/// The number of times delivery to a webhook is attempted before it is given up on.
+pub const MAX_DELIVERY_ATTEMPTS: u32 = 3;
+/// The delay before the first retry of a failed delivery. Doubled for each subsequent retry.
+pub const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(2);
+/// The timeout for a single request to a webhook.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An event which requires the attention of the operator.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// The chain has not finalized an epoch for longer than expected.
+    FinalityStall {
+        current_epoch: u64,
+        finalized_epoch: u64,
+    },
+    /// The eth1 node could not be reached, so deposits and eth1 votes will be stale.
+    Eth1EndpointDown { endpoint: String, error: String },
+    /// A validator managed by this node has been slashed, or has a slashing pending.
+    SlashingDetected {
+        pubkey: String,
+        validator_index: Option<u64>,
+        slashing_pending: bool,
+    },
+    /// The node is connected to too few peers to reliably follow the chain.
+    LowPeerCount { peer_count: usize },
+}
+
+impl Event {
+    /// Events with equal keys are rate-limited together.
+    ///
+    /// Slashings are keyed by validator, so that the slashing of one validator does not suppress
+    /// the notification of another.
+    fn rate_limit_key(&self) -> String {
+        match self {
+            Event::FinalityStall { .. } => "finality_stall".to_string(),
+            Event::Eth1EndpointDown { endpoint, .. } => format!("eth1_endpoint_down_{}", endpoint),
+            Event::SlashingDetected { pubkey, .. } => format!("slashing_detected_{}", pubkey),
+            Event::LowPeerCount { .. } => "low_peer_count".to_string(),
+        }
+    }
+}
+
+/// The JSON body POSTed to each webhook.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    /// The name of the process which raised the event (e.g., `beacon_node`).
+    pub source: String,
+    /// Seconds since the UNIX epoch at which the event was raised.
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// Suppresses repeats of an event which occur within `min_interval` of the last notification.
+#[derive(Debug)]
+struct RateLimiter {
+    last_sent: Mutex<HashMap<String, Instant>>,
+    min_interval: Duration,
+}
+
+impl RateLimiter {
+    fn new(min_interval: Duration) -> Self {
+        Self {
+            last_sent: Mutex::new(HashMap::new()),
+            min_interval,
+        }
+    }
+
+    /// Returns `true` if a notification for `key` may be sent at `now`, recording it as sent.
+    fn allow(&self, key: String, now: Instant) -> bool {
+        let mut last_sent = self.last_sent.lock();
+
+        match last_sent.get(&key) {
+            Some(last) if now.duration_since(*last) < self.min_interval => false,
+            _ => {
+                last_sent.insert(key, now);
+                true
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Inner {
+    webhooks: Vec<String>,
+    source: String,
+    rate_limiter: RateLimiter,
+    log: Logger,
+}
+
+/// A cheaply-clonable handle which POSTs `Event`s to a list of webhooks.
+///
+/// A `Notifier` without any webhooks (e.g., `Notifier::default()`) discards all events.
+#[derive(Debug, Clone)]
+pub struct Notifier {
+    inner: Arc<Inner>,
+}
+
+impl Notifier {
+    /// Instantiates a notifier which POSTs to each of the `webhooks`, identifying itself as
+    /// `source`.
+    pub fn new(webhooks: Vec<String>, source: &str, log: Logger) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                webhooks,
+                source: source.to_string(),
+                rate_limiter: RateLimiter::new(MIN_NOTIFICATION_INTERVAL),
+                log,
+            }),
+        }
+    }
+
+    /// Returns `true` if there is at least one webhook to notify.
+    pub fn is_enabled(&self) -> bool {
+        !self.inner.webhooks.is_empty()
+    }
+
+    /// Sends `event` to each webhook in the background, unless the same event was sent recently.
+    ///
+    /// Must be called from within a tokio runtime if `self.is_enabled()`.
+    pub fn notify(&self, event: Event) {
+        if !self.is_enabled()
+            || !self
+                .inner
+                .rate_limiter
+                .allow(event.rate_limit_key(), Instant::now())
+        {
+            return;
+        }
+
+        let client = match Client::builder().timeout(REQUEST_TIMEOUT).build() {
+            Ok(client) => client,
+            Err(e) => {
+                warn!(
+                    self.inner.log,
+                    "Unable to create webhook client";
+                    "error" => format!("{:?}", e)
+                );
+                return;
+            }
+        };
+
+        let notification = Notification {
+            source: self.inner.source.clone(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0),
+            event,
+        };
+
+        for webhook in &self.inner.webhooks {
+            tokio::spawn(deliver(
+                client.clone(),
+                webhook.clone(),
+                notification.clone(),
+                self.inner.log.new(o!("webhook" => webhook.clone())),
+            ));
+        }
+    }
+}
+
+impl Default for Notifier {
+    fn default() -> Self {
+        Self::new(vec![], "", Logger::root(slog::Discard, o!()))
+    }
+}
+
+/// POSTs `notification` to `webhook`, retrying with an exponential backoff upon failure.
+fn deliver(
+    client: Client,
+    webhook: String,
+    notification: Notification,
+    log: Logger,
+) -> impl Future<Item = (), Error = ()> {
+    loop_fn(0, move |attempt| {
+        let log = log.clone();
+
+        client
+            .post(&webhook)
+            .json(&notification)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .then(move |result| match result {
+                Ok(_) => {
+                    debug!(log, "Webhook notified"; "attempts" => attempt + 1);
+                    Either::A(future::ok(Loop::Break(())))
+                }
+                Err(e) if attempt + 1 < MAX_DELIVERY_ATTEMPTS => {
+                    let delay = INITIAL_RETRY_DELAY * 2_u32.pow(attempt);
+
+                    debug!(
+                        log,
+                        "Retrying webhook";
+                        "retry_millis" => delay.as_millis(),
+                        "error" => format!("{:?}", e)
+                    );
+
+                    Either::B(
+                        Delay::new(Instant::now() + delay)
+                            .map(move |_| Loop::Continue(attempt + 1))
+                            .map_err(move |e| {
+                                warn!(
+                                    log,
+                                    "Webhook retry timer failed";
+                                    "error" => format!("{:?}", e)
+                                )
+                            }),
+                    )
+                }
+                Err(e) => {
+                    warn!(
+                        log,
+                        "Unable to notify webhook";
+                        "attempts" => attempt + 1,
+                        "error" => format!("{:?}", e)
+                    );
+                    Either::A(future::ok(Loop::Break(())))
+                }
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limits_repeated_events() {
+        let limiter = RateLimiter::new(Duration::from_secs(60));
+        let now = Instant::now();
+        let stall = Event::FinalityStall {
+            current_epoch: 10,
+            finalized_epoch: 2,
+        };
+
+        assert!(limiter.allow(stall.rate_limit_key(), now));
+        assert!(!limiter.allow(stall.rate_limit_key(), now + Duration::from_secs(59)));
+        assert!(limiter.allow(stall.rate_limit_key(), now + Duration::from_secs(60)));
+
+        assert!(limiter.allow(Event::LowPeerCount { peer_count: 0 }.rate_limit_key(), now));
+    }
+
+    #[test]
+    fn rate_limits_slashings_per_validator() {
+        let limiter = RateLimiter::new(Duration::from_secs(60));
+        let now = Instant::now();
+        let slashing = |pubkey: &str| Event::SlashingDetected {
+            pubkey: pubkey.to_string(),
+            validator_index: None,
+            slashing_pending: false,
+        };
+
+        assert!(limiter.allow(slashing("0xaa").rate_limit_key(), now));
+        assert!(limiter.allow(slashing("0xbb").rate_limit_key(), now));
+        assert!(!limiter.allow(slashing("0xaa").rate_limit_key(), now));
+    }
+
+    #[test]
+    fn notification_json() {
+        let notification = Notification {
+            source: "beacon_node".to_string(),
+            timestamp: 42,
+            event: Event::LowPeerCount { peer_count: 1 },
+        };
+
+        let json = serde_json::to_value(&notification).expect("should serialize");
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "source": "beacon_node",
+                "timestamp": 42,
+                "event": "low_peer_count",
+                "peer_count": 1
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<Notification>(json).expect("should deserialize"),
+            notification
+        );
+    }
+
+    #[test]
+    fn disabled_notifier_discards_events() {
+        let notifier = Notifier::default();
+
+        assert!(!notifier.is_enabled());
+        // Would panic outside of a tokio runtime if any delivery was attempted.
+        notifier.notify(Event::LowPeerCount { peer_count: 0 });
+    }
+}
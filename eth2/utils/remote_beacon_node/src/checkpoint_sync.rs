@@ -0,0 +1,233 @@
+//! Downloads a `BeaconState` and its `BeaconBlock` from another Lighthouse node, so that a new
+//! node may begin syncing from that checkpoint instead of from genesis.
+//!
+//! The root of the state is resolved first (via `/lighthouse/debug/states/{state_id}/root`), then
+//! the state is downloaded by that root as snappy-compressed SSZ from
+//! `/lighthouse/debug/states/{state_root}/ssz`. Downloading by root ensures that a resumed
+//! download refers to the same state, even if (for example) the finalized checkpoint has changed
+//! in the meantime. The block is requested by the root recorded in the state and the pair is only
+//! returned if the block commits to the state.
+//!
+//! States are large, so a `RemoteBeaconNode` with a generous timeout (see
+//! `RemoteBeaconNode::new_with_timeout`) should be used.
+
+use super::{error_for_status, root_as_string, Error, HttpClient};
+use futures::future::{self, Either};
+use futures::{Future, IntoFuture, Stream};
+use reqwest::header::RANGE;
+use snap::read::FrameDecoder;
+use ssz::Decode;
+use std::io::Read;
+use types::{BeaconBlock, BeaconState, EthSpec, Hash256};
+
+/// The maximum number of bytes that a downloaded state may decompress to.
+///
+/// This is far larger than any state is expected to be, but prevents a malicious node from
+/// exhausting our memory with a small, highly-compressed payload.
+pub const MAX_STATE_BYTES: u64 = 1 << 30;
+
+/// A state and the block which produced it, as downloaded by `CheckpointSync::get_checkpoint`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckpointState<E: EthSpec> {
+    pub block_root: Hash256,
+    pub block: BeaconBlock<E>,
+    pub state: BeaconState<E>,
+}
+
+/// A root, obtained from a trusted source, which a downloaded checkpoint must match.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExpectedRoot {
+    /// The root of the checkpoint block.
+    Block(Hash256),
+    /// The root of the checkpoint state.
+    State(Hash256),
+}
+
+/// Provides the functions on the `/lighthouse/debug/states` endpoint of the node.
+#[derive(Clone)]
+pub struct CheckpointSync<E>(pub(crate) HttpClient<E>);
+
+impl<E: EthSpec> CheckpointSync<E> {
+    /// Returns the root of the state identified by `state_id` (e.g., `finalized`, a slot or a
+    /// `0x`-prefixed state root).
+    pub fn get_state_root(&self, state_id: &str) -> impl Future<Item = Hash256, Error = Error> {
+        let client = self.0.clone();
+        self.0
+            .url(&format!("lighthouse/debug/states/{}/root", state_id))
+            .into_future()
+            .and_then(move |url| client.json_get(url, vec![]))
+    }
+
+    /// Returns the snappy-compressed SSZ bytes of the state with the given `state_root`, starting
+    /// from the byte at `offset`.
+    ///
+    /// A non-zero `offset` allows an interrupted download to be resumed. Only roots are accepted,
+    /// so that every part of a resumed download is taken from the same state.
+    pub fn get_compressed_state_bytes(
+        &self,
+        state_root: Hash256,
+        offset: usize,
+    ) -> impl Future<Item = Vec<u8>, Error = Error> {
+        let client = self.0.clone();
+        self.0
+            .url(&format!(
+                "lighthouse/debug/states/{}/ssz",
+                root_as_string(state_root)
+            ))
+            .into_future()
+            .and_then(move |mut url| {
+                url.query_pairs_mut().append_pair("compression", "snappy");
+
+                let mut request = client.client.get(&url.to_string());
+                if offset > 0 {
+                    request = request.header(RANGE, format!("bytes={}-", offset));
+                }
+
+                request.send().map_err(Error::from)
+            })
+            .and_then(|response| error_for_status(response).map_err(Error::from))
+            .and_then(|response| response.into_body().concat2().map_err(Error::from))
+            .map(|chunk| chunk.to_vec())
+    }
+
+    /// Returns the state with the given `state_root`, verifying that it has that root.
+    pub fn get_state_by_root(
+        &self,
+        state_root: Hash256,
+    ) -> impl Future<Item = BeaconState<E>, Error = Error> {
+        self.get_compressed_state_bytes(state_root, 0)
+            .and_then(|compressed| decode_state(&compressed, MAX_STATE_BYTES))
+            .and_then(move |state: BeaconState<E>| {
+                let root = state.canonical_root();
+                if root == state_root {
+                    Ok(state)
+                } else {
+                    Err(Error::InvalidCheckpoint(format!(
+                        "State root {:?} does not match the requested root {:?}",
+                        root, state_root
+                    )))
+                }
+            })
+    }
+
+    /// Returns the state identified by `state_id`.
+    pub fn get_state(&self, state_id: &str) -> impl Future<Item = BeaconState<E>, Error = Error> {
+        let sync = self.clone();
+        self.get_state_root(state_id)
+            .and_then(move |state_root| sync.get_state_by_root(state_root))
+    }
+
+    /// Returns the state identified by `state_id` and the block which produced it, verifying that
+    /// the block commits to the state.
+    ///
+    /// If an `expected_root` is supplied, `state_id` is ignored: the checkpoint is identified by
+    /// that root instead and an error is returned unless the checkpoint matches it.
+    ///
+    /// The state must be at the slot of its block (e.g., `finalized`, but not a skipped slot).
+    pub fn get_checkpoint(
+        &self,
+        state_id: &str,
+        expected_root: Option<ExpectedRoot>,
+    ) -> impl Future<Item = CheckpointState<E>, Error = Error> {
+        let sync = self.clone();
+        let beacon = self.0.beacon();
+
+        let state_root = match expected_root {
+            Some(ExpectedRoot::State(state_root)) => Either::A(future::ok(state_root)),
+            Some(ExpectedRoot::Block(block_root)) => Either::B(Either::A(
+                beacon
+                    .get_block_by_root(block_root)
+                    .map(|(block, _)| block.state_root),
+            )),
+            None => Either::B(Either::B(self.get_state_root(state_id))),
+        };
+
+        state_root
+            .and_then(move |state_root| sync.get_state_by_root(state_root))
+            .and_then(move |state| {
+                let mut header = state.latest_block_header.clone();
+                // The state root of the latest block header is only filled in at the following
+                // slot.
+                if header.state_root == Hash256::zero() {
+                    header.state_root = state.canonical_root();
+                }
+
+                let block_root = header.canonical_root();
+
+                if let Some(ExpectedRoot::Block(expected)) = expected_root {
+                    if block_root != expected {
+                        return Either::A(future::err(Error::InvalidCheckpoint(format!(
+                            "Block root {:?} does not match the expected root {:?}",
+                            block_root, expected
+                        ))));
+                    }
+                }
+
+                Either::B(
+                    beacon
+                        .get_block_by_root(block_root)
+                        .and_then(move |(block, _)| {
+                            verify_checkpoint(&state, &block, block_root)?;
+
+                            Ok(CheckpointState {
+                                block_root,
+                                block,
+                                state,
+                            })
+                        }),
+                )
+            })
+    }
+}
+
+/// Decodes a `BeaconState` from snappy-compressed SSZ bytes, returning an error if the state
+/// decompresses to more than `max_bytes`.
+fn decode_state<E: EthSpec>(compressed: &[u8], max_bytes: u64) -> Result<BeaconState<E>, Error> {
+    let mut bytes = vec![];
+    FrameDecoder::new(compressed)
+        .take(max_bytes + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| Error::InvalidCheckpoint(format!("Unable to decompress state: {:?}", e)))?;
+
+    if bytes.len() as u64 > max_bytes {
+        return Err(Error::InvalidCheckpoint(format!(
+            "State decompresses to more than {} bytes",
+            max_bytes
+        )));
+    }
+
+    BeaconState::from_ssz_bytes(&bytes)
+        .map_err(|e| Error::InvalidCheckpoint(format!("Unable to decode state: {:?}", e)))
+}
+
+/// Returns an error unless `block` has the root `block_root` and produced `state`.
+pub fn verify_checkpoint<E: EthSpec>(
+    state: &BeaconState<E>,
+    block: &BeaconBlock<E>,
+    block_root: Hash256,
+) -> Result<(), Error> {
+    if block.canonical_root() != block_root {
+        return Err(Error::InvalidCheckpoint(format!(
+            "Block root {:?} does not match the requested root {:?}",
+            block.canonical_root(),
+            block_root
+        )));
+    }
+
+    if block.slot != state.slot {
+        return Err(Error::InvalidCheckpoint(format!(
+            "State slot {} is not the block slot {}",
+            state.slot, block.slot
+        )));
+    }
+
+    let state_root = state.canonical_root();
+    if block.state_root != state_root {
+        return Err(Error::InvalidCheckpoint(format!(
+            "Block state root {:?} does not match the state root {:?}",
+            block.state_root, state_root
+        )));
+    }
+
+    Ok(())
+}
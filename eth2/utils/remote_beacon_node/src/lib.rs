@@ -3,10 +3,13 @@
 //!
 //! Presently, this is only used for testing but it _could_ become a user-facing library.
 
+mod checkpoint_sync;
+
 use eth2_config::Eth2Config;
 use futures::{future, Future, IntoFuture};
 use reqwest::{
-    r#async::{Client, ClientBuilder, Response},
+    header::CONTENT_TYPE,
+    r#async::{Client, ClientBuilder, RequestBuilder, Response},
     StatusCode,
 };
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
@@ -14,12 +17,22 @@ use ssz::Encode;
 use std::marker::PhantomData;
 use std::time::Duration;
 use types::{
-    Attestation, BeaconBlock, BeaconState, CommitteeIndex, Epoch, EthSpec, Fork, Hash256,
-    PublicKey, Signature, Slot,
+    AggregateAndProof, Attestation, AttestationData, AttesterSlashing, BeaconBlock, BeaconState,
+    CommitteeIndex, Epoch, EthSpec, Fork, Hash256, ProposerSlashing, PublicKey, Signature, Slot,
+    ValidatorSubscription, VoluntaryExit,
 };
 use url::Url;
 
-pub use rest_api::{BulkValidatorDutiesRequest, HeadResponse, ValidatorDuty};
+pub use checkpoint_sync::{
+    verify_checkpoint, CheckpointState, CheckpointSync, ExpectedRoot, MAX_STATE_BYTES,
+};
+pub use rest_api::{
+    AttesterDutyData, BulkValidatorDutiesRequest, DutiesResponse, ErrorCode, ErrorMessage,
+    HeadResponse, NodeIdentity, PeerCount, PeerData, ProposerDutyData, StateProofData, SyncState,
+    SyncingResponse, TaskHandle, TaskStatus, ValidatorBalanceData, ValidatorData, ValidatorDuty,
+    ValidatorInclusionRequest, ValidatorInclusionResponse, ValidatorSlashingStatus,
+    ValidatorStatus,
+};
 
 // Setting a long timeout for debug ensures that crypto-heavy operations can still succeed.
 #[cfg(debug_assertions)]
@@ -46,6 +59,14 @@ impl<E: EthSpec> RemoteBeaconNode<E> {
                 .map_err(|e| format!("Unable to create http client: {:?}", e))?,
         })
     }
+
+    /// Sends `token` as a bearer token with each request, as required by a node started with
+    /// `--api-token` (for `POST` requests) or `--api-admin-token` (for the `/lighthouse`
+    /// administrative endpoints).
+    pub fn api_token(mut self, token: Option<String>) -> Self {
+        self.http.api_token = token;
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -58,6 +79,23 @@ pub enum Error {
     SerdeJsonError(serde_json::Error),
     /// The server responded to the request, however it did not return a 200-type success code.
     DidNotSucceed { status: StatusCode, body: String },
+    /// A state downloaded for checkpoint sync could not be decoded or did not match its block.
+    InvalidCheckpoint(String),
+}
+
+impl Error {
+    /// Returns the structured error from the server, if it responded with one.
+    pub fn error_message(&self) -> Option<ErrorMessage> {
+        match self {
+            Error::DidNotSucceed { body, .. } => serde_json::from_str(body).ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the machine-readable code of the error from the server, if any.
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        self.error_message().map(|message| message.code)
+    }
 }
 
 #[derive(Clone)]
@@ -65,6 +103,8 @@ pub struct HttpClient<E> {
     client: Client,
     url: Url,
     timeout: Duration,
+    /// The bearer token sent with each request, if any.
+    api_token: Option<String>,
     _phantom: PhantomData<E>,
 }
 
@@ -78,6 +118,7 @@ impl<E: EthSpec> HttpClient<E> {
                 .expect("should build from static configuration"),
             url: Url::parse(&server_url)?,
             timeout: Duration::from_secs(15),
+            api_token: None,
             _phantom: PhantomData,
         })
     }
@@ -98,18 +139,52 @@ impl<E: EthSpec> HttpClient<E> {
         Node(self.clone())
     }
 
+    pub fn checkpoint_sync(&self) -> CheckpointSync<E> {
+        CheckpointSync(self.clone())
+    }
+
+    pub fn lighthouse(&self) -> Lighthouse<E> {
+        Lighthouse(self.clone())
+    }
+
     fn url(&self, path: &str) -> Result<Url, Error> {
         self.url.join(path).map_err(|e| e.into())
     }
 
+    /// Returns a `POST` request to `url`, authorized with the API token (if any).
+    fn post(&self, url: Url) -> RequestBuilder {
+        self.authorize(self.client.post(&url.to_string()))
+    }
+
+    /// Returns a `GET` request to `url`, authorized with the API token (if any).
+    fn get(&self, url: Url) -> RequestBuilder {
+        self.authorize(self.client.get(&url.to_string()))
+    }
+
+    fn authorize(&self, builder: RequestBuilder) -> RequestBuilder {
+        match &self.api_token {
+            Some(token) => builder.bearer_auth(token),
+            None => builder,
+        }
+    }
+
     pub fn json_post<T: Serialize>(
         &self,
         url: Url,
         body: T,
     ) -> impl Future<Item = Response, Error = Error> {
-        self.client
-            .post(&url.to_string())
-            .json(&body)
+        self.post(url).json(&body).send().map_err(Error::from)
+    }
+
+    /// POSTs `body` encoded as SSZ, which is far more compact than JSON for large objects.
+    pub fn ssz_post<T: Encode>(
+        &self,
+        url: Url,
+        body: &T,
+    ) -> impl Future<Item = Response, Error = Error> {
+        self.post(url)
+            .header(CONTENT_TYPE, "application/ssz")
+            .body(body.as_ssz_bytes())
             .send()
             .map_err(Error::from)
     }
@@ -123,8 +198,7 @@ impl<E: EthSpec> HttpClient<E> {
             url.query_pairs_mut().append_pair(&key, &param);
         });
 
-        self.client
-            .get(&url.to_string())
+        self.get(url)
             .send()
             .map_err(Error::from)
             .and_then(|response| error_for_status(response).map_err(Error::from))
@@ -151,6 +225,14 @@ fn error_for_status(
     }
 }
 
+/// Returns the `message` of an `ErrorMessage` response body, or the whole `body` if it is not an
+/// `ErrorMessage`.
+fn error_description(body: String) -> String {
+    serde_json::from_str::<ErrorMessage>(&body)
+        .map(|error| error.message)
+        .unwrap_or(body)
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum PublishStatus {
     /// The object was valid and has been published to the network.
@@ -215,7 +297,50 @@ impl<E: EthSpec> Validator<E> {
             })
             .and_then(|(response, text)| match response.status() {
                 StatusCode::OK => Ok(PublishStatus::Valid),
-                StatusCode::ACCEPTED => Ok(PublishStatus::Invalid(text)),
+                StatusCode::ACCEPTED => Ok(PublishStatus::Invalid(error_description(text))),
+                _ => response
+                    .error_for_status()
+                    .map_err(Error::from)
+                    .map(|_| PublishStatus::Unknown),
+            })
+    }
+
+    /// Requests the best aggregate attestation the beacon node knows of for the given
+    /// `attestation_data`.
+    pub fn produce_aggregate_attestation(
+        &self,
+        attestation_data: &AttestationData,
+    ) -> impl Future<Item = Attestation<E>, Error = Error> {
+        let query_params = vec![(
+            "attestation_data".into(),
+            format!("0x{}", hex::encode(attestation_data.as_ssz_bytes())),
+        )];
+
+        let client = self.0.clone();
+        self.url("aggregate_attestation")
+            .into_future()
+            .and_then(move |url| client.json_get(url, query_params))
+    }
+
+    /// Posts a list of signed aggregates to the beacon node, expecting it to verify them and
+    /// publish them to the network.
+    pub fn publish_aggregate_and_proof(
+        &self,
+        aggregates: Vec<AggregateAndProof<E>>,
+    ) -> impl Future<Item = PublishStatus, Error = Error> {
+        let client = self.0.clone();
+        self.url("aggregate_and_proofs")
+            .into_future()
+            .and_then(move |url| client.json_post::<_>(url, aggregates))
+            .and_then(|mut response| {
+                response
+                    .text()
+                    .map(|text| (response, text))
+                    .map_err(Error::from)
+            })
+            .and_then(|(response, text)| match response.status() {
+                StatusCode::OK => Ok(PublishStatus::Valid),
+                StatusCode::ACCEPTED => Ok(PublishStatus::Invalid(error_description(text))),
                 _ => response
                     .error_for_status()
                     .map_err(Error::from)
@@ -223,6 +348,20 @@ impl<E: EthSpec> Validator<E> {
             })
     }
 
+    /// Requests that the beacon node subscribes to the attestation subnets required by the given
+    /// validator duties.
+    pub fn subscribe(
+        &self,
+        subscriptions: Vec<ValidatorSubscription>,
+    ) -> impl Future<Item = (), Error = Error> {
+        let client = self.0.clone();
+        self.url("beacon_committee_subscriptions")
+            .into_future()
+            .and_then(move |url| client.json_post::<_>(url, subscriptions))
+            .and_then(|response| error_for_status(response).map_err(Error::from))
+            .map(|_| ())
+    }
+
     /// Returns the duties required of the given validator pubkeys in the given epoch.
     ///
     /// ## Warning
@@ -270,7 +409,56 @@ impl<E: EthSpec> Validator<E> {
             .and_then(|mut success| success.json().map_err(Error::from))
     }
 
-    /// Posts a block to the beacon node, expecting it to verify it and publish it to the network.
+    /// Returns whether each of the given validators has been slashed, either on-chain or by a
+    /// slashing known to the beacon node but not yet included in a block.
+    pub fn get_slashings(
+        &self,
+        validator_pubkeys: &[PublicKey],
+    ) -> impl Future<Item = Vec<ValidatorSlashingStatus>, Error = Error> {
+        let client = self.0.clone();
+        let validator_pubkeys = validator_pubkeys.to_vec();
+
+        self.url("slashings")
+            .into_future()
+            .and_then(move |url| client.json_post::<_>(url, validator_pubkeys))
+            .and_then(|response| error_for_status(response).map_err(Error::from))
+            .and_then(|mut success| success.json().map_err(Error::from))
+    }
+
+    /// Returns the attestation duties of the validators with the given indices in `epoch`, along
+    /// with the root of the block upon which the duties depend.
+    pub fn get_attester_duties(
+        &self,
+        epoch: Epoch,
+        validator_indices: Vec<u64>,
+    ) -> impl Future<Item = DutiesResponse<AttesterDutyData>, Error = Error> {
+        let client = self.0.clone();
+        self.0
+            .url(&format!("eth/v1/validator/duties/attester/{}", epoch))
+            .into_future()
+            .and_then(move |url| client.json_post::<_>(url, validator_indices))
+            .and_then(|response| error_for_status(response).map_err(Error::from))
+            .and_then(|mut success| success.json().map_err(Error::from))
+    }
+
+    /// Returns the block proposer of each slot in `epoch`, along with the root of the block upon
+    /// which the proposers depend.
+    pub fn get_proposer_duties(
+        &self,
+        epoch: Epoch,
+    ) -> impl Future<Item = DutiesResponse<ProposerDutyData>, Error = Error> {
+        let client = self.0.clone();
+        self.0
+            .url(&format!("eth/v1/validator/duties/proposer/{}", epoch))
+            .into_future()
+            .and_then(move |url| client.json_get(url, vec![]))
+    }
+
+    /// Posts a block to the beacon node as SSZ, expecting it to verify it and publish it to the
+    /// network.
+    ///
+    /// The beacon node refuses to publish a block from a proposer which already has a different
+    /// block at the same slot, returning an error with the `ProposerEquivocation` code.
     pub fn publish_block(
         &self,
         block: BeaconBlock<E>,
@@ -278,7 +466,7 @@ impl<E: EthSpec> Validator<E> {
         let client = self.0.clone();
         self.url("block")
             .into_future()
-            .and_then(move |url| client.json_post::<_>(url, block))
+            .and_then(move |url| client.ssz_post(url, &block))
             .and_then(|mut response| {
                 response
                     .text()
@@ -287,7 +475,7 @@ impl<E: EthSpec> Validator<E> {
             })
             .and_then(|(response, text)| match response.status() {
                 StatusCode::OK => Ok(PublishStatus::Valid),
-                StatusCode::ACCEPTED => Ok(PublishStatus::Invalid(text)),
+                StatusCode::ACCEPTED => Ok(PublishStatus::Invalid(error_description(text))),
                 _ => response
                     .error_for_status()
                     .map_err(Error::from)
@@ -408,6 +596,114 @@ impl<E: EthSpec> Beacon<E> {
             })
             .map(|response| (response.beacon_state, response.root))
     }
+
+    /// Returns a page of validator balances from the state identified by `state_id` (e.g.,
+    /// `head`, `finalized`, a slot or a `0x`-prefixed state root).
+    pub fn get_validator_balances(
+        &self,
+        state_id: String,
+        offset: usize,
+        limit: usize,
+    ) -> impl Future<Item = Vec<ValidatorBalanceData>, Error = Error> {
+        let client = self.0.clone();
+        self.0
+            .url(&format!(
+                "eth/v1/beacon/states/{}/validator_balances",
+                state_id
+            ))
+            .into_future()
+            .and_then(move |url| {
+                client.json_get(
+                    url,
+                    vec![
+                        ("offset".into(), format!("{}", offset)),
+                        ("limit".into(), format!("{}", limit)),
+                    ],
+                )
+            })
+    }
+
+    /// Returns a page of validators from the state identified by `state_id`, optionally
+    /// filtered to those with one of the given `statuses`.
+    pub fn get_validators(
+        &self,
+        state_id: String,
+        statuses: Vec<ValidatorStatus>,
+        offset: usize,
+        limit: usize,
+    ) -> impl Future<Item = Vec<ValidatorData>, Error = Error> {
+        let client = self.0.clone();
+        let mut query_pairs = vec![
+            ("offset".into(), format!("{}", offset)),
+            ("limit".into(), format!("{}", limit)),
+        ];
+        if !statuses.is_empty() {
+            let statuses = statuses
+                .iter()
+                .filter_map(|status| serde_json::to_value(status).ok())
+                .filter_map(|value| value.as_str().map(String::from))
+                .collect::<Vec<_>>()
+                .join(",");
+            query_pairs.push(("status".into(), statuses));
+        }
+
+        self.0
+            .url(&format!("eth/v1/beacon/states/{}/validators", state_id))
+            .into_future()
+            .and_then(move |url| client.json_get(url, query_pairs))
+    }
+
+    /// Returns a Merkle proof of a node in the state identified by `state_id`, where `query_key`
+    /// is one of `field`, `validator_index` or `generalized_index`.
+    pub fn get_state_proof(
+        &self,
+        state_id: String,
+        query_key: String,
+        query_param: String,
+    ) -> impl Future<Item = StateProofData, Error = Error> {
+        let client = self.0.clone();
+        self.0
+            .url(&format!("eth/v1/beacon/states/{}/proof", state_id))
+            .into_future()
+            .and_then(move |url| client.json_get(url, vec![(query_key, query_param)]))
+    }
+
+    /// Submits a `VoluntaryExit` to the beacon node's op pool and the network.
+    pub fn publish_voluntary_exit(
+        &self,
+        exit: VoluntaryExit,
+    ) -> impl Future<Item = (), Error = Error> {
+        self.publish_operation("voluntary_exit", exit)
+    }
+
+    /// Submits a `ProposerSlashing` to the beacon node's op pool and the network.
+    pub fn publish_proposer_slashing(
+        &self,
+        slashing: ProposerSlashing,
+    ) -> impl Future<Item = (), Error = Error> {
+        self.publish_operation("proposer_slashing", slashing)
+    }
+
+    /// Submits an `AttesterSlashing` to the beacon node's op pool and the network.
+    pub fn publish_attester_slashing(
+        &self,
+        slashing: AttesterSlashing<E>,
+    ) -> impl Future<Item = (), Error = Error> {
+        self.publish_operation("attester_slashing", slashing)
+    }
+
+    fn publish_operation<T: Serialize>(
+        &self,
+        path: &str,
+        operation: T,
+    ) -> impl Future<Item = (), Error = Error> {
+        let client = self.0.clone();
+        self.url(path)
+            .into_future()
+            .and_then(move |url| client.json_post::<_>(url, operation))
+            .and_then(|response| error_for_status(response).map_err(Error::from))
+            .map(|_| ())
+    }
 }
 
 /// Provides the functions on the `/spec` endpoint of the node.
@@ -448,6 +744,106 @@ impl<E: EthSpec> Node<E> {
             .into_future()
             .and_then(move |url| client.json_get(url, vec![]))
     }
+
+    /// Returns the syncing status of the node.
+    pub fn get_syncing(&self) -> impl Future<Item = SyncingResponse, Error = Error> {
+        let client = self.0.clone();
+        self.url("syncing")
+            .into_future()
+            .and_then(move |url| client.json_get(url, vec![]))
+    }
+
+    /// Returns the identity of the node on the libp2p network.
+    pub fn get_identity(&self) -> impl Future<Item = NodeIdentity, Error = Error> {
+        let client = self.0.clone();
+        self.0
+            .url("eth/v1/node/identity")
+            .into_future()
+            .and_then(move |url| client.json_get(url, vec![]))
+    }
+
+    /// Returns information about each peer connected to the node.
+    pub fn get_peers(&self) -> impl Future<Item = Vec<PeerData>, Error = Error> {
+        let client = self.0.clone();
+        self.0
+            .url("eth/v1/node/peers")
+            .into_future()
+            .and_then(move |url| client.json_get(url, vec![]))
+    }
+
+    /// Returns the number of peers connected to the node.
+    pub fn get_peer_count(&self) -> impl Future<Item = PeerCount, Error = Error> {
+        let client = self.0.clone();
+        self.0
+            .url("eth/v1/node/peer_count")
+            .into_future()
+            .and_then(move |url| client.json_get(url, vec![]))
+    }
+}
+
+/// Provides the functions on the `/lighthouse` endpoint of the node.
+#[derive(Clone)]
+pub struct Lighthouse<E>(HttpClient<E>);
+
+impl<E: EthSpec> Lighthouse<E> {
+    fn url(&self, path: &str) -> Result<Url, Error> {
+        self.0
+            .url("lighthouse/")
+            .and_then(move |url| url.join(path).map_err(Error::from))
+            .map_err(Into::into)
+    }
+
+    /// Returns the attestation inclusion of each of the given validators during `epoch`.
+    ///
+    /// The result is only final once the head of the node has passed the end of `epoch + 1`.
+    pub fn get_validator_inclusion(
+        &self,
+        epoch: Epoch,
+        validator_pubkeys: &[PublicKey],
+    ) -> impl Future<Item = Vec<ValidatorInclusionResponse>, Error = Error> {
+        let client = self.0.clone();
+
+        let request = ValidatorInclusionRequest {
+            epoch,
+            pubkeys: validator_pubkeys.to_vec(),
+        };
+
+        self.url("validator_inclusion/validators")
+            .into_future()
+            .and_then(move |url| client.json_post::<_>(url, request))
+            .and_then(|response| error_for_status(response).map_err(Error::from))
+            .and_then(|mut success| success.json().map_err(Error::from))
+    }
+
+    /// Starts pruning the fork choice tree, operation pool and database against the finalized
+    /// checkpoint. Requires the admin token.
+    pub fn post_database_prune(&self) -> impl Future<Item = TaskHandle, Error = Error> {
+        self.post_task("database/prune")
+    }
+
+    /// Starts clearing the shuffling and state caches and rebuilding the caches of the head
+    /// state. Requires the admin token.
+    pub fn post_caches_clear(&self) -> impl Future<Item = TaskHandle, Error = Error> {
+        self.post_task("caches/clear")
+    }
+
+    /// Returns the status of a task started by an administrative endpoint. Requires the admin
+    /// token.
+    pub fn get_task(&self, task_id: u64) -> impl Future<Item = TaskStatus, Error = Error> {
+        let client = self.0.clone();
+        self.url("tasks").into_future().and_then(move |url| {
+            client.json_get(url, vec![("task_id".into(), format!("{}", task_id))])
+        })
+    }
+
+    fn post_task(&self, path: &str) -> impl Future<Item = TaskHandle, Error = Error> {
+        let client = self.0.clone();
+        self.url(path)
+            .into_future()
+            .and_then(move |url| client.json_post::<_>(url, ()))
+            .and_then(|response| error_for_status(response).map_err(Error::from))
+            .and_then(|mut success| success.json().map_err(Error::from))
+    }
 }
 
 #[derive(Deserialize)]
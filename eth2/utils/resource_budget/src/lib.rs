@@ -0,0 +1,190 @@
+//! Provides a `ResourceBudget`, a handle shared between the background services of a node (eth1
+//! polling, range sync and database migration) which determines how aggressively they may consume
+//! CPU, memory and IO.
+//!
+//! The budget is derived from an `ExecutionMode`. In `ExecutionMode::Default` the services run as
+//! they always have. In `ExecutionMode::LowPower` they poll less frequently, run less work
+//! concurrently and hold smaller caches, which suits nodes that share hardware with other
+//! workloads.
+
+#[macro_use]
+extern crate serde_derive;
+
+use parking_lot::RwLock;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// In low-power mode, the interval between eth1 node polls is multiplied by this value.
+pub const LOW_POWER_ETH1_INTERVAL_MULTIPLIER: u32 = 8;
+/// In low-power mode, concurrent sync work and cache sizes are divided by this value.
+pub const LOW_POWER_DIVISOR: usize = 4;
+/// In low-power mode, the store migrator waits this long after a finalization before migrating,
+/// so that several finalizations are handled by a single migration.
+pub const LOW_POWER_MIGRATION_DELAY: Duration = Duration::from_secs(60);
+
+/// Determines how much of the host's resources the background services of a node may use.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExecutionMode {
+    /// Use resources freely, in order to stay in sync with the least latency.
+    Default,
+    /// Throttle background services, for nodes sharing hardware with other workloads.
+    LowPower,
+}
+
+impl Default for ExecutionMode {
+    fn default() -> Self {
+        ExecutionMode::Default
+    }
+}
+
+impl FromStr for ExecutionMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(ExecutionMode::Default),
+            "low-power" => Ok(ExecutionMode::LowPower),
+            other => Err(format!(
+                "Unknown execution mode: {}. Expected \"default\" or \"low-power\"",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ExecutionMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExecutionMode::Default => write!(f, "default"),
+            ExecutionMode::LowPower => write!(f, "low-power"),
+        }
+    }
+}
+
+/// A cheaply-clonable handle to the `ExecutionMode` of a node.
+///
+/// All clones share the same mode, so a change made via `Self::set_mode` is observed by every
+/// service the next time it consults the budget.
+#[derive(Debug, Clone, Default)]
+pub struct ResourceBudget {
+    mode: Arc<RwLock<ExecutionMode>>,
+}
+
+impl ResourceBudget {
+    /// Instantiates a new budget for the given `mode`.
+    pub fn new(mode: ExecutionMode) -> Self {
+        Self {
+            mode: Arc::new(RwLock::new(mode)),
+        }
+    }
+
+    /// Returns the current execution mode.
+    pub fn mode(&self) -> ExecutionMode {
+        *self.mode.read()
+    }
+
+    /// Changes the execution mode of every service sharing this budget.
+    pub fn set_mode(&self, mode: ExecutionMode) {
+        *self.mode.write() = mode;
+    }
+
+    /// Returns `true` if background services should be throttled.
+    pub fn is_low_power(&self) -> bool {
+        self.mode() == ExecutionMode::LowPower
+    }
+
+    /// Returns the interval that should be waited between polls of the eth1 node, given the
+    /// interval that would otherwise be used.
+    pub fn eth1_update_interval(&self, default: Duration) -> Duration {
+        if self.is_low_power() {
+            default * LOW_POWER_ETH1_INTERVAL_MULTIPLIER
+        } else {
+            default
+        }
+    }
+
+    /// Returns the number of sync batches that may be downloading or awaiting processing at once,
+    /// given the number that would otherwise be used.
+    ///
+    /// Never returns less than one, so that sync always progresses.
+    pub fn sync_batch_buffer_size(&self, default: usize) -> usize {
+        self.scale_down(default)
+    }
+
+    /// Returns the size that a cache should be limited to, given the size that would otherwise be
+    /// used.
+    ///
+    /// Never returns less than one.
+    pub fn cache_size(&self, default: usize) -> usize {
+        self.scale_down(default)
+    }
+
+    /// Returns the duration that the store migrator should wait before running a migration.
+    pub fn migration_delay(&self) -> Duration {
+        if self.is_low_power() {
+            LOW_POWER_MIGRATION_DELAY
+        } else {
+            Duration::from_secs(0)
+        }
+    }
+
+    fn scale_down(&self, default: usize) -> usize {
+        if self.is_low_power() {
+            std::cmp::max(default / LOW_POWER_DIVISOR, 1)
+        } else {
+            default
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn execution_mode_round_trip() {
+        for mode in &[ExecutionMode::Default, ExecutionMode::LowPower] {
+            assert_eq!(ExecutionMode::from_str(&mode.to_string()), Ok(*mode));
+        }
+        assert!(ExecutionMode::from_str("turbo").is_err());
+    }
+
+    #[test]
+    fn default_mode_is_unthrottled() {
+        let budget = ResourceBudget::default();
+        let interval = Duration::from_millis(500);
+
+        assert_eq!(budget.eth1_update_interval(interval), interval);
+        assert_eq!(budget.sync_batch_buffer_size(5), 5);
+        assert_eq!(budget.cache_size(256), 256);
+        assert_eq!(budget.migration_delay(), Duration::from_secs(0));
+    }
+
+    #[test]
+    fn low_power_mode_is_throttled() {
+        let budget = ResourceBudget::new(ExecutionMode::LowPower);
+        let interval = Duration::from_millis(500);
+
+        assert_eq!(
+            budget.eth1_update_interval(interval),
+            Duration::from_secs(4)
+        );
+        assert_eq!(budget.sync_batch_buffer_size(5), 1);
+        assert_eq!(budget.sync_batch_buffer_size(1), 1);
+        assert_eq!(budget.cache_size(256), 64);
+        assert_eq!(budget.migration_delay(), LOW_POWER_MIGRATION_DELAY);
+    }
+
+    #[test]
+    fn clones_share_mode() {
+        let budget = ResourceBudget::default();
+        let clone = budget.clone();
+
+        budget.set_mode(ExecutionMode::LowPower);
+
+        assert!(clone.is_low_power());
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+use types::Slot;
+
+/// The number of recent peer observations that are considered.
+const MAX_OBSERVATIONS: usize = 16;
+/// The number of observations required before drift is reported.
+const MIN_OBSERVATIONS: usize = 3;
+
+/// Detects a local clock that is behind the rest of the network, which is likely to be caused by
+/// a system clock that is not synchronized (e.g., via NTP).
+///
+/// Peers cannot have blocks from slots that have not started, so the head slots that peers report
+/// should not be ahead of the local slot. If the median of the recent head slots reported by peers
+/// is more than `tolerance` slots ahead of the local slot, the local clock is considered to have
+/// drifted.
+pub struct ClockDriftCheck {
+    /// The number of slots each peer was ahead of the local clock, most recent last.
+    observations: VecDeque<i64>,
+    /// The number of slots peers may be ahead before the local clock is considered drifted.
+    tolerance: u64,
+    /// Whether drift was detected by the previous observation.
+    drifted: bool,
+}
+
+impl ClockDriftCheck {
+    pub fn new(tolerance: u64) -> Self {
+        Self {
+            observations: VecDeque::with_capacity(MAX_OBSERVATIONS),
+            tolerance,
+            drifted: false,
+        }
+    }
+
+    /// Records the head slot reported by a peer at the given local slot.
+    ///
+    /// Returns the number of slots the local clock is behind the peers when drift is first
+    /// detected. Returns `None` whilst the drift persists, so that it is only reported once.
+    pub fn observe(&mut self, local_slot: Slot, peer_head_slot: Slot) -> Option<u64> {
+        if self.observations.len() == MAX_OBSERVATIONS {
+            self.observations.pop_front();
+        }
+        self.observations
+            .push_back(peer_head_slot.as_u64() as i64 - local_slot.as_u64() as i64);
+
+        let drift = self.median_drift().filter(|drift| *drift > self.tolerance);
+        let newly_drifted = drift.is_some() && !self.drifted;
+        self.drifted = drift.is_some();
+
+        if newly_drifted {
+            drift
+        } else {
+            None
+        }
+    }
+
+    /// Returns the median number of slots that peers are ahead of the local clock, if enough
+    /// observations have been made.
+    fn median_drift(&self) -> Option<u64> {
+        if self.observations.len() < MIN_OBSERVATIONS {
+            return None;
+        }
+
+        let mut sorted: Vec<i64> = self.observations.iter().cloned().collect();
+        sorted.sort_unstable();
+        let median = sorted[sorted.len() / 2];
+
+        if median > 0 {
+            Some(median as u64)
+        } else {
+            Some(0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_drift_once() {
+        let mut check = ClockDriftCheck::new(2);
+        let local = Slot::new(100);
+
+        assert_eq!(check.observe(local, Slot::new(110)), None);
+        assert_eq!(check.observe(local, Slot::new(110)), None);
+        assert_eq!(check.observe(local, Slot::new(110)), Some(10));
+        assert_eq!(check.observe(local, Slot::new(110)), None);
+    }
+
+    #[test]
+    fn ignores_lagging_and_outlying_peers() {
+        let mut check = ClockDriftCheck::new(2);
+        let local = Slot::new(100);
+
+        assert_eq!(check.observe(local, Slot::new(50)), None);
+        assert_eq!(check.observe(local, Slot::new(1_000)), None);
+        assert_eq!(check.observe(local, Slot::new(100)), None);
+        assert_eq!(check.observe(local, Slot::new(102)), None);
+    }
+
+    #[test]
+    fn reports_recurring_drift() {
+        let mut check = ClockDriftCheck::new(0);
+
+        for _ in 0..MIN_OBSERVATIONS - 1 {
+            check.observe(Slot::new(10), Slot::new(12));
+        }
+        assert_eq!(check.observe(Slot::new(10), Slot::new(12)), Some(2));
+
+        // the clock is corrected
+        for _ in 0..MAX_OBSERVATIONS {
+            assert_eq!(check.observe(Slot::new(20), Slot::new(20)), None);
+        }
+
+        for _ in 0..MAX_OBSERVATIONS / 2 - 1 {
+            check.observe(Slot::new(20), Slot::new(25));
+        }
+        assert_eq!(check.observe(Slot::new(20), Slot::new(25)), Some(5));
+    }
+}
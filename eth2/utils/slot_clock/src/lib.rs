@@ -1,12 +1,17 @@
 #[macro_use]
 extern crate lazy_static;
 
+mod clock_drift;
+mod manual_slot_clock;
 mod metrics;
 mod system_time_slot_clock;
 mod testing_slot_clock;
 
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tokio_timer::Interval;
 
+pub use crate::clock_drift::ClockDriftCheck;
+pub use crate::manual_slot_clock::ManualSlotClock;
 pub use crate::system_time_slot_clock::SystemTimeSlotClock;
 pub use crate::testing_slot_clock::TestingSlotClock;
 pub use metrics::scrape_for_metrics;
@@ -31,4 +36,29 @@ pub trait SlotClock: Send + Sync + Sized {
 
     /// Returns the duration until the first slot of the next epoch.
     fn duration_to_next_epoch(&self, slots_per_epoch: u64) -> Option<Duration>;
+
+    /// Returns the duration since the start of the current slot.
+    ///
+    /// Returns `None` prior to genesis.
+    fn duration_since_slot_start(&self) -> Option<Duration> {
+        self.slot_duration()
+            .checked_sub(self.duration_to_next_slot()?)
+    }
+
+    /// Returns a stream that yields `offset` after the start of each slot, beginning with the next
+    /// slot (or genesis, if it has not yet occurred).
+    ///
+    /// Returns `None` if the duration to the next slot is unknown or the slot duration is zero.
+    fn slot_interval(&self, offset: Duration) -> Option<Interval> {
+        let slot_duration = self.slot_duration();
+        if slot_duration.as_millis() == 0 {
+            return None;
+        }
+
+        let duration_to_next_slot = self.duration_to_next_slot()?;
+        Some(Interval::new(
+            Instant::now() + duration_to_next_slot + offset,
+            slot_duration,
+        ))
+    }
 }
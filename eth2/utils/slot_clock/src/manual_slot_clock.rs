@@ -0,0 +1,204 @@
+use super::SlotClock;
+use std::sync::RwLock;
+use std::time::Duration;
+use types::Slot;
+
+/// Determines the present slot based upon a manually set time, rather than the system time.
+///
+/// Unlike the `TestingSlotClock`, the slot durations and genesis time are respected, so it is
+/// useful for testing time-dependent behaviour (e.g., the duration to the next slot).
+pub struct ManualSlotClock {
+    genesis_slot: Slot,
+    /// Duration from UNIX epoch to genesis.
+    genesis_duration: Duration,
+    /// Duration from UNIX epoch to right now.
+    current_time: RwLock<Duration>,
+    slot_duration: Duration,
+}
+
+impl Clone for ManualSlotClock {
+    fn clone(&self) -> Self {
+        ManualSlotClock {
+            genesis_slot: self.genesis_slot,
+            genesis_duration: self.genesis_duration,
+            current_time: RwLock::new(self.current_time()),
+            slot_duration: self.slot_duration,
+        }
+    }
+}
+
+impl ManualSlotClock {
+    /// Sets the present time, as a duration since the UNIX epoch.
+    pub fn set_current_time(&self, duration: Duration) {
+        *self
+            .current_time
+            .write()
+            .expect("ManualSlotClock poisoned.") = duration;
+    }
+
+    /// Moves the present time forward by `duration`.
+    pub fn advance_time(&self, duration: Duration) {
+        self.set_current_time(self.current_time() + duration)
+    }
+
+    /// Sets the present time to the start of `slot`.
+    ///
+    /// Panics if `slot` is prior to the genesis slot.
+    pub fn set_slot(&self, slot: u64) {
+        let slots_since_genesis = slot
+            .checked_sub(self.genesis_slot.as_u64())
+            .expect("slot must not be prior to genesis") as u32;
+        self.set_current_time(self.genesis_duration + self.slot_duration * slots_since_genesis)
+    }
+
+    /// Moves the present time to the start of the next slot.
+    pub fn advance_slot(&self) {
+        self.set_slot(self.now().unwrap_or(self.genesis_slot).as_u64() + 1)
+    }
+
+    /// Returns the present time, as a duration since the UNIX epoch.
+    pub fn current_time(&self) -> Duration {
+        *self.current_time.read().expect("ManualSlotClock poisoned.")
+    }
+
+    /// Returns the slot at `now`, a duration since the UNIX epoch.
+    pub fn slot_of(&self, now: Duration) -> Option<Slot> {
+        let since_genesis = now.checked_sub(self.genesis_duration)?;
+        let slot = Slot::from((since_genesis.as_millis() / self.slot_duration.as_millis()) as u64);
+        Some(slot + self.genesis_slot)
+    }
+
+    /// Returns the duration from `now` until the start of the slot after the one at `now`, or
+    /// until genesis if `now` is prior to genesis.
+    pub fn duration_to_next_slot_from(&self, now: Duration) -> Option<Duration> {
+        match self.slot_of(now) {
+            Some(slot) => self.start_of(slot + 1)?.checked_sub(now),
+            None => self.genesis_duration.checked_sub(now),
+        }
+    }
+
+    /// Returns the duration from `now` until the start of the epoch after the one at `now`, or
+    /// until genesis if `now` is prior to genesis.
+    pub fn duration_to_next_epoch_from(
+        &self,
+        now: Duration,
+        slots_per_epoch: u64,
+    ) -> Option<Duration> {
+        match self.slot_of(now) {
+            Some(slot) => {
+                let next_epoch = slot.epoch(slots_per_epoch) + 1;
+                self.start_of(next_epoch.start_slot(slots_per_epoch))?
+                    .checked_sub(now)
+            }
+            None => self.genesis_duration.checked_sub(now),
+        }
+    }
+
+    /// Returns the time at which `slot` starts, as a duration since the UNIX epoch.
+    fn start_of(&self, slot: Slot) -> Option<Duration> {
+        let slots_since_genesis = slot.as_u64().checked_sub(self.genesis_slot.as_u64())? as u32;
+        self.slot_duration
+            .checked_mul(slots_since_genesis)
+            .and_then(|duration| self.genesis_duration.checked_add(duration))
+    }
+}
+
+impl SlotClock for ManualSlotClock {
+    /// The present time is set to genesis.
+    fn new(genesis_slot: Slot, genesis_duration: Duration, slot_duration: Duration) -> Self {
+        if slot_duration.as_millis() == 0 {
+            panic!("ManualSlotClock cannot have a < 1ms slot duration.");
+        }
+
+        Self {
+            genesis_slot,
+            genesis_duration,
+            current_time: RwLock::new(genesis_duration),
+            slot_duration,
+        }
+    }
+
+    fn now(&self) -> Option<Slot> {
+        self.slot_of(self.current_time())
+    }
+
+    fn duration_to_next_slot(&self) -> Option<Duration> {
+        self.duration_to_next_slot_from(self.current_time())
+    }
+
+    fn duration_to_next_epoch(&self, slots_per_epoch: u64) -> Option<Duration> {
+        self.duration_to_next_epoch_from(self.current_time(), slots_per_epoch)
+    }
+
+    fn slot_duration(&self) -> Duration {
+        self.slot_duration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slot_now() {
+        let clock = ManualSlotClock::new(
+            Slot::new(10),
+            Duration::from_secs(100),
+            Duration::from_secs(6),
+        );
+        assert_eq!(clock.now(), Some(Slot::new(10)));
+
+        clock.set_current_time(Duration::from_secs(99));
+        assert_eq!(clock.now(), None);
+        assert_eq!(clock.duration_to_next_slot(), Some(Duration::from_secs(1)));
+
+        clock.set_current_time(Duration::from_secs(113));
+        assert_eq!(clock.now(), Some(Slot::new(12)));
+        assert_eq!(clock.duration_to_next_slot(), Some(Duration::from_secs(5)));
+
+        clock.advance_slot();
+        assert_eq!(clock.now(), Some(Slot::new(13)));
+        assert_eq!(clock.duration_to_next_slot(), Some(Duration::from_secs(6)));
+
+        clock.set_slot(20);
+        clock.advance_time(Duration::from_millis(5_999));
+        assert_eq!(clock.now(), Some(Slot::new(20)));
+        assert_eq!(
+            clock.duration_to_next_slot(),
+            Some(Duration::from_millis(1))
+        );
+    }
+
+    #[test]
+    fn duration_to_next_epoch() {
+        let clock =
+            ManualSlotClock::new(Slot::new(0), Duration::from_secs(0), Duration::from_secs(1));
+
+        assert_eq!(
+            clock.duration_to_next_epoch(8),
+            Some(Duration::from_secs(8))
+        );
+
+        clock.set_current_time(Duration::from_millis(8_500));
+        assert_eq!(
+            clock.duration_to_next_epoch(8),
+            Some(Duration::from_millis(7_500))
+        );
+
+        clock.set_slot(15);
+        assert_eq!(
+            clock.duration_to_next_epoch(8),
+            Some(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_millis() {
+        ManualSlotClock::new(
+            Slot::new(0),
+            Duration::from_secs(0),
+            Duration::from_millis(0),
+        );
+    }
+}
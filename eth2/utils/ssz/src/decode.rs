@@ -216,6 +216,18 @@ impl<'a> SszDecoder<'a> {
     pub fn decode_next<T: Decode>(&mut self) -> Result<T, DecodeError> {
         T::from_ssz_bytes(self.items.remove(0))
     }
+
+    /// Skips the next item without decoding it.
+    ///
+    /// The item was still checked to be well-positioned when the decoder was built, however its
+    /// contents are not validated.
+    ///
+    /// # Panics
+    ///
+    /// Panics when attempting to skip more items than actually exist.
+    pub fn skip_next(&mut self) {
+        self.items.remove(0);
+    }
 }
 
 /// Reads a `BYTES_PER_LENGTH_OFFSET`-byte union index from `bytes`, where `bytes.len() >=
@@ -5,7 +5,7 @@ use ssz_derive::{Decode, Encode};
 mod round_trip {
     use super::*;
 
-    fn round_trip<T: Encode + Decode + std::fmt::Debug + PartialEq>(items: Vec<T>) {
+    pub fn round_trip<T: Encode + Decode + std::fmt::Debug + PartialEq>(items: Vec<T>) {
         for item in items {
             let encoded = &item.as_ssz_bytes();
             assert_eq!(item.ssz_bytes_len(), encoded.len());
@@ -378,3 +378,211 @@ mod round_trip {
         round_trip(vec);
     }
 }
+
+mod union {
+    use super::*;
+    use quickcheck_macros::quickcheck;
+
+    #[derive(Debug, PartialEq, Encode, Decode)]
+    #[ssz(union)]
+    enum NoneU16OrVec {
+        None,
+        U16(u16),
+        Vec(Vec<u16>),
+    }
+
+    #[derive(Debug, PartialEq, Encode, Decode)]
+    #[ssz(union)]
+    enum U8OrU16 {
+        U8(u8),
+        U16(u16),
+    }
+
+    #[test]
+    fn encoding() {
+        assert_eq!(NoneU16OrVec::None.as_ssz_bytes(), vec![0, 0, 0, 0]);
+        assert_eq!(
+            NoneU16OrVec::U16(258).as_ssz_bytes(),
+            vec![1, 0, 0, 0, 2, 1]
+        );
+        assert_eq!(
+            NoneU16OrVec::Vec(vec![1, 2]).as_ssz_bytes(),
+            vec![2, 0, 0, 0, 1, 0, 2, 0]
+        );
+        assert_eq!(U8OrU16::U8(42).as_ssz_bytes(), vec![0, 0, 0, 0, 42]);
+    }
+
+    #[test]
+    fn round_trip() {
+        super::round_trip::round_trip(vec![
+            NoneU16OrVec::None,
+            NoneU16OrVec::U16(0),
+            NoneU16OrVec::U16(u16::max_value()),
+            NoneU16OrVec::Vec(vec![]),
+            NoneU16OrVec::Vec(vec![1, 2, 3]),
+        ]);
+        super::round_trip::round_trip(vec![U8OrU16::U8(1), U8OrU16::U16(1)]);
+    }
+
+    #[test]
+    fn invalid_selector() {
+        assert!(NoneU16OrVec::from_ssz_bytes(&[3, 0, 0, 0]).is_err());
+        assert!(U8OrU16::from_ssz_bytes(&[2, 0, 0, 0, 1]).is_err());
+    }
+
+    #[test]
+    fn none_with_value() {
+        assert!(NoneU16OrVec::from_ssz_bytes(&[0, 0, 0, 0, 1]).is_err());
+    }
+
+    #[test]
+    fn short_selector() {
+        assert_eq!(
+            NoneU16OrVec::from_ssz_bytes(&[0, 0, 0]),
+            Err(DecodeError::InvalidByteLength {
+                len: 3,
+                expected: 4
+            })
+        );
+    }
+
+    #[quickcheck]
+    fn round_trip_arbitrary(selector: u8, a: u16, b: Vec<u16>) -> bool {
+        let item = match selector % 3 {
+            0 => NoneU16OrVec::None,
+            1 => NoneU16OrVec::U16(a),
+            _ => NoneU16OrVec::Vec(b),
+        };
+        let bytes = item.as_ssz_bytes();
+        bytes.len() == item.ssz_bytes_len() && NoneU16OrVec::from_ssz_bytes(&bytes) == Ok(item)
+    }
+
+    #[quickcheck]
+    fn decode_arbitrary_bytes(bytes: Vec<u8>) -> bool {
+        // any bytes that decode must be the canonical encoding of the value
+        match NoneU16OrVec::from_ssz_bytes(&bytes) {
+            Ok(item) => item.as_ssz_bytes() == bytes,
+            Err(_) => true,
+        }
+    }
+}
+
+mod stable_container {
+    use super::*;
+    use quickcheck_macros::quickcheck;
+
+    #[derive(Debug, PartialEq, Encode, Decode)]
+    #[ssz(stable_container = 4)]
+    struct Shape {
+        side: Option<u16>,
+        color: Option<u8>,
+        points: Option<Vec<u16>>,
+    }
+
+    #[derive(Debug, PartialEq, Encode, Decode)]
+    #[ssz(stable_container = 10)]
+    struct Wide {
+        a: Option<u8>,
+        b: Option<u8>,
+        c: Option<u8>,
+        d: Option<u8>,
+        e: Option<u8>,
+        f: Option<u8>,
+        g: Option<u8>,
+        h: Option<u8>,
+        i: Option<Vec<u8>>,
+    }
+
+    #[test]
+    fn encoding() {
+        let shape = Shape {
+            side: Some(0x42),
+            color: None,
+            points: Some(vec![1]),
+        };
+
+        let bytes = vec![
+            // bitvector (0b101) | side | offset | points
+            5, 0x42, 0, 6, 0, 0, 0, 1, 0,
+        ];
+
+        assert_eq!(shape.as_ssz_bytes(), bytes);
+        assert_eq!(Shape::from_ssz_bytes(&bytes), Ok(shape));
+
+        let empty = Shape {
+            side: None,
+            color: None,
+            points: None,
+        };
+        assert_eq!(empty.as_ssz_bytes(), vec![0]);
+    }
+
+    #[test]
+    fn multi_byte_bitvector() {
+        let wide = Wide {
+            a: Some(1),
+            b: None,
+            c: None,
+            d: None,
+            e: None,
+            f: None,
+            g: None,
+            h: Some(8),
+            i: Some(vec![9]),
+        };
+
+        let bytes = vec![0b1000_0001, 0b0000_0001, 1, 8, 6, 0, 0, 0, 9];
+
+        assert_eq!(wide.as_ssz_bytes(), bytes);
+        assert_eq!(Wide::from_ssz_bytes(&bytes), Ok(wide));
+    }
+
+    #[test]
+    fn unknown_fields() {
+        // the 4th field is within the capacity, but unknown
+        assert!(Shape::from_ssz_bytes(&[0b0000_1000]).is_err());
+        // the 10th field is beyond the fields of the type
+        assert!(Wide::from_ssz_bytes(&[0, 0b0000_0010]).is_err());
+    }
+
+    #[test]
+    fn excess_bytes() {
+        assert!(Shape::from_ssz_bytes(&[0b0000_0001, 0x42, 0, 0]).is_err());
+        assert!(Shape::from_ssz_bytes(&[0, 1]).is_err());
+    }
+
+    #[test]
+    fn missing_bitvector() {
+        assert_eq!(
+            Wide::from_ssz_bytes(&[0]),
+            Err(DecodeError::InvalidByteLength {
+                len: 1,
+                expected: 2
+            })
+        );
+    }
+
+    #[quickcheck]
+    fn round_trip_arbitrary(
+        side: Option<u16>,
+        color: Option<u8>,
+        points: Option<Vec<u16>>,
+    ) -> bool {
+        let shape = Shape {
+            side,
+            color,
+            points,
+        };
+        let bytes = shape.as_ssz_bytes();
+        bytes.len() == shape.ssz_bytes_len() && Shape::from_ssz_bytes(&bytes) == Ok(shape)
+    }
+
+    #[quickcheck]
+    fn decode_arbitrary_bytes(bytes: Vec<u8>) -> bool {
+        // any bytes that decode must be the canonical encoding of the value
+        match Shape::from_ssz_bytes(&bytes) {
+            Ok(shape) => shape.as_ssz_bytes() == bytes,
+            Err(_) => true,
+        }
+    }
+}
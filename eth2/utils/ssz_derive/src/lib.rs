@@ -2,6 +2,15 @@
 //! Provides procedural derive macros for the `Encode` and `Decode` traits of the `eth2_ssz` crate.
 //!
 //! Supports field attributes, see each derive macro for more information.
+//!
+//! ## Item attributes
+//!
+//! - `#[ssz(union)]`: an `enum` is encoded as an SSZ union. Each variant must have a single
+//! unnamed field, except the first variant which may be a unit variant (i.e., the `None` type).
+//! The value is prefixed with the `BYTES_PER_LENGTH_OFFSET`-byte index of its variant.
+//! - `#[ssz(stable_container = N)]`: a `struct` whose fields are all `Option`s is encoded as a
+//! stable container with a capacity of `N` fields. The value is prefixed with a bitvector of `N`
+//! bits declaring which fields are present, followed by the present fields encoded as a container.
 
 extern crate proc_macro;
 
@@ -58,7 +67,128 @@ fn should_skip_serializing(field: &syn::Field) -> bool {
     })
 }
 
-/// Implements `ssz::Encode` for some `struct`.
+/// Returns the `#[ssz(..)]` attributes in `attrs`, with whitespace removed (e.g., `"(union)"`).
+fn ssz_attributes(attrs: &[syn::Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("ssz"))
+        .map(|attr| attr.tts.to_string().replace(" ", ""))
+        .collect()
+}
+
+/// Returns true if the item has the `#[ssz(union)]` attribute.
+fn is_union(item: &DeriveInput) -> bool {
+    ssz_attributes(&item.attrs)
+        .iter()
+        .any(|attr| attr == "(union)")
+}
+
+/// Returns `N` if the item has the `#[ssz(stable_container = N)]` attribute.
+///
+/// # Panics
+/// A capacity that is not an integer will raise a panic at compile time.
+fn stable_container_capacity(item: &DeriveInput) -> Option<usize> {
+    let prefix = "(stable_container=";
+
+    ssz_attributes(&item.attrs)
+        .iter()
+        .find(|attr| attr.starts_with(prefix) && attr.ends_with(')'))
+        .map(|attr| {
+            attr[prefix.len()..attr.len() - 1]
+                .parse()
+                .expect("ssz_derive stable_container capacity must be an integer.")
+        })
+}
+
+/// A field of a stable container, which must be an `Option`.
+struct StableContainerField<'a> {
+    ident: &'a syn::Ident,
+    /// The type wrapped by the `Option`.
+    ty: &'a syn::Type,
+    /// The index of the byte of the bitvector that declares the presence of the field.
+    byte: usize,
+    /// The mask of the bit in `byte` that declares the presence of the field.
+    mask: u8,
+}
+
+/// Returns the fields of a stable container with a capacity of `capacity` fields.
+///
+/// # Panics
+/// Fields which are unnamed, are not an `Option`, have an `ssz` attribute or exceed the capacity
+/// will raise a panic at compile time.
+fn get_stable_container_fields<'a>(
+    struct_data: &'a syn::DataStruct,
+    capacity: usize,
+) -> Vec<StableContainerField<'a>> {
+    if struct_data.fields.iter().count() > capacity {
+        panic!("ssz_derive stable_container has more fields than its capacity.");
+    }
+
+    struct_data
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            if !ssz_attributes(&field.attrs).is_empty() {
+                panic!("ssz_derive stable_container fields do not support attributes.");
+            }
+
+            StableContainerField {
+                ident: match &field.ident {
+                    Some(ref ident) => ident,
+                    _ => panic!("ssz_derive only supports named struct fields."),
+                },
+                ty: option_inner_type(&field.ty)
+                    .expect("ssz_derive stable_container fields must be an Option."),
+                byte: i / 8,
+                mask: 1 << (i % 8),
+            }
+        })
+        .collect()
+}
+
+/// Returns `T` if `ty` is `Option<T>`.
+fn option_inner_type(ty: &syn::Type) -> Option<&syn::Type> {
+    let segment = match ty {
+        syn::Type::Path(path) => path.path.segments.iter().last()?,
+        _ => return None,
+    };
+
+    if segment.ident != "Option" {
+        return None;
+    }
+
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => match args.args.iter().next()? {
+            syn::GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the `(variant_ident, has_value)` of each variant of a union.
+///
+/// # Panics
+/// A variant with named fields, more than one unnamed field or a unit variant that is not the
+/// first variant will raise a panic at compile time.
+fn get_union_variants<'a>(enum_data: &'a syn::DataEnum) -> Vec<(&'a syn::Ident, bool)> {
+    enum_data
+        .variants
+        .iter()
+        .enumerate()
+        .map(|(i, variant)| match &variant.fields {
+            syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1 => (&variant.ident, true),
+            syn::Fields::Unit if i == 0 => (&variant.ident, false),
+            _ => panic!(
+                "ssz_derive union variants must have a single unnamed field, only the first \
+                 variant may be a unit variant."
+            ),
+        })
+        .collect()
+}
+
+/// Implements `ssz::Encode` for some `struct`, or some `enum` with the `#[ssz(union)]` attribute.
 ///
 /// Fields are encoded in the order they are defined.
 ///
@@ -69,14 +199,21 @@ fn should_skip_serializing(field: &syn::Field) -> bool {
 pub fn ssz_encode_derive(input: TokenStream) -> TokenStream {
     let item = parse_macro_input!(input as DeriveInput);
 
+    match &item.data {
+        syn::Data::Struct(s) => match stable_container_capacity(&item) {
+            Some(capacity) => ssz_encode_derive_stable_container(&item, s, capacity),
+            None => ssz_encode_derive_struct(&item, s),
+        },
+        syn::Data::Enum(e) if is_union(&item) => ssz_encode_derive_union(&item, e),
+        _ => panic!("ssz_derive only supports structs and enums with #[ssz(union)]."),
+    }
+}
+
+/// Implements `ssz::Encode` for a `struct` encoded as an SSZ container.
+fn ssz_encode_derive_struct(item: &DeriveInput, struct_data: &syn::DataStruct) -> TokenStream {
     let name = &item.ident;
     let (impl_generics, ty_generics, where_clause) = &item.generics.split_for_impl();
 
-    let struct_data = match &item.data {
-        syn::Data::Struct(s) => s,
-        _ => panic!("ssz_derive only supports structs."),
-    };
-
     let field_idents = get_serializable_named_field_idents(&struct_data);
     let field_idents_a = get_serializable_named_field_idents(&struct_data);
     let field_types_a = get_serializable_field_types(&struct_data);
@@ -142,6 +279,145 @@ pub fn ssz_encode_derive(input: TokenStream) -> TokenStream {
     output.into()
 }
 
+/// Implements `ssz::Encode` for a `struct` encoded as an SSZ stable container.
+fn ssz_encode_derive_stable_container(
+    item: &DeriveInput,
+    struct_data: &syn::DataStruct,
+    capacity: usize,
+) -> TokenStream {
+    let name = &item.ident;
+    let (impl_generics, ty_generics, where_clause) = &item.generics.split_for_impl();
+
+    let bitvector_len = (capacity + 7) / 8;
+
+    let mut bytes_lens = vec![];
+    let mut set_bits = vec![];
+    let mut appends = vec![];
+
+    for field in get_stable_container_fields(struct_data, capacity) {
+        let ident = field.ident;
+        let ty = field.ty;
+        let byte = field.byte;
+        let mask = field.mask;
+
+        bytes_lens.push(quote! {
+            if let Some(ref value) = self.#ident {
+                if <#ty as ssz::Encode>::is_ssz_fixed_len() {
+                    len += <#ty as ssz::Encode>::ssz_fixed_len();
+                } else {
+                    len += ssz::BYTES_PER_LENGTH_OFFSET;
+                    len += ssz::Encode::ssz_bytes_len(value);
+                }
+            }
+        });
+
+        set_bits.push(quote! {
+            if self.#ident.is_some() {
+                bitvector[#byte] |= #mask;
+                offset += <#ty as ssz::Encode>::ssz_fixed_len();
+            }
+        });
+
+        appends.push(quote! {
+            if let Some(ref value) = self.#ident {
+                encoder.append(value);
+            }
+        });
+    }
+
+    let output = quote! {
+        impl #impl_generics ssz::Encode for #name #ty_generics #where_clause {
+            fn is_ssz_fixed_len() -> bool {
+                false
+            }
+
+            fn ssz_bytes_len(&self) -> usize {
+                let mut len = #bitvector_len;
+                #(
+                    #bytes_lens
+                )*
+                len
+            }
+
+            fn ssz_append(&self, buf: &mut Vec<u8>) {
+                let mut bitvector = [0_u8; #bitvector_len];
+                let mut offset = 0;
+                #(
+                    #set_bits
+                )*
+
+                buf.extend_from_slice(&bitvector);
+
+                let mut encoder = ssz::SszEncoder::container(buf, offset);
+
+                #(
+                    #appends
+                )*
+
+                encoder.finalize();
+            }
+        }
+    };
+    output.into()
+}
+
+/// Implements `ssz::Encode` for an `enum` encoded as an SSZ union.
+fn ssz_encode_derive_union(item: &DeriveInput, enum_data: &syn::DataEnum) -> TokenStream {
+    let name = &item.ident;
+    let (impl_generics, ty_generics, where_clause) = &item.generics.split_for_impl();
+
+    let mut bytes_lens = vec![];
+    let mut appends = vec![];
+
+    for (index, (variant, has_value)) in get_union_variants(enum_data).into_iter().enumerate() {
+        if has_value {
+            bytes_lens.push(quote! {
+                #name::#variant(ref inner) => {
+                    ssz::BYTES_PER_LENGTH_OFFSET + ssz::Encode::ssz_bytes_len(inner)
+                }
+            });
+            appends.push(quote! {
+                #name::#variant(ref inner) => {
+                    buf.append(&mut ssz::encode_union_index(#index));
+                    ssz::Encode::ssz_append(inner, buf);
+                }
+            });
+        } else {
+            bytes_lens.push(quote! {
+                #name::#variant => ssz::BYTES_PER_LENGTH_OFFSET
+            });
+            appends.push(quote! {
+                #name::#variant => buf.append(&mut ssz::encode_union_index(#index))
+            });
+        }
+    }
+
+    let output = quote! {
+        impl #impl_generics ssz::Encode for #name #ty_generics #where_clause {
+            fn is_ssz_fixed_len() -> bool {
+                false
+            }
+
+            fn ssz_bytes_len(&self) -> usize {
+                match self {
+                    #(
+                        #bytes_lens,
+                    )*
+                }
+            }
+
+            fn ssz_append(&self, buf: &mut Vec<u8>) {
+                match self {
+                    #(
+                        #appends,
+                    )*
+                }
+            }
+        }
+    };
+    output.into()
+}
+
 /// Returns true if some field has an attribute declaring it should not be deserialized.
 ///
 /// The field attribute is: `#[ssz(skip_deserializing)]`
@@ -151,7 +427,7 @@ fn should_skip_deserializing(field: &syn::Field) -> bool {
     })
 }
 
-/// Implements `ssz::Decode` for some `struct`.
+/// Implements `ssz::Decode` for some `struct`, or some `enum` with the `#[ssz(union)]` attribute.
 ///
 /// Fields are decoded in the order they are defined.
 ///
@@ -160,18 +436,25 @@ fn should_skip_deserializing(field: &syn::Field) -> bool {
 /// - `#[ssz(skip_deserializing)]`: during de-serialization the field will be instantiated from a
 /// `Default` implementation. The decoder will assume that the field was not serialized at all
 /// (e.g., if it has been serialized, an error will be raised instead of `Default` overriding it).
-#[proc_macro_derive(Decode)]
+#[proc_macro_derive(Decode, attributes(ssz))]
 pub fn ssz_decode_derive(input: TokenStream) -> TokenStream {
     let item = parse_macro_input!(input as DeriveInput);
 
+    match &item.data {
+        syn::Data::Struct(s) => match stable_container_capacity(&item) {
+            Some(capacity) => ssz_decode_derive_stable_container(&item, s, capacity),
+            None => ssz_decode_derive_struct(&item, s),
+        },
+        syn::Data::Enum(e) if is_union(&item) => ssz_decode_derive_union(&item, e),
+        _ => panic!("ssz_derive only supports structs and enums with #[ssz(union)]."),
+    }
+}
+
+/// Implements `ssz::Decode` for a `struct` encoded as an SSZ container.
+fn ssz_decode_derive_struct(item: &DeriveInput, struct_data: &syn::DataStruct) -> TokenStream {
     let name = &item.ident;
     let (impl_generics, ty_generics, where_clause) = &item.generics.split_for_impl();
 
-    let struct_data = match &item.data {
-        syn::Data::Struct(s) => s,
-        _ => panic!("ssz_derive only supports structs."),
-    };
-
     let mut register_types = vec![];
     let mut decodes = vec![];
     let mut is_fixed_lens = vec![];
@@ -250,3 +533,145 @@ pub fn ssz_decode_derive(input: TokenStream) -> TokenStream {
     };
     output.into()
 }
+
+/// Implements `ssz::Decode` for a `struct` encoded as an SSZ stable container.
+fn ssz_decode_derive_stable_container(
+    item: &DeriveInput,
+    struct_data: &syn::DataStruct,
+    capacity: usize,
+) -> TokenStream {
+    let name = &item.ident;
+    let (impl_generics, ty_generics, where_clause) = &item.generics.split_for_impl();
+
+    let bitvector_len = (capacity + 7) / 8;
+    let fields = get_stable_container_fields(struct_data, capacity);
+
+    // The bits of the bitvector that may be set, one per field.
+    let mut known_bits = vec![0_u8; bitvector_len];
+    let mut register_types = vec![];
+    let mut decodes = vec![];
+
+    for field in fields {
+        let ident = field.ident;
+        let ty = field.ty;
+        let byte = field.byte;
+        let mask = field.mask;
+
+        known_bits[byte] |= mask;
+
+        register_types.push(quote! {
+            if bitvector[#byte] & #mask != 0 {
+                builder.register_type::<#ty>()?;
+            }
+        });
+
+        decodes.push(quote! {
+            #ident: if bitvector[#byte] & #mask != 0 {
+                Some(decoder.decode_next()?)
+            } else {
+                None
+            }
+        });
+    }
+
+    let output = quote! {
+        impl #impl_generics ssz::Decode for #name #ty_generics #where_clause {
+            fn is_ssz_fixed_len() -> bool {
+                false
+            }
+
+            fn from_ssz_bytes(bytes: &[u8]) -> std::result::Result<Self, ssz::DecodeError> {
+                if bytes.len() < #bitvector_len {
+                    return Err(ssz::DecodeError::InvalidByteLength {
+                        len: bytes.len(),
+                        expected: #bitvector_len,
+                    });
+                }
+
+                let (bitvector, bytes) = bytes.split_at(#bitvector_len);
+
+                let known_bits: [u8; #bitvector_len] = [#(#known_bits),*];
+                if bitvector
+                    .iter()
+                    .zip(known_bits.iter())
+                    .any(|(bits, known)| bits & !known != 0)
+                {
+                    return Err(ssz::DecodeError::BytesInvalid(format!(
+                        "{:?} declares fields unknown to {}",
+                        bitvector,
+                        stringify!(#name)
+                    )));
+                }
+
+                let mut builder = ssz::SszDecoderBuilder::new(bytes);
+
+                #(
+                    #register_types
+                )*
+
+                let mut decoder = builder.build()?;
+
+                Ok(Self {
+                    #(
+                        #decodes,
+                    )*
+                })
+            }
+        }
+    };
+    output.into()
+}
+
+/// Implements `ssz::Decode` for an `enum` encoded as an SSZ union.
+fn ssz_decode_derive_union(item: &DeriveInput, enum_data: &syn::DataEnum) -> TokenStream {
+    let name = &item.ident;
+    let (impl_generics, ty_generics, where_clause) = &item.generics.split_for_impl();
+
+    let decodes: Vec<_> = get_union_variants(enum_data)
+        .into_iter()
+        .enumerate()
+        .map(|(index, (variant, has_value))| {
+            if has_value {
+                quote! {
+                    #index => Ok(#name::#variant(ssz::Decode::from_ssz_bytes(value_bytes)?))
+                }
+            } else {
+                quote! {
+                    #index if value_bytes.is_empty() => Ok(#name::#variant)
+                }
+            }
+        })
+        .collect();
+
+    let output = quote! {
+        impl #impl_generics ssz::Decode for #name #ty_generics #where_clause {
+            fn is_ssz_fixed_len() -> bool {
+                false
+            }
+
+            fn from_ssz_bytes(bytes: &[u8]) -> std::result::Result<Self, ssz::DecodeError> {
+                if bytes.len() < ssz::BYTES_PER_LENGTH_OFFSET {
+                    return Err(ssz::DecodeError::InvalidByteLength {
+                        len: bytes.len(),
+                        expected: ssz::BYTES_PER_LENGTH_OFFSET,
+                    });
+                }
+
+                let (index_bytes, value_bytes) = bytes.split_at(ssz::BYTES_PER_LENGTH_OFFSET);
+
+                match ssz::read_union_index(index_bytes)? {
+                    #(
+                        #decodes,
+                    )*
+                    index => Err(ssz::DecodeError::BytesInvalid(format!(
+                        "{} is not a valid union index for {} with {} value bytes",
+                        index,
+                        stringify!(#name),
+                        value_bytes.len()
+                    ))),
+                }
+            }
+        }
+    };
+    output.into()
+}
@@ -74,5 +74,5 @@ fn do_deposits<T: EthSpec>(
         })
         .collect();
 
-    deposit_contract.deposit_multiple(deposits)
+    deposit_contract.deposit_with_delays(deposits)
 }
@@ -0,0 +1,68 @@
+use crate::metrics;
+use exit_future::Exit;
+use futures::Future;
+use slog::{trace, Logger};
+use tokio::runtime;
+
+/// A wrapper around a `tokio` executor which names the tasks it spawns, counts them in metrics and
+/// stops them when the `Environment` shuts down.
+#[derive(Clone)]
+pub struct TaskExecutor {
+    executor: runtime::TaskExecutor,
+    /// Resolves when the `Environment` begins to shut down.
+    exit: Exit,
+    log: Logger,
+}
+
+impl TaskExecutor {
+    pub fn new(executor: runtime::TaskExecutor, exit: Exit, log: Logger) -> Self {
+        Self {
+            executor,
+            exit,
+            log,
+        }
+    }
+
+    /// Spawns `task`, which is dropped as soon as the `Environment` begins to shut down.
+    ///
+    /// Tasks which must not be interrupted (e.g., part way through writing to disk) should use
+    /// `spawn_without_exit` instead.
+    pub fn spawn(
+        &self,
+        task: impl Future<Item = (), Error = ()> + Send + 'static,
+        name: &'static str,
+    ) {
+        let exit = self.exit.clone();
+        self.spawn_without_exit(task.select(exit).then(|_| Ok(())), name)
+    }
+
+    /// Spawns `task`, which continues to run after the `Environment` begins to shut down.
+    ///
+    /// The task should observe `Self::exit` and finish promptly once it resolves, otherwise it
+    /// will be dropped when the shutdown grace period elapses.
+    pub fn spawn_without_exit(
+        &self,
+        task: impl Future<Item = (), Error = ()> + Send + 'static,
+        name: &'static str,
+    ) {
+        let log = self.log.clone();
+
+        metrics::inc_gauge_vec(&metrics::ASYNC_TASKS_COUNT, &[name]);
+
+        self.executor.spawn(task.then(move |result| {
+            metrics::dec_gauge_vec(&metrics::ASYNC_TASKS_COUNT, &[name]);
+            trace!(log, "Async task completed"; "task" => name);
+            result
+        }))
+    }
+
+    /// Returns a future which resolves when the `Environment` begins to shut down.
+    pub fn exit(&self) -> Exit {
+        self.exit.clone()
+    }
+
+    /// Returns the underlying `tokio` executor, for services which manage their own shutdown.
+    pub fn runtime_executor(&self) -> &runtime::TaskExecutor {
+        &self.executor
+    }
+}
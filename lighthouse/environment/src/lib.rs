@@ -7,17 +7,89 @@
 //! `Context` which can be handed to any service that wishes to start async tasks or perform
 //! logging.
 
+#[macro_use]
+extern crate lazy_static;
+
+mod executor;
+mod metrics;
+
+pub use executor::TaskExecutor;
+
 use eth2_config::Eth2Config;
+use exit_future::Signal;
 use futures::{sync::oneshot, Future};
-use slog::{info, o, Drain, Level, Logger};
+use logging::{ModuleFilter, RotatingFile};
+use slog::{info, o, warn, Drain, Duplicate, IgnoreResult, Level, Logger, Never};
 use sloggers::{null::NullLoggerBuilder, Build};
 use std::cell::RefCell;
-use std::fs::OpenOptions;
+use std::io;
 use std::path::PathBuf;
-use std::sync::Mutex;
-use tokio::runtime::{Builder as RuntimeBuilder, Runtime, TaskExecutor};
+use std::str::FromStr;
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tokio::runtime::{Builder as RuntimeBuilder, Runtime};
 use types::{EthSpec, InteropEthSpec, MainnetEthSpec, MinimalEthSpec};
 
+/// The default size at which a log file is rotated, in bytes.
+pub const DEFAULT_MAX_LOG_SIZE: u64 = 200 * 1024 * 1024;
+/// The default number of rotated log files which are kept.
+pub const DEFAULT_MAX_LOG_NUMBER: usize = 5;
+
+/// The time allowed for tasks to finish once the exit signal has fired, before the runtime is
+/// dropped.
+pub const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// The format in which log records are output.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LogFormat {
+    /// Human-readable, aligned text.
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            unknown => Err(format!("Unknown log-format: {}", unknown)),
+        }
+    }
+}
+
+/// Describes the outputs, format and levels of the logger of an `Environment`.
+#[derive(Debug, Clone)]
+pub struct LoggerConfig {
+    /// If `Some`, logs are also written to this file.
+    pub path: Option<PathBuf>,
+    /// The minimum level of logs which are output, unless overridden by `module_levels`.
+    pub log_level: String,
+    pub log_format: LogFormat,
+    /// Minimum levels for specific modules (e.g., `network`), including their sub-modules.
+    pub module_levels: Vec<(String, Level)>,
+    /// The size at which the log file is rotated, in bytes. Zero disables rotation.
+    pub max_log_size: u64,
+    /// The number of rotated log files which are kept.
+    pub max_log_number: usize,
+}
+
+impl Default for LoggerConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            log_level: "info".to_string(),
+            log_format: LogFormat::Text,
+            module_levels: vec![],
+            max_log_size: DEFAULT_MAX_LOG_SIZE,
+            max_log_number: DEFAULT_MAX_LOG_NUMBER,
+        }
+    }
+}
+
 /// Builds an `Environment`.
 pub struct EnvironmentBuilder<E: EthSpec> {
     runtime: Option<Runtime>,
@@ -92,34 +164,84 @@ impl<E: EthSpec> EnvironmentBuilder<E> {
         Ok(self)
     }
 
-    /// Specifies that the `slog` asynchronous logger should be used. Ideal for production.
+    /// Specifies that the `slog` asynchronous logger should be used, with text output to the
+    /// terminal. Ideal for testing.
+    pub fn async_logger(self, debug_level: &str) -> Result<Self, String> {
+        self.initialize_logger(LoggerConfig {
+            log_level: debug_level.to_string(),
+            ..LoggerConfig::default()
+        })
+    }
+
+    /// Specifies that the `slog` asynchronous logger should be used, as described by `config`.
+    /// Ideal for production.
     ///
     /// The logger is "async" because it has a dedicated thread that accepts logs and then
     /// asynchronously flushes them to stdout/files/etc. This means the thread that raised the log
     /// does not have to wait for the logs to be flushed.
-    pub fn async_logger(mut self, debug_level: &str) -> Result<Self, String> {
-        // Build the initial logger.
-        let decorator = slog_term::TermDecorator::new().build();
-        let decorator = logging::AlignedTermDecorator::new(decorator, logging::MAX_MESSAGE_WIDTH);
-        let drain = slog_term::FullFormat::new(decorator).build().fuse();
-        let drain = slog_async::Async::new(drain).build();
+    pub fn initialize_logger(mut self, config: LoggerConfig) -> Result<Self, String> {
+        let terminal_drain: Box<dyn Drain<Ok = (), Err = Never> + Send> = match config.log_format {
+            LogFormat::Text => {
+                let decorator = slog_term::TermDecorator::new().build();
+                let decorator =
+                    logging::AlignedTermDecorator::new(decorator, logging::MAX_MESSAGE_WIDTH);
+                Box::new(slog_term::FullFormat::new(decorator).build().fuse())
+            }
+            LogFormat::Json => Box::new(Mutex::new(slog_json::Json::default(io::stdout())).fuse()),
+        };
+
+        let drain = match &config.path {
+            Some(path) => {
+                let file =
+                    RotatingFile::open(path.clone(), config.max_log_size, config.max_log_number)
+                        .map_err(|e| format!("Unable to open logfile {:?}: {:?}", path, e))?;
+
+                let file_drain: Box<dyn Drain<Ok = (), Err = Never> + Send> = match config
+                    .log_format
+                {
+                    LogFormat::Text => {
+                        let decorator = slog_term::PlainDecorator::new(file);
+                        Box::new(slog_term::FullFormat::new(decorator).build().fuse())
+                    }
+                    LogFormat::Json => Box::new(Mutex::new(slog_json::Json::default(file)).fuse()),
+                };
 
-        let drain = match debug_level {
-            "info" => drain.filter_level(Level::Info),
-            "debug" => drain.filter_level(Level::Debug),
-            "trace" => drain.filter_level(Level::Trace),
-            "warn" => drain.filter_level(Level::Warning),
-            "error" => drain.filter_level(Level::Error),
-            "crit" => drain.filter_level(Level::Critical),
-            unknown => return Err(format!("Unknown debug-level: {}", unknown)),
+                Box::new(IgnoreResult::new(Duplicate::new(
+                    terminal_drain,
+                    file_drain,
+                )))
+            }
+            None => terminal_drain,
         };
 
-        self.log = Some(Logger::root(drain.fuse(), o!()));
+        let drain = slog_async::Async::new(drain).build();
+        let drain = ModuleFilter::new(drain, parse_level(&config.log_level)?, config.module_levels);
+
+        let log = Logger::root(drain.fuse(), o!());
+
+        if let Some(path) = config.path {
+            info!(
+                log,
+                "Logging to file";
+                "path" => format!("{:?}", path),
+                "format" => format!("{:?}", config.log_format)
+            );
+        }
+
+        self.log = Some(log);
         Ok(self)
     }
 
+    /// Specifies the `Eth2Config` of the environment, replacing that of the preset.
+    pub fn eth2_config(mut self, eth2_config: Eth2Config) -> Self {
+        self.eth2_config = eth2_config;
+        self
+    }
+
     /// Consumes the builder, returning an `Environment`.
     pub fn build(self) -> Result<Environment<E>, String> {
+        let (exit_signal, exit) = exit_future::signal();
+
         Ok(Environment {
             runtime: self
                 .runtime
@@ -127,6 +249,8 @@ impl<E: EthSpec> EnvironmentBuilder<E> {
             log: self
                 .log
                 .ok_or_else(|| "Cannot build environment without log".to_string())?,
+            exit_signal: Some(exit_signal),
+            exit,
             eth_spec_instance: self.eth_spec_instance,
             eth2_config: self.eth2_config,
         })
@@ -136,7 +260,8 @@ impl<E: EthSpec> EnvironmentBuilder<E> {
 /// An execution context that can be used by a service.
 ///
 /// Distinct from an `Environment` because a `Context` is not able to give a mutable reference to a
-/// `Runtime`, instead it only has access to a `TaskExecutor`.
+/// `Runtime`, instead it only has access to a `TaskExecutor` (which stops its tasks when the
+/// `Environment` shuts down).
 #[derive(Clone)]
 pub struct RuntimeContext<E: EthSpec> {
     pub executor: TaskExecutor,
@@ -150,9 +275,15 @@ impl<E: EthSpec> RuntimeContext<E> {
     ///
     /// The generated service will have the `service_name` in all it's logs.
     pub fn service_context(&self, service_name: &'static str) -> Self {
+        let log = self.log.new(o!("service" => service_name));
+
         Self {
-            executor: self.executor.clone(),
-            log: self.log.new(o!("service" => service_name)),
+            executor: TaskExecutor::new(
+                self.executor.runtime_executor().clone(),
+                self.executor.exit(),
+                log.clone(),
+            ),
+            log,
             eth_spec_instance: self.eth_spec_instance.clone(),
             eth2_config: self.eth2_config.clone(),
         }
@@ -169,6 +300,9 @@ impl<E: EthSpec> RuntimeContext<E> {
 pub struct Environment<E: EthSpec> {
     runtime: Runtime,
     log: Logger,
+    /// Fires when the environment begins to shut down, stopping the tasks of each `TaskExecutor`.
+    exit_signal: Option<Signal>,
+    exit: exit_future::Exit,
     eth_spec_instance: E,
     eth2_config: Eth2Config,
 }
@@ -185,7 +319,11 @@ impl<E: EthSpec> Environment<E> {
     /// Returns a `Context` where no "service" has been added to the logger output.
     pub fn core_context(&mut self) -> RuntimeContext<E> {
         RuntimeContext {
-            executor: self.runtime.executor(),
+            executor: TaskExecutor::new(
+                self.runtime.executor(),
+                self.exit.clone(),
+                self.log.clone(),
+            ),
             log: self.log.clone(),
             eth_spec_instance: self.eth_spec_instance.clone(),
             eth2_config: self.eth2_config.clone(),
@@ -194,9 +332,11 @@ impl<E: EthSpec> Environment<E> {
 
     /// Returns a `Context` where the `service_name` is added to the logger output.
     pub fn service_context(&mut self, service_name: String) -> RuntimeContext<E> {
+        let log = self.log.new(o!("service" => service_name));
+
         RuntimeContext {
-            executor: self.runtime.executor(),
-            log: self.log.new(o!("service" => service_name)),
+            executor: TaskExecutor::new(self.runtime.executor(), self.exit.clone(), log.clone()),
+            log,
             eth_spec_instance: self.eth_spec_instance.clone(),
             eth2_config: self.eth2_config.clone(),
         }
@@ -219,34 +359,34 @@ impl<E: EthSpec> Environment<E> {
             .map_err(|e| format!("Ctrlc oneshot failed: {:?}", e))
     }
 
-    /// Shutdown the `tokio` runtime when all tasks are idle.
-    pub fn shutdown_on_idle(self) -> Result<(), String> {
-        self.runtime
-            .shutdown_on_idle()
-            .wait()
-            .map_err(|e| format!("Tokio runtime shutdown returned an error: {:?}", e))
-    }
+    /// Fires the exit signal, stopping the tasks of each `TaskExecutor` then shutting down the
+    /// `tokio` runtime once all tasks are idle.
+    ///
+    /// Tasks which have not finished within `SHUTDOWN_GRACE_PERIOD` are dropped.
+    pub fn shutdown_on_idle(mut self) -> Result<(), String> {
+        if let Some(exit_signal) = self.exit_signal.take() {
+            exit_signal.fire();
+        }
 
-    /// Sets the logger (and all child loggers) to log to a file.
-    pub fn log_to_json_file(&mut self, path: PathBuf) -> Result<(), String> {
-        let file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(&path)
-            .map_err(|e| format!("Unable to open logfile: {:?}", e))?;
-
-        let drain = Mutex::new(slog_json::Json::default(file)).fuse();
-        let drain = slog_async::Async::new(drain).build().fuse();
-        self.log = slog::Logger::root(drain, o!());
-
-        info!(
-            self.log,
-            "Logging to JSON file";
-            "path" => format!("{:?}", path)
-        );
+        let shutdown = self.runtime.shutdown_on_idle();
+        let (result_send, result_recv) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = result_send.send(shutdown.wait());
+        });
 
-        Ok(())
+        match result_recv.recv_timeout(SHUTDOWN_GRACE_PERIOD) {
+            Ok(result) => {
+                result.map_err(|e| format!("Tokio runtime shutdown returned an error: {:?}", e))
+            }
+            Err(_) => {
+                warn!(
+                    self.log,
+                    "Async tasks did not finish in time";
+                    "grace_period_secs" => SHUTDOWN_GRACE_PERIOD.as_secs()
+                );
+                Ok(())
+            }
+        }
     }
 
     pub fn eth_spec_instance(&self) -> &E {
@@ -258,9 +398,72 @@ impl<E: EthSpec> Environment<E> {
     }
 }
 
+/// Parses a log level, as accepted by the `--log-level` flag.
+pub fn parse_level(level: &str) -> Result<Level, String> {
+    match level {
+        "info" => Ok(Level::Info),
+        "debug" => Ok(Level::Debug),
+        "trace" => Ok(Level::Trace),
+        "warn" => Ok(Level::Warning),
+        "error" => Ok(Level::Error),
+        "crit" => Ok(Level::Critical),
+        unknown => Err(format!("Unknown log-level: {}", unknown)),
+    }
+}
+
+/// Parses a comma-separated list of `module=level` pairs, as accepted by the `--log-filter` flag.
+///
+/// E.g., `network=debug,beacon_chain=trace`.
+pub fn parse_module_levels(filter: &str) -> Result<Vec<(String, Level)>, String> {
+    filter
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let mut split = pair.splitn(2, '=');
+            match (split.next(), split.next()) {
+                (Some(module), Some(level)) if !module.is_empty() => {
+                    Ok((module.to_string(), parse_level(level)?))
+                }
+                _ => Err(format!(
+                    "Invalid log-filter \"{}\", expected module=level",
+                    pair
+                )),
+            }
+        })
+        .collect()
+}
+
 pub fn null_logger() -> Result<Logger, String> {
     let log_builder = NullLoggerBuilder;
     log_builder
         .build()
         .map_err(|e| format!("Failed to start null logger: {:?}", e))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_levels() {
+        assert_eq!(
+            parse_module_levels("network=debug, beacon_chain::fork_choice=trace,"),
+            Ok(vec![
+                ("network".to_string(), Level::Debug),
+                ("beacon_chain::fork_choice".to_string(), Level::Trace)
+            ])
+        );
+        assert_eq!(parse_module_levels(""), Ok(vec![]));
+        assert!(parse_module_levels("network").is_err());
+        assert!(parse_module_levels("=debug").is_err());
+        assert!(parse_module_levels("network=loud").is_err());
+    }
+
+    #[test]
+    fn log_format() {
+        assert_eq!("text".parse(), Ok(LogFormat::Text));
+        assert_eq!("json".parse(), Ok(LogFormat::Json));
+        assert!("xml".parse::<LogFormat>().is_err());
+    }
+}
@@ -0,0 +1,9 @@
+pub use lighthouse_metrics::*;
+
+lazy_static! {
+    pub static ref ASYNC_TASKS_COUNT: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "async_tasks_count",
+        "Number of running async tasks, by task name",
+        &["task_name"]
+    );
+}
@@ -2,16 +2,16 @@
 extern crate clap;
 
 use beacon_node::ProductionBeaconNode;
-use clap::{App, Arg, ArgMatches};
+use clap::{App, ArgMatches};
 use env_logger::{Builder, Env};
-use environment::EnvironmentBuilder;
+use environment::{parse_module_levels, EnvironmentBuilder, LoggerConfig};
+use eth2_config::{read_testnet_dir, Eth2Config};
 use slog::{crit, info, warn};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::exit;
 use types::EthSpec;
 use validator_client::ProductionValidatorClient;
 
-pub const DEFAULT_DATA_DIR: &str = ".lighthouse";
 pub const CLIENT_CONFIG_FILENAME: &str = "beacon-node.toml";
 pub const ETH2_CONFIG_FILENAME: &str = "eth2-spec.toml";
 
@@ -24,60 +24,51 @@ fn main() {
         .version(crate_version!())
         .author("Sigma Prime <contact@sigmaprime.io>")
         .about("Eth 2.0 Client")
-        .arg(
-            Arg::with_name("spec")
-                .short("s")
-                .long("spec")
-                .value_name("TITLE")
-                .help("Specifies the default eth2 spec type.")
-                .takes_value(true)
-                .possible_values(&["mainnet", "minimal", "interop"])
-                .global(true)
-                .default_value("minimal"),
-        )
-        .arg(
-            Arg::with_name("logfile")
-                .long("logfile")
-                .value_name("FILE")
-                .help("File path where output will be written.")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("debug-level")
-                .long("debug-level")
-                .value_name("LEVEL")
-                .help("The title of the spec constants for chain config.")
-                .takes_value(true)
-                .possible_values(&["info", "debug", "trace", "warn", "error", "crit"])
-                .default_value("trace"),
-        )
-        .arg(
-            Arg::with_name("datadir")
-                .long("datadir")
-                .short("d")
-                .value_name("DIR")
-                .global(true)
-                .help("Data directory for keys and databases.")
-                .takes_value(true),
-        )
+        .args(&clap_utils::global_args())
         .subcommand(beacon_node::cli_app())
         .subcommand(validator_client::cli_app())
         .subcommand(account_manager::cli_app())
+        .subcommand(database_manager::cli_app())
+        .subcommand(eth1_manager::cli_app())
+        .subcommand(debug_manager::cli_app())
         .get_matches();
 
+    let testnet_config = match matches.value_of("testnet-dir") {
+        Some(testnet_dir) => match read_testnet_dir(Path::new(testnet_dir))
+            .and_then(|file| Eth2Config::from_chain_spec_file(&file))
+        {
+            Ok(eth2_config) => Some(eth2_config),
+            Err(e) => {
+                println!("Failed to load testnet directory: {}", e);
+                exit(1)
+            }
+        },
+        None => None,
+    };
+
+    let spec_constants = match &testnet_config {
+        Some(eth2_config) => Some(eth2_config.spec_constants.clone()),
+        None => matches.value_of("spec").map(String::from),
+    };
+
     macro_rules! run_with_spec {
-        ($env_builder: expr) => {
-            match run($env_builder, &matches) {
+        ($env_builder: expr) => {{
+            let env_builder = match testnet_config {
+                Some(eth2_config) => $env_builder.eth2_config(eth2_config),
+                None => $env_builder,
+            };
+
+            match run(env_builder, &matches) {
                 Ok(()) => exit(0),
                 Err(e) => {
                     println!("Failed to start Lighthouse: {}", e);
                     exit(1)
                 }
             }
-        };
+        }};
     }
 
-    match matches.value_of("spec") {
+    match spec_constants.as_ref().map(String::as_str) {
         Some("minimal") => run_with_spec!(EnvironmentBuilder::minimal()),
         Some("mainnet") => run_with_spec!(EnvironmentBuilder::mainnet()),
         Some("interop") => run_with_spec!(EnvironmentBuilder::interop()),
@@ -92,24 +83,29 @@ fn run<E: EthSpec>(
     environment_builder: EnvironmentBuilder<E>,
     matches: &ArgMatches,
 ) -> Result<(), String> {
+    let logger_config = LoggerConfig {
+        path: matches.value_of("logfile").map(PathBuf::from),
+        log_level: matches
+            .value_of("log-level")
+            .ok_or_else(|| "Expected --log-level flag".to_string())?
+            .to_string(),
+        log_format: clap_utils::parse_required(matches, "log-format")?,
+        module_levels: matches
+            .value_of("log-filter")
+            .map(parse_module_levels)
+            .transpose()?
+            .unwrap_or_else(Vec::new),
+        max_log_size: clap_utils::parse_required::<u64>(matches, "logfile-max-size")? * 1024 * 1024,
+        max_log_number: clap_utils::parse_required(matches, "logfile-max-number")?,
+    };
+
     let mut environment = environment_builder
-        .async_logger(
-            matches
-                .value_of("debug-level")
-                .ok_or_else(|| "Expected --debug-level flag".to_string())?,
-        )?
+        .initialize_logger(logger_config)?
         .multi_threaded_tokio_runtime()?
         .build()?;
 
     let log = environment.core_context().log;
 
-    if let Some(log_path) = matches.value_of("logfile") {
-        let path = log_path
-            .parse::<PathBuf>()
-            .map_err(|e| format!("Failed to parse log path: {:?}", e))?;
-        environment.log_to_json_file(path)?;
-    }
-
     if std::mem::size_of::<usize>() != 8 {
         crit!(
             log,
@@ -142,6 +138,31 @@ fn run<E: EthSpec>(
         return Ok(());
     }
 
+    if let Some(sub_matches) = matches.subcommand_matches("database_manager") {
+        let runtime_context = environment.core_context();
+
+        // The database manager operates directly on the datadir, without the tokio executor. Its
+        // error is returned so that a failed operation exits with a non-zero code.
+        return database_manager::run(sub_matches, runtime_context);
+    }
+
+    if let Some(sub_matches) = matches.subcommand_matches("eth1_manager") {
+        let runtime_context = environment.core_context();
+
+        // The eth1 manager runs its requests on its own runtime, not the tokio executor. Its error
+        // is returned so that a failed verification exits with a non-zero code.
+        return eth1_manager::run(sub_matches, runtime_context);
+    }
+
+    if let Some(sub_matches) = matches.subcommand_matches("debug_manager") {
+        let runtime_context = environment.core_context();
+
+        debug_manager::run(sub_matches, runtime_context);
+
+        // The debug manager operates on files, without the tokio executor.
+        return Ok(());
+    }
+
     let beacon_node = if let Some(sub_matches) = matches.subcommand_matches("beacon_node") {
         let runtime_context = environment.core_context();
 
@@ -11,6 +11,7 @@ mod bls_priv_to_pub;
 mod bls_sign_msg;
 mod common;
 mod epoch_processing;
+mod fork_choice;
 mod genesis_initialization;
 mod genesis_validity;
 mod operations;
@@ -28,6 +29,7 @@ pub use bls_priv_to_pub::*;
 pub use bls_sign_msg::*;
 pub use common::SszStaticType;
 pub use epoch_processing::*;
+pub use fork_choice::*;
 pub use genesis_initialization::*;
 pub use genesis_validity::*;
 pub use operations::*;
@@ -0,0 +1,675 @@
+use super::*;
+use crate::decode::yaml_decode_file;
+use proto_array::ProtoArrayForkChoice;
+use serde_derive::Deserialize;
+use state_processing::{
+    common::get_indexed_attestation,
+    per_block_processing,
+    per_block_processing::{is_valid_indexed_attestation, VerifySignatures},
+    per_slot_processing, BlockSignatureStrategy,
+};
+use std::collections::HashMap;
+use types::test_utils::{
+    AttestationTestTask, TestingAttestationBuilder, TestingBeaconBlockBuilder,
+    TestingBeaconStateBuilder,
+};
+use types::{
+    Attestation, BeaconBlock, BeaconState, ChainSpec, Checkpoint, EthSpec, Hash256, Keypair,
+    RelativeEpoch, SecretKey, Slot,
+};
+
+/// The name by which the steps of a fixture refer to the anchor block.
+const ANCHOR_NAME: &str = "anchor";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Head {
+    pub slot: Slot,
+    pub root: Hash256,
+}
+
+/// The expected values of the fork choice store. Only the values that are present are checked.
+///
+/// Blocks are referred to by name, as `H`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Checks<H> {
+    pub head: Option<H>,
+    pub time: Option<u64>,
+    pub justified_checkpoint_root: Option<H>,
+    pub finalized_checkpoint_root: Option<H>,
+    pub best_justified_checkpoint: Option<H>,
+}
+
+/// A single step of a fork choice test.
+///
+/// The fixtures describe blocks and attestations as `B` and `A` respectively, and refer to blocks
+/// by name. They are built into `BeaconBlock`s and `Attestation`s when the test is loaded.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Step<B, A, H> {
+    Tick {
+        tick: u64,
+    },
+    Block {
+        block: B,
+        /// Blocks which must be rejected are marked as invalid.
+        #[serde(default = "default_valid")]
+        valid: bool,
+    },
+    Attestation {
+        attestation: A,
+    },
+    Checks {
+        checks: Checks<H>,
+    },
+}
+
+fn default_valid() -> bool {
+    true
+}
+
+/// The anchor of a fixture, from which all of its blocks descend.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnchorFixture {
+    /// The number of validators, all of which are active from genesis.
+    pub validator_count: usize,
+}
+
+/// A block with slot `slot` and parent `parent`, to be referred to as `name` by later steps.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BlockFixture {
+    pub name: String,
+    pub parent: String,
+    pub slot: Slot,
+    /// Distinguishes blocks which would otherwise be identical, such as siblings at one slot.
+    #[serde(default)]
+    pub graffiti: String,
+}
+
+/// The attestations of every committee of `slot` to the block named `block`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AttestationFixture {
+    pub block: String,
+    pub slot: Slot,
+}
+
+#[derive(Debug)]
+pub struct ForkChoiceTest<E: EthSpec> {
+    pub anchor_state: BeaconState<E>,
+    pub anchor_block: BeaconBlock<E>,
+    pub steps: Vec<Step<BeaconBlock<E>, Attestation<E>, Head>>,
+}
+
+impl<E: EthSpec> LoadCase for ForkChoiceTest<E> {
+    fn load_from_dir(path: &Path) -> Result<Self, Error> {
+        let anchor: AnchorFixture = yaml_decode_file(&path.join("anchor.yaml"))?;
+        let fixture_steps: Vec<Step<BlockFixture, AttestationFixture, String>> =
+            yaml_decode_file(&path.join("steps.yaml"))?;
+
+        let mut builder = FixtureBuilder::new(anchor.validator_count, E::default_spec());
+        let mut steps = vec![];
+
+        for step in fixture_steps {
+            match step {
+                Step::Tick { tick } => steps.push(Step::Tick { tick }),
+                Step::Block { block, valid } => steps.push(Step::Block {
+                    block: builder.build_block(&block)?,
+                    valid,
+                }),
+                Step::Attestation { attestation } => steps.extend(
+                    builder
+                        .build_attestations(&attestation)?
+                        .into_iter()
+                        .map(|attestation| Step::Attestation { attestation }),
+                ),
+                Step::Checks { checks } => steps.push(Step::Checks {
+                    checks: builder.resolve_checks(checks)?,
+                }),
+            }
+        }
+
+        Ok(Self {
+            anchor_state: builder.anchor_state,
+            anchor_block: builder.anchor_block,
+            steps,
+        })
+    }
+}
+
+/// Builds the blocks and attestations described by a fixture, which are signed by deterministic
+/// keypairs.
+struct FixtureBuilder<E: EthSpec> {
+    spec: ChainSpec,
+    keypairs: Vec<Keypair>,
+    anchor_state: BeaconState<E>,
+    anchor_block: BeaconBlock<E>,
+    /// The block and post-state of each block built so far, by name.
+    blocks: HashMap<String, (BeaconBlock<E>, BeaconState<E>)>,
+}
+
+impl<E: EthSpec> FixtureBuilder<E> {
+    fn new(validator_count: usize, spec: ChainSpec) -> Self {
+        let (mut anchor_state, keypairs) =
+            TestingBeaconStateBuilder::from_deterministic_keypairs(validator_count, &spec).build();
+        // Ticks are given in seconds since genesis.
+        anchor_state.genesis_time = 0;
+
+        let mut anchor_block = BeaconBlock::empty(&spec);
+        anchor_block.state_root = anchor_state.canonical_root();
+
+        let mut blocks = HashMap::new();
+        blocks.insert(
+            ANCHOR_NAME.to_string(),
+            (anchor_block.clone(), anchor_state.clone()),
+        );
+
+        Self {
+            spec,
+            keypairs,
+            anchor_state,
+            anchor_block,
+            blocks,
+        }
+    }
+
+    fn get(&self, name: &str) -> Result<&(BeaconBlock<E>, BeaconState<E>), Error> {
+        self.blocks
+            .get(name)
+            .ok_or_else(|| Error::FailedToParseTest(format!("Unknown block {}", name)))
+    }
+
+    fn secret_key(&self, validator_index: usize) -> Result<&SecretKey, Error> {
+        self.keypairs
+            .get(validator_index)
+            .map(|keypair| &keypair.sk)
+            .ok_or_else(|| {
+                Error::FailedToParseTest(format!("Unknown validator {}", validator_index))
+            })
+    }
+
+    /// Returns the post-state of the block named `name`, advanced to `slot` with its committee
+    /// caches built.
+    fn state_at_slot(&self, name: &str, slot: Slot) -> Result<BeaconState<E>, Error> {
+        let mut state = self.get(name)?.1.clone();
+        while state.slot < slot {
+            per_slot_processing(&mut state, &self.spec)
+                .map_err(|e| Error::FailedToParseTest(format!("{:?}", e)))?;
+        }
+        state
+            .build_committee_cache(RelativeEpoch::Previous, &self.spec)
+            .and_then(|()| state.build_committee_cache(RelativeEpoch::Current, &self.spec))
+            .map_err(|e| Error::FailedToParseTest(format!("{:?}", e)))?;
+        Ok(state)
+    }
+
+    fn build_block(&mut self, fixture: &BlockFixture) -> Result<BeaconBlock<E>, Error> {
+        let parent_root = self.get(&fixture.parent)?.0.canonical_root();
+        let mut state = self.state_at_slot(&fixture.parent, fixture.slot)?;
+
+        let proposer_index = state
+            .get_beacon_proposer_index(fixture.slot, &self.spec)
+            .map_err(|e| Error::FailedToParseTest(format!("{:?}", e)))?;
+        let secret_key = self.secret_key(proposer_index)?.clone();
+
+        let mut builder = TestingBeaconBlockBuilder::new(&self.spec);
+        builder.set_slot(fixture.slot);
+        builder.set_parent_root(parent_root);
+        let graffiti = fixture.graffiti.as_bytes();
+        if graffiti.len() > builder.block.body.graffiti.len() {
+            return Err(Error::FailedToParseTest(format!(
+                "Graffiti of block {} is too long",
+                fixture.name
+            )));
+        }
+        builder.block.body.graffiti[..graffiti.len()].copy_from_slice(graffiti);
+        builder.set_randao_reveal(&secret_key, &state.fork, &self.spec);
+
+        per_block_processing(
+            &mut state,
+            &builder.block,
+            None,
+            BlockSignatureStrategy::NoVerification,
+            &self.spec,
+        )
+        .map_err(|e| Error::FailedToParseTest(format!("{:?}", e)))?;
+        builder.block.state_root = state.canonical_root();
+
+        let block = builder.build(&secret_key, &state.fork, &self.spec);
+        self.blocks
+            .insert(fixture.name.clone(), (block.clone(), state));
+
+        Ok(block)
+    }
+
+    fn build_attestations(
+        &self,
+        fixture: &AttestationFixture,
+    ) -> Result<Vec<Attestation<E>>, Error> {
+        // The block roots of the state include `fixture.slot` once it has been advanced past it.
+        let state = self.state_at_slot(&fixture.block, fixture.slot + 1)?;
+        let committees = state
+            .get_beacon_committees_at_slot(fixture.slot)
+            .map_err(|e| Error::FailedToParseTest(format!("{:?}", e)))?;
+
+        committees
+            .into_iter()
+            .map(|committee| {
+                let secret_keys = committee
+                    .committee
+                    .iter()
+                    .map(|validator_index| self.secret_key(*validator_index))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let mut builder = TestingAttestationBuilder::new(
+                    AttestationTestTask::Valid,
+                    &state,
+                    committee.committee,
+                    fixture.slot,
+                    committee.index,
+                    &self.spec,
+                );
+                builder.sign(
+                    AttestationTestTask::Valid,
+                    committee.committee,
+                    &secret_keys,
+                    &state.fork,
+                    &self.spec,
+                );
+                Ok(builder.build())
+            })
+            .collect()
+    }
+
+    fn head(&self, name: &str) -> Result<Head, Error> {
+        let block = &self.get(name)?.0;
+        Ok(Head {
+            slot: block.slot,
+            root: block.canonical_root(),
+        })
+    }
+
+    fn resolve_checks(&self, checks: Checks<String>) -> Result<Checks<Head>, Error> {
+        let resolve = |name: Option<String>| name.map(|name| self.head(&name)).transpose();
+
+        Ok(Checks {
+            head: resolve(checks.head)?,
+            time: checks.time,
+            justified_checkpoint_root: resolve(checks.justified_checkpoint_root)?,
+            finalized_checkpoint_root: resolve(checks.finalized_checkpoint_root)?,
+            best_justified_checkpoint: resolve(checks.best_justified_checkpoint)?,
+        })
+    }
+}
+
+impl<E: EthSpec> Case for ForkChoiceTest<E> {
+    fn result(&self, _case_index: usize) -> Result<(), Error> {
+        let spec = &E::default_spec();
+        let mut tester = Tester::new(self.anchor_state.clone(), &self.anchor_block, spec)?;
+
+        for step in &self.steps {
+            match step {
+                Step::Tick { tick } => tester.on_tick(*tick),
+                Step::Block { block, valid } => match (tester.on_block(block), valid) {
+                    (Ok(()), false) => {
+                        return Err(Error::DidntFail(format!(
+                            "block at slot {} should be invalid",
+                            block.slot
+                        )))
+                    }
+                    (Err(e), true) => {
+                        return Err(Error::NotEqual(format!(
+                            "block at slot {} should be valid: {}",
+                            block.slot, e
+                        )))
+                    }
+                    _ => {}
+                },
+                // Invalid attestations are simply ignored by fork choice.
+                Step::Attestation { attestation } => {
+                    let _ = tester.on_attestation(attestation);
+                }
+                Step::Checks { checks } => tester.check(checks)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Implements the fork choice `Store` of the specification using `ProtoArrayForkChoice`.
+struct Tester<E: EthSpec> {
+    spec: ChainSpec,
+    genesis_time: u64,
+    time: u64,
+    justified_checkpoint: Checkpoint,
+    finalized_checkpoint: Checkpoint,
+    best_justified_checkpoint: Checkpoint,
+    /// The slot and parent root of each known block.
+    blocks: HashMap<Hash256, (Slot, Hash256)>,
+    /// The post-state of each known block.
+    states: HashMap<Hash256, BeaconState<E>>,
+    fork_choice: ProtoArrayForkChoice,
+}
+
+impl<E: EthSpec> Tester<E> {
+    fn new(
+        mut anchor_state: BeaconState<E>,
+        anchor_block: &BeaconBlock<E>,
+        spec: &ChainSpec,
+    ) -> Result<Self, Error> {
+        if anchor_block.state_root != anchor_state.canonical_root() {
+            return Err(Error::FailedToParseTest(
+                "anchor block does not match anchor state".into(),
+            ));
+        }
+
+        anchor_state
+            .build_all_caches(spec)
+            .map_err(|e| Error::FailedToParseTest(format!("{:?}", e)))?;
+
+        let anchor_root = anchor_block.canonical_root();
+        let anchor_checkpoint = Checkpoint {
+            epoch: anchor_state.current_epoch(),
+            root: anchor_root,
+        };
+        let fork_choice = ProtoArrayForkChoice::new(
+            anchor_block.slot,
+            anchor_checkpoint.epoch,
+            anchor_checkpoint.epoch,
+            anchor_root,
+        )
+        .map_err(Error::FailedToParseTest)?;
+
+        let mut blocks = HashMap::new();
+        blocks.insert(anchor_root, (anchor_block.slot, anchor_block.parent_root));
+
+        let genesis_time = anchor_state.genesis_time;
+        let time = genesis_time + anchor_state.slot.as_u64() * seconds_per_slot(spec);
+
+        let mut states = HashMap::new();
+        states.insert(anchor_root, anchor_state);
+
+        Ok(Self {
+            spec: spec.clone(),
+            genesis_time,
+            time,
+            justified_checkpoint: anchor_checkpoint.clone(),
+            finalized_checkpoint: anchor_checkpoint.clone(),
+            best_justified_checkpoint: anchor_checkpoint,
+            blocks,
+            states,
+            fork_choice,
+        })
+    }
+
+    fn current_slot(&self) -> Slot {
+        Slot::new((self.time - self.genesis_time) / seconds_per_slot(&self.spec))
+    }
+
+    /// Returns the root of the ancestor of `root` at `slot`, or `None` if it is prior to the
+    /// anchor block.
+    fn get_ancestor(&self, mut root: Hash256, slot: Slot) -> Option<Hash256> {
+        loop {
+            let (block_slot, parent_root) = self.blocks.get(&root)?;
+            if *block_slot <= slot {
+                return Some(root);
+            }
+            root = *parent_root;
+        }
+    }
+
+    /// Returns a clone of the post-state of `root`, advanced to `slot`.
+    fn state_at_slot(&self, root: &Hash256, slot: Slot) -> Result<BeaconState<E>, String> {
+        let mut state = self
+            .states
+            .get(root)
+            .cloned()
+            .ok_or_else(|| format!("Unknown block {:?}", root))?;
+
+        while state.slot < slot {
+            per_slot_processing(&mut state, &self.spec).map_err(|e| format!("{:?}", e))?;
+        }
+        state
+            .build_committee_cache(RelativeEpoch::Previous, &self.spec)
+            .map_err(|e| format!("{:?}", e))?;
+        state
+            .build_committee_cache(RelativeEpoch::Current, &self.spec)
+            .map_err(|e| format!("{:?}", e))?;
+
+        Ok(state)
+    }
+
+    fn should_update_justified_checkpoint(&self, new_justified: &Checkpoint) -> bool {
+        let slots_per_epoch = E::slots_per_epoch();
+        if self.current_slot() % slots_per_epoch < self.spec.safe_slots_to_update_justified {
+            return true;
+        }
+
+        let justified_slot = self.justified_checkpoint.epoch.start_slot(slots_per_epoch);
+        self.get_ancestor(new_justified.root, justified_slot)
+            == Some(self.justified_checkpoint.root)
+    }
+
+    fn on_tick(&mut self, time: u64) {
+        let previous_slot = self.current_slot();
+        self.time = time;
+        let current_slot = self.current_slot();
+
+        if current_slot > previous_slot
+            && current_slot % E::slots_per_epoch() == 0
+            && self.best_justified_checkpoint.epoch > self.justified_checkpoint.epoch
+        {
+            self.justified_checkpoint = self.best_justified_checkpoint.clone();
+        }
+    }
+
+    fn on_block(&mut self, block: &BeaconBlock<E>) -> Result<(), String> {
+        if block.slot > self.current_slot() {
+            return Err(format!("Block is from the future slot {}", block.slot));
+        }
+
+        let finalized_slot = self
+            .finalized_checkpoint
+            .epoch
+            .start_slot(E::slots_per_epoch());
+        if block.slot <= finalized_slot {
+            return Err("Block is prior to the finalized slot".into());
+        }
+        if self.get_ancestor(block.parent_root, finalized_slot)
+            != Some(self.finalized_checkpoint.root)
+        {
+            return Err("Block does not descend from the finalized block".into());
+        }
+
+        let mut state = self.state_at_slot(&block.parent_root, block.slot)?;
+        per_block_processing(
+            &mut state,
+            block,
+            None,
+            BlockSignatureStrategy::VerifyIndividual,
+            &self.spec,
+        )
+        .map_err(|e| format!("{:?}", e))?;
+        if block.state_root != state.canonical_root() {
+            return Err("Block has an invalid state root".into());
+        }
+
+        let block_root = block.canonical_root();
+        self.fork_choice.process_block(
+            block.slot,
+            block_root,
+            block.parent_root,
+            state.current_justified_checkpoint.epoch,
+            state.finalized_checkpoint.epoch,
+        )?;
+        self.blocks
+            .insert(block_root, (block.slot, block.parent_root));
+
+        let justified = state.current_justified_checkpoint.clone();
+        if justified.epoch > self.justified_checkpoint.epoch {
+            if justified.epoch > self.best_justified_checkpoint.epoch {
+                self.best_justified_checkpoint = justified.clone();
+            }
+            if self.should_update_justified_checkpoint(&justified) {
+                self.justified_checkpoint = justified.clone();
+            }
+        }
+
+        if state.finalized_checkpoint.epoch > self.finalized_checkpoint.epoch {
+            self.finalized_checkpoint = state.finalized_checkpoint.clone();
+
+            if self.justified_checkpoint != justified {
+                // Update the justified checkpoint if it is later than the store, or if the
+                // justified block of the store does not descend from the finalized block.
+                let finalized_slot = self
+                    .finalized_checkpoint
+                    .epoch
+                    .start_slot(E::slots_per_epoch());
+                if justified.epoch > self.justified_checkpoint.epoch
+                    || self.get_ancestor(self.justified_checkpoint.root, finalized_slot)
+                        != Some(self.finalized_checkpoint.root)
+                {
+                    self.justified_checkpoint = justified;
+                }
+            }
+        }
+
+        self.states.insert(block_root, state);
+
+        Ok(())
+    }
+
+    fn on_attestation(&mut self, attestation: &Attestation<E>) -> Result<(), String> {
+        let slots_per_epoch = E::slots_per_epoch();
+        let data = &attestation.data;
+        let target = &data.target;
+
+        let current_epoch = self.current_slot().epoch(slots_per_epoch);
+        let previous_epoch = if current_epoch > 0 {
+            current_epoch - 1
+        } else {
+            current_epoch
+        };
+        if target.epoch != current_epoch && target.epoch != previous_epoch {
+            return Err("Attestation target is not in the current or previous epoch".into());
+        }
+        if target.epoch != data.slot.epoch(slots_per_epoch) {
+            return Err("Attestation target does not match its slot".into());
+        }
+
+        let (block_slot, _) = self
+            .blocks
+            .get(&data.beacon_block_root)
+            .ok_or_else(|| "Attestation is for an unknown block".to_string())?;
+        if *block_slot > data.slot {
+            return Err("Attestation is for a block after its slot".into());
+        }
+
+        let target_slot = target.epoch.start_slot(slots_per_epoch);
+        if self.get_ancestor(data.beacon_block_root, target_slot) != Some(target.root) {
+            return Err("Attestation head does not descend from its target".into());
+        }
+
+        // Attestations only affect fork choice from the slot after they are made.
+        if self.current_slot() <= data.slot {
+            return Err("Attestation is from the current slot or later".into());
+        }
+
+        let target_state = self.state_at_slot(&target.root, target_slot)?;
+        let indexed_attestation =
+            get_indexed_attestation(&target_state, attestation).map_err(|e| format!("{:?}", e))?;
+        is_valid_indexed_attestation(
+            &target_state,
+            &indexed_attestation,
+            VerifySignatures::True,
+            &self.spec,
+        )
+        .map_err(|e| format!("{:?}", e))?;
+
+        for validator_index in indexed_attestation.attesting_indices.iter() {
+            self.fork_choice.process_attestation(
+                *validator_index as usize,
+                data.beacon_block_root,
+                target.epoch,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn get_head(&self) -> Result<Hash256, String> {
+        let justified_slot = self
+            .justified_checkpoint
+            .epoch
+            .start_slot(E::slots_per_epoch());
+        let justified_state =
+            self.state_at_slot(&self.justified_checkpoint.root, justified_slot)?;
+        let balances = justified_state
+            .validators
+            .iter()
+            .map(|validator| {
+                if validator.is_active_at(justified_state.current_epoch()) {
+                    validator.effective_balance
+                } else {
+                    0
+                }
+            })
+            .collect::<Vec<_>>();
+
+        self.fork_choice.find_head(
+            self.justified_checkpoint.epoch,
+            self.justified_checkpoint.root,
+            self.finalized_checkpoint.epoch,
+            &balances,
+        )
+    }
+
+    fn check(&self, checks: &Checks<Head>) -> Result<(), Error> {
+        if let Some(head) = checks.head.as_ref() {
+            let head_root = self.get_head().map_err(Error::NotEqual)?;
+            let head_slot = self.blocks.get(&head_root).map(|(slot, _)| *slot);
+            check_equal("head root", &head_root, &head.root)?;
+            check_equal("head slot", &head_slot, &Some(head.slot))?;
+        }
+        if let Some(time) = checks.time {
+            check_equal("time", &self.time, &time)?;
+        }
+        if let Some(checkpoint) = checks.justified_checkpoint_root.as_ref() {
+            check_equal(
+                "justified checkpoint root",
+                &self.justified_checkpoint.root,
+                &checkpoint.root,
+            )?;
+        }
+        if let Some(checkpoint) = checks.finalized_checkpoint_root.as_ref() {
+            check_equal(
+                "finalized checkpoint root",
+                &self.finalized_checkpoint.root,
+                &checkpoint.root,
+            )?;
+        }
+        if let Some(checkpoint) = checks.best_justified_checkpoint.as_ref() {
+            check_equal(
+                "best justified checkpoint root",
+                &self.best_justified_checkpoint.root,
+                &checkpoint.root,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn seconds_per_slot(spec: &ChainSpec) -> u64 {
+    spec.milliseconds_per_slot / 1_000
+}
+
+fn check_equal<T: PartialEq + Debug>(name: &str, local: &T, expected: &T) -> Result<(), Error> {
+    if local == expected {
+        Ok(())
+    } else {
+        Err(Error::NotEqual(format!(
+            "{}: got {:?}, expected {:?}",
+            name, local, expected
+        )))
+    }
+}
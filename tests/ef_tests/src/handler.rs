@@ -24,9 +24,13 @@ pub trait Handler {
 
     fn handler_name() -> String;
 
+    /// The directory containing the `tests` of each config, which defaults to the test vectors.
+    fn tests_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("eth2.0-spec-tests")
+    }
+
     fn run() {
-        let handler_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-            .join("eth2.0-spec-tests")
+        let handler_path = Self::tests_dir()
             .join("tests")
             .join(Self::config_name())
             .join(Self::fork_name())
@@ -285,6 +289,36 @@ impl<E: EthSpec + TypeName, O: Operation<E>> Handler for OperationsHandler<E, O>
     }
 }
 
+pub struct ForkChoiceHandler<E, H>(PhantomData<(E, H)>);
+
+impl<E: EthSpec + TypeName, H: TypeName> Handler for ForkChoiceHandler<E, H> {
+    type Case = cases::ForkChoiceTest<E>;
+
+    fn config_name() -> &'static str {
+        E::name()
+    }
+
+    fn runner_name() -> &'static str {
+        "fork_choice"
+    }
+
+    fn handler_name() -> String {
+        H::name().into()
+    }
+
+    /// The test vectors of the pinned release do not contain fork choice tests, so they are run
+    /// against the fixtures committed to this crate instead.
+    fn tests_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures")
+    }
+}
+
+// Supported fork choice handlers
+pub struct GetHead;
+type_name!(GetHead, "get_head");
+pub struct OnBlock;
+type_name!(OnBlock, "on_block");
+
 pub struct SszGenericHandler<H>(PhantomData<H>);
 
 impl<H: TypeName> Handler for SszGenericHandler<H> {
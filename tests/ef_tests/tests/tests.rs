@@ -57,6 +57,16 @@ fn sanity_slots() {
     SanitySlotsHandler::<MainnetEthSpec>::run();
 }
 
+#[test]
+fn fork_choice_get_head() {
+    ForkChoiceHandler::<MinimalEthSpec, GetHead>::run();
+}
+
+#[test]
+fn fork_choice_on_block() {
+    ForkChoiceHandler::<MinimalEthSpec, OnBlock>::run();
+}
+
 #[test]
 #[cfg(not(feature = "fake_crypto"))]
 fn bls_aggregate_pubkeys() {
@@ -193,7 +203,7 @@ fn epoch_processing_slashings() {
 
 #[test]
 fn epoch_processing_final_updates() {
-    EpochProcessingHandler::<MainnetEthSpec, FinalUpdates>::run();
+    EpochProcessingHandler::<MinimalEthSpec, FinalUpdates>::run();
     EpochProcessingHandler::<MainnetEthSpec, FinalUpdates>::run();
 }
 
@@ -21,6 +21,9 @@ use web3::{Transport, Web3};
 
 pub const DEPLOYER_ACCOUNTS_INDEX: usize = 0;
 pub const DEPOSIT_ACCOUNTS_INDEX: usize = 0;
+/// The difference between the amount sent and the amount signed by an
+/// `InvalidDeposit::WrongAmount` deposit (1 ETH).
+pub const WRONG_AMOUNT_DIFFERENCE_GWEI: u64 = 1_000_000_000;
 
 /// Provides a dedicated ganache-cli instance with the deposit contract already deployed.
 pub struct GanacheEth1Instance {
@@ -130,47 +133,80 @@ impl DepositContract {
         &self,
         deposit_data: DepositData,
     ) -> impl Future<Item = (), Error = String> {
-        let contract = self.contract.clone();
+        let amount = deposit_data.amount;
+        self.send_deposit(deposit_data, amount)
+    }
+
+    /// Performs a non-blocking deposit which is accepted by the deposit contract, but which is
+    /// invalid as per the eth2 specification.
+    ///
+    /// Useful for testing that invalid deposits are handled by the eth1 caches and genesis.
+    pub fn deposit_invalid_async<E: EthSpec>(
+        &self,
+        mut deposit_data: DepositData,
+        invalid_deposit: InvalidDeposit,
+    ) -> impl Future<Item = (), Error = String> {
+        let amount = match invalid_deposit {
+            InvalidDeposit::BadSignature => {
+                // Sign with a key other than that of the validator.
+                deposit_data.signature =
+                    deposit_data.create_signature(&Keypair::random().sk, &E::default_spec());
+                deposit_data.amount
+            }
+            InvalidDeposit::WrongAmount => deposit_data.amount + WRONG_AMOUNT_DIFFERENCE_GWEI,
+        };
+
+        self.send_deposit(deposit_data, amount)
+    }
+
+    /// Performs many deposits, sent from the same account in a single sequence of transactions.
+    ///
+    /// The account nonce is only read once, with each transaction using the next nonce. This is
+    /// much faster than `deposit_async` for large numbers of deposits.
+    pub fn deposit_multiple(
+        &self,
+        deposits: Vec<DepositData>,
+    ) -> impl Future<Item = (), Error = String> {
+        let contract_address = self.contract.address();
         let web3_1 = self.web3.clone();
+        let web3_2 = self.web3.clone();
 
-        self.web3
-            .eth()
-            .accounts()
-            .map_err(|e| format!("Failed to get accounts: {:?}", e))
-            .and_then(|accounts| {
-                accounts
-                    .get(DEPOSIT_ACCOUNTS_INDEX)
-                    .cloned()
-                    .ok_or_else(|| "Insufficient accounts for deposit".to_string())
-            })
+        self.deposit_account()
             .and_then(move |from| {
-                let tx_request = TransactionRequest {
-                    from,
-                    to: Some(contract.address()),
-                    gas: Some(U256::from(DEPOSIT_GAS)),
-                    gas_price: None,
-                    value: Some(from_gwei(deposit_data.amount)),
-                    // Note: the reason we use this `TransactionRequest` instead of just using the
-                    // function in `self.contract` is so that the `eth1_tx_data` function gets used
-                    // during testing.
-                    //
-                    // It's important that `eth1_tx_data` stays correct and does not suffer from
-                    // code-rot.
-                    data: eth1_tx_data(&deposit_data).map(Into::into).ok(),
-                    nonce: None,
-                    condition: None,
-                };
-
                 web3_1
                     .eth()
-                    .send_transaction(tx_request)
-                    .map_err(|e| format!("Failed to call deposit fn: {:?}", e))
+                    .transaction_count(from, None)
+                    .map_err(|e| format!("Failed to get nonce: {:?}", e))
+                    .map(move |nonce| (from, nonce))
+            })
+            .and_then(move |(from, first_nonce)| {
+                let transactions =
+                    deposits
+                        .into_iter()
+                        .enumerate()
+                        .map(move |(i, deposit_data)| {
+                            let amount = deposit_data.amount;
+                            deposit_transaction(
+                                from,
+                                contract_address,
+                                &deposit_data,
+                                amount,
+                                Some(first_nonce + U256::from(i)),
+                            )
+                        });
+
+                stream::iter_ok(transactions).for_each(move |tx_request| {
+                    web3_2
+                        .eth()
+                        .send_transaction(tx_request)
+                        .map(|_| ())
+                        .map_err(|e| format!("Failed to call deposit fn: {:?}", e))
+                })
             })
-            .map(|_| ())
     }
 
     /// Peforms many deposits, each preceded by a delay.
-    pub fn deposit_multiple(
+    pub fn deposit_with_delays(
         &self,
         deposits: Vec<DelayThenDeposit>,
     ) -> impl Future<Item = (), Error = String> {
@@ -190,6 +226,53 @@ impl DepositContract {
         .collect()
         .map(|_| ())
     }
+
+    /// Sends `deposit_data` to the deposit contract, along with `amount` gwei (which is usually,
+    /// but not necessarily, `deposit_data.amount`).
+    fn send_deposit(
+        &self,
+        deposit_data: DepositData,
+        amount: u64,
+    ) -> impl Future<Item = (), Error = String> {
+        let contract_address = self.contract.address();
+        let web3_1 = self.web3.clone();
+
+        self.deposit_account()
+            .and_then(move |from| {
+                let tx_request =
+                    deposit_transaction(from, contract_address, &deposit_data, amount, None);
+
+                web3_1
+                    .eth()
+                    .send_transaction(tx_request)
+                    .map_err(|e| format!("Failed to call deposit fn: {:?}", e))
+            })
+            .map(|_| ())
+    }
+
+    /// Returns the account which deposits are sent from.
+    fn deposit_account(&self) -> impl Future<Item = Address, Error = String> {
+        self.web3
+            .eth()
+            .accounts()
+            .map_err(|e| format!("Failed to get accounts: {:?}", e))
+            .and_then(|accounts| {
+                accounts
+                    .get(DEPOSIT_ACCOUNTS_INDEX)
+                    .cloned()
+                    .ok_or_else(|| "Insufficient accounts for deposit".to_string())
+            })
+    }
+}
+
+/// A way in which a deposit may be invalid, whilst still being accepted by the deposit contract.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InvalidDeposit {
+    /// The deposit is signed by a key other than that of the validator.
+    BadSignature,
+    /// The amount sent with the deposit differs from the amount that was signed, so the signature
+    /// of the logged deposit is invalid.
+    WrongAmount,
 }
 
 /// Describes a deposit and a delay that should should precede it's submission to the deposit
@@ -202,6 +285,32 @@ pub struct DelayThenDeposit {
     pub deposit: DepositData,
 }
 
+/// Returns a transaction which sends `deposit_data` and `amount` gwei to the deposit contract.
+fn deposit_transaction(
+    from: Address,
+    contract_address: Address,
+    deposit_data: &DepositData,
+    amount: u64,
+    nonce: Option<U256>,
+) -> TransactionRequest {
+    TransactionRequest {
+        from,
+        to: Some(contract_address),
+        gas: Some(U256::from(DEPOSIT_GAS)),
+        gas_price: None,
+        value: Some(from_gwei(amount)),
+        // Note: the reason we use this `TransactionRequest` instead of just using the
+        // function in `self.contract` is so that the `eth1_tx_data` function gets used
+        // during testing.
+        //
+        // It's important that `eth1_tx_data` stays correct and does not suffer from
+        // code-rot.
+        data: eth1_tx_data(deposit_data).map(Into::into).ok(),
+        nonce,
+        condition: None,
+    }
+}
+
 fn from_gwei(gwei: u64) -> U256 {
     U256::from(gwei) * U256::exp10(9)
 }
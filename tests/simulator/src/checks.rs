@@ -0,0 +1,58 @@
+//! Assertions about the state of the simulated network, made once the simulation has run for the
+//! requested number of epochs.
+
+use crate::BeaconNode;
+use types::{Epoch, EthSpec, Slot};
+
+/// Verifies that the head of each node is no more than `max_lag` slots behind `current_slot`
+/// (i.e., that blocks are still being produced and imported).
+pub fn verify_liveness<E: EthSpec>(
+    nodes: &[BeaconNode<E>],
+    current_slot: Slot,
+    max_lag: u64,
+) -> Result<(), String> {
+    for (i, node) in nodes.iter().enumerate() {
+        let head_slot = node
+            .client
+            .beacon_chain()
+            .ok_or_else(|| format!("Node {} does not have a beacon chain", i))?
+            .head()
+            .beacon_block
+            .slot;
+
+        if head_slot + max_lag < current_slot {
+            return Err(format!(
+                "Node {} head slot {} is more than {} slots behind the current slot {}",
+                i, head_slot, max_lag, current_slot
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Verifies that each node has finalized at least `min_finalized_epoch`.
+pub fn verify_finality<E: EthSpec>(
+    nodes: &[BeaconNode<E>],
+    min_finalized_epoch: Epoch,
+) -> Result<(), String> {
+    for (i, node) in nodes.iter().enumerate() {
+        let finalized_epoch = node
+            .client
+            .beacon_chain()
+            .ok_or_else(|| format!("Node {} does not have a beacon chain", i))?
+            .head()
+            .beacon_state
+            .finalized_checkpoint
+            .epoch;
+
+        if finalized_epoch < min_finalized_epoch {
+            return Err(format!(
+                "Node {} finalized epoch {} is prior to the expected epoch {}",
+                i, finalized_epoch, min_finalized_epoch
+            ));
+        }
+    }
+
+    Ok(())
+}
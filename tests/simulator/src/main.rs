@@ -0,0 +1,306 @@
+//! Runs a local testnet of in-process beacon nodes and validator clients, with genesis triggered by
+//! deposits to a deposit contract on a `ganache-cli` eth1 chain.
+//!
+//! Once the network has run for the requested number of epochs, asserts that all nodes are live
+//! and have finalized. Exits with a non-zero code if any assertion fails, so it may be used in CI.
+//!
+//! Requires `ganache-cli` to be available on `$PATH`.
+
+#[macro_use]
+extern crate clap;
+
+mod checks;
+
+use clap::{App, Arg, ArgMatches};
+use eth1_test_rig::GanacheEth1Instance;
+use eth2_config::Eth2Config;
+use node_test_rig::{
+    environment::{Environment, EnvironmentBuilder, RuntimeContext},
+    testing_client_config, ClientConfig, ClientGenesis, LocalBeaconNode, LocalValidatorClient,
+    ProductionClient, ValidatorConfig,
+};
+use std::net::TcpListener;
+use std::process::exit;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::timer::Delay;
+use types::{test_utils::generate_deterministic_keypairs, Epoch, EthSpec, Hash256, MinimalEthSpec};
+
+pub type E = MinimalEthSpec;
+pub type BeaconNode<T> = LocalBeaconNode<ProductionClient<T>>;
+
+/// The eth1 follow distance, for both genesis and the eth1 caches.
+const ETH1_FOLLOW_DISTANCE: u64 = 1;
+/// Genesis occurs between one and two "days" after the eth1 block that triggers it.
+const SECONDS_PER_DAY: u64 = 10;
+/// The first epoch at which the network can be expected to have finalized an epoch.
+const MIN_END_EPOCH: u64 = 4;
+
+fn main() {
+    let matches = cli_app().get_matches();
+
+    match run(&matches) {
+        Ok(()) => println!("Simulation exited successfully"),
+        Err(e) => {
+            eprintln!("Simulation exited with error: {}", e);
+            exit(1)
+        }
+    }
+}
+
+fn cli_app<'a, 'b>() -> App<'a, 'b> {
+    App::new("simulator")
+        .about(
+            "Runs a local testnet of beacon nodes and validator clients, started from a \
+             deposit contract on ganache-cli, and checks that it stays live and finalizes.",
+        )
+        .arg(
+            Arg::with_name("nodes")
+                .long("nodes")
+                .value_name("COUNT")
+                .help("The number of beacon nodes, each with its own validator client.")
+                .takes_value(true)
+                .default_value("4"),
+        )
+        .arg(
+            Arg::with_name("validators-per-node")
+                .long("validators-per-node")
+                .value_name("COUNT")
+                .help("The number of validators managed by each validator client.")
+                .takes_value(true)
+                .default_value("16"),
+        )
+        .arg(
+            Arg::with_name("end-after-epochs")
+                .long("end-after-epochs")
+                .value_name("EPOCHS")
+                .help("The number of epochs after genesis at which the network is checked.")
+                .takes_value(true)
+                .default_value("5"),
+        )
+        .arg(
+            Arg::with_name("speed-up-factor")
+                .long("speed-up-factor")
+                .value_name("FACTOR")
+                .help("Divides the slot duration of the minimal spec by this factor.")
+                .takes_value(true)
+                .default_value("3"),
+        )
+        .arg(
+            Arg::with_name("continue-after-checks")
+                .long("continue-after-checks")
+                .help("Keeps the network running (until Ctrl+C) once the checks have passed."),
+        )
+}
+
+fn run(matches: &ArgMatches) -> Result<(), String> {
+    let num_nodes = value_t!(matches, "nodes", usize).map_err(|e| e.to_string())?;
+    let validators_per_node =
+        value_t!(matches, "validators-per-node", usize).map_err(|e| e.to_string())?;
+    let end_after_epochs = value_t!(matches, "end-after-epochs", u64).map_err(|e| e.to_string())?;
+    let speed_up_factor = value_t!(matches, "speed-up-factor", u64).map_err(|e| e.to_string())?;
+
+    if num_nodes < 1 {
+        return Err("Must have at least one node".into());
+    }
+    if end_after_epochs < MIN_END_EPOCH {
+        return Err(format!(
+            "Must run for at least {} epochs to check finality",
+            MIN_END_EPOCH
+        ));
+    }
+    if speed_up_factor < 1 {
+        return Err("The speed-up factor must be at least 1".into());
+    }
+
+    let validator_count = num_nodes * validators_per_node;
+
+    let mut eth2_config = Eth2Config::minimal();
+    let spec = &mut eth2_config.spec;
+    spec.milliseconds_per_slot /= speed_up_factor;
+    spec.eth1_follow_distance = ETH1_FOLLOW_DISTANCE;
+    spec.min_genesis_active_validator_count = validator_count as u64;
+    spec.min_genesis_time = 0;
+    spec.seconds_per_day = SECONDS_PER_DAY;
+    let spec = spec.clone();
+
+    let mut env = EnvironmentBuilder::minimal()
+        .async_logger("debug")?
+        .multi_threaded_tokio_runtime()?
+        .eth2_config(eth2_config)
+        .build()?;
+
+    /*
+     * Start an eth1 chain and make a deposit for every validator, triggering genesis.
+     */
+    let eth1 = env.runtime().block_on(GanacheEth1Instance::new())?;
+
+    let deposits = generate_deterministic_keypairs(validator_count)
+        .into_iter()
+        .map(|keypair| {
+            eth1.deposit_contract.deposit_helper::<E>(
+                keypair,
+                Hash256::zero(),
+                spec.max_effective_balance,
+            )
+        })
+        .collect();
+    env.runtime()
+        .block_on(eth1.deposit_contract.deposit_multiple(deposits))?;
+
+    // Mine enough blocks for the final deposit to be within the follow distance.
+    for _ in 0..=ETH1_FOLLOW_DISTANCE {
+        env.runtime().block_on(eth1.ganache.evm_mine())?;
+    }
+
+    /*
+     * Start the beacon nodes, each of which determines genesis from the deposit contract.
+     */
+    let mut base_config = testing_client_config();
+    base_config.genesis = ClientGenesis::DepositContract;
+    base_config.eth1.endpoint = eth1.endpoint();
+    base_config.eth1.deposit_contract_address = eth1.deposit_contract.address();
+    base_config.eth1.deposit_contract_deploy_block = 0;
+    base_config.eth1.lowest_cached_block_number = 0;
+    base_config.eth1.follow_distance = ETH1_FOLLOW_DISTANCE;
+
+    let boot_node = BeaconNode::production(
+        env.service_context("boot_node".into()),
+        with_unused_ports(base_config.clone())?,
+    );
+
+    let mut nodes = vec![];
+    for i in 1..num_nodes {
+        let context = env.service_context(format!("node_{}", i));
+        let config = with_unused_ports(base_config.clone())?;
+        nodes.push(new_with_bootnode_via_enr(context, &boot_node, config));
+    }
+    nodes.insert(0, boot_node);
+
+    /*
+     * Start a validator client for each node, using the keypairs that were deposited.
+     */
+    let validators = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| {
+            let context = env.service_context(format!("validator_{}", i));
+            let indices =
+                (i * validators_per_node..(i + 1) * validators_per_node).collect::<Vec<_>>();
+            new_validator_client(
+                &mut env,
+                context,
+                node,
+                ValidatorConfig::default(),
+                &indices,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    /*
+     * Wait until the middle of the first slot of the end epoch, then check the network.
+     */
+    let beacon_chain = nodes[0]
+        .client
+        .beacon_chain()
+        .ok_or_else(|| "Boot node does not have a beacon chain".to_string())?;
+    let genesis_time = beacon_chain.head().beacon_state.genesis_time;
+    let slot_duration = Duration::from_millis(spec.milliseconds_per_slot);
+    let end_slot = Epoch::new(end_after_epochs).start_slot(E::slots_per_epoch());
+
+    let check_time = Duration::from_secs(genesis_time)
+        + slot_duration * end_slot.as_u64() as u32
+        + slot_duration / 2;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| format!("Unable to read system time: {:?}", e))?;
+    let wait = check_time
+        .checked_sub(now)
+        .unwrap_or_else(|| Duration::from_secs(0));
+
+    env.runtime()
+        .block_on(Delay::new(Instant::now() + wait))
+        .map_err(|e| format!("Failed to wait for the end epoch: {:?}", e))?;
+
+    let current_slot = beacon_chain
+        .slot()
+        .map_err(|e| format!("Unable to read the current slot: {:?}", e))?;
+
+    checks::verify_liveness(&nodes, current_slot, E::slots_per_epoch())?;
+    checks::verify_finality(&nodes, Epoch::new(end_after_epochs - 2))?;
+
+    println!(
+        "Checks passed at slot {}: all {} nodes are live and have finalized epoch {}",
+        current_slot,
+        nodes.len(),
+        end_after_epochs - 2
+    );
+
+    if matches.is_present("continue-after-checks") {
+        env.block_until_ctrl_c()?;
+    }
+
+    drop(validators);
+    drop(nodes);
+    drop(eth1);
+
+    env.shutdown_on_idle()
+}
+
+/// Assigns an unused port to the libp2p and discovery listeners of `config`.
+///
+/// Using port `0` is not sufficient, since the node would then advertise port `0` in its ENR and
+/// other nodes would be unable to connect to it.
+fn with_unused_ports(mut config: ClientConfig) -> Result<ClientConfig, String> {
+    let port = TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .map_err(|e| format!("Unable to find an unused port: {:?}", e))?;
+
+    config.network.libp2p_port = port;
+    config.network.discovery_port = port;
+
+    Ok(config)
+}
+
+fn new_with_bootnode_via_enr(
+    context: RuntimeContext<E>,
+    boot_node: &BeaconNode<E>,
+    base_config: ClientConfig,
+) -> BeaconNode<E> {
+    let mut config = base_config;
+    config.network.boot_nodes.push(
+        boot_node
+            .client
+            .enr()
+            .expect("bootnode must have a network"),
+    );
+
+    BeaconNode::production(context, config)
+}
+
+// Note: this function will block until the validator can connect to the beaco node. It is
+// recommended to ensure that the beacon node is running first.
+fn new_validator_client(
+    env: &mut Environment<E>,
+    context: RuntimeContext<E>,
+    beacon_node: &BeaconNode<E>,
+    base_config: ValidatorConfig,
+    keypair_indices: &[usize],
+) -> LocalValidatorClient<E> {
+    let mut config = base_config;
+
+    let socket_addr = beacon_node
+        .client
+        .http_listen_addr()
+        .expect("Must have http started");
+
+    config.http_server = format!("http://{}:{}", socket_addr.ip(), socket_addr.port());
+
+    env.runtime()
+        .block_on(LocalValidatorClient::production_with_insecure_keypairs(
+            context,
+            config,
+            keypair_indices,
+        ))
+        .expect("should start validator")
+}
@@ -8,8 +8,7 @@ use slot_clock::SlotClock;
 use std::collections::HashMap;
 use std::ops::Deref;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
-use tokio::timer::Interval;
+use std::time::Duration;
 use types::{ChainSpec, CommitteeIndex, EthSpec, Slot};
 
 /// Builds an `AttestationService`.
@@ -120,19 +119,12 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationService<T, E> {
         let context = &self.context;
         let log = context.log.clone();
 
-        let duration_to_next_slot = self
+        let slot_duration = Duration::from_millis(spec.milliseconds_per_slot);
+        let interval = self
             .slot_clock
-            .duration_to_next_slot()
+            .slot_interval(slot_duration / 3)
             .ok_or_else(|| "Unable to determine duration to next slot".to_string())?;
 
-        let interval = {
-            let slot_duration = Duration::from_millis(spec.milliseconds_per_slot);
-            Interval::new(
-                Instant::now() + duration_to_next_slot + slot_duration / 3,
-                slot_duration,
-            )
-        };
-
         let (exit_signal, exit_fut) = exit_future::signal();
         let service = self.clone();
         let log_1 = log.clone();
@@ -170,6 +162,7 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationService<T, E> {
                         .then(|_| Ok(())),
                 )
                 .map(move |_| info!(log_3, "Shutdown complete")),
+            "attestation_service",
         );
 
         Ok(exit_signal)
@@ -204,11 +197,11 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationService<T, E> {
             .into_iter()
             .for_each(|(committee_index, validator_duties)| {
                 // Spawn a separate task for each attestation.
-                service.context.executor.spawn(self.clone().do_attestation(
-                    slot,
-                    committee_index,
-                    validator_duties,
-                ));
+                service.context.executor.spawn(
+                    self.clone()
+                        .do_attestation(slot, committee_index, validator_duties),
+                    "attestation_producer",
+                );
             });
 
         Ok(())
@@ -3,17 +3,31 @@ use environment::RuntimeContext;
 use exit_future::Signal;
 use futures::{stream, Future, IntoFuture, Stream};
 use remote_beacon_node::{PublishStatus, RemoteBeaconNode};
-use slog::{crit, error, info, trace};
+use slog::{crit, debug, error, info, trace, warn};
 use slot_clock::SlotClock;
 use std::ops::Deref;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::timer::Interval;
-use types::{ChainSpec, EthSpec};
+use types::{EthSpec, Slot};
 
 /// Delay this period of time after the slot starts. This allows the node to process the new slot.
 const TIME_DELAY_FROM_SLOT: Duration = Duration::from_millis(100);
 
+/// The time after the start of the slot at which each stage of a block proposal completed.
+///
+/// Producing the block includes loading and advancing the state on the beacon node, so a late
+/// `produced` indicates a slow beacon node whilst a late `signed` indicates a slow signer.
+struct ProposalTimes {
+    /// The proposal began (i.e., the duty was read).
+    duty: Duration,
+    /// The block was returned by the beacon node.
+    produced: Duration,
+    /// The block was signed.
+    signed: Duration,
+    /// The beacon node accepted the block for publication.
+    published: Duration,
+}
+
 /// Builds a `BlockService`.
 pub struct BlockServiceBuilder<T, E: EthSpec> {
     duties_service: Option<DutiesService<T, E>>,
@@ -21,6 +35,7 @@ pub struct BlockServiceBuilder<T, E: EthSpec> {
     slot_clock: Option<Arc<T>>,
     beacon_node: Option<RemoteBeaconNode<E>>,
     context: Option<RuntimeContext<E>>,
+    late_block_threshold: Option<Duration>,
 }
 
 impl<T: SlotClock + 'static, E: EthSpec> BlockServiceBuilder<T, E> {
@@ -31,6 +46,7 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockServiceBuilder<T, E> {
             slot_clock: None,
             beacon_node: None,
             context: None,
+            late_block_threshold: None,
         }
     }
 
@@ -59,7 +75,21 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockServiceBuilder<T, E> {
         self
     }
 
+    /// Blocks published later than `threshold` after the start of their slot are logged with a
+    /// warning. If `None`, one third of a slot is used.
+    pub fn late_block_threshold(mut self, threshold: Option<Duration>) -> Self {
+        self.late_block_threshold = threshold;
+        self
+    }
+
     pub fn build(self) -> Result<BlockService<T, E>, String> {
+        let slot_clock = self
+            .slot_clock
+            .ok_or_else(|| "Cannot build BlockService without slot_clock")?;
+        let late_block_threshold = self
+            .late_block_threshold
+            .unwrap_or_else(|| slot_clock.slot_duration() / 3);
+
         Ok(BlockService {
             inner: Arc::new(Inner {
                 duties_service: self
@@ -68,15 +98,14 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockServiceBuilder<T, E> {
                 validator_store: self
                     .validator_store
                     .ok_or_else(|| "Cannot build BlockService without validator_store")?,
-                slot_clock: self
-                    .slot_clock
-                    .ok_or_else(|| "Cannot build BlockService without slot_clock")?,
+                slot_clock,
                 beacon_node: self
                     .beacon_node
                     .ok_or_else(|| "Cannot build BlockService without beacon_node")?,
                 context: self
                     .context
                     .ok_or_else(|| "Cannot build BlockService without runtime_context")?,
+                late_block_threshold,
             }),
         })
     }
@@ -89,6 +118,7 @@ pub struct Inner<T, E: EthSpec> {
     slot_clock: Arc<T>,
     beacon_node: RemoteBeaconNode<E>,
     context: RuntimeContext<E>,
+    late_block_threshold: Duration,
 }
 
 /// Attempts to produce attestations for any block producer(s) at the start of the epoch.
@@ -114,22 +144,14 @@ impl<T, E: EthSpec> Deref for BlockService<T, E> {
 
 impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
     /// Starts the service that periodically attempts to produce blocks.
-    pub fn start_update_service(&self, spec: &ChainSpec) -> Result<Signal, String> {
+    pub fn start_update_service(&self) -> Result<Signal, String> {
         let log = self.context.log.clone();
 
-        let duration_to_next_slot = self
+        let interval = self
             .slot_clock
-            .duration_to_next_slot()
+            .slot_interval(TIME_DELAY_FROM_SLOT)
             .ok_or_else(|| "Unable to determine duration to next slot".to_string())?;
 
-        let interval = {
-            let slot_duration = Duration::from_millis(spec.milliseconds_per_slot);
-            Interval::new(
-                Instant::now() + duration_to_next_slot + TIME_DELAY_FROM_SLOT,
-                slot_duration,
-            )
-        };
-
         let (exit_signal, exit_fut) = exit_future::signal();
         let service = self.clone();
         let log_1 = log.clone();
@@ -151,6 +173,7 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
                         .then(|_| Ok(())),
                 )
                 .map(move |_| info!(log_2, "Shutdown complete")),
+            "block_service",
         );
 
         Ok(exit_signal)
@@ -187,14 +210,24 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
                     )
                 }
 
+                // Stages of the proposal are timed relative to the start of the slot.
+                let slot_start = service
+                    .slot_clock
+                    .duration_since_slot_start()
+                    .and_then(|since_start| Instant::now().checked_sub(since_start))
+                    .unwrap_or_else(Instant::now);
+
                 stream::unfold(iter, move |mut block_producers| {
                     let log_1 = service.context.log.clone();
                     let log_2 = service.context.log.clone();
                     let service_1 = service.clone();
                     let service_2 = service.clone();
                     let service_3 = service.clone();
+                    let service_4 = service.clone();
 
                     block_producers.next().map(move |validator_pubkey| {
+                        let duty = slot_start.elapsed();
+
                         service_1
                             .validator_store
                             .randao_reveal(&validator_pubkey, slot.epoch(E::slots_per_epoch()))
@@ -206,6 +239,7 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
                                     .http
                                     .validator()
                                     .produce_block(slot, randao_reveal)
+                                    .map(move |block| (block, slot_start.elapsed()))
                                     .map_err(|e| {
                                         format!(
                                             "Error from beacon node when producing block: {:?}",
@@ -213,19 +247,28 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
                                         )
                                     })
                             })
-                            .and_then(move |block| {
+                            .and_then(move |(block, produced)| {
                                 service_2
                                     .validator_store
                                     .sign_block(&validator_pubkey, block)
+                                    .map(|block| (block, produced, slot_start.elapsed()))
                                     .ok_or_else(|| "Unable to sign block".to_string())
                             })
-                            .and_then(move |block| {
+                            .and_then(move |(block, produced, signed)| {
                                 service_3
                                     .beacon_node
                                     .http
                                     .validator()
                                     .publish_block(block.clone())
-                                    .map(|publish_status| (block, publish_status))
+                                    .map(move |publish_status| {
+                                        let times = ProposalTimes {
+                                            duty,
+                                            produced,
+                                            signed,
+                                            published: slot_start.elapsed(),
+                                        };
+                                        (block, publish_status, times)
+                                    })
                                     .map_err(|e| {
                                         format!(
                                             "Error from beacon node when publishing block: {:?}",
@@ -233,23 +276,27 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
                                         )
                                     })
                             })
-                            .map(move |(block, publish_status)| match publish_status {
-                                PublishStatus::Valid => info!(
-                                    log_1,
-                                    "Successfully published block";
-                                    "deposits" => block.body.deposits.len(),
-                                    "attestations" => block.body.attestations.len(),
-                                    "slot" => block.slot.as_u64(),
-                                ),
-                                PublishStatus::Invalid(msg) => crit!(
-                                    log_1,
-                                    "Published block was invalid";
-                                    "message" => msg,
-                                    "slot" => block.slot.as_u64(),
-                                ),
-                                PublishStatus::Unknown => {
-                                    crit!(log_1, "Unknown condition when publishing block")
+                            .map(move |(block, publish_status, times)| {
+                                match publish_status {
+                                    PublishStatus::Valid => info!(
+                                        log_1,
+                                        "Successfully published block";
+                                        "deposits" => block.body.deposits.len(),
+                                        "attestations" => block.body.attestations.len(),
+                                        "slot" => block.slot.as_u64(),
+                                    ),
+                                    PublishStatus::Invalid(msg) => crit!(
+                                        log_1,
+                                        "Published block was invalid";
+                                        "message" => msg,
+                                        "slot" => block.slot.as_u64(),
+                                    ),
+                                    PublishStatus::Unknown => {
+                                        crit!(log_1, "Unknown condition when publishing block")
+                                    }
                                 }
+
+                                service_4.log_proposal_times(block.slot, &times);
                             })
                             .map_err(move |e| {
                                 crit!(
@@ -265,4 +312,45 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
                 .map(|_| ())
             })
     }
+
+    /// Logs the time taken by each stage of a block proposal, with a warning if the block was
+    /// published after the `late_block_threshold`.
+    fn log_proposal_times(&self, slot: Slot, times: &ProposalTimes) {
+        let log = &self.context.log;
+
+        // The time spent in each stage, from the end of the previous stage.
+        let stage_ms = |end: Duration, start: Duration| {
+            end.checked_sub(start)
+                .map(|duration| duration.as_millis() as u64)
+                .unwrap_or(0)
+        };
+        let produce_ms = stage_ms(times.produced, times.duty);
+        let sign_ms = stage_ms(times.signed, times.produced);
+        let publish_ms = stage_ms(times.published, times.signed);
+
+        if times.published > self.late_block_threshold {
+            warn!(
+                log,
+                "Block published late";
+                "slot" => slot.as_u64(),
+                "published_ms" => times.published.as_millis() as u64,
+                "threshold_ms" => self.late_block_threshold.as_millis() as u64,
+                "duty_ms" => times.duty.as_millis() as u64,
+                "produce_ms" => produce_ms,
+                "sign_ms" => sign_ms,
+                "publish_ms" => publish_ms,
+            );
+        } else {
+            debug!(
+                log,
+                "Block proposal times";
+                "slot" => slot.as_u64(),
+                "published_ms" => times.published.as_millis() as u64,
+                "duty_ms" => times.duty.as_millis() as u64,
+                "produce_ms" => produce_ms,
+                "sign_ms" => sign_ms,
+                "publish_ms" => publish_ms,
+            );
+        }
+    }
 }
@@ -13,6 +13,31 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                 .default_value(&DEFAULT_HTTP_SERVER)
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("beacon-node-api-token")
+                .long("beacon-node-api-token")
+                .value_name("TOKEN")
+                .help("The bearer token required by a BeaconNode started with --api-token.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("late-block-threshold")
+                .long("late-block-threshold")
+                .value_name("MILLIS")
+                .help("Log a warning, with the time taken by each stage of the proposal, when a \
+                       block is published more than this many milliseconds after the start of \
+                       its slot. Defaults to one third of a slot.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("webhooks")
+                .long("webhooks")
+                .value_name("URL LIST")
+                .help("One or more comma-delimited URLs to which an alert is POSTed as JSON if a \
+                       validator is found to be slashed. The validator is always disabled and a \
+                       critical message is logged, regardless of this flag.")
+                .takes_value(true),
+        )
         /*
          * The "testnet" sub-command.
          *
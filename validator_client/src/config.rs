@@ -31,6 +31,16 @@ pub struct Config {
     ///
     /// Should be similar to `http://localhost:8080`
     pub http_server: String,
+    /// The bearer token sent with `POST` requests to a beacon node started with `--api-token`.
+    #[serde(default)]
+    pub beacon_node_api_token: Option<String>,
+    /// Blocks published later than this many milliseconds after the start of their slot are
+    /// logged with a warning. If `None`, one third of a slot is used.
+    #[serde(default)]
+    pub late_block_threshold_millis: Option<u64>,
+    /// URLs to which an alert is POSTed when a validator is found to be slashed.
+    #[serde(default)]
+    pub webhooks: Vec<String>,
 }
 
 impl Default for Config {
@@ -40,6 +50,9 @@ impl Default for Config {
             data_dir: PathBuf::from(".lighthouse/validators"),
             key_source: <_>::default(),
             http_server: DEFAULT_HTTP_SERVER.to_string(),
+            beacon_node_api_token: None,
+            late_block_threshold_millis: None,
+            webhooks: vec![],
         }
     }
 }
@@ -50,10 +63,28 @@ impl Config {
     pub fn from_cli(cli_args: &ArgMatches) -> Result<Config, String> {
         let mut config = Config::default();
 
+        config.data_dir = clap_utils::parse_validators_dir(cli_args)?;
+
         if let Some(server) = cli_args.value_of("server") {
             config.http_server = server.to_string();
         }
 
+        if let Some(token) = cli_args.value_of("beacon-node-api-token") {
+            config.beacon_node_api_token = Some(token.to_string());
+        }
+
+        if let Some(threshold) = cli_args.value_of("late-block-threshold") {
+            config.late_block_threshold_millis = Some(
+                threshold
+                    .parse::<u64>()
+                    .map_err(|e| format!("Unable to parse late-block-threshold: {:?}", e))?,
+            );
+        }
+
+        if let Some(webhooks) = cli_args.value_of("webhooks") {
+            config.webhooks = webhooks.split(',').map(String::from).collect();
+        }
+
         let config = match cli_args.subcommand() {
             ("testnet", Some(sub_cli_args)) => {
                 if cli_args.is_present("eth2-config") && sub_cli_args.is_present("bootstrap") {
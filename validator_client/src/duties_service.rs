@@ -9,9 +9,8 @@ use slot_clock::SlotClock;
 use std::collections::HashMap;
 use std::ops::Deref;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
-use tokio::timer::Interval;
-use types::{ChainSpec, Epoch, EthSpec, PublicKey, Slot};
+use std::time::Duration;
+use types::{Epoch, EthSpec, PublicKey, Slot};
 
 /// Delay this period of time after the slot starts. This allows the node to process the new slot.
 const TIME_DELAY_FROM_SLOT: Duration = Duration::from_millis(100);
@@ -233,29 +232,23 @@ impl<T: SlotClock + 'static, E: EthSpec> DutiesService<T, E> {
     }
 
     /// Start the service that periodically polls the beacon node for validator duties.
-    pub fn start_update_service(&self, spec: &ChainSpec) -> Result<Signal, String> {
+    pub fn start_update_service(&self) -> Result<Signal, String> {
         let log = self.context.log.clone();
 
-        let duration_to_next_slot = self
+        let interval = self
             .slot_clock
-            .duration_to_next_slot()
+            .slot_interval(TIME_DELAY_FROM_SLOT)
             .ok_or_else(|| "Unable to determine duration to next slot".to_string())?;
 
-        let interval = {
-            let slot_duration = Duration::from_millis(spec.milliseconds_per_slot);
-            Interval::new(
-                Instant::now() + duration_to_next_slot + TIME_DELAY_FROM_SLOT,
-                slot_duration,
-            )
-        };
-
         let (exit_signal, exit_fut) = exit_future::signal();
         let service = self.clone();
         let log_1 = log.clone();
         let log_2 = log.clone();
 
         // Run an immediate update before starting the updater service.
-        self.context.executor.spawn(service.clone().do_update());
+        self.context
+            .executor
+            .spawn(service.clone().do_update(), "duties_service");
 
         self.context.executor.spawn(
             exit_fut
@@ -273,6 +266,7 @@ impl<T: SlotClock + 'static, E: EthSpec> DutiesService<T, E> {
                         .then(|_| Ok(())),
                 )
                 .map(move |_| info!(log_2, "Shutdown complete")),
+            "duties_service",
         );
 
         Ok(exit_signal)
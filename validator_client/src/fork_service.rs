@@ -123,7 +123,9 @@ impl<T: SlotClock + 'static, E: EthSpec> ForkService<T, E> {
         let log_2 = log.clone();
 
         // Run an immediate update before starting the updater service.
-        self.context.executor.spawn(service.clone().do_update());
+        self.context
+            .executor
+            .spawn(service.clone().do_update(), "fork_service");
 
         self.context.executor.spawn(
             exit_fut
@@ -141,6 +143,7 @@ impl<T: SlotClock + 'static, E: EthSpec> ForkService<T, E> {
                         .then(|_| Ok(())),
                 )
                 .map(move |_| info!(log_2, "Shutdown complete")),
+            "fork_service",
         );
 
         Ok(exit_signal)
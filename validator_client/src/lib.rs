@@ -1,9 +1,15 @@
+#[macro_use]
+extern crate lazy_static;
+
 mod attestation_service;
 mod block_service;
 mod cli;
 mod config;
 mod duties_service;
 mod fork_service;
+mod metrics;
+mod performance_service;
+mod slashing_service;
 mod validator_store;
 
 pub mod validator_directory;
@@ -22,13 +28,17 @@ use futures::{
     future::{self, loop_fn, Loop},
     Future, IntoFuture,
 };
+use notifier::Notifier;
+use performance_service::{PerformanceService, PerformanceServiceBuilder};
 use remote_beacon_node::RemoteBeaconNode;
-use slog::{error, info, Logger};
+use slashing_service::{SlashingService, SlashingServiceBuilder};
+use slog::{error, info, warn, Logger};
 use slot_clock::SlotClock;
 use slot_clock::SystemTimeSlotClock;
 use std::time::{Duration, Instant};
 use tokio::timer::Delay;
 use types::EthSpec;
+use validator_directory::ValidatorsDirLock;
 use validator_store::ValidatorStore;
 
 /// The interval between attempts to contact the beacon node during startup.
@@ -43,7 +53,11 @@ pub struct ProductionValidatorClient<T: EthSpec> {
     fork_service: ForkService<SystemTimeSlotClock, T>,
     block_service: BlockService<SystemTimeSlotClock, T>,
     attestation_service: AttestationService<SystemTimeSlotClock, T>,
+    slashing_service: SlashingService<SystemTimeSlotClock, T>,
+    performance_service: PerformanceService<SystemTimeSlotClock, T>,
     exit_signals: Vec<Signal>,
+    /// Held whilst validators are loaded from disk, released when `self` is dropped.
+    _validators_dir_lock: Option<ValidatorsDirLock>,
 }
 
 impl<T: EthSpec> ProductionValidatorClient<T> {
@@ -68,6 +82,7 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
         let log_1 = context.log.clone();
         let log_2 = context.log.clone();
         let log_3 = context.log.clone();
+        let log_4 = context.log.clone();
 
         info!(
             log_1,
@@ -77,9 +92,11 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
         );
 
         RemoteBeaconNode::new_with_timeout(config.http_server.clone(), HTTP_TIMEOUT)
+            .map(|beacon_node| beacon_node.api_token(config.beacon_node_api_token.clone()))
             .map_err(|e| format!("Unable to init beacon node http client: {}", e))
             .into_future()
             .and_then(move |beacon_node| wait_for_node(beacon_node, log_2))
+            .and_then(move |beacon_node| wait_for_sync(beacon_node, log_4))
             .and_then(|beacon_node| {
                 beacon_node
                     .http
@@ -126,6 +143,12 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
                     .runtime_context(context.service_context("fork"))
                     .build()?;
 
+                // Prevent the validators from being exported whilst they are in use.
+                let validators_dir_lock = match &config.key_source {
+                    KeySource::Disk => Some(ValidatorsDirLock::acquire(&config.data_dir)?),
+                    KeySource::InsecureKeypairs(_) => None,
+                };
+
                 let validator_store: ValidatorStore<SystemTimeSlotClock, T> =
                     match &config.key_source {
                         // Load pre-existing validators from the data dir.
@@ -169,14 +192,38 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
                     .validator_store(validator_store.clone())
                     .beacon_node(beacon_node.clone())
                     .runtime_context(context.service_context("block"))
+                    .late_block_threshold(
+                        config
+                            .late_block_threshold_millis
+                            .map(Duration::from_millis),
+                    )
                     .build()?;
 
                 let attestation_service = AttestationServiceBuilder::new()
                     .duties_service(duties_service.clone())
+                    .slot_clock(slot_clock.clone())
+                    .validator_store(validator_store.clone())
+                    .beacon_node(beacon_node.clone())
+                    .runtime_context(context.service_context("attestation"))
+                    .build()?;
+
+                let slashing_service = SlashingServiceBuilder::new()
+                    .slot_clock(slot_clock.clone())
+                    .validator_store(validator_store.clone())
+                    .beacon_node(beacon_node.clone())
+                    .runtime_context(context.service_context("slashing"))
+                    .notifier(Notifier::new(
+                        config.webhooks.clone(),
+                        "validator_client",
+                        context.log.clone(),
+                    ))
+                    .build()?;
+
+                let performance_service = PerformanceServiceBuilder::new()
                     .slot_clock(slot_clock)
                     .validator_store(validator_store)
                     .beacon_node(beacon_node)
-                    .runtime_context(context.service_context("attestation"))
+                    .runtime_context(context.service_context("performance"))
                     .build()?;
 
                 Ok(Self {
@@ -185,7 +232,10 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
                     fork_service,
                     block_service,
                     attestation_service,
+                    slashing_service,
+                    performance_service,
                     exit_signals: vec![],
+                    _validators_dir_lock: validators_dir_lock,
                 })
             })
     }
@@ -193,7 +243,7 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
     pub fn start_service(&mut self) -> Result<(), String> {
         let duties_exit = self
             .duties_service
-            .start_update_service(&self.context.eth2_config.spec)
+            .start_update_service()
             .map_err(|e| format!("Unable to start duties service: {}", e))?;
 
         let fork_exit = self
@@ -203,7 +253,7 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
 
         let block_exit = self
             .block_service
-            .start_update_service(&self.context.eth2_config.spec)
+            .start_update_service()
             .map_err(|e| format!("Unable to start block service: {}", e))?;
 
         let attestation_exit = self
@@ -211,7 +261,24 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
             .start_update_service(&self.context.eth2_config.spec)
             .map_err(|e| format!("Unable to start attestation service: {}", e))?;
 
-        self.exit_signals = vec![duties_exit, fork_exit, block_exit, attestation_exit];
+        let slashing_exit = self
+            .slashing_service
+            .start_update_service()
+            .map_err(|e| format!("Unable to start slashing service: {}", e))?;
+
+        let performance_exit = self
+            .performance_service
+            .start_update_service(&self.context.eth2_config.spec)
+            .map_err(|e| format!("Unable to start performance service: {}", e))?;
+
+        self.exit_signals = vec![
+            duties_exit,
+            fork_exit,
+            block_exit,
+            attestation_exit,
+            slashing_exit,
+            performance_exit,
+        ];
 
         Ok(())
     }
@@ -264,3 +331,62 @@ fn wait_for_node<E: EthSpec>(
     })
     .map(|_| beacon_node)
 }
+
+/// Request the sync status from the node, looping back and trying again whilst the node is range
+/// syncing. Exit once the node is synced, or has no peers to sync from.
+///
+/// Blocks cannot be produced, and attestations would be made to a stale head, whilst the node is
+/// far behind its peers.
+fn wait_for_sync<E: EthSpec>(
+    beacon_node: RemoteBeaconNode<E>,
+    log: Logger,
+) -> impl Future<Item = RemoteBeaconNode<E>, Error = String> {
+    loop_fn(beacon_node.clone(), move |beacon_node| {
+        let log = log.clone();
+        beacon_node
+            .clone()
+            .http
+            .node()
+            .get_syncing()
+            .map_err(|e| format!("{:?}", e))
+            .then(move |result| {
+                let future: Box<dyn Future<Item = Loop<_, _>, Error = String> + Send> = match result
+                {
+                    Ok(ref syncing) if !syncing.sync_state.is_syncing() => {
+                        info!(
+                            log,
+                            "Beacon node is synced";
+                            "sync_state" => format!("{:?}", syncing.sync_state),
+                        );
+
+                        Box::new(future::ok(Loop::Break(beacon_node)))
+                    }
+                    result => {
+                        match result {
+                            Ok(syncing) => warn!(
+                                log,
+                                "Waiting for beacon node to sync";
+                                "sync_state" => format!("{:?}", syncing.sync_state),
+                                "current_slot" => syncing.sync_status.current_slot,
+                                "highest_slot" => syncing.sync_status.highest_slot,
+                            ),
+                            Err(e) => error!(
+                                log,
+                                "Unable to read sync status of beacon node";
+                                "error" => e,
+                            ),
+                        }
+
+                        Box::new(
+                            Delay::new(Instant::now() + RETRY_DELAY)
+                                .map_err(|e| format!("Failed to trigger delay: {:?}", e))
+                                .and_then(|_| future::ok(Loop::Continue(beacon_node))),
+                        )
+                    }
+                };
+
+                future
+            })
+    })
+    .map(|_| beacon_node)
+}
@@ -0,0 +1,11 @@
+pub use lighthouse_metrics::*;
+
+lazy_static! {
+    /*
+     * Slashing service
+     */
+    pub static ref DISABLED_VALIDATORS_TOTAL: Result<IntGauge> = try_create_int_gauge(
+        "vc_disabled_validators_total",
+        "Count of validators which are disabled from signing because they have been slashed"
+    );
+}
@@ -0,0 +1,290 @@
+use crate::validator_store::ValidatorStore;
+use environment::RuntimeContext;
+use exit_future::Signal;
+use futures::future::{self, Either};
+use futures::{Future, Stream};
+use parking_lot::Mutex;
+use remote_beacon_node::{RemoteBeaconNode, ValidatorInclusionResponse};
+use slog::{crit, info, warn};
+use slot_clock::SlotClock;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::timer::Interval;
+use types::{ChainSpec, Epoch, EthSpec, PublicKey};
+
+/// Delay this period of time after the epoch starts. This allows the node to process the first
+/// block of the epoch.
+const TIME_DELAY_FROM_SLOT: Duration = Duration::from_millis(500);
+
+/// Builds a `PerformanceService`.
+pub struct PerformanceServiceBuilder<T, E: EthSpec> {
+    validator_store: Option<ValidatorStore<T, E>>,
+    slot_clock: Option<T>,
+    beacon_node: Option<RemoteBeaconNode<E>>,
+    context: Option<RuntimeContext<E>>,
+}
+
+impl<T: SlotClock + 'static, E: EthSpec> PerformanceServiceBuilder<T, E> {
+    pub fn new() -> Self {
+        Self {
+            validator_store: None,
+            slot_clock: None,
+            beacon_node: None,
+            context: None,
+        }
+    }
+
+    pub fn validator_store(mut self, store: ValidatorStore<T, E>) -> Self {
+        self.validator_store = Some(store);
+        self
+    }
+
+    pub fn slot_clock(mut self, slot_clock: T) -> Self {
+        self.slot_clock = Some(slot_clock);
+        self
+    }
+
+    pub fn beacon_node(mut self, beacon_node: RemoteBeaconNode<E>) -> Self {
+        self.beacon_node = Some(beacon_node);
+        self
+    }
+
+    pub fn runtime_context(mut self, context: RuntimeContext<E>) -> Self {
+        self.context = Some(context);
+        self
+    }
+
+    pub fn build(self) -> Result<PerformanceService<T, E>, String> {
+        Ok(PerformanceService {
+            inner: Arc::new(Inner {
+                validator_store: self
+                    .validator_store
+                    .ok_or_else(|| "Cannot build PerformanceService without validator_store")?,
+                slot_clock: self
+                    .slot_clock
+                    .ok_or_else(|| "Cannot build PerformanceService without slot_clock")?,
+                beacon_node: self
+                    .beacon_node
+                    .ok_or_else(|| "Cannot build PerformanceService without beacon_node")?,
+                context: self
+                    .context
+                    .ok_or_else(|| "Cannot build PerformanceService without runtime_context")?,
+                previous_balances: Mutex::new(HashMap::new()),
+            }),
+        })
+    }
+}
+
+/// Helper to minimise `Arc` usage.
+pub struct Inner<T, E: EthSpec> {
+    validator_store: ValidatorStore<T, E>,
+    slot_clock: T,
+    beacon_node: RemoteBeaconNode<E>,
+    context: RuntimeContext<E>,
+    /// The balance of each validator as of the last summary, used to compute balance changes.
+    previous_balances: Mutex<HashMap<PublicKey, u64>>,
+}
+
+/// The performance of the validators in the `ValidatorStore` during a single epoch.
+#[derive(Debug, Default, PartialEq)]
+struct EpochPerformance {
+    /// The count of validators which were active during the epoch.
+    active: u64,
+    /// The count of active validators with an attestation included on-chain.
+    included: u64,
+    /// The count of included attestations which voted for the correct head.
+    head_hits: u64,
+    /// The sum of the inclusion delays of the `included` attestations.
+    total_inclusion_delay: u64,
+    /// The change in the total balance of the validators since the previous summary (usually one
+    /// epoch earlier), or `None` if there was no previous summary for any of them.
+    balance_change: Option<i64>,
+}
+
+impl EpochPerformance {
+    /// Summarises `responses`, updating `previous_balances` to the balances they contain.
+    fn from_responses(
+        responses: &[ValidatorInclusionResponse],
+        previous_balances: &mut HashMap<PublicKey, u64>,
+    ) -> Self {
+        let mut performance = EpochPerformance::default();
+
+        for response in responses {
+            let inclusion = match &response.inclusion {
+                Some(inclusion) => inclusion,
+                None => continue,
+            };
+
+            if inclusion.is_active {
+                performance.active += 1;
+            }
+
+            if let Some(delay) = inclusion.inclusion_delay {
+                performance.included += 1;
+                performance.total_inclusion_delay += delay;
+
+                if inclusion.is_head_attester {
+                    performance.head_hits += 1;
+                }
+            }
+
+            if let Some(previous) =
+                previous_balances.insert(response.validator_pubkey.clone(), inclusion.balance)
+            {
+                let change = inclusion.balance as i64 - previous as i64;
+                performance.balance_change = Some(performance.balance_change.unwrap_or(0) + change);
+            }
+        }
+
+        performance
+    }
+
+    /// The fraction of active validators with an included attestation.
+    fn hit_rate(&self) -> f64 {
+        if self.active == 0 {
+            0.0
+        } else {
+            self.included as f64 / self.active as f64
+        }
+    }
+
+    /// The mean inclusion delay of the included attestations, in slots.
+    fn average_inclusion_delay(&self) -> Option<f64> {
+        if self.included == 0 {
+            None
+        } else {
+            Some(self.total_inclusion_delay as f64 / self.included as f64)
+        }
+    }
+}
+
+/// Logs a summary of the attestation performance and balance changes of the validators in the
+/// `ValidatorStore`, once per epoch.
+///
+/// An epoch is summarised at the start of the epoch two after it, once every attestation for it has
+/// had a chance to be included.
+pub struct PerformanceService<T, E: EthSpec> {
+    inner: Arc<Inner<T, E>>,
+}
+
+impl<T, E: EthSpec> Clone for PerformanceService<T, E> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T, E: EthSpec> Deref for PerformanceService<T, E> {
+    type Target = Inner<T, E>;
+
+    fn deref(&self) -> &Self::Target {
+        self.inner.deref()
+    }
+}
+
+impl<T: SlotClock + 'static, E: EthSpec> PerformanceService<T, E> {
+    /// Starts the service that summarises the performance of the validators each epoch.
+    pub fn start_update_service(&self, spec: &ChainSpec) -> Result<Signal, String> {
+        let log = self.context.log.clone();
+
+        let duration_to_next_epoch = self
+            .slot_clock
+            .duration_to_next_epoch(E::slots_per_epoch())
+            .ok_or_else(|| "Unable to determine duration to next epoch".to_string())?;
+
+        let interval = {
+            let slot_duration = Duration::from_millis(spec.milliseconds_per_slot);
+            Interval::new(
+                Instant::now() + duration_to_next_epoch + TIME_DELAY_FROM_SLOT,
+                slot_duration * E::slots_per_epoch() as u32,
+            )
+        };
+
+        let (exit_signal, exit_fut) = exit_future::signal();
+        let service = self.clone();
+        let log_1 = log.clone();
+        let log_2 = log.clone();
+
+        self.context.executor.spawn(
+            exit_fut
+                .until(
+                    interval
+                        .map_err(move |e| {
+                            crit! {
+                                log_1,
+                                "Timer thread failed";
+                                "error" => format!("{}", e)
+                            }
+                        })
+                        .for_each(move |_| service.do_update())
+                        // Prevent any errors from escaping and stopping the interval.
+                        .then(|_| Ok(())),
+                )
+                .map(move |_| info!(log_2, "Shutdown complete")),
+            "performance_service",
+        );
+
+        Ok(exit_signal)
+    }
+
+    /// Requests the inclusion of all validators during the epoch before last and logs a summary.
+    fn do_update(&self) -> impl Future<Item = (), Error = ()> {
+        let service = self.clone();
+        let log = self.context.log.clone();
+
+        let pubkeys = self.validator_store.voting_pubkeys();
+        let epoch = match self
+            .slot_clock
+            .now()
+            .map(|slot| slot.epoch(E::slots_per_epoch()))
+        {
+            Some(epoch) if epoch >= 2 && !pubkeys.is_empty() => epoch - 2,
+            // There is no epoch which can be summarised yet.
+            _ => return Either::A(future::ok(())),
+        };
+
+        Either::B(
+            self.beacon_node
+                .http
+                .lighthouse()
+                .get_validator_inclusion(epoch, &pubkeys)
+                .map(move |responses| service.log_summary(epoch, &responses))
+                .map_err(move |e| {
+                    warn!(
+                        log,
+                        "Unable to summarise validator performance";
+                        "error" => format!("{:?}", e)
+                    )
+                })
+                // Returning an error will stop the interval. This is not desired, a single failure
+                // should not stop all future attempts.
+                .then(|_| Ok(())),
+        )
+    }
+
+    /// Logs a single line summarising the performance of the validators during `epoch`.
+    fn log_summary(&self, epoch: Epoch, responses: &[ValidatorInclusionResponse]) {
+        let performance =
+            EpochPerformance::from_responses(responses, &mut self.previous_balances.lock());
+
+        info!(
+            self.context.log,
+            "Validator performance summary";
+            "epoch" => epoch,
+            "active_validators" => performance.active,
+            "hit_rate" => format!("{:.1}%", performance.hit_rate() * 100.0),
+            "head_hits" => performance.head_hits,
+            "avg_inclusion_delay" => performance
+                .average_inclusion_delay()
+                .map(|delay| format!("{:.2}", delay))
+                .unwrap_or_else(|| "n/a".to_string()),
+            "balance_change_gwei" => performance
+                .balance_change
+                .map(|change| change.to_string())
+                .unwrap_or_else(|| "n/a".to_string()),
+        );
+    }
+}
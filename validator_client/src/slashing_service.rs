@@ -0,0 +1,357 @@
+use crate::metrics;
+use crate::validator_store::ValidatorStore;
+use environment::RuntimeContext;
+use exit_future::Signal;
+use futures::{Future, Stream};
+use notifier::{Event, Notifier};
+use remote_beacon_node::{RemoteBeaconNode, ValidatorSlashingStatus};
+use slog::{crit, info, warn};
+use slot_clock::SlotClock;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::Duration;
+use types::EthSpec;
+
+/// Delay this period of time after the slot starts. This allows the node to process the new slot.
+const TIME_DELAY_FROM_SLOT: Duration = Duration::from_millis(150);
+
+/// Builds a `SlashingService`.
+pub struct SlashingServiceBuilder<T, E: EthSpec> {
+    validator_store: Option<ValidatorStore<T, E>>,
+    slot_clock: Option<T>,
+    beacon_node: Option<RemoteBeaconNode<E>>,
+    context: Option<RuntimeContext<E>>,
+    notifier: Notifier,
+}
+
+impl<T: SlotClock + 'static, E: EthSpec> SlashingServiceBuilder<T, E> {
+    pub fn new() -> Self {
+        Self {
+            validator_store: None,
+            slot_clock: None,
+            beacon_node: None,
+            context: None,
+            notifier: Notifier::default(),
+        }
+    }
+
+    pub fn validator_store(mut self, store: ValidatorStore<T, E>) -> Self {
+        self.validator_store = Some(store);
+        self
+    }
+
+    pub fn slot_clock(mut self, slot_clock: T) -> Self {
+        self.slot_clock = Some(slot_clock);
+        self
+    }
+
+    pub fn beacon_node(mut self, beacon_node: RemoteBeaconNode<E>) -> Self {
+        self.beacon_node = Some(beacon_node);
+        self
+    }
+
+    pub fn runtime_context(mut self, context: RuntimeContext<E>) -> Self {
+        self.context = Some(context);
+        self
+    }
+
+    /// Raises an alert for each slashed validator.
+    pub fn notifier(mut self, notifier: Notifier) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
+    pub fn build(self) -> Result<SlashingService<T, E>, String> {
+        Ok(SlashingService {
+            inner: Arc::new(Inner {
+                validator_store: self
+                    .validator_store
+                    .ok_or_else(|| "Cannot build SlashingService without validator_store")?,
+                slot_clock: self
+                    .slot_clock
+                    .ok_or_else(|| "Cannot build SlashingService without slot_clock")?,
+                beacon_node: self
+                    .beacon_node
+                    .ok_or_else(|| "Cannot build SlashingService without beacon_node")?,
+                context: self
+                    .context
+                    .ok_or_else(|| "Cannot build SlashingService without runtime_context")?,
+                notifier: self.notifier,
+            }),
+        })
+    }
+}
+
+/// Helper to minimise `Arc` usage.
+pub struct Inner<T, E: EthSpec> {
+    validator_store: ValidatorStore<T, E>,
+    slot_clock: T,
+    beacon_node: RemoteBeaconNode<E>,
+    context: RuntimeContext<E>,
+    notifier: Notifier,
+}
+
+/// Asks the beacon node whether any of the validators in the `ValidatorStore` have been slashed,
+/// once per slot.
+///
+/// A validator that has been slashed (or has a pending slashing) is disabled in the
+/// `ValidatorStore`, so that it signs nothing further. A slashing of a validator which this client
+/// never double-signed for indicates that its keys are in use elsewhere.
+pub struct SlashingService<T, E: EthSpec> {
+    inner: Arc<Inner<T, E>>,
+}
+
+impl<T, E: EthSpec> Clone for SlashingService<T, E> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T, E: EthSpec> Deref for SlashingService<T, E> {
+    type Target = Inner<T, E>;
+
+    fn deref(&self) -> &Self::Target {
+        self.inner.deref()
+    }
+}
+
+impl<T: SlotClock + 'static, E: EthSpec> SlashingService<T, E> {
+    /// Starts the service that periodically checks for slashings.
+    pub fn start_update_service(&self) -> Result<Signal, String> {
+        let log = self.context.log.clone();
+
+        let interval = self
+            .slot_clock
+            .slot_interval(TIME_DELAY_FROM_SLOT)
+            .ok_or_else(|| "Unable to determine duration to next slot".to_string())?;
+
+        let (exit_signal, exit_fut) = exit_future::signal();
+        let service = self.clone();
+        let log_1 = log.clone();
+        let log_2 = log.clone();
+
+        // Validators may have been disabled before a restart.
+        self.update_metrics();
+
+        // Check immediately, rather than signing for a slashed validator until the next slot.
+        self.context
+            .executor
+            .spawn(service.clone().do_update(), "slashing_service");
+
+        self.context.executor.spawn(
+            exit_fut
+                .until(
+                    interval
+                        .map_err(move |e| {
+                            crit! {
+                                log_1,
+                                "Timer thread failed";
+                                "error" => format!("{}", e)
+                            }
+                        })
+                        .for_each(move |_| service.clone().do_update())
+                        // Prevent any errors from escaping and stopping the interval.
+                        .then(|_| Ok(())),
+                )
+                .map(move |_| info!(log_2, "Shutdown complete")),
+            "slashing_service",
+        );
+
+        Ok(exit_signal)
+    }
+
+    /// Requests the slashing status of all enabled validators, disabling any that are slashed.
+    fn do_update(self) -> impl Future<Item = (), Error = ()> {
+        let service = self.clone();
+        let log = self.context.log.clone();
+
+        let pubkeys = self
+            .validator_store
+            .voting_pubkeys()
+            .into_iter()
+            .filter(|pubkey| !self.validator_store.is_disabled(pubkey))
+            .collect::<Vec<_>>();
+
+        self.beacon_node
+            .http
+            .validator()
+            .get_slashings(&pubkeys)
+            .map(move |statuses| {
+                statuses
+                    .into_iter()
+                    .filter(|status| status.slashed || status.slashing_pending)
+                    .for_each(|status| service.handle_slashing(status))
+            })
+            .map_err(move |e| {
+                warn!(
+                    log,
+                    "Unable to check for slashings";
+                    "error" => format!("{:?}", e)
+                )
+            })
+            // Returning an error will stop the interval. This is not desired, a single failure
+            // should not stop all future attempts.
+            .then(|_| Ok(()))
+    }
+
+    /// Disables the slashed validator and raises an alert.
+    fn handle_slashing(&self, status: ValidatorSlashingStatus) {
+        let log = self.context.log.clone();
+
+        if !self.validator_store.disable(&status.pubkey) {
+            return;
+        }
+        self.update_metrics();
+
+        crit!(
+            log,
+            "Validator slashed, signing disabled";
+            "msg" => "ensure the validator keys are not in use by any other validator client",
+            "pubkey" => format!("{:?}", status.pubkey),
+            "validator_index" => status.validator_index,
+            "slashing_pending" => status.slashing_pending,
+        );
+
+        self.notifier.notify(Event::SlashingDetected {
+            pubkey: format!("{:?}", status.pubkey),
+            validator_index: status.validator_index,
+            slashing_pending: status.slashing_pending,
+        });
+    }
+
+    /// Sets the metrics of the validators disabled in the `ValidatorStore`.
+    fn update_metrics(&self) {
+        metrics::set_gauge(
+            &metrics::DISABLED_VALIDATORS_TOTAL,
+            self.validator_store.num_disabled_validators() as i64,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fork_service::ForkServiceBuilder;
+    use crate::validator_directory::ValidatorDirectoryBuilder;
+    use environment::{Environment, EnvironmentBuilder};
+    use slot_clock::TestingSlotClock;
+    use tempdir::TempDir;
+    use types::{MinimalEthSpec, PublicKey, Slot};
+
+    type E = MinimalEthSpec;
+
+    /// The service never contacts the beacon node in these tests.
+    const BEACON_NODE_URL: &str = "http://localhost:5052";
+
+    fn environment() -> Environment<E> {
+        EnvironmentBuilder::minimal()
+            .null_logger()
+            .expect("should set logger")
+            .single_thread_tokio_runtime()
+            .expect("should start runtime")
+            .build()
+            .expect("should build environment")
+    }
+
+    fn slot_clock() -> TestingSlotClock {
+        TestingSlotClock::new(Slot::new(0), Duration::from_secs(0), Duration::from_secs(1))
+    }
+
+    fn beacon_node() -> RemoteBeaconNode<E> {
+        RemoteBeaconNode::new(BEACON_NODE_URL.to_string()).expect("should create beacon node")
+    }
+
+    /// Loads the validators in `base_dir`, as if the validator client had been restarted.
+    fn load_validator_store(
+        base_dir: &TempDir,
+        context: &RuntimeContext<E>,
+    ) -> ValidatorStore<TestingSlotClock, E> {
+        let fork_service = ForkServiceBuilder::new()
+            .slot_clock(slot_clock())
+            .beacon_node(beacon_node())
+            .runtime_context(context.clone())
+            .build()
+            .expect("should build fork service");
+
+        ValidatorStore::load_from_disk(
+            base_dir.path().into(),
+            E::default_spec(),
+            fork_service,
+            context.log.clone(),
+        )
+        .expect("should load validators")
+    }
+
+    fn slashing_service(
+        validator_store: ValidatorStore<TestingSlotClock, E>,
+        context: &RuntimeContext<E>,
+    ) -> SlashingService<TestingSlotClock, E> {
+        SlashingServiceBuilder::new()
+            .validator_store(validator_store)
+            .slot_clock(slot_clock())
+            .beacon_node(beacon_node())
+            .runtime_context(context.clone())
+            .build()
+            .expect("should build slashing service")
+    }
+
+    fn slashing_status(pubkey: &PublicKey, slashing_pending: bool) -> ValidatorSlashingStatus {
+        ValidatorSlashingStatus {
+            pubkey: pubkey.clone(),
+            validator_index: Some(0),
+            slashed: !slashing_pending,
+            slashing_pending,
+        }
+    }
+
+    #[test]
+    fn slashed_validators_remain_disabled_after_a_restart() {
+        let mut env = environment();
+        let context = env.core_context();
+        let base_dir = TempDir::new("slashing_service").expect("should create test dir");
+
+        for index in 0..2 {
+            ValidatorDirectoryBuilder::default()
+                .spec(E::default_spec())
+                .full_deposit_amount()
+                .expect("should set full deposit amount")
+                .insecure_keypairs(index)
+                .create_directory(base_dir.path().into())
+                .expect("should create directory")
+                .write_keypair_files()
+                .expect("should write keypair files")
+                .build()
+                .expect("should build dir");
+        }
+
+        let validator_store = load_validator_store(&base_dir, &context);
+        let pubkeys = validator_store.voting_pubkeys();
+        let (slashed, honest) = (&pubkeys[0], &pubkeys[1]);
+
+        let service = slashing_service(validator_store.clone(), &context);
+        service.handle_slashing(slashing_status(slashed, true));
+        // A repeated slashing status is ignored.
+        service.handle_slashing(slashing_status(slashed, false));
+
+        assert!(validator_store.is_disabled(slashed));
+        assert!(!validator_store.is_disabled(honest));
+        assert_eq!(validator_store.num_disabled_validators(), 1);
+        assert_eq!(
+            metrics::DISABLED_VALIDATORS_TOTAL
+                .as_ref()
+                .expect("should register metric")
+                .get(),
+            1
+        );
+
+        let validator_store = load_validator_store(&base_dir, &context);
+        assert!(
+            validator_store.is_disabled(slashed),
+            "the slashed validator should remain disabled"
+        );
+        assert!(!validator_store.is_disabled(honest));
+        assert_eq!(validator_store.num_disabled_validators(), 1);
+    }
+}
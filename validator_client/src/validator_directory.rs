@@ -7,7 +7,8 @@ use std::fs;
 use std::fs::File;
 use std::io::prelude::*;
 use std::os::unix::fs::PermissionsExt;
-use std::path::PathBuf;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
 use types::{
     test_utils::generate_deterministic_keypair, ChainSpec, DepositData, Hash256, Keypair,
     PublicKey, SecretKey, Signature,
@@ -16,6 +17,11 @@ use types::{
 const VOTING_KEY_PREFIX: &str = "voting";
 const WITHDRAWAL_KEY_PREFIX: &str = "withdrawal";
 const ETH1_DEPOSIT_DATA_FILE: &str = "eth1_deposit_data.rlp";
+/// The file created in a validator's directory once it has been slashed, so that it remains
+/// disabled after a restart. Deleting the file re-enables the validator.
+const DISABLED_FILE: &str = "disabled";
+/// The file in the validators directory which is locked whilst a validator client is using it.
+const LOCK_FILE: &str = "validator_client.lock";
 
 /// Returns the filename of a keypair file.
 fn keypair_file(prefix: &str) -> String {
@@ -59,6 +65,120 @@ impl ValidatorDirectory {
             directory,
         })
     }
+
+    /// Returns `true` if the validator has been disabled via `Self::disable`.
+    pub fn is_disabled(&self) -> bool {
+        self.directory.join(DISABLED_FILE).exists()
+    }
+
+    /// Prevents the validator from signing after a restart, by creating the disabled file in its
+    /// directory.
+    pub fn disable(&self) -> Result<(), String> {
+        File::create(self.directory.join(DISABLED_FILE))
+            .map(|_| ())
+            .map_err(|e| format!("Unable to create disabled file: {}", e))
+    }
+}
+
+/// Copies the validator directory at `source` into `base_path` (e.g.,
+/// `~/.lighthouse/validators/`), returning the path of the new directory.
+///
+/// The files are first copied into a temporary directory in `base_path` which is then renamed,
+/// ensuring `base_path` never contains a partially-copied validator.
+pub fn copy_validator_directory(source: &Path, base_path: &Path) -> Result<PathBuf, String> {
+    let voting_keypair = ValidatorDirectory::load_for_signing(source.to_path_buf())?
+        .voting_keypair
+        .ok_or_else(|| "Validator directory has no voting keypair")?;
+
+    let name = dir_name(&voting_keypair.pk);
+    let destination = base_path.join(&name);
+
+    if destination.exists() {
+        return Err(format!(
+            "Validator directory already exists: {:?}",
+            destination
+        ));
+    }
+
+    let temp = base_path.join(format!(".{}.tmp", name));
+
+    if temp.exists() {
+        fs::remove_dir_all(&temp)
+            .map_err(|e| format!("Unable to remove stale temporary directory: {}", e))?;
+    }
+
+    let copy_files = || -> Result<(), String> {
+        fs::create_dir_all(&temp)
+            .map_err(|e| format!("Unable to create temporary directory: {}", e))?;
+
+        for entry in fs::read_dir(source)
+            .map_err(|e| format!("Unable to read validator directory: {}", e))?
+        {
+            let path = entry
+                .map_err(|e| format!("Unable to read validator directory entry: {}", e))?
+                .path();
+
+            if let Some(file_name) = path.file_name().filter(|_| path.is_file()) {
+                // Note: `fs::copy` also copies the permissions of the file.
+                fs::copy(&path, temp.join(file_name))
+                    .map_err(|e| format!("Unable to copy {:?}: {}", path, e))?;
+            }
+        }
+
+        fs::rename(&temp, &destination)
+            .map_err(|e| format!("Unable to move validator directory into place: {}", e))
+    };
+
+    copy_files().map_err(|e| {
+        let _ = fs::remove_dir_all(&temp);
+        e
+    })?;
+
+    Ok(destination)
+}
+
+/// A lock on a validators directory, held by a running validator client so that its validators
+/// are not exported whilst they may be signing messages, and by the account manager whilst it
+/// imports or exports validators.
+///
+/// The lock is an advisory lock (`flock`) on a file in the directory. It is released when `Self`
+/// is dropped or the process exits, so a validator client which crashed never leaves the
+/// directory locked.
+#[derive(Debug)]
+pub struct ValidatorsDirLock {
+    _file: File,
+}
+
+impl ValidatorsDirLock {
+    /// Locks the validators directory at `base_path`, returning an error if it is already locked.
+    pub fn acquire(base_path: &Path) -> Result<Self, String> {
+        let path = base_path.join(LOCK_FILE);
+
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .open(&path)
+            .map_err(|e| format!("Unable to open lock file {:?}: {}", path, e))?;
+
+        try_lock(&file).map_err(|e| {
+            format!(
+                "Unable to lock validators directory, is another validator client running? \
+                 Error: {}",
+                e
+            )
+        })?;
+
+        Ok(Self { _file: file })
+    }
+}
+
+/// Attempts to take an exclusive advisory lock on `file`, without blocking.
+fn try_lock(file: &File) -> Result<(), std::io::Error> {
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
 }
 
 /// Load a `Keypair` from a file.
@@ -401,4 +521,77 @@ mod tests {
             "the directory created should match the one loaded"
         );
     }
+
+    #[test]
+    fn copy_validator_directory_between_base_dirs() {
+        let spec = E::default_spec();
+        let source_dir = TempDir::new("acc_manager_source").expect("should create test dir");
+        let dest_dir = TempDir::new("acc_manager_dest").expect("should create test dir");
+
+        let created_dir = ValidatorDirectoryBuilder::default()
+            .spec(spec)
+            .full_deposit_amount()
+            .expect("should set full deposit amount")
+            .insecure_keypairs(7)
+            .create_directory(source_dir.path().into())
+            .expect("should create directory")
+            .write_keypair_files()
+            .expect("should write keypair files")
+            .write_eth1_data_file()
+            .expect("should write eth1 data file")
+            .build()
+            .expect("should build dir");
+
+        let copied = copy_validator_directory(&created_dir.directory, dest_dir.path())
+            .expect("should copy directory");
+
+        let loaded_dir =
+            ValidatorDirectory::load_for_signing(copied.clone()).expect("should load copy");
+        assert_eq!(
+            loaded_dir,
+            ValidatorDirectory {
+                directory: copied,
+                ..created_dir.clone()
+            },
+            "the copied directory should match the original"
+        );
+        assert_eq!(
+            fs::read_dir(dest_dir.path())
+                .expect("should read dest dir")
+                .count(),
+            1,
+            "no temporary directory should remain"
+        );
+
+        assert!(
+            copy_validator_directory(&created_dir.directory, dest_dir.path()).is_err(),
+            "should not overwrite an existing validator"
+        );
+        assert!(
+            copy_validator_directory(source_dir.path(), dest_dir.path()).is_err(),
+            "should not copy a directory without a voting keypair"
+        );
+    }
+
+    #[test]
+    fn validators_dir_lock() {
+        let temp_dir = TempDir::new("acc_manager").expect("should create test dir");
+
+        let lock = ValidatorsDirLock::acquire(temp_dir.path()).expect("should acquire lock");
+        assert!(
+            ValidatorsDirLock::acquire(temp_dir.path()).is_err(),
+            "should not acquire a held lock"
+        );
+
+        drop(lock);
+
+        // The lock file remains, but does not prevent the lock being acquired again (e.g., after
+        // a validator client crashed).
+        assert!(temp_dir.path().join(LOCK_FILE).exists());
+        let _lock = ValidatorsDirLock::acquire(temp_dir.path()).expect("should reacquire lock");
+        assert!(
+            ValidatorsDirLock::acquire(temp_dir.path()).is_err(),
+            "should not acquire a reacquired lock"
+        );
+    }
 }
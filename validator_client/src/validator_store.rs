@@ -2,9 +2,9 @@ use crate::fork_service::ForkService;
 use crate::validator_directory::{ValidatorDirectory, ValidatorDirectoryBuilder};
 use parking_lot::RwLock;
 use rayon::prelude::*;
-use slog::{error, Logger};
+use slog::{error, warn, Logger};
 use slot_clock::SlotClock;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::read_dir;
 use std::iter::FromIterator;
 use std::marker::PhantomData;
@@ -19,6 +19,8 @@ use types::{
 #[derive(Clone)]
 pub struct ValidatorStore<T, E: EthSpec> {
     validators: Arc<RwLock<HashMap<PublicKey, ValidatorDirectory>>>,
+    /// Validators which must not sign any further messages, e.g., because they have been slashed.
+    disabled: Arc<RwLock<HashSet<PublicKey>>>,
     spec: Arc<ChainSpec>,
     log: Logger,
     temp_dir: Option<Arc<TempDir>>,
@@ -61,9 +63,26 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
                     .clone()
                     .map(|voting_keypair| (voting_keypair.pk, validator_directory))
             });
+        let validators = HashMap::from_iter(validator_iter);
+
+        let disabled = validators
+            .iter()
+            .filter(|(_pubkey, validator_directory)| validator_directory.is_disabled())
+            .map(|(pubkey, validator_directory)| {
+                warn!(
+                    log,
+                    "Validator is disabled";
+                    "msg" => "it was slashed, delete the disabled file to enable it",
+                    "pubkey" => format!("{:?}", pubkey),
+                    "path" => validator_directory.directory.to_str(),
+                );
+                pubkey.clone()
+            })
+            .collect();
 
         Ok(Self {
-            validators: Arc::new(RwLock::new(HashMap::from_iter(validator_iter))),
+            validators: Arc::new(RwLock::new(validators)),
+            disabled: Arc::new(RwLock::new(disabled)),
             spec: Arc::new(spec),
             log,
             temp_dir: None,
@@ -105,6 +124,7 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
 
         Ok(Self {
             validators: Arc::new(RwLock::new(HashMap::from_iter(validators))),
+            disabled: Arc::new(RwLock::new(HashSet::new())),
             spec: Arc::new(spec),
             log,
             temp_dir: Some(Arc::new(temp_dir)),
@@ -125,6 +145,57 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
         self.validators.read().len()
     }
 
+    /// Returns the number of validators which have been disabled.
+    pub fn num_disabled_validators(&self) -> usize {
+        self.disabled.read().len()
+    }
+
+    /// Prevents the validator from signing any further messages, including after a restart.
+    ///
+    /// Returns `false` if the validator was already disabled.
+    pub fn disable(&self, validator_pubkey: &PublicKey) -> bool {
+        if !self.disabled.write().insert(validator_pubkey.clone()) {
+            return false;
+        }
+
+        if let Some(validator_directory) = self.validators.read().get(validator_pubkey) {
+            if let Err(e) = validator_directory.disable() {
+                error!(
+                    self.log,
+                    "Unable to persist disabled validator";
+                    "msg" => "the validator will sign again after a restart",
+                    "error" => e,
+                    "pubkey" => format!("{:?}", validator_pubkey),
+                );
+            }
+        }
+
+        true
+    }
+
+    /// Returns `true` if the validator has been disabled via `Self::disable`.
+    pub fn is_disabled(&self, validator_pubkey: &PublicKey) -> bool {
+        self.disabled.read().contains(validator_pubkey)
+    }
+
+    /// Calls `func` with the directory of the validator, unless it is unknown or has been disabled.
+    fn signing_validator<U>(
+        &self,
+        validator_pubkey: &PublicKey,
+        func: impl FnOnce(&ValidatorDirectory) -> Option<U>,
+    ) -> Option<U> {
+        if self.is_disabled(validator_pubkey) {
+            error!(
+                self.log,
+                "Refusing to sign for disabled validator";
+                "pubkey" => format!("{:?}", validator_pubkey),
+            );
+            return None;
+        }
+
+        self.validators.read().get(validator_pubkey).and_then(func)
+    }
+
     fn fork(&self) -> Option<Fork> {
         if self.fork_service.fork().is_none() {
             error!(
@@ -137,16 +208,13 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
 
     pub fn randao_reveal(&self, validator_pubkey: &PublicKey, epoch: Epoch) -> Option<Signature> {
         // TODO: check this against the slot clock to make sure it's not an early reveal?
-        self.validators
-            .read()
-            .get(validator_pubkey)
-            .and_then(|validator_dir| {
-                let voting_keypair = validator_dir.voting_keypair.as_ref()?;
-                let message = epoch.tree_hash_root();
-                let domain = self.spec.get_domain(epoch, Domain::Randao, &self.fork()?);
+        self.signing_validator(validator_pubkey, |validator_dir| {
+            let voting_keypair = validator_dir.voting_keypair.as_ref()?;
+            let message = epoch.tree_hash_root();
+            let domain = self.spec.get_domain(epoch, Domain::Randao, &self.fork()?);
 
-                Some(Signature::new(&message, domain, &voting_keypair.sk))
-            })
+            Some(Signature::new(&message, domain, &voting_keypair.sk))
+        })
     }
 
     pub fn sign_block(
@@ -155,14 +223,11 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
         mut block: BeaconBlock<E>,
     ) -> Option<BeaconBlock<E>> {
         // TODO: check for slashing.
-        self.validators
-            .read()
-            .get(validator_pubkey)
-            .and_then(|validator_dir| {
-                let voting_keypair = validator_dir.voting_keypair.as_ref()?;
-                block.sign(&voting_keypair.sk, &self.fork()?, &self.spec);
-                Some(block)
-            })
+        self.signing_validator(validator_pubkey, |validator_dir| {
+            let voting_keypair = validator_dir.voting_keypair.as_ref()?;
+            block.sign(&voting_keypair.sk, &self.fork()?, &self.spec);
+            Some(block)
+        })
     }
 
     pub fn sign_attestation(
@@ -172,29 +237,26 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
         attestation: &mut Attestation<E>,
     ) -> Option<()> {
         // TODO: check for slashing.
-        self.validators
-            .read()
-            .get(validator_pubkey)
-            .and_then(|validator_dir| {
-                let voting_keypair = validator_dir.voting_keypair.as_ref()?;
-
-                attestation
-                    .sign(
-                        &voting_keypair.sk,
-                        validator_committee_position,
-                        &self.fork()?,
-                        &self.spec,
+        self.signing_validator(validator_pubkey, |validator_dir| {
+            let voting_keypair = validator_dir.voting_keypair.as_ref()?;
+
+            attestation
+                .sign(
+                    &voting_keypair.sk,
+                    validator_committee_position,
+                    &self.fork()?,
+                    &self.spec,
+                )
+                .map_err(|e| {
+                    error!(
+                        self.log,
+                        "Error whilst signing attestation";
+                        "error" => format!("{:?}", e)
                     )
-                    .map_err(|e| {
-                        error!(
-                            self.log,
-                            "Error whilst signing attestation";
-                            "error" => format!("{:?}", e)
-                        )
-                    })
-                    .ok()?;
-
-                Some(())
-            })
+                })
+                .ok()?;
+
+            Some(())
+        })
     }
 }